@@ -0,0 +1,8 @@
+//! The `sqlitegis-features` OGC API - Features server; see
+//! [`sqlitegis::sqlite::ogc_features`].
+
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    sqlitegis::sqlite::ogc_features::run(std::env::args_os())
+}
@@ -0,0 +1,8 @@
+//! The `sqlitegis-tiles` vector tile server; see
+//! [`sqlitegis::sqlite::tile_server`].
+
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    sqlitegis::sqlite::tile_server::run(std::env::args_os())
+}
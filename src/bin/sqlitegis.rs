@@ -0,0 +1,7 @@
+//! The `sqlitegis` command-line tool; see [`sqlitegis::sqlite::cli`].
+
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    sqlitegis::sqlite::cli::run(std::env::args_os())
+}
@@ -0,0 +1,588 @@
+//! Parallel one-probe-vs-many batch variants of the hot predicates and
+//! measurements. Available under `feature = "rayon"`.
+//!
+//! Every `*_many(probe, blobs, ...)` function returns exactly what
+//! `blobs.iter().map(|b| scalar(probe, b)).collect::<Result<Vec<_>>>()`
+//! would: the same values, and on failure the error of the first
+//! offending blob in input order. The difference is that the probe is
+//! decoded (and its MBR extracted) once up front, and the per-blob work
+//! fans out across the rayon thread pool.
+//!
+//! If the probe itself fails to decode, the batch falls back to calling
+//! the scalar function per blob so that error precedence (for example the
+//! MBR-disjoint fastpath that answers `false` without a full decode) still
+//! matches the scalar surface bit for bit.
+//!
+//! ```
+//! use sqlitegis::core::batch::intersects_many;
+//! use sqlitegis::core::functions::io::geom_from_text;
+//!
+//! let window = geom_from_text("POLYGON((0 0,10 0,10 10,0 10,0 0))", None).unwrap();
+//! let inside = geom_from_text("POINT(5 5)", None).unwrap();
+//! let outside = geom_from_text("POINT(50 50)", None).unwrap();
+//! let hits = intersects_many(&window, &[&inside, &outside]).unwrap();
+//! assert_eq!(hits, vec![true, false]);
+//! ```
+
+use geo::algorithm::line_measures::metric_spaces::{Geodesic, Haversine};
+use geo::algorithm::line_measures::Distance;
+use geo::algorithm::{Contains, Intersects, Relate};
+use geo::{Geometry, Rect};
+use rayon::prelude::*;
+
+use crate::core::error::Result;
+use crate::core::ewkb::{ensure_matching_srid, extract_mbr, parse_ewkb};
+use crate::core::functions::measurement::{
+    euclidean_geometry_distance, geographic_point_pair, require_non_empty_geometry, st_distance,
+    st_distance_sphere, st_distance_spheroid,
+};
+use crate::core::functions::predicates::{
+    ensure_dwithin_distance, st_contains, st_covers, st_intersects,
+};
+use crate::core::limits;
+
+/// Probe blob decoded once and shared read-only across worker threads.
+struct Probe {
+    mbr: Option<Rect<f64>>,
+    geom: Geometry<f64>,
+    srid: Option<i32>,
+}
+
+impl Probe {
+    /// Decode `blob`, or return `None` when it does not parse so the caller
+    /// can fall back to the scalar path.
+    fn prepare(blob: &[u8]) -> Option<Self> {
+        let (geom, srid) = parse_ewkb(blob).ok()?;
+        Some(Self {
+            mbr: extract_mbr(blob).ok().flatten(),
+            geom,
+            srid,
+        })
+    }
+
+    /// Mirror of the scalar MBR fastpath: returns the probe and candidate
+    /// bboxes only when both are computable.
+    fn mbr_pair(&self, other: &[u8]) -> Option<(Rect<f64>, Rect<f64>)> {
+        let probe = self.mbr?;
+        match extract_mbr(other) {
+            Ok(Some(rect)) => Some((probe, rect)),
+            _ => None,
+        }
+    }
+}
+
+/// Run `f` over every blob on the rayon pool and return the first error in
+/// input order, matching a sequential `collect::<Result<Vec<_>>>()`. The
+/// caller's [`limits`] are installed on each worker.
+fn fan_out<T, F>(blobs: &[&[u8]], f: F) -> Result<Vec<T>>
+where
+    T: Send,
+    F: Fn(&[u8]) -> Result<T> + Sync,
+{
+    // Worker threads do not inherit the caller's thread-local limits.
+    let limits = limits::current();
+    let results: Vec<Result<T>> = blobs
+        .par_iter()
+        .map(|b| limits::with_limits(limits, || f(b)))
+        .collect();
+    results.into_iter().collect()
+}
+
+/// Probe-first binary op. Uses the prepared probe when it decodes, the
+/// scalar function otherwise.
+fn probe_first<T, P, S>(probe: &[u8], blobs: &[&[u8]], prepared: P, scalar: S) -> Result<Vec<T>>
+where
+    T: Send,
+    P: Fn(&Probe, &[u8]) -> Result<T> + Sync,
+    S: Fn(&[u8], &[u8]) -> Result<T> + Sync,
+{
+    match Probe::prepare(probe) {
+        Some(p) => fan_out(blobs, |b| prepared(&p, b)),
+        None => fan_out(blobs, |b| scalar(probe, b)),
+    }
+}
+
+/// Decode a candidate blob with the probe as the left-hand operand,
+/// enforcing the same SRID rule as `parse_ewkb_pair(probe, blob)`.
+fn decode_right(probe: &Probe, blob: &[u8]) -> Result<Geometry<f64>> {
+    let (geom, srid) = parse_ewkb(blob)?;
+    ensure_matching_srid(probe.srid, srid)?;
+    Ok(geom)
+}
+
+/// Decode a candidate blob with the probe as the right-hand operand,
+/// enforcing the same SRID rule as `parse_ewkb_pair(blob, probe)`.
+fn decode_left(probe: &Probe, blob: &[u8]) -> Result<Geometry<f64>> {
+    let (geom, srid) = parse_ewkb(blob)?;
+    ensure_matching_srid(srid, probe.srid)?;
+    Ok(geom)
+}
+
+fn intersects_one(probe: &Probe, blob: &[u8]) -> Result<bool> {
+    if let Some((rp, rb)) = probe.mbr_pair(blob) {
+        if !rp.intersects(&rb) {
+            return Ok(false);
+        }
+    }
+    let gb = decode_right(probe, blob)?;
+    Ok(probe.geom.intersects(&gb))
+}
+
+/// Batch `ST_Intersects(probe, blob)` for every blob.
+///
+/// ```
+/// use sqlitegis::core::batch::intersects_many;
+/// use sqlitegis::core::functions::constructors::st_point;
+///
+/// let probe = st_point(0.0, 0.0, None).unwrap();
+/// let same = st_point(0.0, 0.0, None).unwrap();
+/// let other = st_point(1.0, 1.0, None).unwrap();
+/// assert_eq!(intersects_many(&probe, &[&same, &other]).unwrap(), vec![true, false]);
+/// ```
+pub fn intersects_many(probe: &[u8], blobs: &[&[u8]]) -> Result<Vec<bool>> {
+    probe_first(probe, blobs, intersects_one, st_intersects)
+}
+
+/// Batch `ST_Disjoint(probe, blob)` for every blob.
+///
+/// ```
+/// use sqlitegis::core::batch::disjoint_many;
+/// use sqlitegis::core::functions::constructors::st_point;
+///
+/// let probe = st_point(0.0, 0.0, None).unwrap();
+/// let other = st_point(1.0, 1.0, None).unwrap();
+/// assert_eq!(disjoint_many(&probe, &[&other]).unwrap(), vec![true]);
+/// ```
+pub fn disjoint_many(probe: &[u8], blobs: &[&[u8]]) -> Result<Vec<bool>> {
+    probe_first(
+        probe,
+        blobs,
+        |p, b| Ok(!intersects_one(p, b)?),
+        |a, b| Ok(!st_intersects(a, b)?),
+    )
+}
+
+fn contains_one(probe: &Probe, blob: &[u8]) -> Result<bool> {
+    if let Some((rp, rb)) = probe.mbr_pair(blob) {
+        if !rp.contains(&rb) {
+            return Ok(false);
+        }
+    }
+    let gb = decode_right(probe, blob)?;
+    Ok(probe.geom.contains(&gb))
+}
+
+/// Batch `ST_Contains(probe, blob)`: does the probe contain each blob?
+///
+/// ```
+/// use sqlitegis::core::batch::contains_many;
+/// use sqlitegis::core::functions::io::geom_from_text;
+///
+/// let poly = geom_from_text("POLYGON((0 0,4 0,4 4,0 4,0 0))", None).unwrap();
+/// let inside = geom_from_text("POINT(2 2)", None).unwrap();
+/// let outside = geom_from_text("POINT(9 9)", None).unwrap();
+/// assert_eq!(contains_many(&poly, &[&inside, &outside]).unwrap(), vec![true, false]);
+/// ```
+pub fn contains_many(probe: &[u8], blobs: &[&[u8]]) -> Result<Vec<bool>> {
+    probe_first(probe, blobs, contains_one, st_contains)
+}
+
+/// Batch `ST_Within(probe, blob)`: is the probe within each blob?
+///
+/// ```
+/// use sqlitegis::core::batch::within_many;
+/// use sqlitegis::core::functions::io::geom_from_text;
+///
+/// let pt = geom_from_text("POINT(2 2)", None).unwrap();
+/// let big = geom_from_text("POLYGON((0 0,4 0,4 4,0 4,0 0))", None).unwrap();
+/// let small = geom_from_text("POLYGON((0 0,1 0,1 1,0 1,0 0))", None).unwrap();
+/// assert_eq!(within_many(&pt, &[&big, &small]).unwrap(), vec![true, false]);
+/// ```
+pub fn within_many(probe: &[u8], blobs: &[&[u8]]) -> Result<Vec<bool>> {
+    // ST_Within(probe, b) == ST_Contains(b, probe): the probe is the
+    // right-hand operand of the underlying containment test.
+    probe_first(
+        probe,
+        blobs,
+        |p, b| {
+            if let Some((rp, rb)) = p.mbr_pair(b) {
+                if !rb.contains(&rp) {
+                    return Ok(false);
+                }
+            }
+            let ga = decode_left(p, b)?;
+            Ok(ga.contains(&p.geom))
+        },
+        |a, b| st_contains(b, a),
+    )
+}
+
+/// Batch `ST_Covers(probe, blob)`: does the probe cover each blob?
+///
+/// ```
+/// use sqlitegis::core::batch::covers_many;
+/// use sqlitegis::core::functions::io::geom_from_text;
+///
+/// let poly = geom_from_text("POLYGON((0 0,4 0,4 4,0 4,0 0))", None).unwrap();
+/// let corner = geom_from_text("POINT(0 0)", None).unwrap();
+/// assert_eq!(covers_many(&poly, &[&corner]).unwrap(), vec![true]);
+/// ```
+pub fn covers_many(probe: &[u8], blobs: &[&[u8]]) -> Result<Vec<bool>> {
+    probe_first(
+        probe,
+        blobs,
+        |p, b| {
+            if let Some((rp, rb)) = p.mbr_pair(b) {
+                if !rp.contains(&rb) {
+                    return Ok(false);
+                }
+            }
+            let gb = decode_right(p, b)?;
+            Ok(p.geom.relate(&gb).is_covers())
+        },
+        st_covers,
+    )
+}
+
+/// Batch `ST_CoveredBy(probe, blob)`: is the probe covered by each blob?
+///
+/// ```
+/// use sqlitegis::core::batch::covered_by_many;
+/// use sqlitegis::core::functions::io::geom_from_text;
+///
+/// let corner = geom_from_text("POINT(0 0)", None).unwrap();
+/// let poly = geom_from_text("POLYGON((0 0,4 0,4 4,0 4,0 0))", None).unwrap();
+/// assert_eq!(covered_by_many(&corner, &[&poly]).unwrap(), vec![true]);
+/// ```
+pub fn covered_by_many(probe: &[u8], blobs: &[&[u8]]) -> Result<Vec<bool>> {
+    probe_first(
+        probe,
+        blobs,
+        |p, b| {
+            if let Some((rp, rb)) = p.mbr_pair(b) {
+                if !rb.contains(&rp) {
+                    return Ok(false);
+                }
+            }
+            let ga = decode_left(p, b)?;
+            Ok(ga.relate(&p.geom).is_covers())
+        },
+        |a, b| st_covers(b, a),
+    )
+}
+
+fn distance_one(probe: &Probe, blob: &[u8]) -> Result<f64> {
+    let gb = decode_right(probe, blob)?;
+    require_non_empty_geometry(&probe.geom, "ST_Distance")?;
+    require_non_empty_geometry(&gb, "ST_Distance")?;
+    Ok(euclidean_geometry_distance(&probe.geom, &gb))
+}
+
+/// Batch `ST_Distance(probe, blob)` for every blob.
+///
+/// ```
+/// use sqlitegis::core::batch::distance_many;
+/// use sqlitegis::core::functions::constructors::st_point;
+///
+/// let origin = st_point(0.0, 0.0, None).unwrap();
+/// let p = st_point(3.0, 4.0, None).unwrap();
+/// assert_eq!(distance_many(&origin, &[&p]).unwrap(), vec![5.0]);
+/// ```
+pub fn distance_many(probe: &[u8], blobs: &[&[u8]]) -> Result<Vec<f64>> {
+    probe_first(probe, blobs, distance_one, st_distance)
+}
+
+/// Batch `ST_DWithin(probe, blob, distance)` for every blob.
+///
+/// ```
+/// use sqlitegis::core::batch::dwithin_many;
+/// use sqlitegis::core::functions::constructors::st_point;
+///
+/// let origin = st_point(0.0, 0.0, None).unwrap();
+/// let p = st_point(3.0, 4.0, None).unwrap();
+/// assert_eq!(dwithin_many(&origin, &[&p], 5.0).unwrap(), vec![true]);
+/// assert_eq!(dwithin_many(&origin, &[&p], 4.0).unwrap(), vec![false]);
+/// ```
+pub fn dwithin_many(probe: &[u8], blobs: &[&[u8]], distance: f64) -> Result<Vec<bool>> {
+    probe_first(
+        probe,
+        blobs,
+        |p, b| {
+            ensure_dwithin_distance(distance, "ST_DWithin")?;
+            Ok(distance_one(p, b)? <= distance)
+        },
+        |a, b| {
+            ensure_dwithin_distance(distance, "ST_DWithin")?;
+            Ok(st_distance(a, b)? <= distance)
+        },
+    )
+}
+
+fn distance_sphere_one(probe: &Probe, blob: &[u8]) -> Result<f64> {
+    let (gb, srid) = parse_ewkb(blob)?;
+    let (pa, pb, _) =
+        geographic_point_pair(&probe.geom, probe.srid, &gb, srid, "ST_DistanceSphere")?;
+    Ok(Haversine.distance(pa, pb))
+}
+
+fn distance_spheroid_one(probe: &Probe, blob: &[u8]) -> Result<f64> {
+    let (gb, srid) = parse_ewkb(blob)?;
+    let (pa, pb, _) =
+        geographic_point_pair(&probe.geom, probe.srid, &gb, srid, "ST_DistanceSpheroid")?;
+    Ok(Geodesic.distance(pa, pb))
+}
+
+/// Batch `ST_DistanceSphere(probe, blob)`: Haversine metres, SRID 4326 Points.
+///
+/// ```
+/// use sqlitegis::core::batch::distance_sphere_many;
+/// use sqlitegis::core::functions::constructors::st_point;
+///
+/// let london = st_point(-0.1278, 51.5074, Some(4326)).unwrap();
+/// let paris = st_point(2.3522, 48.8566, Some(4326)).unwrap();
+/// let d = distance_sphere_many(&london, &[&paris]).unwrap();
+/// assert!(d[0] > 300_000.0 && d[0] < 400_000.0);
+/// ```
+pub fn distance_sphere_many(probe: &[u8], blobs: &[&[u8]]) -> Result<Vec<f64>> {
+    probe_first(probe, blobs, distance_sphere_one, st_distance_sphere)
+}
+
+/// Batch `ST_DistanceSpheroid(probe, blob)`: geodesic metres, SRID 4326 Points.
+///
+/// ```
+/// use sqlitegis::core::batch::distance_spheroid_many;
+/// use sqlitegis::core::functions::constructors::st_point;
+///
+/// let london = st_point(-0.1278, 51.5074, Some(4326)).unwrap();
+/// let paris = st_point(2.3522, 48.8566, Some(4326)).unwrap();
+/// let d = distance_spheroid_many(&london, &[&paris]).unwrap();
+/// assert!(d[0] > 300_000.0 && d[0] < 400_000.0);
+/// ```
+pub fn distance_spheroid_many(probe: &[u8], blobs: &[&[u8]]) -> Result<Vec<f64>> {
+    probe_first(probe, blobs, distance_spheroid_one, st_distance_spheroid)
+}
+
+/// Batch `ST_DWithinSphere(probe, blob, distance)` for every blob.
+///
+/// ```
+/// use sqlitegis::core::batch::dwithin_sphere_many;
+/// use sqlitegis::core::functions::constructors::st_point;
+///
+/// let london = st_point(-0.1278, 51.5074, Some(4326)).unwrap();
+/// let paris = st_point(2.3522, 48.8566, Some(4326)).unwrap();
+/// assert_eq!(dwithin_sphere_many(&london, &[&paris], 400_000.0).unwrap(), vec![true]);
+/// ```
+pub fn dwithin_sphere_many(probe: &[u8], blobs: &[&[u8]], distance: f64) -> Result<Vec<bool>> {
+    probe_first(
+        probe,
+        blobs,
+        |p, b| {
+            ensure_dwithin_distance(distance, "ST_DWithinSphere")?;
+            Ok(distance_sphere_one(p, b)? <= distance)
+        },
+        |a, b| {
+            ensure_dwithin_distance(distance, "ST_DWithinSphere")?;
+            Ok(st_distance_sphere(a, b)? <= distance)
+        },
+    )
+}
+
+/// Batch `ST_DWithinSpheroid(probe, blob, distance)` for every blob.
+///
+/// ```
+/// use sqlitegis::core::batch::dwithin_spheroid_many;
+/// use sqlitegis::core::functions::constructors::st_point;
+///
+/// let london = st_point(-0.1278, 51.5074, Some(4326)).unwrap();
+/// let paris = st_point(2.3522, 48.8566, Some(4326)).unwrap();
+/// assert_eq!(dwithin_spheroid_many(&london, &[&paris], 300_000.0).unwrap(), vec![false]);
+/// ```
+pub fn dwithin_spheroid_many(probe: &[u8], blobs: &[&[u8]], distance: f64) -> Result<Vec<bool>> {
+    probe_first(
+        probe,
+        blobs,
+        |p, b| {
+            ensure_dwithin_distance(distance, "ST_DWithinSpheroid")?;
+            Ok(distance_spheroid_one(p, b)? <= distance)
+        },
+        |a, b| {
+            ensure_dwithin_distance(distance, "ST_DWithinSpheroid")?;
+            Ok(st_distance_spheroid(a, b)? <= distance)
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::functions::constructors::st_point;
+    use crate::core::functions::io::geom_from_text;
+    use crate::core::functions::predicates::{st_covered_by, st_dwithin, st_within};
+
+    fn corpus() -> Vec<Vec<u8>> {
+        vec![
+            st_point(1.0, 1.0, None).unwrap(),
+            st_point(50.0, 50.0, None).unwrap(),
+            geom_from_text("POINT EMPTY", None).unwrap(),
+            geom_from_text("LINESTRING(-1 2,5 2)", None).unwrap(),
+            geom_from_text("POLYGON((0 0,4 0,4 4,0 4,0 0))", None).unwrap(),
+            geom_from_text("POLYGON((-10 -10,10 -10,10 10,-10 10,-10 -10))", None).unwrap(),
+            geom_from_text("POLYGON((20 20,30 20,30 30,20 30,20 20))", None).unwrap(),
+        ]
+    }
+
+    fn refs(blobs: &[Vec<u8>]) -> Vec<&[u8]> {
+        blobs.iter().map(Vec::as_slice).collect()
+    }
+
+    fn scalar<T>(
+        probe: &[u8],
+        blobs: &[&[u8]],
+        f: impl Fn(&[u8], &[u8]) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        blobs.iter().map(|b| f(probe, b)).collect()
+    }
+
+    fn assert_same<T: std::fmt::Debug + PartialEq>(batch: Result<Vec<T>>, scalar: Result<Vec<T>>) {
+        match (batch, scalar) {
+            (Ok(a), Ok(b)) => assert_eq!(a, b),
+            (Err(a), Err(b)) => assert_eq!(a.to_string(), b.to_string()),
+            (a, b) => panic!("batch/scalar disagree: {a:?} vs {b:?}"),
+        }
+    }
+
+    #[test]
+    fn predicates_match_scalar_for_every_probe() {
+        // geo's relate engine panics on NaN coordinates, so POINT EMPTY is
+        // exercised by the distance test instead.
+        let blobs: Vec<Vec<u8>> = corpus()
+            .into_iter()
+            .filter(|b| !crate::core::ewkb::is_empty_point_blob(b).unwrap())
+            .collect();
+        let targets = refs(&blobs);
+        for probe in &blobs {
+            assert_same(
+                intersects_many(probe, &targets),
+                scalar(probe, &targets, st_intersects),
+            );
+            assert_same(
+                disjoint_many(probe, &targets),
+                scalar(probe, &targets, |a, b| Ok(!st_intersects(a, b)?)),
+            );
+            assert_same(
+                contains_many(probe, &targets),
+                scalar(probe, &targets, st_contains),
+            );
+            assert_same(
+                within_many(probe, &targets),
+                scalar(probe, &targets, st_within),
+            );
+            assert_same(
+                covers_many(probe, &targets),
+                scalar(probe, &targets, st_covers),
+            );
+            assert_same(
+                covered_by_many(probe, &targets),
+                scalar(probe, &targets, st_covered_by),
+            );
+        }
+    }
+
+    #[test]
+    fn distance_matches_scalar_including_empty_point_error() {
+        let blobs = corpus();
+        let targets = refs(&blobs);
+        let probe = st_point(0.0, 0.0, None).unwrap();
+        assert_same(
+            distance_many(&probe, &targets),
+            scalar(&probe, &targets, st_distance),
+        );
+        let non_empty: Vec<&[u8]> = targets
+            .iter()
+            .copied()
+            .filter(|b| st_distance(&probe, b).is_ok())
+            .collect();
+        assert_same(
+            dwithin_many(&probe, &non_empty, 3.0),
+            scalar(&probe, &non_empty, |a, b| st_dwithin(a, b, 3.0)),
+        );
+    }
+
+    #[test]
+    fn mixed_srid_reports_same_error_as_scalar() {
+        let probe = st_point(0.0, 0.0, Some(4326)).unwrap();
+        let other = st_point(0.0, 0.0, Some(3857)).unwrap();
+        let targets: Vec<&[u8]> = vec![&other];
+        assert_same(
+            intersects_many(&probe, &targets),
+            scalar(&probe, &targets, st_intersects),
+        );
+        assert_same(
+            within_many(&probe, &targets),
+            scalar(&probe, &targets, st_within),
+        );
+    }
+
+    #[test]
+    fn malformed_probe_falls_back_to_scalar_semantics() {
+        let bad = [0x01u8, 0x01, 0x00];
+        let ok = st_point(0.0, 0.0, None).unwrap();
+        let targets: Vec<&[u8]> = vec![&ok];
+        assert_same(
+            intersects_many(&bad, &targets),
+            scalar(&bad, &targets, st_intersects),
+        );
+        assert!(intersects_many(&bad, &targets).is_err());
+    }
+
+    #[test]
+    fn first_error_in_input_order_wins() {
+        let probe = st_point(0.0, 0.0, None).unwrap();
+        let ok = st_point(1.0, 1.0, None).unwrap();
+        let truncated = [0x01u8, 0x01, 0x00, 0x00, 0x00];
+        let mixed = st_point(0.0, 0.0, Some(4326)).unwrap();
+        let targets: Vec<&[u8]> = vec![&ok, &mixed, &truncated];
+        let err = distance_many(&probe, &targets).unwrap_err();
+        assert!(err.to_string().contains("mixed SRID"), "{err}");
+    }
+
+    #[test]
+    fn geographic_batches_match_scalar() {
+        let probe = st_point(-0.1278, 51.5074, Some(4326)).unwrap();
+        let blobs = [
+            st_point(2.3522, 48.8566, Some(4326)).unwrap(),
+            st_point(13.405, 52.52, Some(4326)).unwrap(),
+        ];
+        let targets = refs(&blobs);
+        assert_same(
+            distance_sphere_many(&probe, &targets),
+            scalar(&probe, &targets, st_distance_sphere),
+        );
+        assert_same(
+            distance_spheroid_many(&probe, &targets),
+            scalar(&probe, &targets, st_distance_spheroid),
+        );
+        assert_eq!(
+            dwithin_sphere_many(&probe, &targets, 400_000.0).unwrap(),
+            vec![true, false]
+        );
+        assert_eq!(
+            dwithin_spheroid_many(&probe, &targets, 400_000.0).unwrap(),
+            vec![true, false]
+        );
+        assert!(dwithin_sphere_many(&probe, &targets, -1.0).is_err());
+
+        let planar = st_point(0.0, 0.0, None).unwrap();
+        assert_same(
+            distance_sphere_many(&planar, &targets),
+            scalar(&planar, &targets, st_distance_sphere),
+        );
+    }
+
+    #[test]
+    fn empty_input_yields_empty_output() {
+        let probe = st_point(0.0, 0.0, None).unwrap();
+        assert!(intersects_many(&probe, &[]).unwrap().is_empty());
+        assert!(dwithin_many(&probe, &[], f64::NAN).unwrap().is_empty());
+    }
+}
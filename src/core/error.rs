@@ -1,3 +1,5 @@
+use std::cell::Cell;
+
 use thiserror::Error;
 
 use crate::core::ewkb::geometry_type_name;
@@ -27,7 +29,11 @@ use crate::core::ewkb::geometry_type_name;
 ///     Err(_other) => {}
 /// }
 /// ```
+///
+/// New variants may be added in minor releases, so matches need a
+/// wildcard arm.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum SqliteGisError {
     /// The supplied bytes did not parse as a valid EWKB BLOB.
     #[error("invalid EWKB: {0}")]
@@ -68,15 +74,254 @@ pub enum SqliteGisError {
     #[error("{0}")]
     InvalidInput(String),
 
+    /// A binary operation received geometries in different spatial
+    /// reference systems. An absent SRID counts as `0`.
+    #[error("operation on mixed SRID geometries ({left} != {right})")]
+    SridMismatch {
+        /// SRID of the first geometry argument.
+        left: i32,
+        /// SRID of the second geometry argument.
+        right: i32,
+    },
+
+    /// A configured [`Limits`](crate::core::limits::Limits) cap was hit.
+    #[error("{limit} exceeded: {value} > {max}")]
+    LimitExceeded {
+        /// Name of the limit, as accepted by `sqlitegis_config`.
+        limit: &'static str,
+        /// Size of the offending input or output.
+        value: usize,
+        /// The configured maximum.
+        max: usize,
+    },
+
+    /// A long-running operation was cancelled through its
+    /// [`Interrupt`](crate::core::interrupt::Interrupt) hook before it
+    /// produced a result.
+    #[error("interrupted")]
+    Interrupted,
+
     /// An underlying `std::io::Error` from a reader or writer.
     #[error("io error: {0}")]
     Io(#[from] std::io::Error),
+
+    /// SQLite rejected a statement run on the caller's behalf, such as the
+    /// inserts of a file import. Carries SQLite's error message.
+    #[error("sqlite error: {0}")]
+    Sqlite(String),
 }
 
 /// Result alias used by every fallible function in the crate.
 pub type Result<T> = std::result::Result<T, SqliteGisError>;
 
+thread_local! {
+    static FAILED_OPERAND: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Pass `result` through, recording `operand` (the 0-based position of a
+/// geometry among a function's geometry arguments) as the one at fault if
+/// it is an error. Parse and type checks that see one input of a
+/// multi-geometry function wrap their results in this, so the SQLite layer
+/// can name the SQL argument without re-parsing the inputs.
+pub(crate) fn on_operand<T>(operand: usize, result: Result<T>) -> Result<T> {
+    if result.is_err() {
+        FAILED_OPERAND.with(|failed| failed.set(Some(operand)));
+    }
+    result
+}
+
+/// Run `f` and return its result together with the operand the error was
+/// recorded against by [`on_operand`], if any.
+#[cfg(feature = "sqlite")]
+pub(crate) fn track_failed_operand<T>(f: impl FnOnce() -> Result<T>) -> (Result<T>, Option<usize>) {
+    let previous = FAILED_OPERAND.with(|failed| failed.take());
+    let result = f();
+    let operand = FAILED_OPERAND
+        .with(|failed| failed.replace(previous))
+        .filter(|_| result.is_err());
+    (result, operand)
+}
+
+/// Stable classification of a [`SqliteGisError`], one per variant.
+///
+/// The SQLite layer tags every error message with [`ErrorKind::as_str`] and
+/// reports [`ErrorKind::sqlite_code`] as the result code, so hosts can
+/// branch on either without matching message wording. Each kind has its
+/// own extended result code, built like SQLite's own from a primary code
+/// and a subcode; connections without extended result codes enabled see
+/// the primary code:
+///
+/// | Kind                    | Tag                      | Primary code        | Extended code |
+/// |-------------------------|--------------------------|---------------------|---------------|
+/// | `InvalidEwkb`           | `invalid_ewkb`           | `SQLITE_MISMATCH`   | 16404         |
+/// | `Geozero`               | `parse`                  | `SQLITE_ERROR`      | 16641         |
+/// | `WrongType`             | `wrong_type`             | `SQLITE_MISMATCH`   | 16916         |
+/// | `UnsupportedDimensions` | `unsupported_dimensions` | `SQLITE_MISMATCH`   | 17172         |
+/// | `OutOfBounds`           | `out_of_bounds`          | `SQLITE_RANGE`      | 17433         |
+/// | `InvalidInput`          | `invalid_input`          | `SQLITE_ERROR`      | 17665         |
+/// | `SridMismatch`          | `srid_mismatch`          | `SQLITE_CONSTRAINT` | 17939         |
+/// | `LimitExceeded`         | `limit_exceeded`         | `SQLITE_TOOBIG`     | 18194         |
+/// | `Interrupted`           | `interrupted`            | `SQLITE_INTERRUPT`  | 18441         |
+/// | `Io`                    | `io`                     | `SQLITE_IOERR`      | 18698         |
+/// | `Sqlite`                | `sqlite`                 | `SQLITE_ERROR`      | 18945         |
+///
+/// ```
+/// use sqlitegis::core::error::ErrorKind;
+/// use sqlitegis::core::ewkb::ensure_matching_srid;
+///
+/// let err = ensure_matching_srid(Some(4326), Some(3857)).unwrap_err();
+/// assert_eq!(err.kind(), ErrorKind::SridMismatch);
+/// assert_eq!(err.kind().as_str(), "srid_mismatch");
+/// assert_eq!(ErrorKind::from_tag("srid_mismatch"), Some(ErrorKind::SridMismatch));
+/// assert_eq!(err.kind().sqlite_code() & 0xff, 19); // SQLITE_CONSTRAINT
+/// assert_eq!(ErrorKind::from_sqlite_code(17939), Some(ErrorKind::SridMismatch));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// See [`SqliteGisError::InvalidEwkb`].
+    InvalidEwkb,
+    /// See [`SqliteGisError::Geozero`].
+    Geozero,
+    /// See [`SqliteGisError::WrongType`].
+    WrongType,
+    /// See [`SqliteGisError::UnsupportedDimensions`].
+    UnsupportedDimensions,
+    /// See [`SqliteGisError::OutOfBounds`].
+    OutOfBounds,
+    /// See [`SqliteGisError::InvalidInput`].
+    InvalidInput,
+    /// See [`SqliteGisError::SridMismatch`].
+    SridMismatch,
+    /// See [`SqliteGisError::LimitExceeded`].
+    LimitExceeded,
+    /// See [`SqliteGisError::Interrupted`].
+    Interrupted,
+    /// See [`SqliteGisError::Io`].
+    Io,
+    /// See [`SqliteGisError::Sqlite`].
+    Sqlite,
+}
+
+impl ErrorKind {
+    /// Every kind, in declaration order.
+    pub const ALL: &[ErrorKind] = &[
+        ErrorKind::InvalidEwkb,
+        ErrorKind::Geozero,
+        ErrorKind::WrongType,
+        ErrorKind::UnsupportedDimensions,
+        ErrorKind::OutOfBounds,
+        ErrorKind::InvalidInput,
+        ErrorKind::SridMismatch,
+        ErrorKind::LimitExceeded,
+        ErrorKind::Interrupted,
+        ErrorKind::Io,
+        ErrorKind::Sqlite,
+    ];
+
+    /// Stable snake_case tag used in SQLite error messages.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            ErrorKind::InvalidEwkb => "invalid_ewkb",
+            ErrorKind::Geozero => "parse",
+            ErrorKind::WrongType => "wrong_type",
+            ErrorKind::UnsupportedDimensions => "unsupported_dimensions",
+            ErrorKind::OutOfBounds => "out_of_bounds",
+            ErrorKind::InvalidInput => "invalid_input",
+            ErrorKind::SridMismatch => "srid_mismatch",
+            ErrorKind::LimitExceeded => "limit_exceeded",
+            ErrorKind::Interrupted => "interrupted",
+            ErrorKind::Io => "io",
+            ErrorKind::Sqlite => "sqlite",
+        }
+    }
+
+    /// Inverse of [`ErrorKind::as_str`].
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|kind| kind.as_str() == tag)
+    }
+
+    /// Primary SQLite result code of this kind. The values are SQLite's
+    /// own (`SQLITE_MISMATCH` is 20, and so on), spelled out here so the
+    /// core does not depend on SQLite bindings.
+    pub const fn primary_sqlite_code(self) -> i32 {
+        const SQLITE_ERROR: i32 = 1;
+        const SQLITE_INTERRUPT: i32 = 9;
+        const SQLITE_IOERR: i32 = 10;
+        const SQLITE_TOOBIG: i32 = 18;
+        const SQLITE_CONSTRAINT: i32 = 19;
+        const SQLITE_MISMATCH: i32 = 20;
+        const SQLITE_RANGE: i32 = 25;
+        match self {
+            ErrorKind::InvalidEwkb | ErrorKind::WrongType | ErrorKind::UnsupportedDimensions => {
+                SQLITE_MISMATCH
+            }
+            ErrorKind::Geozero | ErrorKind::InvalidInput | ErrorKind::Sqlite => SQLITE_ERROR,
+            ErrorKind::OutOfBounds => SQLITE_RANGE,
+            ErrorKind::SridMismatch => SQLITE_CONSTRAINT,
+            ErrorKind::LimitExceeded => SQLITE_TOOBIG,
+            ErrorKind::Interrupted => SQLITE_INTERRUPT,
+            ErrorKind::Io => SQLITE_IOERR,
+        }
+    }
+
+    /// Extended SQLite result code reported for this kind: the
+    /// [primary code](ErrorKind::primary_sqlite_code) in the low byte and
+    /// a subcode above it, the layout of SQLite's own extended codes
+    /// (`SQLITE_CONSTRAINT_CHECK` is `SQLITE_CONSTRAINT | 1 << 8`). The
+    /// subcodes start at 64, past every subcode SQLite defines.
+    pub const fn sqlite_code(self) -> i32 {
+        let subcode = match self {
+            ErrorKind::InvalidEwkb => 64,
+            ErrorKind::Geozero => 65,
+            ErrorKind::WrongType => 66,
+            ErrorKind::UnsupportedDimensions => 67,
+            ErrorKind::OutOfBounds => 68,
+            ErrorKind::InvalidInput => 69,
+            ErrorKind::SridMismatch => 70,
+            ErrorKind::LimitExceeded => 71,
+            ErrorKind::Interrupted => 72,
+            ErrorKind::Io => 73,
+            ErrorKind::Sqlite => 74,
+        };
+        self.primary_sqlite_code() | subcode << 8
+    }
+
+    /// Inverse of [`ErrorKind::sqlite_code`], for an extended result code
+    /// read with `sqlite3_extended_errcode` (or rusqlite's
+    /// `ffi::Error::extended_code`).
+    pub fn from_sqlite_code(code: i32) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.sqlite_code() == code)
+    }
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 impl SqliteGisError {
+    /// The [`ErrorKind`] of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            SqliteGisError::InvalidEwkb(_) => ErrorKind::InvalidEwkb,
+            SqliteGisError::Geozero(_) => ErrorKind::Geozero,
+            SqliteGisError::WrongType { .. } => ErrorKind::WrongType,
+            SqliteGisError::UnsupportedDimensions { .. } => ErrorKind::UnsupportedDimensions,
+            SqliteGisError::OutOfBounds { .. } => ErrorKind::OutOfBounds,
+            SqliteGisError::InvalidInput(_) => ErrorKind::InvalidInput,
+            SqliteGisError::SridMismatch { .. } => ErrorKind::SridMismatch,
+            SqliteGisError::LimitExceeded { .. } => ErrorKind::LimitExceeded,
+            SqliteGisError::Interrupted => ErrorKind::Interrupted,
+            SqliteGisError::Io(_) => ErrorKind::Io,
+            SqliteGisError::Sqlite(_) => ErrorKind::Sqlite,
+        }
+    }
+
     /// Construct a `WrongType` error from an `expected` label and the actual
     /// geometry that was supplied. Centralises the `geometry_type_name`
     /// lookup so call sites don't have to repeat the boilerplate.
@@ -10,11 +10,18 @@
 //!   \[i32\]: SRID (only when SRID flag set, in declared byte order)
 //!   \[rest\]: ISO WKB geometry payload
 
+use std::borrow::Cow;
+use std::cell::Cell;
+
 use geo::{Coord, Geometry, Point, Rect};
 use geozero::wkb::Ewkb;
 use geozero::{CoordDimensions, ToGeo, ToWkb};
 
-use crate::core::error::{Result, SqliteGisError};
+use crate::core::error::{on_operand, Result, SqliteGisError};
+use crate::core::gpb;
+use crate::core::limits::{check_input_bytes, check_input_vertices, check_output_vertices};
+
+pub mod spatialite;
 
 /// EWKB type flag: SRID is present immediately after the type word.
 pub const EWKB_SRID_FLAG: u32 = 0x20000000;
@@ -126,6 +133,7 @@ pub fn is_empty_point_blob(blob: &[u8]) -> Result<bool> {
 /// assert_eq!(hdr.srid, Some(4326));
 /// ```
 pub fn validate_ewkb_payload(blob: &[u8]) -> Result<EwkbHeader> {
+    check_input_bytes(blob.len())?;
     let header = parse_ewkb_header(blob)?;
     if !point_is_empty_with_header(blob, &header)? {
         let _: Geometry<f64> = Ewkb(blob).to_geo()?;
@@ -271,9 +279,7 @@ pub fn ensure_matching_srid(left: Option<i32>, right: Option<i32>) -> Result<Opt
     let l = left.unwrap_or(0);
     let r = right.unwrap_or(0);
     if l != r {
-        return Err(SqliteGisError::InvalidInput(format!(
-            "operation on mixed SRID geometries ({l} != {r})"
-        )));
+        return Err(SqliteGisError::SridMismatch { left: l, right: r });
     }
 
     if left.is_none() && right.is_none() {
@@ -297,12 +303,14 @@ pub fn ensure_matching_srid(left: Option<i32>, right: Option<i32>) -> Result<Opt
 /// assert_eq!(srid, Some(4326));
 /// ```
 pub fn parse_ewkb(blob: &[u8]) -> Result<(Geometry<f64>, Option<i32>)> {
+    check_input_bytes(blob.len())?;
     let header = parse_ewkb_header(blob)?;
     ensure_xy_only(header.has_z, header.has_m)?;
     if point_is_empty_with_header(blob, &header)? {
         return Ok((Geometry::Point(Point::new(f64::NAN, f64::NAN)), header.srid));
     }
     let geom = Ewkb(blob).to_geo()?;
+    check_input_vertices(&geom)?;
     Ok((geom, header.srid))
 }
 
@@ -310,8 +318,8 @@ pub fn parse_ewkb(blob: &[u8]) -> Result<(Geometry<f64>, Option<i32>)> {
 ///
 /// Returns `(left_geometry, right_geometry, shared_srid)`.
 pub fn parse_ewkb_pair(a: &[u8], b: &[u8]) -> Result<(Geometry<f64>, Geometry<f64>, Option<i32>)> {
-    let (ga, srid_a) = parse_ewkb(a)?;
-    let (gb, srid_b) = parse_ewkb(b)?;
+    let (ga, srid_a) = on_operand(0, parse_ewkb(a))?;
+    let (gb, srid_b) = on_operand(1, parse_ewkb(b))?;
     let srid = ensure_matching_srid(srid_a, srid_b)?;
     Ok((ga, gb, srid))
 }
@@ -345,6 +353,7 @@ pub fn parse_ewkb_pair(a: &[u8], b: &[u8]) -> Result<(Geometry<f64>, Geometry<f6
 /// assert!(extract_mbr(&empty).unwrap().is_none());
 /// ```
 pub fn extract_mbr(blob: &[u8]) -> Result<Option<Rect<f64>>> {
+    check_input_bytes(blob.len())?;
     let header = parse_ewkb_header(blob)?;
     let mut acc: BboxAcc = None;
     walk_for_mbr(
@@ -563,6 +572,7 @@ fn patch_wkb_with_srid(iso_wkb: &[u8], srid_val: i32) -> Result<Vec<u8>> {
 /// assert_eq!(srid, Some(4326));
 /// ```
 pub fn write_ewkb(geom: &Geometry<f64>, srid: Option<i32>) -> Result<Vec<u8>> {
+    check_output_vertices(geom)?;
     if let Geometry::Point(p) = geom {
         if p.x().is_nan() && p.y().is_nan() {
             let mut out = Vec::with_capacity(if srid.is_some() { 25 } else { 21 });
@@ -692,6 +702,12 @@ pub fn concat_multipolygon_bodies(a: &[u8], b: &[u8]) -> Result<Vec<u8>> {
     })?;
     out[count_pos..count_pos + 4].copy_from_slice(&total.to_le_bytes());
 
+    // The splice never decodes, so only pay for a decode when an output
+    // cap is actually configured.
+    if crate::core::limits::current().max_output_vertices.is_some() {
+        let geom = Ewkb(&out).to_geo()?;
+        check_output_vertices(&geom)?;
+    }
     Ok(out)
 }
 
@@ -778,6 +794,451 @@ pub fn geom_type_name(raw_type: u32) -> &'static str {
     }
 }
 
+/// Geometry encodings besides EWKB that [`normalize_input`] converts to
+/// EWKB. All off by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InputFormats {
+    /// GeoPackage binary geometry, see [`crate::core::gpb`].
+    pub gpb: bool,
+    /// SpatiaLite BLOB geometry, see [`spatialite`].
+    pub spatialite: bool,
+    /// ISO WKB with Z/M type codes (`1001`, `2002`, ...). XY ISO WKB is
+    /// already valid EWKB.
+    pub iso_wkb: bool,
+    /// Hex-encoded EWKB or ISO WKB text, as `ST_AsHEXEWKB` and PostGIS
+    /// dumps write it.
+    pub hex_ewkb: bool,
+    /// WKT text, optionally with an EWKT `SRID=n;` prefix.
+    pub wkt: bool,
+}
+
+impl InputFormats {
+    /// EWKB only.
+    pub const NONE: Self = Self {
+        gpb: false,
+        spatialite: false,
+        iso_wkb: false,
+        hex_ewkb: false,
+        wkt: false,
+    };
+    /// Every format [`detect_input_format`] recognises.
+    pub const ALL: Self = Self {
+        gpb: true,
+        spatialite: true,
+        iso_wkb: true,
+        hex_ewkb: true,
+        wkt: true,
+    };
+}
+
+/// Encoding of a geometry argument, as sniffed by [`detect_input_format`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    /// EWKB, or ISO WKB without Z/M (the same bytes).
+    Ewkb,
+    /// ISO WKB with Z/M type codes.
+    IsoWkb,
+    /// GeoPackage binary geometry.
+    Gpb,
+    /// SpatiaLite BLOB geometry.
+    SpatiaLite,
+    /// Hex-encoded (E)WKB text.
+    HexEwkb,
+    /// WKT or EWKT text.
+    Wkt,
+}
+
+/// Sniff the encoding of a geometry argument from its leading bytes and
+/// markers, without validating the rest. `None` means none of the
+/// [`InputFormat`]s.
+///
+/// ```
+/// use sqlitegis::core::ewkb::{detect_input_format, InputFormat};
+/// use sqlitegis::core::functions::io::geom_from_text;
+///
+/// let blob = geom_from_text("POINT(1 2)", None).unwrap();
+/// assert_eq!(detect_input_format(&blob), Some(InputFormat::Ewkb));
+/// assert_eq!(detect_input_format(b"0101000000000000000000F03F000000000000F03F"), Some(InputFormat::HexEwkb));
+/// assert_eq!(detect_input_format(b"SRID=4326;POINT(1 2)"), Some(InputFormat::Wkt));
+/// assert_eq!(detect_input_format(b"\xff"), None);
+/// ```
+pub fn detect_input_format(bytes: &[u8]) -> Option<InputFormat> {
+    // SpatiaLite first: its 0x00 start byte is also a big-endian WKB marker.
+    if spatialite::is_spatialite_blob(bytes) {
+        return Some(InputFormat::SpatiaLite);
+    }
+    if gpb::is_gpb(bytes) {
+        return Some(InputFormat::Gpb);
+    }
+    match bytes.first()? {
+        0x00 | 0x01 => {
+            let little_endian = bytes[0] == 0x01;
+            let raw_type = read_u32_at(bytes, 1, little_endian).ok()?;
+            return Some(if iso_dimensions(raw_type).is_some() {
+                InputFormat::IsoWkb
+            } else {
+                InputFormat::Ewkb
+            });
+        }
+        _ => {}
+    }
+    let text = std::str::from_utf8(bytes).ok()?.trim();
+    if text.len() >= 10
+        && (text.starts_with("00") || text.starts_with("01"))
+        && text.bytes().all(|b| b.is_ascii_hexdigit())
+    {
+        return Some(InputFormat::HexEwkb);
+    }
+    if text.bytes().next().is_some_and(|b| b.is_ascii_alphabetic()) {
+        return Some(InputFormat::Wkt);
+    }
+    None
+}
+
+/// Base type and Z/M of an ISO WKB type code with a dimension offset
+/// (`1001..=3007`); `None` for XY codes and EWKB flag words.
+fn iso_dimensions(raw_type: u32) -> Option<(u32, bool, bool)> {
+    let base = raw_type % 1000;
+    let dims = raw_type / 1000;
+    ((1..=3).contains(&dims) && (WKB_POINT..=WKB_GEOMETRYCOLLECTION).contains(&base)).then_some((
+        base,
+        dims & 1 != 0,
+        dims & 2 != 0,
+    ))
+}
+
+/// Rewrite the ISO Z/M type codes of a WKB blob as EWKB flags in place,
+/// walking nested geometries. Coordinates are untouched.
+fn iso_wkb_to_ewkb(wkb: &[u8]) -> Result<Vec<u8>> {
+    fn patch(buf: &mut [u8], mut offset: usize) -> Result<usize> {
+        let little_endian = match buf.get(offset) {
+            Some(0x01) => true,
+            Some(0x00) => false,
+            _ => {
+                return Err(SqliteGisError::InvalidEwkb(format!(
+                    "invalid byte order marker at offset {offset}"
+                )))
+            }
+        };
+        let raw_type = read_u32_at(buf, offset + 1, little_endian)?;
+        let (geom_type, has_z, has_m) =
+            iso_dimensions(raw_type).unwrap_or((raw_type, false, false));
+        let mut word = geom_type;
+        if has_z {
+            word |= EWKB_Z_FLAG;
+        }
+        if has_m {
+            word |= EWKB_M_FLAG;
+        }
+        let bytes = if little_endian {
+            word.to_le_bytes()
+        } else {
+            word.to_be_bytes()
+        };
+        buf[offset + 1..offset + 5].copy_from_slice(&bytes);
+        offset += 5;
+
+        let coord_size = 16 + 8 * usize::from(has_z) + 8 * usize::from(has_m);
+        let points = |buf: &[u8], offset: usize| -> Result<usize> {
+            let n = read_u32_at(buf, offset, little_endian)? as usize;
+            n.checked_mul(coord_size)
+                .and_then(|len| len.checked_add(offset + 4))
+                .ok_or_else(|| {
+                    SqliteGisError::InvalidEwkb(format!(
+                        "point count {n} at offset {offset} overflows the blob size"
+                    ))
+                })
+        };
+        offset = match geom_type {
+            WKB_POINT => offset + coord_size,
+            WKB_LINESTRING => points(buf, offset)?,
+            WKB_POLYGON => {
+                let rings = read_u32_at(buf, offset, little_endian)?;
+                let mut offset = offset + 4;
+                for _ in 0..rings {
+                    offset = points(buf, offset)?;
+                }
+                offset
+            }
+            WKB_MULTIPOINT..=WKB_GEOMETRYCOLLECTION => {
+                let count = read_u32_at(buf, offset, little_endian)?;
+                let mut offset = offset + 4;
+                for _ in 0..count {
+                    offset = patch(buf, offset)?;
+                }
+                offset
+            }
+            other => {
+                return Err(SqliteGisError::InvalidEwkb(format!(
+                    "unsupported WKB geometry type code {other}"
+                )))
+            }
+        };
+        if offset > buf.len() {
+            return Err(SqliteGisError::InvalidEwkb(format!(
+                "blob truncated: need {offset} bytes, got {}",
+                buf.len()
+            )));
+        }
+        Ok(offset)
+    }
+
+    let mut out = wkb.to_vec();
+    let end = patch(&mut out, 0)?;
+    if end != out.len() {
+        return Err(SqliteGisError::InvalidEwkb(format!(
+            "{} trailing bytes after WKB geometry",
+            out.len() - end
+        )));
+    }
+    Ok(out)
+}
+
+/// Re-encode an XY EWKB blob in little-endian (NDR) or big-endian (XDR)
+/// byte order, walking nested geometries. Flags, SRID and coordinates are
+/// kept as they are.
+///
+/// ```
+/// use sqlitegis::core::ewkb::{with_byte_order, parse_ewkb};
+/// use sqlitegis::core::functions::io::geom_from_text;
+///
+/// let blob = geom_from_text("LINESTRING(0 0,1 2)", Some(4326)).unwrap();
+/// let xdr = with_byte_order(&blob, false).unwrap();
+/// assert_eq!(xdr[0], 0x00);
+/// assert_eq!(parse_ewkb(&xdr).unwrap(), parse_ewkb(&blob).unwrap());
+/// assert_eq!(with_byte_order(&xdr, true).unwrap(), blob);
+/// ```
+pub fn with_byte_order(blob: &[u8], little_endian: bool) -> Result<Vec<u8>> {
+    fn copy(blob: &[u8], mut offset: usize, out: &mut Vec<u8>, le_out: bool) -> Result<usize> {
+        let le_in = match blob.get(offset) {
+            Some(0x01) => true,
+            Some(0x00) => false,
+            _ => {
+                return Err(SqliteGisError::InvalidEwkb(format!(
+                    "invalid byte order marker at offset {offset}"
+                )))
+            }
+        };
+        let put_u32 = |out: &mut Vec<u8>, v: u32| {
+            out.extend_from_slice(&if le_out {
+                v.to_le_bytes()
+            } else {
+                v.to_be_bytes()
+            });
+        };
+        let put_f64 = |out: &mut Vec<u8>, v: f64| {
+            out.extend_from_slice(&if le_out {
+                v.to_le_bytes()
+            } else {
+                v.to_be_bytes()
+            });
+        };
+        out.push(u8::from(le_out));
+        let raw_type = read_u32_at(blob, offset + 1, le_in)?;
+        put_u32(out, raw_type);
+        offset += 5;
+        if raw_type & EWKB_SRID_FLAG != 0 {
+            put_u32(out, read_u32_at(blob, offset, le_in)?);
+            offset += 4;
+        }
+        let points = |out: &mut Vec<u8>, mut offset: usize, n: usize| -> Result<usize> {
+            for _ in 0..n {
+                put_f64(out, read_f64_at(blob, offset, le_in)?);
+                put_f64(out, read_f64_at(blob, offset + 8, le_in)?);
+                offset += 16;
+            }
+            Ok(offset)
+        };
+        let counted = |out: &mut Vec<u8>, offset: usize| -> Result<(usize, usize)> {
+            let n = read_u32_at(blob, offset, le_in)?;
+            put_u32(out, n);
+            Ok((n as usize, offset + 4))
+        };
+        match raw_type & !(EWKB_SRID_FLAG | EWKB_Z_FLAG | EWKB_M_FLAG) {
+            WKB_POINT => points(out, offset, 1),
+            WKB_LINESTRING => {
+                let (n, offset) = counted(out, offset)?;
+                points(out, offset, n)
+            }
+            WKB_POLYGON => {
+                let (rings, mut offset) = counted(out, offset)?;
+                for _ in 0..rings {
+                    let (n, next) = counted(out, offset)?;
+                    offset = points(out, next, n)?;
+                }
+                Ok(offset)
+            }
+            WKB_MULTIPOINT..=WKB_GEOMETRYCOLLECTION => {
+                let (count, mut offset) = counted(out, offset)?;
+                for _ in 0..count {
+                    offset = copy(blob, offset, out, le_out)?;
+                }
+                Ok(offset)
+            }
+            other => Err(SqliteGisError::InvalidEwkb(format!(
+                "unsupported WKB geometry type code {other}"
+            ))),
+        }
+    }
+
+    validate_xy_ewkb_payload(blob)?;
+    let mut out = Vec::with_capacity(blob.len());
+    copy(blob, 0, &mut out, little_endian)?;
+    Ok(out)
+}
+
+/// Decode hex text (either case) into bytes.
+pub(crate) fn decode_hex(hex: &[u8]) -> Result<Vec<u8>> {
+    let digit = |b: u8| match b {
+        b'0'..=b'9' => Some(b - b'0'),
+        b'a'..=b'f' => Some(b - b'a' + 10),
+        b'A'..=b'F' => Some(b - b'A' + 10),
+        _ => None,
+    };
+    if !hex.len().is_multiple_of(2) {
+        return Err(SqliteGisError::InvalidInput(
+            "hex geometry has an odd number of digits".to_string(),
+        ));
+    }
+    hex.chunks_exact(2)
+        .map(|pair| match (digit(pair[0]), digit(pair[1])) {
+            (Some(hi), Some(lo)) => Ok((hi << 4) | lo),
+            _ => Err(SqliteGisError::InvalidInput(
+                "hex geometry contains a non-hex digit".to_string(),
+            )),
+        })
+        .collect()
+}
+
+fn is_empty_point_wkt(wkt: &str) -> bool {
+    let mut parts = wkt.split_whitespace();
+    matches!(
+        (parts.next(), parts.next(), parts.next()),
+        (Some(a), Some(b), None)
+            if a.eq_ignore_ascii_case("POINT") && b.eq_ignore_ascii_case("EMPTY")
+    )
+}
+
+fn is_geometrycollection_single_empty_point_wkt(wkt: &str) -> bool {
+    let compact_upper = wkt
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace())
+        .map(|c| c.to_ascii_uppercase())
+        .collect::<String>();
+    compact_upper == "GEOMETRYCOLLECTION(POINTEMPTY)"
+}
+
+/// Parse WKT into EWKB with `srid`; what `ST_GeomFromText` runs.
+pub(crate) fn wkt_to_ewkb(wkt: &str, srid: Option<i32>) -> Result<Vec<u8>> {
+    check_input_bytes(wkt.len())?;
+    if is_empty_point_wkt(wkt) {
+        return write_ewkb(&Geometry::Point(Point::new(f64::NAN, f64::NAN)), srid);
+    }
+    if is_geometrycollection_single_empty_point_wkt(wkt) {
+        let gc = geo::GeometryCollection::new_from(vec![]);
+        return write_ewkb(&Geometry::GeometryCollection(gc), srid);
+    }
+    let geom: Geometry<f64> = geozero::wkt::Wkt(wkt.as_bytes()).to_geo()?;
+    check_input_vertices(&geom)?;
+    write_ewkb(&geom, srid)
+}
+
+/// Parse WKT, or EWKT with a leading `SRID=n;`, into EWKB.
+fn ewkt_to_ewkb(text: &str) -> Result<Vec<u8>> {
+    let text = text.trim();
+    let Some(rest) = text
+        .get(..5)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("SRID="))
+        .map(|_| &text[5..])
+    else {
+        return wkt_to_ewkb(text, None);
+    };
+    let (srid, wkt) = rest.split_once(';').ok_or_else(|| {
+        SqliteGisError::InvalidInput("EWKT SRID prefix must end with ';'".to_string())
+    })?;
+    let srid = srid.trim().parse::<i32>().map_err(|_| {
+        SqliteGisError::InvalidInput(format!("invalid EWKT SRID [{}]", srid.trim()))
+    })?;
+    wkt_to_ewkb(wkt, Some(srid))
+}
+
+thread_local! {
+    static INPUT_FORMATS: Cell<InputFormats> = const { Cell::new(InputFormats::NONE) };
+}
+
+/// Restores the previous thread-local input formats on drop.
+struct RestoreInputFormats(InputFormats);
+
+impl Drop for RestoreInputFormats {
+    fn drop(&mut self) {
+        INPUT_FORMATS.with(|active| active.set(self.0));
+    }
+}
+
+/// Run `f` with `formats` accepted by [`normalize_input`] on the current
+/// thread, restoring the previous formats afterwards. The SQLite layer
+/// installs the connection's formats (`sqlitegis_config('accept_gpb', 1)`,
+/// `sqlitegis_config('autodetect_input', 1)`) around every call and
+/// normalizes each geometry argument, so every SQL function accepts them.
+pub fn with_input_formats<R>(formats: InputFormats, f: impl FnOnce() -> R) -> R {
+    let _restore = RestoreInputFormats(INPUT_FORMATS.with(|active| active.replace(formats)));
+    f()
+}
+
+/// The input formats accepted on the current thread.
+pub fn input_formats() -> InputFormats {
+    INPUT_FORMATS.with(Cell::get)
+}
+
+/// Convert a geometry argument in one of the accepted [`InputFormats`] to
+/// EWKB, using [`detect_input_format`]. Anything else, EWKB included, is
+/// returned unchanged for the EWKB parsers to validate.
+///
+/// ```
+/// use sqlitegis::core::ewkb::{normalize_input, with_input_formats, InputFormats};
+/// use sqlitegis::core::functions::io::geom_from_text;
+/// use sqlitegis::core::gpb::ewkb_to_gpb;
+///
+/// let blob = geom_from_text("POINT(1 2)", Some(4326)).unwrap();
+/// let gpb = ewkb_to_gpb(&blob).unwrap();
+///
+/// assert_eq!(normalize_input(&gpb).unwrap().as_ref(), gpb.as_slice());
+/// with_input_formats(InputFormats { gpb: true, ..InputFormats::NONE }, || {
+///     assert_eq!(normalize_input(&gpb).unwrap().as_ref(), blob.as_slice());
+///     assert_eq!(normalize_input(&blob).unwrap().as_ref(), blob.as_slice());
+/// });
+/// with_input_formats(InputFormats::ALL, || {
+///     let ewkt = normalize_input(b"SRID=4326;POINT(1 2)").unwrap();
+///     assert_eq!(ewkt.as_ref(), blob.as_slice());
+/// });
+/// ```
+pub fn normalize_input(bytes: &[u8]) -> Result<Cow<'_, [u8]>> {
+    let formats = input_formats();
+    if formats == InputFormats::NONE {
+        return Ok(Cow::Borrowed(bytes));
+    }
+    let converted = match detect_input_format(bytes) {
+        Some(InputFormat::Gpb) if formats.gpb => gpb::gpb_to_ewkb(bytes)?,
+        Some(InputFormat::SpatiaLite) if formats.spatialite => {
+            spatialite::spatialite_to_ewkb(bytes)?
+        }
+        Some(InputFormat::IsoWkb) if formats.iso_wkb => iso_wkb_to_ewkb(bytes)?,
+        Some(InputFormat::HexEwkb) if formats.hex_ewkb => {
+            check_input_bytes(bytes.len())?;
+            let decoded = decode_hex(std::str::from_utf8(bytes).unwrap_or("").trim().as_bytes())?;
+            match detect_input_format(&decoded) {
+                Some(InputFormat::IsoWkb) => iso_wkb_to_ewkb(&decoded)?,
+                _ => decoded,
+            }
+        }
+        Some(InputFormat::Wkt) if formats.wkt => {
+            ewkt_to_ewkb(std::str::from_utf8(bytes).unwrap_or(""))?
+        }
+        _ => return Ok(Cow::Borrowed(bytes)),
+    };
+    Ok(Cow::Owned(converted))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1034,7 +1495,13 @@ mod tests {
 
     #[test]
     fn ensure_matching_srid_rejects_mismatch() {
-        assert!(ensure_matching_srid(Some(4326), Some(3857)).is_err());
+        assert!(matches!(
+            ensure_matching_srid(Some(4326), Some(3857)),
+            Err(SqliteGisError::SridMismatch {
+                left: 4326,
+                right: 3857
+            })
+        ));
         assert!(ensure_matching_srid(Some(4326), None).is_err());
     }
 
@@ -1550,4 +2017,81 @@ mod tests {
         let combined = concat_multipolygon_bodies(&a, &b).unwrap();
         assert_eq!(poly_count(&combined), 2);
     }
+
+    #[test]
+    fn detects_every_input_format() {
+        let ewkb = geom_from_text("LINESTRING(0 0,1 1)", Some(4326)).unwrap();
+        let hex: String = ewkb.iter().map(|b| format!("{b:02x}")).collect();
+        let cases: [(&[u8], Option<InputFormat>); 8] = [
+            (&ewkb, Some(InputFormat::Ewkb)),
+            (
+                &crate::core::gpb::ewkb_to_gpb(&ewkb).unwrap(),
+                Some(InputFormat::Gpb),
+            ),
+            (
+                &spatialite::ewkb_to_spatialite(&ewkb).unwrap(),
+                Some(InputFormat::SpatiaLite),
+            ),
+            (b"\x01\xe9\x03\x00\x00", Some(InputFormat::IsoWkb)),
+            (hex.as_bytes(), Some(InputFormat::HexEwkb)),
+            (b"  linestring(0 0,1 1)", Some(InputFormat::Wkt)),
+            (b"0102", None),
+            (b"", None),
+        ];
+        for (bytes, expected) in cases {
+            assert_eq!(detect_input_format(bytes), expected, "{bytes:?}");
+        }
+    }
+
+    #[test]
+    fn normalize_input_converts_only_enabled_formats() {
+        let ewkb = geom_from_text("POINT(1 2)", Some(4326)).unwrap();
+        let hex: String = ewkb.iter().map(|b| format!("{b:02X}")).collect();
+        let spatialite = spatialite::ewkb_to_spatialite(&ewkb).unwrap();
+
+        let only_hex = InputFormats {
+            hex_ewkb: true,
+            ..InputFormats::NONE
+        };
+        with_input_formats(only_hex, || {
+            assert_eq!(normalize_input(hex.as_bytes()).unwrap().as_ref(), ewkb);
+            assert_eq!(normalize_input(&spatialite).unwrap().as_ref(), spatialite);
+            assert_eq!(
+                normalize_input(b"POINT(1 2)").unwrap().as_ref(),
+                b"POINT(1 2)"
+            );
+        });
+        with_input_formats(InputFormats::ALL, || {
+            assert_eq!(normalize_input(&spatialite).unwrap().as_ref(), ewkb);
+            assert_eq!(
+                normalize_input(b"srid=4326; POINT(1 2)").unwrap().as_ref(),
+                ewkb
+            );
+            assert!(matches!(
+                normalize_input(b"SRID=x;POINT(1 2)"),
+                Err(SqliteGisError::InvalidInput(_))
+            ));
+        });
+        assert_eq!(input_formats(), InputFormats::NONE);
+    }
+
+    #[test]
+    fn iso_wkb_type_codes_become_ewkb_flags() {
+        // MULTIPOINT Z((1 2 3)), big-endian outer, little-endian element.
+        let mut wkb = vec![0x00];
+        wkb.extend_from_slice(&1004u32.to_be_bytes());
+        wkb.extend_from_slice(&1u32.to_be_bytes());
+        wkb.push(0x01);
+        wkb.extend_from_slice(&1001u32.to_le_bytes());
+        for v in [1.0f64, 2.0, 3.0] {
+            wkb.extend_from_slice(&v.to_le_bytes());
+        }
+        let ewkb = iso_wkb_to_ewkb(&wkb).unwrap();
+        let hdr = validate_ewkb_payload(&ewkb).unwrap();
+        assert_eq!(hdr.geom_type, WKB_MULTIPOINT);
+        assert!(hdr.has_z && !hdr.has_m);
+        assert_eq!(&ewkb[10..14], &(WKB_POINT | EWKB_Z_FLAG).to_le_bytes());
+
+        assert!(iso_wkb_to_ewkb(&wkb[..wkb.len() - 1]).is_err());
+    }
 }
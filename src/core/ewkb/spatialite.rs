@@ -0,0 +1,603 @@
+//! SpatiaLite internal BLOB geometry reader and writer.
+//!
+//! Wire format (SpatiaLite 4.x):
+//!   \[0x00\]: start marker
+//!   \[0x00|0x01\]: byte order (big-endian or little-endian)
+//!   \[i32\]: SRID
+//!   \[f64 * 4\]: MBR as min_x, min_y, max_x, max_y
+//!   \[0x7C\]: MBR end marker
+//!   \[i32\]: class type, `1..=7` plus `1000` (Z), `2000` (M) or `3000` (ZM)
+//!   \[body\]: WKB-like payload without per-geometry headers; each element
+//!     of a collection is a `0x69` marker, a class type and its body
+//!   \[0xFE\]: end marker
+//!
+//! Class types `1_000_002`/`1_000_003` (plus the dimension offsets) mark
+//! compressed linestrings and polygons, whose intermediate vertices are
+//! `f32` deltas from the previous vertex; M values stay full `f64`. The
+//! reader also accepts the TinyPoint layout (`0x00`, `0x80|0x81`, SRID, a
+//! dimension byte, coordinates, `0xFE`) that SpatiaLite 4.3 writes for
+//! points. The writer always emits the uncompressed, little-endian form.
+//!
+//! SpatiaLite uses SRID `0` (and `-1`) for "undefined"; both map to an EWKB
+//! blob without SRID, and a blob without SRID is written with SRID `0`.
+//!
+//! ```
+//! use sqlitegis::core::ewkb::extract_srid;
+//! use sqlitegis::core::ewkb::spatialite::{ewkb_to_spatialite, is_spatialite_blob, spatialite_to_ewkb};
+//! use sqlitegis::core::functions::io::geom_from_text;
+//!
+//! let blob = geom_from_text("POLYGON((0 0,4 0,4 3,0 0))", Some(4326)).unwrap();
+//! let spatialite = ewkb_to_spatialite(&blob).unwrap();
+//! assert!(is_spatialite_blob(&spatialite));
+//! assert_eq!(spatialite.last(), Some(&0xFE));
+//!
+//! let back = spatialite_to_ewkb(&spatialite).unwrap();
+//! assert_eq!(back, blob);
+//! assert_eq!(extract_srid(&back), Some(4326));
+//! ```
+
+use crate::core::error::{Result, SqliteGisError};
+use crate::core::ewkb::{
+    extract_mbr, read_f64_at, read_u32_at, validate_ewkb_payload, EWKB_M_FLAG, EWKB_SRID_FLAG,
+    EWKB_Z_FLAG, WKB_GEOMETRYCOLLECTION, WKB_LINESTRING, WKB_MULTILINESTRING, WKB_MULTIPOINT,
+    WKB_MULTIPOLYGON, WKB_POINT, WKB_POLYGON,
+};
+use crate::core::limits::check_input_bytes;
+
+const START: u8 = 0x00;
+const MBR_END: u8 = 0x7C;
+const ENTITY: u8 = 0x69;
+const END: u8 = 0xFE;
+const TINY_POINT_BE: u8 = 0x80;
+const TINY_POINT_LE: u8 = 0x81;
+
+/// Offset of the class type: start, byte order, SRID, MBR, MBR end.
+const CLASS_OFFSET: usize = 39;
+/// Class type offset marking a compressed linestring or polygon.
+const COMPRESSED: u32 = 1_000_000;
+
+/// Whether `blob` looks like a SpatiaLite geometry: the start, MBR-end and
+/// end markers of the regular layout, or a TinyPoint.
+///
+/// ```
+/// use sqlitegis::core::ewkb::spatialite::is_spatialite_blob;
+/// use sqlitegis::core::functions::constructors::st_point;
+///
+/// assert!(!is_spatialite_blob(&st_point(1.0, 2.0, None).unwrap()));
+/// ```
+pub fn is_spatialite_blob(blob: &[u8]) -> bool {
+    if blob.first() != Some(&START) || blob.last() != Some(&END) {
+        return false;
+    }
+    match blob[1] {
+        0x00 | 0x01 => blob.len() > CLASS_OFFSET + 4 && blob[CLASS_OFFSET - 1] == MBR_END,
+        TINY_POINT_BE | TINY_POINT_LE => {
+            blob.get(6).and_then(|&dims| tiny_point_len(dims)) == Some(blob.len())
+        }
+        _ => false,
+    }
+}
+
+/// Convert a SpatiaLite geometry BLOB into EWKB, keeping Z and M.
+pub fn spatialite_to_ewkb(blob: &[u8]) -> Result<Vec<u8>> {
+    check_input_bytes(blob.len())?;
+    if !is_spatialite_blob(blob) {
+        return Err(invalid(
+            "not a SpatiaLite geometry: missing start, MBR or end marker",
+        ));
+    }
+    let little_endian = blob[1] == 0x01 || blob[1] == TINY_POINT_LE;
+    let srid = read_u32_at(blob, 2, little_endian)? as i32;
+    let srid = (srid > 0).then_some(srid);
+
+    let mut out = Vec::with_capacity(blob.len());
+    if matches!(blob[1], TINY_POINT_BE | TINY_POINT_LE) {
+        let (has_z, has_m) = match blob[6] {
+            1 => (false, false),
+            2 => (true, false),
+            3 => (false, true),
+            _ => (true, true),
+        };
+        write_ewkb_header(&mut out, WKB_POINT, has_z, has_m, srid);
+        let mut reader = Reader::new(&blob[..blob.len() - 1], 7, little_endian);
+        reader.copy_coord(has_z, has_m, &mut out)?;
+        reader.finish()?;
+        return Ok(out);
+    }
+
+    // The trailing END marker is not part of the body.
+    let mut reader = Reader::new(&blob[..blob.len() - 1], CLASS_OFFSET, little_endian);
+    let class = decode_class(reader.u32()?)?;
+    write_ewkb_header(&mut out, class.base, class.has_z, class.has_m, srid);
+    reader.body(&class, &mut out)?;
+    reader.finish()?;
+    Ok(out)
+}
+
+/// Convert an EWKB blob into a little-endian, uncompressed SpatiaLite BLOB.
+///
+/// SpatiaLite collections hold only points, linestrings and polygons, so
+/// nested multi-geometries and collections are flattened into their
+/// parts. Empty geometries have no MBR and are rejected.
+pub fn ewkb_to_spatialite(blob: &[u8]) -> Result<Vec<u8>> {
+    check_input_bytes(blob.len())?;
+    let header = validate_ewkb_payload(blob)?;
+    let Some(mbr) = extract_mbr(blob)? else {
+        return Err(invalid(
+            "SpatiaLite BLOBs cannot represent empty geometries",
+        ));
+    };
+
+    let mut out = Vec::with_capacity(blob.len() + 40);
+    out.push(START);
+    out.push(0x01);
+    out.extend_from_slice(&header.srid.unwrap_or(0).to_le_bytes());
+    for v in [mbr.min().x, mbr.min().y, mbr.max().x, mbr.max().y] {
+        out.extend_from_slice(&v.to_le_bytes());
+    }
+    out.push(MBR_END);
+    out.extend_from_slice(&class_code(header.geom_type, header.has_z, header.has_m).to_le_bytes());
+    let writer = Writer { blob };
+    let geom = EwkbGeom {
+        geom_type: header.geom_type,
+        has_z: header.has_z,
+        has_m: header.has_m,
+        little_endian: header.little_endian,
+    };
+    match header.geom_type {
+        WKB_MULTIPOINT | WKB_MULTILINESTRING | WKB_MULTIPOLYGON | WKB_GEOMETRYCOLLECTION => {
+            let mut entities = Vec::new();
+            let (_, count) = writer.entities(header.data_offset, &geom, &mut entities)?;
+            out.extend_from_slice(&count.to_le_bytes());
+            out.extend_from_slice(&entities);
+        }
+        _ => {
+            writer.elementary(header.data_offset, &geom, &mut out)?;
+        }
+    }
+    out.push(END);
+    Ok(out)
+}
+
+fn invalid(msg: &str) -> SqliteGisError {
+    SqliteGisError::InvalidInput(msg.to_string())
+}
+
+fn tiny_point_len(dims: u8) -> Option<usize> {
+    // Start, byte order, SRID, dimension byte, coordinates, end.
+    match dims {
+        1 => Some(8 + 16),
+        2 | 3 => Some(8 + 24),
+        4 => Some(8 + 32),
+        _ => None,
+    }
+}
+
+fn class_code(geom_type: u32, has_z: bool, has_m: bool) -> u32 {
+    geom_type + 1000 * (u32::from(has_z) + 2 * u32::from(has_m))
+}
+
+fn write_ewkb_header(
+    out: &mut Vec<u8>,
+    geom_type: u32,
+    has_z: bool,
+    has_m: bool,
+    srid: Option<i32>,
+) {
+    let mut word = geom_type;
+    if has_z {
+        word |= EWKB_Z_FLAG;
+    }
+    if has_m {
+        word |= EWKB_M_FLAG;
+    }
+    if srid.is_some() {
+        word |= EWKB_SRID_FLAG;
+    }
+    out.push(0x01);
+    out.extend_from_slice(&word.to_le_bytes());
+    if let Some(srid) = srid {
+        out.extend_from_slice(&srid.to_le_bytes());
+    }
+}
+
+/// A decoded SpatiaLite class type.
+struct Class {
+    base: u32,
+    has_z: bool,
+    has_m: bool,
+    compressed: bool,
+}
+
+fn decode_class(code: u32) -> Result<Class> {
+    let (compressed, code) = if code >= COMPRESSED {
+        (true, code - COMPRESSED)
+    } else {
+        (false, code)
+    };
+    let base = code % 1000;
+    let dims = code / 1000;
+    let valid = match base {
+        WKB_LINESTRING | WKB_POLYGON => dims <= 3,
+        WKB_POINT..=WKB_GEOMETRYCOLLECTION => dims <= 3 && !compressed,
+        _ => false,
+    };
+    if !valid {
+        return Err(SqliteGisError::InvalidInput(format!(
+            "unsupported SpatiaLite class type {}",
+            code + if compressed { COMPRESSED } else { 0 }
+        )));
+    }
+    Ok(Class {
+        base,
+        has_z: dims & 1 != 0,
+        has_m: dims & 2 != 0,
+        compressed,
+    })
+}
+
+/// Cursor over a SpatiaLite body, writing little-endian EWKB.
+struct Reader<'a> {
+    blob: &'a [u8],
+    offset: usize,
+    little_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn new(blob: &'a [u8], offset: usize, little_endian: bool) -> Self {
+        Self {
+            blob,
+            offset,
+            little_endian,
+        }
+    }
+
+    fn truncated(&self) -> SqliteGisError {
+        SqliteGisError::InvalidInput(format!(
+            "SpatiaLite geometry truncated at offset {}",
+            self.offset
+        ))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let v = read_u32_at(self.blob, self.offset, self.little_endian)
+            .map_err(|_| self.truncated())?;
+        self.offset += 4;
+        Ok(v)
+    }
+
+    fn f64(&mut self) -> Result<f64> {
+        let v = read_f64_at(self.blob, self.offset, self.little_endian)
+            .map_err(|_| self.truncated())?;
+        self.offset += 8;
+        Ok(v)
+    }
+
+    fn f32(&mut self) -> Result<f64> {
+        let bytes: [u8; 4] = self
+            .blob
+            .get(self.offset..self.offset + 4)
+            .ok_or_else(|| self.truncated())?
+            .try_into()
+            .expect("slice of length 4");
+        self.offset += 4;
+        Ok(f64::from(if self.little_endian {
+            f32::from_le_bytes(bytes)
+        } else {
+            f32::from_be_bytes(bytes)
+        }))
+    }
+
+    fn finish(&self) -> Result<()> {
+        if self.offset != self.blob.len() {
+            return Err(SqliteGisError::InvalidInput(format!(
+                "SpatiaLite geometry has {} unexpected bytes before the end marker",
+                self.blob.len() - self.offset
+            )));
+        }
+        Ok(())
+    }
+
+    fn copy_coord(&mut self, has_z: bool, has_m: bool, out: &mut Vec<u8>) -> Result<()> {
+        let dims = 2 + usize::from(has_z) + usize::from(has_m);
+        for _ in 0..dims {
+            out.extend_from_slice(&self.f64()?.to_le_bytes());
+        }
+        Ok(())
+    }
+
+    /// Copy `count` vertices, expanding compressed intermediate vertices.
+    fn points(&mut self, class: &Class, out: &mut Vec<u8>) -> Result<()> {
+        let count = self.u32()?;
+        out.extend_from_slice(&count.to_le_bytes());
+        let dims = 2 + usize::from(class.has_z) + usize::from(class.has_m);
+        let mut prev = [0.0f64; 4];
+        for i in 0..count {
+            let mut coord = [0.0f64; 4];
+            if class.compressed && i > 0 && i + 1 < count {
+                let deltas = 2 + usize::from(class.has_z);
+                for d in 0..deltas {
+                    coord[d] = prev[d] + self.f32()?;
+                }
+                if class.has_m {
+                    coord[deltas] = self.f64()?;
+                }
+            } else {
+                for value in coord.iter_mut().take(dims) {
+                    *value = self.f64()?;
+                }
+            }
+            for value in &coord[..dims] {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+            prev = coord;
+        }
+        Ok(())
+    }
+
+    fn body(&mut self, class: &Class, out: &mut Vec<u8>) -> Result<()> {
+        match class.base {
+            WKB_POINT => self.copy_coord(class.has_z, class.has_m, out),
+            WKB_LINESTRING => self.points(class, out),
+            WKB_POLYGON => {
+                let rings = self.u32()?;
+                out.extend_from_slice(&rings.to_le_bytes());
+                for _ in 0..rings {
+                    self.points(class, out)?;
+                }
+                Ok(())
+            }
+            _ => {
+                let count = self.u32()?;
+                out.extend_from_slice(&count.to_le_bytes());
+                for _ in 0..count {
+                    if self.blob.get(self.offset) != Some(&ENTITY) {
+                        return Err(SqliteGisError::InvalidInput(format!(
+                            "missing SpatiaLite entity marker at offset {}",
+                            self.offset
+                        )));
+                    }
+                    self.offset += 1;
+                    let child = decode_class(self.u32()?)?;
+                    let allowed = match class.base {
+                        WKB_MULTIPOINT => child.base == WKB_POINT,
+                        WKB_MULTILINESTRING => child.base == WKB_LINESTRING,
+                        WKB_MULTIPOLYGON => child.base == WKB_POLYGON,
+                        _ => child.base <= WKB_POLYGON,
+                    };
+                    if !allowed {
+                        return Err(SqliteGisError::InvalidInput(format!(
+                            "SpatiaLite class type {} cannot be an element of class type {}",
+                            child.base, class.base
+                        )));
+                    }
+                    write_ewkb_header(out, child.base, child.has_z, child.has_m, None);
+                    self.body(&child, out)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Type and layout of one EWKB geometry being written out.
+struct EwkbGeom {
+    geom_type: u32,
+    has_z: bool,
+    has_m: bool,
+    little_endian: bool,
+}
+
+/// Walks a validated EWKB payload, writing the SpatiaLite body.
+struct Writer<'a> {
+    blob: &'a [u8],
+}
+
+impl Writer<'_> {
+    fn u32(&self, offset: usize, geom: &EwkbGeom, out: &mut Vec<u8>) -> Result<u32> {
+        let v = read_u32_at(self.blob, offset, geom.little_endian)?;
+        out.extend_from_slice(&v.to_le_bytes());
+        Ok(v)
+    }
+
+    fn coords(
+        &self,
+        mut offset: usize,
+        count: u32,
+        geom: &EwkbGeom,
+        out: &mut Vec<u8>,
+    ) -> Result<usize> {
+        let dims = 2 + usize::from(geom.has_z) + usize::from(geom.has_m);
+        for _ in 0..count as usize * dims {
+            let v = read_f64_at(self.blob, offset, geom.little_endian)?;
+            out.extend_from_slice(&v.to_le_bytes());
+            offset += 8;
+        }
+        Ok(offset)
+    }
+
+    /// Write the body of a point, linestring or polygon at `offset`,
+    /// returning the offset just past it.
+    fn elementary(&self, mut offset: usize, geom: &EwkbGeom, out: &mut Vec<u8>) -> Result<usize> {
+        match geom.geom_type {
+            WKB_POINT => self.coords(offset, 1, geom, out),
+            WKB_LINESTRING => {
+                let n = self.u32(offset, geom, out)?;
+                self.coords(offset + 4, n, geom, out)
+            }
+            _ => {
+                let rings = self.u32(offset, geom, out)?;
+                offset += 4;
+                for _ in 0..rings {
+                    let n = self.u32(offset, geom, out)?;
+                    offset = self.coords(offset + 4, n, geom, out)?;
+                }
+                Ok(offset)
+            }
+        }
+    }
+
+    /// Write every elementary part of the collection at `offset` as a
+    /// SpatiaLite entity, returning the offset past it and the part count.
+    fn entities(
+        &self,
+        mut offset: usize,
+        geom: &EwkbGeom,
+        out: &mut Vec<u8>,
+    ) -> Result<(usize, u32)> {
+        let count = read_u32_at(self.blob, offset, geom.little_endian)?;
+        offset += 4;
+        let mut written = 0u32;
+        for _ in 0..count {
+            // validate_ewkb_payload has checked every nested header.
+            let little_endian = self.blob[offset] == 0x01;
+            let word = read_u32_at(self.blob, offset + 1, little_endian)?;
+            offset += 5;
+            let child = EwkbGeom {
+                geom_type: word & 0x1FFF_FFFF,
+                has_z: word & EWKB_Z_FLAG != 0,
+                has_m: word & EWKB_M_FLAG != 0,
+                little_endian,
+            };
+            if child.geom_type > WKB_POLYGON {
+                let (next, n) = self.entities(offset, &child, out)?;
+                offset = next;
+                written += n;
+                continue;
+            }
+            out.push(ENTITY);
+            out.extend_from_slice(
+                &class_code(child.geom_type, child.has_z, child.has_m).to_le_bytes(),
+            );
+            offset = self.elementary(offset, &child, out)?;
+            written += 1;
+        }
+        Ok((offset, written))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ewkb::{extract_srid, parse_ewkb_header};
+    use crate::core::functions::io::{as_text, geom_from_text};
+
+    #[test]
+    fn round_trips_every_geometry_type() {
+        for wkt in [
+            "POINT(1 2)",
+            "LINESTRING(0 0,1 1,2 0)",
+            "POLYGON((0 0,4 0,4 4,0 0),(1 1,2 1,2 2,1 1))",
+            "MULTIPOINT((0 0),(1 1))",
+            "MULTILINESTRING((0 0,1 1),(2 2,3 3))",
+            "MULTIPOLYGON(((0 0,1 0,1 1,0 0)),((5 5,6 5,6 6,5 5)))",
+            "GEOMETRYCOLLECTION(POINT(1 1),LINESTRING(0 0,2 2))",
+        ] {
+            let blob = geom_from_text(wkt, Some(4326)).unwrap();
+            let spatialite = ewkb_to_spatialite(&blob).unwrap();
+            assert!(is_spatialite_blob(&spatialite), "{wkt}");
+            let back = spatialite_to_ewkb(&spatialite).unwrap();
+            assert_eq!(as_text(&back).unwrap(), as_text(&blob).unwrap(), "{wkt}");
+            assert_eq!(extract_srid(&back), Some(4326), "{wkt}");
+        }
+    }
+
+    #[test]
+    fn writes_the_documented_point_layout() {
+        let blob = geom_from_text("POINT(1 2)", Some(4326)).unwrap();
+        let spatialite = ewkb_to_spatialite(&blob).unwrap();
+        assert_eq!(spatialite.len(), 60);
+        assert_eq!(&spatialite[..6], &[0x00, 0x01, 0xE6, 0x10, 0x00, 0x00]);
+        assert_eq!(spatialite[38], MBR_END);
+        assert_eq!(&spatialite[39..43], &[1, 0, 0, 0]);
+        assert_eq!(spatialite[59], END);
+    }
+
+    #[test]
+    fn keeps_z_and_m_and_flattens_nested_collections() {
+        // POINT Z(1 2 3): the WKT reader is XY-only, so build the EWKB by hand.
+        let mut blob = vec![0x01];
+        blob.extend_from_slice(&(WKB_POINT | EWKB_Z_FLAG).to_le_bytes());
+        for v in [1.0f64, 2.0, 3.0] {
+            blob.extend_from_slice(&v.to_le_bytes());
+        }
+        let spatialite = ewkb_to_spatialite(&blob).unwrap();
+        assert_eq!(&spatialite[39..43], &1001u32.to_le_bytes());
+        let back = spatialite_to_ewkb(&spatialite).unwrap();
+        assert_eq!(back, blob);
+        assert_eq!(extract_srid(&back), None);
+
+        let nested = geom_from_text(
+            "GEOMETRYCOLLECTION(MULTIPOINT((0 0),(1 1)),POINT(2 2))",
+            None,
+        )
+        .unwrap();
+        let back = spatialite_to_ewkb(&ewkb_to_spatialite(&nested).unwrap()).unwrap();
+        assert_eq!(
+            as_text(&back).unwrap(),
+            "GEOMETRYCOLLECTION(POINT(0 0),POINT(1 1),POINT(2 2))"
+        );
+    }
+
+    #[test]
+    fn reads_compressed_linestrings_big_endian_and_tiny_points() {
+        // Big-endian compressed LINESTRING(0 0,1 1,3 2,4 4), SRID 3857.
+        let mut blob = vec![START, 0x00];
+        blob.extend_from_slice(&3857i32.to_be_bytes());
+        for v in [0.0f64, 0.0, 4.0, 4.0] {
+            blob.extend_from_slice(&v.to_be_bytes());
+        }
+        blob.push(MBR_END);
+        blob.extend_from_slice(&1_000_002u32.to_be_bytes());
+        blob.extend_from_slice(&4u32.to_be_bytes());
+        blob.extend_from_slice(&0.0f64.to_be_bytes());
+        blob.extend_from_slice(&0.0f64.to_be_bytes());
+        for v in [1.0f32, 1.0, 2.0, 1.0] {
+            blob.extend_from_slice(&v.to_be_bytes());
+        }
+        blob.extend_from_slice(&4.0f64.to_be_bytes());
+        blob.extend_from_slice(&4.0f64.to_be_bytes());
+        blob.push(END);
+
+        let ewkb = spatialite_to_ewkb(&blob).unwrap();
+        assert_eq!(as_text(&ewkb).unwrap(), "LINESTRING(0 0,1 1,3 2,4 4)");
+        assert_eq!(extract_srid(&ewkb), Some(3857));
+
+        let mut tiny = vec![START, TINY_POINT_LE];
+        tiny.extend_from_slice(&4326i32.to_le_bytes());
+        tiny.push(1);
+        tiny.extend_from_slice(&1.5f64.to_le_bytes());
+        tiny.extend_from_slice(&2.5f64.to_le_bytes());
+        tiny.push(END);
+        assert!(is_spatialite_blob(&tiny));
+        let ewkb = spatialite_to_ewkb(&tiny).unwrap();
+        assert_eq!(as_text(&ewkb).unwrap(), "POINT(1.5 2.5)");
+        assert_eq!(parse_ewkb_header(&ewkb).unwrap().srid, Some(4326));
+    }
+
+    #[test]
+    fn rejects_empty_geometries_and_malformed_blobs() {
+        let empty = geom_from_text("LINESTRING EMPTY", None).unwrap();
+        assert!(matches!(
+            ewkb_to_spatialite(&empty),
+            Err(SqliteGisError::InvalidInput(_))
+        ));
+
+        let good = ewkb_to_spatialite(&geom_from_text("POINT(1 2)", None).unwrap()).unwrap();
+        let mut bad_class = good.clone();
+        bad_class[39] = 9;
+        let mut truncated = good[..50].to_vec();
+        truncated.push(END);
+        let mut trailing = good[..59].to_vec();
+        trailing.extend_from_slice(&[0, END]);
+        for blob in [bad_class, truncated, trailing, good[..59].to_vec()] {
+            assert!(
+                matches!(
+                    spatialite_to_ewkb(&blob),
+                    Err(SqliteGisError::InvalidInput(_))
+                ),
+                "{blob:?}"
+            );
+        }
+    }
+}
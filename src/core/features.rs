@@ -0,0 +1,155 @@
+//! Row-shaped features shared by the file-format readers and writers.
+//!
+//! A [`Feature`] is one EWKB geometry plus one [`Value`] per attribute
+//! field, in the order of the format's [`Field`] list. Values use SQLite's
+//! storage classes so they bind to, and come back from, table columns
+//! without another conversion step.
+//!
+//! ```
+//! use sqlitegis::core::features::{FieldType, Value};
+//!
+//! let column = [Value::Integer(1), Value::Null, Value::Real(2.5)];
+//! assert_eq!(FieldType::infer(&column), FieldType::Real);
+//! assert_eq!(FieldType::Real.sql_type(), "REAL");
+//! ```
+
+use std::io::{self, Read};
+
+use crate::core::error::Result;
+
+/// One attribute value, as one of SQLite's storage classes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// SQL `NULL`, or an attribute the feature does not set.
+    Null,
+    /// A 64-bit signed integer.
+    Integer(i64),
+    /// A 64-bit float.
+    Real(f64),
+    /// UTF-8 text.
+    Text(String),
+    /// Raw bytes.
+    Blob(Vec<u8>),
+}
+
+impl Value {
+    /// The value converted to `field_type`. Integers widen to reals; a
+    /// value that cannot be represented as `field_type` is written as text,
+    /// with blobs in uppercase hex.
+    pub fn coerce(&self, field_type: FieldType) -> Value {
+        match (self, field_type) {
+            (Value::Null, _) => Value::Null,
+            (Value::Integer(i), FieldType::Real) => Value::Real(*i as f64),
+            (Value::Integer(_), FieldType::Integer)
+            | (Value::Real(_), FieldType::Real)
+            | (Value::Text(_), FieldType::Text)
+            | (Value::Blob(_), FieldType::Blob) => self.clone(),
+            (Value::Integer(i), _) => Value::Text(i.to_string()),
+            (Value::Real(r), _) => Value::Text(r.to_string()),
+            (Value::Text(t), _) => Value::Text(t.clone()),
+            (Value::Blob(b), _) => {
+                Value::Text(b.iter().map(|byte| format!("{byte:02X}")).collect())
+            }
+        }
+    }
+}
+
+/// Attribute type of a [`Field`], one per non-NULL storage class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    /// Integer attribute, declared `INTEGER`.
+    Integer,
+    /// Floating point attribute, declared `REAL`.
+    Real,
+    /// Text attribute, declared `TEXT`.
+    Text,
+    /// Binary attribute, declared `BLOB`.
+    Blob,
+}
+
+impl FieldType {
+    /// Declared SQLite column type.
+    pub const fn sql_type(self) -> &'static str {
+        match self {
+            FieldType::Integer => "INTEGER",
+            FieldType::Real => "REAL",
+            FieldType::Text => "TEXT",
+            FieldType::Blob => "BLOB",
+        }
+    }
+
+    /// The narrowest type that holds every non-NULL value of a column:
+    /// the values' own type when they agree, `Real` for a mix of integers
+    /// and reals, and `Text` for any other mix or an all-NULL column.
+    pub fn infer<'a>(values: impl IntoIterator<Item = &'a Value>) -> FieldType {
+        values
+            .into_iter()
+            .fold(None, FieldType::widen)
+            .unwrap_or(FieldType::Text)
+    }
+
+    /// One step of [`FieldType::infer`]: the type of a column inferred as
+    /// `inferred` so far once it also holds `value`, for callers that see
+    /// the values one at a time.
+    pub fn widen(inferred: Option<FieldType>, value: &Value) -> Option<FieldType> {
+        let field_type = match value {
+            Value::Null => return inferred,
+            Value::Integer(_) => FieldType::Integer,
+            Value::Real(_) => FieldType::Real,
+            Value::Text(_) => FieldType::Text,
+            Value::Blob(_) => FieldType::Blob,
+        };
+        Some(match (inferred, field_type) {
+            (None, t) => t,
+            (Some(a), b) if a == b => a,
+            (Some(FieldType::Integer | FieldType::Real), FieldType::Integer | FieldType::Real) => {
+                FieldType::Real
+            }
+            _ => FieldType::Text,
+        })
+    }
+}
+
+/// A named attribute of a feature schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    /// Attribute name, used as the column name on import.
+    pub name: String,
+    /// Attribute type.
+    pub field_type: FieldType,
+}
+
+impl Field {
+    /// A field named `name` of type `field_type`.
+    pub fn new(name: impl Into<String>, field_type: FieldType) -> Self {
+        Self {
+            name: name.into(),
+            field_type,
+        }
+    }
+}
+
+/// One geometry with its attribute values.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Feature {
+    /// EWKB geometry, `None` for a feature without one.
+    pub geometry: Option<Vec<u8>>,
+    /// One value per field of the schema, in field order.
+    pub properties: Vec<Value>,
+}
+
+/// Read exactly `buf.len()` bytes, or report `false` at a clean end of
+/// input.
+pub(crate) fn read_or_eof(reader: &mut impl Read, buf: &mut [u8]) -> Result<bool> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(true)
+}
@@ -0,0 +1,1180 @@
+//! FlatGeobuf reader and writer on top of the `flatgeobuf` crate.
+//!
+//! File layout (FlatGeobuf 3.x):
+//!   \[8 bytes\]: magic `fgb\x03fgb\x00`
+//!   \[u32 + bytes\]: size-prefixed `Header` flatbuffer
+//!   \[40 bytes * n\]: optional packed Hilbert R-tree, root first
+//!   \[u32 + bytes\] * features_count: size-prefixed `Feature` flatbuffers
+//!
+//! The schema tables, their verification and the packed R-tree come from
+//! the `flatgeobuf` crate. This module frames the records itself, so every
+//! length is checked against the [limits](crate::core::limits) before it is
+//! allocated, and converts geometries and attributes with bounds-checked
+//! code that also carries NULL and empty geometries, which the crate's own
+//! reader and writer cannot.
+//!
+//! [`FgbReader`] streams features one at a time and skips the index.
+//! [`FgbWriter`] has to see every feature before it can write the index,
+//! so it spills the encoded features to an anonymous temporary file and
+//! keeps only their bounding boxes and offsets in memory until
+//! [`FgbWriter::finish`] copies them out along the Hilbert curve.
+//!
+//! Only XY geometries are supported; files declaring Z or M coordinates
+//! are rejected rather than flattened. The SRID is the header's CRS code.
+//! Attribute values map onto [`Value`]: every integer column type becomes
+//! `Integer`, `Float` and `Double` become `Real`, `String`, `Json` and
+//! `DateTime` become `Text`, and `Binary` becomes `Blob`. The writer
+//! declares `Long`, `Double`, `String` and `Binary` columns.
+//!
+//! ```
+//! use sqlitegis::core::features::{Feature, Field, FieldType, Value};
+//! use sqlitegis::core::flatgeobuf::{FgbReader, FgbWriter};
+//! use sqlitegis::core::functions::io::geom_from_text;
+//!
+//! let mut writer = FgbWriter::new("places", vec![Field::new("name", FieldType::Text)]).unwrap();
+//! writer
+//!     .add_feature(&Feature {
+//!         geometry: Some(geom_from_text("POINT(1 2)", Some(4326)).unwrap()),
+//!         properties: vec![Value::Text("a".into())],
+//!     })
+//!     .unwrap();
+//! let mut file = Vec::new();
+//! assert_eq!(writer.finish(&mut file).unwrap(), 1);
+//!
+//! let mut reader = FgbReader::open(file.as_slice()).unwrap();
+//! assert_eq!(reader.header().srid, Some(4326));
+//! assert_eq!(reader.header().fields[0].name, "name");
+//! let feature = reader.next_feature().unwrap().unwrap();
+//! assert_eq!(feature.properties, vec![Value::Text("a".into())]);
+//! assert!(reader.next_feature().unwrap().is_none());
+//! ```
+
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+
+use ::flatgeobuf as fgb;
+use fgb::packed_r_tree::{hilbert_sort, NodeItem, PackedRTree};
+use fgb::{ColumnType, GeometryType};
+use flatbuffers::{FlatBufferBuilder, WIPOffset};
+use geo::{
+    BoundingRect, Coord, Geometry, GeometryCollection, LineString, MultiLineString, MultiPoint,
+    MultiPolygon, Point, Polygon,
+};
+
+use crate::core::error::{Result, SqliteGisError};
+use crate::core::ewkb::{ensure_matching_srid, ensure_xy_only, parse_ewkb, write_ewkb};
+use crate::core::features::{Feature, Field, FieldType, Value};
+use crate::core::limits::{check_input_bytes, check_input_vertices};
+
+/// The eight bytes every FlatGeobuf 3.x file starts with. The fourth byte
+/// is the major version, the eighth the patch version.
+pub const MAGIC: [u8; 8] = *b"fgb\x03fgb\x00";
+
+/// Branching factor of the packed R-tree written by [`FgbWriter`], and
+/// the format's default.
+pub const DEFAULT_INDEX_NODE_SIZE: u16 = PackedRTree::DEFAULT_NODE_SIZE;
+
+/// Bytes per packed R-tree node: four `f64` bounds and a `u64` offset.
+const NODE_ITEM_LEN: u64 = 40;
+
+/// Deepest geometry nesting the reader follows.
+const MAX_PART_DEPTH: usize = 32;
+
+/// Decoded FlatGeobuf header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FgbHeader {
+    /// Dataset name, `None` when the file does not set one.
+    pub name: Option<String>,
+    /// Geometry type code shared by every feature; `0` for mixed types.
+    pub geometry_type: u8,
+    /// Attribute columns, in the order their values appear in features.
+    pub fields: Vec<Field>,
+    /// Number of features, `0` when the writer did not know it.
+    pub features_count: u64,
+    /// Branching factor of the packed R-tree, `0` for a file without one.
+    pub index_node_size: u16,
+    /// SRID taken from the CRS code.
+    pub srid: Option<i32>,
+    /// Dataset extent as `[minx, miny, maxx, maxy]`.
+    pub envelope: Option<[f64; 4]>,
+}
+
+/// Streaming FlatGeobuf reader.
+#[derive(Debug)]
+pub struct FgbReader<R> {
+    reader: R,
+    header: FgbHeader,
+    column_types: Vec<ColumnType>,
+}
+
+impl<R: Read> FgbReader<R> {
+    /// Read the magic bytes and the header, and skip the spatial index so
+    /// the next read returns the first feature.
+    pub fn open(mut reader: R) -> Result<Self> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if magic[..3] != MAGIC[..3] || magic[4..7] != MAGIC[4..7] {
+            return Err(corrupt("missing magic bytes"));
+        }
+        if magic[3] != MAGIC[3] {
+            return Err(SqliteGisError::InvalidInput(format!(
+                "unsupported FlatGeobuf major version {}",
+                magic[3]
+            )));
+        }
+
+        let buf = read_record(&mut reader)?.ok_or_else(|| corrupt("missing header"))?;
+        let (header, column_types) = parse_header(&buf)?;
+
+        if header.index_node_size > 0 && header.features_count > 0 {
+            let size = packed_rtree_len(header.features_count, header.index_node_size)?;
+            let skipped = io::copy(&mut (&mut reader).take(size), &mut io::sink())?;
+            if skipped != size {
+                return Err(corrupt("truncated spatial index"));
+            }
+        }
+
+        Ok(Self {
+            reader,
+            header,
+            column_types,
+        })
+    }
+
+    /// The file's header.
+    pub fn header(&self) -> &FgbHeader {
+        &self.header
+    }
+
+    /// The next feature, or `None` at the end of the file. Its properties
+    /// hold one value per header field, `Null` where the feature sets none.
+    pub fn next_feature(&mut self) -> Result<Option<Feature>> {
+        let Some(buf) = read_record(&mut self.reader)? else {
+            return Ok(None);
+        };
+        self.parse_feature(&buf).map(Some)
+    }
+
+    fn parse_feature(&self, buf: &[u8]) -> Result<Feature> {
+        let feature = fgb::size_prefixed_root_as_feature(buf).map_err(invalid_flatbuffer)?;
+        if feature.columns().is_some() {
+            return Err(SqliteGisError::InvalidInput(
+                "FlatGeobuf features with their own column schema are not supported".to_string(),
+            ));
+        }
+        let geometry = match feature.geometry() {
+            Some(geometry) => {
+                let inherited = GeometryType(self.header.geometry_type);
+                let geom = read_geometry(geometry, inherited, 0)?;
+                check_input_vertices(&geom)?;
+                Some(write_ewkb(&geom, self.header.srid)?)
+            }
+            None => None,
+        };
+        let properties = feature.properties().map_or(&[][..], |p| p.bytes());
+        let properties = read_properties(properties, &self.column_types)?;
+        Ok(Feature {
+            geometry,
+            properties,
+        })
+    }
+}
+
+impl<R: Read> Iterator for FgbReader<R> {
+    type Item = Result<Feature>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_feature().transpose()
+    }
+}
+
+/// Where [`FgbWriter`] keeps the encoded features until it can order them:
+/// an anonymous temporary file, removed when it is closed. wasm32 has no
+/// filesystem, so there the features stay in memory.
+#[cfg(not(target_arch = "wasm32"))]
+type Spill = std::fs::File;
+#[cfg(target_arch = "wasm32")]
+type Spill = io::Cursor<Vec<u8>>;
+
+fn spill_file() -> io::Result<Spill> {
+    #[cfg(not(target_arch = "wasm32"))]
+    return tempfile::tempfile();
+    #[cfg(target_arch = "wasm32")]
+    return Ok(io::Cursor::default());
+}
+
+/// FlatGeobuf writer with a packed Hilbert R-tree.
+///
+/// Encoded features go to an anonymous temporary file as they are added,
+/// so memory stays at 48 bytes per feature (its bounding box, offset and
+/// length) however large the features are; [`FgbWriter::finish`] also
+/// builds the index in memory, another 40 bytes per tree node. The index
+/// is written when every feature has a non-empty geometry; otherwise the
+/// file is written without one, as the format requires.
+#[derive(Debug)]
+pub struct FgbWriter {
+    name: String,
+    fields: Vec<Field>,
+    index_node_size: u16,
+    srid: Option<Option<i32>>,
+    geometry_type: Option<GeometryType>,
+    spill: BufWriter<Spill>,
+    /// End offset of each feature in `spill`.
+    ends: Vec<u64>,
+    /// Bounding box of each feature, its `offset` the feature's number.
+    /// Emptied, and no longer filled, once a feature has no bounding box.
+    nodes: Vec<NodeItem>,
+    indexable: bool,
+    extent: NodeItem,
+}
+
+impl FgbWriter {
+    /// A writer for a dataset called `name` with the given attribute
+    /// columns. Fails when the temporary file cannot be created.
+    pub fn new(name: impl Into<String>, fields: Vec<Field>) -> Result<Self> {
+        Ok(Self {
+            name: name.into(),
+            fields,
+            index_node_size: DEFAULT_INDEX_NODE_SIZE,
+            srid: None,
+            geometry_type: None,
+            spill: BufWriter::new(spill_file()?),
+            ends: Vec::new(),
+            nodes: Vec::new(),
+            indexable: true,
+            extent: NodeItem::create(0),
+        })
+    }
+
+    /// Set the branching factor of the R-tree; `0` writes no index.
+    pub fn set_index_node_size(&mut self, index_node_size: u16) -> Result<()> {
+        if index_node_size == 1 {
+            return Err(SqliteGisError::InvalidInput(
+                "index node size must be 0 or at least 2".to_string(),
+            ));
+        }
+        self.index_node_size = index_node_size;
+        Ok(())
+    }
+
+    /// Number of features added so far.
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    /// Whether no feature has been added.
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    /// Encode one feature. It needs one property per field, converted to
+    /// the field's type as by [`Value::coerce`], and every geometry of a
+    /// file must share one SRID.
+    pub fn add_feature(&mut self, feature: &Feature) -> Result<()> {
+        if feature.properties.len() != self.fields.len() {
+            return Err(SqliteGisError::InvalidInput(format!(
+                "feature has {} properties but the dataset has {} columns",
+                feature.properties.len(),
+                self.fields.len()
+            )));
+        }
+
+        let mut fbb = FlatBufferBuilder::new();
+        let mut bbox = None;
+        let geometry = match &feature.geometry {
+            Some(blob) => {
+                let (geom, srid) = parse_ewkb(blob)?;
+                match self.srid {
+                    Some(shared) => {
+                        ensure_matching_srid(shared, srid)?;
+                    }
+                    None => self.srid = Some(srid),
+                }
+                let geometry_type = geometry_type_of(&geom);
+                self.geometry_type = match self.geometry_type {
+                    Some(previous) if previous != geometry_type => Some(GeometryType::Unknown),
+                    Some(previous) => Some(previous),
+                    None => Some(geometry_type),
+                };
+                bbox = geom.bounding_rect();
+                Some(build_geometry(&mut fbb, &geom))
+            }
+            None => None,
+        };
+        let properties = write_properties(&self.fields, &feature.properties)?;
+        let properties = Some(fbb.create_vector(&properties));
+        let root = fgb::Feature::create(
+            &mut fbb,
+            &fgb::FeatureArgs {
+                geometry,
+                properties,
+                ..Default::default()
+            },
+        );
+        fbb.finish_size_prefixed(root, None);
+        let bytes = fbb.finished_data();
+        self.spill.write_all(bytes)?;
+        let start = self.ends.last().copied().unwrap_or(0);
+        self.ends.push(start + bytes.len() as u64);
+
+        match bbox {
+            Some(bbox) => {
+                let mut node =
+                    NodeItem::bounds(bbox.min().x, bbox.min().y, bbox.max().x, bbox.max().y);
+                self.extent.expand(&node);
+                if self.indexable {
+                    node.offset = self.nodes.len() as u64;
+                    self.nodes.push(node);
+                }
+            }
+            None => {
+                self.indexable = false;
+                self.nodes = Vec::new();
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the file to `out` and return the number of features.
+    pub fn finish<W: Write>(mut self, mut out: W) -> Result<u64> {
+        let count = self.ends.len() as u64;
+        let indexed = self.index_node_size >= 2 && self.indexable && count > 0;
+        let spill = self.spill.into_inner().map_err(|e| e.into_error())?;
+        let mut spill = BufReader::new(spill);
+
+        out.write_all(&MAGIC)?;
+        out.write_all(&build_header(
+            &self.name,
+            &self.fields,
+            self.srid.flatten(),
+            self.geometry_type.unwrap_or(GeometryType::Unknown),
+            &self.extent,
+            count,
+            if indexed { self.index_node_size } else { 0 },
+        ))?;
+
+        if indexed {
+            hilbert_sort(&mut self.nodes, &self.extent);
+            // Leaves point at their feature's offset in the output, which
+            // is only known once the features are in index order.
+            let order: Vec<usize> = self.nodes.iter().map(|n| n.offset as usize).collect();
+            let span = |i: usize| (i.checked_sub(1).map_or(0, |j| self.ends[j]), self.ends[i]);
+            let mut offset = 0;
+            for (node, &i) in self.nodes.iter_mut().zip(&order) {
+                let (start, end) = span(i);
+                node.offset = offset;
+                offset += end - start;
+            }
+            PackedRTree::build(&self.nodes, &self.extent, self.index_node_size)
+                .map_err(flatgeobuf_error)?
+                .stream_write(&mut out)?;
+
+            let mut buf = Vec::new();
+            for i in order {
+                let (start, end) = span(i);
+                spill.seek(SeekFrom::Start(start))?;
+                buf.resize((end - start) as usize, 0);
+                spill.read_exact(&mut buf)?;
+                out.write_all(&buf)?;
+            }
+        } else {
+            spill.rewind()?;
+            io::copy(&mut spill, &mut out)?;
+        }
+        out.flush()?;
+        Ok(count)
+    }
+}
+
+fn build_header(
+    name: &str,
+    fields: &[Field],
+    srid: Option<i32>,
+    geometry_type: GeometryType,
+    extent: &NodeItem,
+    features_count: u64,
+    index_node_size: u16,
+) -> Vec<u8> {
+    let mut fbb = FlatBufferBuilder::new();
+    let name = (!name.is_empty()).then(|| fbb.create_string(name));
+    let envelope = (extent.min_x <= extent.max_x)
+        .then(|| fbb.create_vector(&[extent.min_x, extent.min_y, extent.max_x, extent.max_y]));
+    let columns: Vec<_> = fields
+        .iter()
+        .map(|field| {
+            let args = fgb::ColumnArgs {
+                name: Some(fbb.create_string(&field.name)),
+                type_: column_type_of(field.field_type),
+                ..Default::default()
+            };
+            fgb::Column::create(&mut fbb, &args)
+        })
+        .collect();
+    let columns = Some(fbb.create_vector(&columns));
+    let crs = match srid {
+        Some(srid) if srid != 0 => {
+            let args = fgb::CrsArgs {
+                org: Some(fbb.create_string("EPSG")),
+                code: srid,
+                ..Default::default()
+            };
+            Some(fgb::Crs::create(&mut fbb, &args))
+        }
+        _ => None,
+    };
+    let root = fgb::Header::create(
+        &mut fbb,
+        &fgb::HeaderArgs {
+            name,
+            envelope,
+            geometry_type,
+            columns,
+            features_count,
+            index_node_size,
+            crs,
+            ..Default::default()
+        },
+    );
+    fbb.finish_size_prefixed(root, None);
+    fbb.finished_data().to_vec()
+}
+
+// Header and feature decoding
+
+fn corrupt(what: &str) -> SqliteGisError {
+    SqliteGisError::InvalidInput(format!("corrupt FlatGeobuf: {what}"))
+}
+
+fn invalid_flatbuffer(e: flatbuffers::InvalidFlatbuffer) -> SqliteGisError {
+    corrupt(&e.to_string())
+}
+
+fn flatgeobuf_error(e: fgb::Error) -> SqliteGisError {
+    match e {
+        fgb::Error::IO(e) => e.into(),
+        other => corrupt(&other.to_string()),
+    }
+}
+
+/// Read one size-prefixed record, prefix included, or `None` at a clean
+/// end of input.
+fn read_record(reader: &mut impl Read) -> Result<Option<Vec<u8>>> {
+    let mut prefix = [0; 4];
+    let mut filled = 0;
+    while filled < prefix.len() {
+        match reader.read(&mut prefix[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => return Err(corrupt("truncated record length")),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    let len = u32::from_le_bytes(prefix) as usize;
+    check_input_bytes(len)?;
+    let mut buf = vec![0; 4 + len];
+    buf[..4].copy_from_slice(&prefix);
+    reader.read_exact(&mut buf[4..])?;
+    Ok(Some(buf))
+}
+
+fn parse_header(buf: &[u8]) -> Result<(FgbHeader, Vec<ColumnType>)> {
+    let header = fgb::size_prefixed_root_as_header(buf).map_err(invalid_flatbuffer)?;
+    ensure_xy_only(header.has_z(), header.has_m())?;
+    if header.has_t() || header.has_tm() {
+        return Err(SqliteGisError::UnsupportedDimensions { dimensions: "T" });
+    }
+
+    let mut fields = Vec::new();
+    let mut column_types = Vec::new();
+    for column in header.columns().iter().flat_map(|columns| columns.iter()) {
+        fields.push(Field::new(column.name(), field_type_of(column.type_())?));
+        column_types.push(column.type_());
+    }
+
+    let envelope: Vec<f64> = header
+        .envelope()
+        .map(|envelope| envelope.iter().take(4).collect())
+        .unwrap_or_default();
+    let header = FgbHeader {
+        name: header.name().map(str::to_string),
+        geometry_type: header.geometry_type().0,
+        fields,
+        features_count: header.features_count(),
+        index_node_size: header.index_node_size(),
+        srid: header.crs().map(|crs| crs.code()).filter(|&code| code != 0),
+        envelope: <[f64; 4]>::try_from(envelope).ok(),
+    };
+    if header.index_node_size == 1 {
+        return Err(corrupt("index node size 1"));
+    }
+    Ok((header, column_types))
+}
+
+fn field_type_of(column_type: ColumnType) -> Result<FieldType> {
+    Ok(match column_type {
+        ColumnType::Byte
+        | ColumnType::UByte
+        | ColumnType::Bool
+        | ColumnType::Short
+        | ColumnType::UShort
+        | ColumnType::Int
+        | ColumnType::UInt
+        | ColumnType::Long
+        | ColumnType::ULong => FieldType::Integer,
+        ColumnType::Float | ColumnType::Double => FieldType::Real,
+        ColumnType::String | ColumnType::Json | ColumnType::DateTime => FieldType::Text,
+        ColumnType::Binary => FieldType::Blob,
+        other => {
+            return Err(SqliteGisError::InvalidInput(format!(
+                "unsupported FlatGeobuf column type {}",
+                other.0
+            )))
+        }
+    })
+}
+
+fn column_type_of(field_type: FieldType) -> ColumnType {
+    match field_type {
+        FieldType::Integer => ColumnType::Long,
+        FieldType::Real => ColumnType::Double,
+        FieldType::Text => ColumnType::String,
+        FieldType::Blob => ColumnType::Binary,
+    }
+}
+
+fn read_properties(mut bytes: &[u8], column_types: &[ColumnType]) -> Result<Vec<Value>> {
+    fn take<'a>(bytes: &mut &'a [u8], n: usize) -> Result<&'a [u8]> {
+        if bytes.len() < n {
+            return Err(corrupt("truncated properties"));
+        }
+        let (head, tail) = bytes.split_at(n);
+        *bytes = tail;
+        Ok(head)
+    }
+    fn fixed<const N: usize>(bytes: &mut &[u8]) -> Result<[u8; N]> {
+        Ok(take(bytes, N)?.try_into().expect("length checked by take"))
+    }
+
+    let mut values = vec![Value::Null; column_types.len()];
+    while !bytes.is_empty() {
+        let column = u16::from_le_bytes(fixed(&mut bytes)?) as usize;
+        let Some(&column_type) = column_types.get(column) else {
+            return Err(corrupt("property for an undeclared column"));
+        };
+        values[column] = match column_type {
+            ColumnType::Byte => Value::Integer(i8::from_le_bytes(fixed(&mut bytes)?).into()),
+            ColumnType::UByte | ColumnType::Bool => {
+                Value::Integer(fixed::<1>(&mut bytes)?[0].into())
+            }
+            ColumnType::Short => Value::Integer(i16::from_le_bytes(fixed(&mut bytes)?).into()),
+            ColumnType::UShort => Value::Integer(u16::from_le_bytes(fixed(&mut bytes)?).into()),
+            ColumnType::Int => Value::Integer(i32::from_le_bytes(fixed(&mut bytes)?).into()),
+            ColumnType::UInt => Value::Integer(u32::from_le_bytes(fixed(&mut bytes)?).into()),
+            ColumnType::Long => Value::Integer(i64::from_le_bytes(fixed(&mut bytes)?)),
+            ColumnType::ULong => {
+                let v = u64::from_le_bytes(fixed(&mut bytes)?);
+                i64::try_from(v).map_or(Value::Real(v as f64), Value::Integer)
+            }
+            ColumnType::Float => Value::Real(f32::from_le_bytes(fixed(&mut bytes)?).into()),
+            ColumnType::Double => Value::Real(f64::from_le_bytes(fixed(&mut bytes)?)),
+            _ => {
+                let len = u32::from_le_bytes(fixed(&mut bytes)?) as usize;
+                let data = take(&mut bytes, len)?;
+                if column_type == ColumnType::Binary {
+                    Value::Blob(data.to_vec())
+                } else {
+                    let text = std::str::from_utf8(data)
+                        .map_err(|_| corrupt("string property is not valid UTF-8"))?;
+                    Value::Text(text.to_string())
+                }
+            }
+        };
+    }
+    Ok(values)
+}
+
+fn write_properties(fields: &[Field], values: &[Value]) -> Result<Vec<u8>> {
+    fn value_len(len: usize) -> Result<[u8; 4]> {
+        u32::try_from(len).map(u32::to_le_bytes).map_err(|_| {
+            SqliteGisError::InvalidInput(format!(
+                "FlatGeobuf property values are limited to 4 GiB, got {len} bytes"
+            ))
+        })
+    }
+
+    let mut out = Vec::new();
+    for (column, (field, value)) in fields.iter().zip(values).enumerate() {
+        let value = value.coerce(field.field_type);
+        if value == Value::Null {
+            continue;
+        }
+        let column = u16::try_from(column).map_err(|_| {
+            SqliteGisError::InvalidInput(format!(
+                "FlatGeobuf files hold at most 65536 columns, got {}",
+                fields.len()
+            ))
+        })?;
+        out.extend_from_slice(&column.to_le_bytes());
+        match value {
+            Value::Null => {}
+            Value::Integer(i) => out.extend_from_slice(&i.to_le_bytes()),
+            Value::Real(r) => out.extend_from_slice(&r.to_le_bytes()),
+            Value::Text(t) => {
+                out.extend_from_slice(&value_len(t.len())?);
+                out.extend_from_slice(t.as_bytes());
+            }
+            Value::Blob(b) => {
+                out.extend_from_slice(&value_len(b.len())?);
+                out.extend_from_slice(&b);
+            }
+        }
+    }
+    // The flatbuffers builder panics rather than grow past this.
+    if out.len() >= flatbuffers::FLATBUFFERS_MAX_BUFFER_SIZE {
+        return Err(SqliteGisError::InvalidInput(format!(
+            "FlatGeobuf feature properties take {} bytes, over the 2 GiB record limit",
+            out.len()
+        )));
+    }
+    Ok(out)
+}
+
+// Geometry codec
+
+fn geometry_type_of(geom: &Geometry<f64>) -> GeometryType {
+    match geom {
+        Geometry::Point(_) => GeometryType::Point,
+        Geometry::Line(_) | Geometry::LineString(_) => GeometryType::LineString,
+        Geometry::Polygon(_) | Geometry::Rect(_) | Geometry::Triangle(_) => GeometryType::Polygon,
+        Geometry::MultiPoint(_) => GeometryType::MultiPoint,
+        Geometry::MultiLineString(_) => GeometryType::MultiLineString,
+        Geometry::MultiPolygon(_) => GeometryType::MultiPolygon,
+        Geometry::GeometryCollection(_) => GeometryType::GeometryCollection,
+    }
+}
+
+/// Coordinates and ring or line ends of one flat geometry table.
+#[derive(Default)]
+struct FlatCoords {
+    xy: Vec<f64>,
+    ends: Vec<u32>,
+}
+
+impl FlatCoords {
+    fn push_line(&mut self, line: &LineString<f64>) {
+        for c in line.coords() {
+            self.xy.extend_from_slice(&[c.x, c.y]);
+        }
+        self.ends.push((self.xy.len() / 2) as u32);
+    }
+
+    fn push_polygon(&mut self, polygon: &Polygon<f64>) {
+        if polygon.exterior().0.is_empty() {
+            return;
+        }
+        self.push_line(polygon.exterior());
+        for interior in polygon.interiors() {
+            self.push_line(interior);
+        }
+    }
+}
+
+fn build_geometry<'a>(
+    fbb: &mut FlatBufferBuilder<'a>,
+    geom: &Geometry<f64>,
+) -> WIPOffset<fgb::Geometry<'a>> {
+    let mut flat = FlatCoords::default();
+    let mut parts = Vec::new();
+    match geom {
+        Geometry::Point(p) => {
+            if !(p.x().is_nan() && p.y().is_nan()) {
+                flat.xy.extend_from_slice(&[p.x(), p.y()]);
+            }
+        }
+        Geometry::Line(line) => flat.push_line(&LineString::from(*line)),
+        Geometry::LineString(line) => flat.push_line(line),
+        Geometry::Polygon(polygon) => flat.push_polygon(polygon),
+        Geometry::Rect(rect) => flat.push_polygon(&rect.to_polygon()),
+        Geometry::Triangle(triangle) => flat.push_polygon(&triangle.to_polygon()),
+        Geometry::MultiPoint(points) => {
+            for p in points {
+                flat.xy.extend_from_slice(&[p.x(), p.y()]);
+            }
+        }
+        Geometry::MultiLineString(lines) => {
+            for line in lines {
+                flat.push_line(line);
+            }
+        }
+        Geometry::MultiPolygon(polygons) => {
+            for polygon in polygons {
+                parts.push(build_geometry(fbb, &Geometry::Polygon(polygon.clone())));
+            }
+        }
+        Geometry::GeometryCollection(collection) => {
+            for member in collection {
+                parts.push(build_geometry(fbb, member));
+            }
+        }
+    }
+
+    // Ends are only needed to split more than one ring or line.
+    let args = fgb::GeometryArgs {
+        ends: (flat.ends.len() > 1).then(|| fbb.create_vector(&flat.ends)),
+        xy: (!flat.xy.is_empty()).then(|| fbb.create_vector(&flat.xy)),
+        parts: (!parts.is_empty()).then(|| fbb.create_vector(&parts)),
+        type_: geometry_type_of(geom),
+        ..Default::default()
+    };
+    fgb::Geometry::create(fbb, &args)
+}
+
+fn read_geometry(
+    geometry: fgb::Geometry<'_>,
+    inherited_type: GeometryType,
+    depth: usize,
+) -> Result<Geometry<f64>> {
+    if depth > MAX_PART_DEPTH {
+        return Err(corrupt("geometry parts nested too deeply"));
+    }
+    if geometry.z().is_some() || geometry.m().is_some() {
+        return Err(SqliteGisError::UnsupportedDimensions {
+            dimensions: "XYZ/XYM",
+        });
+    }
+    let geometry_type = match geometry.type_() {
+        GeometryType::Unknown => inherited_type,
+        own => own,
+    };
+    let xy: Vec<f64> = geometry.xy().iter().flat_map(|xy| xy.iter()).collect();
+    if !xy.len().is_multiple_of(2) {
+        return Err(corrupt("odd number of xy values"));
+    }
+    let coords: Vec<Coord<f64>> = xy
+        .chunks_exact(2)
+        .map(|c| Coord { x: c[0], y: c[1] })
+        .collect();
+    let lines = || -> Result<Vec<LineString<f64>>> {
+        let Some(ends) = geometry.ends().filter(|ends| !ends.is_empty()) else {
+            return Ok(if coords.is_empty() {
+                Vec::new()
+            } else {
+                vec![LineString::new(coords.clone())]
+            });
+        };
+        let mut start = 0;
+        let mut lines = Vec::with_capacity(ends.len());
+        for end in ends.iter() {
+            let end = end as usize;
+            if end < start || end > coords.len() {
+                return Err(corrupt("ring end out of range"));
+            }
+            lines.push(LineString::new(coords[start..end].to_vec()));
+            start = end;
+        }
+        Ok(lines)
+    };
+    let parts = || geometry.parts().into_iter().flat_map(|parts| parts.iter());
+
+    Ok(match geometry_type {
+        GeometryType::Point => match coords.first() {
+            Some(&c) => Geometry::Point(Point(c)),
+            None => Geometry::Point(Point::new(f64::NAN, f64::NAN)),
+        },
+        GeometryType::LineString => Geometry::LineString(LineString::new(coords)),
+        GeometryType::Polygon => Geometry::Polygon(polygon_from_rings(lines()?)),
+        GeometryType::MultiPoint => {
+            Geometry::MultiPoint(MultiPoint::new(coords.iter().map(|&c| Point(c)).collect()))
+        }
+        GeometryType::MultiLineString => Geometry::MultiLineString(MultiLineString::new(lines()?)),
+        GeometryType::MultiPolygon => {
+            let mut polygons = Vec::new();
+            for part in parts() {
+                match read_geometry(part, GeometryType::Polygon, depth + 1)? {
+                    Geometry::Polygon(polygon) => polygons.push(polygon),
+                    _ => return Err(corrupt("MultiPolygon part is not a Polygon")),
+                }
+            }
+            Geometry::MultiPolygon(MultiPolygon::new(polygons))
+        }
+        GeometryType::GeometryCollection => {
+            let mut members = Vec::new();
+            for part in parts() {
+                members.push(read_geometry(part, GeometryType::Unknown, depth + 1)?);
+            }
+            Geometry::GeometryCollection(GeometryCollection::new_from(members))
+        }
+        GeometryType::Unknown => return Err(corrupt("geometry without a type")),
+        other => {
+            return Err(SqliteGisError::InvalidInput(format!(
+                "unsupported FlatGeobuf geometry type {}",
+                other.0
+            )))
+        }
+    })
+}
+
+fn polygon_from_rings(rings: Vec<LineString<f64>>) -> Polygon<f64> {
+    let mut rings = rings.into_iter();
+    let exterior = rings.next().unwrap_or_else(|| LineString::new(Vec::new()));
+    Polygon::new(exterior, rings.collect())
+}
+
+/// Number of nodes on each level, leaves first. A lone leaf still gets a
+/// root above it.
+fn level_node_counts(num_items: u64, node_size: u64) -> Vec<u64> {
+    let mut n = num_items;
+    let mut counts = vec![n];
+    loop {
+        n = n.div_ceil(node_size);
+        counts.push(n);
+        if n == 1 {
+            return counts;
+        }
+    }
+}
+
+/// Size in bytes of the index over `num_items` features, checked because
+/// both numbers come from the file.
+fn packed_rtree_len(num_items: u64, node_size: u16) -> Result<u64> {
+    level_node_counts(num_items, node_size.into())
+        .iter()
+        .try_fold(0_u64, |acc, &n| acc.checked_add(n))
+        .and_then(|nodes| nodes.checked_mul(NODE_ITEM_LEN))
+        .ok_or_else(|| corrupt("spatial index size overflows"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::functions::io::{as_text, geom_from_text};
+    use crate::core::limits::{with_limits, Limits};
+
+    fn write(features: &[Feature], fields: Vec<Field>) -> Vec<u8> {
+        let mut writer = FgbWriter::new("test", fields).unwrap();
+        for feature in features {
+            writer.add_feature(feature).unwrap();
+        }
+        let mut out = Vec::new();
+        writer.finish(&mut out).unwrap();
+        out
+    }
+
+    fn point(x: f64, y: f64) -> Feature {
+        Feature {
+            geometry: Some(geom_from_text(&format!("POINT({x} {y})"), Some(3857)).unwrap()),
+            properties: vec![Value::Integer(x as i64)],
+        }
+    }
+
+    #[test]
+    fn every_geometry_type_round_trips() {
+        let wkts = [
+            "POINT(1 2)",
+            "POINT EMPTY",
+            "LINESTRING(0 0,1 1,2 0)",
+            "POLYGON((0 0,4 0,4 4,0 4,0 0),(1 1,2 1,2 2,1 1))",
+            "POLYGON((0 0,1 0,1 1,0 0))",
+            "MULTIPOINT((0 0),(1 1))",
+            "MULTILINESTRING((0 0,1 1),(2 2,3 3))",
+            "MULTILINESTRING((0 0,1 1))",
+            "MULTIPOLYGON(((0 0,1 0,1 1,0 0)),((5 5,6 5,6 6,5 5),(5.1 5.1,5.2 5.1,5.2 5.2,5.1 5.1)))",
+            "GEOMETRYCOLLECTION(POINT(1 2),LINESTRING(0 0,1 1),MULTIPOLYGON(((0 0,1 0,1 1,0 0))))",
+        ];
+        let features: Vec<Feature> = wkts
+            .iter()
+            .map(|wkt| Feature {
+                geometry: Some(geom_from_text(wkt, None).unwrap()),
+                properties: vec![Value::Text(wkt.to_string())],
+            })
+            .chain(std::iter::once(Feature {
+                geometry: None,
+                properties: vec![Value::Null],
+            }))
+            .collect();
+        let file = write(&features, vec![Field::new("wkt", FieldType::Text)]);
+
+        let reader = FgbReader::open(file.as_slice()).unwrap();
+        // A feature without geometry rules out the index.
+        assert_eq!(reader.header().index_node_size, 0);
+        assert_eq!(reader.header().geometry_type, GeometryType::Unknown.0);
+        assert_eq!(reader.header().srid, None);
+        let read: Vec<Feature> = reader.collect::<Result<_>>().unwrap();
+        assert_eq!(read.len(), features.len());
+        for (feature, original) in read.iter().zip(&features) {
+            match &original.properties[0] {
+                Value::Text(wkt) => {
+                    let geometry = feature.geometry.as_ref().unwrap();
+                    assert_eq!(
+                        as_text(geometry).unwrap(),
+                        as_text(original.geometry.as_ref().unwrap()).unwrap(),
+                        "{wkt}"
+                    );
+                    assert_eq!(feature.properties, original.properties);
+                }
+                _ => assert_eq!(feature, original),
+            }
+        }
+    }
+
+    #[test]
+    fn property_types_round_trip() {
+        let fields = vec![
+            Field::new("i", FieldType::Integer),
+            Field::new("r", FieldType::Real),
+            Field::new("t", FieldType::Text),
+            Field::new("b", FieldType::Blob),
+        ];
+        let feature = Feature {
+            geometry: Some(geom_from_text("POINT(0 0)", None).unwrap()),
+            properties: vec![
+                Value::Integer(-7),
+                Value::Integer(2),
+                Value::Text("é".into()),
+                Value::Blob(vec![0, 1, 2]),
+            ],
+        };
+        let file = write(&[feature], fields.clone());
+        let mut reader = FgbReader::open(file.as_slice()).unwrap();
+        assert_eq!(reader.header().fields, fields);
+        let read = reader.next_feature().unwrap().unwrap();
+        assert_eq!(
+            read.properties,
+            vec![
+                Value::Integer(-7),
+                Value::Real(2.0),
+                Value::Text("é".into()),
+                Value::Blob(vec![0, 1, 2]),
+            ]
+        );
+
+        // Column numbers are 16-bit; a value past them is an error, not
+        // a property written under a wrapped-around column.
+        let fields: Vec<Field> = (0..=u16::MAX as usize + 1)
+            .map(|i| Field::new(format!("c{i}"), FieldType::Integer))
+            .collect();
+        let mut properties = vec![Value::Null; fields.len()];
+        *properties.last_mut().unwrap() = Value::Integer(1);
+        let mut writer = FgbWriter::new("wide", fields).unwrap();
+        let err = writer
+            .add_feature(&Feature {
+                geometry: None,
+                properties,
+            })
+            .unwrap_err();
+        assert!(err.to_string().contains("at most 65536 columns"), "{err}");
+        assert!(writer.is_empty());
+    }
+
+    #[test]
+    fn index_is_written_in_hilbert_order() {
+        let features: Vec<Feature> = (0..40)
+            .map(|i| point(f64::from(i % 7), f64::from(i / 7)))
+            .collect();
+        let file = write(&features, vec![Field::new("x", FieldType::Integer)]);
+
+        let reader = FgbReader::open(file.as_slice()).unwrap();
+        let header = reader.header().clone();
+        assert_eq!(header.index_node_size, DEFAULT_INDEX_NODE_SIZE);
+        assert_eq!(header.features_count, 40);
+        assert_eq!(header.srid, Some(3857));
+        assert_eq!(header.geometry_type, GeometryType::Point.0);
+        assert_eq!(header.envelope, Some([0.0, 0.0, 6.0, 5.0]));
+        let read: Vec<Feature> = reader.collect::<Result<_>>().unwrap();
+        assert_eq!(read.len(), 40);
+
+        // 40 leaves, 3 parents and the root, as the crate sizes the tree.
+        let header_len = u32::from_le_bytes(file[8..12].try_into().unwrap()) as usize;
+        let index_start = 12 + header_len;
+        assert_eq!(packed_rtree_len(40, 16).unwrap(), 44 * NODE_ITEM_LEN);
+        assert_eq!(
+            PackedRTree::index_size(40, 16) as u64,
+            packed_rtree_len(40, 16).unwrap()
+        );
+        let index =
+            PackedRTree::from_buf(&file[index_start..], 40, DEFAULT_INDEX_NODE_SIZE).unwrap();
+        let extent = index.extent();
+        assert_eq!(
+            [extent.min_x, extent.min_y, extent.max_x, extent.max_y],
+            [0.0, 0.0, 6.0, 5.0]
+        );
+
+        // Every leaf found by a window search points at a feature inside it.
+        let features_start = index_start + 44 * NODE_ITEM_LEN as usize;
+        let hits = index.search(2.0, 1.0, 3.0, 2.0).unwrap();
+        assert_eq!(hits.len(), 4);
+        for hit in hits {
+            let mut at_offset = FgbReader {
+                reader: &file[features_start + hit.offset..],
+                header: header.clone(),
+                column_types: vec![ColumnType::Long],
+            };
+            let feature = at_offset.next_feature().unwrap().unwrap();
+            let (geom, _) = parse_ewkb(feature.geometry.as_ref().unwrap()).unwrap();
+            let Geometry::Point(p) = geom else {
+                panic!("not a point")
+            };
+            assert!((2.0..=3.0).contains(&p.x()) && (1.0..=2.0).contains(&p.y()));
+            assert!(read.contains(&feature));
+        }
+    }
+
+    #[test]
+    fn writer_spills_features_and_skips_the_index_without_bounds() {
+        let mut writer =
+            FgbWriter::new("spill", vec![Field::new("x", FieldType::Integer)]).unwrap();
+        for i in 0..1000 {
+            writer
+                .add_feature(&point(f64::from(i % 50), f64::from(i / 50)))
+                .unwrap();
+        }
+        assert_eq!(writer.len(), 1000);
+        // Only the bounding boxes and offsets stay in memory.
+        assert_eq!(writer.nodes.len(), 1000);
+        assert!(writer.ends.windows(2).all(|w| w[0] < w[1]));
+        let mut file = Vec::new();
+        assert_eq!(writer.finish(&mut file).unwrap(), 1000);
+        let reader = FgbReader::open(file.as_slice()).unwrap();
+        assert_eq!(reader.header().index_node_size, DEFAULT_INDEX_NODE_SIZE);
+        assert_eq!(reader.count(), 1000);
+
+        // An empty geometry has no bounding box, so the file has no index
+        // and keeps the features in insertion order.
+        let mut writer = FgbWriter::new("", Vec::new()).unwrap();
+        for wkt in ["POINT(1 1)", "LINESTRING EMPTY", "POINT(0 0)"] {
+            writer
+                .add_feature(&Feature {
+                    geometry: Some(geom_from_text(wkt, None).unwrap()),
+                    properties: Vec::new(),
+                })
+                .unwrap();
+        }
+        assert!(writer.nodes.is_empty());
+        let mut file = Vec::new();
+        writer.finish(&mut file).unwrap();
+        let reader = FgbReader::open(file.as_slice()).unwrap();
+        assert_eq!(reader.header().index_node_size, 0);
+        assert_eq!(reader.header().name, None);
+        assert_eq!(reader.header().envelope, Some([0.0, 0.0, 1.0, 1.0]));
+        let wkts: Vec<String> = reader
+            .map(|f| as_text(&f.unwrap().geometry.unwrap()).unwrap())
+            .collect();
+        assert_eq!(wkts, ["POINT(1 1)", "LINESTRING EMPTY", "POINT(0 0)"]);
+    }
+
+    #[test]
+    fn malformed_files_are_rejected() {
+        let file = write(
+            &[point(1.0, 2.0)],
+            vec![Field::new("x", FieldType::Integer)],
+        );
+        let limits = Limits {
+            max_vertices: Some(0),
+            ..Limits::default()
+        };
+        let err =
+            with_limits(limits, || FgbReader::open(file.as_slice())?.next_feature()).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                SqliteGisError::LimitExceeded {
+                    limit: "max_vertices",
+                    ..
+                }
+            ),
+            "{err}"
+        );
+        assert!(FgbReader::open(&b"not a flatgeobuf"[..]).is_err());
+        let mut wrong_version = file.clone();
+        wrong_version[3] = 2;
+        assert!(FgbReader::open(wrong_version.as_slice()).is_err());
+        for len in [4, 12, 40, file.len() - 1] {
+            let truncated = &file[..len];
+            let result = FgbReader::open(truncated).and_then(|mut r| r.next_feature());
+            assert!(result.is_err(), "truncated at {len}");
+        }
+        // Garbage in place of the header must fail cleanly, not panic.
+        let mut garbage = file.clone();
+        for byte in &mut garbage[12..40] {
+            *byte = 0xFF;
+        }
+        let _ = FgbReader::open(garbage.as_slice()).and_then(|mut r| r.next_feature());
+
+        let mut writer = FgbWriter::new("x", Vec::new()).unwrap();
+        assert!(writer.set_index_node_size(1).is_err());
+        assert!(writer.add_feature(&point(0.0, 0.0)).is_err());
+        writer
+            .add_feature(&Feature {
+                geometry: Some(geom_from_text("POINT(0 0)", Some(4326)).unwrap()),
+                properties: Vec::new(),
+            })
+            .unwrap();
+        assert!(matches!(
+            writer.add_feature(&Feature {
+                geometry: Some(geom_from_text("POINT(0 0)", Some(3857)).unwrap()),
+                properties: Vec::new(),
+            }),
+            Err(SqliteGisError::SridMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn inconsistent_geometry_and_properties_fail_without_panicking() {
+        let header = write(&[], vec![Field::new("x", FieldType::Integer)]);
+        let read = |type_, ends: &[u32], xy: &[f64], properties: &[u8]| {
+            let mut fbb = FlatBufferBuilder::new();
+            let args = fgb::GeometryArgs {
+                ends: (!ends.is_empty()).then(|| fbb.create_vector(ends)),
+                xy: (!xy.is_empty()).then(|| fbb.create_vector(xy)),
+                type_,
+                ..Default::default()
+            };
+            let geometry = Some(fgb::Geometry::create(&mut fbb, &args));
+            let properties = Some(fbb.create_vector(properties));
+            let root = fgb::Feature::create(
+                &mut fbb,
+                &fgb::FeatureArgs {
+                    geometry,
+                    properties,
+                    ..Default::default()
+                },
+            );
+            fbb.finish_size_prefixed(root, None);
+            let mut file = header.clone();
+            file.extend_from_slice(fbb.finished_data());
+            FgbReader::open(file.as_slice())?.next_feature()
+        };
+
+        // `ends` past the coordinates, an odd coordinate count, a property
+        // cut short and a property for a column that does not exist.
+        for result in [
+            read(
+                GeometryType::MultiLineString,
+                &[1, 9],
+                &[0.0, 0.0, 1.0, 1.0],
+                &[],
+            ),
+            read(GeometryType::LineString, &[], &[0.0, 0.0, 1.0], &[]),
+            read(GeometryType::Point, &[], &[0.0, 0.0], &[0, 0, 1, 2]),
+            read(
+                GeometryType::Point,
+                &[],
+                &[0.0, 0.0],
+                &[1, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            ),
+        ] {
+            let err = result.unwrap_err();
+            assert!(
+                matches!(&err, SqliteGisError::InvalidInput(msg) if msg.contains("corrupt")),
+                "{err}"
+            );
+        }
+        let point = read(
+            GeometryType::Point,
+            &[],
+            &[3.0, 4.0],
+            &[0, 0, 5, 0, 0, 0, 0, 0, 0, 0],
+        );
+        assert_eq!(point.unwrap().unwrap().properties, vec![Value::Integer(5)]);
+    }
+}
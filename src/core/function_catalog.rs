@@ -88,6 +88,9 @@ macro_rules! null_sql {
     ($name:literal, 5) => {
         concat!("SELECT ", $name, "(NULL, NULL, NULL, NULL, NULL)")
     };
+    ($name:literal, 6) => {
+        concat!("SELECT ", $name, "(NULL, NULL, NULL, NULL, NULL, NULL)")
+    };
 }
 
 macro_rules! expected_for_return_class {
@@ -191,6 +194,43 @@ macro_rules! direct_spec {
     };
 }
 
+/// Like `direct_spec!`, for functions that read or write files. Tests
+/// cannot count on a writable directory, so the smoke SQL points at a
+/// missing one and expects the I/O error, which the callback only reaches
+/// after decoding its arguments. A connection without file access fails
+/// the same case with its own `[io]` refusal.
+macro_rules! direct_file_spec {
+    (
+        $name:literal,
+        $n_arg:tt,
+        $return_class:ident,
+        $smoke_sql:literal,
+        $null_sql:literal,
+        $null_error_contains:literal,
+        $xfunc:literal
+    ) => {
+        SqliteFunctionSpec {
+            name: $name,
+            n_arg: $n_arg,
+            return_class: SqliteReturnClass::$return_class,
+            smoke_sql: $smoke_sql,
+            semantic_cases: &[
+                case!(
+                    "smoke",
+                    $smoke_sql,
+                    SemanticExpectation::ErrorContains("[io]")
+                ),
+                case!(
+                    "null_input_error",
+                    $null_sql,
+                    SemanticExpectation::ErrorContains($null_error_contains)
+                ),
+            ],
+            xfunc_override: Some($xfunc),
+        }
+    };
+}
+
 /// Catalog of every deterministic SQL function the crate exposes. Each entry
 /// is registered with SQLite's `SQLITE_DETERMINISTIC` flag so the planner can
 /// hoist calls out of inner loops and reuse cached results.
@@ -226,6 +266,12 @@ pub const SQLITE_DETERMINISTIC_FUNCTIONS: &[SqliteFunctionSpec] = &[
         Blob,
         "SELECT ST_GeomFromEWKB(ST_AsEWKB(ST_Point(1, 2, 4326)))"
     ),
+    spec!(
+        "ST_GeomFromHEXEWKB",
+        1,
+        Blob,
+        "SELECT ST_GeomFromHEXEWKB('0101000020E6100000000000000000F03F0000000000000040')"
+    ),
     // PostGIS parity: GeoJSON parser is one-argument only.
     // Use ST_SetSRID(ST_GeomFromGeoJSON(...), srid) to override 4326.
     spec!(
@@ -253,12 +299,230 @@ pub const SQLITE_DETERMINISTIC_FUNCTIONS: &[SqliteFunctionSpec] = &[
         Blob,
         "SELECT ST_AsEWKB(ST_Point(1, 2, 4326))"
     ),
+    spec!(
+        "ST_AsHEXEWKB",
+        1,
+        Text,
+        "SELECT ST_AsHEXEWKB(ST_Point(1, 2, 4326))"
+    ),
+    spec!(
+        "ST_AsHEXEWKB",
+        2,
+        Text,
+        "SELECT ST_AsHEXEWKB(ST_Point(1, 2, 4326), 'XDR')"
+    ),
     spec!(
         "ST_AsGeoJSON",
         1,
         Text,
         "SELECT ST_AsGeoJSON(ST_Point(1, 2))"
     ),
+    spec!(
+        "ST_AsGeoJSON",
+        2,
+        Text,
+        "SELECT ST_AsGeoJSON(ST_Point(1.23456, 2), 2)"
+    ),
+    spec!(
+        "ST_AsGeoJSON",
+        3,
+        Text,
+        "SELECT ST_AsGeoJSON(json_object('name', 'a'), ST_Point(1.23456, 2), 2)"
+    ),
+    spec!(
+        "ST_GeomFromGPB",
+        1,
+        Blob,
+        "SELECT ST_GeomFromGPB(AsGPB(ST_Point(1, 2, 4326)))"
+    ),
+    spec_override!(
+        "AsGPB",
+        1,
+        Blob,
+        "SELECT AsGPB(ST_Point(1, 2, 4326))",
+        "asgpb_xfunc"
+    ),
+    spec!(
+        "ST_GeomFromSpatiaLite",
+        1,
+        Blob,
+        "SELECT ST_GeomFromSpatiaLite(ST_AsSpatiaLite(ST_Point(1, 2, 4326)))"
+    ),
+    spec!(
+        "ST_AsSpatiaLite",
+        1,
+        Blob,
+        "SELECT ST_AsSpatiaLite(ST_Point(1, 2, 4326))"
+    ),
+    spec!(
+        "ST_AsTWKB",
+        1,
+        Blob,
+        "SELECT ST_AsTWKB(ST_Point(1, 2))"
+    ),
+    spec!(
+        "ST_AsTWKB",
+        2,
+        Blob,
+        "SELECT ST_AsTWKB(ST_Point(1, 2), 1)"
+    ),
+    spec!(
+        "ST_AsTWKB",
+        3,
+        Blob,
+        "SELECT ST_AsTWKB(ST_Point(1, 2), 1, 0)"
+    ),
+    spec!(
+        "ST_AsTWKB",
+        4,
+        Blob,
+        "SELECT ST_AsTWKB(ST_Point(1, 2), 1, 0, 0)"
+    ),
+    spec!(
+        "ST_AsTWKB",
+        5,
+        Blob,
+        "SELECT ST_AsTWKB(ST_Point(1, 2), 1, 0, 0, 1)"
+    ),
+    spec!(
+        "ST_AsTWKB",
+        6,
+        Blob,
+        "SELECT ST_AsTWKB(ST_Point(1, 2), 1, 0, 0, 1, 1)"
+    ),
+    spec!(
+        "ST_GeomFromTWKB",
+        1,
+        Blob,
+        "SELECT ST_GeomFromTWKB(ST_AsTWKB(ST_Point(1, 2)))"
+    ),
+    spec!(
+        "ST_AsKML",
+        1,
+        Text,
+        "SELECT ST_AsKML(ST_Point(1, 2, 4326))"
+    ),
+    spec!(
+        "ST_AsKML",
+        2,
+        Text,
+        "SELECT ST_AsKML(ST_Point(1.23456, 2, 4326), 3)"
+    ),
+    spec!("ST_AsGML", 1, Text, "SELECT ST_AsGML(ST_Point(1, 2, 4326))"),
+    spec!(
+        "ST_AsGML",
+        2,
+        Text,
+        "SELECT ST_AsGML(3, ST_Point(1, 2, 4326))"
+    ),
+    spec!(
+        "ST_AsGML",
+        3,
+        Text,
+        "SELECT ST_AsGML(3, ST_Point(1, 2, 4326), 5)"
+    ),
+    spec!(
+        "ST_AsGML",
+        4,
+        Text,
+        "SELECT ST_AsGML(3, ST_Point(1, 2, 4326), 5, 1)"
+    ),
+    spec!(
+        "ST_GeomFromKML",
+        1,
+        Blob,
+        "SELECT ST_GeomFromKML('<Point><coordinates>1,2</coordinates></Point>')"
+    ),
+    spec!(
+        "ST_GeomFromGML",
+        1,
+        Blob,
+        "SELECT ST_GeomFromGML('<gml:Point><gml:pos>1 2</gml:pos></gml:Point>')"
+    ),
+    spec!(
+        "ST_GeomFromGML",
+        2,
+        Blob,
+        "SELECT ST_GeomFromGML('<gml:Point><gml:pos>1 2</gml:pos></gml:Point>', 4326)"
+    ),
+    spec!(
+        "ST_AsSVG",
+        1,
+        Text,
+        "SELECT ST_AsSVG(ST_MakeEnvelope(0, 0, 1, 1))"
+    ),
+    spec!(
+        "ST_AsSVG",
+        2,
+        Text,
+        "SELECT ST_AsSVG(ST_MakeEnvelope(0, 0, 1, 1), 1)"
+    ),
+    spec!(
+        "ST_AsSVG",
+        3,
+        Text,
+        "SELECT ST_AsSVG(ST_MakeEnvelope(0, 0, 1, 1), 1, 2)"
+    ),
+    spec!(
+        "ST_AsEncodedPolyline",
+        1,
+        Text,
+        "SELECT ST_AsEncodedPolyline(ST_GeomFromText('LINESTRING(-120.2 38.5,-120.95 40.7)'))"
+    ),
+    spec!(
+        "ST_AsEncodedPolyline",
+        2,
+        Text,
+        "SELECT ST_AsEncodedPolyline(ST_GeomFromText('LINESTRING(-120.2 38.5,-120.95 40.7)'), 6)"
+    ),
+    spec!(
+        "ST_LineFromEncodedPolyline",
+        1,
+        Blob,
+        "SELECT ST_LineFromEncodedPolyline('_p~iF~ps|U_ulLnnqC')"
+    ),
+    spec!(
+        "ST_LineFromEncodedPolyline",
+        2,
+        Blob,
+        "SELECT ST_LineFromEncodedPolyline('_p~iF~ps|U_ulLnnqC', 5)"
+    ),
+    spec!(
+        "ST_GeoHash",
+        1,
+        Text,
+        "SELECT ST_GeoHash(ST_Point(-126, 48, 4326))"
+    ),
+    spec!(
+        "ST_GeoHash",
+        2,
+        Text,
+        "SELECT ST_GeoHash(ST_Point(-126, 48, 4326), 5)"
+    ),
+    spec!(
+        "ST_AsMVTGeom",
+        2,
+        Blob,
+        "SELECT ST_AsMVTGeom(ST_Point(1, 1), ST_MakeEnvelope(0, 0, 10, 10))"
+    ),
+    spec!(
+        "ST_AsMVTGeom",
+        3,
+        Blob,
+        "SELECT ST_AsMVTGeom(ST_Point(1, 1), ST_MakeEnvelope(0, 0, 10, 10), 256)"
+    ),
+    spec!(
+        "ST_AsMVTGeom",
+        4,
+        Blob,
+        "SELECT ST_AsMVTGeom(ST_Point(1, 1), ST_MakeEnvelope(0, 0, 10, 10), 256, 0)"
+    ),
+    spec!(
+        "ST_AsMVTGeom",
+        5,
+        Blob,
+        "SELECT ST_AsMVTGeom(ST_Point(1, 1), ST_MakeEnvelope(0, 0, 10, 10), 256, 0, 0)"
+    ),
     // Constructors
     spec!("ST_Point", 2, Blob, "SELECT ST_Point(1, 2)"),
     spec!("ST_Point", 3, Blob, "SELECT ST_Point(1, 2, 4326)"),
@@ -305,6 +569,30 @@ pub const SQLITE_DETERMINISTIC_FUNCTIONS: &[SqliteFunctionSpec] = &[
         Blob,
         "SELECT ST_TileEnvelope(1, 0, 0)"
     ),
+    spec!(
+        "ST_GeomFromGeoHash",
+        1,
+        Blob,
+        "SELECT ST_GeomFromGeoHash('c0w3h')"
+    ),
+    spec!(
+        "ST_GeomFromGeoHash",
+        2,
+        Blob,
+        "SELECT ST_GeomFromGeoHash('c0w3hf1s70', 5)"
+    ),
+    spec!(
+        "ST_PointFromGeoHash",
+        1,
+        Blob,
+        "SELECT ST_PointFromGeoHash('c0w3h')"
+    ),
+    spec!(
+        "ST_PointFromGeoHash",
+        2,
+        Blob,
+        "SELECT ST_PointFromGeoHash('c0w3hf1s70', 5)"
+    ),
     // Accessors
     spec!("ST_SRID", 1, Numeric, "SELECT ST_SRID(ST_Point(1, 2, 4326))"),
     spec!(
@@ -517,6 +805,34 @@ pub const SQLITE_DETERMINISTIC_FUNCTIONS: &[SqliteFunctionSpec] = &[
         Numeric,
         "SELECT ST_YMax(ST_GeomFromText('POLYGON((0 0,2 0,2 2,0 2,0 0))'))"
     ),
+    spec_override!(
+        "ST_MinX",
+        1,
+        Numeric,
+        "SELECT ST_MinX(ST_GeomFromText('POLYGON((0 0,2 0,2 2,0 2,0 0))'))",
+        "st_xmin_xfunc"
+    ),
+    spec_override!(
+        "ST_MaxX",
+        1,
+        Numeric,
+        "SELECT ST_MaxX(ST_GeomFromText('POLYGON((0 0,2 0,2 2,0 2,0 0))'))",
+        "st_xmax_xfunc"
+    ),
+    spec_override!(
+        "ST_MinY",
+        1,
+        Numeric,
+        "SELECT ST_MinY(ST_GeomFromText('POLYGON((0 0,2 0,2 2,0 2,0 0))'))",
+        "st_ymin_xfunc"
+    ),
+    spec_override!(
+        "ST_MaxY",
+        1,
+        Numeric,
+        "SELECT ST_MaxY(ST_GeomFromText('POLYGON((0 0,2 0,2 2,0 2,0 0))'))",
+        "st_ymax_xfunc"
+    ),
     spec!(
         "ST_DistanceSphere",
         2,
@@ -683,10 +999,53 @@ pub const SQLITE_DETERMINISTIC_FUNCTIONS: &[SqliteFunctionSpec] = &[
     ),
 ];
 
+/// Catalog of aggregate SQL functions. Registered with the same flags as
+/// [`SQLITE_DETERMINISTIC_FUNCTIONS`], but through `xStep`/`xFinal`
+/// callbacks instead of `xFunc`.
+pub const SQLITE_AGGREGATE_FUNCTIONS: &[SqliteFunctionSpec] = &[
+    spec!(
+        "ST_AsMVT",
+        2,
+        Blob,
+        "SELECT ST_AsMVT(json_object('id', 1, 'geom', hex(ST_Point(1, 2))), 'layer')"
+    ),
+    spec!(
+        "ST_AsMVT",
+        3,
+        Blob,
+        "SELECT ST_AsMVT(json_object('id', 1, 'geom', hex(ST_Point(1, 2))), 'layer', 4096)"
+    ),
+    spec!(
+        "ST_AsMVT",
+        4,
+        Blob,
+        "SELECT ST_AsMVT(json_object('id', 1, 'g', hex(ST_Point(1, 2))), 'layer', 4096, 'g')"
+    ),
+    spec!(
+        "ST_AsGeoJSONFeatureCollection",
+        1,
+        Text,
+        "SELECT ST_AsGeoJSONFeatureCollection(ST_AsGeoJSON(json_object('id', 1), ST_Point(1, 2)))"
+    ),
+    spec!(
+        "ST_AsGeoJSONFeatureCollection",
+        2,
+        Text,
+        "SELECT ST_AsGeoJSONFeatureCollection(json_object('id', 1), ST_Point(1, 2))"
+    ),
+    spec!(
+        "ST_AsGeoJSONFeatureCollection",
+        3,
+        Text,
+        "SELECT ST_AsGeoJSONFeatureCollection(json_object('id', 1), ST_Point(1, 2), 3)"
+    ),
+];
+
 /// Catalog of SQL functions registered with `SQLITE_DIRECT_ONLY`, meaning they
 /// cannot be invoked from triggers, views, generated columns, or CHECK
 /// constraints. Used for mutating helpers like `CreateSpatialIndex` that have
-/// non-deterministic side effects on the schema.
+/// non-deterministic side effects on the schema, and for `sqlitegis_config`,
+/// which reads and tightens the connection's resource limits.
 pub const SQLITE_DIRECT_ONLY_FUNCTIONS: &[SqliteFunctionSpec] = &[
     direct_spec!(
         "CreateSpatialIndex",
@@ -706,4 +1065,160 @@ pub const SQLITE_DIRECT_ONLY_FUNCTIONS: &[SqliteFunctionSpec] = &[
         "table name must not be NULL",
         "drop_spatial_index_xfunc"
     ),
+    direct_spec!(
+        "gpkgAddGeometryColumn",
+        4,
+        Numeric,
+        "SELECT gpkgAddGeometryColumn('_rt', 'geom', 'GEOMETRY', 0)",
+        "SELECT gpkgAddGeometryColumn(NULL, 'geom', 'POINT', 4326)",
+        "table name must not be NULL",
+        "gpkg_add_geometry_column_xfunc"
+    ),
+    direct_spec!(
+        "gpkgAddSpatialIndex",
+        2,
+        Numeric,
+        "SELECT gpkgAddSpatialIndex('_rt', 'geom')",
+        "SELECT gpkgAddSpatialIndex(NULL, 'geom')",
+        "table name must not be NULL",
+        "gpkg_add_spatial_index_xfunc"
+    ),
+    direct_spec!(
+        "ConvertSpatiaLiteColumn",
+        2,
+        Numeric,
+        "SELECT ConvertSpatiaLiteColumn('_rt', 'geom')",
+        "SELECT ConvertSpatiaLiteColumn(NULL, 'geom')",
+        "table name must not be NULL",
+        "convert_spatialite_column_xfunc"
+    ),
+    direct_spec!(
+        "sqlitegis_config",
+        1,
+        Numeric,
+        "SELECT sqlitegis_config('max_vertices')",
+        "SELECT sqlitegis_config(NULL)",
+        "name must not be NULL",
+        "sqlitegis_config_get_xfunc"
+    ),
+    direct_spec!(
+        "sqlitegis_config",
+        2,
+        Numeric,
+        "SELECT sqlitegis_config('max_vertices', 1000000)",
+        "SELECT sqlitegis_config(NULL, 1)",
+        "name must not be NULL",
+        "sqlitegis_config_set_xfunc"
+    ),
+    direct_file_spec!(
+        "ImportFlatGeobuf",
+        2,
+        Numeric,
+        "SELECT ImportFlatGeobuf('sqlitegis-missing-dir/in.fgb', '_rt')",
+        "SELECT ImportFlatGeobuf(NULL, '_rt')",
+        "path must not be NULL",
+        "import_flatgeobuf_xfunc"
+    ),
+    direct_file_spec!(
+        "ImportFlatGeobuf",
+        3,
+        Numeric,
+        "SELECT ImportFlatGeobuf('sqlitegis-missing-dir/in.fgb', '_rt', 1)",
+        "SELECT ImportFlatGeobuf(NULL, '_rt', 1)",
+        "path must not be NULL",
+        "import_flatgeobuf_xfunc"
+    ),
+    direct_file_spec!(
+        "ExportFlatGeobuf",
+        2,
+        Numeric,
+        "SELECT ExportFlatGeobuf('sqlitegis-missing-dir/out.fgb', 'SELECT ST_Point(1, 2) AS geom')",
+        "SELECT ExportFlatGeobuf(NULL, 'SELECT 1')",
+        "path must not be NULL",
+        "export_flatgeobuf_xfunc"
+    ),
+    direct_file_spec!(
+        "ImportSHP",
+        2,
+        Numeric,
+        "SELECT ImportSHP('sqlitegis-missing-dir/in', '_rt')",
+        "SELECT ImportSHP(NULL, '_rt')",
+        "path must not be NULL",
+        "import_shp_xfunc"
+    ),
+    direct_file_spec!(
+        "ImportSHP",
+        4,
+        Numeric,
+        "SELECT ImportSHP('sqlitegis-missing-dir/in', '_rt', 4326, 'UTF-8')",
+        "SELECT ImportSHP(NULL, '_rt', 4326, 'UTF-8')",
+        "path must not be NULL",
+        "import_shp_xfunc"
+    ),
+    direct_file_spec!(
+        "ExportSHP",
+        3,
+        Numeric,
+        "SELECT ExportSHP('_rt', 'geom', 'sqlitegis-missing-dir/out')",
+        "SELECT ExportSHP(NULL, 'geom', 'sqlitegis-missing-dir/out')",
+        "table name must not be NULL",
+        "export_shp_xfunc"
+    ),
+    direct_file_spec!(
+        "ImportOSM",
+        1,
+        Numeric,
+        "SELECT ImportOSM('sqlitegis-missing-dir/in.osm.pbf')",
+        "SELECT ImportOSM(NULL)",
+        "path must not be NULL",
+        "import_osm_xfunc"
+    ),
+    direct_file_spec!(
+        "ImportOSM",
+        2,
+        Numeric,
+        "SELECT ImportOSM('sqlitegis-missing-dir/in.osm.pbf', 1)",
+        "SELECT ImportOSM(NULL, 1)",
+        "path must not be NULL",
+        "import_osm_xfunc"
+    ),
+    direct_file_spec!(
+        "GenerateMBTiles",
+        4,
+        Numeric,
+        "SELECT GenerateMBTiles('sqlitegis-missing-dir/out.mbtiles', 'SELECT ST_Point(1, 2) AS geom', 0, 2)",
+        "SELECT GenerateMBTiles(NULL, 'SELECT 1', 0, 2)",
+        "path must not be NULL",
+        "generate_mbtiles_xfunc"
+    ),
+    #[cfg(feature = "geoparquet")]
+    direct_file_spec!(
+        "ImportGeoParquet",
+        2,
+        Numeric,
+        "SELECT ImportGeoParquet('sqlitegis-missing-dir/in.parquet', '_rt')",
+        "SELECT ImportGeoParquet(NULL, '_rt')",
+        "path must not be NULL",
+        "import_geoparquet_xfunc"
+    ),
+    #[cfg(feature = "geoparquet")]
+    direct_file_spec!(
+        "ImportGeoParquet",
+        3,
+        Numeric,
+        "SELECT ImportGeoParquet('sqlitegis-missing-dir/in.parquet', '_rt', 1)",
+        "SELECT ImportGeoParquet(NULL, '_rt', 1)",
+        "path must not be NULL",
+        "import_geoparquet_xfunc"
+    ),
+    #[cfg(feature = "geoparquet")]
+    direct_file_spec!(
+        "ExportGeoParquet",
+        2,
+        Numeric,
+        "SELECT ExportGeoParquet('sqlitegis-missing-dir/out.parquet', 'SELECT ST_Point(1, 2) AS geom')",
+        "SELECT ExportGeoParquet(NULL, 'SELECT 1')",
+        "path must not be NULL",
+        "export_geoparquet_xfunc"
+    ),
 ];
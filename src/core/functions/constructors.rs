@@ -1,11 +1,12 @@
 //! Geometry constructor functions.
 //!
 //! ST_Point, ST_MakePoint, ST_MakeLine, ST_MakePolygon,
-//! ST_MakeEnvelope, ST_Collect
+//! ST_MakeEnvelope, ST_Collect, ST_TileEnvelope, ST_GeomFromGeoHash,
+//! ST_PointFromGeoHash
 
 use geo::{Coord, Geometry, LineString, Point, Polygon, Rect};
 
-use crate::core::error::{Result, SqliteGisError};
+use crate::core::error::{on_operand, Result, SqliteGisError};
 use crate::core::ewkb::{parse_ewkb, parse_ewkb_pair, write_ewkb};
 
 /// ST_Point / ST_MakePoint (2D): construct a Point geometry.
@@ -49,19 +50,25 @@ pub fn st_make_line(a: &[u8], b: &[u8]) -> Result<Vec<u8>> {
         Geometry::Point(p) => Ok(p),
         other => Err(SqliteGisError::wrong_type("Point", &other)),
     };
-    let pa = extract_point(ga)?;
-    let pb = extract_point(gb)?;
+    let pa = on_operand(0, extract_point(ga))?;
+    let pb = on_operand(1, extract_point(gb))?;
 
-    for p in [&pa, &pb] {
+    for (operand, p) in [&pa, &pb].into_iter().enumerate() {
         if p.x().is_nan() || p.y().is_nan() {
-            return Err(SqliteGisError::InvalidInput(
-                "point must not be empty".to_string(),
-            ));
+            return on_operand(
+                operand,
+                Err(SqliteGisError::InvalidInput(
+                    "point must not be empty".to_string(),
+                )),
+            );
         }
         if !p.x().is_finite() || !p.y().is_finite() {
-            return Err(SqliteGisError::InvalidInput(
-                "point coordinates must be finite".to_string(),
-            ));
+            return on_operand(
+                operand,
+                Err(SqliteGisError::InvalidInput(
+                    "point coordinates must be finite".to_string(),
+                )),
+            );
         }
     }
 
@@ -157,7 +164,35 @@ pub fn st_collect(a: &[u8], b: &[u8]) -> Result<Vec<u8>> {
 }
 
 /// Half the Web Mercator circumference in metres (EPSG:3857).
-const WEB_MERCATOR_HALF_SIZE: f64 = 20037508.3427892;
+pub(crate) const WEB_MERCATOR_HALF_SIZE: f64 = 20037508.3427892;
+
+/// Latitude where Web Mercator's square world ends.
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+pub(crate) const MAX_LATITUDE: f64 = 85.051_128_779_806_59;
+
+/// Longitude and latitude in Web Mercator metres, clamped to
+/// [`MAX_LATITUDE`].
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+pub(crate) fn to_web_mercator(c: Coord) -> Coord {
+    let lat = c.y.clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians();
+    Coord {
+        x: c.x / 180.0 * WEB_MERCATOR_HALF_SIZE,
+        y: (std::f64::consts::FRAC_PI_4 + lat / 2.0).tan().ln() / std::f64::consts::PI
+            * WEB_MERCATOR_HALF_SIZE,
+    }
+}
+
+/// Web Mercator metres in longitude and latitude.
+#[cfg_attr(not(feature = "sqlite"), allow(dead_code))]
+pub(crate) fn from_web_mercator(c: Coord) -> Coord {
+    Coord {
+        x: c.x / WEB_MERCATOR_HALF_SIZE * 180.0,
+        y: (c.y / WEB_MERCATOR_HALF_SIZE * std::f64::consts::PI)
+            .sinh()
+            .atan()
+            .to_degrees(),
+    }
+}
 
 /// ST_TileEnvelope: Web Mercator tile bounding box (EPSG:3857).
 /// Returns a Polygon in EPSG:3857 coordinates.
@@ -193,6 +228,86 @@ pub fn st_tile_envelope(zoom: u32, tile_x: u32, tile_y: u32) -> Result<Vec<u8>>
     st_make_envelope(xmin, ymin, xmax, ymax, Some(3857))
 }
 
+/// GeoHash base-32 alphabet: digits and lowercase letters minus `a`, `i`,
+/// `l` and `o`.
+pub(crate) const GEOHASH_ALPHABET: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// Decode the cell of a GeoHash as `(xmin, ymin, xmax, ymax)` in degrees,
+/// using only its first `precision` characters when given. Letters are
+/// case-insensitive.
+fn decode_geohash(hash: &str, precision: Option<i32>) -> Result<(f64, f64, f64, f64)> {
+    let chars = match precision {
+        Some(p) if p < 0 => {
+            return Err(SqliteGisError::InvalidInput(format!(
+                "GeoHash precision must be non-negative, got {p}"
+            )))
+        }
+        Some(p) => hash.len().min(p as usize),
+        None => hash.len(),
+    };
+    if hash.is_empty() {
+        return Err(SqliteGisError::InvalidInput(
+            "GeoHash must not be empty".to_string(),
+        ));
+    }
+    let (mut lon, mut lat) = ((-180.0, 180.0), (-90.0, 90.0));
+    let mut even = true;
+    for c in hash.bytes().take(chars) {
+        let lower = c.to_ascii_lowercase();
+        let Some(value) = GEOHASH_ALPHABET.iter().position(|&a| a == lower) else {
+            return Err(SqliteGisError::InvalidInput(format!(
+                "invalid GeoHash character '{}'",
+                char::from(c)
+            )));
+        };
+        for bit in (0..5).rev() {
+            let range: &mut (f64, f64) = if even { &mut lon } else { &mut lat };
+            let mid = (range.0 + range.1) / 2.0;
+            if value & (1 << bit) != 0 {
+                range.0 = mid;
+            } else {
+                range.1 = mid;
+            }
+            even = !even;
+        }
+    }
+    Ok((lon.0, lat.0, lon.1, lat.1))
+}
+
+/// ST_GeomFromGeoHash: the cell of a GeoHash as an SRID 4326 Polygon,
+/// using its first `precision` characters when given.
+///
+/// # Example
+///
+/// ```
+/// use sqlitegis::core::functions::constructors::st_geom_from_geohash;
+/// use sqlitegis::core::functions::io::as_text;
+///
+/// let cell = st_geom_from_geohash("s", None).unwrap();
+/// assert_eq!(as_text(&cell).unwrap(), "POLYGON((45 0,45 45,0 45,0 0,45 0))");
+/// ```
+pub fn st_geom_from_geohash(hash: &str, precision: Option<i32>) -> Result<Vec<u8>> {
+    let (xmin, ymin, xmax, ymax) = decode_geohash(hash, precision)?;
+    st_make_envelope(xmin, ymin, xmax, ymax, Some(4326))
+}
+
+/// ST_PointFromGeoHash: the centre of a GeoHash cell as an SRID 4326
+/// Point, using its first `precision` characters when given.
+///
+/// # Example
+///
+/// ```
+/// use sqlitegis::core::functions::constructors::st_point_from_geohash;
+/// use sqlitegis::core::functions::io::as_ewkt;
+///
+/// let centre = st_point_from_geohash("s", None).unwrap();
+/// assert_eq!(as_ewkt(&centre).unwrap(), "SRID=4326;POINT(22.5 22.5)");
+/// ```
+pub fn st_point_from_geohash(hash: &str, precision: Option<i32>) -> Result<Vec<u8>> {
+    let (xmin, ymin, xmax, ymax) = decode_geohash(hash, precision)?;
+    st_point((xmin + xmax) / 2.0, (ymin + ymax) / 2.0, Some(4326))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -363,4 +478,44 @@ mod tests {
         let err = st_make_line(&inf, &other).expect_err("Inf x must be rejected");
         assert!(matches!(err, SqliteGisError::InvalidInput(ref s) if s.contains("finite")));
     }
+
+    #[test]
+    fn geohash_constructors_decode_cells() {
+        let (xmin, ymin, xmax, ymax) = decode_geohash("u4pruydqqvj", None).unwrap();
+        assert!(xmin <= 10.40744 && 10.40744 <= xmax, "{xmin} {xmax}");
+        assert!(ymin <= 57.64911 && 57.64911 <= ymax, "{ymin} {ymax}");
+        assert!(xmax - xmin < 1e-5 && ymax - ymin < 1e-5);
+
+        // Truncation and case-insensitivity.
+        assert_eq!(
+            st_geom_from_geohash("U4PRUYD", Some(2)).unwrap(),
+            st_geom_from_geohash("u4", None).unwrap()
+        );
+        let centre = st_point_from_geohash("u4", Some(10)).unwrap();
+        assert_eq!(extract_srid(&centre), Some(4326));
+        assert_eq!(st_x(&centre).unwrap(), Some(5.625));
+        assert_eq!(st_y(&centre).unwrap(), Some(59.0625));
+        assert_eq!(
+            decode_geohash("u4", Some(0)).unwrap(),
+            (-180.0, -90.0, 180.0, 90.0)
+        );
+
+        for (hash, precision) in [("", None), ("u4a", None), ("u4", Some(-1))] {
+            assert!(
+                matches!(
+                    st_geom_from_geohash(hash, precision),
+                    Err(SqliteGisError::InvalidInput(_))
+                ),
+                "{hash:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn web_mercator_round_trips() {
+        let c = to_web_mercator(Coord { x: 180.0, y: 0.0 });
+        assert!((c.x - WEB_MERCATOR_HALF_SIZE).abs() < 1e-6 && c.y.abs() < 1e-6);
+        let back = from_web_mercator(to_web_mercator(Coord { x: 13.4, y: 52.5 }));
+        assert!((back.x - 13.4).abs() < 1e-9 && (back.y - 52.5).abs() < 1e-9);
+    }
 }
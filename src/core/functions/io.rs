@@ -1,38 +1,37 @@
 //! I/O and serialization functions.
 //!
-//! ST_AsText, ST_AsEWKT, ST_AsBinary, ST_AsEWKB, ST_AsGeoJSON,
-//! ST_GeomFromText, ST_GeomFromWKB, ST_GeomFromEWKB, ST_GeomFromGeoJSON
+//! ST_AsText, ST_AsEWKT, ST_AsBinary, ST_AsEWKB, ST_AsHEXEWKB, ST_AsGeoJSON
+//! (geometry or Feature), ST_AsGeoJSONFeatureCollection,
+//! ST_AsTWKB, ST_AsKML, ST_AsGML, ST_AsSVG, ST_AsEncodedPolyline, ST_GeoHash,
+//! ST_GeomFromText, ST_GeomFromWKB, ST_GeomFromEWKB, ST_GeomFromHEXEWKB,
+//! ST_GeomFromGeoJSON, ST_GeomFromTWKB, ST_GeomFromKML, ST_GeomFromGML,
+//! ST_LineFromEncodedPolyline
 
-use geo::{Geometry, Point};
+use geo::{BoundingRect, Geometry, MapCoords, Point};
 use geozero::wkb::Ewkb;
 use geozero::{CoordDimensions, ToGeo, ToJson, ToWkb, ToWkt};
 use serde_json::Value;
 
 use crate::core::error::{Result, SqliteGisError};
 use crate::core::ewkb::{
-    ensure_xy_only, extract_srid, is_empty_point_blob, parse_ewkb, parse_ewkb_header,
-    validate_xy_ewkb_payload, write_ewkb, EWKB_M_FLAG, EWKB_Z_FLAG, WKB_POINT,
+    decode_hex, ensure_xy_only, extract_srid, is_empty_point_blob, parse_ewkb, parse_ewkb_header,
+    validate_xy_ewkb_payload, with_byte_order, wkt_to_ewkb, write_ewkb, EWKB_M_FLAG, EWKB_Z_FLAG,
+    WKB_POINT,
 };
+use crate::core::functions::constructors::GEOHASH_ALPHABET;
+use crate::core::functions::emptiness::{is_empty_geometry, is_empty_point};
+use crate::core::limits::{check_input_bytes, check_input_vertices};
 
-const EMPTY_POINT_GEOJSON: &str = r#"{"type":"Point","coordinates":[]}"#;
-
-fn is_empty_point_wkt(wkt: &str) -> bool {
-    let mut parts = wkt.split_whitespace();
-    matches!(
-        (parts.next(), parts.next(), parts.next()),
-        (Some(a), Some(b), None)
-            if a.eq_ignore_ascii_case("POINT") && b.eq_ignore_ascii_case("EMPTY")
-    )
-}
+mod geojson;
+mod markup;
+pub use geojson::{as_geojson_feature, GeoJsonFeatureCollection};
+use markup::check_decimal_digits;
+pub use markup::{
+    as_gml, as_kml, as_svg, geom_from_gml, geom_from_kml, GML_ENVELOPE, GML_LAT_LON,
+    GML_LINESTRING, GML_LONG_CRS, GML_NO_SRS_DIMENSION, MAX_DECIMAL_DIGITS,
+};
 
-fn is_geometrycollection_single_empty_point_wkt(wkt: &str) -> bool {
-    let compact_upper = wkt
-        .chars()
-        .filter(|c| !c.is_ascii_whitespace())
-        .map(|c| c.to_ascii_uppercase())
-        .collect::<String>();
-    compact_upper == "GEOMETRYCOLLECTION(POINTEMPTY)"
-}
+const EMPTY_POINT_GEOJSON: &str = r#"{"type":"Point","coordinates":[]}"#;
 
 fn is_empty_point_geojson(json: &str) -> bool {
     let Ok(value) = serde_json::from_str::<Value>(json) else {
@@ -103,15 +102,7 @@ fn wkb_has_z_or_m(raw_type: u32) -> (bool, bool) {
 /// assert!(!blob.is_empty());
 /// ```
 pub fn geom_from_text(wkt: &str, srid: Option<i32>) -> Result<Vec<u8>> {
-    if is_empty_point_wkt(wkt) {
-        return write_ewkb(&Geometry::Point(Point::new(f64::NAN, f64::NAN)), srid);
-    }
-    if is_geometrycollection_single_empty_point_wkt(wkt) {
-        let gc = geo::GeometryCollection::new_from(vec![]);
-        return write_ewkb(&Geometry::GeometryCollection(gc), srid);
-    }
-    let geom: Geometry<f64> = geozero::wkt::Wkt(wkt.as_bytes()).to_geo()?;
-    write_ewkb(&geom, srid)
+    wkt_to_ewkb(wkt, srid)
 }
 
 /// Parse ISO WKB bytes (optionally override SRID) into an EWKB blob.
@@ -128,6 +119,7 @@ pub fn geom_from_text(wkt: &str, srid: Option<i32>) -> Result<Vec<u8>> {
 /// assert_eq!(extract_srid(&restored), Some(4326));
 /// ```
 pub fn geom_from_wkb(wkb: &[u8], srid: Option<i32>) -> Result<Vec<u8>> {
+    check_input_bytes(wkb.len())?;
     let raw_type = read_raw_wkb_type(wkb)?;
     let (has_z, has_m) = wkb_has_z_or_m(raw_type);
     ensure_xy_only(has_z, has_m)?;
@@ -135,6 +127,7 @@ pub fn geom_from_wkb(wkb: &[u8], srid: Option<i32>) -> Result<Vec<u8>> {
         return write_ewkb(&Geometry::Point(Point::new(f64::NAN, f64::NAN)), srid);
     }
     let geom: Geometry<f64> = Ewkb(wkb).to_geo()?;
+    check_input_vertices(&geom)?;
     write_ewkb(&geom, srid)
 }
 
@@ -154,7 +147,9 @@ pub fn geom_from_ewkb(ewkb: &[u8]) -> Result<Vec<u8>> {
     Ok(ewkb.to_vec())
 }
 
-/// Parse a GeoJSON string into an EWKB blob (SRID = 4326 by default, per spec).
+/// Parse a GeoJSON geometry, or the geometry of a GeoJSON Feature, into an
+/// EWKB blob (SRID = 4326 by default, per spec). A Feature without a
+/// geometry is an error.
 ///
 /// # Example
 ///
@@ -164,11 +159,17 @@ pub fn geom_from_ewkb(ewkb: &[u8]) -> Result<Vec<u8>> {
 ///
 /// let blob = geom_from_geojson(r#"{"type":"Point","coordinates":[1,2]}"#, None).unwrap();
 /// assert_eq!(extract_srid(&blob), Some(4326));
+/// let feature = r#"{"type":"Feature","geometry":{"type":"Point","coordinates":[1,2]},"properties":{}}"#;
+/// assert_eq!(geom_from_geojson(feature, None).unwrap(), blob);
 /// ```
 pub fn geom_from_geojson(json: &str, srid: Option<i32>) -> Result<Vec<u8>> {
+    check_input_bytes(json.len())?;
     let effective_srid = srid.or(Some(4326));
     match geozero::geojson::GeoJson(json).to_geo() {
-        Ok(geom) => write_ewkb(&geom, effective_srid),
+        Ok(geom) => {
+            check_input_vertices(&geom)?;
+            write_ewkb(&geom, effective_srid)
+        }
         Err(_) if is_empty_point_geojson(json) => write_ewkb(
             &Geometry::Point(Point::new(f64::NAN, f64::NAN)),
             effective_srid,
@@ -191,6 +192,7 @@ pub fn geom_from_geojson(json: &str, srid: Option<i32>) -> Result<Vec<u8>> {
 /// assert!(wkt.contains("POINT"));
 /// ```
 pub fn as_text(blob: &[u8]) -> Result<String> {
+    check_input_bytes(blob.len())?;
     if is_empty_point_blob(blob)? {
         return Ok("POINT EMPTY".to_string());
     }
@@ -209,6 +211,7 @@ pub fn as_text(blob: &[u8]) -> Result<String> {
 /// assert!(ewkt.starts_with("SRID=4326;"));
 /// ```
 pub fn as_ewkt(blob: &[u8]) -> Result<String> {
+    check_input_bytes(blob.len())?;
     let srid = extract_srid(blob);
     if is_empty_point_blob(blob)? {
         if let Some(s) = srid {
@@ -238,6 +241,7 @@ pub fn as_ewkt(blob: &[u8]) -> Result<String> {
 /// assert_eq!(extract_srid(&wkb), None);
 /// ```
 pub fn as_binary(blob: &[u8]) -> Result<Vec<u8>> {
+    check_input_bytes(blob.len())?;
     let header = parse_ewkb_header(blob)?;
     ensure_xy_only(header.has_z, header.has_m)?;
     if is_empty_point_blob(blob)? {
@@ -275,6 +279,55 @@ pub fn as_ewkb(blob: &[u8]) -> Result<Vec<u8>> {
     geom_from_ewkb(blob)
 }
 
+/// Encode an EWKB blob as uppercase hex text in `NDR` (little-endian) or
+/// `XDR` (big-endian) byte order, as PostGIS prints geometries.
+///
+/// # Example
+///
+/// ```
+/// use sqlitegis::core::functions::io::{as_hexewkb, geom_from_text};
+///
+/// let blob = geom_from_text("POINT(1 2)", Some(4326)).unwrap();
+/// assert_eq!(
+///     as_hexewkb(&blob, "NDR").unwrap(),
+///     "0101000020E6100000000000000000F03F0000000000000040"
+/// );
+/// assert!(as_hexewkb(&blob, "xdr").unwrap().starts_with("0020000001000010E6"));
+/// ```
+pub fn as_hexewkb(blob: &[u8], endian: &str) -> Result<String> {
+    let little_endian = if endian.eq_ignore_ascii_case("NDR") {
+        true
+    } else if endian.eq_ignore_ascii_case("XDR") {
+        false
+    } else {
+        return Err(SqliteGisError::InvalidInput(format!(
+            "endian must be 'NDR' or 'XDR', got '{endian}'"
+        )));
+    };
+    let bytes = with_byte_order(blob, little_endian)?;
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        out.push_str(&format!("{b:02X}"));
+    }
+    Ok(out)
+}
+
+/// Decode hex EWKB text, such as a psql dump or `ST_AsHEXEWKB` output,
+/// into an EWKB blob. Surrounding whitespace is ignored.
+///
+/// # Example
+///
+/// ```
+/// use sqlitegis::core::functions::io::{as_ewkt, geom_from_hexewkb};
+///
+/// let blob = geom_from_hexewkb("0101000020E6100000000000000000F03F0000000000000040").unwrap();
+/// assert_eq!(as_ewkt(&blob).unwrap(), "SRID=4326;POINT(1 2)");
+/// ```
+pub fn geom_from_hexewkb(hex: &str) -> Result<Vec<u8>> {
+    check_input_bytes(hex.len())?;
+    geom_from_ewkb(&decode_hex(hex.trim().as_bytes())?)
+}
+
 /// Convert an EWKB blob to GeoJSON text.
 ///
 /// # Example
@@ -288,12 +341,770 @@ pub fn as_ewkb(blob: &[u8]) -> Result<Vec<u8>> {
 /// assert!(json.contains("coordinates"));
 /// ```
 pub fn as_geojson(blob: &[u8]) -> Result<String> {
+    check_input_bytes(blob.len())?;
     if is_empty_point_blob(blob)? {
         return Ok(EMPTY_POINT_GEOJSON.to_string());
     }
     Ok(Ewkb(blob).to_json()?)
 }
 
+/// Convert an EWKB blob to GeoJSON text with coordinates rounded to at
+/// most `maxdecimaldigits` decimals (`0..=15`).
+///
+/// # Example
+///
+/// ```
+/// use sqlitegis::core::functions::io::{as_geojson_precision, geom_from_text};
+///
+/// let blob = geom_from_text("POINT(1.23456 -0.0001)", None).unwrap();
+/// assert_eq!(
+///     as_geojson_precision(&blob, 2).unwrap(),
+///     r#"{"type": "Point", "coordinates": [1.23,0]}"#
+/// );
+/// ```
+pub fn as_geojson_precision(blob: &[u8], maxdecimaldigits: i32) -> Result<String> {
+    let digits = check_decimal_digits(maxdecimaldigits)?;
+    check_input_bytes(blob.len())?;
+    if is_empty_point_blob(blob)? {
+        return Ok(EMPTY_POINT_GEOJSON.to_string());
+    }
+    let (geom, _srid) = parse_ewkb(blob)?;
+    let scale = 10f64.powi(digits as i32);
+    // `+ 0.0` turns a rounded -0 into 0.
+    let rounded = geom.map_coords(|c| geo::Coord {
+        x: (c.x * scale).round() / scale + 0.0,
+        y: (c.y * scale).round() / scale + 0.0,
+    });
+    Ok(rounded.to_json()?)
+}
+
+// TWKB
+
+const TWKB_BBOX: u8 = 0x01;
+const TWKB_SIZE: u8 = 0x02;
+const TWKB_IDLIST: u8 = 0x04;
+const TWKB_EXTENDED_DIMS: u8 = 0x08;
+const TWKB_EMPTY: u8 = 0x10;
+
+fn zigzag(v: i64) -> u64 {
+    ((v << 1) ^ (v >> 63)) as u64
+}
+
+fn unzigzag(v: u64) -> i64 {
+    ((v >> 1) as i64) ^ -((v & 1) as i64)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        out.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+/// Per-geometry encoder state: the scale factor, the last written
+/// coordinate (TWKB stores deltas from it) and the bounding box of the
+/// rounded coordinates.
+struct TwkbWriter {
+    factor: f64,
+    last: [i64; 2],
+    bbox: Option<[i64; 4]>,
+}
+
+impl TwkbWriter {
+    /// Write the vertices of one part, dropping a vertex that rounds onto
+    /// its predecessor once the part has `min_points` vertices (as PostGIS
+    /// does), and prefixing the vertex count unless `count` is false.
+    fn points(
+        &mut self,
+        coords: &[geo::Coord<f64>],
+        min_points: usize,
+        count: bool,
+        out: &mut Vec<u8>,
+    ) -> Result<()> {
+        let mut body = Vec::with_capacity(coords.len() * 4);
+        let mut written = 0u64;
+        for (i, c) in coords.iter().enumerate() {
+            let x = twkb_scale(c.x, self.factor)?;
+            let y = twkb_scale(c.y, self.factor)?;
+            let (dx, dy) = (twkb_delta(x, self.last[0])?, twkb_delta(y, self.last[1])?);
+            if i > min_points && dx == 0 && dy == 0 {
+                continue;
+            }
+            write_varint(&mut body, zigzag(dx));
+            write_varint(&mut body, zigzag(dy));
+            self.last = [x, y];
+            self.bbox = Some(match self.bbox {
+                Some([x0, y0, x1, y1]) => [x0.min(x), y0.min(y), x1.max(x), y1.max(y)],
+                None => [x, y, x, y],
+            });
+            written += 1;
+        }
+        if count {
+            write_varint(out, written);
+        }
+        out.extend_from_slice(&body);
+        Ok(())
+    }
+
+    fn polygon(&mut self, polygon: &geo::Polygon<f64>, out: &mut Vec<u8>) -> Result<()> {
+        if polygon.exterior().0.is_empty() {
+            write_varint(out, 0);
+            return Ok(());
+        }
+        write_varint(out, 1 + polygon.interiors().len() as u64);
+        self.points(&polygon.exterior().0, 4, true, out)?;
+        for ring in polygon.interiors() {
+            self.points(&ring.0, 4, true, out)?;
+        }
+        Ok(())
+    }
+}
+
+/// Scale and round one coordinate to TWKB integer units, refusing values
+/// that are not finite or do not fit an `i64` instead of saturating.
+fn twkb_scale(v: f64, factor: f64) -> Result<i64> {
+    // 2^63: the first value past i64::MAX; i64::MIN is exactly -2^63.
+    const LIMIT: f64 = 9_223_372_036_854_775_808.0;
+    let scaled = (v * factor).round();
+    if scaled.is_finite() && (-LIMIT..LIMIT).contains(&scaled) {
+        Ok(scaled as i64)
+    } else {
+        Err(SqliteGisError::InvalidInput(format!(
+            "coordinate {v} is out of range for TWKB at this precision"
+        )))
+    }
+}
+
+/// Difference between two rounded TWKB coordinates.
+fn twkb_delta(next: i64, last: i64) -> Result<i64> {
+    next.checked_sub(last).ok_or_else(|| {
+        SqliteGisError::InvalidInput(format!(
+            "coordinate step from {last} to {next} is too large for TWKB"
+        ))
+    })
+}
+
+fn twkb_type(geom: &Geometry<f64>) -> Result<u8> {
+    Ok(match geom {
+        Geometry::Point(_) => 1,
+        Geometry::LineString(_) => 2,
+        Geometry::Polygon(_) => 3,
+        Geometry::MultiPoint(_) => 4,
+        Geometry::MultiLineString(_) => 5,
+        Geometry::MultiPolygon(_) => 6,
+        Geometry::GeometryCollection(_) => 7,
+        other => {
+            return Err(SqliteGisError::WrongType {
+                expected: "TWKB-encodable geometry",
+                actual: crate::core::ewkb::geometry_type_name(other),
+            })
+        }
+    })
+}
+
+fn write_twkb(
+    geom: &Geometry<f64>,
+    precision: i32,
+    with_sizes: bool,
+    with_boxes: bool,
+    out: &mut Vec<u8>,
+) -> Result<()> {
+    let kind = twkb_type(geom)?;
+    out.push(kind | ((zigzag(i64::from(precision)) as u8) << 4));
+    if is_empty_geometry(geom) {
+        out.push(TWKB_EMPTY);
+        return Ok(());
+    }
+
+    let mut writer = TwkbWriter {
+        factor: 10f64.powi(precision),
+        last: [0, 0],
+        bbox: None,
+    };
+    let mut body = Vec::new();
+    match geom {
+        Geometry::Point(p) => writer.points(&[p.0], 1, false, &mut body)?,
+        Geometry::LineString(ls) => writer.points(&ls.0, 2, true, &mut body)?,
+        Geometry::Polygon(polygon) => writer.polygon(polygon, &mut body)?,
+        Geometry::MultiPoint(mp) => {
+            let points: Vec<_> =
+                mp.0.iter()
+                    .filter(|p| !is_empty_point(p))
+                    .map(|p| p.0)
+                    .collect();
+            write_varint(&mut body, points.len() as u64);
+            for p in points {
+                writer.points(&[p], 1, false, &mut body)?;
+            }
+        }
+        Geometry::MultiLineString(mls) => {
+            write_varint(&mut body, mls.0.len() as u64);
+            for ls in &mls.0 {
+                writer.points(&ls.0, 2, true, &mut body)?;
+            }
+        }
+        Geometry::MultiPolygon(mp) => {
+            write_varint(&mut body, mp.0.len() as u64);
+            for polygon in &mp.0 {
+                writer.polygon(polygon, &mut body)?;
+            }
+        }
+        Geometry::GeometryCollection(gc) => {
+            write_varint(&mut body, gc.0.len() as u64);
+            for child in &gc.0 {
+                write_twkb(child, precision, with_sizes, with_boxes, &mut body)?;
+                if let Some(child_bbox) = child_twkb_bbox(child, writer.factor)? {
+                    writer.bbox = Some(match writer.bbox {
+                        Some(b) => [
+                            b[0].min(child_bbox[0]),
+                            b[1].min(child_bbox[1]),
+                            b[2].max(child_bbox[2]),
+                            b[3].max(child_bbox[3]),
+                        ],
+                        None => child_bbox,
+                    });
+                }
+            }
+        }
+        _ => unreachable!("rejected by twkb_type"),
+    }
+
+    let mut metadata = 0u8;
+    let mut bbox = Vec::new();
+    if with_boxes {
+        if let Some([x0, y0, x1, y1]) = writer.bbox {
+            metadata |= TWKB_BBOX;
+            for (min, max) in [(x0, x1), (y0, y1)] {
+                write_varint(&mut bbox, zigzag(min));
+                write_varint(&mut bbox, zigzag(twkb_delta(max, min)?));
+            }
+        }
+    }
+    if with_sizes {
+        metadata |= TWKB_SIZE;
+    }
+    out.push(metadata);
+    if with_sizes {
+        write_varint(out, (bbox.len() + body.len()) as u64);
+    }
+    out.extend_from_slice(&bbox);
+    out.extend_from_slice(&body);
+    Ok(())
+}
+
+/// Bounding box of a collection member in rounded TWKB units.
+fn child_twkb_bbox(child: &Geometry<f64>, factor: f64) -> Result<Option<[i64; 4]>> {
+    let Some(rect) = child.bounding_rect() else {
+        return Ok(None);
+    };
+    Ok(Some([
+        twkb_scale(rect.min().x, factor)?,
+        twkb_scale(rect.min().y, factor)?,
+        twkb_scale(rect.max().x, factor)?,
+        twkb_scale(rect.max().y, factor)?,
+    ]))
+}
+
+/// Encode an EWKB blob as TWKB (Tiny WKB), as PostGIS `ST_AsTWKB` does.
+///
+/// Coordinates are rounded to `precision_xy` decimal digits (`-7..=7`;
+/// negative values round to tens, hundreds, ...). The encoder is 2D only:
+/// `precision_z` and `precision_m` are accepted for PostGIS parity but must
+/// be `0`, and Z or M input fails with
+/// [`SqliteGisError::UnsupportedDimensions`] instead of losing its extra
+/// coordinates. `with_sizes` and `with_boxes` add the optional size and
+/// bounding-box fields. TWKB carries no SRID. A coordinate, or a step
+/// between two coordinates, that does not fit a 64-bit integer once scaled
+/// fails with [`SqliteGisError::InvalidInput`].
+///
+/// # Example
+///
+/// ```
+/// use sqlitegis::core::functions::io::{as_twkb, geom_from_text, geom_from_twkb, as_text};
+///
+/// let blob = geom_from_text("LINESTRING(0 0,1.234 5.678)", Some(4326)).unwrap();
+/// let twkb = as_twkb(&blob, 1, 0, 0, false, false).unwrap();
+/// assert_eq!(twkb.len(), 7);
+/// assert_eq!(as_text(&geom_from_twkb(&twkb).unwrap()).unwrap(), "LINESTRING(0 0,1.2 5.7)");
+/// ```
+pub fn as_twkb(
+    blob: &[u8],
+    precision_xy: i32,
+    precision_z: i32,
+    precision_m: i32,
+    with_sizes: bool,
+    with_boxes: bool,
+) -> Result<Vec<u8>> {
+    if !(-7..=7).contains(&precision_xy) {
+        return Err(SqliteGisError::InvalidInput(format!(
+            "TWKB precision_xy must be between -7 and 7, got {precision_xy}"
+        )));
+    }
+    for (name, value) in [("precision_z", precision_z), ("precision_m", precision_m)] {
+        if !(0..=7).contains(&value) {
+            return Err(SqliteGisError::InvalidInput(format!(
+                "TWKB {name} must be between 0 and 7, got {value}"
+            )));
+        }
+    }
+    ensure_xy_only(precision_z != 0, precision_m != 0)?;
+    let header = parse_ewkb_header(blob)?;
+    ensure_xy_only(header.has_z, header.has_m)?;
+    let (geom, _srid) = parse_ewkb(blob)?;
+    let mut out = Vec::new();
+    write_twkb(&geom, precision_xy, with_sizes, with_boxes, &mut out)?;
+    Ok(out)
+}
+
+/// Cursor over a TWKB buffer.
+struct TwkbReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl TwkbReader<'_> {
+    fn truncated(&self) -> SqliteGisError {
+        SqliteGisError::InvalidInput(format!("TWKB truncated at offset {}", self.offset))
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        let b = *self
+            .bytes
+            .get(self.offset)
+            .ok_or_else(|| self.truncated())?;
+        self.offset += 1;
+        Ok(b)
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            value |= u64::from(b & 0x7F) << shift;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(SqliteGisError::InvalidInput(format!(
+            "TWKB varint too long at offset {}",
+            self.offset
+        )))
+    }
+
+    fn count(&mut self) -> Result<usize> {
+        let n = self.varint()?;
+        // Every element takes at least one byte, which bounds allocations.
+        if n > (self.bytes.len() - self.offset) as u64 {
+            return Err(self.truncated());
+        }
+        Ok(n as usize)
+    }
+
+    fn geometry(&mut self) -> Result<Geometry<f64>> {
+        let header = self.byte()?;
+        let kind = header & 0x0F;
+        let precision = unzigzag(u64::from(header >> 4));
+        let metadata = self.byte()?;
+        if metadata & TWKB_EXTENDED_DIMS != 0 {
+            let dims = self.byte()?;
+            ensure_xy_only(dims & 0x01 != 0, dims & 0x02 != 0)?;
+        }
+        if metadata & TWKB_SIZE != 0 {
+            let size = self.varint()?;
+            if size > (self.bytes.len() - self.offset) as u64 {
+                return Err(self.truncated());
+            }
+        }
+        if metadata & TWKB_BBOX != 0 {
+            for _ in 0..4 {
+                self.varint()?;
+            }
+        }
+        let empty = metadata & TWKB_EMPTY != 0;
+        let divisor = 10f64.powi(precision as i32);
+        let mut last = [0i64; 2];
+        let mut coords = |r: &mut Self, n: usize| -> Result<Vec<geo::Coord<f64>>> {
+            let mut out = Vec::with_capacity(n);
+            for _ in 0..n {
+                for v in &mut last {
+                    *v = v.checked_add(unzigzag(r.varint()?)).ok_or_else(|| {
+                        SqliteGisError::InvalidInput(format!(
+                            "TWKB coordinate overflows at offset {}",
+                            r.offset
+                        ))
+                    })?;
+                }
+                out.push(geo::Coord {
+                    x: last[0] as f64 / divisor,
+                    y: last[1] as f64 / divisor,
+                });
+            }
+            Ok(out)
+        };
+        let ids = |r: &mut Self, n: usize| -> Result<()> {
+            if metadata & TWKB_IDLIST != 0 {
+                for _ in 0..n {
+                    r.varint()?;
+                }
+            }
+            Ok(())
+        };
+
+        Ok(match (kind, empty) {
+            (1, true) => Geometry::Point(Point::new(f64::NAN, f64::NAN)),
+            (1, false) => Geometry::Point(Point(coords(self, 1)?[0])),
+            (2, true) => Geometry::LineString(geo::LineString::new(vec![])),
+            (2, false) => {
+                let n = self.count()?;
+                Geometry::LineString(geo::LineString::new(coords(self, n)?))
+            }
+            (3, true) => Geometry::Polygon(geo::Polygon::new(geo::LineString::new(vec![]), vec![])),
+            (3, false) => {
+                let rings = self.count()?;
+                let mut parsed = Vec::with_capacity(rings);
+                for _ in 0..rings {
+                    let n = self.count()?;
+                    parsed.push(geo::LineString::new(coords(self, n)?));
+                }
+                Geometry::Polygon(polygon_from_rings(parsed))
+            }
+            (4, _) => {
+                let n = if empty { 0 } else { self.count()? };
+                ids(self, n)?;
+                let points = coords(self, n)?.into_iter().map(Point).collect();
+                Geometry::MultiPoint(geo::MultiPoint::new(points))
+            }
+            (5, _) => {
+                let n = if empty { 0 } else { self.count()? };
+                ids(self, n)?;
+                let mut lines = Vec::with_capacity(n);
+                for _ in 0..n {
+                    let points = self.count()?;
+                    lines.push(geo::LineString::new(coords(self, points)?));
+                }
+                Geometry::MultiLineString(geo::MultiLineString::new(lines))
+            }
+            (6, _) => {
+                let n = if empty { 0 } else { self.count()? };
+                ids(self, n)?;
+                let mut polygons = Vec::with_capacity(n);
+                for _ in 0..n {
+                    let rings = self.count()?;
+                    let mut parsed = Vec::with_capacity(rings);
+                    for _ in 0..rings {
+                        let points = self.count()?;
+                        parsed.push(geo::LineString::new(coords(self, points)?));
+                    }
+                    polygons.push(polygon_from_rings(parsed));
+                }
+                Geometry::MultiPolygon(geo::MultiPolygon::new(polygons))
+            }
+            (7, _) => {
+                let n = if empty { 0 } else { self.count()? };
+                ids(self, n)?;
+                let mut children = Vec::with_capacity(n);
+                for _ in 0..n {
+                    children.push(self.geometry()?);
+                }
+                Geometry::GeometryCollection(geo::GeometryCollection::new_from(children))
+            }
+            (other, _) => {
+                return Err(SqliteGisError::InvalidInput(format!(
+                    "invalid TWKB geometry type {other}"
+                )))
+            }
+        })
+    }
+}
+
+fn polygon_from_rings(mut rings: Vec<geo::LineString<f64>>) -> geo::Polygon<f64> {
+    if rings.is_empty() {
+        return geo::Polygon::new(geo::LineString::new(vec![]), vec![]);
+    }
+    let exterior = rings.remove(0);
+    geo::Polygon::new(exterior, rings)
+}
+
+/// Decode TWKB into an EWKB blob without SRID, as PostGIS
+/// `ST_GeomFromTWKB` does.
+///
+/// # Example
+///
+/// ```
+/// use sqlitegis::core::functions::io::{as_text, geom_from_twkb};
+///
+/// // POINT(1 2) at precision 0.
+/// let blob = geom_from_twkb(&[0x01, 0x00, 0x02, 0x04]).unwrap();
+/// assert_eq!(as_text(&blob).unwrap(), "POINT(1 2)");
+/// ```
+pub fn geom_from_twkb(twkb: &[u8]) -> Result<Vec<u8>> {
+    check_input_bytes(twkb.len())?;
+    let mut reader = TwkbReader {
+        bytes: twkb,
+        offset: 0,
+    };
+    let geom = reader.geometry()?;
+    if reader.offset != twkb.len() {
+        return Err(SqliteGisError::InvalidInput(format!(
+            "{} trailing bytes after TWKB geometry",
+            twkb.len() - reader.offset
+        )));
+    }
+    check_input_vertices(&geom)?;
+    write_ewkb(&geom, None)
+}
+
+// Encoded polyline
+
+/// Default `ST_AsEncodedPolyline` precision: 5 decimal digits, as Google
+/// Maps uses.
+pub const DEFAULT_POLYLINE_PRECISION: i32 = 5;
+
+fn check_polyline_precision(precision: i32) -> Result<f64> {
+    if !(0..=MAX_DECIMAL_DIGITS).contains(&precision) {
+        return Err(SqliteGisError::InvalidInput(format!(
+            "polyline precision must be between 0 and {MAX_DECIMAL_DIGITS}, got {precision}"
+        )));
+    }
+    Ok(10f64.powi(precision))
+}
+
+/// Difference between two rounded polyline coordinates, kept within the
+/// 63 bits the zigzag encoding can carry.
+fn polyline_delta(next: i64, last: i64) -> Result<i64> {
+    next.checked_sub(last)
+        .filter(|delta| delta.checked_mul(2).is_some())
+        .ok_or_else(|| {
+            SqliteGisError::InvalidInput(format!(
+                "coordinate step from {last} to {next} is too large for an encoded polyline"
+            ))
+        })
+}
+
+fn push_polyline_value(out: &mut String, delta: i64) {
+    let mut v = if delta < 0 { !(delta << 1) } else { delta << 1 } as u64;
+    while v >= 0x20 {
+        out.push(char::from((0x20 | (v & 0x1f) as u8) + 63));
+        v >>= 5;
+    }
+    out.push(char::from(v as u8 + 63));
+}
+
+/// Encode a LineString (or MultiPoint) as a Google encoded polyline, as
+/// PostGIS `ST_AsEncodedPolyline` does: latitude (y) before longitude (x),
+/// rounded to `precision` decimal digits (`0..=15`, usually
+/// [`DEFAULT_POLYLINE_PRECISION`]). Empty geometries give an empty string.
+///
+/// # Example
+///
+/// ```
+/// use sqlitegis::core::functions::io::{as_encoded_polyline, geom_from_text};
+///
+/// let line = geom_from_text("LINESTRING(-120.2 38.5,-120.95 40.7,-126.453 43.252)", Some(4326)).unwrap();
+/// assert_eq!(as_encoded_polyline(&line, 5).unwrap(), "_p~iF~ps|U_ulLnnqC_mqNvxq`@");
+/// ```
+pub fn as_encoded_polyline(blob: &[u8], precision: i32) -> Result<String> {
+    let factor = check_polyline_precision(precision)?;
+    let (geom, _srid) = parse_ewkb(blob)?;
+    let coords: Vec<geo::Coord<f64>> = match &geom {
+        Geometry::LineString(ls) => ls.0.clone(),
+        Geometry::MultiPoint(mp) => {
+            mp.0.iter()
+                .filter(|p| !is_empty_point(p))
+                .map(|p| p.0)
+                .collect()
+        }
+        other => {
+            return Err(SqliteGisError::WrongType {
+                expected: "LineString or MultiPoint",
+                actual: crate::core::ewkb::geometry_type_name(other),
+            })
+        }
+    };
+    let mut out = String::with_capacity(coords.len() * 8);
+    let mut last = (0i64, 0i64);
+    for c in coords {
+        let next = ((c.y * factor).round() as i64, (c.x * factor).round() as i64);
+        push_polyline_value(&mut out, polyline_delta(next.0, last.0)?);
+        push_polyline_value(&mut out, polyline_delta(next.1, last.1)?);
+        last = next;
+    }
+    Ok(out)
+}
+
+/// Decode a Google encoded polyline into an SRID 4326 LineString, as
+/// PostGIS `ST_LineFromEncodedPolyline` does. `precision` must match the
+/// one used to encode it.
+///
+/// # Example
+///
+/// ```
+/// use sqlitegis::core::functions::io::{as_ewkt, line_from_encoded_polyline};
+///
+/// let line = line_from_encoded_polyline("_p~iF~ps|U_ulLnnqC", 5).unwrap();
+/// assert_eq!(as_ewkt(&line).unwrap(), "SRID=4326;LINESTRING(-120.2 38.5,-120.95 40.7)");
+/// ```
+pub fn line_from_encoded_polyline(polyline: &str, precision: i32) -> Result<Vec<u8>> {
+    let factor = check_polyline_precision(precision)?;
+    check_input_bytes(polyline.len())?;
+    let mut values = Vec::new();
+    let (mut value, mut shift) = (0u64, 0u32);
+    for (i, c) in polyline.bytes().enumerate() {
+        let chunk = match c.checked_sub(63) {
+            Some(chunk) if chunk < 64 => u64::from(chunk),
+            _ => {
+                return Err(SqliteGisError::InvalidInput(format!(
+                    "invalid encoded polyline character '{}' at offset {i}",
+                    char::from(c)
+                )))
+            }
+        };
+        if shift > 60 {
+            return Err(SqliteGisError::InvalidInput(format!(
+                "encoded polyline value too long at offset {i}"
+            )));
+        }
+        value |= (chunk & 0x1f) << shift;
+        shift += 5;
+        if chunk < 0x20 {
+            let delta = (value >> 1) as i64;
+            values.push(if value & 1 != 0 { !delta } else { delta });
+            (value, shift) = (0, 0);
+        }
+    }
+    if shift != 0 || values.len() % 2 != 0 {
+        return Err(SqliteGisError::InvalidInput(
+            "truncated encoded polyline".to_string(),
+        ));
+    }
+    let mut last = (0i64, 0i64);
+    let mut coords = Vec::with_capacity(values.len() / 2);
+    for pair in values.chunks(2) {
+        last = match (last.0.checked_add(pair[0]), last.1.checked_add(pair[1])) {
+            (Some(lat), Some(lng)) => (lat, lng),
+            _ => {
+                return Err(SqliteGisError::InvalidInput(format!(
+                    "encoded polyline coordinate {} overflows",
+                    coords.len() + 1
+                )))
+            }
+        };
+        coords.push(geo::Coord {
+            x: last.1 as f64 / factor,
+            y: last.0 as f64 / factor,
+        });
+    }
+    let geom = Geometry::LineString(geo::LineString::new(coords));
+    check_input_vertices(&geom)?;
+    write_ewkb(&geom, Some(4326))
+}
+
+// GeoHash
+
+/// Longest GeoHash `ST_GeoHash` writes: 20 characters, 100 bits, which is
+/// also what a point gets by default.
+pub const MAX_GEOHASH_CHARS: usize = 20;
+
+/// Number of GeoHash characters whose cell still contains the whole of
+/// `rect`, as PostGIS `lwgeom_geohash_precision` computes it.
+fn geohash_precision(rect: geo::Rect<f64>) -> usize {
+    let (min, max) = (rect.min(), rect.max());
+    if min == max {
+        return MAX_GEOHASH_CHARS;
+    }
+    let (mut lon, mut lat) = ((-180.0f64, 180.0f64), (-90.0f64, 90.0f64));
+    let mut bits = 0;
+    'halve: loop {
+        for (range, lo, hi) in [(&mut lon, min.x, max.x), (&mut lat, min.y, max.y)] {
+            let mid = (range.0 + range.1) / 2.0;
+            if lo > mid {
+                range.0 = mid;
+            } else if hi < mid {
+                range.1 = mid;
+            } else {
+                break 'halve;
+            }
+            bits += 1;
+        }
+    }
+    (bits / 5).min(MAX_GEOHASH_CHARS)
+}
+
+fn geohash_point(lon: f64, lat: f64, chars: usize) -> String {
+    let (mut lon_range, mut lat_range) = ((-180.0, 180.0), (-90.0, 90.0));
+    let mut even = true;
+    let mut out = String::with_capacity(chars);
+    for _ in 0..chars {
+        let mut value = 0usize;
+        for _ in 0..5 {
+            let (range, v): (&mut (f64, f64), f64) = if even {
+                (&mut lon_range, lon)
+            } else {
+                (&mut lat_range, lat)
+            };
+            let mid = (range.0 + range.1) / 2.0;
+            value <<= 1;
+            if v >= mid {
+                value |= 1;
+                range.0 = mid;
+            } else {
+                range.1 = mid;
+            }
+            even = !even;
+        }
+        out.push(char::from(GEOHASH_ALPHABET[value]));
+    }
+    out
+}
+
+/// GeoHash of the centre of a geometry's bounding box, as PostGIS
+/// `ST_GeoHash` does. Without `max_chars` (or with `max_chars <= 0`) the
+/// hash is as long as possible while its cell still holds the whole box, up
+/// to [`MAX_GEOHASH_CHARS`] for points. Coordinates must be longitude and
+/// latitude in degrees. Returns `None` for empty geometries.
+///
+/// # Example
+///
+/// ```
+/// use sqlitegis::core::functions::constructors::st_point;
+/// use sqlitegis::core::functions::io::as_geohash;
+///
+/// let point = st_point(-126.0, 48.0, Some(4326)).unwrap();
+/// assert_eq!(as_geohash(&point, None).unwrap().unwrap(), "c0w3hf1s70w3hf1s70w3");
+/// assert_eq!(as_geohash(&point, Some(5)).unwrap().unwrap(), "c0w3h");
+/// ```
+pub fn as_geohash(blob: &[u8], max_chars: Option<i32>) -> Result<Option<String>> {
+    let (geom, _srid) = parse_ewkb(blob)?;
+    let Some(rect) = (!is_empty_geometry(&geom))
+        .then(|| geom.bounding_rect())
+        .flatten()
+    else {
+        return Ok(None);
+    };
+    let (min, max) = (rect.min(), rect.max());
+    if min.x < -180.0 || max.x > 180.0 || min.y < -90.0 || max.y > 90.0 {
+        return Err(SqliteGisError::InvalidInput(format!(
+            "GeoHash needs longitude/latitude degrees, got bounds ({} {}, {} {})",
+            min.x, min.y, max.x, max.y
+        )));
+    }
+    let chars = match max_chars {
+        Some(n) if n > 0 => {
+            let n = n as usize;
+            if n > MAX_GEOHASH_CHARS {
+                return Err(SqliteGisError::InvalidInput(format!(
+                    "GeoHash length must be at most {MAX_GEOHASH_CHARS}, got {n}"
+                )));
+            }
+            n
+        }
+        _ => geohash_precision(rect),
+    };
+    let centre = rect.center();
+    Ok(Some(geohash_point(centre.x, centre.y, chars)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -482,6 +1293,31 @@ mod tests {
         assert!(format!("{err}").contains("unsupported coordinate dimensions"));
     }
 
+    #[test]
+    fn hexewkb_round_trips_in_both_byte_orders() {
+        let blob = geom_from_text(
+            "GEOMETRYCOLLECTION(POINT(1 2),POLYGON((0 0,1 0,1 1,0 0)))",
+            Some(3857),
+        )
+        .unwrap();
+        let ndr = as_hexewkb(&blob, "ndr").unwrap();
+        let xdr = as_hexewkb(&blob, "XDR").unwrap();
+        assert_eq!(ndr.len(), xdr.len());
+        assert_eq!(geom_from_hexewkb(&ndr).unwrap(), blob);
+        let big = geom_from_hexewkb(&format!(" {} ", xdr.to_lowercase())).unwrap();
+        assert_eq!(big[0], 0x00);
+        assert_eq!(as_hexewkb(&big, "NDR").unwrap(), ndr);
+        assert!(matches!(
+            as_hexewkb(&blob, "big"),
+            Err(SqliteGisError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            geom_from_hexewkb("0101000000ZZ"),
+            Err(SqliteGisError::InvalidInput(_))
+        ));
+        assert!(geom_from_hexewkb("01010000000000").is_err());
+    }
+
     #[test]
     fn geom_from_ewkb_and_as_ewkb_roundtrip() {
         let blob = geom_from_text("LINESTRING(0 0,1 1)", Some(4326)).unwrap();
@@ -582,4 +1418,232 @@ mod tests {
         assert!(f64::from_be_bytes(out[5..13].try_into().unwrap()).is_nan());
         assert!(f64::from_be_bytes(out[13..21].try_into().unwrap()).is_nan());
     }
+
+    #[test]
+    fn as_twkb_matches_postgis_output() {
+        // PostGIS: ST_AsTWKB('LINESTRING(1 1,5 5)') = \x02000202020808
+        let blob = geom_from_text("LINESTRING(1 1,5 5)", None).unwrap();
+        assert_eq!(
+            as_twkb(&blob, 0, 0, 0, false, false).unwrap(),
+            [0x02, 0x00, 0x02, 0x02, 0x02, 0x08, 0x08]
+        );
+        // With sizes and boxes: size covers bbox + body, bbox is min/delta.
+        let out = as_twkb(&blob, 0, 0, 0, true, true).unwrap();
+        assert_eq!(
+            out,
+            [0x02, 0x03, 0x09, 0x02, 0x08, 0x02, 0x08, 0x02, 0x02, 0x02, 0x08, 0x08]
+        );
+        assert_eq!(
+            as_text(&geom_from_twkb(&out).unwrap()).unwrap(),
+            "LINESTRING(1 1,5 5)"
+        );
+    }
+
+    #[test]
+    fn twkb_round_trips_every_geometry_type() {
+        for wkt in [
+            "POINT(1.5 -2.25)",
+            "LINESTRING(0 0,1 1,2 0)",
+            "POLYGON((0 0,4 0,4 4,0 4,0 0),(1 1,2 1,2 2,1 1))",
+            "MULTIPOINT((0 0),(1 1))",
+            "MULTILINESTRING((0 0,1 1),(2 2,3 3))",
+            "MULTIPOLYGON(((0 0,1 0,1 1,0 0)),((5 5,6 5,6 6,5 5)))",
+            "GEOMETRYCOLLECTION(POINT(1 1),LINESTRING(0 0,2 2))",
+            "POINT EMPTY",
+            "LINESTRING EMPTY",
+            "GEOMETRYCOLLECTION EMPTY",
+        ] {
+            let blob = geom_from_text(wkt, Some(4326)).unwrap();
+            for (sizes, boxes) in [(false, false), (true, true)] {
+                let twkb = as_twkb(&blob, 2, 0, 0, sizes, boxes).unwrap();
+                let back = geom_from_twkb(&twkb).unwrap();
+                assert_eq!(as_text(&back).unwrap(), as_text(&blob).unwrap(), "{wkt}");
+                assert_eq!(crate::core::ewkb::extract_srid(&back), None, "{wkt}");
+            }
+        }
+    }
+
+    #[test]
+    fn as_twkb_rounds_and_drops_repeated_vertices() {
+        let blob = geom_from_text("LINESTRING(0 0,0.4 0.4,10 10,10.2 10.1,30 30)", None).unwrap();
+        let twkb = as_twkb(&blob, -1, 0, 0, false, false).unwrap();
+        assert_eq!(
+            as_text(&geom_from_twkb(&twkb).unwrap()).unwrap(),
+            "LINESTRING(0 0,0 0,10 10,30 30)"
+        );
+    }
+
+    #[test]
+    fn as_twkb_rejects_coordinates_outside_the_integer_range() {
+        for wkt in [
+            "LINESTRING(-1e300 0,1e300 0)",
+            // Used to saturate to i64::MAX and decode as 92233720368547.77.
+            "LINESTRING(1e300 0,0 0)",
+            "POINT(0 1e300)",
+        ] {
+            let blob = geom_from_text(wkt, None).unwrap();
+            for (sizes, boxes) in [(false, false), (true, true)] {
+                assert!(
+                    matches!(
+                        as_twkb(&blob, 5, 0, 0, sizes, boxes),
+                        Err(SqliteGisError::InvalidInput(msg)) if msg.contains("TWKB")
+                    ),
+                    "{wkt}"
+                );
+            }
+        }
+        // Both ends fit in an i64, but the step between them does not.
+        let blob = geom_from_text("LINESTRING(-5e18 0,5e18 0)", None).unwrap();
+        assert!(matches!(
+            as_twkb(&blob, 0, 0, 0, false, false),
+            Err(SqliteGisError::InvalidInput(msg)) if msg.contains("too large for TWKB")
+        ));
+        // A step that fits still encodes and round-trips.
+        let blob = geom_from_text("LINESTRING(-4e18 0,4e18 0)", None).unwrap();
+        let twkb = as_twkb(&blob, 0, 0, 0, true, true).unwrap();
+        assert_eq!(
+            as_text(&geom_from_twkb(&twkb).unwrap()).unwrap(),
+            "LINESTRING(-4000000000000000000 0,4000000000000000000 0)"
+        );
+    }
+
+    #[test]
+    fn twkb_rejects_bad_precision_and_malformed_input() {
+        let blob = geom_from_text("POINT(1 2)", None).unwrap();
+        for (xy, z, m) in [(8, 0, 0), (-8, 0, 0), (0, -1, 0), (0, 0, 8)] {
+            assert!(matches!(
+                as_twkb(&blob, xy, z, m, false, false),
+                Err(SqliteGisError::InvalidInput(_))
+            ));
+        }
+        for bad in [
+            &b""[..],
+            b"\x01",
+            b"\x01\x00\x02",
+            b"\x09\x00",
+            b"\x02\x00\x7f",
+            b"\x01\x00\x02\x04\x00",
+        ] {
+            assert!(
+                matches!(geom_from_twkb(bad), Err(SqliteGisError::InvalidInput(_))),
+                "{bad:?}"
+            );
+        }
+        // A delta that carries the running coordinate past i64::MAX.
+        let mut overflow = vec![0x02u8, 0x00, 0x02];
+        overflow.extend_from_slice(&[0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]);
+        overflow.extend_from_slice(&[0x00, 0x02, 0x00]);
+        assert!(matches!(
+            geom_from_twkb(&overflow),
+            Err(SqliteGisError::InvalidInput(msg)) if msg.contains("overflows")
+        ));
+        // Z dimension in the extended-dimensions byte.
+        assert!(matches!(
+            geom_from_twkb(b"\x01\x08\x01\x02\x04\x06"),
+            Err(SqliteGisError::UnsupportedDimensions { .. })
+        ));
+
+        // The encoder is 2D: no Z/M precision and no Z/M input.
+        let mut point_z = vec![0x01u8];
+        point_z.extend_from_slice(&(WKB_POINT | EWKB_Z_FLAG).to_le_bytes());
+        for v in [1.0f64, 2.0, 3.0] {
+            point_z.extend_from_slice(&v.to_le_bytes());
+        }
+        for (geom, z, m) in [
+            (&blob, 1, 0),
+            (&blob, 0, 1),
+            (&point_z, 0, 0),
+            (&point_z, 1, 0),
+        ] {
+            assert!(matches!(
+                as_twkb(geom, 0, z, m, false, false),
+                Err(SqliteGisError::UnsupportedDimensions { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn encoded_polyline_round_trips_and_rejects_garbage() {
+        let line =
+            geom_from_text("LINESTRING(-120.2 38.5,-120.95 40.7,-126.453 43.252)", None).unwrap();
+        for precision in [0, 5, 6] {
+            let polyline = as_encoded_polyline(&line, precision).unwrap();
+            let back = line_from_encoded_polyline(&polyline, precision).unwrap();
+            assert_eq!(
+                as_encoded_polyline(&back, precision).unwrap(),
+                polyline,
+                "{precision}"
+            );
+        }
+        let back = line_from_encoded_polyline(&as_encoded_polyline(&line, 6).unwrap(), 6).unwrap();
+        assert_eq!(
+            as_text(&back).unwrap(),
+            "LINESTRING(-120.2 38.5,-120.95 40.7,-126.453 43.252)"
+        );
+        let points = geom_from_text("MULTIPOINT(-120.2 38.5,-120.95 40.7)", None).unwrap();
+        assert_eq!(
+            as_encoded_polyline(&points, 5).unwrap(),
+            "_p~iF~ps|U_ulLnnqC"
+        );
+        assert_eq!(
+            as_text(&line_from_encoded_polyline("", 5).unwrap()).unwrap(),
+            "LINESTRING EMPTY"
+        );
+
+        let polygon = geom_from_text("POLYGON((0 0,1 0,1 1,0 0))", None).unwrap();
+        assert!(matches!(
+            as_encoded_polyline(&polygon, 5),
+            Err(SqliteGisError::WrongType { .. })
+        ));
+        // Two latitude steps of 2^62 each overflow i64.
+        assert!(matches!(
+            line_from_encoded_polyline("____________G?____________G?", 5),
+            Err(SqliteGisError::InvalidInput(msg)) if msg.contains("overflows")
+        ));
+        let far = geom_from_text("LINESTRING(0 0,0 1e300)", None).unwrap();
+        assert!(matches!(
+            as_encoded_polyline(&far, 5),
+            Err(SqliteGisError::InvalidInput(_))
+        ));
+        for bad in ["_p~iF", "_p~iF~ps|", "_p~iF ~ps|U", "~~~~~~~~~~~~~~~?"] {
+            assert!(
+                matches!(
+                    line_from_encoded_polyline(bad, 5),
+                    Err(SqliteGisError::InvalidInput(_))
+                ),
+                "{bad}"
+            );
+        }
+    }
+
+    #[test]
+    fn as_geohash_sizes_the_hash_to_the_bounding_box() {
+        let hash = |wkt, chars| as_geohash(&geom_from_text(wkt, None).unwrap(), chars).unwrap();
+        assert_eq!(
+            hash("POINT(10.40744 57.64911)", Some(11)).unwrap(),
+            "u4pruydqqvj"
+        );
+        // The cell of the auto-sized hash contains the whole box.
+        let auto = hash("LINESTRING(10.40 57.64,10.41 57.65)", None).unwrap();
+        assert_eq!(auto, "u4pru");
+        assert_eq!(
+            hash("POLYGON((-170 -80,170 -80,170 80,-170 -80))", None).unwrap(),
+            ""
+        );
+        assert_eq!(hash("LINESTRING EMPTY", None), None);
+        // Zero and negative lengths select the automatic size.
+        for chars in [Some(0), Some(-1), Some(i32::MIN)] {
+            assert_eq!(
+                hash("LINESTRING(10.40 57.64,10.41 57.65)", chars).unwrap(),
+                auto
+            );
+        }
+
+        for (wkt, chars) in [("POINT(200 0)", None), ("POINT(0 0)", Some(21))] {
+            assert!(matches!(
+                as_geohash(&geom_from_text(wkt, None).unwrap(), chars),
+                Err(SqliteGisError::InvalidInput(_))
+            ));
+        }
+    }
 }
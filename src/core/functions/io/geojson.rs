@@ -0,0 +1,164 @@
+//! GeoJSON Feature and FeatureCollection output.
+//!
+//! SQLite has no row values, so a Feature's properties come in as a JSON
+//! object, typically built with `json_object(...)`. Features are written
+//! in the same spacing as the geometry writer, and a FeatureCollection
+//! keeps each member Feature's text as it was given.
+//!
+//! ```
+//! use sqlitegis::core::functions::io::{as_geojson_feature, geom_from_text, GeoJsonFeatureCollection};
+//!
+//! let blob = geom_from_text("POINT(1 2)", Some(4326)).unwrap();
+//! let feature = as_geojson_feature(Some(r#"{"name":"a"}"#), &blob, None).unwrap();
+//! assert_eq!(
+//!     feature,
+//!     r#"{"type": "Feature", "geometry": {"type": "Point", "coordinates": [1,2]}, "properties": {"name":"a"}}"#
+//! );
+//!
+//! let mut collection = GeoJsonFeatureCollection::new();
+//! collection.add_feature(&feature).unwrap();
+//! assert_eq!(collection.len(), 1);
+//! assert!(collection.finish().starts_with(r#"{"type": "FeatureCollection", "features": [{"type": "Feature""#));
+//! ```
+
+use serde_json::Value;
+
+use super::{as_geojson, as_geojson_precision};
+use crate::core::error::{Result, SqliteGisError};
+use crate::core::limits::check_input_bytes;
+
+/// Wrap an EWKB blob in a GeoJSON Feature. `properties` must be a JSON
+/// object; `None` writes `{}`. `maxdecimaldigits` rounds the coordinates
+/// as in [`as_geojson_precision`].
+pub fn as_geojson_feature(
+    properties: Option<&str>,
+    blob: &[u8],
+    maxdecimaldigits: Option<i32>,
+) -> Result<String> {
+    let properties = match properties {
+        Some(text) => {
+            check_input_bytes(text.len())?;
+            let value: Value = serde_json::from_str(text).map_err(|e| {
+                SqliteGisError::InvalidInput(format!("properties are not valid JSON: {e}"))
+            })?;
+            if !value.is_object() {
+                return Err(SqliteGisError::InvalidInput(
+                    "properties must be a JSON object".to_string(),
+                ));
+            }
+            value.to_string()
+        }
+        None => "{}".to_string(),
+    };
+    let geometry = match maxdecimaldigits {
+        Some(digits) => as_geojson_precision(blob, digits)?,
+        None => as_geojson(blob)?,
+    };
+    Ok(format!(
+        r#"{{"type": "Feature", "geometry": {geometry}, "properties": {properties}}}"#
+    ))
+}
+
+/// Accumulates Features into a GeoJSON FeatureCollection, as the
+/// `ST_AsGeoJSONFeatureCollection` aggregate does.
+#[derive(Debug, Clone, Default)]
+pub struct GeoJsonFeatureCollection {
+    features: Vec<String>,
+}
+
+impl GeoJsonFeatureCollection {
+    /// An empty collection.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of Features added so far.
+    pub fn len(&self) -> usize {
+        self.features.len()
+    }
+
+    /// Whether no Feature has been added.
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty()
+    }
+
+    /// Add a Feature given as GeoJSON text, such as the output of
+    /// [`as_geojson_feature`]. Anything but a JSON object of type
+    /// `Feature` is rejected.
+    pub fn add_feature(&mut self, feature: &str) -> Result<()> {
+        check_input_bytes(feature.len())?;
+        let value: Value = serde_json::from_str(feature)
+            .map_err(|e| SqliteGisError::InvalidInput(format!("feature is not valid JSON: {e}")))?;
+        if value.get("type").and_then(Value::as_str) != Some("Feature") {
+            return Err(SqliteGisError::InvalidInput(
+                "feature must be a GeoJSON object of type \"Feature\"".to_string(),
+            ));
+        }
+        self.features.push(feature.trim().to_string());
+        Ok(())
+    }
+
+    /// Write the collection.
+    pub fn finish(&self) -> String {
+        format!(
+            r#"{{"type": "FeatureCollection", "features": [{}]}}"#,
+            self.features.join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::functions::io::{geom_from_geojson, geom_from_text};
+
+    #[test]
+    fn features_round_trip_through_geom_from_geojson() {
+        let blob = geom_from_text("LINESTRING(0.123456 1,2 3.98765)", Some(4326)).unwrap();
+        let feature = as_geojson_feature(Some(r#" {"id": 7} "#), &blob, Some(1)).unwrap();
+        assert_eq!(
+            feature,
+            r#"{"type": "Feature", "geometry": {"type": "LineString", "coordinates": [[0.1,1],[2,4]]}, "properties": {"id":7}}"#
+        );
+        let restored = geom_from_geojson(&feature, None).unwrap();
+        assert_eq!(
+            restored,
+            geom_from_text("LINESTRING(0.1 1,2 4)", Some(4326)).unwrap()
+        );
+        assert!(as_geojson_feature(None, &blob, None)
+            .unwrap()
+            .ends_with(r#""properties": {}}"#));
+        for bad in ["[1]", "{", "null"] {
+            assert!(matches!(
+                as_geojson_feature(Some(bad), &blob, None),
+                Err(SqliteGisError::InvalidInput(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn feature_collection_only_takes_features() {
+        let blob = geom_from_text("POINT(1 2)", None).unwrap();
+        let mut collection = GeoJsonFeatureCollection::new();
+        assert!(collection.is_empty());
+        assert_eq!(
+            collection.finish(),
+            r#"{"type": "FeatureCollection", "features": []}"#
+        );
+        collection
+            .add_feature(&as_geojson_feature(None, &blob, None).unwrap())
+            .unwrap();
+        collection
+            .add_feature(r#"{"type":"Feature","geometry":null,"properties":null}"#)
+            .unwrap();
+        assert!(collection
+            .add_feature(r#"{"type":"Point","coordinates":[1,2]}"#)
+            .is_err());
+        assert!(collection.add_feature("nope").is_err());
+        assert_eq!(collection.len(), 2);
+
+        let json: Value = serde_json::from_str(&collection.finish()).unwrap();
+        assert_eq!(json["features"][0]["geometry"]["type"], "Point");
+        assert!(json["features"][1]["geometry"].is_null());
+    }
+}
@@ -0,0 +1,1363 @@
+//! KML and GML output and input, and SVG path output.
+//!
+//! KML coordinates are always WGS 84 longitude/latitude, so `ST_AsKML`
+//! only accepts SRID 4326 (or no SRID) and `ST_GeomFromKML` stamps 4326 on
+//! its output.
+//!
+//! In GML the srsName decides the axis order. `EPSG:4326` and
+//! `http://www.opengis.net/gml/srs/epsg.xml#4326` are longitude first.
+//! `urn:ogc:def:crs:EPSG::4326` and `http://www.opengis.net/def/crs/EPSG/0/4326`
+//! follow the EPSG definition, which for geographic CRSs is latitude
+//! first. The writer swaps the axes when it emits a URN srsName for such a
+//! CRS and the reader swaps them back, so a round trip preserves the
+//! stored `x = longitude` layout.
+//!
+//! SVG output is path data for a `d` (or `cx`/`cy`) attribute rather than
+//! a document, so callers can embed it in their own markup.
+//!
+//! Both readers take the geometry element alone, as `ST_AsKML` and
+//! `ST_AsGML` write it. The `gml:` and `kml:` prefixes may be used without
+//! declaring their namespaces. Z values are dropped on input, like the WKT
+//! reader does.
+//!
+//! ```
+//! use sqlitegis::core::functions::io::{as_gml, as_text, geom_from_gml, geom_from_text};
+//! use sqlitegis::core::functions::io::GML_LONG_CRS;
+//!
+//! let blob = geom_from_text("POINT(13.4 52.5)", Some(4326)).unwrap();
+//! let gml = as_gml(3, &blob, 15, GML_LONG_CRS).unwrap().unwrap();
+//! assert_eq!(
+//!     gml,
+//!     "<gml:Point srsName=\"urn:ogc:def:crs:EPSG::4326\">\
+//!      <gml:pos srsDimension=\"2\">52.5 13.4</gml:pos></gml:Point>"
+//! );
+//! assert_eq!(as_text(&geom_from_gml(&gml, None).unwrap()).unwrap(), "POINT(13.4 52.5)");
+//! ```
+
+use std::fmt::Write;
+
+use geo::{
+    Coord, Geometry, GeometryCollection, LineString, MultiLineString, MultiPoint, MultiPolygon,
+    Point, Polygon, Rect,
+};
+use geozero::GeozeroGeometry;
+use roxmltree::{Document, Node};
+
+use crate::core::error::{Result, SqliteGisError};
+use crate::core::ewkb::{geometry_type_name, parse_ewkb, write_ewkb};
+use crate::core::functions::emptiness::{is_empty_geometry, is_empty_point};
+use crate::core::limits::{check_input_bytes, check_input_vertices};
+
+/// Largest number of decimal digits `ST_AsKML` and `ST_AsGML` write.
+pub const MAX_DECIMAL_DIGITS: i32 = 15;
+
+/// `ST_AsGML` option: write `urn:ogc:def:crs:EPSG::<srid>` instead of
+/// `EPSG:<srid>`.
+pub const GML_LONG_CRS: i32 = 1;
+/// `ST_AsGML` option: omit the `srsDimension` attribute (GML 3 only).
+pub const GML_NO_SRS_DIMENSION: i32 = 2;
+/// `ST_AsGML` option: write `<gml:LineString>` instead of `<gml:Curve>`
+/// (GML 3 only).
+pub const GML_LINESTRING: i32 = 4;
+/// `ST_AsGML` option: write latitude before longitude whatever the
+/// srsName.
+pub const GML_LAT_LON: i32 = 16;
+/// `ST_AsGML` option: write the envelope (`<gml:Box>` or `<gml:Envelope>`)
+/// instead of the geometry.
+pub const GML_ENVELOPE: i32 = 32;
+
+const GML_NS: &str = "http://www.opengis.net/gml";
+const KML_NS: &str = "http://www.opengis.net/kml/2.2";
+
+/// EPSG geographic CRSs whose definition puts latitude first. Only these
+/// get their axes swapped under a URN srsName.
+fn is_lat_lon_srid(srid: i32) -> bool {
+    matches!(srid, 4326 | 4258 | 4267 | 4269)
+}
+
+pub(super) fn check_decimal_digits(precision: i32) -> Result<usize> {
+    if !(0..=MAX_DECIMAL_DIGITS).contains(&precision) {
+        return Err(SqliteGisError::InvalidInput(format!(
+            "precision must be between 0 and {MAX_DECIMAL_DIGITS}, got {precision}"
+        )));
+    }
+    Ok(precision as usize)
+}
+
+/// Write `v` with at most `digits` decimals and no trailing zeros.
+fn push_number(out: &mut String, v: f64, digits: usize) {
+    let start = out.len();
+    let _ = write!(out, "{v:.digits$}");
+    if out[start..].contains('.') {
+        let kept = out[start..]
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .len();
+        out.truncate(start + kept);
+    }
+    if &out[start..] == "-0" {
+        out.truncate(start);
+        out.push('0');
+    }
+}
+
+// KML output
+
+/// Serialize an EWKB blob as a KML geometry element, as PostGIS
+/// `ST_AsKML` does, with at most `precision` decimal digits (`0..=15`).
+///
+/// Returns `None` for empty geometries, which KML cannot express.
+///
+/// # Example
+///
+/// ```
+/// use sqlitegis::core::functions::io::{as_kml, geom_from_text};
+///
+/// let blob = geom_from_text("LINESTRING(1 2,3.14159 4)", Some(4326)).unwrap();
+/// assert_eq!(
+///     as_kml(&blob, 2).unwrap().unwrap(),
+///     "<LineString><coordinates>1,2 3.14,4</coordinates></LineString>"
+/// );
+/// ```
+pub fn as_kml(blob: &[u8], precision: i32) -> Result<Option<String>> {
+    let digits = check_decimal_digits(precision)?;
+    let (geom, srid) = parse_ewkb(blob)?;
+    if let Some(srid) = srid.filter(|&s| s != 4326) {
+        return Err(SqliteGisError::InvalidInput(format!(
+            "KML needs WGS 84 longitude/latitude (SRID 4326) coordinates, got SRID {srid}"
+        )));
+    }
+    if is_empty_geometry(&geom) {
+        return Ok(None);
+    }
+    let mut out = String::new();
+    write_kml(&geom, digits, &mut out)?;
+    Ok(Some(out))
+}
+
+fn push_kml_coordinates(out: &mut String, coords: &[Coord<f64>], digits: usize) {
+    out.push_str("<coordinates>");
+    for (i, c) in coords.iter().enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        push_number(out, c.x, digits);
+        out.push(',');
+        push_number(out, c.y, digits);
+    }
+    out.push_str("</coordinates>");
+}
+
+fn write_kml_polygon(polygon: &Polygon<f64>, digits: usize, out: &mut String) {
+    out.push_str("<Polygon><outerBoundaryIs><LinearRing>");
+    push_kml_coordinates(out, &polygon.exterior().0, digits);
+    out.push_str("</LinearRing></outerBoundaryIs>");
+    for ring in polygon.interiors() {
+        out.push_str("<innerBoundaryIs><LinearRing>");
+        push_kml_coordinates(out, &ring.0, digits);
+        out.push_str("</LinearRing></innerBoundaryIs>");
+    }
+    out.push_str("</Polygon>");
+}
+
+fn write_kml(geom: &Geometry<f64>, digits: usize, out: &mut String) -> Result<()> {
+    match geom {
+        Geometry::Point(p) => {
+            out.push_str("<Point>");
+            push_kml_coordinates(out, &[p.0], digits);
+            out.push_str("</Point>");
+        }
+        Geometry::LineString(ls) => {
+            out.push_str("<LineString>");
+            push_kml_coordinates(out, &ls.0, digits);
+            out.push_str("</LineString>");
+        }
+        Geometry::Polygon(polygon) => write_kml_polygon(polygon, digits, out),
+        Geometry::MultiPoint(mp) => {
+            out.push_str("<MultiGeometry>");
+            for p in mp.0.iter().filter(|p| !is_empty_point(p)) {
+                write_kml(&Geometry::Point(*p), digits, out)?;
+            }
+            out.push_str("</MultiGeometry>");
+        }
+        Geometry::MultiLineString(mls) => {
+            out.push_str("<MultiGeometry>");
+            for ls in mls.0.iter().filter(|ls| !ls.0.is_empty()) {
+                out.push_str("<LineString>");
+                push_kml_coordinates(out, &ls.0, digits);
+                out.push_str("</LineString>");
+            }
+            out.push_str("</MultiGeometry>");
+        }
+        Geometry::MultiPolygon(mp) => {
+            out.push_str("<MultiGeometry>");
+            for polygon in mp.0.iter().filter(|p| !p.exterior().0.is_empty()) {
+                write_kml_polygon(polygon, digits, out);
+            }
+            out.push_str("</MultiGeometry>");
+        }
+        Geometry::GeometryCollection(gc) => {
+            out.push_str("<MultiGeometry>");
+            for child in gc.0.iter().filter(|g| !is_empty_geometry(g)) {
+                write_kml(child, digits, out)?;
+            }
+            out.push_str("</MultiGeometry>");
+        }
+        other => {
+            return Err(SqliteGisError::WrongType {
+                expected: "KML-encodable geometry",
+                actual: geometry_type_name(other),
+            })
+        }
+    }
+    Ok(())
+}
+
+// GML output
+
+struct GmlWriter {
+    version3: bool,
+    digits: usize,
+    swap: bool,
+    srs_dimension: bool,
+    curve: bool,
+    out: String,
+}
+
+impl GmlWriter {
+    fn open(&mut self, name: &str, srs_name: Option<&str>) {
+        let _ = write!(self.out, "<gml:{name}");
+        if let Some(srs_name) = srs_name {
+            let _ = write!(self.out, " srsName=\"{srs_name}\"");
+        }
+        self.out.push('>');
+    }
+
+    fn close(&mut self, name: &str) {
+        let _ = write!(self.out, "</gml:{name}>");
+    }
+
+    fn pair(&mut self, c: Coord<f64>, separator: char) {
+        let (a, b) = if self.swap { (c.y, c.x) } else { (c.x, c.y) };
+        push_number(&mut self.out, a, self.digits);
+        self.out.push(separator);
+        push_number(&mut self.out, b, self.digits);
+    }
+
+    /// GML 3 `<gml:pos>`/`<gml:posList>`, GML 2 `<gml:coordinates>`.
+    fn coords(&mut self, coords: &[Coord<f64>], single: bool) {
+        if !self.version3 {
+            self.out.push_str("<gml:coordinates>");
+            for (i, c) in coords.iter().enumerate() {
+                if i > 0 {
+                    self.out.push(' ');
+                }
+                self.pair(*c, ',');
+            }
+            self.out.push_str("</gml:coordinates>");
+            return;
+        }
+        let element = if single { "pos" } else { "posList" };
+        let _ = write!(self.out, "<gml:{element}");
+        if self.srs_dimension {
+            self.out.push_str(" srsDimension=\"2\"");
+        }
+        self.out.push('>');
+        for (i, c) in coords.iter().enumerate() {
+            if i > 0 {
+                self.out.push(' ');
+            }
+            self.pair(*c, ' ');
+        }
+        let _ = write!(self.out, "</gml:{element}>");
+    }
+
+    fn point(&mut self, p: &Point<f64>, srs_name: Option<&str>) {
+        self.open("Point", srs_name);
+        self.coords(&[p.0], true);
+        self.close("Point");
+    }
+
+    fn line(&mut self, ls: &LineString<f64>, srs_name: Option<&str>, allow_curve: bool) {
+        if self.version3 && self.curve && allow_curve {
+            self.open("Curve", srs_name);
+            self.out.push_str("<gml:segments><gml:LineStringSegment>");
+            self.coords(&ls.0, false);
+            self.out.push_str("</gml:LineStringSegment></gml:segments>");
+            self.close("Curve");
+        } else {
+            self.open("LineString", srs_name);
+            self.coords(&ls.0, false);
+            self.close("LineString");
+        }
+    }
+
+    fn polygon(&mut self, polygon: &Polygon<f64>, srs_name: Option<&str>) {
+        let (outer, inner) = if self.version3 {
+            ("exterior", "interior")
+        } else {
+            ("outerBoundaryIs", "innerBoundaryIs")
+        };
+        self.open("Polygon", srs_name);
+        for (i, ring) in std::iter::once(polygon.exterior())
+            .chain(polygon.interiors())
+            .enumerate()
+        {
+            let boundary = if i == 0 { outer } else { inner };
+            let _ = write!(self.out, "<gml:{boundary}><gml:LinearRing>");
+            self.coords(&ring.0, false);
+            let _ = write!(self.out, "</gml:LinearRing></gml:{boundary}>");
+        }
+        self.close("Polygon");
+    }
+
+    fn geometry(&mut self, geom: &Geometry<f64>, srs_name: Option<&str>) -> Result<()> {
+        match geom {
+            Geometry::Point(p) => self.point(p, srs_name),
+            Geometry::LineString(ls) => self.line(ls, srs_name, true),
+            Geometry::Polygon(polygon) => self.polygon(polygon, srs_name),
+            Geometry::MultiPoint(mp) => {
+                self.open("MultiPoint", srs_name);
+                for p in mp.0.iter().filter(|p| !is_empty_point(p)) {
+                    self.out.push_str("<gml:pointMember>");
+                    self.point(p, None);
+                    self.out.push_str("</gml:pointMember>");
+                }
+                self.close("MultiPoint");
+            }
+            Geometry::MultiLineString(mls) => {
+                let (name, member) = if self.version3 {
+                    ("MultiCurve", "curveMember")
+                } else {
+                    ("MultiLineString", "lineStringMember")
+                };
+                self.open(name, srs_name);
+                for ls in mls.0.iter().filter(|ls| !ls.0.is_empty()) {
+                    let _ = write!(self.out, "<gml:{member}>");
+                    self.line(ls, None, false);
+                    let _ = write!(self.out, "</gml:{member}>");
+                }
+                self.close(name);
+            }
+            Geometry::MultiPolygon(mp) => {
+                let (name, member) = if self.version3 {
+                    ("MultiSurface", "surfaceMember")
+                } else {
+                    ("MultiPolygon", "polygonMember")
+                };
+                self.open(name, srs_name);
+                for polygon in mp.0.iter().filter(|p| !p.exterior().0.is_empty()) {
+                    let _ = write!(self.out, "<gml:{member}>");
+                    self.polygon(polygon, None);
+                    let _ = write!(self.out, "</gml:{member}>");
+                }
+                self.close(name);
+            }
+            Geometry::GeometryCollection(gc) => {
+                self.open("MultiGeometry", srs_name);
+                for child in gc.0.iter().filter(|g| !is_empty_geometry(g)) {
+                    self.out.push_str("<gml:geometryMember>");
+                    self.geometry(child, None)?;
+                    self.out.push_str("</gml:geometryMember>");
+                }
+                self.close("MultiGeometry");
+            }
+            other => {
+                return Err(SqliteGisError::WrongType {
+                    expected: "GML-encodable geometry",
+                    actual: geometry_type_name(other),
+                })
+            }
+        }
+        Ok(())
+    }
+
+    fn envelope(&mut self, rect: Rect<f64>, srs_name: Option<&str>) {
+        if self.version3 {
+            self.out.push_str("<gml:Envelope");
+            if let Some(srs_name) = srs_name {
+                let _ = write!(self.out, " srsName=\"{srs_name}\"");
+            }
+            if self.srs_dimension {
+                self.out.push_str(" srsDimension=\"2\"");
+            }
+            self.out.push_str("><gml:lowerCorner>");
+            self.pair(rect.min(), ' ');
+            self.out.push_str("</gml:lowerCorner><gml:upperCorner>");
+            self.pair(rect.max(), ' ');
+            self.out.push_str("</gml:upperCorner></gml:Envelope>");
+        } else {
+            self.open("Box", srs_name);
+            self.coords(&[rect.min(), rect.max()], false);
+            self.close("Box");
+        }
+    }
+}
+
+/// Serialize an EWKB blob as a GML 2 or GML 3 geometry element, as PostGIS
+/// `ST_AsGML(version, geom, precision, options)` does.
+///
+/// `precision` caps the decimal digits (`0..=15`). `options` is a bit mask
+/// of [`GML_LONG_CRS`], [`GML_NO_SRS_DIMENSION`], [`GML_LINESTRING`],
+/// [`GML_LAT_LON`] and [`GML_ENVELOPE`], with PostGIS's values. The
+/// srsName is only written when the blob has an SRID; see the module docs
+/// for axis order. Returns `None` for empty geometries.
+///
+/// # Example
+///
+/// ```
+/// use sqlitegis::core::functions::io::{as_gml, geom_from_text};
+///
+/// let blob = geom_from_text("LINESTRING(1 2,3 4)", Some(3857)).unwrap();
+/// assert_eq!(
+///     as_gml(2, &blob, 15, 0).unwrap().unwrap(),
+///     "<gml:LineString srsName=\"EPSG:3857\">\
+///      <gml:coordinates>1,2 3,4</gml:coordinates></gml:LineString>"
+/// );
+/// ```
+pub fn as_gml(version: i32, blob: &[u8], precision: i32, options: i32) -> Result<Option<String>> {
+    if version != 2 && version != 3 {
+        return Err(SqliteGisError::InvalidInput(format!(
+            "GML version must be 2 or 3, got {version}"
+        )));
+    }
+    let digits = check_decimal_digits(precision)?;
+    let (geom, srid) = parse_ewkb(blob)?;
+    if is_empty_geometry(&geom) {
+        return Ok(None);
+    }
+
+    let long_crs = options & GML_LONG_CRS != 0;
+    let srs_name = srid.map(|srid| {
+        if long_crs {
+            format!("urn:ogc:def:crs:EPSG::{srid}")
+        } else {
+            format!("EPSG:{srid}")
+        }
+    });
+    let mut writer = GmlWriter {
+        version3: version == 3,
+        digits,
+        swap: options & GML_LAT_LON != 0 || (long_crs && srid.is_some_and(is_lat_lon_srid)),
+        srs_dimension: options & GML_NO_SRS_DIMENSION == 0,
+        curve: options & GML_LINESTRING == 0,
+        out: String::new(),
+    };
+    if options & GML_ENVELOPE != 0 {
+        if let Some(rect) = geo::BoundingRect::bounding_rect(&geom) {
+            writer.envelope(rect, srs_name.as_deref());
+        }
+    } else {
+        writer.geometry(&geom, srs_name.as_deref())?;
+    }
+    Ok(Some(writer.out))
+}
+
+// SVG output
+
+/// Serialize an EWKB blob as SVG path data, as PostGIS `ST_AsSVG` does.
+///
+/// The y axis is negated to match SVG's downward-pointing y. Points are
+/// written as `cx="x" cy="-y"` attributes (`x="x" y="-y"` when `relative`),
+/// lines and polygons as path data using absolute `M`/`L` commands, or
+/// relative `l` moves between coordinates rounded to `precision` decimal
+/// digits (`0..=15`) when `relative` is set. Polygon rings end in `Z` (`z`
+/// when relative) instead of repeating the first vertex. Members of a
+/// multi geometry are joined with spaces (commas for points), members of a
+/// GeometryCollection with `;`. Empty geometries give an empty string.
+///
+/// # Example
+///
+/// ```
+/// use sqlitegis::core::functions::io::{as_svg, geom_from_text};
+///
+/// let blob = geom_from_text("POLYGON((0 0,0 1,1 1,1 0,0 0))", None).unwrap();
+/// assert_eq!(as_svg(&blob, false, 15).unwrap(), "M 0 0 L 0 -1 1 -1 1 0 Z");
+/// assert_eq!(as_svg(&blob, true, 15).unwrap(), "M 0 0 l 0 -1 1 0 0 1 z");
+/// ```
+pub fn as_svg(blob: &[u8], relative: bool, precision: i32) -> Result<String> {
+    let digits = check_decimal_digits(precision)?;
+    let (geom, _srid) = parse_ewkb(blob)?;
+    if is_empty_geometry(&geom) {
+        return Ok(String::new());
+    }
+    // geozero has no SVG for empty points, and PostGIS skips empty members.
+    let geom = without_empty_members(geom);
+    let mut writer = SvgWriter {
+        relative,
+        digits,
+        scale: 10f64.powi(precision),
+        coords: svg_coords(&geom)?.into_iter(),
+        out: String::new(),
+    };
+    writer.geometry(&geom)?;
+    Ok(writer.out)
+}
+
+/// `geom` with the empty members of its multi geometries and collections
+/// removed, recursively.
+fn without_empty_members(geom: Geometry<f64>) -> Geometry<f64> {
+    match geom {
+        Geometry::MultiPoint(mp) => {
+            Geometry::MultiPoint(mp.into_iter().filter(|p| !is_empty_point(p)).collect())
+        }
+        Geometry::MultiLineString(mls) => Geometry::MultiLineString(MultiLineString::new(
+            mls.0.into_iter().filter(|ls| !ls.0.is_empty()).collect(),
+        )),
+        Geometry::MultiPolygon(mp) => Geometry::MultiPolygon(MultiPolygon::new(
+            mp.0.into_iter()
+                .filter(|p| !p.exterior().0.is_empty())
+                .collect(),
+        )),
+        Geometry::GeometryCollection(gc) => Geometry::GeometryCollection(GeometryCollection(
+            gc.0.into_iter()
+                .filter(|g| !is_empty_geometry(g))
+                .map(without_empty_members)
+                .collect(),
+        )),
+        other => other,
+    }
+}
+
+/// Every coordinate of `geom` in traversal order, y already negated, read
+/// back from geozero's SVG writer. geozero writes each geometry as a
+/// `<path d="..."/>` element of bare `x y` pairs, so the numbers are all
+/// that is kept; [`SvgWriter`] puts back the PostGIS commands around them.
+fn svg_coords(geom: &Geometry<f64>) -> Result<Vec<(f64, f64)>> {
+    let mut data = Vec::new();
+    geom.process_geom(&mut geozero::svg::SvgWriter::new(&mut data, true))?;
+    let text = String::from_utf8_lossy(&data);
+    let numbers: Vec<f64> = text
+        .split(|c: char| c.is_ascii_whitespace() || c == '"')
+        .filter_map(|token| token.parse().ok())
+        .collect();
+    Ok(numbers.chunks_exact(2).map(|xy| (xy[0], xy[1])).collect())
+}
+
+/// PostGIS `ST_AsSVG` formatting over the coordinates of [`svg_coords`],
+/// consumed in the same order the geometry is walked.
+struct SvgWriter {
+    relative: bool,
+    digits: usize,
+    scale: f64,
+    coords: std::vec::IntoIter<(f64, f64)>,
+    out: String,
+}
+
+impl SvgWriter {
+    fn round(&self, v: f64) -> f64 {
+        (v * self.scale).round() / self.scale
+    }
+
+    fn next(&mut self) -> Result<(f64, f64)> {
+        self.coords.next().ok_or_else(|| {
+            SqliteGisError::InvalidInput("SVG writer ran out of coordinates".to_string())
+        })
+    }
+
+    fn pair(&mut self, x: f64, y: f64) {
+        push_number(&mut self.out, x, self.digits);
+        self.out.push(' ');
+        push_number(&mut self.out, y, self.digits);
+    }
+
+    fn point(&mut self) -> Result<()> {
+        let (x, y) = self.next()?;
+        let (x_attr, y_attr) = if self.relative {
+            ("x", "y")
+        } else {
+            ("cx", "cy")
+        };
+        let _ = write!(self.out, "{x_attr}=\"");
+        push_number(&mut self.out, x, self.digits);
+        let _ = write!(self.out, "\" {y_attr}=\"");
+        push_number(&mut self.out, y, self.digits);
+        self.out.push('"');
+        Ok(())
+    }
+
+    /// `M` to the first of `len` vertices, then every following one; a
+    /// closed ring drops its repeated last vertex and ends in `Z`.
+    fn path(&mut self, len: usize, ring: bool) -> Result<()> {
+        if len == 0 {
+            return Ok(());
+        }
+        let written = if ring && len > 1 { len - 1 } else { len };
+        let first = self.next()?;
+        self.out.push_str("M ");
+        if self.relative {
+            let mut last = (self.round(first.0), self.round(first.1));
+            self.pair(last.0, last.1);
+            for i in 1..written {
+                self.out.push_str(if i == 1 { " l " } else { " " });
+                let (x, y) = self.next()?;
+                let next = (self.round(x), self.round(y));
+                self.pair(next.0 - last.0, next.1 - last.1);
+                last = next;
+            }
+        } else {
+            self.pair(first.0, first.1);
+            for i in 1..written {
+                self.out.push_str(if i == 1 { " L " } else { " " });
+                let (x, y) = self.next()?;
+                self.pair(x, y);
+            }
+        }
+        if written < len {
+            self.next()?;
+        }
+        if ring {
+            self.out.push_str(if self.relative { " z" } else { " Z" });
+        }
+        Ok(())
+    }
+
+    fn polygon(&mut self, polygon: &Polygon<f64>) -> Result<()> {
+        for (i, ring) in std::iter::once(polygon.exterior())
+            .chain(polygon.interiors())
+            .enumerate()
+        {
+            if i > 0 {
+                self.out.push(' ');
+            }
+            self.path(ring.0.len(), true)?;
+        }
+        Ok(())
+    }
+
+    fn geometry(&mut self, geom: &Geometry<f64>) -> Result<()> {
+        match geom {
+            Geometry::Point(_) => self.point()?,
+            Geometry::LineString(ls) => self.path(ls.0.len(), false)?,
+            Geometry::Polygon(polygon) => self.polygon(polygon)?,
+            Geometry::MultiPoint(mp) => {
+                for i in 0..mp.0.len() {
+                    if i > 0 {
+                        self.out.push(',');
+                    }
+                    self.point()?;
+                }
+            }
+            Geometry::MultiLineString(mls) => {
+                for (i, ls) in mls.0.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push(' ');
+                    }
+                    self.path(ls.0.len(), false)?;
+                }
+            }
+            Geometry::MultiPolygon(mp) => {
+                for (i, polygon) in mp.0.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push(' ');
+                    }
+                    self.polygon(polygon)?;
+                }
+            }
+            Geometry::GeometryCollection(gc) => {
+                for (i, child) in gc.0.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push(';');
+                    }
+                    self.geometry(child)?;
+                }
+            }
+            other => {
+                return Err(SqliteGisError::WrongType {
+                    expected: "SVG-encodable geometry",
+                    actual: geometry_type_name(other),
+                })
+            }
+        }
+        Ok(())
+    }
+}
+
+// XML input
+
+/// Parse a geometry fragment. It is wrapped in an element that declares
+/// the `gml:` and `kml:` prefixes, since `ST_AsGML` output uses `gml:`
+/// without declaring it.
+fn with_fragment<T>(text: &str, f: impl FnOnce(Node) -> Result<T>) -> Result<T> {
+    check_input_bytes(text.len())?;
+    let mut body = text.trim_start();
+    if body.starts_with("<?xml") {
+        body = body.find("?>").map_or("", |end| &body[end + 2..]);
+    }
+    let wrapped =
+        format!("<fragment xmlns:gml=\"{GML_NS}\" xmlns:kml=\"{KML_NS}\">{body}</fragment>");
+    let doc = Document::parse(&wrapped)
+        .map_err(|e| SqliteGisError::InvalidInput(format!("invalid XML: {e}")))?;
+    let mut roots = doc.root_element().children().filter(Node::is_element);
+    match (roots.next(), roots.next()) {
+        (Some(root), None) => f(root),
+        _ => Err(SqliteGisError::InvalidInput(
+            "expected exactly one geometry element".to_string(),
+        )),
+    }
+}
+
+fn name<'a>(node: &Node<'a, '_>) -> &'a str {
+    node.tag_name().name()
+}
+
+fn elements<'a, 'input>(node: Node<'a, 'input>) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(Node::is_element)
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, wanted: &str) -> Option<Node<'a, 'input>> {
+    elements(node).find(|n| name(n) == wanted)
+}
+
+fn required<'a, 'input>(node: Node<'a, 'input>, wanted: &str) -> Result<Node<'a, 'input>> {
+    child(node, wanted)
+        .ok_or_else(|| SqliteGisError::InvalidInput(format!("<{}> has no <{wanted}>", name(&node))))
+}
+
+fn parse_number(s: &str) -> Result<f64> {
+    match s.parse::<f64>() {
+        Ok(v) if v.is_finite() => Ok(v),
+        _ => Err(SqliteGisError::InvalidInput(format!(
+            "invalid coordinate value '{s}'"
+        ))),
+    }
+}
+
+/// Parse `x,y[,z] x,y[,z] ...` as used by KML and GML 2.
+fn parse_tuples(text: &str) -> Result<Vec<(f64, f64)>> {
+    text.split_whitespace()
+        .map(|tuple| {
+            let mut parts = tuple.split(',');
+            match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                (Some(x), Some(y), _, None) => Ok((parse_number(x)?, parse_number(y)?)),
+                _ => Err(SqliteGisError::InvalidInput(format!(
+                    "invalid coordinate tuple '{tuple}'"
+                ))),
+            }
+        })
+        .collect()
+}
+
+fn single_point(coords: Vec<Coord<f64>>, element: &str) -> Result<Point<f64>> {
+    match coords[..] {
+        [c] => Ok(Point(c)),
+        _ => Err(SqliteGisError::InvalidInput(format!(
+            "<{element}> must have exactly one coordinate, got {}",
+            coords.len()
+        ))),
+    }
+}
+
+/// Build the tightest multi type for `parts`, falling back to a
+/// GeometryCollection for mixed or empty input.
+fn collect_parts(parts: Vec<Geometry<f64>>) -> Geometry<f64> {
+    let all = |f: fn(&Geometry<f64>) -> bool| !parts.is_empty() && parts.iter().all(f);
+    if all(|g| matches!(g, Geometry::Point(_))) {
+        let points = parts.into_iter().filter_map(|g| Point::try_from(g).ok());
+        Geometry::MultiPoint(MultiPoint::from_iter(points))
+    } else if all(|g| matches!(g, Geometry::LineString(_))) {
+        let lines = parts
+            .into_iter()
+            .filter_map(|g| LineString::try_from(g).ok());
+        Geometry::MultiLineString(MultiLineString::from_iter(lines))
+    } else if all(|g| matches!(g, Geometry::Polygon(_))) {
+        let polygons = parts.into_iter().filter_map(|g| Polygon::try_from(g).ok());
+        Geometry::MultiPolygon(MultiPolygon::from_iter(polygons))
+    } else {
+        Geometry::GeometryCollection(GeometryCollection::from(parts))
+    }
+}
+
+// KML input
+
+/// Parse a KML geometry element (`<Point>`, `<LineString>`, `<Polygon>` or
+/// `<MultiGeometry>`) into an EWKB blob with SRID 4326, as PostGIS
+/// `ST_GeomFromKML` does. A `<MultiGeometry>` of a single geometry type
+/// becomes the matching multi type.
+///
+/// # Example
+///
+/// ```
+/// use sqlitegis::core::ewkb::extract_srid;
+/// use sqlitegis::core::functions::io::{as_text, geom_from_kml};
+///
+/// let blob = geom_from_kml("<Point><coordinates>13.4,52.5,34</coordinates></Point>").unwrap();
+/// assert_eq!(as_text(&blob).unwrap(), "POINT(13.4 52.5)");
+/// assert_eq!(extract_srid(&blob), Some(4326));
+/// ```
+pub fn geom_from_kml(kml: &str) -> Result<Vec<u8>> {
+    let geom = with_fragment(kml, kml_geometry)?;
+    check_input_vertices(&geom)?;
+    write_ewkb(&geom, Some(4326))
+}
+
+fn kml_coordinates(node: Node) -> Result<Vec<Coord<f64>>> {
+    let text = required(node, "coordinates")?.text().unwrap_or("");
+    Ok(parse_tuples(text)?
+        .into_iter()
+        .map(|(x, y)| Coord { x, y })
+        .collect())
+}
+
+fn kml_ring(boundary: Node) -> Result<LineString<f64>> {
+    Ok(LineString::new(kml_coordinates(required(
+        boundary,
+        "LinearRing",
+    )?)?))
+}
+
+fn kml_geometry(node: Node) -> Result<Geometry<f64>> {
+    Ok(match name(&node) {
+        "Point" => Geometry::Point(single_point(kml_coordinates(node)?, "Point")?),
+        "LineString" => Geometry::LineString(LineString::new(kml_coordinates(node)?)),
+        "Polygon" => {
+            let exterior = kml_ring(required(node, "outerBoundaryIs")?)?;
+            let interiors = elements(node)
+                .filter(|n| name(n) == "innerBoundaryIs")
+                .map(kml_ring)
+                .collect::<Result<_>>()?;
+            Geometry::Polygon(Polygon::new(exterior, interiors))
+        }
+        "MultiGeometry" => collect_parts(elements(node).map(kml_geometry).collect::<Result<_>>()?),
+        other => {
+            return Err(SqliteGisError::InvalidInput(format!(
+                "unsupported KML element <{other}>"
+            )))
+        }
+    })
+}
+
+// GML input
+
+/// SRID and axis order named by a GML srsName.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SrsName {
+    srid: i32,
+    lat_lon: bool,
+}
+
+fn parse_srs_name(srs_name: &str) -> Result<SrsName> {
+    let (code, authority_axes) = if let Some(code) = srs_name.strip_prefix("EPSG:") {
+        (code, false)
+    } else if let Some(code) = srs_name.strip_prefix("http://www.opengis.net/gml/srs/epsg.xml#") {
+        (code, false)
+    } else if let Some(rest) = srs_name.strip_prefix("http://www.opengis.net/def/crs/EPSG/") {
+        (rest.rsplit('/').next().unwrap_or(""), true)
+    } else if let Some(rest) = srs_name
+        .strip_prefix("urn:ogc:def:crs:EPSG:")
+        .or_else(|| srs_name.strip_prefix("urn:x-ogc:def:crs:EPSG:"))
+    {
+        (rest.rsplit(':').next().unwrap_or(""), true)
+    } else {
+        return Err(SqliteGisError::InvalidInput(format!(
+            "unsupported srsName '{srs_name}'"
+        )));
+    };
+    let srid = code
+        .parse::<i32>()
+        .map_err(|_| SqliteGisError::InvalidInput(format!("unsupported srsName '{srs_name}'")))?;
+    Ok(SrsName {
+        srid,
+        lat_lon: authority_axes && is_lat_lon_srid(srid),
+    })
+}
+
+/// Parse a GML 2 or GML 3 geometry element into an EWKB blob, as PostGIS
+/// `ST_GeomFromGML` does.
+///
+/// The SRID is `srid` when given, otherwise the one named by the root
+/// element's srsName, if any. Supported elements are `Point`,
+/// `LineString`, `Curve`, `Polygon`, `Surface`, `MultiPoint`,
+/// `MultiLineString`, `MultiCurve`, `MultiPolygon`, `MultiSurface`,
+/// `MultiGeometry`, `Envelope` and `Box`. Coordinates may be given as
+/// `pos`, `posList`, `coordinates` or `coord`.
+///
+/// # Example
+///
+/// ```
+/// use sqlitegis::core::ewkb::extract_srid;
+/// use sqlitegis::core::functions::io::{as_text, geom_from_gml};
+///
+/// let gml = "<gml:Point srsName=\"EPSG:4326\"><gml:coordinates>1,2</gml:coordinates></gml:Point>";
+/// let blob = geom_from_gml(gml, None).unwrap();
+/// assert_eq!(as_text(&blob).unwrap(), "POINT(1 2)");
+/// assert_eq!(extract_srid(&blob), Some(4326));
+/// ```
+pub fn geom_from_gml(gml: &str, srid: Option<i32>) -> Result<Vec<u8>> {
+    let (geom, srs_name) = with_fragment(gml, |root| {
+        let srs_name = root.attribute("srsName").map(parse_srs_name).transpose()?;
+        let reader = GmlReader { srs_name };
+        Ok((reader.geometry(root)?, srs_name))
+    })?;
+    check_input_vertices(&geom)?;
+    write_ewkb(&geom, srid.or(srs_name.map(|s| s.srid)))
+}
+
+struct GmlReader {
+    srs_name: Option<SrsName>,
+}
+
+impl GmlReader {
+    fn coord(&self, a: f64, b: f64) -> Coord<f64> {
+        if self.srs_name.is_some_and(|s| s.lat_lon) {
+            Coord { x: b, y: a }
+        } else {
+            Coord { x: a, y: b }
+        }
+    }
+
+    /// Read the coordinates held directly by `node`, in any of the GML 2
+    /// and GML 3 encodings.
+    fn coords(&self, node: Node) -> Result<Vec<Coord<f64>>> {
+        let mut out = Vec::new();
+        for element in elements(node) {
+            let text = element.text().unwrap_or("");
+            match name(&element) {
+                "posList" | "pos" | "lowerCorner" | "upperCorner" => {
+                    let dimension = match element
+                        .ancestors()
+                        .find_map(|n| n.attribute("srsDimension"))
+                    {
+                        Some(d) => match d.parse::<usize>() {
+                            Ok(d @ 2..=4) => d,
+                            _ => {
+                                return Err(SqliteGisError::InvalidInput(format!(
+                                    "invalid srsDimension '{d}'"
+                                )))
+                            }
+                        },
+                        None => 2,
+                    };
+                    let values = text
+                        .split_whitespace()
+                        .map(parse_number)
+                        .collect::<Result<Vec<_>>>()?;
+                    if values.len() % dimension != 0 {
+                        return Err(SqliteGisError::InvalidInput(format!(
+                            "<{}> holds {} values, not a multiple of srsDimension {dimension}",
+                            name(&element),
+                            values.len()
+                        )));
+                    }
+                    out.extend(values.chunks(dimension).map(|c| self.coord(c[0], c[1])));
+                }
+                "coordinates" => {
+                    out.extend(
+                        parse_tuples(text)?
+                            .into_iter()
+                            .map(|(a, b)| self.coord(a, b)),
+                    );
+                }
+                "coord" => {
+                    let axis = |axis| -> Result<f64> {
+                        parse_number(required(element, axis)?.text().unwrap_or("").trim())
+                    };
+                    out.push(self.coord(axis("X")?, axis("Y")?));
+                }
+                _ => {}
+            }
+        }
+        Ok(out)
+    }
+
+    fn ring(&self, boundary: Node) -> Result<LineString<f64>> {
+        Ok(LineString::new(
+            self.coords(required(boundary, "LinearRing")?)?,
+        ))
+    }
+
+    fn polygon(&self, node: Node) -> Result<Polygon<f64>> {
+        let mut exterior = None;
+        let mut interiors = Vec::new();
+        for boundary in elements(node) {
+            match name(&boundary) {
+                "exterior" | "outerBoundaryIs" => exterior = Some(self.ring(boundary)?),
+                "interior" | "innerBoundaryIs" => interiors.push(self.ring(boundary)?),
+                _ => {}
+            }
+        }
+        let exterior = exterior.ok_or_else(|| {
+            SqliteGisError::InvalidInput(format!("<{}> has no exterior ring", name(&node)))
+        })?;
+        Ok(Polygon::new(exterior, interiors))
+    }
+
+    /// The geometries under `node`'s `<single>` (one child each) and
+    /// `<plural>` (any number of children) member elements.
+    fn members(&self, node: Node, single: &str, plural: &str) -> Result<Vec<Geometry<f64>>> {
+        let mut out = Vec::new();
+        for member in elements(node) {
+            let member_name = name(&member);
+            if member_name == single {
+                let geom = elements(member)
+                    .next()
+                    .ok_or_else(|| SqliteGisError::InvalidInput(format!("empty <{single}>")))?;
+                out.push(self.geometry(geom)?);
+            } else if member_name == plural {
+                for geom in elements(member) {
+                    out.push(self.geometry(geom)?);
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    fn geometry(&self, node: Node) -> Result<Geometry<f64>> {
+        if let Some(srs_name) = node.attribute("srsName") {
+            if Some(parse_srs_name(srs_name)?) != self.srs_name {
+                return Err(SqliteGisError::InvalidInput(format!(
+                    "srsName '{srs_name}' differs from the root element's"
+                )));
+            }
+        }
+        let element = name(&node);
+        let wrong_member = |member: &Geometry<f64>| {
+            SqliteGisError::InvalidInput(format!(
+                "<{element}> cannot hold a {}",
+                geometry_type_name(member)
+            ))
+        };
+        Ok(match element {
+            "Point" => Geometry::Point(single_point(self.coords(node)?, element)?),
+            "LineString" => Geometry::LineString(LineString::new(self.coords(node)?)),
+            "Curve" => {
+                let mut coords: Vec<Coord<f64>> = Vec::new();
+                for segment in elements(required(node, "segments")?) {
+                    let part = self.coords(segment)?;
+                    // Consecutive segments share their junction vertex.
+                    let skip =
+                        usize::from(coords.last().is_some() && coords.last() == part.first());
+                    coords.extend(part.into_iter().skip(skip));
+                }
+                Geometry::LineString(LineString::new(coords))
+            }
+            "Polygon" => Geometry::Polygon(self.polygon(node)?),
+            "Surface" => {
+                let patches = elements(required(node, "patches")?)
+                    .map(|patch| self.polygon(patch))
+                    .collect::<Result<Vec<_>>>()?;
+                match <[Polygon<f64>; 1]>::try_from(patches) {
+                    Ok([polygon]) => Geometry::Polygon(polygon),
+                    Err(patches) => Geometry::MultiPolygon(MultiPolygon::new(patches)),
+                }
+            }
+            "MultiPoint" => {
+                let mut points = Vec::new();
+                for member in self.members(node, "pointMember", "pointMembers")? {
+                    match member {
+                        Geometry::Point(p) => points.push(p),
+                        other => return Err(wrong_member(&other)),
+                    }
+                }
+                Geometry::MultiPoint(MultiPoint::new(points))
+            }
+            "MultiLineString" | "MultiCurve" => {
+                let (single, plural) = if element == "MultiCurve" {
+                    ("curveMember", "curveMembers")
+                } else {
+                    ("lineStringMember", "lineStringMembers")
+                };
+                let mut lines = Vec::new();
+                for member in self.members(node, single, plural)? {
+                    match member {
+                        Geometry::LineString(ls) => lines.push(ls),
+                        other => return Err(wrong_member(&other)),
+                    }
+                }
+                Geometry::MultiLineString(MultiLineString::new(lines))
+            }
+            "MultiPolygon" | "MultiSurface" => {
+                let (single, plural) = if element == "MultiSurface" {
+                    ("surfaceMember", "surfaceMembers")
+                } else {
+                    ("polygonMember", "polygonMembers")
+                };
+                let mut polygons = Vec::new();
+                for member in self.members(node, single, plural)? {
+                    match member {
+                        Geometry::Polygon(polygon) => polygons.push(polygon),
+                        Geometry::MultiPolygon(mp) => polygons.extend(mp.0),
+                        other => return Err(wrong_member(&other)),
+                    }
+                }
+                Geometry::MultiPolygon(MultiPolygon::new(polygons))
+            }
+            "MultiGeometry" => Geometry::GeometryCollection(GeometryCollection::from(
+                self.members(node, "geometryMember", "geometryMembers")?,
+            )),
+            "Envelope" | "Box" => match self.coords(node)?[..] {
+                [a, b] => Geometry::Polygon(Rect::new(a, b).to_polygon()),
+                _ => {
+                    return Err(SqliteGisError::InvalidInput(format!(
+                        "<{element}> must have exactly two corners"
+                    )))
+                }
+            },
+            other => {
+                return Err(SqliteGisError::InvalidInput(format!(
+                    "unsupported GML element <{other}>"
+                )))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ewkb::extract_srid;
+    use crate::core::functions::io::{as_text, geom_from_text};
+
+    fn gml(version: i32, wkt: &str, srid: Option<i32>, options: i32) -> String {
+        let blob = geom_from_text(wkt, srid).unwrap();
+        as_gml(version, &blob, 15, options).unwrap().unwrap()
+    }
+
+    #[test]
+    fn as_kml_writes_every_geometry_type() {
+        let kml = |wkt, precision| as_kml(&geom_from_text(wkt, None).unwrap(), precision).unwrap();
+        assert_eq!(
+            kml("POLYGON((0 0,4 0,4 4,0 0),(1 1,2 1,2 2,1 1))", 15).unwrap(),
+            "<Polygon><outerBoundaryIs><LinearRing><coordinates>0,0 4,0 4,4 0,0</coordinates>\
+             </LinearRing></outerBoundaryIs><innerBoundaryIs><LinearRing><coordinates>\
+             1,1 2,1 2,2 1,1</coordinates></LinearRing></innerBoundaryIs></Polygon>"
+        );
+        assert_eq!(
+            kml(
+                "GEOMETRYCOLLECTION(POINT(-0.0000001 1),LINESTRING EMPTY)",
+                6
+            )
+            .unwrap(),
+            "<MultiGeometry><Point><coordinates>0,1</coordinates></Point></MultiGeometry>"
+        );
+        assert_eq!(kml("POINT EMPTY", 15), None);
+    }
+
+    #[test]
+    fn as_kml_rejects_projected_srids_and_bad_precision() {
+        let projected = geom_from_text("POINT(1 2)", Some(3857)).unwrap();
+        assert!(matches!(
+            as_kml(&projected, 15),
+            Err(SqliteGisError::InvalidInput(_))
+        ));
+        let wgs84 = geom_from_text("POINT(1 2)", Some(4326)).unwrap();
+        for precision in [-1, 16] {
+            assert!(matches!(
+                as_kml(&wgs84, precision),
+                Err(SqliteGisError::InvalidInput(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn as_gml_follows_postgis_layouts_and_options() {
+        assert_eq!(
+            gml(3, "LINESTRING(1 2,3 4)", None, 0),
+            "<gml:Curve><gml:segments><gml:LineStringSegment>\
+             <gml:posList srsDimension=\"2\">1 2 3 4</gml:posList>\
+             </gml:LineStringSegment></gml:segments></gml:Curve>"
+        );
+        assert_eq!(
+            gml(3, "LINESTRING(1 2,3 4)", Some(4326), GML_LINESTRING | GML_NO_SRS_DIMENSION),
+            "<gml:LineString srsName=\"EPSG:4326\"><gml:posList>1 2 3 4</gml:posList></gml:LineString>"
+        );
+        assert_eq!(
+            gml(2, "MULTIPOINT((1 2),(3 4))", Some(4326), 0),
+            "<gml:MultiPoint srsName=\"EPSG:4326\"><gml:pointMember><gml:Point>\
+             <gml:coordinates>1,2</gml:coordinates></gml:Point></gml:pointMember>\
+             <gml:pointMember><gml:Point><gml:coordinates>3,4</gml:coordinates></gml:Point>\
+             </gml:pointMember></gml:MultiPoint>"
+        );
+        assert_eq!(
+            gml(
+                3,
+                "LINESTRING(1 2,3 4)",
+                Some(4326),
+                GML_LONG_CRS | GML_ENVELOPE
+            ),
+            "<gml:Envelope srsName=\"urn:ogc:def:crs:EPSG::4326\" srsDimension=\"2\">\
+             <gml:lowerCorner>2 1</gml:lowerCorner><gml:upperCorner>4 3</gml:upperCorner>\
+             </gml:Envelope>"
+        );
+        // Projected CRSs keep x/y order under a URN srsName.
+        assert_eq!(
+            gml(3, "POINT(1 2)", Some(3857), GML_LONG_CRS),
+            "<gml:Point srsName=\"urn:ogc:def:crs:EPSG::3857\">\
+             <gml:pos srsDimension=\"2\">1 2</gml:pos></gml:Point>"
+        );
+        assert!(matches!(
+            as_gml(4, &geom_from_text("POINT(1 2)", None).unwrap(), 15, 0),
+            Err(SqliteGisError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn gml_round_trips_every_geometry_type() {
+        for wkt in [
+            "POINT(13.4 52.5)",
+            "LINESTRING(0 0,1 1,2 0)",
+            "POLYGON((0 0,4 0,4 4,0 4,0 0),(1 1,2 1,2 2,1 1))",
+            "MULTIPOINT(0 0,1 1)",
+            "MULTILINESTRING((0 0,1 1),(2 2,3 3))",
+            "MULTIPOLYGON(((0 0,1 0,1 1,0 0)),((5 5,6 5,6 6,5 5)))",
+            "GEOMETRYCOLLECTION(POINT(1 1),LINESTRING(0 0,2 2))",
+        ] {
+            let blob = geom_from_text(wkt, Some(4326)).unwrap();
+            for version in [2, 3] {
+                for options in [0, GML_LONG_CRS, GML_LINESTRING] {
+                    let gml = as_gml(version, &blob, 15, options).unwrap().unwrap();
+                    let back = geom_from_gml(&gml, None).unwrap();
+                    assert_eq!(as_text(&back).unwrap(), wkt, "{gml}");
+                    assert_eq!(extract_srid(&back), Some(4326), "{gml}");
+                }
+            }
+            let kml = as_kml(&blob, 15).unwrap().unwrap();
+            assert_eq!(
+                as_text(&geom_from_kml(&kml).unwrap()).unwrap(),
+                wkt,
+                "{kml}"
+            );
+        }
+    }
+
+    #[test]
+    fn geom_from_gml_reads_srs_names_and_foreign_layouts() {
+        let read = |gml: &str| {
+            let blob = geom_from_gml(gml, None).unwrap();
+            (as_text(&blob).unwrap(), extract_srid(&blob))
+        };
+        assert_eq!(
+            read(
+                "<gml:Point xmlns:gml=\"http://www.opengis.net/gml/3.2\" \
+                 srsName=\"http://www.opengis.net/def/crs/EPSG/0/4326\">\
+                 <gml:pos>52.5 13.4</gml:pos></gml:Point>"
+            ),
+            ("POINT(13.4 52.5)".to_string(), Some(4326))
+        );
+        assert_eq!(
+            read(
+                "<Point srsName=\"http://www.opengis.net/gml/srs/epsg.xml#4326\">\
+                 <coord><X>13.4</X><Y>52.5</Y></coord></Point>"
+            ),
+            ("POINT(13.4 52.5)".to_string(), Some(4326))
+        );
+        assert_eq!(
+            read(
+                "<gml:LineString srsDimension=\"3\"><gml:posList>0 0 9 1 1 9</gml:posList>\
+                 </gml:LineString>"
+            ),
+            ("LINESTRING(0 0,1 1)".to_string(), None)
+        );
+        assert_eq!(
+            read(
+                "<gml:Envelope srsName=\"EPSG:3857\"><gml:lowerCorner>0 0</gml:lowerCorner>\
+                 <gml:upperCorner>2 1</gml:upperCorner></gml:Envelope>"
+            )
+            .1,
+            Some(3857)
+        );
+        // An explicit SRID wins over the srsName.
+        let blob = geom_from_gml(
+            "<gml:Point srsName=\"EPSG:4326\"><gml:pos>1 2</gml:pos></gml:Point>",
+            Some(3857),
+        )
+        .unwrap();
+        assert_eq!(extract_srid(&blob), Some(3857));
+    }
+
+    #[test]
+    fn xml_readers_reject_malformed_input() {
+        for gml in [
+            "",
+            "<gml:Point><gml:pos>1 2</gml:pos>",
+            "<gml:Point><gml:pos>1 2</gml:pos></gml:Point><gml:Point/>",
+            "<gml:Point><gml:pos>1 x</gml:pos></gml:Point>",
+            "<gml:Point><gml:pos>1 2 3</gml:pos></gml:Point>",
+            "<gml:Point srsName=\"CRS:84\"><gml:pos>1 2</gml:pos></gml:Point>",
+            "<gml:Polygon><gml:interior/></gml:Polygon>",
+            "<gml:MultiPoint><gml:pointMember><gml:LineString><gml:posList>0 0 1 1</gml:posList>\
+             </gml:LineString></gml:pointMember></gml:MultiPoint>",
+            "<gml:Solid/>",
+        ] {
+            assert!(
+                matches!(
+                    geom_from_gml(gml, None),
+                    Err(SqliteGisError::InvalidInput(_))
+                ),
+                "{gml}"
+            );
+        }
+        for kml in [
+            "<Placemark/>",
+            "<Point><coordinates>1</coordinates></Point>",
+            "<Polygon><innerBoundaryIs/></Polygon>",
+        ] {
+            assert!(
+                matches!(geom_from_kml(kml), Err(SqliteGisError::InvalidInput(_))),
+                "{kml}"
+            );
+        }
+    }
+
+    #[test]
+    fn as_svg_matches_postgis_output() {
+        let svg = |wkt, relative, precision| {
+            as_svg(
+                &geom_from_text(wkt, Some(4326)).unwrap(),
+                relative,
+                precision,
+            )
+            .unwrap()
+        };
+        assert_eq!(svg("POINT(1 2)", false, 15), "cx=\"1\" cy=\"-2\"");
+        assert_eq!(svg("POINT(1 0)", true, 15), "x=\"1\" y=\"0\"");
+        assert_eq!(
+            svg("MULTIPOINT(1 2,3 4)", false, 15),
+            "cx=\"1\" cy=\"-2\",cx=\"3\" cy=\"-4\""
+        );
+        assert_eq!(
+            svg("LINESTRING(1 2,3 4,5 6)", false, 15),
+            "M 1 -2 L 3 -4 5 -6"
+        );
+        assert_eq!(
+            svg("LINESTRING(1 2,3 4,5 6)", true, 15),
+            "M 1 -2 l 2 -2 2 -2"
+        );
+        // Relative moves are taken between rounded vertices, so they add up.
+        assert_eq!(
+            svg("LINESTRING(0.04 0,0.16 0,0.28 0)", true, 1),
+            "M 0 0 l 0.2 0 0.1 0"
+        );
+        assert_eq!(
+            svg(
+                "MULTIPOLYGON(((0 0,2 0,2 2,0 0)),((5 5,6 5,6 6,5 5)))",
+                false,
+                15
+            ),
+            "M 0 0 L 2 0 2 -2 Z M 5 -5 L 6 -5 6 -6 Z"
+        );
+        assert_eq!(
+            svg(
+                "GEOMETRYCOLLECTION(POINT(1 1),LINESTRING(0 0,1.23456 1))",
+                false,
+                2
+            ),
+            "cx=\"1\" cy=\"-1\";M 0 0 L 1.23 -1"
+        );
+        assert_eq!(svg("POLYGON EMPTY", false, 15), "");
+        // Empty members are skipped without shifting later coordinates.
+        assert_eq!(
+            svg(
+                "GEOMETRYCOLLECTION(LINESTRING EMPTY,POLYGON((0 0,1 0,1 1,0 0),(0.2 0.1,0.8 0.1,0.8 0.7,0.2 0.1)),MULTILINESTRING(EMPTY,(3 4,5 6)))",
+                true,
+                15
+            ),
+            "M 0 0 l 1 0 0 -1 z M 0.2 -0.1 l 0.6 0 0 -0.6 z;M 3 -4 l 2 -2"
+        );
+        let blob = geom_from_text("POINT(1 2)", None).unwrap();
+        assert!(matches!(
+            as_svg(&blob, false, 16),
+            Err(SqliteGisError::InvalidInput(_))
+        ));
+    }
+}
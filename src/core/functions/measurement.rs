@@ -8,15 +8,16 @@
 use geo::algorithm::line_measures::metric_spaces::{Euclidean, Geodesic, Haversine};
 use geo::algorithm::line_measures::{Bearing, Destination, Distance, Length};
 use geo::algorithm::InteriorPoint;
-use geo::algorithm::{Area, BoundingRect, Centroid, ClosestPoint, HausdorffDistance};
+use geo::algorithm::{Area, BoundingRect, Centroid, ClosestPoint, CoordsIter};
 use geo::Closest;
-use geo::{Geometry, Point, Rect};
+use geo::{Coord, Geometry, Point, Rect};
 
-use crate::core::error::{Result, SqliteGisError};
+use crate::core::error::{on_operand, Result, SqliteGisError};
 use crate::core::ewkb::{ensure_matching_srid, parse_ewkb, parse_ewkb_pair, write_ewkb};
 use crate::core::functions::emptiness::is_empty_geometry;
+use crate::core::interrupt::{check, Interrupt, NeverInterrupt};
 
-fn require_non_empty_geometry(geom: &Geometry<f64>, fn_name: &str) -> Result<()> {
+pub(crate) fn require_non_empty_geometry(geom: &Geometry<f64>, fn_name: &str) -> Result<()> {
     if is_empty_geometry(geom) {
         return Err(SqliteGisError::InvalidInput(format!(
             "{fn_name} does not accept empty geometries"
@@ -105,7 +106,7 @@ pub fn st_perimeter(blob: &[u8]) -> Result<f64> {
 }
 
 /// Dispatch euclidean distance between any two geo geometry types.
-fn euclidean_geometry_distance(a: &Geometry<f64>, b: &Geometry<f64>) -> f64 {
+pub(crate) fn euclidean_geometry_distance(a: &Geometry<f64>, b: &Geometry<f64>) -> f64 {
     Euclidean.distance(a, b)
 }
 
@@ -123,8 +124,8 @@ fn euclidean_geometry_distance(a: &Geometry<f64>, b: &Geometry<f64>) -> f64 {
 /// ```
 pub fn st_distance(a: &[u8], b: &[u8]) -> Result<f64> {
     let (ga, gb, _) = parse_ewkb_pair(a, b)?;
-    require_non_empty_geometry(&ga, "ST_Distance")?;
-    require_non_empty_geometry(&gb, "ST_Distance")?;
+    on_operand(0, require_non_empty_geometry(&ga, "ST_Distance"))?;
+    on_operand(1, require_non_empty_geometry(&gb, "ST_Distance"))?;
     Ok(euclidean_geometry_distance(&ga, &gb))
 }
 
@@ -185,10 +186,60 @@ pub fn st_point_on_surface(blob: &[u8]) -> Result<Vec<u8>> {
 /// assert!((st_hausdorff_distance(&a, &b).unwrap() - 1.0).abs() < 1e-10);
 /// ```
 pub fn st_hausdorff_distance(a: &[u8], b: &[u8]) -> Result<f64> {
+    st_hausdorff_distance_interruptible(a, b, &NeverInterrupt)
+}
+
+/// [`st_hausdorff_distance`] that polls `interrupt` once per vertex of the
+/// outer loop and returns [`SqliteGisError::Interrupted`] once it fires.
+///
+/// # Example
+///
+/// ```
+/// use sqlitegis::SqliteGisError;
+/// use sqlitegis::core::functions::measurement::st_hausdorff_distance_interruptible;
+/// use sqlitegis::core::functions::io::geom_from_text;
+///
+/// let a = geom_from_text("LINESTRING(0 0,1 0)", None).unwrap();
+/// let b = geom_from_text("LINESTRING(0 1,1 1)", None).unwrap();
+/// let d = st_hausdorff_distance_interruptible(&a, &b, &|| false).unwrap();
+/// assert!((d - 1.0).abs() < 1e-10);
+/// let err = st_hausdorff_distance_interruptible(&a, &b, &|| true).unwrap_err();
+/// assert!(matches!(err, SqliteGisError::Interrupted));
+/// ```
+pub fn st_hausdorff_distance_interruptible(
+    a: &[u8],
+    b: &[u8],
+    interrupt: &dyn Interrupt,
+) -> Result<f64> {
+    check(interrupt)?;
     let (ga, gb, _) = parse_ewkb_pair(a, b)?;
-    require_non_empty_geometry(&ga, "ST_HausdorffDistance")?;
-    require_non_empty_geometry(&gb, "ST_HausdorffDistance")?;
-    Ok(ga.hausdorff_distance(&gb))
+    on_operand(0, require_non_empty_geometry(&ga, "ST_HausdorffDistance"))?;
+    on_operand(1, require_non_empty_geometry(&gb, "ST_HausdorffDistance"))?;
+    let ca: Vec<Coord<f64>> = ga.coords_iter().collect();
+    let cb: Vec<Coord<f64>> = gb.coords_iter().collect();
+    let hd1 = directed_hausdorff(&ca, &cb, interrupt)?;
+    let hd2 = directed_hausdorff(&cb, &ca, interrupt)?;
+    Ok(hd1.max(hd2))
+}
+
+/// Max over `from` of the distance to the nearest vertex of `to`. Same
+/// vertex-to-vertex formula as geo's `HausdorffDistance`, unrolled so the
+/// outer loop can poll `interrupt`.
+fn directed_hausdorff(
+    from: &[Coord<f64>],
+    to: &[Coord<f64>],
+    interrupt: &dyn Interrupt,
+) -> Result<f64> {
+    let mut max = f64::MIN;
+    for &c in from {
+        check(interrupt)?;
+        let nearest = to
+            .iter()
+            .map(|&c2| Euclidean.distance(c, c2))
+            .fold(f64::MAX, f64::min);
+        max = max.max(nearest);
+    }
+    Ok(max)
 }
 
 // Bounding-box accessors
@@ -277,10 +328,10 @@ fn bbox(blob: &[u8]) -> Result<Option<Rect<f64>>> {
 
 // Spherical / geodetic variants
 
-fn require_point(g: Geometry<f64>) -> Result<Point<f64>> {
+fn require_point(g: &Geometry<f64>) -> Result<Point<f64>> {
     match g {
-        Geometry::Point(p) => Ok(p),
-        other => Err(SqliteGisError::wrong_type("Point", &other)),
+        Geometry::Point(p) => Ok(*p),
+        other => Err(SqliteGisError::wrong_type("Point", other)),
     }
 }
 
@@ -311,11 +362,30 @@ fn parse_two_geographic_points(
     b: &[u8],
     fn_name: &str,
 ) -> Result<(Point<f64>, Point<f64>, Option<i32>)> {
-    let (ga, srid_a) = parse_ewkb(a)?;
-    let (gb, srid_b) = parse_ewkb(b)?;
+    let (ga, srid_a) = on_operand(0, parse_ewkb(a))?;
+    let (gb, srid_b) = on_operand(1, parse_ewkb(b))?;
+    geographic_point_pair(&ga, srid_a, &gb, srid_b, fn_name)
+}
+
+/// Second half of [`parse_two_geographic_points`], split out so callers
+/// that already hold decoded geometries (e.g. `core::batch`) run the exact
+/// same SRID and Point checks in the same order.
+pub(crate) fn geographic_point_pair(
+    ga: &Geometry<f64>,
+    srid_a: Option<i32>,
+    gb: &Geometry<f64>,
+    srid_b: Option<i32>,
+    fn_name: &str,
+) -> Result<(Point<f64>, Point<f64>, Option<i32>)> {
     let srid = ensure_matching_geographic_srid(srid_a, srid_b, fn_name)?;
-    let pa = require_non_empty_point(require_point(ga)?, fn_name)?;
-    let pb = require_non_empty_point(require_point(gb)?, fn_name)?;
+    let pa = on_operand(
+        0,
+        require_point(ga).and_then(|p| require_non_empty_point(p, fn_name)),
+    )?;
+    let pb = on_operand(
+        1,
+        require_point(gb).and_then(|p| require_non_empty_point(p, fn_name)),
+    )?;
     Ok((pa, pb, srid))
 }
 
@@ -427,7 +497,7 @@ pub fn st_project(origin: &[u8], distance: f64, azimuth: f64) -> Result<Vec<u8>>
     }
     let (go, srid) = parse_ewkb(origin)?;
     ensure_geographic_srid(srid, "ST_Project")?;
-    let po = require_non_empty_point(require_point(go)?, "ST_Project")?;
+    let po = require_non_empty_point(require_point(&go)?, "ST_Project")?;
     let dest: Point<f64> = Geodesic.destination(po, azimuth.to_degrees(), distance);
     write_ewkb(&Geometry::Point(dest), srid)
 }
@@ -450,8 +520,11 @@ pub fn st_project(origin: &[u8], distance: f64, azimuth: f64) -> Result<Vec<u8>>
 /// ```
 pub fn st_closest_point(a: &[u8], b: &[u8]) -> Result<Vec<u8>> {
     let (ga, gb, srid) = parse_ewkb_pair(a, b)?;
-    require_non_empty_geometry(&ga, "ST_ClosestPoint")?;
-    let pb = require_non_empty_point(require_point(gb)?, "ST_ClosestPoint")?;
+    on_operand(0, require_non_empty_geometry(&ga, "ST_ClosestPoint"))?;
+    let pb = on_operand(
+        1,
+        require_point(&gb).and_then(|p| require_non_empty_point(p, "ST_ClosestPoint")),
+    )?;
     let cp = ga.closest_point(&pb);
     let pt = match cp {
         Closest::Intersection(p) | Closest::SinglePoint(p) => p,
@@ -647,6 +720,41 @@ mod tests {
         assert!(format!("{err}").contains("does not accept empty geometries"));
     }
 
+    #[test]
+    fn hausdorff_matches_geo() {
+        use geo::HausdorffDistance;
+        let cases = [
+            ("LINESTRING(0 0,1 0,5 3)", "LINESTRING(0 1,1 1,2 7)"),
+            ("POLYGON((0 0,4 0,4 4,0 4,0 0))", "MULTIPOINT((1 1),(9 9))"),
+            ("POINT(3 4)", "LINESTRING(0 0,10 0)"),
+        ];
+        for (wa, wb) in cases {
+            let a = geom_from_text(wa, None).unwrap();
+            let b = geom_from_text(wb, None).unwrap();
+            let (ga, _) = crate::core::ewkb::parse_ewkb(&a).unwrap();
+            let (gb, _) = crate::core::ewkb::parse_ewkb(&b).unwrap();
+            assert_eq!(
+                st_hausdorff_distance(&a, &b).unwrap(),
+                ga.hausdorff_distance(&gb)
+            );
+        }
+    }
+
+    #[test]
+    fn hausdorff_interrupt_stops_outer_loop() {
+        use std::cell::Cell;
+        let coords: Vec<String> = (0..100).map(|i| format!("{i} 0")).collect();
+        let line = geom_from_text(&format!("LINESTRING({})", coords.join(",")), None).unwrap();
+        let polls = Cell::new(0);
+        let hook = || {
+            polls.set(polls.get() + 1);
+            polls.get() > 10
+        };
+        let err = st_hausdorff_distance_interruptible(&line, &line, &hook).unwrap_err();
+        assert!(matches!(err, SqliteGisError::Interrupted));
+        assert_eq!(polls.get(), 11);
+    }
+
     // -- Closest point ----------------------------------------------
 
     #[test]
@@ -11,6 +11,8 @@
 //! - [`crate::core::functions::measurement`] -- numeric measurements
 //!   (`st_distance`, `st_area`, `st_length`, `st_distance_sphere`,
 //!   `st_distance_spheroid`, ...).
+//! - [`crate::core::functions::mvt`] -- Mapbox Vector Tile output
+//!   (`st_as_mvt_geom`, [`MvtLayer`](crate::core::functions::mvt::MvtLayer)).
 //! - [`crate::core::functions::operations`] -- derive new geometries from
 //!   existing ones (`st_buffer`, `st_union`, `st_intersection`,
 //!   `st_difference`, ...).
@@ -30,5 +32,6 @@ pub mod constructors;
 pub(crate) mod emptiness;
 pub mod io;
 pub mod measurement;
+pub mod mvt;
 pub mod operations;
 pub mod predicates;
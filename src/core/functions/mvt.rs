@@ -0,0 +1,984 @@
+//! Mapbox Vector Tile output
+//!
+//! ST_AsMVTGeom, ST_AsMVT
+//!
+//! [`st_as_mvt_geom`] moves a geometry into the integer coordinate space of
+//! one tile, and [`MvtLayer`] encodes such geometries plus their
+//! properties as a [Mapbox Vector Tile] (protobuf, spec version 2). A tile
+//! with several layers is the concatenation of single-layer tiles, exactly
+//! as with PostGIS; in SQLite `||` returns TEXT, so write
+//! `CAST(ST_AsMVT(a) || ST_AsMVT(b) AS BLOB)`.
+//!
+//! ```
+//! use sqlitegis::core::functions::constructors::st_tile_envelope;
+//! use sqlitegis::core::functions::io::geom_from_text;
+//! use sqlitegis::core::functions::mvt::{st_as_mvt_geom, MvtLayer, DEFAULT_BUFFER, DEFAULT_EXTENT};
+//!
+//! let bounds = st_tile_envelope(0, 0, 0).unwrap();
+//! let road = geom_from_text("LINESTRING(-1000000 0,1000000 500000)", Some(3857)).unwrap();
+//! let tile_geom = st_as_mvt_geom(&road, &bounds, DEFAULT_EXTENT, DEFAULT_BUFFER, true)
+//!     .unwrap()
+//!     .unwrap();
+//!
+//! let mut layer = MvtLayer::new("roads", DEFAULT_EXTENT);
+//! assert!(layer.add_feature(&tile_geom, [("name", "A1".into())]).unwrap());
+//! let tile: Vec<u8> = layer.encode();
+//! assert!(!tile.is_empty());
+//! ```
+//!
+//! [Mapbox Vector Tile]: https://github.com/mapbox/vector-tile-spec
+
+use std::collections::HashMap;
+
+use geo::algorithm::bool_ops::BooleanOps;
+use geo::{
+    BoundingRect, Coord, Geometry, LineString, MapCoords, MultiLineString, MultiPoint,
+    MultiPolygon, Point, Polygon, Rect,
+};
+
+use crate::core::error::{on_operand, Result, SqliteGisError};
+use crate::core::ewkb::{decode_hex, parse_ewkb, write_ewkb};
+use crate::core::functions::emptiness::is_empty_point;
+
+/// Tile extent used by PostGIS when none is given.
+pub const DEFAULT_EXTENT: u32 = 4096;
+
+/// Clip buffer, in tile units, used by PostGIS when none is given.
+pub const DEFAULT_BUFFER: u32 = 256;
+
+/// Points, lines and polygons pulled out of a possibly nested geometry.
+#[derive(Default)]
+struct Parts {
+    points: Vec<Point<f64>>,
+    lines: Vec<LineString<f64>>,
+    polygons: Vec<Polygon<f64>>,
+}
+
+impl Parts {
+    fn collect(&mut self, geom: Geometry<f64>) {
+        match geom {
+            Geometry::Point(p) => {
+                if !is_empty_point(&p) {
+                    self.points.push(p);
+                }
+            }
+            Geometry::MultiPoint(mp) => {
+                self.points
+                    .extend(mp.0.into_iter().filter(|p| !is_empty_point(p)));
+            }
+            Geometry::Line(l) => self.lines.push(l.into()),
+            Geometry::LineString(ls) => self.lines.push(ls),
+            Geometry::MultiLineString(mls) => self.lines.extend(mls.0),
+            Geometry::Polygon(p) => self.polygons.push(p),
+            Geometry::MultiPolygon(mp) => self.polygons.extend(mp.0),
+            Geometry::Rect(r) => self.polygons.push(r.to_polygon()),
+            Geometry::Triangle(t) => self.polygons.push(t.to_polygon()),
+            Geometry::GeometryCollection(gc) => {
+                for g in gc.0 {
+                    self.collect(g);
+                }
+            }
+        }
+    }
+
+    /// Keep only the parts of the highest dimension present, which is what
+    /// a single MVT feature can hold.
+    fn keep_highest_dimension(&mut self) {
+        if !self.polygons.is_empty() {
+            self.points.clear();
+            self.lines.clear();
+        } else if !self.lines.is_empty() {
+            self.points.clear();
+        }
+    }
+}
+
+/// Twice the signed area of a ring (shoelace formula). Positive means
+/// counter-clockwise with the y axis up, which is clockwise on screen in
+/// tile coordinates and marks an MVT exterior ring.
+fn ring_area2(coords: &[Coord<f64>]) -> f64 {
+    coords
+        .windows(2)
+        .map(|w| w[0].x * w[1].y - w[1].x * w[0].y)
+        .sum()
+}
+
+/// Round a ring or line onto the integer grid and drop consecutive
+/// duplicates created by the rounding.
+fn snap(coords: &[Coord<f64>]) -> Vec<Coord<f64>> {
+    let mut out: Vec<Coord<f64>> = Vec::with_capacity(coords.len());
+    for c in coords {
+        let snapped = Coord {
+            x: c.x.round(),
+            y: c.y.round(),
+        };
+        if out.last() != Some(&snapped) {
+            out.push(snapped);
+        }
+    }
+    out
+}
+
+/// Snap a closed ring; `None` when it collapsed to zero area.
+fn snap_ring(ring: &LineString<f64>, exterior: bool) -> Option<LineString<f64>> {
+    let mut coords = snap(&ring.0);
+    if coords.len() < 4 {
+        return None;
+    }
+    let area = ring_area2(&coords);
+    if area == 0.0 {
+        return None;
+    }
+    if (area > 0.0) != exterior {
+        coords.reverse();
+    }
+    Some(LineString(coords))
+}
+
+fn snap_polygon(polygon: &Polygon<f64>) -> Option<Polygon<f64>> {
+    let exterior = snap_ring(polygon.exterior(), true)?;
+    let interiors = polygon
+        .interiors()
+        .iter()
+        .filter_map(|ring| snap_ring(ring, false))
+        .collect();
+    Some(Polygon::new(exterior, interiors))
+}
+
+/// Bounding box of the `bounds` geometry of [`st_as_mvt_geom`], which must
+/// have a positive area.
+fn tile_bounds(bounds: &[u8]) -> Result<Rect<f64>> {
+    let (bounds, _) = parse_ewkb(bounds)?;
+    let Some(rect) = bounds.bounding_rect() else {
+        return Err(SqliteGisError::InvalidInput(
+            "bounds must not be empty".to_string(),
+        ));
+    };
+    if !(rect.width() > 0.0 && rect.height() > 0.0) {
+        return Err(SqliteGisError::InvalidInput(
+            "bounds must have positive width and height".to_string(),
+        ));
+    }
+    Ok(rect)
+}
+
+/// ST_AsMVTGeom: transform a geometry into the coordinate space of the
+/// tile covering `bounds`, ready for [`MvtLayer::add_feature`].
+///
+/// The geometry is scaled so `bounds` maps onto `0..extent` with the y axis
+/// pointing down, clipped (when `clip` is set) to the tile grown by
+/// `buffer` units on every side, snapped to the integer grid, and stripped
+/// of parts that collapsed to nothing. Collections keep only their parts
+/// of the highest dimension. Polygon rings are wound the way the MVT spec
+/// requires. Returns `None` when nothing is left, like PostGIS returns
+/// NULL. The result carries no SRID.
+///
+/// `bounds` is usually `ST_TileEnvelope(z, x, y)`; only its bounding box
+/// is used, and it must be in the same coordinate system as `geom`.
+///
+/// # Example
+///
+/// ```
+/// use sqlitegis::core::functions::constructors::st_make_envelope;
+/// use sqlitegis::core::functions::io::{as_text, geom_from_text};
+/// use sqlitegis::core::functions::mvt::st_as_mvt_geom;
+///
+/// let bounds = st_make_envelope(0.0, 0.0, 100.0, 100.0, None).unwrap();
+/// let line = geom_from_text("LINESTRING(10 10,50.2 90,500 90)", None).unwrap();
+/// let out = st_as_mvt_geom(&line, &bounds, 100, 10, true).unwrap().unwrap();
+/// assert_eq!(as_text(&out).unwrap(), "LINESTRING(10 90,50 10,110 10)");
+///
+/// let far = geom_from_text("POINT(1000 1000)", None).unwrap();
+/// assert!(st_as_mvt_geom(&far, &bounds, 100, 10, true).unwrap().is_none());
+/// ```
+pub fn st_as_mvt_geom(
+    geom: &[u8],
+    bounds: &[u8],
+    extent: u32,
+    buffer: u32,
+    clip: bool,
+) -> Result<Option<Vec<u8>>> {
+    if extent == 0 {
+        return Err(SqliteGisError::InvalidInput(
+            "extent must be positive".to_string(),
+        ));
+    }
+    let (geom, _) = on_operand(0, parse_ewkb(geom))?;
+    let rect = on_operand(1, tile_bounds(bounds))?;
+
+    let extent = f64::from(extent);
+    let sx = extent / rect.width();
+    let sy = extent / rect.height();
+    let (xmin, ymax) = (rect.min().x, rect.max().y);
+    let tile = geom.map_coords(|c| Coord {
+        x: (c.x - xmin) * sx,
+        y: (ymax - c.y) * sy,
+    });
+
+    let mut parts = Parts::default();
+    parts.collect(tile);
+    parts.keep_highest_dimension();
+
+    if clip {
+        let lo = -f64::from(buffer);
+        let hi = extent + f64::from(buffer);
+        let window = Rect::new(Coord { x: lo, y: lo }, Coord { x: hi, y: hi }).to_polygon();
+        parts.points.retain(|p| {
+            let (x, y) = p.x_y();
+            (lo..=hi).contains(&x) && (lo..=hi).contains(&y)
+        });
+        if !parts.lines.is_empty() {
+            let lines = MultiLineString::new(std::mem::take(&mut parts.lines));
+            parts.lines = window.clip(&lines, false).0;
+        }
+        if !parts.polygons.is_empty() {
+            let polygons = MultiPolygon::new(std::mem::take(&mut parts.polygons));
+            parts.polygons = polygons.intersection(&window).0;
+        }
+    }
+
+    let points: Vec<Point<f64>> = parts
+        .points
+        .iter()
+        .map(|p| Point::new(p.x().round(), p.y().round()))
+        .collect();
+    let lines: Vec<LineString<f64>> = parts
+        .lines
+        .iter()
+        .map(|l| LineString(snap(&l.0)))
+        .filter(|l| l.0.len() >= 2)
+        .collect();
+    let polygons: Vec<Polygon<f64>> = parts.polygons.iter().filter_map(snap_polygon).collect();
+
+    let out = if !polygons.is_empty() {
+        match <[Polygon<f64>; 1]>::try_from(polygons) {
+            Ok([p]) => Geometry::Polygon(p),
+            Err(many) => Geometry::MultiPolygon(MultiPolygon::new(many)),
+        }
+    } else if !lines.is_empty() {
+        match <[LineString<f64>; 1]>::try_from(lines) {
+            Ok([l]) => Geometry::LineString(l),
+            Err(many) => Geometry::MultiLineString(MultiLineString::new(many)),
+        }
+    } else if !points.is_empty() {
+        match <[Point<f64>; 1]>::try_from(points) {
+            Ok([p]) => Geometry::Point(p),
+            Err(many) => Geometry::MultiPoint(MultiPoint::new(many)),
+        }
+    } else {
+        return Ok(None);
+    };
+    write_ewkb(&out, None).map(Some)
+}
+
+/// A feature property value, one of the MVT `Value` variants.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MvtValue {
+    /// `string_value`.
+    String(String),
+    /// `double_value`.
+    Double(f64),
+    /// Signed integer, written as `uint_value` when non-negative and as
+    /// `sint_value` otherwise.
+    Int(i64),
+    /// `uint_value`.
+    UInt(u64),
+    /// `bool_value`.
+    Bool(bool),
+}
+
+impl From<&str> for MvtValue {
+    fn from(v: &str) -> Self {
+        MvtValue::String(v.to_string())
+    }
+}
+
+impl From<String> for MvtValue {
+    fn from(v: String) -> Self {
+        MvtValue::String(v)
+    }
+}
+
+impl From<f64> for MvtValue {
+    fn from(v: f64) -> Self {
+        MvtValue::Double(v)
+    }
+}
+
+impl From<i64> for MvtValue {
+    fn from(v: i64) -> Self {
+        MvtValue::Int(v)
+    }
+}
+
+impl From<u64> for MvtValue {
+    fn from(v: u64) -> Self {
+        MvtValue::UInt(v)
+    }
+}
+
+impl From<bool> for MvtValue {
+    fn from(v: bool) -> Self {
+        MvtValue::Bool(v)
+    }
+}
+
+/// Hashable identity of an [`MvtValue`], used to share one entry of the
+/// layer's value table between features.
+#[derive(PartialEq, Eq, Hash)]
+enum ValueKey {
+    String(String),
+    Double(u64),
+    Int(i64),
+    UInt(u64),
+    Bool(bool),
+}
+
+impl From<&MvtValue> for ValueKey {
+    fn from(v: &MvtValue) -> Self {
+        match v {
+            MvtValue::String(s) => ValueKey::String(s.clone()),
+            MvtValue::Double(d) => ValueKey::Double(d.to_bits()),
+            MvtValue::Int(i) => ValueKey::Int(*i),
+            MvtValue::UInt(u) => ValueKey::UInt(*u),
+            MvtValue::Bool(b) => ValueKey::Bool(*b),
+        }
+    }
+}
+
+// Protobuf wire helpers
+
+const WIRE_VARINT: u32 = 0;
+const WIRE_FIXED64: u32 = 1;
+const WIRE_LEN: u32 = 2;
+
+fn put_varint(buf: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        buf.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    buf.push(v as u8);
+}
+
+fn put_tag(buf: &mut Vec<u8>, field: u32, wire: u32) {
+    put_varint(buf, u64::from((field << 3) | wire));
+}
+
+fn put_bytes(buf: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+    put_tag(buf, field, WIRE_LEN);
+    put_varint(buf, bytes.len() as u64);
+    buf.extend_from_slice(bytes);
+}
+
+fn put_packed(buf: &mut Vec<u8>, field: u32, values: &[u32]) {
+    let mut packed = Vec::with_capacity(values.len() * 2);
+    for v in values {
+        put_varint(&mut packed, u64::from(*v));
+    }
+    put_bytes(buf, field, &packed);
+}
+
+/// Zigzag parameter of a command; the spec's parameters are 32-bit, so a
+/// coordinate or delta outside the `i32` range is an error.
+fn zigzag32(v: i64) -> Result<u32> {
+    let v = i32::try_from(v).map_err(|_| {
+        SqliteGisError::InvalidInput(format!(
+            "tile coordinate {v} is outside the 32-bit range MVT can encode"
+        ))
+    })?;
+    Ok(((v << 1) ^ (v >> 31)) as u32)
+}
+
+fn zigzag64(v: i64) -> u64 {
+    ((v << 1) ^ (v >> 63)) as u64
+}
+
+// Geometry command encoding
+
+const CMD_MOVE_TO: u32 = 1;
+const CMD_LINE_TO: u32 = 2;
+const CMD_CLOSE_PATH: u32 = 7;
+
+/// MVT `GeomType`.
+#[derive(Clone, Copy)]
+enum GeomType {
+    Point = 1,
+    LineString = 2,
+    Polygon = 3,
+}
+
+/// Builds the command stream of one feature. The cursor carries over
+/// between parts, as the spec requires.
+#[derive(Default)]
+struct CommandWriter {
+    commands: Vec<u32>,
+    cursor: (i64, i64),
+}
+
+impl CommandWriter {
+    fn command(&mut self, id: u32, count: usize) {
+        self.commands.push((id & 0x7) | ((count as u32) << 3));
+    }
+
+    fn params(&mut self, coords: &[(i64, i64)]) -> Result<()> {
+        for &(x, y) in coords {
+            zigzag32(x)?;
+            zigzag32(y)?;
+            self.commands.push(zigzag32(x - self.cursor.0)?);
+            self.commands.push(zigzag32(y - self.cursor.1)?);
+            self.cursor = (x, y);
+        }
+        Ok(())
+    }
+
+    fn points(&mut self, coords: &[(i64, i64)]) -> Result<()> {
+        self.command(CMD_MOVE_TO, coords.len());
+        self.params(coords)
+    }
+
+    fn line(&mut self, coords: &[(i64, i64)]) -> Result<()> {
+        self.command(CMD_MOVE_TO, 1);
+        self.params(&coords[..1])?;
+        self.command(CMD_LINE_TO, coords.len() - 1);
+        self.params(&coords[1..])
+    }
+
+    fn ring(&mut self, coords: &[(i64, i64)]) -> Result<()> {
+        self.line(coords)?;
+        self.command(CMD_CLOSE_PATH, 1);
+        Ok(())
+    }
+}
+
+fn grid(coords: &[Coord<f64>]) -> Vec<(i64, i64)> {
+    let mut out: Vec<(i64, i64)> = Vec::with_capacity(coords.len());
+    for c in coords {
+        let p = (c.x.round() as i64, c.y.round() as i64);
+        if out.last() != Some(&p) {
+            out.push(p);
+        }
+    }
+    out
+}
+
+/// Integer ring without its closing point, wound clockwise on screen for
+/// an exterior ring and counter-clockwise for a hole; `None` if degenerate.
+fn grid_ring(ring: &LineString<f64>, exterior: bool) -> Option<Vec<(i64, i64)>> {
+    let mut coords = grid(&ring.0);
+    if coords.len() > 1 && coords.first() == coords.last() {
+        coords.pop();
+    }
+    if coords.len() < 3 {
+        return None;
+    }
+    let n = coords.len();
+    let area2: i128 = (0..n)
+        .map(|i| {
+            let (x0, y0) = coords[i];
+            let (x1, y1) = coords[(i + 1) % n];
+            i128::from(x0) * i128::from(y1) - i128::from(x1) * i128::from(y0)
+        })
+        .sum();
+    if area2 == 0 {
+        return None;
+    }
+    if (area2 > 0) != exterior {
+        coords.reverse();
+    }
+    Some(coords)
+}
+
+fn encode_polygons(writer: &mut CommandWriter, polygons: &[Polygon<f64>]) -> Result<()> {
+    for polygon in polygons {
+        let Some(exterior) = grid_ring(polygon.exterior(), true) else {
+            continue;
+        };
+        writer.ring(&exterior)?;
+        for hole in polygon.interiors() {
+            if let Some(hole) = grid_ring(hole, false) {
+                writer.ring(&hole)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Encode a tile-space geometry as an MVT command stream, or `None` when
+/// nothing drawable is left.
+fn encode_geometry(geom: &Geometry<f64>) -> Result<Option<(GeomType, Vec<u32>)>> {
+    let mut writer = CommandWriter::default();
+    let kind = match geom {
+        Geometry::Point(p) => {
+            if !is_empty_point(p) {
+                writer.points(&grid(&[p.0]))?;
+            }
+            GeomType::Point
+        }
+        Geometry::MultiPoint(mp) => {
+            let coords: Vec<(i64, i64)> = mp
+                .iter()
+                .filter(|p| !is_empty_point(p))
+                .map(|p| (p.x().round() as i64, p.y().round() as i64))
+                .collect();
+            if !coords.is_empty() {
+                writer.points(&coords)?;
+            }
+            GeomType::Point
+        }
+        Geometry::Line(_) | Geometry::LineString(_) | Geometry::MultiLineString(_) => {
+            let lines: Vec<LineString<f64>> = match geom {
+                Geometry::Line(l) => vec![(*l).into()],
+                Geometry::LineString(ls) => vec![ls.clone()],
+                Geometry::MultiLineString(mls) => mls.0.clone(),
+                _ => unreachable!(),
+            };
+            for line in &lines {
+                let coords = grid(&line.0);
+                if coords.len() >= 2 {
+                    writer.line(&coords)?;
+                }
+            }
+            GeomType::LineString
+        }
+        Geometry::Polygon(p) => {
+            encode_polygons(&mut writer, std::slice::from_ref(p))?;
+            GeomType::Polygon
+        }
+        Geometry::MultiPolygon(mp) => {
+            encode_polygons(&mut writer, &mp.0)?;
+            GeomType::Polygon
+        }
+        Geometry::Rect(r) => {
+            encode_polygons(&mut writer, &[r.to_polygon()])?;
+            GeomType::Polygon
+        }
+        Geometry::Triangle(t) => {
+            encode_polygons(&mut writer, &[t.to_polygon()])?;
+            GeomType::Polygon
+        }
+        Geometry::GeometryCollection(_) => {
+            return Err(SqliteGisError::wrong_type(
+                "Point, LineString or Polygon (or their Multi variants)",
+                geom,
+            ));
+        }
+    };
+    if writer.commands.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some((kind, writer.commands)))
+}
+
+/// One MVT layer being built feature by feature. Keys and values are
+/// de-duplicated across features, as the spec recommends.
+#[derive(Debug, Clone)]
+pub struct MvtLayer {
+    name: String,
+    extent: u32,
+    keys: Vec<String>,
+    key_ids: HashMap<String, u32>,
+    values: Vec<MvtValue>,
+    value_ids: HashMap<ValueKeyHash, u32>,
+    features: Vec<Vec<u8>>,
+}
+
+/// Newtype so [`MvtLayer`] can derive `Debug` without exposing `ValueKey`.
+#[derive(PartialEq, Eq, Hash)]
+struct ValueKeyHash(ValueKey);
+
+impl std::fmt::Debug for ValueKeyHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ValueKey")
+    }
+}
+
+impl Clone for ValueKeyHash {
+    fn clone(&self) -> Self {
+        ValueKeyHash(match &self.0 {
+            ValueKey::String(s) => ValueKey::String(s.clone()),
+            ValueKey::Double(d) => ValueKey::Double(*d),
+            ValueKey::Int(i) => ValueKey::Int(*i),
+            ValueKey::UInt(u) => ValueKey::UInt(*u),
+            ValueKey::Bool(b) => ValueKey::Bool(*b),
+        })
+    }
+}
+
+impl MvtLayer {
+    /// Empty layer called `name` with the given tile extent.
+    pub fn new(name: impl Into<String>, extent: u32) -> Self {
+        Self {
+            name: name.into(),
+            extent,
+            keys: Vec::new(),
+            key_ids: HashMap::new(),
+            values: Vec::new(),
+            value_ids: HashMap::new(),
+            features: Vec::new(),
+        }
+    }
+
+    /// Layer name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Tile extent the feature coordinates are expressed in.
+    pub fn extent(&self) -> u32 {
+        self.extent
+    }
+
+    /// Number of features added so far.
+    pub fn len(&self) -> usize {
+        self.features.len()
+    }
+
+    /// Whether no feature has been added.
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty()
+    }
+
+    fn key_id(&mut self, key: &str) -> u32 {
+        if let Some(id) = self.key_ids.get(key) {
+            return *id;
+        }
+        let id = self.keys.len() as u32;
+        self.keys.push(key.to_string());
+        self.key_ids.insert(key.to_string(), id);
+        id
+    }
+
+    fn value_id(&mut self, value: MvtValue) -> u32 {
+        let key = ValueKeyHash(ValueKey::from(&value));
+        if let Some(id) = self.value_ids.get(&key) {
+            return *id;
+        }
+        let id = self.values.len() as u32;
+        self.values.push(value);
+        self.value_ids.insert(key, id);
+        id
+    }
+
+    /// Add one feature. `geom` is an EWKB geometry already in tile space
+    /// (see [`st_as_mvt_geom`]). Returns `false`, adding nothing, when the
+    /// geometry has nothing drawable left on the integer grid, and fails
+    /// with [`SqliteGisError::InvalidInput`] when a coordinate or the step
+    /// between two does not fit MVT's 32-bit parameters.
+    pub fn add_feature<K, I>(&mut self, geom: &[u8], properties: I) -> Result<bool>
+    where
+        K: AsRef<str>,
+        I: IntoIterator<Item = (K, MvtValue)>,
+    {
+        let (geom, _) = parse_ewkb(geom)?;
+        let Some((kind, commands)) = encode_geometry(&geom)? else {
+            return Ok(false);
+        };
+        let mut tags = Vec::new();
+        for (key, value) in properties {
+            tags.push(self.key_id(key.as_ref()));
+            tags.push(self.value_id(value));
+        }
+
+        let mut feature = Vec::new();
+        if !tags.is_empty() {
+            put_packed(&mut feature, 2, &tags);
+        }
+        put_tag(&mut feature, 3, WIRE_VARINT);
+        put_varint(&mut feature, kind as u64);
+        put_packed(&mut feature, 4, &commands);
+        self.features.push(feature);
+        Ok(true)
+    }
+
+    /// Add one feature from a JSON object, as produced by SQLite's
+    /// `json_object(...)`. The `geom_column` member holds the geometry as
+    /// hex-encoded EWKB (SQLite's `hex(geom)`, since JSON cannot hold
+    /// BLOBs); every other non-null member becomes a property. Nested
+    /// arrays and objects are stored as their JSON text. A row whose
+    /// geometry is null or the empty string (what `hex(NULL)` returns) is
+    /// skipped and returns `false`.
+    ///
+    /// ```
+    /// use sqlitegis::core::functions::mvt::MvtLayer;
+    ///
+    /// let mut layer = MvtLayer::new("places", 4096);
+    /// // POINT(1 2) as hex EWKB.
+    /// let row = r#"{"name":"a","geom":"0101000000000000000000F03F0000000000000040"}"#;
+    /// assert!(layer.add_json_row(row, "geom").unwrap());
+    /// assert!(!layer.add_json_row(r#"{"name":"b","geom":null}"#, "geom").unwrap());
+    /// assert!(layer.add_json_row(r#"{"name":"c"}"#, "geom").is_err());
+    /// assert_eq!(layer.len(), 1);
+    /// ```
+    pub fn add_json_row(&mut self, row: &str, geom_column: &str) -> Result<bool> {
+        let row: serde_json::Value = serde_json::from_str(row)
+            .map_err(|e| SqliteGisError::InvalidInput(format!("row is not valid JSON: {e}")))?;
+        let serde_json::Value::Object(members) = row else {
+            return Err(SqliteGisError::InvalidInput(
+                "row must be a JSON object".to_string(),
+            ));
+        };
+        let geom = match members.get(geom_column) {
+            None => {
+                return Err(SqliteGisError::InvalidInput(format!(
+                    "row has no geometry column {geom_column:?}"
+                )))
+            }
+            // `hex(NULL)` is the empty string in SQLite.
+            Some(serde_json::Value::Null) => return Ok(false),
+            Some(serde_json::Value::String(hex)) if hex.is_empty() => return Ok(false),
+            Some(serde_json::Value::String(hex)) => decode_hex(hex.as_bytes())?,
+            Some(_) => {
+                return Err(SqliteGisError::InvalidInput(format!(
+                    "geometry column {geom_column:?} must hold hex-encoded EWKB"
+                )))
+            }
+        };
+        let properties = members
+            .iter()
+            .filter(|(key, _)| key.as_str() != geom_column)
+            .filter_map(|(key, value)| json_property(value).map(|v| (key, v)));
+        self.add_feature(&geom, properties)
+    }
+
+    /// Encode the layer as a complete single-layer tile. Concatenate the
+    /// output of several layers to build a multi-layer tile.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut layer = Vec::new();
+        put_bytes(&mut layer, 1, self.name.as_bytes());
+        for feature in &self.features {
+            put_bytes(&mut layer, 2, feature);
+        }
+        for key in &self.keys {
+            put_bytes(&mut layer, 3, key.as_bytes());
+        }
+        for value in &self.values {
+            put_bytes(&mut layer, 4, &encode_value(value));
+        }
+        put_tag(&mut layer, 5, WIRE_VARINT);
+        put_varint(&mut layer, u64::from(self.extent));
+        put_tag(&mut layer, 15, WIRE_VARINT);
+        put_varint(&mut layer, 2);
+
+        let mut tile = Vec::with_capacity(layer.len() + 8);
+        put_bytes(&mut tile, 3, &layer);
+        tile
+    }
+}
+
+fn encode_value(value: &MvtValue) -> Vec<u8> {
+    let mut buf = Vec::new();
+    match value {
+        MvtValue::String(s) => put_bytes(&mut buf, 1, s.as_bytes()),
+        MvtValue::Double(d) => {
+            put_tag(&mut buf, 3, WIRE_FIXED64);
+            buf.extend_from_slice(&d.to_le_bytes());
+        }
+        MvtValue::Int(i) if *i >= 0 => {
+            put_tag(&mut buf, 5, WIRE_VARINT);
+            put_varint(&mut buf, *i as u64);
+        }
+        MvtValue::Int(i) => {
+            put_tag(&mut buf, 6, WIRE_VARINT);
+            put_varint(&mut buf, zigzag64(*i));
+        }
+        MvtValue::UInt(u) => {
+            put_tag(&mut buf, 5, WIRE_VARINT);
+            put_varint(&mut buf, *u);
+        }
+        MvtValue::Bool(b) => {
+            put_tag(&mut buf, 7, WIRE_VARINT);
+            put_varint(&mut buf, u64::from(*b));
+        }
+    }
+    buf
+}
+
+fn json_property(value: &serde_json::Value) -> Option<MvtValue> {
+    match value {
+        serde_json::Value::Null => None,
+        serde_json::Value::Bool(b) => Some(MvtValue::Bool(*b)),
+        serde_json::Value::Number(n) => Some(if let Some(i) = n.as_i64() {
+            MvtValue::Int(i)
+        } else if let Some(u) = n.as_u64() {
+            MvtValue::UInt(u)
+        } else {
+            MvtValue::Double(n.as_f64().unwrap_or(f64::NAN))
+        }),
+        serde_json::Value::String(s) => Some(MvtValue::String(s.clone())),
+        other => Some(MvtValue::String(other.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::functions::constructors::{st_make_envelope, st_tile_envelope};
+    use crate::core::functions::io::{as_text, geom_from_text};
+
+    fn mvt_geom(wkt: &str, extent: u32, buffer: u32, clip: bool) -> Option<String> {
+        let bounds = st_make_envelope(0.0, 0.0, 100.0, 100.0, None).unwrap();
+        let geom = geom_from_text(wkt, None).unwrap();
+        st_as_mvt_geom(&geom, &bounds, extent, buffer, clip)
+            .unwrap()
+            .map(|blob| as_text(&blob).unwrap())
+    }
+
+    #[test]
+    fn flips_y_and_scales_to_extent() {
+        assert_eq!(
+            mvt_geom("POINT(25 75)", 4096, 0, true).as_deref(),
+            Some("POINT(1024 1024)")
+        );
+    }
+
+    #[test]
+    fn clips_to_buffered_tile() {
+        assert_eq!(mvt_geom("POINT(150 50)", 100, 10, true), None);
+        assert_eq!(
+            mvt_geom("POINT(150 50)", 100, 10, false).as_deref(),
+            Some("POINT(150 50)")
+        );
+        let poly = mvt_geom(
+            "POLYGON((-50 -50,150 -50,150 150,-50 150,-50 -50))",
+            100,
+            5,
+            true,
+        )
+        .unwrap();
+        assert!(poly.starts_with("POLYGON(("), "{poly}");
+        for coord in ["-5 -5", "105 -5", "105 105", "-5 105"] {
+            assert!(poly.contains(coord), "{poly}");
+        }
+    }
+
+    #[test]
+    fn drops_parts_that_collapse_on_the_grid() {
+        assert_eq!(
+            mvt_geom("POLYGON((0 0,0.1 0,0.1 0.1,0 0.1,0 0))", 100, 0, true),
+            None
+        );
+        assert_eq!(mvt_geom("LINESTRING(10 10,10.2 10.2)", 100, 0, true), None);
+        assert_eq!(
+            mvt_geom(
+                "GEOMETRYCOLLECTION(POINT(1 1),LINESTRING(0 0,50 50))",
+                100,
+                0,
+                true
+            )
+            .as_deref(),
+            Some("LINESTRING(0 100,50 50)")
+        );
+        // Like PostGIS, the dimension is chosen before snapping.
+        assert_eq!(
+            mvt_geom(
+                "GEOMETRYCOLLECTION(POLYGON((0 0,0.1 0,0.1 0.1,0 0.1,0 0)),LINESTRING(0 0,50 50))",
+                100,
+                0,
+                true
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn exterior_rings_are_clockwise_on_screen() {
+        let out = mvt_geom(
+            "POLYGON((10 10,90 10,90 90,10 90,10 10),(20 20,20 80,80 80,80 20,20 20))",
+            100,
+            0,
+            true,
+        )
+        .unwrap();
+        let blob = geom_from_text(&out, None).unwrap();
+        let (Geometry::Polygon(p), _) = parse_ewkb(&blob).unwrap() else {
+            panic!("expected polygon: {out}");
+        };
+        assert!(ring_area2(&p.exterior().0) > 0.0);
+        assert!(ring_area2(&p.interiors()[0].0) < 0.0);
+    }
+
+    #[test]
+    fn rejects_degenerate_bounds_and_extent() {
+        let geom = geom_from_text("POINT(0 0)", None).unwrap();
+        let flat = geom_from_text("LINESTRING(0 0,10 0)", None).unwrap();
+        let bounds = st_tile_envelope(0, 0, 0).unwrap();
+        assert!(st_as_mvt_geom(&geom, &flat, 4096, 0, true).is_err());
+        assert!(st_as_mvt_geom(&geom, &bounds, 0, 0, true).is_err());
+    }
+
+    #[test]
+    fn encodes_spec_example_point() {
+        // From the vector-tile spec: POINT(25 17) is [9, 50, 34].
+        let blob = geom_from_text("POINT(25 17)", None).unwrap();
+        let (geom, _) = parse_ewkb(&blob).unwrap();
+        let (_, commands) = encode_geometry(&geom).unwrap().unwrap();
+        assert_eq!(commands, vec![9, 50, 34]);
+    }
+
+    #[test]
+    fn encodes_spec_example_polygon() {
+        // From the vector-tile spec: POLYGON((3 6,8 12,20 34,3 6)).
+        let blob = geom_from_text("POLYGON((3 6,8 12,20 34,3 6))", None).unwrap();
+        let (geom, _) = parse_ewkb(&blob).unwrap();
+        let (_, commands) = encode_geometry(&geom).unwrap().unwrap();
+        assert_eq!(commands, vec![9, 6, 12, 18, 10, 12, 24, 44, 15]);
+    }
+
+    #[test]
+    fn rejects_coordinates_outside_32_bits() {
+        let mut layer = MvtLayer::new("l", 4096);
+        let no_props = || -> [(&str, MvtValue); 0] { [] };
+        for wkt in [
+            "POINT(3000000000 0)",
+            "LINESTRING(-2000000000 0,2000000000 0)",
+            "POINT(1e300 0)",
+        ] {
+            let geom = geom_from_text(wkt, None).unwrap();
+            assert!(
+                matches!(
+                    layer.add_feature(&geom, no_props()),
+                    Err(SqliteGisError::InvalidInput(_))
+                ),
+                "{wkt}"
+            );
+        }
+        assert!(layer.is_empty());
+        let edge = geom_from_text("LINESTRING(-2147483648 0,-1 0)", None).unwrap();
+        assert!(layer.add_feature(&edge, no_props()).unwrap());
+    }
+
+    #[test]
+    fn shares_keys_and_values_between_features() {
+        let mut layer = MvtLayer::new("l", 4096);
+        let geom = geom_from_text("POINT(1 1)", None).unwrap();
+        for _ in 0..3 {
+            layer
+                .add_feature(&geom, [("kind", MvtValue::from("a")), ("n", 1i64.into())])
+                .unwrap();
+        }
+        assert_eq!(layer.len(), 3);
+        assert_eq!(layer.keys, vec!["kind", "n"]);
+        assert_eq!(layer.values.len(), 2);
+    }
+
+    #[test]
+    fn rejects_collections_in_features() {
+        let mut layer = MvtLayer::new("l", 4096);
+        let gc = geom_from_text("GEOMETRYCOLLECTION(POINT(1 1))", None).unwrap();
+        let no_props: [(&str, MvtValue); 0] = [];
+        assert!(matches!(
+            layer.add_feature(&gc, no_props),
+            Err(SqliteGisError::WrongType { .. })
+        ));
+    }
+
+    #[test]
+    fn encodes_layer_header() {
+        let layer = MvtLayer::new("ab", 4096);
+        // Tile.layers (3, len 9): name "ab", extent 4096, version 2.
+        assert_eq!(
+            layer.encode(),
+            vec![0x1a, 9, 0x0a, 2, b'a', b'b', 0x28, 0x80, 0x20, 0x78, 2]
+        );
+    }
+}
@@ -6,18 +6,19 @@ use std::cmp::Ordering;
 
 use geo::algorithm::bool_ops::BooleanOps;
 use geo::algorithm::line_intersection::{line_intersection, LineIntersection};
-use geo::algorithm::Buffer;
 use geo::algorithm::Intersects;
+use geo::algorithm::{unary_union, Buffer, OpType};
 use geo::{
     Geometry, GeometryCollection, LineString, MultiLineString, MultiPoint, MultiPolygon, Point,
     Polygon,
 };
 
-use crate::core::error::{Result, SqliteGisError};
+use crate::core::error::{on_operand, Result, SqliteGisError};
 use crate::core::ewkb::{
     concat_multipolygon_bodies, extract_mbr, extract_srid, parse_ewkb, parse_ewkb_pair, write_ewkb,
 };
 use crate::core::functions::emptiness::{is_empty_geometry, is_empty_point};
+use crate::core::interrupt::{check, Interrupt, NeverInterrupt};
 
 /// Extract a Polygon or MultiPolygon from a geometry, converting single
 /// Polygons into MultiPolygon for uniform BooleanOps handling.
@@ -32,17 +33,42 @@ fn require_multi_polygon(geom: Geometry<f64>) -> Result<MultiPolygon<f64>> {
     }
 }
 
-fn binary_polygon_op<F>(a: &[u8], b: &[u8], op: F) -> Result<Vec<u8>>
+fn binary_polygon_op<F>(a: &[u8], b: &[u8], interrupt: &dyn Interrupt, op: F) -> Result<Vec<u8>>
 where
-    F: FnOnce(&MultiPolygon<f64>, &MultiPolygon<f64>) -> MultiPolygon<f64>,
+    F: FnOnce(MultiPolygon<f64>, MultiPolygon<f64>) -> Result<MultiPolygon<f64>>,
 {
+    check(interrupt)?;
     let (ga, gb, srid) = parse_ewkb_pair(a, b)?;
-    let ma = require_multi_polygon(ga)?;
-    let mb = require_multi_polygon(gb)?;
-    let result = op(&ma, &mb);
+    let ma = on_operand(0, require_multi_polygon(ga))?;
+    let mb = on_operand(1, require_multi_polygon(gb))?;
+    let result = op(ma, mb)?;
     write_ewkb(&Geometry::MultiPolygon(result), srid)
 }
 
+/// Combine `a` with `b` under `op`. With a hook that can fire, a `b` of
+/// several polygons is folded in one polygon per overlay pass, so
+/// `interrupt` is polled between passes; otherwise this is the single
+/// whole-geometry pass the plain functions run. Polls once at the end.
+fn overlay(
+    a: MultiPolygon<f64>,
+    b: MultiPolygon<f64>,
+    op: OpType,
+    interrupt: &dyn Interrupt,
+) -> Result<MultiPolygon<f64>> {
+    if !interrupt.can_interrupt() || b.0.len() < 2 {
+        let result = a.boolean_op(&b, op);
+        check(interrupt)?;
+        return Ok(result);
+    }
+    let mut acc = a;
+    for part in &b {
+        check(interrupt)?;
+        acc = acc.boolean_op(part, op);
+    }
+    check(interrupt)?;
+    Ok(acc)
+}
+
 /// Bag of homogeneous-typed pieces extracted from a possibly-nested input.
 ///
 /// `ST_Intersection` accepts any geometry on either side. We normalise the
@@ -115,10 +141,15 @@ fn decompose_into(geom: Geometry<f64>, bag: &mut GeometryBag) -> Result<()> {
     Ok(())
 }
 
-fn intersect_bags(a: &GeometryBag, b: &GeometryBag) -> GeometryBag {
+fn intersect_bags(
+    a: &GeometryBag,
+    b: &GeometryBag,
+    interrupt: &dyn Interrupt,
+) -> Result<GeometryBag> {
     let mut out = GeometryBag::new();
 
     if !a.polygons.is_empty() && !b.polygons.is_empty() {
+        check(interrupt)?;
         let ma = MultiPolygon::new(a.polygons.clone());
         let mb = MultiPolygon::new(b.polygons.clone());
         let result = ma.intersection(&mb);
@@ -126,6 +157,7 @@ fn intersect_bags(a: &GeometryBag, b: &GeometryBag) -> GeometryBag {
     }
 
     if !a.lines.is_empty() && !b.polygons.is_empty() {
+        check(interrupt)?;
         let mls = MultiLineString::new(a.lines.clone());
         let mb = MultiPolygon::new(b.polygons.clone());
         let clipped = mb.clip(&mls, false);
@@ -133,6 +165,7 @@ fn intersect_bags(a: &GeometryBag, b: &GeometryBag) -> GeometryBag {
     }
 
     if !a.polygons.is_empty() && !b.lines.is_empty() {
+        check(interrupt)?;
         let ma = MultiPolygon::new(a.polygons.clone());
         let mls = MultiLineString::new(b.lines.clone());
         let clipped = ma.clip(&mls, false);
@@ -140,6 +173,7 @@ fn intersect_bags(a: &GeometryBag, b: &GeometryBag) -> GeometryBag {
     }
 
     if !a.points.is_empty() && !b.polygons.is_empty() {
+        check(interrupt)?;
         let mb = MultiPolygon::new(b.polygons.clone());
         for p in &a.points {
             if mb.intersects(p) {
@@ -149,6 +183,7 @@ fn intersect_bags(a: &GeometryBag, b: &GeometryBag) -> GeometryBag {
     }
 
     if !a.polygons.is_empty() && !b.points.is_empty() {
+        check(interrupt)?;
         let ma = MultiPolygon::new(a.polygons.clone());
         for p in &b.points {
             if ma.intersects(p) {
@@ -158,6 +193,7 @@ fn intersect_bags(a: &GeometryBag, b: &GeometryBag) -> GeometryBag {
     }
 
     if !a.points.is_empty() && !b.lines.is_empty() {
+        check(interrupt)?;
         let mls = MultiLineString::new(b.lines.clone());
         for p in &a.points {
             if mls.intersects(p) {
@@ -167,6 +203,7 @@ fn intersect_bags(a: &GeometryBag, b: &GeometryBag) -> GeometryBag {
     }
 
     if !a.lines.is_empty() && !b.points.is_empty() {
+        check(interrupt)?;
         let mls = MultiLineString::new(a.lines.clone());
         for p in &b.points {
             if mls.intersects(p) {
@@ -176,6 +213,7 @@ fn intersect_bags(a: &GeometryBag, b: &GeometryBag) -> GeometryBag {
     }
 
     if !a.points.is_empty() && !b.points.is_empty() {
+        check(interrupt)?;
         for pa in &a.points {
             for pb in &b.points {
                 if pa.x() == pb.x() && pa.y() == pb.y() {
@@ -187,19 +225,26 @@ fn intersect_bags(a: &GeometryBag, b: &GeometryBag) -> GeometryBag {
     }
 
     if !a.lines.is_empty() && !b.lines.is_empty() {
-        intersect_lines_into(&a.lines, &b.lines, &mut out);
+        intersect_lines_into(&a.lines, &b.lines, &mut out, interrupt)?;
     }
 
-    out
+    Ok(out)
 }
 
 /// Naive O(n*m) pairwise segment-intersection sweep. Sufficient for typical
 /// LineString sizes. A Bentley-Ottmann sweep would only pay off for very
-/// long, very sparse-intersection inputs.
-fn intersect_lines_into(a: &[LineString<f64>], b: &[LineString<f64>], out: &mut GeometryBag) {
+/// long, very sparse-intersection inputs. `interrupt` is polled once per
+/// segment of `a`.
+fn intersect_lines_into(
+    a: &[LineString<f64>],
+    b: &[LineString<f64>],
+    out: &mut GeometryBag,
+    interrupt: &dyn Interrupt,
+) -> Result<()> {
     let mut collinear: Vec<LineString<f64>> = Vec::new();
     for la in a {
         for seg_a in la.lines() {
+            check(interrupt)?;
             for lb in b {
                 for seg_b in lb.lines() {
                     match line_intersection(seg_a, seg_b) {
@@ -219,6 +264,7 @@ fn intersect_lines_into(a: &[LineString<f64>], b: &[LineString<f64>], out: &mut
         }
     }
     out.lines.extend(collinear);
+    Ok(())
 }
 
 fn coord_cmp(a: &Point<f64>, b: &Point<f64>) -> Ordering {
@@ -303,6 +349,28 @@ fn pack(bag: GeometryBag) -> Geometry<f64> {
 /// assert!((st_area(&u).unwrap() - 6.0).abs() < 1e-10);
 /// ```
 pub fn st_union(a: &[u8], b: &[u8]) -> Result<Vec<u8>> {
+    st_union_interruptible(a, b, &NeverInterrupt)
+}
+
+/// [`st_union`] that unions one polygon of the operand with fewer parts
+/// per overlay pass, polls `interrupt` between and after the passes, and
+/// returns [`SqliteGisError::Interrupted`] once it fires. With
+/// [`NeverInterrupt`] it is the single overlay pass of [`st_union`].
+///
+/// # Example
+///
+/// ```
+/// use sqlitegis::SqliteGisError;
+/// use sqlitegis::core::functions::operations::st_union_interruptible;
+/// use sqlitegis::core::functions::io::geom_from_text;
+///
+/// let a = geom_from_text("POLYGON((0 0,2 0,2 2,0 2,0 0))", None).unwrap();
+/// let b = geom_from_text("POLYGON((1 0,3 0,3 2,1 2,1 0))", None).unwrap();
+/// assert!(st_union_interruptible(&a, &b, &|| false).is_ok());
+/// let err = st_union_interruptible(&a, &b, &|| true).unwrap_err();
+/// assert!(matches!(err, SqliteGisError::Interrupted));
+/// ```
+pub fn st_union_interruptible(a: &[u8], b: &[u8], interrupt: &dyn Interrupt) -> Result<Vec<u8>> {
     // MBR-only fastpath. If both bboxes exist and are disjoint, the
     // union is simply the concatenation of both polygon lists. We splice
     // the input EWKB bytes directly without decoding, which is several
@@ -312,7 +380,14 @@ pub fn st_union(a: &[u8], b: &[u8]) -> Result<Vec<u8>> {
             return concat_multipolygon_bodies(a, b);
         }
     }
-    binary_polygon_op(a, b, |ma, mb| ma.union(mb))
+    binary_polygon_op(a, b, interrupt, |ma, mb| {
+        // Per-part passes fold the operand with fewer parts into the other.
+        if interrupt.can_interrupt() && ma.0.len() < mb.0.len() {
+            overlay(mb, ma, OpType::Union, interrupt)
+        } else {
+            overlay(ma, mb, OpType::Union, interrupt)
+        }
+    })
 }
 
 /// ST_Intersection: compute the geometric intersection of two geometries.
@@ -360,18 +435,43 @@ pub fn st_union(a: &[u8], b: &[u8]) -> Result<Vec<u8>> {
 /// assert_eq!(as_text(&r).unwrap(), "POINT(1 1)");
 /// ```
 pub fn st_intersection(a: &[u8], b: &[u8]) -> Result<Vec<u8>> {
+    st_intersection_interruptible(a, b, &NeverInterrupt)
+}
+
+/// [`st_intersection`] that polls `interrupt` between the per-dimension
+/// passes and once per segment of the line-line sweep.
+///
+/// # Example
+///
+/// ```
+/// use sqlitegis::SqliteGisError;
+/// use sqlitegis::core::functions::operations::st_intersection_interruptible;
+/// use sqlitegis::core::functions::io::geom_from_text;
+///
+/// let a = geom_from_text("POLYGON((0 0,2 0,2 2,0 2,0 0))", None).unwrap();
+/// let b = geom_from_text("POLYGON((1 0,3 0,3 2,1 2,1 0))", None).unwrap();
+/// assert!(st_intersection_interruptible(&a, &b, &|| false).is_ok());
+/// let err = st_intersection_interruptible(&a, &b, &|| true).unwrap_err();
+/// assert!(matches!(err, SqliteGisError::Interrupted));
+/// ```
+pub fn st_intersection_interruptible(
+    a: &[u8],
+    b: &[u8],
+    interrupt: &dyn Interrupt,
+) -> Result<Vec<u8>> {
     if let (Ok(Some(ra)), Ok(Some(rb))) = (extract_mbr(a), extract_mbr(b)) {
         if !ra.intersects(&rb) {
             let empty = Geometry::GeometryCollection(GeometryCollection::new_from(vec![]));
             return write_ewkb(&empty, extract_srid(a));
         }
     }
+    check(interrupt)?;
     let (ga, gb, srid) = parse_ewkb_pair(a, b)?;
     let mut bag_a = GeometryBag::new();
     let mut bag_b = GeometryBag::new();
-    decompose_into(ga, &mut bag_a)?;
-    decompose_into(gb, &mut bag_b)?;
-    let result = intersect_bags(&bag_a, &bag_b);
+    on_operand(0, decompose_into(ga, &mut bag_a))?;
+    on_operand(1, decompose_into(gb, &mut bag_b))?;
+    let result = intersect_bags(&bag_a, &bag_b, interrupt)?;
     write_ewkb(&pack(result), srid)
 }
 
@@ -390,7 +490,34 @@ pub fn st_intersection(a: &[u8], b: &[u8]) -> Result<Vec<u8>> {
 /// assert!((st_area(&d).unwrap() - 2.0).abs() < 1e-10);
 /// ```
 pub fn st_difference(a: &[u8], b: &[u8]) -> Result<Vec<u8>> {
-    binary_polygon_op(a, b, |ma, mb| ma.difference(mb))
+    st_difference_interruptible(a, b, &NeverInterrupt)
+}
+
+/// [`st_difference`] that removes one polygon of `b` per overlay pass and
+/// polls `interrupt` between and after the passes. With
+/// [`NeverInterrupt`] it is the single overlay pass of [`st_difference`].
+///
+/// # Example
+///
+/// ```
+/// use sqlitegis::SqliteGisError;
+/// use sqlitegis::core::functions::operations::st_difference_interruptible;
+/// use sqlitegis::core::functions::io::geom_from_text;
+///
+/// let a = geom_from_text("POLYGON((0 0,2 0,2 2,0 2,0 0))", None).unwrap();
+/// let b = geom_from_text("POLYGON((1 0,3 0,3 2,1 2,1 0))", None).unwrap();
+/// assert!(st_difference_interruptible(&a, &b, &|| false).is_ok());
+/// let err = st_difference_interruptible(&a, &b, &|| true).unwrap_err();
+/// assert!(matches!(err, SqliteGisError::Interrupted));
+/// ```
+pub fn st_difference_interruptible(
+    a: &[u8],
+    b: &[u8],
+    interrupt: &dyn Interrupt,
+) -> Result<Vec<u8>> {
+    binary_polygon_op(a, b, interrupt, |ma, mb| {
+        overlay(ma, mb, OpType::Difference, interrupt)
+    })
 }
 
 /// ST_SymDifference: compute the symmetric difference (XOR) of two polygon geometries.
@@ -408,6 +535,30 @@ pub fn st_difference(a: &[u8], b: &[u8]) -> Result<Vec<u8>> {
 /// assert!((st_area(&sd).unwrap() - 4.0).abs() < 1e-10);
 /// ```
 pub fn st_sym_difference(a: &[u8], b: &[u8]) -> Result<Vec<u8>> {
+    st_sym_difference_interruptible(a, b, &NeverInterrupt)
+}
+
+/// [`st_sym_difference`] that polls `interrupt` before and after its
+/// single overlay pass.
+///
+/// # Example
+///
+/// ```
+/// use sqlitegis::SqliteGisError;
+/// use sqlitegis::core::functions::operations::st_sym_difference_interruptible;
+/// use sqlitegis::core::functions::io::geom_from_text;
+///
+/// let a = geom_from_text("POLYGON((0 0,2 0,2 2,0 2,0 0))", None).unwrap();
+/// let b = geom_from_text("POLYGON((1 0,3 0,3 2,1 2,1 0))", None).unwrap();
+/// assert!(st_sym_difference_interruptible(&a, &b, &|| false).is_ok());
+/// let err = st_sym_difference_interruptible(&a, &b, &|| true).unwrap_err();
+/// assert!(matches!(err, SqliteGisError::Interrupted));
+/// ```
+pub fn st_sym_difference_interruptible(
+    a: &[u8],
+    b: &[u8],
+    interrupt: &dyn Interrupt,
+) -> Result<Vec<u8>> {
     // MBR-only fastpath. Symmetric difference of disjoint geometries is
     // their union (XOR of non-overlapping sets is the full pair). Same
     // bytes-only splice as `st_union`.
@@ -416,7 +567,11 @@ pub fn st_sym_difference(a: &[u8], b: &[u8]) -> Result<Vec<u8>> {
             return concat_multipolygon_bodies(a, b);
         }
     }
-    binary_polygon_op(a, b, |ma, mb| ma.xor(mb))
+    binary_polygon_op(a, b, interrupt, |ma, mb| {
+        let result = ma.xor(&mb);
+        check(interrupt)?;
+        Ok(result)
+    })
 }
 
 /// ST_Buffer: expand or shrink a geometry by a given distance.
@@ -435,12 +590,36 @@ pub fn st_sym_difference(a: &[u8], b: &[u8]) -> Result<Vec<u8>> {
 /// assert!((area - std::f64::consts::PI).abs() < 0.1);
 /// ```
 pub fn st_buffer(blob: &[u8], distance: f64) -> Result<Vec<u8>> {
+    st_buffer_interruptible(blob, distance, &NeverInterrupt)
+}
+
+/// [`st_buffer`] that polls `interrupt` before buffering, between the
+/// members of multi-part inputs and after the last pass.
+///
+/// # Example
+///
+/// ```
+/// use sqlitegis::SqliteGisError;
+/// use sqlitegis::core::functions::operations::st_buffer_interruptible;
+/// use sqlitegis::core::functions::constructors::st_point;
+///
+/// let pt = st_point(0.0, 0.0, None).unwrap();
+/// assert!(st_buffer_interruptible(&pt, 1.0, &|| false).is_ok());
+/// let err = st_buffer_interruptible(&pt, 1.0, &|| true).unwrap_err();
+/// assert!(matches!(err, SqliteGisError::Interrupted));
+/// ```
+pub fn st_buffer_interruptible(
+    blob: &[u8],
+    distance: f64,
+    interrupt: &dyn Interrupt,
+) -> Result<Vec<u8>> {
+    check(interrupt)?;
     let (geom, srid) = parse_ewkb(blob)?;
     if is_empty_geometry(&geom) {
         let empty = Geometry::Polygon(geo::Polygon::new(geo::LineString::new(vec![]), vec![]));
         return write_ewkb(&empty, srid);
     }
-    let result = geom.buffer(distance);
+    let result = buffer_geometry(&geom, distance, interrupt)?;
     let mut polygons = result.0;
     let out_geom = match polygons.len() {
         0 => Geometry::Polygon(geo::Polygon::new(geo::LineString::new(vec![]), vec![])),
@@ -457,6 +636,56 @@ pub fn st_buffer(blob: &[u8], distance: f64) -> Result<Vec<u8>> {
     write_ewkb(&out_geom, srid)
 }
 
+/// `geom.buffer(distance)`, split per member for multi-part inputs so
+/// `interrupt` is polled between members and after every pass. Same
+/// buffer-each-then-`unary_union` plan as geo's own GeometryCollection
+/// impl; geo already offsets the members of a Multi* independently.
+fn buffer_geometry(
+    geom: &Geometry<f64>,
+    distance: f64,
+    interrupt: &dyn Interrupt,
+) -> Result<MultiPolygon<f64>> {
+    let buffered = match geom {
+        Geometry::GeometryCollection(gc) => {
+            let mut buffered = Vec::with_capacity(gc.0.len());
+            for member in gc.iter() {
+                check(interrupt)?;
+                buffered.push(buffer_geometry(member, distance, interrupt)?);
+            }
+            buffered
+        }
+        Geometry::MultiPoint(mp) => buffer_members(&mp.0, distance, interrupt)?,
+        Geometry::MultiLineString(mls) => buffer_members(&mls.0, distance, interrupt)?,
+        Geometry::MultiPolygon(mp) => buffer_members(&mp.0, distance, interrupt)?,
+        _ => {
+            let result = geom.buffer(distance);
+            check(interrupt)?;
+            return Ok(result);
+        }
+    };
+    check(interrupt)?;
+    let result = unary_union(buffered.iter());
+    check(interrupt)?;
+    Ok(result)
+}
+
+/// Buffer each of `members` on its own, polling `interrupt` before each.
+fn buffer_members<G>(
+    members: &[G],
+    distance: f64,
+    interrupt: &dyn Interrupt,
+) -> Result<Vec<MultiPolygon<f64>>>
+where
+    G: Buffer<Scalar = f64>,
+{
+    let mut buffered = Vec::with_capacity(members.len());
+    for member in members {
+        check(interrupt)?;
+        buffered.push(member.buffer(distance));
+    }
+    Ok(buffered)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -513,6 +742,144 @@ mod tests {
         assert_eq!(st_geometry_type(&buffered).unwrap(), "ST_MultiPolygon");
     }
 
+    #[test]
+    fn intersection_interrupt_stops_line_sweep() {
+        use std::cell::Cell;
+        let coords: Vec<String> = (0..100).map(|i| format!("{i} {}", i % 2)).collect();
+        let line = geom_from_text(&format!("LINESTRING({})", coords.join(",")), None).unwrap();
+        let polls = Cell::new(0);
+        let hook = || {
+            polls.set(polls.get() + 1);
+            polls.get() > 20
+        };
+        let err = st_intersection_interruptible(&line, &line, &hook).unwrap_err();
+        assert!(matches!(err, SqliteGisError::Interrupted));
+        assert_eq!(polls.get(), 21);
+    }
+
+    #[test]
+    fn buffer_collection_matches_geo_and_polls_per_member() {
+        use std::cell::Cell;
+        let gc = geom_from_text(
+            "GEOMETRYCOLLECTION(POINT(0 0),LINESTRING(0 0,3 0),POLYGON((5 5,6 5,6 6,5 6,5 5)))",
+            None,
+        )
+        .unwrap();
+        let (geom, _) = parse_ewkb(&gc).unwrap();
+        let expected = write_ewkb(&Geometry::MultiPolygon(geom.buffer(0.5)), None).unwrap();
+        assert_eq!(st_buffer(&gc, 0.5).unwrap(), expected);
+
+        let polls = Cell::new(0);
+        let hook = || {
+            polls.set(polls.get() + 1);
+            false
+        };
+        st_buffer_interruptible(&gc, 0.5, &hook).unwrap();
+        // Entry, before and after each member, before and after the union.
+        assert_eq!(polls.get(), 9);
+    }
+
+    #[test]
+    fn buffer_multi_parts_match_geo() {
+        let mp = geom_from_text(
+            "MULTIPOLYGON(((0 0,1 0,1 1,0 1,0 0)),((1 0,2 0,2 1,1 1,1 0)),((5 5,6 5,6 6,5 6,5 5)))",
+            None,
+        )
+        .unwrap();
+        let (geom, _) = parse_ewkb(&mp).unwrap();
+        let outward = st_buffer(&mp, 0.25).unwrap();
+        let expected = write_ewkb(&Geometry::MultiPolygon(geom.buffer(0.25)), None).unwrap();
+        assert!((st_area(&outward).unwrap() - st_area(&expected).unwrap()).abs() < 1e-6);
+        assert_eq!(st_geometry_type(&outward).unwrap(), "ST_MultiPolygon");
+
+        // Like geo, touching members erode independently.
+        let inward = st_buffer(&mp, -0.25).unwrap();
+        let expected = write_ewkb(&Geometry::MultiPolygon(geom.buffer(-0.25)), None).unwrap();
+        assert!((st_area(&inward).unwrap() - st_area(&expected).unwrap()).abs() < 1e-9);
+        assert!((st_area(&inward).unwrap() - 3.0 * 0.25).abs() < 1e-9);
+
+        use std::cell::Cell;
+        let polls = Cell::new(0);
+        let hook = || {
+            polls.set(polls.get() + 1);
+            polls.get() > 3
+        };
+        let err = st_buffer_interruptible(&mp, 0.25, &hook).unwrap_err();
+        assert!(matches!(err, SqliteGisError::Interrupted));
+        assert_eq!(polls.get(), 4);
+    }
+
+    #[test]
+    fn overlays_poll_per_part_and_after_the_last_pass() {
+        use std::cell::Cell;
+        let a = geom_from_text("POLYGON((0 0,10 0,10 10,0 10,0 0))", None).unwrap();
+        let b = geom_from_text(
+            "MULTIPOLYGON(((1 1,2 1,2 2,1 2,1 1)),((3 3,4 3,4 4,3 4,3 3)),((5 5,6 5,6 6,5 6,5 5)))",
+            None,
+        )
+        .unwrap();
+        type Overlay = fn(&[u8], &[u8], &dyn Interrupt) -> Result<Vec<u8>>;
+        let count = |op: Overlay| {
+            let polls = Cell::new(0);
+            let hook = || {
+                polls.set(polls.get() + 1);
+                false
+            };
+            op(&a, &b, &hook).unwrap();
+            polls.get()
+        };
+        // Entry, one per polygon of b, one after the last pass.
+        assert_eq!(count(st_difference_interruptible), 5);
+        // The union folds the single polygon of a into b, in one pass.
+        assert_eq!(count(st_union_interruptible), 2);
+        // One pass, polled before and after.
+        assert_eq!(count(st_sym_difference_interruptible), 2);
+
+        let polls = Cell::new(0);
+        let hook = || {
+            polls.set(polls.get() + 1);
+            polls.get() > 2
+        };
+        let err = st_difference_interruptible(&a, &b, &hook).unwrap_err();
+        assert!(matches!(err, SqliteGisError::Interrupted));
+        assert_eq!(polls.get(), 3);
+
+        assert!((st_area(&st_difference(&a, &b).unwrap()).unwrap() - 97.0).abs() < 1e-10);
+        assert!((st_area(&st_sym_difference(&a, &b).unwrap()).unwrap() - 97.0).abs() < 1e-10);
+        assert!((st_area(&st_union(&a, &b).unwrap()).unwrap() - 100.0).abs() < 1e-10);
+    }
+
+    #[test]
+    fn plain_overlays_run_one_whole_geometry_pass() {
+        let a = geom_from_text(
+            "MULTIPOLYGON(((0 0,4 0,4 4,0 4,0 0)),((10 0,14 0,14 4,10 4,10 0)))",
+            None,
+        )
+        .unwrap();
+        let b = geom_from_text(
+            "MULTIPOLYGON(((2 2,12 2,12 6,2 6,2 2)),((1 -1,3 -1,3 1,1 1,1 -1)))",
+            None,
+        )
+        .unwrap();
+        let (ga, _) = parse_ewkb(&a).unwrap();
+        let (gb, _) = parse_ewkb(&b).unwrap();
+        let (Geometry::MultiPolygon(ma), Geometry::MultiPolygon(mb)) = (ga, gb) else {
+            panic!("not multipolygons")
+        };
+        let whole = |m: MultiPolygon<f64>| write_ewkb(&Geometry::MultiPolygon(m), None).unwrap();
+        assert_eq!(st_union(&a, &b).unwrap(), whole(ma.union(&mb)));
+        assert_eq!(st_difference(&a, &b).unwrap(), whole(ma.difference(&mb)));
+        assert_eq!(st_sym_difference(&a, &b).unwrap(), whole(ma.xor(&mb)));
+        assert_eq!(
+            st_union_interruptible(&a, &b, &NeverInterrupt).unwrap(),
+            whole(ma.union(&mb))
+        );
+        assert_eq!(
+            st_difference_interruptible(&a, &b, &NeverInterrupt).unwrap(),
+            whole(ma.difference(&mb))
+        );
+    }
+
     #[test]
     fn union_wrong_type() {
         let line = geom_from_text("LINESTRING(0 0,1 1)", None).unwrap();
@@ -114,6 +114,21 @@ pub fn st_disjoint(a: &[u8], b: &[u8]) -> Result<bool> {
     Ok(!st_intersects(a, b)?)
 }
 
+/// Reject non-finite or negative `ST_DWithin*` distance thresholds.
+pub(crate) fn ensure_dwithin_distance(distance: f64, fn_name: &str) -> Result<()> {
+    if !distance.is_finite() {
+        return Err(SqliteGisError::InvalidInput(format!(
+            "{fn_name}: distance must be finite"
+        )));
+    }
+    if distance < 0.0 {
+        return Err(SqliteGisError::InvalidInput(format!(
+            "{fn_name}: distance must be non-negative"
+        )));
+    }
+    Ok(())
+}
+
 /// ST_DWithin: true if the geometries are within `distance` of each other (Euclidean).
 ///
 /// # Example
@@ -129,16 +144,7 @@ pub fn st_disjoint(a: &[u8], b: &[u8]) -> Result<bool> {
 /// ```
 pub fn st_dwithin(a: &[u8], b: &[u8], distance: f64) -> Result<bool> {
     use super::measurement::st_distance;
-    if !distance.is_finite() {
-        return Err(SqliteGisError::InvalidInput(
-            "ST_DWithin: distance must be finite".to_string(),
-        ));
-    }
-    if distance < 0.0 {
-        return Err(SqliteGisError::InvalidInput(
-            "ST_DWithin: distance must be non-negative".to_string(),
-        ));
-    }
+    ensure_dwithin_distance(distance, "ST_DWithin")?;
     Ok(st_distance(a, b)? <= distance)
 }
 
@@ -158,16 +164,7 @@ pub fn st_dwithin(a: &[u8], b: &[u8], distance: f64) -> Result<bool> {
 /// ```
 pub fn st_dwithin_sphere(a: &[u8], b: &[u8], distance: f64) -> Result<bool> {
     use super::measurement::st_distance_sphere;
-    if !distance.is_finite() {
-        return Err(SqliteGisError::InvalidInput(
-            "ST_DWithinSphere: distance must be finite".to_string(),
-        ));
-    }
-    if distance < 0.0 {
-        return Err(SqliteGisError::InvalidInput(
-            "ST_DWithinSphere: distance must be non-negative".to_string(),
-        ));
-    }
+    ensure_dwithin_distance(distance, "ST_DWithinSphere")?;
     Ok(st_distance_sphere(a, b)? <= distance)
 }
 
@@ -187,16 +184,7 @@ pub fn st_dwithin_sphere(a: &[u8], b: &[u8], distance: f64) -> Result<bool> {
 /// ```
 pub fn st_dwithin_spheroid(a: &[u8], b: &[u8], distance: f64) -> Result<bool> {
     use super::measurement::st_distance_spheroid;
-    if !distance.is_finite() {
-        return Err(SqliteGisError::InvalidInput(
-            "ST_DWithinSpheroid: distance must be finite".to_string(),
-        ));
-    }
-    if distance < 0.0 {
-        return Err(SqliteGisError::InvalidInput(
-            "ST_DWithinSpheroid: distance must be non-negative".to_string(),
-        ));
-    }
+    ensure_dwithin_distance(distance, "ST_DWithinSpheroid")?;
     Ok(st_distance_spheroid(a, b)? <= distance)
 }
 
@@ -0,0 +1,1033 @@
+//! GeoParquet reader and writer.
+//!
+//! A GeoParquet file is a Parquet file whose geometry columns hold WKB,
+//! described by a JSON document under the `geo` key of the file's
+//! key-value metadata (GeoParquet 1.1). [`GeoParquetWriter`] writes one
+//! `geometry` column of ISO WKB, converted from EWKB, plus one column per
+//! attribute, and records the encoding, the geometry types, the bounding
+//! box and the CRS. [`GeoParquetReader`] reads the columns back and turns
+//! WKB into EWKB carrying the SRID derived from the recorded CRS.
+//!
+//! The CRS and the SRID map onto each other as follows: SRID 4326 is the
+//! spec's default `OGC:CRS84` and leaves `crs` out, a geometry without an
+//! SRID writes `"crs": null`, and the other SRIDs [`projjson_for_srid`]
+//! describes (NAD83, ETRS89, Web Mercator and the WGS 84, NAD83 and ETRS89
+//! UTM zones) write their PROJJSON definition. The writer rejects any
+//! other SRID, since readers need the full definition, not just the EPSG
+//! code, to place the coordinates. On read, a missing `crs`
+//! gives SRID 4326, `null` gives none, and otherwise the SRID is the
+//! `id` code (or `OGC:CRS84`, which is 4326). Only the `WKB` encoding is
+//! read; GeoArrow native encodings are rejected.
+//!
+//! Attribute columns map onto [`Value`]: booleans and integers become
+//! `Integer`, floats `Real`, strings, JSON and enums `Text`, other byte
+//! arrays `Blob`, and dates, times, timestamps, decimals and nested
+//! columns their text rendering. The writer declares `INT64`, `DOUBLE`,
+//! UTF-8 `BYTE_ARRAY` and plain `BYTE_ARRAY` columns, Snappy compressed.
+//!
+//! ```
+//! use std::fs::File;
+//! use sqlitegis::core::features::{Feature, Field, FieldType, Value};
+//! use sqlitegis::core::functions::io::geom_from_text;
+//! use sqlitegis::core::geoparquet::{GeoParquetReader, GeoParquetWriter};
+//!
+//! let path = std::env::temp_dir().join("sqlitegis-geoparquet-doc.parquet");
+//! let fields = vec![Field::new("name", FieldType::Text)];
+//! let mut writer = GeoParquetWriter::new(File::create(&path).unwrap(), "geometry", fields).unwrap();
+//! writer
+//!     .add_feature(&Feature {
+//!         geometry: Some(geom_from_text("POINT(1 2)", Some(3857)).unwrap()),
+//!         properties: vec![Value::Text("a".into())],
+//!     })
+//!     .unwrap();
+//! assert_eq!(writer.finish().unwrap(), 1);
+//!
+//! let mut reader = GeoParquetReader::open(File::open(&path).unwrap()).unwrap();
+//! assert_eq!(reader.srid(), Some(3857));
+//! assert_eq!(reader.fields()[0].name, "name");
+//! let feature = reader.next().unwrap().unwrap();
+//! assert_eq!(feature.properties, vec![Value::Text("a".into())]);
+//! assert!(reader.next().is_none());
+//! # std::fs::remove_file(&path).unwrap();
+//! ```
+
+use std::io::Write;
+use std::sync::Arc;
+
+use parquet::basic::{Compression, ConvertedType, LogicalType, Repetition, Type as PhysicalType};
+use parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type};
+use parquet::errors::ParquetError;
+use parquet::file::metadata::KeyValue;
+use parquet::file::properties::WriterProperties;
+use parquet::file::reader::{ChunkReader, FileReader, SerializedFileReader};
+use parquet::file::writer::SerializedFileWriter;
+use parquet::record::reader::RowIter;
+use parquet::record::Field as ParquetField;
+use parquet::schema::types::{Type, TypePtr};
+use serde_json::{json, Map, Value as Json};
+
+use crate::core::error::{Result, SqliteGisError};
+use crate::core::ewkb::{ensure_matching_srid, extract_mbr, parse_ewkb_header};
+use crate::core::features::{Feature, Field, FieldType, Value};
+use crate::core::functions::io::{as_binary, geom_from_wkb};
+
+/// Key of the GeoParquet metadata in the file's key-value metadata.
+pub const GEO_METADATA_KEY: &str = "geo";
+
+/// GeoParquet specification version the writer declares.
+pub const GEOPARQUET_VERSION: &str = "1.1.0";
+
+/// Rows buffered per row group before the writer flushes it.
+pub const DEFAULT_ROW_GROUP_SIZE: usize = 65_536;
+
+/// SRID of `OGC:CRS84`, the CRS a column without `crs` metadata is in.
+const CRS84_SRID: i32 = 4326;
+
+/// Schema URL [`projjson_for_srid`] declares.
+const PROJJSON_SCHEMA: &str = "https://proj.org/schemas/v0.7/projjson.schema.json";
+
+/// GeoParquet names of the geometry types, indexed by WKB type code.
+const GEOMETRY_TYPE_NAMES: [&str; 7] = [
+    "Point",
+    "LineString",
+    "Polygon",
+    "MultiPoint",
+    "MultiLineString",
+    "MultiPolygon",
+    "GeometryCollection",
+];
+
+fn parquet_error(e: ParquetError) -> SqliteGisError {
+    SqliteGisError::InvalidInput(format!("parquet error: {e}"))
+}
+
+/// Writes features to a GeoParquet file.
+///
+/// Rows are buffered and written a row group at a time; the `geo`
+/// metadata, which needs the bounding box of every geometry, is written
+/// by [`GeoParquetWriter::finish`].
+pub struct GeoParquetWriter<W: Write + Send> {
+    writer: SerializedFileWriter<W>,
+    geometry_column: String,
+    fields: Vec<Field>,
+    row_group_size: usize,
+    rows: Vec<Feature>,
+    written: u64,
+    srid: Option<Option<i32>>,
+    geometry_types: [bool; 7],
+    bbox: Option<[f64; 4]>,
+}
+
+impl<W: Write + Send> GeoParquetWriter<W> {
+    /// A writer to `out` with a WKB column named `geometry_column` followed
+    /// by the given attribute columns.
+    pub fn new(out: W, geometry_column: impl Into<String>, fields: Vec<Field>) -> Result<Self> {
+        let geometry_column = geometry_column.into();
+        if let Some(field) = fields.iter().find(|f| f.name == geometry_column) {
+            return Err(SqliteGisError::InvalidInput(format!(
+                "attribute [{}] clashes with the geometry column",
+                field.name
+            )));
+        }
+        let schema = build_schema(&geometry_column, &fields).map_err(parquet_error)?;
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let writer =
+            SerializedFileWriter::new(out, schema, Arc::new(properties)).map_err(parquet_error)?;
+        Ok(Self {
+            writer,
+            geometry_column,
+            fields,
+            row_group_size: DEFAULT_ROW_GROUP_SIZE,
+            rows: Vec::new(),
+            written: 0,
+            srid: None,
+            geometry_types: [false; 7],
+            bbox: None,
+        })
+    }
+
+    /// Set the number of rows per row group; must be at least 1.
+    pub fn set_row_group_size(&mut self, row_group_size: usize) -> Result<()> {
+        if row_group_size == 0 {
+            return Err(SqliteGisError::InvalidInput(
+                "row group size must be at least 1".to_string(),
+            ));
+        }
+        self.row_group_size = row_group_size;
+        Ok(())
+    }
+
+    /// Add one feature. It needs one property per field, converted to the
+    /// field's type as by [`Value::coerce`], and every geometry of a file
+    /// must share one SRID.
+    pub fn add_feature(&mut self, feature: &Feature) -> Result<()> {
+        if feature.properties.len() != self.fields.len() {
+            return Err(SqliteGisError::InvalidInput(format!(
+                "feature has {} properties but the dataset has {} columns",
+                feature.properties.len(),
+                self.fields.len()
+            )));
+        }
+
+        let geometry = match &feature.geometry {
+            Some(blob) => {
+                let header = parse_ewkb_header(blob)?;
+                match self.srid {
+                    Some(shared) => {
+                        ensure_matching_srid(shared, header.srid)?;
+                    }
+                    None => {
+                        if let Some(srid) = header.srid {
+                            if srid != 0 && srid != CRS84_SRID && projjson_for_srid(srid).is_none()
+                            {
+                                return Err(SqliteGisError::InvalidInput(format!(
+                                    "GeoParquet needs a PROJJSON definition of the CRS and \
+                                     SRID {srid} has none; transform to a supported SRID \
+                                     such as 4326 or 3857 first"
+                                )));
+                            }
+                        }
+                        self.srid = Some(header.srid);
+                    }
+                }
+                let wkb = as_binary(blob)?;
+                if let Some(seen) = (header.geom_type as usize)
+                    .checked_sub(1)
+                    .and_then(|i| self.geometry_types.get_mut(i))
+                {
+                    *seen = true;
+                }
+                if let Some(rect) = extract_mbr(blob)? {
+                    let (min, max) = (rect.min(), rect.max());
+                    self.bbox = Some(match self.bbox {
+                        Some([x0, y0, x1, y1]) => {
+                            [x0.min(min.x), y0.min(min.y), x1.max(max.x), y1.max(max.y)]
+                        }
+                        None => [min.x, min.y, max.x, max.y],
+                    });
+                }
+                Some(wkb)
+            }
+            None => None,
+        };
+        self.rows.push(Feature {
+            geometry,
+            properties: feature
+                .properties
+                .iter()
+                .zip(&self.fields)
+                .map(|(value, field)| value.coerce(field.field_type))
+                .collect(),
+        });
+        if self.rows.len() >= self.row_group_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write the buffered rows as one row group.
+    fn flush(&mut self) -> Result<()> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+        let rows = std::mem::take(&mut self.rows);
+        let mut row_group = self.writer.next_row_group().map_err(parquet_error)?;
+
+        let mut column = row_group
+            .next_column()
+            .map_err(parquet_error)?
+            .expect("schema has a geometry column");
+        let levels: Vec<i16> = rows
+            .iter()
+            .map(|row| i16::from(row.geometry.is_some()))
+            .collect();
+        let values: Vec<ByteArray> = rows
+            .iter()
+            .filter_map(|row| row.geometry.clone().map(ByteArray::from))
+            .collect();
+        column
+            .typed::<ByteArrayType>()
+            .write_batch(&values, Some(&levels), None)
+            .map_err(parquet_error)?;
+        column.close().map_err(parquet_error)?;
+
+        for (i, field) in self.fields.iter().enumerate() {
+            let mut column = row_group
+                .next_column()
+                .map_err(parquet_error)?
+                .expect("schema has one column per field");
+            let levels: Vec<i16> = rows
+                .iter()
+                .map(|row| i16::from(row.properties[i] != Value::Null))
+                .collect();
+            let values = rows.iter().map(|row| &row.properties[i]);
+            let written = match field.field_type {
+                FieldType::Integer => {
+                    let values: Vec<i64> = values
+                        .filter_map(|v| match v {
+                            Value::Integer(i) => Some(*i),
+                            _ => None,
+                        })
+                        .collect();
+                    column
+                        .typed::<Int64Type>()
+                        .write_batch(&values, Some(&levels), None)
+                }
+                FieldType::Real => {
+                    let values: Vec<f64> = values
+                        .filter_map(|v| match v {
+                            Value::Real(r) => Some(*r),
+                            _ => None,
+                        })
+                        .collect();
+                    column
+                        .typed::<DoubleType>()
+                        .write_batch(&values, Some(&levels), None)
+                }
+                FieldType::Text | FieldType::Blob => {
+                    let values: Vec<ByteArray> = values
+                        .filter_map(|v| match v {
+                            Value::Text(t) => Some(ByteArray::from(t.as_str())),
+                            Value::Blob(b) => Some(ByteArray::from(b.clone())),
+                            _ => None,
+                        })
+                        .collect();
+                    column
+                        .typed::<ByteArrayType>()
+                        .write_batch(&values, Some(&levels), None)
+                }
+            };
+            written.map_err(parquet_error)?;
+            column.close().map_err(parquet_error)?;
+        }
+        row_group.close().map_err(parquet_error)?;
+        self.written += rows.len() as u64;
+        Ok(())
+    }
+
+    /// Write the remaining rows and the `geo` metadata, close the file and
+    /// return the number of rows written.
+    pub fn finish(mut self) -> Result<u64> {
+        self.flush()?;
+        let metadata = self.geo_metadata();
+        self.writer
+            .append_key_value_metadata(KeyValue::new(GEO_METADATA_KEY.to_string(), metadata));
+        self.writer.close().map_err(parquet_error)?;
+        Ok(self.written)
+    }
+
+    fn geo_metadata(&self) -> String {
+        let geometry_types: Vec<&str> = GEOMETRY_TYPE_NAMES
+            .iter()
+            .zip(self.geometry_types)
+            .filter(|&(_, seen)| seen)
+            .map(|(&name, _)| name)
+            .collect();
+        let mut column = Map::new();
+        column.insert("encoding".to_string(), json!("WKB"));
+        column.insert("geometry_types".to_string(), json!(geometry_types));
+        if let Some(bbox) = self.bbox {
+            column.insert("bbox".to_string(), json!(bbox));
+        }
+        match self.srid.flatten() {
+            Some(CRS84_SRID) => {}
+            None | Some(0) => {
+                column.insert("crs".to_string(), Json::Null);
+            }
+            Some(srid) => {
+                if let Some(crs) = projjson_for_srid(srid) {
+                    column.insert("crs".to_string(), crs);
+                }
+            }
+        }
+        json!({
+            "version": GEOPARQUET_VERSION,
+            "primary_column": self.geometry_column,
+            "columns": { self.geometry_column.clone(): column },
+        })
+        .to_string()
+    }
+}
+
+fn build_schema(geometry_column: &str, fields: &[Field]) -> parquet::errors::Result<TypePtr> {
+    let mut columns = vec![Arc::new(
+        Type::primitive_type_builder(geometry_column, PhysicalType::BYTE_ARRAY)
+            .with_repetition(Repetition::OPTIONAL)
+            .build()?,
+    )];
+    for field in fields {
+        let (physical_type, logical_type) = match field.field_type {
+            FieldType::Integer => (PhysicalType::INT64, None),
+            FieldType::Real => (PhysicalType::DOUBLE, None),
+            FieldType::Text => (PhysicalType::BYTE_ARRAY, Some(LogicalType::String)),
+            FieldType::Blob => (PhysicalType::BYTE_ARRAY, None),
+        };
+        columns.push(Arc::new(
+            Type::primitive_type_builder(&field.name, physical_type)
+                .with_repetition(Repetition::OPTIONAL)
+                .with_logical_type(logical_type)
+                .build()?,
+        ));
+    }
+    Ok(Arc::new(
+        Type::group_type_builder("schema")
+            .with_fields(columns)
+            .build()?,
+    ))
+}
+
+/// Reads features from a GeoParquet file.
+///
+/// The geometry of each feature is the primary geometry column; other
+/// columns listed in the `geo` metadata come back as `Blob` fields of
+/// EWKB, and the remaining columns as attribute fields.
+pub struct GeoParquetReader {
+    rows: RowIter<'static>,
+    fields: Vec<Field>,
+    geometry_column: String,
+    geometry_index: usize,
+    geometry_columns: Vec<bool>,
+    srid: Option<i32>,
+}
+
+impl GeoParquetReader {
+    /// Read the schema and the `geo` metadata of the file in `reader`.
+    pub fn open<R: ChunkReader + 'static>(reader: R) -> Result<Self> {
+        let reader = SerializedFileReader::new(reader).map_err(parquet_error)?;
+        let file_metadata = reader.metadata().file_metadata();
+        let geo = file_metadata
+            .key_value_metadata()
+            .and_then(|kv| kv.iter().find(|kv| kv.key == GEO_METADATA_KEY))
+            .and_then(|kv| kv.value.as_deref())
+            .ok_or_else(|| {
+                SqliteGisError::InvalidInput("file has no GeoParquet metadata".to_string())
+            })?;
+        let geo: Json = serde_json::from_str(geo).map_err(|e| {
+            SqliteGisError::InvalidInput(format!("invalid GeoParquet metadata: {e}"))
+        })?;
+        let invalid =
+            |what: &str| SqliteGisError::InvalidInput(format!("GeoParquet metadata {what}"));
+        let geometry_column = geo["primary_column"]
+            .as_str()
+            .ok_or_else(|| invalid("has no primary_column"))?
+            .to_string();
+        let columns = geo["columns"]
+            .as_object()
+            .ok_or_else(|| invalid("has no columns"))?;
+        let mut srid = None;
+        for (name, column) in columns {
+            let encoding = column["encoding"].as_str().unwrap_or_default();
+            if !encoding.eq_ignore_ascii_case("WKB") {
+                return Err(SqliteGisError::InvalidInput(format!(
+                    "column [{name}] has unsupported encoding [{encoding}], only WKB is supported"
+                )));
+            }
+            if *name == geometry_column {
+                srid = srid_from_crs(column.get("crs"))?;
+            }
+        }
+        if !columns.contains_key(&geometry_column) {
+            return Err(invalid("does not describe the primary column"));
+        }
+
+        let schema = file_metadata.schema_descr().root_schema().get_fields();
+        let geometry_index = schema
+            .iter()
+            .position(|ty| ty.name() == geometry_column)
+            .ok_or_else(|| {
+                SqliteGisError::InvalidInput(format!(
+                    "primary column [{geometry_column}] is missing from the file"
+                ))
+            })?;
+        let geometry_columns: Vec<bool> = schema
+            .iter()
+            .map(|ty| columns.contains_key(ty.name()))
+            .collect();
+        let fields = schema
+            .iter()
+            .zip(&geometry_columns)
+            .enumerate()
+            .filter(|&(i, _)| i != geometry_index)
+            .map(|(_, (ty, &is_geometry))| {
+                let field_type = if is_geometry {
+                    FieldType::Blob
+                } else {
+                    field_type(ty)
+                };
+                Field::new(ty.name(), field_type)
+            })
+            .collect();
+
+        Ok(Self {
+            rows: RowIter::from_file_into(Box::new(reader)),
+            fields,
+            geometry_column,
+            geometry_index,
+            geometry_columns,
+            srid,
+        })
+    }
+
+    /// Attribute fields, in column order, without the primary geometry
+    /// column.
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Name of the primary geometry column.
+    pub fn geometry_column(&self) -> &str {
+        &self.geometry_column
+    }
+
+    /// SRID derived from the primary column's CRS.
+    pub fn srid(&self) -> Option<i32> {
+        self.srid
+    }
+
+    fn read_row(&self, row: parquet::record::Row) -> Result<Feature> {
+        let mut geometry = None;
+        let mut properties = Vec::with_capacity(self.fields.len());
+        for (i, (name, value)) in row.get_column_iter().enumerate() {
+            if !self.geometry_columns[i] {
+                let field = &self.fields[properties.len()];
+                properties.push(value_from_parquet(value).coerce(field.field_type));
+                continue;
+            }
+            let blob = match value {
+                ParquetField::Null => None,
+                ParquetField::Bytes(wkb) => Some(geom_from_wkb(wkb.data(), self.srid)?),
+                _ => {
+                    return Err(SqliteGisError::InvalidInput(format!(
+                        "geometry column [{name}] is not a binary column"
+                    )))
+                }
+            };
+            if i == self.geometry_index {
+                geometry = blob;
+            } else {
+                properties.push(blob.map_or(Value::Null, Value::Blob));
+            }
+        }
+        Ok(Feature {
+            geometry,
+            properties,
+        })
+    }
+}
+
+impl Iterator for GeoParquetReader {
+    type Item = Result<Feature>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let row = self.rows.next()?;
+        Some(
+            row.map_err(parquet_error)
+                .and_then(|row| self.read_row(row)),
+        )
+    }
+}
+
+/// Geographic CRSs [`projjson_for_srid`] knows: SRID, name, datum,
+/// ellipsoid and its inverse flattening.
+const GEOGRAPHIC_CRS: [(i32, &str, &str, &str, f64); 3] = [
+    (
+        4326,
+        "WGS 84",
+        "World Geodetic System 1984",
+        "WGS 84",
+        298.257223563,
+    ),
+    (
+        4269,
+        "NAD83",
+        "North American Datum 1983",
+        "GRS 1980",
+        298.257222101,
+    ),
+    (
+        4258,
+        "ETRS89",
+        "European Terrestrial Reference System 1989",
+        "GRS 1980",
+        298.257222101,
+    ),
+];
+
+/// UTM zone families: SRID of zone 0, zones, the geographic SRID they
+/// project and whether they are the southern zones.
+const UTM_ZONES: [(i32, std::ops::RangeInclusive<i32>, i32, bool); 4] = [
+    (32600, 1..=60, 4326, false),
+    (32700, 1..=60, 4326, true),
+    (26900, 1..=23, 4269, false),
+    (25800, 28..=38, 4258, false),
+];
+
+/// PROJJSON of the geographic CRS `srid`, without `$schema`.
+fn geographic_projjson(srid: i32) -> Option<Json> {
+    let (_, name, datum, ellipsoid, inverse_flattening) =
+        GEOGRAPHIC_CRS.iter().find(|crs| crs.0 == srid)?;
+    Some(json!({
+        "type": "GeographicCRS",
+        "name": name,
+        "datum": {
+            "type": "GeodeticReferenceFrame",
+            "name": datum,
+            "ellipsoid": {
+                "name": ellipsoid,
+                "semi_major_axis": 6378137,
+                "inverse_flattening": inverse_flattening,
+            },
+        },
+        "coordinate_system": {
+            "subtype": "ellipsoidal",
+            "axis": [
+                { "name": "Geodetic latitude", "abbreviation": "Lat", "direction": "north", "unit": "degree" },
+                { "name": "Geodetic longitude", "abbreviation": "Lon", "direction": "east", "unit": "degree" },
+            ],
+        },
+        "id": { "authority": "EPSG", "code": srid },
+    }))
+}
+
+/// A projection parameter with its EPSG code.
+fn projjson_parameter(name: &str, code: i32, value: f64, unit: &str) -> Json {
+    json!({
+        "name": name,
+        "value": value,
+        "unit": unit,
+        "id": { "authority": "EPSG", "code": code },
+    })
+}
+
+/// PROJJSON definition of `srid`, the value a GeoParquet `crs` needs:
+/// WGS 84, NAD83, ETRS89, Web Mercator and their UTM zones (the CRSs the
+/// shapefile writer describes in a `.prj`). `None` for any other SRID.
+pub fn projjson_for_srid(srid: i32) -> Option<Json> {
+    let (name, base, conversion, method, parameters) = if srid == 3857 {
+        (
+            "WGS 84 / Pseudo-Mercator".to_string(),
+            4326,
+            "Popular Visualisation Pseudo-Mercator".to_string(),
+            ("Popular Visualisation Pseudo Mercator", 1024),
+            vec![
+                projjson_parameter("Latitude of natural origin", 8801, 0.0, "degree"),
+                projjson_parameter("Longitude of natural origin", 8802, 0.0, "degree"),
+                projjson_parameter("False easting", 8806, 0.0, "metre"),
+                projjson_parameter("False northing", 8807, 0.0, "metre"),
+            ],
+        )
+    } else if let Some((zero, _, base, south)) = UTM_ZONES
+        .iter()
+        .find(|(zero, zones, _, _)| zones.contains(&(srid - zero)))
+    {
+        let zone = format!("UTM zone {}{}", srid - zero, if *south { 'S' } else { 'N' });
+        let base_name = GEOGRAPHIC_CRS.iter().find(|crs| crs.0 == *base)?.1;
+        (
+            format!("{base_name} / {zone}"),
+            *base,
+            zone,
+            ("Transverse Mercator", 9807),
+            vec![
+                projjson_parameter("Latitude of natural origin", 8801, 0.0, "degree"),
+                projjson_parameter(
+                    "Longitude of natural origin",
+                    8802,
+                    f64::from((srid - zero) * 6 - 183),
+                    "degree",
+                ),
+                projjson_parameter("Scale factor at natural origin", 8805, 0.9996, "unity"),
+                projjson_parameter("False easting", 8806, 500_000.0, "metre"),
+                projjson_parameter(
+                    "False northing",
+                    8807,
+                    if *south { 10_000_000.0 } else { 0.0 },
+                    "metre",
+                ),
+            ],
+        )
+    } else {
+        let mut crs = geographic_projjson(srid)?;
+        crs["$schema"] = json!(PROJJSON_SCHEMA);
+        return Some(crs);
+    };
+    Some(json!({
+        "$schema": PROJJSON_SCHEMA,
+        "type": "ProjectedCRS",
+        "name": name,
+        "base_crs": geographic_projjson(base)?,
+        "conversion": {
+            "name": conversion,
+            "method": {
+                "name": method.0,
+                "id": { "authority": "EPSG", "code": method.1 },
+            },
+            "parameters": parameters,
+        },
+        "coordinate_system": {
+            "subtype": "Cartesian",
+            "axis": [
+                { "name": "Easting", "abbreviation": "E", "direction": "east", "unit": "metre" },
+                { "name": "Northing", "abbreviation": "N", "direction": "north", "unit": "metre" },
+            ],
+        },
+        "id": { "authority": "EPSG", "code": srid },
+    }))
+}
+
+/// SRID of a GeoParquet `crs` entry: `None` (key absent) is `OGC:CRS84`,
+/// `null` is an undefined CRS, and a PROJJSON object or a string names an
+/// authority code.
+fn srid_from_crs(crs: Option<&Json>) -> Result<Option<i32>> {
+    let Some(crs) = crs else {
+        return Ok(Some(CRS84_SRID));
+    };
+    let (authority, code) = match crs {
+        Json::Null => return Ok(None),
+        Json::String(s) => match s.split_once(':') {
+            Some((authority, code)) => (authority.to_string(), Json::from(code)),
+            None => (String::new(), Json::Null),
+        },
+        Json::Object(object) => {
+            let id = object
+                .get("id")
+                .or_else(|| object.get("ids").and_then(|ids| ids.get(0)));
+            match id {
+                Some(id) => (
+                    id["authority"].as_str().unwrap_or_default().to_string(),
+                    id["code"].clone(),
+                ),
+                None => (String::new(), Json::Null),
+            }
+        }
+        _ => (String::new(), Json::Null),
+    };
+    let code = match &code {
+        Json::Number(n) => n.as_i64().map(|n| n.to_string()),
+        Json::String(s) => Some(s.clone()),
+        _ => None,
+    };
+    match (authority.to_ascii_uppercase().as_str(), code.as_deref()) {
+        ("OGC", Some("CRS84")) => Ok(Some(CRS84_SRID)),
+        (_, Some(code)) => code.parse::<i32>().map(Some).map_err(|_| {
+            SqliteGisError::InvalidInput(format!(
+                "cannot derive an SRID from CRS {authority}:{code}"
+            ))
+        }),
+        _ => Err(SqliteGisError::InvalidInput(
+            "cannot derive an SRID from a CRS without an authority code".to_string(),
+        )),
+    }
+}
+
+/// Attribute type of a top-level Parquet column.
+fn field_type(ty: &Type) -> FieldType {
+    if !ty.is_primitive() {
+        return FieldType::Text;
+    }
+    let info = ty.get_basic_info();
+    let logical_type = info.logical_type();
+    let converted_type = info.converted_type();
+    match ty.get_physical_type() {
+        PhysicalType::BOOLEAN => FieldType::Integer,
+        PhysicalType::INT32 | PhysicalType::INT64 => match (logical_type, converted_type) {
+            (None | Some(LogicalType::Integer { .. }), _)
+                if matches!(
+                    converted_type,
+                    ConvertedType::NONE
+                        | ConvertedType::INT_8
+                        | ConvertedType::INT_16
+                        | ConvertedType::INT_32
+                        | ConvertedType::INT_64
+                        | ConvertedType::UINT_8
+                        | ConvertedType::UINT_16
+                        | ConvertedType::UINT_32
+                        | ConvertedType::UINT_64
+                ) =>
+            {
+                FieldType::Integer
+            }
+            _ => FieldType::Text,
+        },
+        PhysicalType::INT96 => FieldType::Text,
+        PhysicalType::FLOAT | PhysicalType::DOUBLE => FieldType::Real,
+        PhysicalType::BYTE_ARRAY | PhysicalType::FIXED_LEN_BYTE_ARRAY => {
+            match (logical_type, converted_type) {
+                (Some(LogicalType::Float16), _) => FieldType::Real,
+                (Some(LogicalType::String | LogicalType::Json | LogicalType::Enum), _)
+                | (Some(LogicalType::Decimal { .. }), _)
+                | (
+                    None,
+                    ConvertedType::UTF8
+                    | ConvertedType::JSON
+                    | ConvertedType::ENUM
+                    | ConvertedType::DECIMAL,
+                ) => FieldType::Text,
+                _ => FieldType::Blob,
+            }
+        }
+    }
+}
+
+/// A Parquet record field as a [`Value`], before coercion to the column's
+/// [`FieldType`].
+fn value_from_parquet(field: &ParquetField) -> Value {
+    match field {
+        ParquetField::Null => Value::Null,
+        ParquetField::Bool(b) => Value::Integer(i64::from(*b)),
+        ParquetField::Byte(i) => Value::Integer(i64::from(*i)),
+        ParquetField::Short(i) => Value::Integer(i64::from(*i)),
+        ParquetField::Int(i) => Value::Integer(i64::from(*i)),
+        ParquetField::Long(i) => Value::Integer(*i),
+        ParquetField::UByte(i) => Value::Integer(i64::from(*i)),
+        ParquetField::UShort(i) => Value::Integer(i64::from(*i)),
+        ParquetField::UInt(i) => Value::Integer(i64::from(*i)),
+        ParquetField::ULong(i) => match i64::try_from(*i) {
+            Ok(i) => Value::Integer(i),
+            Err(_) => Value::Text(i.to_string()),
+        },
+        ParquetField::Float16(f) => Value::Real(f.to_f64()),
+        ParquetField::Float(f) => Value::Real(f64::from(*f)),
+        ParquetField::Double(f) => Value::Real(*f),
+        ParquetField::Str(s) => Value::Text(s.clone()),
+        ParquetField::Bytes(b) => Value::Blob(b.data().to_vec()),
+        other => Value::Text(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::functions::io::geom_from_text;
+    use std::fs::File;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "sqlitegis-geoparquet-{}-{name}.parquet",
+            std::process::id()
+        ))
+    }
+
+    fn point(wkt: &str, srid: Option<i32>) -> Option<Vec<u8>> {
+        Some(geom_from_text(wkt, srid).unwrap())
+    }
+
+    fn write(path: &PathBuf, features: &[Feature], fields: Vec<Field>) -> u64 {
+        let mut writer =
+            GeoParquetWriter::new(File::create(path).unwrap(), "geometry", fields).unwrap();
+        writer.set_row_group_size(2).unwrap();
+        for feature in features {
+            writer.add_feature(feature).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    fn geo_metadata(path: &PathBuf) -> Json {
+        let reader = SerializedFileReader::new(File::open(path).unwrap()).unwrap();
+        let kv = reader
+            .metadata()
+            .file_metadata()
+            .key_value_metadata()
+            .unwrap();
+        let geo = kv.iter().find(|kv| kv.key == GEO_METADATA_KEY).unwrap();
+        serde_json::from_str(geo.value.as_deref().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn round_trips_features_across_row_groups() {
+        let path = temp_path("round-trip");
+        let fields = vec![
+            Field::new("id", FieldType::Integer),
+            Field::new("score", FieldType::Real),
+            Field::new("name", FieldType::Text),
+            Field::new("raw", FieldType::Blob),
+        ];
+        let features = vec![
+            Feature {
+                geometry: point("POINT(1 2)", Some(3857)),
+                properties: vec![
+                    Value::Integer(1),
+                    Value::Integer(2),
+                    Value::Text("a".into()),
+                    Value::Blob(vec![1, 2]),
+                ],
+            },
+            Feature {
+                geometry: None,
+                properties: vec![Value::Null, Value::Real(0.5), Value::Null, Value::Null],
+            },
+            Feature {
+                geometry: point("LINESTRING(-3 0, 5 9)", Some(3857)),
+                properties: vec![
+                    Value::Integer(3),
+                    Value::Null,
+                    Value::Text("c".into()),
+                    Value::Blob(vec![]),
+                ],
+            },
+        ];
+        assert_eq!(write(&path, &features, fields.clone()), 3);
+
+        let reader = GeoParquetReader::open(File::open(&path).unwrap()).unwrap();
+        assert_eq!(reader.geometry_column(), "geometry");
+        assert_eq!(reader.srid(), Some(3857));
+        assert_eq!(reader.fields(), fields.as_slice());
+        let read: Vec<Feature> = reader.map(|f| f.unwrap()).collect();
+        assert_eq!(read.len(), 3);
+        assert_eq!(read[0].geometry, features[0].geometry);
+        assert_eq!(read[0].properties[1], Value::Real(2.0));
+        assert_eq!(read[1].geometry, None);
+        assert_eq!(read[1].properties, features[1].properties);
+        assert_eq!(read[2], features[2]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn writes_geo_metadata() {
+        let path = temp_path("metadata");
+        let features = vec![
+            Feature {
+                geometry: point("POINT(1 2)", Some(32631)),
+                properties: vec![],
+            },
+            Feature {
+                geometry: point("MULTIPOINT((-1 5),(4 -2))", Some(32631)),
+                properties: vec![],
+            },
+        ];
+        write(&path, &features, vec![]);
+        let geo = geo_metadata(&path);
+        assert_eq!(geo["version"], "1.1.0");
+        assert_eq!(geo["primary_column"], "geometry");
+        let column = &geo["columns"]["geometry"];
+        assert_eq!(column["encoding"], "WKB");
+        assert_eq!(column["geometry_types"], json!(["Point", "MultiPoint"]));
+        assert_eq!(column["bbox"], json!([-1.0, -2.0, 4.0, 5.0]));
+        let crs = &column["crs"];
+        assert_eq!(crs["type"], "ProjectedCRS");
+        assert_eq!(crs["name"], "WGS 84 / UTM zone 31N");
+        assert_eq!(
+            crs["base_crs"]["id"],
+            json!({ "authority": "EPSG", "code": 4326 })
+        );
+        assert_eq!(crs["conversion"]["parameters"][1]["value"], 3.0);
+        assert_eq!(crs["id"], json!({ "authority": "EPSG", "code": 32631 }));
+        let reader = GeoParquetReader::open(File::open(&path).unwrap()).unwrap();
+        assert_eq!(reader.srid(), Some(32631));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn describes_known_srids_and_rejects_others() {
+        let south = projjson_for_srid(32733).unwrap();
+        assert_eq!(south["name"], "WGS 84 / UTM zone 33S");
+        assert_eq!(south["conversion"]["parameters"][4]["value"], 10_000_000.0);
+        assert_eq!(
+            projjson_for_srid(25832).unwrap()["name"],
+            "ETRS89 / UTM zone 32N"
+        );
+        assert_eq!(projjson_for_srid(4269).unwrap()["type"], "GeographicCRS");
+        assert_eq!(
+            projjson_for_srid(3857).unwrap()["conversion"]["method"]["id"]["code"],
+            1024
+        );
+        assert!(projjson_for_srid(2154).is_none());
+        assert!(projjson_for_srid(32661).is_none());
+
+        let path = temp_path("unknown-crs");
+        let mut writer =
+            GeoParquetWriter::new(File::create(&path).unwrap(), "geometry", vec![]).unwrap();
+        let err = writer
+            .add_feature(&Feature {
+                geometry: point("POINT(1 2)", Some(2154)),
+                properties: vec![],
+            })
+            .unwrap_err();
+        assert!(err.to_string().contains("SRID 2154"), "got: {err}");
+        drop(writer);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn maps_crs_to_srid() {
+        assert_eq!(srid_from_crs(None).unwrap(), Some(4326));
+        assert_eq!(srid_from_crs(Some(&Json::Null)).unwrap(), None);
+        assert_eq!(
+            srid_from_crs(Some(&json!("EPSG:32631"))).unwrap(),
+            Some(32631)
+        );
+        assert_eq!(
+            srid_from_crs(Some(&json!("OGC:CRS84"))).unwrap(),
+            Some(4326)
+        );
+        let projjson =
+            json!({ "type": "GeographicCRS", "id": { "authority": "OGC", "code": "CRS84" } });
+        assert_eq!(srid_from_crs(Some(&projjson)).unwrap(), Some(4326));
+        assert!(srid_from_crs(Some(&json!({ "name": "custom" }))).is_err());
+
+        let path = temp_path("crs");
+        write(
+            &path,
+            &[Feature {
+                geometry: point("POINT(1 2)", Some(4326)),
+                properties: vec![],
+            }],
+            vec![],
+        );
+        assert!(geo_metadata(&path)["columns"]["geometry"]
+            .get("crs")
+            .is_none());
+        let reader = GeoParquetReader::open(File::open(&path).unwrap()).unwrap();
+        assert_eq!(reader.srid(), Some(4326));
+        std::fs::remove_file(&path).unwrap();
+
+        write(
+            &path,
+            &[Feature {
+                geometry: point("POINT(1 2)", None),
+                properties: vec![],
+            }],
+            vec![],
+        );
+        assert_eq!(
+            geo_metadata(&path)["columns"]["geometry"]["crs"],
+            Json::Null
+        );
+        let mut reader = GeoParquetReader::open(File::open(&path).unwrap()).unwrap();
+        assert_eq!(reader.srid(), None);
+        let blob = reader.next().unwrap().unwrap().geometry.unwrap();
+        assert_eq!(parse_ewkb_header(&blob).unwrap().srid, None);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_mixed_srids_and_clashing_fields() {
+        let path = temp_path("mixed");
+        let mut writer =
+            GeoParquetWriter::new(File::create(&path).unwrap(), "geometry", vec![]).unwrap();
+        writer
+            .add_feature(&Feature {
+                geometry: point("POINT(1 2)", Some(4326)),
+                properties: vec![],
+            })
+            .unwrap();
+        let err = writer
+            .add_feature(&Feature {
+                geometry: point("POINT(1 2)", Some(3857)),
+                properties: vec![],
+            })
+            .unwrap_err();
+        assert!(matches!(err, SqliteGisError::SridMismatch { .. }));
+        drop(writer);
+
+        let clash = GeoParquetWriter::new(
+            File::create(&path).unwrap(),
+            "geometry",
+            vec![Field::new("geometry", FieldType::Text)],
+        );
+        assert!(clash.is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,288 @@
+//! GeoPackage binary geometry (GPB) encoder and decoder.
+//!
+//! Wire format (GeoPackage 1.3, clause 2.1.3):
+//!   \["GP"\]: magic
+//!   \[u8\]: version, 0 for version 1
+//!   \[u8\]: flags
+//!     Bit 5: extended GeoPackage geometry (not supported)
+//!     Bit 4: empty geometry
+//!     Bits 1-3: envelope contents (0 none, 1 XY, 2 XYZ, 3 XYM, 4 XYZM)
+//!     Bit 0: byte order of the SRID and envelope (1 little-endian)
+//!   \[i32\]: SRS id
+//!   \[f64 * 0/4/6/8\]: envelope, as min/max pairs per axis
+//!   \[rest\]: ISO WKB geometry
+//!
+//! GeoPackage reserves SRS id `0` for undefined geographic and `-1` for
+//! undefined Cartesian coordinates; both map to an EWKB blob without SRID.
+//! A blob without SRID is written with SRS id `0`.
+//!
+//! ```
+//! use sqlitegis::core::ewkb::extract_srid;
+//! use sqlitegis::core::functions::io::geom_from_text;
+//! use sqlitegis::core::gpb::{ewkb_to_gpb, gpb_to_ewkb, is_gpb};
+//!
+//! let blob = geom_from_text("LINESTRING(0 0,2 1)", Some(4326)).unwrap();
+//! let gpb = ewkb_to_gpb(&blob).unwrap();
+//! assert!(is_gpb(&gpb));
+//! assert_eq!(&gpb[..2], b"GP");
+//!
+//! let back = gpb_to_ewkb(&gpb).unwrap();
+//! assert_eq!(back, blob);
+//! assert_eq!(extract_srid(&back), Some(4326));
+//! ```
+
+use geo::{BoundingRect, Geometry};
+
+use crate::core::error::{Result, SqliteGisError};
+use crate::core::ewkb::parse_ewkb;
+use crate::core::functions::emptiness::is_empty_geometry;
+use crate::core::functions::io::{as_binary, geom_from_wkb};
+use crate::core::limits::check_input_bytes;
+
+/// The two bytes every GPB blob starts with.
+pub const GPB_MAGIC: [u8; 2] = *b"GP";
+
+const FLAG_LITTLE_ENDIAN: u8 = 0x01;
+const FLAG_EMPTY: u8 = 0x10;
+const FLAG_EXTENDED: u8 = 0x20;
+const ENVELOPE_SHIFT: u8 = 1;
+const ENVELOPE_MASK: u8 = 0x07;
+
+/// Length of the fixed part of the header: magic, version, flags, SRS id.
+const FIXED_HEADER_LEN: usize = 8;
+
+/// Decoded GPB header.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GpbHeader {
+    /// Format version byte, `0` for GeoPackage 1.x.
+    pub version: u8,
+    /// SRS id as stored; `0` and `-1` mean "undefined".
+    pub srs_id: i32,
+    /// XY part of the envelope as `[minx, maxx, miny, maxy]`, when present.
+    pub envelope: Option<[f64; 4]>,
+    /// Whether the empty-geometry flag is set.
+    pub empty: bool,
+    /// Offset of the WKB payload from the start of the blob.
+    pub header_len: usize,
+}
+
+impl GpbHeader {
+    /// The SRID an EWKB blob decoded from this header carries.
+    pub fn srid(&self) -> Option<i32> {
+        (self.srs_id > 0).then_some(self.srs_id)
+    }
+}
+
+/// Whether `blob` starts with the GPB magic. A valid EWKB blob never does,
+/// since its first byte is a `0x00`/`0x01` byte-order marker.
+///
+/// ```
+/// use sqlitegis::core::functions::constructors::st_point;
+/// use sqlitegis::core::gpb::is_gpb;
+///
+/// assert!(is_gpb(b"GP\x00\x01\x00\x00\x00\x00"));
+/// assert!(!is_gpb(&st_point(1.0, 2.0, None).unwrap()));
+/// ```
+pub fn is_gpb(blob: &[u8]) -> bool {
+    blob.starts_with(&GPB_MAGIC)
+}
+
+/// Decode the GPB header at the start of `blob`.
+///
+/// ```
+/// use sqlitegis::core::functions::io::geom_from_text;
+/// use sqlitegis::core::gpb::{ewkb_to_gpb, parse_gpb_header};
+///
+/// let blob = geom_from_text("LINESTRING(0 0,2 1)", Some(3857)).unwrap();
+/// let header = parse_gpb_header(&ewkb_to_gpb(&blob).unwrap()).unwrap();
+/// assert_eq!(header.srs_id, 3857);
+/// assert_eq!(header.envelope, Some([0.0, 2.0, 0.0, 1.0]));
+/// assert_eq!(header.header_len, 40);
+/// ```
+pub fn parse_gpb_header(blob: &[u8]) -> Result<GpbHeader> {
+    if !is_gpb(blob) {
+        return Err(SqliteGisError::InvalidInput(
+            "not a GeoPackage geometry: missing GP magic".to_string(),
+        ));
+    }
+    if blob.len() < FIXED_HEADER_LEN {
+        return Err(SqliteGisError::InvalidInput(format!(
+            "GeoPackage geometry header truncated: got {} bytes, need at least {FIXED_HEADER_LEN}",
+            blob.len()
+        )));
+    }
+    let version = blob[2];
+    let flags = blob[3];
+    if flags & FLAG_EXTENDED != 0 {
+        return Err(SqliteGisError::InvalidInput(
+            "extended GeoPackage geometries are not supported".to_string(),
+        ));
+    }
+    let little_endian = flags & FLAG_LITTLE_ENDIAN != 0;
+    let srs_bytes = [blob[4], blob[5], blob[6], blob[7]];
+    let srs_id = if little_endian {
+        i32::from_le_bytes(srs_bytes)
+    } else {
+        i32::from_be_bytes(srs_bytes)
+    };
+
+    let doubles = match (flags >> ENVELOPE_SHIFT) & ENVELOPE_MASK {
+        0 => 0,
+        1 => 4,
+        2 | 3 => 6,
+        4 => 8,
+        other => {
+            return Err(SqliteGisError::InvalidInput(format!(
+                "invalid GeoPackage envelope indicator {other}"
+            )))
+        }
+    };
+    let header_len = FIXED_HEADER_LEN + doubles * 8;
+    if blob.len() < header_len {
+        return Err(SqliteGisError::InvalidInput(format!(
+            "GeoPackage geometry header truncated: got {} bytes, need at least {header_len}",
+            blob.len()
+        )));
+    }
+    let envelope = (doubles > 0).then(|| {
+        let mut xy = [0.0; 4];
+        for (i, value) in xy.iter_mut().enumerate() {
+            let start = FIXED_HEADER_LEN + i * 8;
+            let bytes: [u8; 8] = blob[start..start + 8]
+                .try_into()
+                .expect("slice length checked above");
+            *value = if little_endian {
+                f64::from_le_bytes(bytes)
+            } else {
+                f64::from_be_bytes(bytes)
+            };
+        }
+        xy
+    });
+
+    Ok(GpbHeader {
+        version,
+        srs_id,
+        envelope,
+        empty: flags & FLAG_EMPTY != 0,
+        header_len,
+    })
+}
+
+/// Convert a GPB blob into EWKB, keeping its SRS id as the SRID (see the
+/// module docs for the undefined ids).
+pub fn gpb_to_ewkb(blob: &[u8]) -> Result<Vec<u8>> {
+    check_input_bytes(blob.len())?;
+    let header = parse_gpb_header(blob)?;
+    geom_from_wkb(&blob[header.header_len..], header.srid())
+}
+
+/// Convert an EWKB blob into GPB: little-endian, with an XY envelope
+/// except for points and empty geometries (as GDAL writes them), and SRS
+/// id `0` when the blob has no SRID.
+pub fn ewkb_to_gpb(blob: &[u8]) -> Result<Vec<u8>> {
+    let (geom, srid) = parse_ewkb(blob)?;
+    let wkb = as_binary(blob)?;
+    let empty = is_empty_geometry(&geom);
+    let envelope = match geom {
+        Geometry::Point(_) => None,
+        _ if empty => None,
+        _ => geom.bounding_rect(),
+    };
+
+    let mut flags = FLAG_LITTLE_ENDIAN;
+    if empty {
+        flags |= FLAG_EMPTY;
+    }
+    if envelope.is_some() {
+        flags |= 1 << ENVELOPE_SHIFT;
+    }
+    let mut out = Vec::with_capacity(FIXED_HEADER_LEN + 32 + wkb.len());
+    out.extend_from_slice(&GPB_MAGIC);
+    out.push(0);
+    out.push(flags);
+    out.extend_from_slice(&srid.unwrap_or(0).to_le_bytes());
+    if let Some(rect) = envelope {
+        for v in [rect.min().x, rect.max().x, rect.min().y, rect.max().y] {
+            out.extend_from_slice(&v.to_le_bytes());
+        }
+    }
+    out.extend_from_slice(&wkb);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ewkb::extract_srid;
+    use crate::core::functions::io::{as_text, geom_from_text};
+
+    #[test]
+    fn round_trips_every_geometry_type() {
+        for wkt in [
+            "POINT(1 2)",
+            "LINESTRING(0 0,1 1)",
+            "POLYGON((0 0,1 0,1 1,0 0))",
+            "MULTIPOINT((0 0),(1 1))",
+            "GEOMETRYCOLLECTION(POINT(1 1),LINESTRING(0 0,2 2))",
+            "POINT EMPTY",
+            "LINESTRING EMPTY",
+        ] {
+            let blob = geom_from_text(wkt, Some(4326)).unwrap();
+            let gpb = ewkb_to_gpb(&blob).unwrap();
+            let back = gpb_to_ewkb(&gpb).unwrap();
+            assert_eq!(as_text(&back).unwrap(), as_text(&blob).unwrap(), "{wkt}");
+            assert_eq!(extract_srid(&back), Some(4326), "{wkt}");
+        }
+    }
+
+    #[test]
+    fn empty_and_point_geometries_have_no_envelope() {
+        let empty = geom_from_text("LINESTRING EMPTY", None).unwrap();
+        let header = parse_gpb_header(&ewkb_to_gpb(&empty).unwrap()).unwrap();
+        assert!(header.empty);
+        assert_eq!(header.envelope, None);
+        assert_eq!(header.srs_id, 0);
+
+        let point = geom_from_text("POINT(1 2)", None).unwrap();
+        let header = parse_gpb_header(&ewkb_to_gpb(&point).unwrap()).unwrap();
+        assert!(!header.empty);
+        assert_eq!(header.envelope, None);
+        assert_eq!(header.header_len, 8);
+    }
+
+    #[test]
+    fn reads_big_endian_headers_and_undefined_srs_ids() {
+        // Big-endian header, XYZ envelope (ignored beyond XY), SRS id -1.
+        let mut blob = b"GP\x00\x04".to_vec();
+        blob.extend_from_slice(&(-1i32).to_be_bytes());
+        for v in [0.0f64, 1.0, 0.0, 1.0, 0.0, 0.0] {
+            blob.extend_from_slice(&v.to_be_bytes());
+        }
+        let wkb = as_binary(&geom_from_text("POINT(1 1)", None).unwrap()).unwrap();
+        blob.extend_from_slice(&wkb);
+
+        let header = parse_gpb_header(&blob).unwrap();
+        assert_eq!(header.srs_id, -1);
+        assert_eq!(header.envelope, Some([0.0, 1.0, 0.0, 1.0]));
+        let ewkb = gpb_to_ewkb(&blob).unwrap();
+        assert_eq!(extract_srid(&ewkb), None);
+        assert_eq!(as_text(&ewkb).unwrap(), "POINT(1 1)");
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        for blob in [
+            &b"GP\x00"[..],
+            b"GP\x00\x21\x00\x00\x00\x00",
+            b"GP\x00\x0b\x00\x00\x00\x00",
+            b"GP\x00\x03\x00\x00\x00\x00\x00",
+            b"\x01\x01\x00\x00\x00",
+        ] {
+            assert!(
+                matches!(gpb_to_ewkb(blob), Err(SqliteGisError::InvalidInput(_))),
+                "{blob:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,715 @@
+//! In-memory R-tree over caller-keyed EWKB blobs.
+//!
+//! [`SpatialIndex`] is the pure-Rust counterpart of the `*_rtree` shadow
+//! tables that `CreateSpatialIndex` maintains in SQLite. It is bulk-loaded
+//! once from a `(key, blob)` list using each blob's [`extract_mbr`] bounding
+//! box, and every query runs the same two-stage plan the SQL helpers in
+//! `crate::diesel::query_helpers` emit: an R-tree bounding-box prefilter
+//! followed by an exact refinement through the functions in
+//! [`crate::core::functions::predicates`] and
+//! [`crate::core::functions::measurement`].
+//!
+//! Empty geometries have no bounding box and are left out of the tree,
+//! matching the `ST_IsEmpty(...) = 0` filter on the SQL triggers.
+//!
+//! ```
+//! use sqlitegis::core::functions::constructors::st_point;
+//! use sqlitegis::core::index::SpatialIndex;
+//!
+//! let rows = vec![
+//!     ("berlin", st_point(13.4, 52.5, Some(4326)).unwrap()),
+//!     ("paris", st_point(2.35, 48.85, Some(4326)).unwrap()),
+//! ];
+//! let index = SpatialIndex::bulk_load(rows).unwrap();
+//!
+//! let hits = index.dwithin_sphere((13.4, 52.5), 100_000.0).unwrap();
+//! assert_eq!(hits, vec![&"berlin"]);
+//!
+//! let nearest = index.nearest_sphere((13.4, 52.5), 2).unwrap();
+//! assert_eq!(nearest[0].0, &"berlin");
+//! assert_eq!(nearest[1].0, &"paris");
+//! ```
+
+use geo::algorithm::line_measures::metric_spaces::Haversine;
+use rstar::primitives::{GeomWithData, Rectangle};
+use rstar::{RTree, AABB};
+
+use crate::core::error::Result;
+use crate::core::ewkb::{extract_mbr, extract_srid};
+use crate::core::functions::constructors::{st_make_envelope, st_point};
+use crate::core::functions::measurement::st_distance_sphere;
+use crate::core::functions::predicates::{
+    ensure_dwithin_distance, st_dwithin, st_dwithin_sphere, st_intersects,
+};
+
+/// Degree offsets around a point that enclose a spherical cap.
+///
+/// `dlat` is the cap's angular radius and does not depend on latitude.
+/// `dlon` is the widest longitude the cap reaches, which grows toward the
+/// poles because meridians converge: at lat 60 degrees it is roughly twice
+/// the equator value. Once the cap reaches a pole it spans every
+/// longitude, so [`radius_bbox`] sets `dlon` to 180.0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RadiusBbox {
+    /// Half-width of the bounding box in degrees of longitude.
+    pub dlon: f64,
+    /// Half-height of the bounding box in degrees of latitude.
+    pub dlat: f64,
+}
+
+impl RadiusBbox {
+    /// The longitude ranges `lon ± dlon` covers within `-180..=180`: one
+    /// range, or two when the box crosses the antimeridian.
+    ///
+    /// ```rust
+    /// use sqlitegis::core::index::radius_bbox;
+    ///
+    /// let bbox = radius_bbox(0.0, 1_000_000.0);
+    /// assert_eq!(bbox.lon_ranges(0.0).len(), 1);
+    /// let [(west, east), (west2, east2)] = bbox.lon_ranges(175.0)[..] else {
+    ///     panic!("expected two ranges");
+    /// };
+    /// assert!(east == 180.0 && west2 == -180.0 && west < 175.0 && east2 > -180.0);
+    /// ```
+    pub fn lon_ranges(&self, lon: f64) -> Vec<(f64, f64)> {
+        let (west, east) = (lon - self.dlon, lon + self.dlon);
+        if self.dlon >= 180.0 {
+            vec![(-180.0, 180.0)]
+        } else if west < -180.0 {
+            vec![(west + 360.0, 180.0), (-180.0, east)]
+        } else if east > 180.0 {
+            vec![(west, 180.0), (-180.0, east - 360.0)]
+        } else {
+            vec![(west, east)]
+        }
+    }
+
+    /// SQL condition on the R-tree alias `r` for the box around
+    /// `(lon, lat)`, split at the antimeridian like
+    /// [`RadiusBbox::lon_ranges`].
+    fn rtree_filter(&self, lon: f64, lat: f64) -> String {
+        let x: Vec<String> = self
+            .lon_ranges(lon)
+            .into_iter()
+            .map(|(west, east)| format!("r.xmax >= {west} AND r.xmin <= {east}"))
+            .collect();
+        format!(
+            "({}) AND r.ymax >= {y_min} AND r.ymin <= {y_max}",
+            x.join(" OR "),
+            y_min = lat - self.dlat,
+            y_max = lat + self.dlat,
+        )
+    }
+}
+
+/// Compute the degree-offset bounding box for a spherical radius search.
+///
+/// `lat_deg` is the latitude of the probe point in degrees, `radius_m` is
+/// the search radius in metres. The box is that of the cap of `radius_m`
+/// on the sphere `ST_DistanceSphere` and `ST_DWithinSphere` measure on
+/// (mean Earth radius, 6371008.8 m), widened by a relative 1e-9 and by
+/// 1e-9 degrees (0.1 mm) against rounding in the distance and in the
+/// `lon ± dlon` bounds, so every point those functions put within
+/// `radius_m` of the probe falls inside it. It also takes in points
+/// beyond the radius, so refine with `ST_DWithinSphere`. Near the
+/// antimeridian `lon ± dlon` leaves `-180..=180`;
+/// [`RadiusBbox::lon_ranges`] splits it there.
+///
+/// Worked numbers for a 1000 km radius:
+///
+/// | latitude | `dlon`  | `dlat` |
+/// | -------: | ------: | -----: |
+/// | 0°       | 8.99°   | 8.99°  |
+/// | 45°      | 12.77°  | 8.99°  |
+/// | 60°      | 18.22°  | 8.99°  |
+/// | 80°      | 64.18°  | 8.99°  |
+/// | 89°      | 180.0°  | 8.99°  |
+///
+/// `dlon` is 180 whenever `|lat| + dlat` reaches 90, that is whenever the
+/// cap contains a pole.
+///
+/// ```rust
+/// use sqlitegis::core::index::radius_bbox;
+///
+/// let equator = radius_bbox(0.0, 1_000_000.0);
+/// let berlin = radius_bbox(52.5, 1_000_000.0);
+/// // dlat is constant. dlon grows with |lat| because longitude shrinks.
+/// assert!((equator.dlat - berlin.dlat).abs() < 1e-9);
+/// assert!(berlin.dlon > equator.dlon);
+///
+/// // Once the cap covers the pole, dlon spans every longitude.
+/// assert_eq!(radius_bbox(89.0, 1_000_000.0).dlon, 180.0);
+/// ```
+pub fn radius_bbox(lat_deg: f64, radius_m: f64) -> RadiusBbox {
+    // Angular radius, padded against rounding for rows right on it.
+    let delta = radius_m / Haversine.radius() * (1.0 + 1e-9);
+    let (sin_delta, cos_lat) = (delta.sin(), lat_deg.to_radians().cos());
+    let dlon = if delta >= std::f64::consts::FRAC_PI_2 || sin_delta >= cos_lat {
+        180.0
+    } else {
+        (sin_delta / cos_lat).asin().to_degrees() + 1e-9
+    };
+    RadiusBbox {
+        dlon,
+        dlat: delta.to_degrees() + 1e-9,
+    }
+}
+
+/// SQL for a geodesic radius search through the `{table}_{geom_column}_rtree`
+/// shadow table: a [`radius_bbox`] prefilter refined by `ST_DWithinSphere`.
+///
+/// This is what `diesel::query_helpers::dwithin_sphere_indexed_sql` and
+/// `rusqlite::dwithin_sphere_indexed` run; take the string to log it, to
+/// prepend `EXPLAIN QUERY PLAN` or to add binds of your own. `table` and
+/// `geom_column` are bracketed into the SQL, so they must be trusted
+/// identifiers; the numbers are formatted as `f64` literals.
+/// `select_cols` goes between `SELECT` and `FROM`, with the table aliased
+/// `t` and its R-tree `r`.
+///
+/// ```rust
+/// use sqlitegis::core::index::dwithin_sphere_indexed_sql_string;
+///
+/// let sql = dwithin_sphere_indexed_sql_string(
+///     "places", "geom", (13.4, 52.5), 1_000_000.0, "t.id, t.name",
+/// );
+/// assert!(sql.contains("JOIN [places_geom_rtree]"));
+/// assert!(sql.contains("ST_DWithinSphere"));
+/// ```
+pub fn dwithin_sphere_indexed_sql_string(
+    table: &str,
+    geom_column: &str,
+    probe: (f64, f64),
+    radius_m: f64,
+    select_cols: &str,
+) -> String {
+    let (lon, lat) = probe;
+    let bbox = radius_bbox(lat, radius_m);
+    format!(
+        "SELECT {select_cols} \
+         FROM [{table}] t \
+         JOIN [{table}_{geom_column}_rtree] r ON t.rowid = r.id \
+         WHERE {filter} \
+           AND ST_DWithinSphere(t.[{geom_column}], \
+                                ST_Point({lon}, {lat}, 4326), {radius_m})",
+        filter = bbox.rtree_filter(lon, lat),
+    )
+}
+
+/// SQL for an envelope-window search through the R-tree shadow table:
+/// the bounding-box join refined by `ST_Intersects` against
+/// `ST_MakeEnvelope(..., 4326)`.
+///
+/// Behind `diesel::query_helpers::intersects_window_indexed_sql` and
+/// `rusqlite::intersects_window_indexed`. `window` is
+/// `(xmin, ymin, xmax, ymax)`; the other inputs are as for
+/// [`dwithin_sphere_indexed_sql_string`].
+///
+/// ```rust
+/// use sqlitegis::core::index::intersects_window_indexed_sql_string;
+///
+/// let sql = intersects_window_indexed_sql_string(
+///     "places", "geom", (-1.6, 37.5, 28.4, 67.5), "t.id, t.name",
+/// );
+/// assert!(sql.contains("JOIN [places_geom_rtree]"));
+/// assert!(sql.contains("ST_MakeEnvelope(-1.6, 37.5, 28.4, 67.5, 4326)"));
+/// ```
+pub fn intersects_window_indexed_sql_string(
+    table: &str,
+    geom_column: &str,
+    window: (f64, f64, f64, f64),
+    select_cols: &str,
+) -> String {
+    let (xmin, ymin, xmax, ymax) = window;
+    format!(
+        "SELECT {select_cols} \
+         FROM [{table}] t \
+         JOIN [{table}_{geom_column}_rtree] r ON t.rowid = r.id \
+         WHERE r.xmax >= {xmin} AND r.xmin <= {xmax} \
+           AND r.ymax >= {ymin} AND r.ymin <= {ymax} \
+           AND ST_Intersects(t.[{geom_column}], \
+                             ST_MakeEnvelope({xmin}, {ymin}, {xmax}, {ymax}, 4326))",
+    )
+}
+
+/// SQL for the `limit` geodesic nearest neighbours among the rows whose
+/// R-tree box meets a `search_radius_m` [`radius_bbox`], ordered by
+/// `ST_DistanceSphere`.
+///
+/// Behind `diesel::query_helpers::nearest_sphere_indexed_sql` and
+/// `rusqlite::nearest_sphere_indexed`; neighbours beyond the search radius
+/// are missed. The other inputs are as for
+/// [`dwithin_sphere_indexed_sql_string`].
+///
+/// ```rust
+/// use sqlitegis::core::index::nearest_sphere_indexed_sql_string;
+///
+/// let sql = nearest_sphere_indexed_sql_string(
+///     "places", "geom", (13.4, 52.5), 1_000_000.0, 10, "t.id, t.name",
+/// );
+/// assert!(sql.contains("JOIN [places_geom_rtree]"));
+/// assert!(sql.contains("ORDER BY ST_DistanceSphere"));
+/// assert!(sql.contains("LIMIT 10"));
+/// ```
+pub fn nearest_sphere_indexed_sql_string(
+    table: &str,
+    geom_column: &str,
+    probe: (f64, f64),
+    search_radius_m: f64,
+    limit: usize,
+    select_cols: &str,
+) -> String {
+    let (lon, lat) = probe;
+    let bbox = radius_bbox(lat, search_radius_m);
+    format!(
+        "SELECT {select_cols} \
+         FROM [{table}] t \
+         JOIN [{table}_{geom_column}_rtree] r ON t.rowid = r.id \
+         WHERE {filter} \
+         ORDER BY ST_DistanceSphere(t.[{geom_column}], \
+                                    ST_Point({lon}, {lat}, 4326)) \
+         LIMIT {limit}",
+        filter = bbox.rtree_filter(lon, lat),
+    )
+}
+
+/// R-tree leaf: the blob's bounding box tagged with its slot in `entries`.
+type Leaf = GeomWithData<Rectangle<[f64; 2]>, usize>;
+
+/// Bulk-loaded, immutable R-tree over `(key, EWKB blob)` pairs.
+///
+/// Built once with [`SpatialIndex::bulk_load`] (rstar's OMT packing, a
+/// top-down variant of Sort-Tile-Recursive). Query results borrow the
+/// caller's keys and are returned in the order the rows were loaded, so
+/// output is deterministic regardless of tree layout.
+#[derive(Debug, Clone)]
+pub struct SpatialIndex<K> {
+    entries: Vec<(K, Vec<u8>)>,
+    tree: RTree<Leaf>,
+}
+
+impl<K> SpatialIndex<K> {
+    /// Build an index from `(key, blob)` pairs.
+    ///
+    /// Every blob is validated through [`extract_mbr`]. Malformed EWKB
+    /// aborts the load with the offending blob's error. Empty geometries
+    /// are kept out of the tree and never match a query.
+    ///
+    /// ```
+    /// use sqlitegis::core::functions::io::geom_from_text;
+    /// use sqlitegis::core::index::SpatialIndex;
+    ///
+    /// let index = SpatialIndex::bulk_load(vec![
+    ///     (1_i64, geom_from_text("POINT(1 1)", None).unwrap()),
+    ///     (2_i64, geom_from_text("POINT EMPTY", None).unwrap()),
+    /// ])
+    /// .unwrap();
+    /// assert_eq!(index.len(), 2);
+    /// assert_eq!(index.indexed_len(), 1);
+    /// ```
+    pub fn bulk_load<I, B>(items: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (K, B)>,
+        B: Into<Vec<u8>>,
+    {
+        let mut entries = Vec::new();
+        let mut leaves = Vec::new();
+        for (key, blob) in items {
+            let blob = blob.into();
+            if let Some(rect) = extract_mbr(&blob)? {
+                let min = rect.min();
+                let max = rect.max();
+                leaves.push(GeomWithData::new(
+                    Rectangle::from_corners([min.x, min.y], [max.x, max.y]),
+                    entries.len(),
+                ));
+            }
+            entries.push((key, blob));
+        }
+        Ok(Self {
+            entries,
+            tree: RTree::bulk_load(leaves),
+        })
+    }
+
+    /// Number of rows the index was built from, including empty geometries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// True when the index was built from zero rows.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Number of rows that made it into the R-tree (non-empty geometries).
+    pub fn indexed_len(&self) -> usize {
+        self.tree.size()
+    }
+
+    /// Iterate over the `(key, blob)` pairs in load order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &[u8])> {
+        self.entries.iter().map(|(k, b)| (k, b.as_slice()))
+    }
+
+    /// Stage 1 only: slots whose stored bbox overlaps the given box, in
+    /// load order.
+    fn candidates(&self, xmin: f64, ymin: f64, xmax: f64, ymax: f64) -> Vec<usize> {
+        let envelope = AABB::from_corners([xmin, ymin], [xmax, ymax]);
+        let mut slots: Vec<usize> = self
+            .tree
+            .locate_in_envelope_intersecting(&envelope)
+            .map(|leaf| leaf.data)
+            .collect();
+        slots.sort_unstable();
+        slots
+    }
+
+    /// Run `refine` on every stage-1 candidate and keep the ones it accepts.
+    fn refine<F>(&self, slots: Vec<usize>, refine: F) -> Result<Vec<&K>>
+    where
+        F: Fn(&[u8]) -> Result<bool>,
+    {
+        let mut hits = Vec::new();
+        for slot in slots {
+            let (key, blob) = &self.entries[slot];
+            if refine(blob)? {
+                hits.push(key);
+            }
+        }
+        Ok(hits)
+    }
+
+    /// Keys whose bounding box overlaps the window, without refinement.
+    ///
+    /// This is the raw R-tree stage, equivalent to joining the `*_rtree`
+    /// shadow table without a follow-up predicate. `window` is
+    /// `(xmin, ymin, xmax, ymax)`.
+    pub fn bbox_candidates(&self, window: (f64, f64, f64, f64)) -> Vec<&K> {
+        let (xmin, ymin, xmax, ymax) = window;
+        self.candidates(xmin, ymin, xmax, ymax)
+            .into_iter()
+            .map(|slot| &self.entries[slot].0)
+            .collect()
+    }
+
+    /// Keys whose geometry intersects the envelope `window`.
+    ///
+    /// Two-stage, like `intersects_window_indexed_sql`: R-tree prefilter on
+    /// the window, then `st_intersects` against the envelope built with the
+    /// candidate's own SRID. `window` is `(xmin, ymin, xmax, ymax)`.
+    ///
+    /// ```
+    /// use sqlitegis::core::functions::io::geom_from_text;
+    /// use sqlitegis::core::index::SpatialIndex;
+    ///
+    /// // The L-shape's bbox overlaps the window but the geometry does not.
+    /// let index = SpatialIndex::bulk_load(vec![
+    ///     ("ell", geom_from_text("POLYGON((0 0,4 0,4 1,1 1,1 4,0 4,0 0))", None).unwrap()),
+    ///     ("dot", geom_from_text("POINT(3 3)", None).unwrap()),
+    /// ])
+    /// .unwrap();
+    /// assert_eq!(index.bbox_candidates((2.0, 2.0, 5.0, 5.0)).len(), 2);
+    /// assert_eq!(index.intersects_window((2.0, 2.0, 5.0, 5.0)).unwrap(), vec![&"dot"]);
+    /// ```
+    pub fn intersects_window(&self, window: (f64, f64, f64, f64)) -> Result<Vec<&K>> {
+        let (xmin, ymin, xmax, ymax) = window;
+        let slots = self.candidates(xmin, ymin, xmax, ymax);
+        self.refine(slots, |blob| {
+            let envelope = st_make_envelope(xmin, ymin, xmax, ymax, extract_srid(blob))?;
+            st_intersects(blob, &envelope)
+        })
+    }
+
+    /// Keys whose geometry is within planar `distance` of `probe`.
+    ///
+    /// The probe's bbox is expanded by `distance` for the R-tree stage, then
+    /// each candidate is refined with `st_dwithin(candidate, probe,
+    /// distance)`, so SRID and distance validation follow the scalar rules.
+    ///
+    /// ```
+    /// use sqlitegis::core::functions::constructors::st_point;
+    /// use sqlitegis::core::index::SpatialIndex;
+    ///
+    /// let index = SpatialIndex::bulk_load(vec![
+    ///     (1, st_point(3.0, 4.0, None).unwrap()),
+    ///     (2, st_point(30.0, 40.0, None).unwrap()),
+    /// ])
+    /// .unwrap();
+    /// let origin = st_point(0.0, 0.0, None).unwrap();
+    /// assert_eq!(index.dwithin(&origin, 5.0).unwrap(), vec![&1]);
+    /// ```
+    pub fn dwithin(&self, probe: &[u8], distance: f64) -> Result<Vec<&K>> {
+        ensure_dwithin_distance(distance, "ST_DWithin")?;
+        let Some(rect) = extract_mbr(probe)? else {
+            return Ok(Vec::new());
+        };
+        let slots = self.candidates(
+            rect.min().x - distance,
+            rect.min().y - distance,
+            rect.max().x + distance,
+            rect.max().y + distance,
+        );
+        self.refine(slots, |blob| st_dwithin(blob, probe, distance))
+    }
+
+    /// Keys within `radius_m` metres (Haversine) of the SRID 4326 point
+    /// `(lon, lat)`.
+    ///
+    /// Same plan as `dwithin_sphere_indexed_sql`: a [`radius_bbox`]
+    /// prefilter, split at the antimeridian, followed by
+    /// `st_dwithin_sphere`.
+    pub fn dwithin_sphere(&self, probe: (f64, f64), radius_m: f64) -> Result<Vec<&K>> {
+        ensure_dwithin_distance(radius_m, "ST_DWithinSphere")?;
+        let (lon, lat) = probe;
+        let point = st_point(lon, lat, Some(4326))?;
+        let slots = self.radius_candidates(lon, lat, radius_m);
+        self.refine(slots, |blob| st_dwithin_sphere(blob, &point, radius_m))
+    }
+
+    /// Stage 1 for the spherical queries: the [`radius_bbox`] of
+    /// `radius_m` around `(lon, lat)`.
+    fn radius_candidates(&self, lon: f64, lat: f64, radius_m: f64) -> Vec<usize> {
+        let bbox = radius_bbox(lat, radius_m);
+        let mut slots: Vec<usize> = bbox
+            .lon_ranges(lon)
+            .into_iter()
+            .flat_map(|(west, east)| self.candidates(west, lat - bbox.dlat, east, lat + bbox.dlat))
+            .collect();
+        slots.sort_unstable();
+        slots.dedup();
+        slots
+    }
+
+    /// The `k` nearest rows to the SRID 4326 point `(lon, lat)` by Haversine
+    /// distance, closest first, with their distances in metres.
+    ///
+    /// Unlike `nearest_sphere_indexed_sql` this needs no caller-supplied
+    /// search radius. The `k` nearest bboxes in degree space give an upper
+    /// bound `r` on the k-th spherical distance, and a second pass over the
+    /// bounding box of the spherical cap of radius `r` contains the true
+    /// `k` nearest. Indexed rows must be SRID 4326 Points, as for
+    /// `st_distance_sphere`. Ties keep load order.
+    pub fn nearest_sphere(&self, probe: (f64, f64), k: usize) -> Result<Vec<(&K, f64)>> {
+        let (lon, lat) = probe;
+        let point = st_point(lon, lat, Some(4326))?;
+        if k == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut bound = 0.0_f64;
+        for leaf in self.tree.nearest_neighbor_iter(&[lon, lat]).take(k) {
+            bound = bound.max(st_distance_sphere(&self.entries[leaf.data].1, &point)?);
+        }
+
+        let slots = self.radius_candidates(lon, lat, bound);
+        let mut ranked = Vec::with_capacity(slots.len());
+        for slot in slots {
+            let (key, blob) = &self.entries[slot];
+            ranked.push((key, st_distance_sphere(blob, &point)?));
+        }
+        ranked.sort_by(|a, b| a.1.total_cmp(&b.1));
+        ranked.truncate(k);
+        Ok(ranked)
+    }
+
+    /// Keys for which `predicate(candidate, probe)` holds, prefiltered by
+    /// the probe's bounding box.
+    ///
+    /// Valid for any predicate that implies bbox overlap: `st_intersects`,
+    /// `st_contains`, `st_within`, `st_covers`, `st_covered_by`,
+    /// `st_equals`, `st_touches`, `st_crosses`, `st_overlaps`. Do not pass
+    /// `st_disjoint` (disjoint rows are exactly the ones the prefilter
+    /// drops).
+    ///
+    /// ```
+    /// use sqlitegis::core::functions::io::geom_from_text;
+    /// use sqlitegis::core::functions::predicates::st_within;
+    /// use sqlitegis::core::index::SpatialIndex;
+    ///
+    /// let index = SpatialIndex::bulk_load(vec![
+    ///     ("in", geom_from_text("POINT(1 1)", None).unwrap()),
+    ///     ("edge", geom_from_text("POINT(0 1)", None).unwrap()),
+    ///     ("out", geom_from_text("POINT(9 9)", None).unwrap()),
+    /// ])
+    /// .unwrap();
+    /// let square = geom_from_text("POLYGON((0 0,2 0,2 2,0 2,0 0))", None).unwrap();
+    /// assert_eq!(index.query(&square, st_within).unwrap(), vec![&"in"]);
+    /// ```
+    pub fn query<F>(&self, probe: &[u8], predicate: F) -> Result<Vec<&K>>
+    where
+        F: Fn(&[u8], &[u8]) -> Result<bool>,
+    {
+        let Some(rect) = extract_mbr(probe)? else {
+            return Ok(Vec::new());
+        };
+        let slots = self.candidates(rect.min().x, rect.min().y, rect.max().x, rect.max().y);
+        self.refine(slots, |blob| predicate(blob, probe))
+    }
+}
+
+impl<K> Default for SpatialIndex<K> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+            tree: RTree::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::error::SqliteGisError;
+    use crate::core::functions::io::geom_from_text;
+    use crate::core::functions::predicates::st_contains;
+
+    fn planar() -> SpatialIndex<usize> {
+        let wkts = [
+            "POINT(1 1)",
+            "POINT(50 50)",
+            "POINT EMPTY",
+            "LINESTRING(-1 2,5 2)",
+            "POLYGON((0 0,4 0,4 4,0 4,0 0))",
+            "POLYGON((-10 -10,10 -10,10 10,-10 10,-10 -10))",
+            "POLYGON((20 20,30 20,30 30,20 30,20 20))",
+        ];
+        SpatialIndex::bulk_load(
+            wkts.iter()
+                .enumerate()
+                .map(|(i, wkt)| (i, geom_from_text(wkt, None).unwrap())),
+        )
+        .unwrap()
+    }
+
+    /// Deterministic lon/lat grid covering Europe at half-degree spacing.
+    fn grid() -> SpatialIndex<usize> {
+        let mut rows = Vec::new();
+        for i in 0..40 {
+            for j in 0..30 {
+                let lon = -10.0 + f64::from(i) * 0.5;
+                let lat = 40.0 + f64::from(j) * 0.5;
+                rows.push((rows.len(), st_point(lon, lat, Some(4326)).unwrap()));
+            }
+        }
+        SpatialIndex::bulk_load(rows).unwrap()
+    }
+
+    /// Brute-force scan over every non-empty row, the reference the
+    /// two-stage plan must reproduce.
+    fn scan<K>(index: &SpatialIndex<K>, f: impl Fn(&[u8]) -> bool) -> Vec<&K> {
+        index
+            .iter()
+            .filter(|(_, blob)| extract_mbr(blob).unwrap().is_some() && f(blob))
+            .map(|(k, _)| k)
+            .collect()
+    }
+
+    #[test]
+    fn empty_geometries_are_counted_but_not_indexed() {
+        let index = planar();
+        assert_eq!(index.len(), 7);
+        assert_eq!(index.indexed_len(), 6);
+        assert!(SpatialIndex::<u8>::default().is_empty());
+    }
+
+    #[test]
+    fn malformed_blob_aborts_load() {
+        let err = SpatialIndex::bulk_load(vec![(1, vec![0x01, 0x02])]).unwrap_err();
+        assert!(matches!(err, SqliteGisError::InvalidEwkb(_)), "{err:?}");
+    }
+
+    #[test]
+    fn intersects_window_matches_scan() {
+        let index = planar();
+        for window in [
+            (0.5, 0.5, 1.5, 1.5),
+            (3.0, 3.0, 25.0, 25.0),
+            (100.0, 100.0, 101.0, 101.0),
+        ] {
+            let (xmin, ymin, xmax, ymax) = window;
+            let envelope = st_make_envelope(xmin, ymin, xmax, ymax, None).unwrap();
+            let expected = scan(&index, |b| st_intersects(b, &envelope).unwrap());
+            assert_eq!(
+                index.intersects_window(window).unwrap(),
+                expected,
+                "{window:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn dwithin_matches_scan() {
+        let index = planar();
+        let probe = st_point(15.0, 15.0, None).unwrap();
+        for distance in [0.0, 5.0, 8.0, 50.0] {
+            let expected = scan(&index, |b| st_dwithin(b, &probe, distance).unwrap());
+            assert_eq!(
+                index.dwithin(&probe, distance).unwrap(),
+                expected,
+                "{distance}"
+            );
+        }
+    }
+
+    #[test]
+    fn dwithin_rejects_bad_distance() {
+        let index = planar();
+        let probe = st_point(0.0, 0.0, None).unwrap();
+        assert!(index.dwithin(&probe, -1.0).is_err());
+        assert!(index.dwithin(&probe, f64::NAN).is_err());
+        assert!(index.dwithin_sphere((0.0, 0.0), -1.0).is_err());
+    }
+
+    #[test]
+    fn dwithin_sphere_matches_scan() {
+        let index = grid();
+        let probe = st_point(2.35, 48.85, Some(4326)).unwrap();
+        for radius in [30_000.0, 120_000.0, 500_000.0] {
+            let expected = scan(&index, |b| st_dwithin_sphere(b, &probe, radius).unwrap());
+            assert!(!expected.is_empty());
+            assert_eq!(
+                index.dwithin_sphere((2.35, 48.85), radius).unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn nearest_sphere_matches_full_sort() {
+        let index = grid();
+        let probe = st_point(3.1, 52.9, Some(4326)).unwrap();
+        let mut all: Vec<(&usize, f64)> = index
+            .iter()
+            .map(|(k, b)| (k, st_distance_sphere(b, &probe).unwrap()))
+            .collect();
+        all.sort_by(|a, b| a.1.total_cmp(&b.1));
+        for k in [0, 1, 5, 17] {
+            let got = index.nearest_sphere((3.1, 52.9), k).unwrap();
+            assert_eq!(got.len(), k);
+            for (g, e) in got.iter().zip(&all) {
+                assert!((g.1 - e.1).abs() < 1e-6, "k={k}: {g:?} vs {e:?}");
+            }
+        }
+
+        // One degree of latitude on the Haversine sphere is shorter than
+        // the 111.32 km of the WGS84 average.
+        let index =
+            SpatialIndex::bulk_load(vec![(0, st_point(0.0, 1.0, Some(4326)).unwrap())]).unwrap();
+        let got = index.nearest_sphere((0.0, 0.0), 1).unwrap();
+        assert_eq!(got.len(), 1);
+        assert_eq!(got[0].0, &0);
+        assert_eq!(
+            index.dwithin_sphere((0.0, 0.0), got[0].1).unwrap(),
+            vec![&0]
+        );
+    }
+
+    #[test]
+    fn query_prefilters_by_probe_bbox() {
+        let index = planar();
+        let probe = geom_from_text("POINT(2 2)", None).unwrap();
+        let expected = scan(&index, |b| st_contains(b, &probe).unwrap());
+        assert_eq!(index.query(&probe, st_contains).unwrap(), expected);
+        assert_eq!(index.query(&probe, st_contains).unwrap(), vec![&3, &4, &5]);
+    }
+}
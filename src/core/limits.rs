@@ -0,0 +1,250 @@
+//! Resource limits on geometry size.
+//!
+//! A [`Limits`] value caps three things:
+//!
+//! - `max_input_bytes`: length of an EWKB/WKB BLOB or WKT/GeoJSON text
+//!   handed to a parser, checked before any decoding happens.
+//! - `max_vertices`: vertex count of a decoded input geometry.
+//! - `max_output_vertices`: vertex count of a geometry about to be written
+//!   out as EWKB (for example the result of `st_buffer`).
+//!
+//! Limits are scoped to the current thread with [`with_limits`] and are
+//! enforced inside [`crate::core::ewkb`] and [`crate::core::functions::io`],
+//! so every function built on those parsers and writers honours them
+//! without taking an extra argument. Exceeding one returns
+//! [`SqliteGisError::LimitExceeded`]. Outside [`with_limits`] nothing is
+//! capped.
+//!
+//! The SQLite layer keeps one [`Limits`] per connection, installed around
+//! every callback, and exposes it as `sqlitegis_config(name[, value])`.
+//!
+//! ```
+//! use sqlitegis::SqliteGisError;
+//! use sqlitegis::core::functions::io::geom_from_text;
+//! use sqlitegis::core::limits::{with_limits, Limits};
+//!
+//! let limits = Limits {
+//!     max_vertices: Some(3),
+//!     ..Limits::default()
+//! };
+//! with_limits(limits, || {
+//!     assert!(geom_from_text("LINESTRING(0 0,1 1,2 2)", None).is_ok());
+//!     let err = geom_from_text("LINESTRING(0 0,1 1,2 2,3 3)", None).unwrap_err();
+//!     assert!(matches!(
+//!         err,
+//!         SqliteGisError::LimitExceeded { limit: "max_vertices", value: 4, max: 3 }
+//!     ));
+//! });
+//! ```
+
+use std::cell::Cell;
+
+use geo::{CoordsIter, Geometry};
+
+use crate::core::error::{Result, SqliteGisError};
+
+/// Per-scope caps on input and output geometry size. `None` means
+/// unlimited, which is also the [`Default`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum length in bytes of a BLOB or text input to a parser.
+    pub max_input_bytes: Option<usize>,
+    /// Maximum vertex count of a decoded input geometry.
+    pub max_vertices: Option<usize>,
+    /// Maximum vertex count of a geometry written out as EWKB.
+    pub max_output_vertices: Option<usize>,
+}
+
+/// Names accepted by [`Limits::get`] and [`Limits::set`], in declaration
+/// order. These are also the `sqlitegis_config` setting names.
+pub const LIMIT_NAMES: &[&str] = &["max_input_bytes", "max_vertices", "max_output_vertices"];
+
+impl Limits {
+    fn slot(&mut self, name: &str) -> Result<&mut Option<usize>> {
+        match name {
+            "max_input_bytes" => Ok(&mut self.max_input_bytes),
+            "max_vertices" => Ok(&mut self.max_vertices),
+            "max_output_vertices" => Ok(&mut self.max_output_vertices),
+            other => Err(SqliteGisError::InvalidInput(format!(
+                "unknown limit {other:?} (expected one of {})",
+                LIMIT_NAMES.join(", ")
+            ))),
+        }
+    }
+
+    /// Look up a limit by its [`LIMIT_NAMES`] name.
+    ///
+    /// ```
+    /// use sqlitegis::core::limits::Limits;
+    ///
+    /// let limits = Limits { max_vertices: Some(10), ..Limits::default() };
+    /// assert_eq!(limits.get("max_vertices").unwrap(), Some(10));
+    /// assert_eq!(limits.get("max_input_bytes").unwrap(), None);
+    /// assert!(limits.get("max_rows").is_err());
+    /// ```
+    pub fn get(&self, name: &str) -> Result<Option<usize>> {
+        let mut copy = *self;
+        Ok(*copy.slot(name)?)
+    }
+
+    /// Set a limit by its [`LIMIT_NAMES`] name. `None` removes the cap.
+    pub fn set(&mut self, name: &str, value: Option<usize>) -> Result<()> {
+        *self.slot(name)? = value;
+        Ok(())
+    }
+}
+
+thread_local! {
+    static ACTIVE: Cell<Limits> = const {
+        Cell::new(Limits {
+            max_input_bytes: None,
+            max_vertices: None,
+            max_output_vertices: None,
+        })
+    };
+}
+
+/// Restores the previous thread-local limits on drop, so a panic inside
+/// [`with_limits`] cannot leak its limits into later calls.
+struct Restore(Limits);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        ACTIVE.with(|active| active.set(self.0));
+    }
+}
+
+/// Run `f` with `limits` active on the current thread, restoring the
+/// previous limits afterwards. Calls nest; the innermost scope wins.
+pub fn with_limits<R>(limits: Limits, f: impl FnOnce() -> R) -> R {
+    let _restore = Restore(ACTIVE.with(|active| active.replace(limits)));
+    f()
+}
+
+/// The limits active on the current thread.
+pub fn current() -> Limits {
+    ACTIVE.with(Cell::get)
+}
+
+fn check(limit: &'static str, value: usize, max: Option<usize>) -> Result<()> {
+    match max {
+        Some(max) if value > max => Err(SqliteGisError::LimitExceeded { limit, value, max }),
+        _ => Ok(()),
+    }
+}
+
+/// Enforce `max_input_bytes` on a BLOB or text input of `len` bytes.
+pub(crate) fn check_input_bytes(len: usize) -> Result<()> {
+    check("max_input_bytes", len, current().max_input_bytes)
+}
+
+/// Enforce `max_vertices` on a decoded input geometry.
+pub(crate) fn check_input_vertices(geom: &Geometry<f64>) -> Result<()> {
+    match current().max_vertices {
+        Some(max) => check("max_vertices", geom.coords_count(), Some(max)),
+        None => Ok(()),
+    }
+}
+
+/// Enforce `max_output_vertices` on a geometry about to be serialised.
+pub(crate) fn check_output_vertices(geom: &Geometry<f64>) -> Result<()> {
+    match current().max_output_vertices {
+        Some(max) => check("max_output_vertices", geom.coords_count(), Some(max)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ewkb::parse_ewkb;
+    use crate::core::functions::io::{geom_from_geojson, geom_from_text};
+    use crate::core::functions::operations::st_buffer;
+
+    #[test]
+    fn unlimited_by_default() {
+        assert_eq!(current(), Limits::default());
+        let coords: Vec<String> = (0..10_000).map(|i| format!("{i} 0")).collect();
+        assert!(geom_from_text(&format!("LINESTRING({})", coords.join(",")), None).is_ok());
+    }
+
+    #[test]
+    fn scopes_nest_and_restore() {
+        let outer = Limits {
+            max_vertices: Some(5),
+            ..Limits::default()
+        };
+        let inner = Limits {
+            max_vertices: Some(1),
+            ..Limits::default()
+        };
+        with_limits(outer, || {
+            with_limits(inner, || assert_eq!(current(), inner));
+            assert_eq!(current(), outer);
+        });
+        assert_eq!(current(), Limits::default());
+    }
+
+    #[test]
+    fn restores_after_panic() {
+        let limits = Limits {
+            max_input_bytes: Some(1),
+            ..Limits::default()
+        };
+        let caught = std::panic::catch_unwind(|| with_limits(limits, || panic!("boom")));
+        assert!(caught.is_err());
+        assert_eq!(current(), Limits::default());
+    }
+
+    #[test]
+    fn input_bytes_cap_applies_to_text_and_blobs() {
+        let blob = geom_from_text("POINT(1 2)", None).unwrap();
+        let limits = Limits {
+            max_input_bytes: Some(12),
+            ..Limits::default()
+        };
+        with_limits(limits, || {
+            let err = parse_ewkb(&blob).unwrap_err();
+            assert!(matches!(
+                err,
+                SqliteGisError::LimitExceeded {
+                    limit: "max_input_bytes",
+                    value: 21,
+                    max: 12
+                }
+            ));
+            let err = geom_from_geojson(r#"{"type":"Point","coordinates":[1,2]}"#, None);
+            assert!(matches!(err, Err(SqliteGisError::LimitExceeded { .. })));
+        });
+    }
+
+    #[test]
+    fn output_vertex_cap_applies_to_results() {
+        let pt = geom_from_text("POINT(0 0)", None).unwrap();
+        let limits = Limits {
+            max_output_vertices: Some(8),
+            ..Limits::default()
+        };
+        with_limits(limits, || {
+            let err = st_buffer(&pt, 1.0).unwrap_err();
+            assert!(matches!(
+                err,
+                SqliteGisError::LimitExceeded {
+                    limit: "max_output_vertices",
+                    ..
+                }
+            ));
+        });
+        assert!(st_buffer(&pt, 1.0).is_ok());
+    }
+
+    #[test]
+    fn set_and_get_by_name() {
+        let mut limits = Limits::default();
+        for name in LIMIT_NAMES {
+            limits.set(name, Some(7)).unwrap();
+            assert_eq!(limits.get(name).unwrap(), Some(7));
+        }
+        assert!(limits.set("max_rows", Some(1)).is_err());
+    }
+}
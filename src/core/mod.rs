@@ -2,14 +2,44 @@
 //! catalog used by the SQLite and Diesel layers to generate their surfaces.
 //! No SQLite, Diesel, or wasm dependency at this level.
 
+/// Parallel one-probe-vs-many variants of the hot predicates and
+/// measurements, fanned out over the rayon thread pool.
+#[cfg(feature = "rayon")]
+pub mod batch;
 /// Crate-wide error and result types returned by every fallible function.
 pub mod error;
 /// EWKB (Extended Well-Known Binary) wire format encoder and decoder, used
 /// as the on-disk and over-the-wire representation for geometry BLOBs.
 pub mod ewkb;
+/// Row-shaped features (an EWKB geometry plus attribute values) shared
+/// by the file-format readers and writers.
+pub mod features;
+/// FlatGeobuf reader and writer, with the packed Hilbert R-tree index.
+pub mod flatgeobuf;
 /// Authoritative catalog of every SQL function the crate exposes, used by
 /// the SQLite and Diesel layers to keep their surfaces in sync.
 pub mod function_catalog;
 /// Pure-Rust implementations of the spatial functions in the catalog,
 /// operating on EWKB BLOBs and primitive scalars.
 pub mod functions;
+/// GeoParquet reader and writer: WKB geometry columns described by the
+/// `geo` file metadata.
+#[cfg(feature = "geoparquet")]
+pub mod geoparquet;
+/// GeoPackage binary geometry (GPB) header codec and conversion to and
+/// from EWKB.
+pub mod gpb;
+/// Bulk-loaded in-memory R-tree over EWKB blobs, running the same
+/// prefilter-then-refine plan as the SQLite spatial index.
+pub mod index;
+/// Cancellation hook accepted by the long-running geometry operations.
+pub mod interrupt;
+/// Per-scope caps on input size, input vertices and output vertices,
+/// enforced by the EWKB codec and the I/O parsers.
+pub mod limits;
+/// OpenStreetMap PBF reader producing point, line and polygon layers.
+pub mod osm_pbf;
+/// Esri Shapefile reader and writer, with `.prj` to SRID mapping.
+pub mod shapefile;
+/// Whole-file GeoJSON and CSV-with-WKT readers producing features.
+pub mod text_features;
@@ -0,0 +1,1092 @@
+//! OpenStreetMap PBF reader.
+//!
+//! An `.osm.pbf` file is a sequence of length-prefixed blobs: one
+//! `OSMHeader` block naming the features a reader must support, then
+//! `OSMData` blocks of nodes, dense nodes, ways and relations, each blob
+//! raw or zlib-compressed. [`read_osm_pbf`] decodes the whole file, keeps
+//! every node location so ways can be resolved, and returns three layers
+//! of features in SRID 4326:
+//!
+//! - `points`: every node with at least one tag, as a `Point`;
+//! - `lines`: every tagged way that is not an area, as a `LineString`;
+//! - `polygons`: every tagged closed way that is an area, and every
+//!   `type=multipolygon` relation whose member ways close into rings, as a
+//!   `MultiPolygon`.
+//!
+//! A closed way is an area when it is tagged `area=yes`, or has one of the
+//! usual area keys (`building`, `landuse`, `natural`, `amenity`, ...) and
+//! is not tagged `area=no`. Multipolygon members with the `inner` role are
+//! holes of the outer ring that contains them; any other role is an outer
+//! ring. Relations with a member way missing from the extract, or whose
+//! ways do not close, are skipped, and ways keep only the nodes present.
+//!
+//! Every layer has the fields `osm_id`, `osm_type` (`node`, `way` or
+//! `relation`) and `tags`, a JSON object of the element's tags (without a
+//! relation's `type`). Only zlib and uncompressed blobs are supported, and
+//! files requiring features other than `OsmSchema-V0.6` and `DenseNodes`
+//! (history files, for one) are rejected.
+//!
+//! Blobs are read and decoded one at a time, but the decoded elements stay
+//! in memory until the layers are built, so inside
+//! [`with_limits`](crate::core::limits::with_limits) `max_input_bytes`
+//! caps the total uncompressed size of the file's blocks.
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use flate2::read::ZlibDecoder;
+use geo::{Contains, Coord, Geometry, InteriorPoint, LineString, MultiPolygon, Point, Polygon};
+
+use crate::core::error::{Result, SqliteGisError};
+use crate::core::ewkb::write_ewkb;
+use crate::core::features::{read_or_eof, Feature, Field, FieldType, Value};
+use crate::core::limits::check_input_bytes;
+
+/// SRID of every geometry the reader produces.
+pub const OSM_SRID: i32 = 4326;
+/// Names of the layers [`read_osm_pbf`] returns, in order.
+pub const OSM_LAYERS: [&str; 3] = ["points", "lines", "polygons"];
+
+/// Largest `BlobHeader` the format allows.
+const MAX_BLOB_HEADER_LEN: usize = 64 * 1024;
+/// Largest compressed or uncompressed blob the format allows.
+const MAX_BLOB_LEN: usize = 32 * 1024 * 1024;
+/// Required features the reader implements.
+const SUPPORTED_FEATURES: [&str; 2] = ["OsmSchema-V0.6", "DenseNodes"];
+
+/// Keys that make a closed way an area.
+const AREA_KEYS: [&str; 15] = [
+    "aeroway",
+    "amenity",
+    "building",
+    "building:part",
+    "craft",
+    "historic",
+    "landuse",
+    "leisure",
+    "man_made",
+    "military",
+    "natural",
+    "office",
+    "place",
+    "shop",
+    "tourism",
+];
+/// `natural` values drawn as closed lines rather than areas.
+const LINEAR_NATURAL: [&str; 5] = ["arete", "cliff", "coastline", "ridge", "tree_row"];
+
+/// One layer of an OSM extract.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OsmLayer {
+    /// Layer name, one of [`OSM_LAYERS`].
+    pub name: &'static str,
+    /// `osm_id`, `osm_type` and `tags`.
+    pub fields: Vec<Field>,
+    /// The layer's features, in file order.
+    pub features: Vec<Feature>,
+}
+
+fn invalid(msg: impl Into<String>) -> SqliteGisError {
+    SqliteGisError::InvalidInput(msg.into())
+}
+
+/// Protobuf field value, by wire type.
+enum Wire<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+impl<'a> Wire<'a> {
+    fn varint(self) -> Result<u64> {
+        match self {
+            Wire::Varint(v) => Ok(v),
+            _ => Err(invalid("OSM PBF field has the wrong wire type")),
+        }
+    }
+
+    fn bytes(self) -> Result<&'a [u8]> {
+        match self {
+            Wire::Bytes(b) => Ok(b),
+            _ => Err(invalid("OSM PBF field has the wrong wire type")),
+        }
+    }
+
+    /// Append a repeated varint field, packed or not.
+    fn push_varints(self, out: &mut Vec<u64>) -> Result<()> {
+        match self {
+            Wire::Varint(v) => out.push(v),
+            Wire::Bytes(b) => {
+                let mut packed = Message::new(b);
+                while !packed.is_done() {
+                    out.push(packed.varint()?);
+                }
+            }
+            Wire::Fixed => return Err(invalid("OSM PBF field has the wrong wire type")),
+        }
+        Ok(())
+    }
+}
+
+/// Protobuf message decoder, one field at a time.
+struct Message<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Message<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn is_done(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *self
+                .data
+                .get(self.pos)
+                .ok_or_else(|| invalid("OSM PBF message is truncated"))?;
+            self.pos += 1;
+            value |= u64::from(byte & 0x7F) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid("OSM PBF varint is too long"))
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| invalid("OSM PBF message is truncated"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn next_field(&mut self) -> Result<Option<(u64, Wire<'a>)>> {
+        if self.is_done() {
+            return Ok(None);
+        }
+        let key = self.varint()?;
+        let value = match key & 7 {
+            0 => Wire::Varint(self.varint()?),
+            1 => {
+                self.take(8)?;
+                Wire::Fixed
+            }
+            2 => {
+                let len = usize::try_from(self.varint()?)
+                    .map_err(|_| invalid("OSM PBF message is truncated"))?;
+                Wire::Bytes(self.take(len)?)
+            }
+            5 => {
+                self.take(4)?;
+                Wire::Fixed
+            }
+            other => return Err(invalid(format!("unsupported protobuf wire type {other}"))),
+        };
+        Ok(Some((key >> 3, value)))
+    }
+}
+
+fn zigzag(v: u64) -> i64 {
+    (v >> 1) as i64 ^ -((v & 1) as i64)
+}
+
+/// Running sums of zigzag-encoded deltas.
+fn undelta(values: &[u64]) -> Vec<i64> {
+    values
+        .iter()
+        .scan(0i64, |sum, &v| {
+            *sum = sum.wrapping_add(zigzag(v));
+            Some(*sum)
+        })
+        .collect()
+}
+
+type Tags = Vec<(String, String)>;
+
+struct Way {
+    id: i64,
+    refs: Vec<i64>,
+    tags: Tags,
+}
+
+struct Relation {
+    id: i64,
+    /// Member way ids with their roles.
+    ways: Vec<(i64, String)>,
+    tags: Tags,
+}
+
+/// Everything the layers are built from.
+#[derive(Default)]
+struct OsmData {
+    locations: Vec<(i64, Coord<f64>)>,
+    points: Vec<(i64, Coord<f64>, Tags)>,
+    ways: Vec<Way>,
+    relations: Vec<Relation>,
+}
+
+/// String table and coordinate scaling of one `PrimitiveBlock`.
+struct Block {
+    strings: Vec<String>,
+    granularity: i64,
+    lat_offset: i64,
+    lon_offset: i64,
+}
+
+impl Block {
+    /// Position of a node in degrees, from its coordinates in units of
+    /// the block's granularity.
+    fn coord(&self, lat: i64, lon: i64) -> Result<Coord<f64>> {
+        let nanodegrees = |offset: i64, value: i64| {
+            self.granularity
+                .checked_mul(value)
+                .and_then(|scaled| scaled.checked_add(offset))
+                .ok_or_else(|| invalid("OSM PBF node coordinate overflows"))
+        };
+        Ok(Coord {
+            x: 1e-9 * nanodegrees(self.lon_offset, lon)? as f64,
+            y: 1e-9 * nanodegrees(self.lat_offset, lat)? as f64,
+        })
+    }
+
+    fn string(&self, index: u64) -> Result<&str> {
+        usize::try_from(index)
+            .ok()
+            .and_then(|i| self.strings.get(i))
+            .map(String::as_str)
+            .ok_or_else(|| invalid("OSM PBF string index out of range"))
+    }
+
+    fn tags(&self, keys: &[u64], values: &[u64]) -> Result<Tags> {
+        if keys.len() != values.len() {
+            return Err(invalid("OSM PBF element has unpaired tag keys and values"));
+        }
+        keys.iter()
+            .zip(values)
+            .map(|(&k, &v)| Ok((self.string(k)?.to_string(), self.string(v)?.to_string())))
+            .collect()
+    }
+}
+
+/// Read a whole `.osm.pbf` stream into the `points`, `lines` and
+/// `polygons` layers described in the [module docs](self).
+pub fn read_osm_pbf(mut reader: impl Read) -> Result<Vec<OsmLayer>> {
+    let mut osm = OsmData::default();
+    let mut decoded_len = 0usize;
+    let mut header_len = [0u8; 4];
+    while read_or_eof(&mut reader, &mut header_len)? {
+        let header_len = u32::from_be_bytes(header_len) as usize;
+        if header_len > MAX_BLOB_HEADER_LEN {
+            return Err(invalid("OSM PBF blob header is too large"));
+        }
+        let mut header = vec![0; header_len];
+        reader.read_exact(&mut header)?;
+        let (mut kind, mut data_len) = (String::new(), 0usize);
+        let mut message = Message::new(&header);
+        while let Some((field, value)) = message.next_field()? {
+            match field {
+                1 => kind = String::from_utf8_lossy(value.bytes()?).into_owned(),
+                3 => data_len = blob_len(value.varint()?)?,
+                _ => {}
+            }
+        }
+        if data_len > MAX_BLOB_LEN {
+            return Err(invalid("OSM PBF blob is too large"));
+        }
+        check_input_bytes(data_len)?;
+        let mut blob = vec![0; data_len];
+        reader.read_exact(&mut blob)?;
+        let data = match kind.as_str() {
+            "OSMHeader" | "OSMData" => blob_data(&blob)?,
+            // Readers skip blob types they do not know.
+            _ => continue,
+        };
+        decoded_len = decoded_len.saturating_add(data.len());
+        check_input_bytes(decoded_len)?;
+        if kind == "OSMHeader" {
+            check_required_features(&data)?;
+        } else {
+            read_block(&data, &mut osm)?;
+        }
+    }
+    build_layers(osm)
+}
+
+/// The uncompressed content of a `Blob` message.
+fn blob_data(blob: &[u8]) -> Result<Vec<u8>> {
+    let (mut raw_size, mut data) = (None, None);
+    let mut message = Message::new(blob);
+    while let Some((field, value)) = message.next_field()? {
+        match field {
+            1 => return Ok(value.bytes()?.to_vec()),
+            2 => raw_size = Some(blob_len(value.varint()?)?),
+            3 => data = Some(value.bytes()?),
+            4 => return Err(invalid("LZMA-compressed OSM PBF blobs are not supported")),
+            6 => return Err(invalid("LZ4-compressed OSM PBF blobs are not supported")),
+            7 => {
+                return Err(invalid(
+                    "Zstandard-compressed OSM PBF blobs are not supported",
+                ))
+            }
+            _ => {}
+        }
+    }
+    let data = data.ok_or_else(|| invalid("OSM PBF blob has no data"))?;
+    let raw_size = raw_size.unwrap_or(MAX_BLOB_LEN).min(MAX_BLOB_LEN);
+    check_input_bytes(raw_size)?;
+    // One byte past `raw_size` is enough to tell an overlong stream.
+    let mut out = Vec::with_capacity(raw_size);
+    ZlibDecoder::new(data)
+        .take(raw_size as u64 + 1)
+        .read_to_end(&mut out)
+        .map_err(|e| invalid(format!("corrupt zlib data in OSM PBF blob: {e}")))?;
+    if out.len() > raw_size {
+        return Err(invalid("OSM PBF blob inflates past its raw size"));
+    }
+    Ok(out)
+}
+
+/// A blob or raw size varint as a length.
+fn blob_len(value: u64) -> Result<usize> {
+    usize::try_from(value).map_err(|_| invalid("OSM PBF blob is too large"))
+}
+
+fn check_required_features(header: &[u8]) -> Result<()> {
+    let mut message = Message::new(header);
+    while let Some((field, value)) = message.next_field()? {
+        if field == 4 {
+            let feature = String::from_utf8_lossy(value.bytes()?);
+            if !SUPPORTED_FEATURES.contains(&feature.as_ref()) {
+                return Err(invalid(format!(
+                    "OSM PBF file requires unsupported feature [{feature}]"
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn read_block(data: &[u8], osm: &mut OsmData) -> Result<()> {
+    let mut block = Block {
+        strings: Vec::new(),
+        granularity: 100,
+        lat_offset: 0,
+        lon_offset: 0,
+    };
+    let mut groups = Vec::new();
+    let mut message = Message::new(data);
+    while let Some((field, value)) = message.next_field()? {
+        match field {
+            1 => {
+                let mut table = Message::new(value.bytes()?);
+                while let Some((field, value)) = table.next_field()? {
+                    if field == 1 {
+                        block
+                            .strings
+                            .push(String::from_utf8_lossy(value.bytes()?).into_owned());
+                    }
+                }
+            }
+            2 => groups.push(value.bytes()?),
+            17 => block.granularity = value.varint()? as i64,
+            19 => block.lat_offset = value.varint()? as i64,
+            20 => block.lon_offset = value.varint()? as i64,
+            _ => {}
+        }
+    }
+    for group in groups {
+        let mut message = Message::new(group);
+        while let Some((field, value)) = message.next_field()? {
+            match field {
+                1 => read_node(&block, value.bytes()?, osm)?,
+                2 => read_dense_nodes(&block, value.bytes()?, osm)?,
+                3 => osm.ways.push(read_way(&block, value.bytes()?)?),
+                4 => {
+                    if let Some(relation) = read_relation(&block, value.bytes()?)? {
+                        osm.relations.push(relation);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+fn add_node(osm: &mut OsmData, id: i64, location: Coord<f64>, tags: Tags) {
+    osm.locations.push((id, location));
+    if !tags.is_empty() {
+        osm.points.push((id, location, tags));
+    }
+}
+
+fn read_node(block: &Block, data: &[u8], osm: &mut OsmData) -> Result<()> {
+    let (mut id, mut lat, mut lon) = (0, 0, 0);
+    let (mut keys, mut values) = (Vec::new(), Vec::new());
+    let mut message = Message::new(data);
+    while let Some((field, value)) = message.next_field()? {
+        match field {
+            1 => id = zigzag(value.varint()?),
+            2 => value.push_varints(&mut keys)?,
+            3 => value.push_varints(&mut values)?,
+            8 => lat = zigzag(value.varint()?),
+            9 => lon = zigzag(value.varint()?),
+            _ => {}
+        }
+    }
+    add_node(osm, id, block.coord(lat, lon)?, block.tags(&keys, &values)?);
+    Ok(())
+}
+
+fn read_dense_nodes(block: &Block, data: &[u8], osm: &mut OsmData) -> Result<()> {
+    let (mut ids, mut lats, mut lons, mut keys_values) =
+        (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    let mut message = Message::new(data);
+    while let Some((field, value)) = message.next_field()? {
+        match field {
+            1 => value.push_varints(&mut ids)?,
+            8 => value.push_varints(&mut lats)?,
+            9 => value.push_varints(&mut lons)?,
+            10 => value.push_varints(&mut keys_values)?,
+            _ => {}
+        }
+    }
+    if lats.len() != ids.len() || lons.len() != ids.len() {
+        return Err(invalid("OSM PBF dense nodes have mismatched arrays"));
+    }
+    // Tags are key, value pairs per node, each node's list ending in 0.
+    let mut keys_values = keys_values.into_iter();
+    for ((id, lat), lon) in undelta(&ids)
+        .into_iter()
+        .zip(undelta(&lats))
+        .zip(undelta(&lons))
+    {
+        let mut tags = Vec::new();
+        while let Some(key) = keys_values.next().filter(|&key| key != 0) {
+            let value = keys_values
+                .next()
+                .ok_or_else(|| invalid("OSM PBF dense node tag has no value"))?;
+            tags.push((
+                block.string(key)?.to_string(),
+                block.string(value)?.to_string(),
+            ));
+        }
+        add_node(osm, id, block.coord(lat, lon)?, tags);
+    }
+    Ok(())
+}
+
+fn read_way(block: &Block, data: &[u8]) -> Result<Way> {
+    let mut id = 0;
+    let (mut keys, mut values, mut refs) = (Vec::new(), Vec::new(), Vec::new());
+    let mut message = Message::new(data);
+    while let Some((field, value)) = message.next_field()? {
+        match field {
+            1 => id = value.varint()? as i64,
+            2 => value.push_varints(&mut keys)?,
+            3 => value.push_varints(&mut values)?,
+            8 => value.push_varints(&mut refs)?,
+            _ => {}
+        }
+    }
+    Ok(Way {
+        id,
+        refs: undelta(&refs),
+        tags: block.tags(&keys, &values)?,
+    })
+}
+
+/// A `type=multipolygon` relation with its way members; `None` for any
+/// other relation.
+fn read_relation(block: &Block, data: &[u8]) -> Result<Option<Relation>> {
+    const MEMBER_WAY: u64 = 1;
+    let mut id = 0;
+    let (mut keys, mut values) = (Vec::new(), Vec::new());
+    let (mut roles, mut members, mut types) = (Vec::new(), Vec::new(), Vec::new());
+    let mut message = Message::new(data);
+    while let Some((field, value)) = message.next_field()? {
+        match field {
+            1 => id = value.varint()? as i64,
+            2 => value.push_varints(&mut keys)?,
+            3 => value.push_varints(&mut values)?,
+            8 => value.push_varints(&mut roles)?,
+            9 => value.push_varints(&mut members)?,
+            10 => value.push_varints(&mut types)?,
+            _ => {}
+        }
+    }
+    let mut tags = block.tags(&keys, &values)?;
+    let Some(type_tag) = tags.iter().position(|(k, _)| k == "type") else {
+        return Ok(None);
+    };
+    if tags.remove(type_tag).1 != "multipolygon" {
+        return Ok(None);
+    }
+    if roles.len() != members.len() || types.len() != members.len() {
+        return Err(invalid("OSM PBF relation has mismatched member arrays"));
+    }
+    let ways = undelta(&members)
+        .into_iter()
+        .zip(roles.iter().zip(&types))
+        .filter(|(_, (_, &kind))| kind == MEMBER_WAY)
+        .map(|(member, (&role, _))| Ok((member, block.string(role)?.to_string())))
+        .collect::<Result<_>>()?;
+    Ok(Some(Relation { id, ways, tags }))
+}
+
+fn is_area(tags: &Tags) -> bool {
+    match tags
+        .iter()
+        .find(|(k, _)| k == "area")
+        .map(|(_, v)| v.as_str())
+    {
+        Some("yes") => true,
+        Some("no") => false,
+        _ => tags.iter().any(|(k, v)| {
+            AREA_KEYS.contains(&k.as_str())
+                && v != "no"
+                && !(k == "natural" && LINEAR_NATURAL.contains(&v.as_str()))
+        }),
+    }
+}
+
+/// Join member ways end to end into closed rings of node ids; `None` when
+/// they do not all close.
+fn join_rings(parts: Vec<&[i64]>) -> Option<Vec<Vec<i64>>> {
+    let mut remaining: Vec<Vec<i64>> = parts
+        .into_iter()
+        .filter(|part| part.len() >= 2)
+        .map(<[i64]>::to_vec)
+        .collect();
+    let mut rings = Vec::new();
+    while let Some(mut ring) = remaining.pop() {
+        while ring.first() != ring.last() {
+            let end = *ring.last()?;
+            let next = remaining
+                .iter()
+                .position(|part| part.first() == Some(&end) || part.last() == Some(&end))?;
+            let mut part = remaining.swap_remove(next);
+            if part[0] != end {
+                part.reverse();
+            }
+            ring.extend_from_slice(&part[1..]);
+        }
+        if ring.len() < 4 {
+            return None;
+        }
+        rings.push(ring);
+    }
+    Some(rings)
+}
+
+fn build_layers(mut osm: OsmData) -> Result<Vec<OsmLayer>> {
+    if !osm.locations.windows(2).all(|pair| pair[0].0 <= pair[1].0) {
+        osm.locations.sort_by_key(|&(id, _)| id);
+    }
+    let locations = &osm.locations;
+    let locate = |id: i64| {
+        locations
+            .binary_search_by_key(&id, |&(node, _)| node)
+            .ok()
+            .map(|i| locations[i].1)
+    };
+    let ring = |refs: &[i64]| {
+        refs.iter()
+            .map(|&id| locate(id))
+            .collect::<Option<Vec<_>>>()
+    };
+    let feature = |id: i64, kind: &str, geometry: Geometry<f64>, tags: &Tags| {
+        let tags: serde_json::Map<String, serde_json::Value> = tags
+            .iter()
+            .map(|(k, v)| (k.clone(), serde_json::Value::String(v.clone())))
+            .collect();
+        Ok(Feature {
+            geometry: Some(write_ewkb(&geometry, Some(OSM_SRID))?),
+            properties: vec![
+                Value::Integer(id),
+                Value::Text(kind.to_string()),
+                Value::Text(serde_json::Value::Object(tags).to_string()),
+            ],
+        })
+    };
+
+    let points = osm
+        .points
+        .iter()
+        .map(|(id, location, tags)| feature(*id, "node", Point::from(*location).into(), tags))
+        .collect::<Result<_>>()?;
+
+    let (mut lines, mut polygons) = (Vec::new(), Vec::new());
+    for way in osm.ways.iter().filter(|way| !way.tags.is_empty()) {
+        let closed = way.refs.len() >= 4 && way.refs.first() == way.refs.last();
+        if closed && is_area(&way.tags) {
+            if let Some(coords) = ring(&way.refs) {
+                let polygon = Polygon::new(LineString::new(coords), vec![]);
+                polygons.push(feature(
+                    way.id,
+                    "way",
+                    MultiPolygon::new(vec![polygon]).into(),
+                    &way.tags,
+                )?);
+                continue;
+            }
+        }
+        let coords: Vec<_> = way.refs.iter().filter_map(|&id| locate(id)).collect();
+        if coords.len() >= 2 {
+            lines.push(feature(
+                way.id,
+                "way",
+                LineString::new(coords).into(),
+                &way.tags,
+            )?);
+        }
+    }
+
+    let ways: HashMap<i64, &[i64]> = osm
+        .ways
+        .iter()
+        .map(|way| (way.id, way.refs.as_slice()))
+        .collect();
+    for relation in &osm.relations {
+        if let Some(multipolygon) = assemble_multipolygon(relation, &ways, &ring) {
+            polygons.push(feature(
+                relation.id,
+                "relation",
+                multipolygon.into(),
+                &relation.tags,
+            )?);
+        }
+    }
+
+    let fields = vec![
+        Field::new("osm_id", FieldType::Integer),
+        Field::new("osm_type", FieldType::Text),
+        Field::new("tags", FieldType::Text),
+    ];
+    Ok(OSM_LAYERS
+        .into_iter()
+        .zip([points, lines, polygons])
+        .map(|(name, features)| OsmLayer {
+            name,
+            fields: fields.clone(),
+            features,
+        })
+        .collect())
+}
+
+fn assemble_multipolygon(
+    relation: &Relation,
+    ways: &HashMap<i64, &[i64]>,
+    ring: &impl Fn(&[i64]) -> Option<Vec<Coord<f64>>>,
+) -> Option<MultiPolygon<f64>> {
+    let (mut outers, mut inners) = (Vec::new(), Vec::new());
+    for (id, role) in &relation.ways {
+        let refs = *ways.get(id)?;
+        if role == "inner" {
+            inners.push(refs);
+        } else {
+            outers.push(refs);
+        }
+    }
+    let to_rings = |parts| {
+        join_rings(parts)?
+            .iter()
+            .map(|refs| ring(refs).map(LineString::new))
+            .collect::<Option<Vec<_>>>()
+    };
+    let mut polygons: Vec<Polygon<f64>> = to_rings(outers)?
+        .into_iter()
+        .map(|exterior| Polygon::new(exterior, vec![]))
+        .collect();
+    if polygons.is_empty() {
+        return None;
+    }
+    for inner in to_rings(inners)? {
+        // Inner rings often share nodes with the outer ring, so probe with
+        // a point strictly inside the hole rather than one of its vertices.
+        let Some(probe) = Polygon::new(inner.clone(), vec![]).interior_point() else {
+            continue;
+        };
+        if let Some(polygon) = polygons
+            .iter_mut()
+            .find(|polygon| Polygon::new(polygon.exterior().clone(), vec![]).contains(&probe))
+        {
+            polygon.interiors_push(inner);
+        }
+    }
+    Some(MultiPolygon::new(polygons))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::core::functions::io::as_text;
+    use crate::core::limits::{with_limits, Limits};
+
+    const STRINGS: [&str; 15] = [
+        "",
+        "amenity",
+        "cafe",
+        "name",
+        "Main St",
+        "highway",
+        "residential",
+        "building",
+        "yes",
+        "type",
+        "multipolygon",
+        "landuse",
+        "forest",
+        "outer",
+        "inner",
+    ];
+
+    fn s(text: &str) -> u64 {
+        STRINGS.iter().position(|&t| t == text).unwrap() as u64
+    }
+
+    fn varint(out: &mut Vec<u8>, mut v: u64) {
+        while v >= 0x80 {
+            out.push(v as u8 | 0x80);
+            v >>= 7;
+        }
+        out.push(v as u8);
+    }
+
+    fn field_varint(out: &mut Vec<u8>, field: u64, v: u64) {
+        varint(out, field << 3);
+        varint(out, v);
+    }
+
+    fn field_bytes(out: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+        varint(out, field << 3 | 2);
+        varint(out, bytes.len() as u64);
+        out.extend_from_slice(bytes);
+    }
+
+    fn packed(values: impl IntoIterator<Item = u64>) -> Vec<u8> {
+        let mut out = Vec::new();
+        for v in values {
+            varint(&mut out, v);
+        }
+        out
+    }
+
+    fn deltas(values: &[i64]) -> Vec<u8> {
+        let zigzag = |v: i64| ((v << 1) ^ (v >> 63)) as u64;
+        let mut previous = 0;
+        packed(values.iter().map(|&v| {
+            let delta = v - previous;
+            previous = v;
+            zigzag(delta)
+        }))
+    }
+
+    fn zlib(data: &[u8]) -> Vec<u8> {
+        use std::io::Write;
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn blob(out: &mut Vec<u8>, kind: &str, data: &[u8], compress: bool) {
+        let mut blob = Vec::new();
+        if compress {
+            field_varint(&mut blob, 2, data.len() as u64);
+            field_bytes(&mut blob, 3, &zlib(data));
+        } else {
+            field_bytes(&mut blob, 1, data);
+        }
+        let mut header = Vec::new();
+        field_bytes(&mut header, 1, kind.as_bytes());
+        field_varint(&mut header, 3, blob.len() as u64);
+        out.extend_from_slice(&(header.len() as u32).to_be_bytes());
+        out.extend_from_slice(&header);
+        out.extend_from_slice(&blob);
+    }
+
+    fn header_block(features: &[&str]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for feature in features {
+            field_bytes(&mut out, 4, feature.as_bytes());
+        }
+        out
+    }
+
+    fn primitive_block(groups: &[Vec<u8>]) -> Vec<u8> {
+        let mut table = Vec::new();
+        for text in STRINGS {
+            field_bytes(&mut table, 1, text.as_bytes());
+        }
+        let mut out = Vec::new();
+        field_bytes(&mut out, 1, &table);
+        for group in groups {
+            field_bytes(&mut out, 2, group);
+        }
+        out
+    }
+
+    fn way(id: i64, tags: &[(&str, &str)], refs: &[i64]) -> Vec<u8> {
+        let mut way = Vec::new();
+        field_varint(&mut way, 1, id as u64);
+        field_bytes(&mut way, 2, &packed(tags.iter().map(|(k, _)| s(k))));
+        field_bytes(&mut way, 3, &packed(tags.iter().map(|(_, v)| s(v))));
+        field_bytes(&mut way, 8, &deltas(refs));
+        let mut group = Vec::new();
+        field_bytes(&mut group, 3, &way);
+        group
+    }
+
+    /// A small extract: a tagged node, a street, a building, and a forest
+    /// multipolygon whose outer ring is split over two ways around a
+    /// hole. The ways sit in a zlib-compressed block.
+    pub(crate) fn sample_pbf() -> Vec<u8> {
+        // Node id, lon, lat in degrees.
+        let nodes: [(i64, i64, i64); 15] = [
+            (1, 1, 2),
+            (10, 0, 5),
+            (11, 3, 5),
+            (20, 0, 0),
+            (21, 1, 0),
+            (22, 1, 1),
+            (23, 0, 1),
+            (30, 10, 10),
+            (31, 20, 10),
+            (32, 20, 20),
+            (33, 10, 20),
+            (40, 12, 12),
+            (41, 12, 14),
+            (42, 14, 14),
+            (43, 14, 12),
+        ];
+        let ids: Vec<i64> = nodes.iter().map(|n| n.0).collect();
+        let lons: Vec<i64> = nodes.iter().map(|n| n.1 * 10_000_000).collect();
+        let lats: Vec<i64> = nodes.iter().map(|n| n.2 * 10_000_000).collect();
+        let mut keys_values = vec![s("amenity"), s("cafe"), 0];
+        keys_values.extend(std::iter::repeat_n(0, nodes.len() - 1));
+        let mut dense = Vec::new();
+        field_bytes(&mut dense, 1, &deltas(&ids));
+        field_bytes(&mut dense, 8, &deltas(&lats));
+        field_bytes(&mut dense, 9, &deltas(&lons));
+        field_bytes(&mut dense, 10, &packed(keys_values));
+        let mut dense_group = Vec::new();
+        field_bytes(&mut dense_group, 2, &dense);
+
+        let mut relation = Vec::new();
+        field_varint(&mut relation, 1, 200);
+        field_bytes(&mut relation, 2, &packed([s("type"), s("landuse")]));
+        field_bytes(&mut relation, 3, &packed([s("multipolygon"), s("forest")]));
+        field_bytes(
+            &mut relation,
+            8,
+            &packed([s("outer"), s("inner"), s("outer")]),
+        );
+        field_bytes(&mut relation, 9, &deltas(&[102, 104, 103]));
+        field_bytes(&mut relation, 10, &packed([1, 1, 1]));
+        let mut relation_group = Vec::new();
+        field_bytes(&mut relation_group, 4, &relation);
+
+        let mut file = Vec::new();
+        blob(
+            &mut file,
+            "OSMHeader",
+            &header_block(&["OsmSchema-V0.6", "DenseNodes"]),
+            false,
+        );
+        blob(
+            &mut file,
+            "OSMData",
+            &primitive_block(&[dense_group]),
+            false,
+        );
+        blob(
+            &mut file,
+            "OSMData",
+            &primitive_block(&[
+                way(
+                    100,
+                    &[("highway", "residential"), ("name", "Main St")],
+                    &[10, 11],
+                ),
+                way(101, &[("building", "yes")], &[20, 21, 22, 23, 20]),
+                way(102, &[], &[30, 31, 32]),
+                way(103, &[], &[30, 33, 32]),
+                way(104, &[], &[40, 41, 42, 43, 40]),
+            ]),
+            true,
+        );
+        blob(
+            &mut file,
+            "OSMData",
+            &primitive_block(&[relation_group]),
+            false,
+        );
+        file
+    }
+
+    fn wkt(feature: &Feature) -> String {
+        as_text(feature.geometry.as_ref().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn reads_points_lines_and_polygons() {
+        let layers = read_osm_pbf(sample_pbf().as_slice()).unwrap();
+        let names: Vec<_> = layers.iter().map(|layer| layer.name).collect();
+        assert_eq!(names, OSM_LAYERS);
+
+        let [points, lines, polygons] = &layers[..] else {
+            panic!("three layers");
+        };
+        assert_eq!(points.features.len(), 1);
+        assert_eq!(wkt(&points.features[0]), "POINT(1 2)");
+        assert_eq!(
+            points.features[0].properties,
+            vec![
+                Value::Integer(1),
+                Value::Text("node".into()),
+                Value::Text(r#"{"amenity":"cafe"}"#.into()),
+            ]
+        );
+
+        // Untagged member ways are neither lines nor polygons.
+        assert_eq!(lines.features.len(), 1);
+        assert_eq!(wkt(&lines.features[0]), "LINESTRING(0 5,3 5)");
+        assert_eq!(
+            lines.features[0].properties[2],
+            Value::Text(r#"{"highway":"residential","name":"Main St"}"#.into())
+        );
+
+        assert_eq!(polygons.features.len(), 2);
+        assert_eq!(
+            wkt(&polygons.features[0]),
+            "MULTIPOLYGON(((0 0,1 0,1 1,0 1,0 0)))"
+        );
+        let forest = &polygons.features[1];
+        assert_eq!(
+            forest.properties,
+            vec![
+                Value::Integer(200),
+                Value::Text("relation".into()),
+                Value::Text(r#"{"landuse":"forest"}"#.into()),
+            ]
+        );
+        assert_eq!(
+            wkt(forest),
+            "MULTIPOLYGON(((10 10,10 20,20 20,20 10,10 10),(12 12,12 14,14 14,14 12,12 12)))"
+        );
+    }
+
+    #[test]
+    fn classifies_closed_ways() {
+        let tags = |pairs: &[(&str, &str)]| -> Tags {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        assert!(is_area(&tags(&[("building", "yes")])));
+        assert!(is_area(&tags(&[
+            ("highway", "pedestrian"),
+            ("area", "yes")
+        ])));
+        assert!(!is_area(&tags(&[("highway", "residential")])));
+        assert!(!is_area(&tags(&[("natural", "coastline")])));
+        assert!(!is_area(&tags(&[("building", "yes"), ("area", "no")])));
+
+        assert_eq!(
+            join_rings(vec![&[1, 2, 3], &[3, 4], &[1, 4]]),
+            Some(vec![vec![1, 4, 3, 2, 1]])
+        );
+        assert_eq!(join_rings(vec![&[1, 2, 3], &[3, 4]]), None);
+    }
+
+    #[test]
+    fn rejects_unsupported_files() {
+        let mut history = Vec::new();
+        blob(
+            &mut history,
+            "OSMHeader",
+            &header_block(&["OsmSchema-V0.6", "HistoricalInformation"]),
+            false,
+        );
+        let err = read_osm_pbf(history.as_slice()).unwrap_err();
+        assert!(err.to_string().contains("HistoricalInformation"), "{err}");
+
+        let mut dense = Vec::new();
+        field_bytes(&mut dense, 1, &deltas(&[1]));
+        field_bytes(&mut dense, 8, &deltas(&[0]));
+        field_bytes(&mut dense, 9, &deltas(&[i64::MAX / 10]));
+        let mut group = Vec::new();
+        field_bytes(&mut group, 2, &dense);
+        let mut overflow = Vec::new();
+        blob(&mut overflow, "OSMData", &primitive_block(&[group]), false);
+        let err = read_osm_pbf(overflow.as_slice()).unwrap_err();
+        assert!(err.to_string().contains("overflows"), "{err}");
+
+        let mut truncated = sample_pbf();
+        truncated.truncate(truncated.len() - 3);
+        assert!(read_osm_pbf(truncated.as_slice()).is_err());
+        assert_eq!(
+            read_osm_pbf(&[][..]).unwrap()[0].features,
+            Vec::<Feature>::new()
+        );
+    }
+
+    #[test]
+    fn rejects_corrupt_and_oversized_blobs() {
+        let header = header_block(&["OsmSchema-V0.6"]);
+        let with_blob = |blob_body: Vec<u8>| {
+            let mut head = Vec::new();
+            field_bytes(&mut head, 1, b"OSMHeader");
+            field_varint(&mut head, 3, blob_body.len() as u64);
+            let mut out = (head.len() as u32).to_be_bytes().to_vec();
+            out.extend_from_slice(&head);
+            out.extend_from_slice(&blob_body);
+            out
+        };
+
+        let mut compressed = zlib(&header);
+        let last = compressed.len() - 1;
+        compressed[last] ^= 0xFF;
+        let mut body = Vec::new();
+        field_varint(&mut body, 2, header.len() as u64);
+        field_bytes(&mut body, 3, &compressed);
+        let err = read_osm_pbf(with_blob(body).as_slice()).unwrap_err();
+        assert!(err.to_string().contains("corrupt zlib data"), "{err}");
+
+        let mut body = Vec::new();
+        field_varint(&mut body, 2, header.len() as u64 - 1);
+        field_bytes(&mut body, 3, &zlib(&header));
+        let err = read_osm_pbf(with_blob(body).as_slice()).unwrap_err();
+        assert!(err.to_string().contains("past its raw size"), "{err}");
+
+        let pbf = sample_pbf();
+        let limits = Limits {
+            max_input_bytes: Some(64),
+            ..Limits::default()
+        };
+        let err = with_limits(limits, || read_osm_pbf(pbf.as_slice())).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                SqliteGisError::LimitExceeded {
+                    limit: "max_input_bytes",
+                    ..
+                }
+            ),
+            "{err}"
+        );
+        assert!(read_osm_pbf(pbf.as_slice()).is_ok());
+    }
+}
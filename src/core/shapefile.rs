@@ -0,0 +1,1381 @@
+//! Esri Shapefile reader and writer.
+//!
+//! A shapefile is a set of files sharing one base name: `.shp` holds the
+//! geometries, `.shx` their offsets, `.dbf` a dBase III table with one
+//! attribute record per shape, `.prj` the CRS as WKT and `.cpg` the
+//! attribute encoding. [`ShapefileReader`] reads `.shp` and `.dbf` side by
+//! side; [`srid_from_prj`] and [`Charset`] turn `.prj` and `.cpg` into an
+//! SRID and a decoder. [`ShapefileWriter`] buffers every feature, since
+//! the `.shp` header needs the extent, and writes `.shp`, `.shx` and
+//! `.dbf` in [`ShapefileWriter::finish`]; [`prj_for_srid`] gives the
+//! `.prj` for common SRIDs.
+//!
+//! Only XY shapes are supported; the Z and M shape types are rejected
+//! rather than flattened. Polylines read as `MultiLineString` and polygons
+//! as `MultiPolygon`, with clockwise rings as shells and counter-clockwise
+//! rings as holes of the shell that contains them. A file holds one shape
+//! family, so the writer rejects a mix of points, lines and polygons.
+//!
+//! Attributes map onto [`Value`]: `C` and `D` (as `YYYY-MM-DD`) fields are
+//! `Text`, `N` and `F` fields without decimals `Integer` and with decimals
+//! `Real`, and `L` fields `Integer`; blank cells are `NULL`. The writer
+//! declares `N` fields for integers and reals and `C` fields, at most 254
+//! bytes, for text and BLOBs (as hex), and always encodes text as UTF-8.
+//! dBase field names are cut to 10 bytes, with a numeric suffix to keep
+//! them unique.
+//!
+//! ```
+//! use sqlitegis::core::features::{Feature, Field, FieldType, Value};
+//! use sqlitegis::core::functions::io::geom_from_text;
+//! use sqlitegis::core::shapefile::{Charset, ShapefileReader, ShapefileWriter};
+//!
+//! let mut writer = ShapefileWriter::new(vec![Field::new("name", FieldType::Text)]);
+//! writer
+//!     .add_feature(&Feature {
+//!         geometry: Some(geom_from_text("POINT(1 2)", Some(4326)).unwrap()),
+//!         properties: vec![Value::Text("a".into())],
+//!     })
+//!     .unwrap();
+//! let (mut shp, mut shx, mut dbf) = (Vec::new(), Vec::new(), Vec::new());
+//! assert_eq!(writer.finish(&mut shp, &mut shx, &mut dbf).unwrap(), 1);
+//!
+//! let mut reader =
+//!     ShapefileReader::new(shp.as_slice(), dbf.as_slice(), Charset::Utf8, Some(4326)).unwrap();
+//! assert_eq!(reader.fields()[0].name, "name");
+//! let feature = reader.next().unwrap().unwrap();
+//! assert_eq!(feature.properties, vec![Value::Text("a".into())]);
+//! assert!(reader.next().is_none());
+//! ```
+
+use std::io::{Read, Write};
+
+use geo::{
+    Contains, Coord, Geometry, LineString, MultiLineString, MultiPoint, MultiPolygon, Point,
+    Polygon, Rect,
+};
+
+use crate::core::error::{Result, SqliteGisError};
+use crate::core::ewkb::{ensure_matching_srid, parse_ewkb, write_ewkb};
+use crate::core::features::{read_or_eof, Feature, Field, FieldType, Value};
+use crate::core::limits::{check_input_bytes, check_input_vertices};
+
+/// `.shp` and `.shx` file code.
+const FILE_CODE: i32 = 9994;
+/// `.shp` and `.shx` format version.
+const VERSION: i32 = 1000;
+/// Length of the `.shp` and `.shx` headers in bytes.
+const HEADER_LEN: usize = 100;
+
+const SHAPE_NULL: i32 = 0;
+const SHAPE_POINT: i32 = 1;
+const SHAPE_POLYLINE: i32 = 3;
+const SHAPE_POLYGON: i32 = 5;
+const SHAPE_MULTIPOINT: i32 = 8;
+
+/// dBase III version byte, without a memo file.
+const DBF_VERSION: u8 = 0x03;
+const DBF_HEADER_END: u8 = 0x0D;
+const DBF_EOF: u8 = 0x1A;
+const DBF_DELETED: u8 = b'*';
+/// Longest `C` field dBase allows.
+const DBF_MAX_CHAR_LEN: usize = 254;
+/// Longest dBase field name, in bytes.
+const DBF_MAX_NAME_LEN: usize = 10;
+/// Width of the `N` fields the writer declares for reals.
+const DBF_REAL_LEN: usize = 24;
+/// Most decimals the writer keeps for reals.
+const DBF_REAL_DECIMALS: usize = 15;
+
+/// Text encoding of `.dbf` attribute values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Charset {
+    /// UTF-8; invalid sequences are an error.
+    Utf8,
+    /// ISO-8859-1, every byte its own code point.
+    Latin1,
+    /// Windows-1252, Latin-1 with printable characters in `0x80..=0x9F`.
+    Windows1252,
+}
+
+/// Windows-1252 characters for bytes `0x80..=0x9F`; undefined bytes map
+/// to the C1 control of the same value, as in Latin-1.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{8D}', '\u{017D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{9D}', '\u{017E}', '\u{0178}',
+];
+
+impl Charset {
+    /// The charset called `name`, as found in a `.cpg` file or given by
+    /// the caller: `UTF-8`, `ISO-8859-1` / `Latin1`, or `CP1252` /
+    /// `Windows-1252`, in any case and with or without separators.
+    pub fn from_name(name: &str) -> Result<Charset> {
+        let normalized: String = name
+            .trim()
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_uppercase();
+        match normalized.as_str() {
+            "UTF8" => Ok(Charset::Utf8),
+            "LATIN1" | "ISO88591" | "88591" => Ok(Charset::Latin1),
+            "CP1252" | "WINDOWS1252" | "1252" | "ANSI1252" => Ok(Charset::Windows1252),
+            _ => Err(SqliteGisError::InvalidInput(format!(
+                "unsupported charset [{}]; use UTF-8, ISO-8859-1 or CP1252",
+                name.trim()
+            ))),
+        }
+    }
+
+    fn decode(self, bytes: &[u8]) -> Result<String> {
+        match self {
+            Charset::Utf8 => String::from_utf8(bytes.to_vec()).map_err(|_| {
+                SqliteGisError::InvalidInput(
+                    "attribute text is not valid UTF-8; pass the .dbf charset".to_string(),
+                )
+            }),
+            Charset::Latin1 => Ok(bytes.iter().map(|&b| char::from(b)).collect()),
+            Charset::Windows1252 => Ok(bytes
+                .iter()
+                .map(|&b| match b {
+                    0x80..=0x9F => WINDOWS_1252_HIGH[usize::from(b - 0x80)],
+                    _ => char::from(b),
+                })
+                .collect()),
+        }
+    }
+}
+
+fn invalid(msg: impl Into<String>) -> SqliteGisError {
+    SqliteGisError::InvalidInput(msg.into())
+}
+
+fn le_i32(bytes: &[u8], at: usize) -> i32 {
+    i32::from_le_bytes(bytes[at..at + 4].try_into().expect("4 bytes"))
+}
+
+fn be_i32(bytes: &[u8], at: usize) -> i32 {
+    i32::from_be_bytes(bytes[at..at + 4].try_into().expect("4 bytes"))
+}
+
+fn le_f64(bytes: &[u8], at: usize) -> f64 {
+    f64::from_le_bytes(bytes[at..at + 8].try_into().expect("8 bytes"))
+}
+
+/// Bounds-checked view of one `.shp` record's content.
+struct Content<'a> {
+    bytes: &'a [u8],
+}
+
+impl Content<'_> {
+    fn need(&self, end: usize) -> Result<()> {
+        if end > self.bytes.len() {
+            return Err(invalid("shapefile record is truncated"));
+        }
+        Ok(())
+    }
+
+    fn count(&self, at: usize) -> Result<usize> {
+        self.need(at + 4)?;
+        usize::try_from(le_i32(self.bytes, at))
+            .map_err(|_| invalid("shapefile record has a negative count"))
+    }
+
+    fn points(&self, at: usize, n: usize) -> Result<Vec<Coord<f64>>> {
+        let end = n
+            .checked_mul(16)
+            .and_then(|len| len.checked_add(at))
+            .ok_or_else(|| invalid("shapefile record is truncated"))?;
+        self.need(end)?;
+        Ok((0..n)
+            .map(|i| Coord {
+                x: le_f64(self.bytes, at + i * 16),
+                y: le_f64(self.bytes, at + i * 16 + 8),
+            })
+            .collect())
+    }
+
+    /// The parts of a polyline or polygon record.
+    fn parts(&self) -> Result<Vec<LineString<f64>>> {
+        let num_parts = self.count(36)?;
+        let num_points = self.count(40)?;
+        let parts_at = 44;
+        let points_at = num_parts
+            .checked_mul(4)
+            .and_then(|len| len.checked_add(parts_at))
+            .ok_or_else(|| invalid("shapefile record is truncated"))?;
+        self.need(points_at)?;
+        let starts: Vec<usize> = (0..num_parts)
+            .map(|i| le_i32(self.bytes, parts_at + i * 4).max(0) as usize)
+            .collect();
+        let points = self.points(points_at, num_points)?;
+        starts
+            .iter()
+            .enumerate()
+            .map(|(i, &start)| {
+                let end = starts.get(i + 1).copied().unwrap_or(num_points);
+                if start > end || end > num_points {
+                    return Err(invalid("shapefile part index out of range"));
+                }
+                Ok(LineString::new(points[start..end].to_vec()))
+            })
+            .collect()
+    }
+}
+
+/// Twice the signed area of a ring; negative when it runs clockwise.
+fn signed_area(ring: &LineString<f64>) -> f64 {
+    ring.lines()
+        .map(|line| line.start.x * line.end.y - line.end.x * line.start.y)
+        .sum()
+}
+
+/// Group polygon rings into polygons: clockwise rings are shells, and
+/// every other ring is a hole of the first shell containing it, or a shell
+/// of its own when none does.
+fn assemble_polygons(rings: Vec<LineString<f64>>) -> MultiPolygon<f64> {
+    let (shells, holes): (Vec<_>, Vec<_>) = rings
+        .into_iter()
+        .filter(|ring| ring.0.len() >= 4)
+        .partition(|ring| signed_area(ring) < 0.0);
+    let mut polygons: Vec<Polygon<f64>> = shells
+        .into_iter()
+        .map(|shell| Polygon::new(shell, vec![]))
+        .collect();
+    for hole in holes {
+        let probe = Point::from(hole.0[0]);
+        match polygons
+            .iter_mut()
+            .find(|polygon| Polygon::new(polygon.exterior().clone(), vec![]).contains(&probe))
+        {
+            Some(polygon) => polygon.interiors_push(hole),
+            None => polygons.push(Polygon::new(hole, vec![])),
+        }
+    }
+    MultiPolygon::new(polygons)
+}
+
+/// Decode one `.shp` record's content into a geometry; `None` for a null
+/// shape.
+fn read_shape(bytes: &[u8]) -> Result<Option<Geometry<f64>>> {
+    let content = Content { bytes };
+    content.need(4)?;
+    let shape_type = le_i32(bytes, 0);
+    let geom = match shape_type {
+        SHAPE_NULL => None,
+        SHAPE_POINT => Some(Geometry::Point(Point::from(content.points(4, 1)?[0]))),
+        SHAPE_MULTIPOINT => {
+            let n = content.count(36)?;
+            Some(Geometry::MultiPoint(MultiPoint::new(
+                content
+                    .points(40, n)?
+                    .into_iter()
+                    .map(Point::from)
+                    .collect(),
+            )))
+        }
+        SHAPE_POLYLINE => Some(Geometry::MultiLineString(MultiLineString::new(
+            content.parts()?,
+        ))),
+        SHAPE_POLYGON => Some(Geometry::MultiPolygon(assemble_polygons(content.parts()?))),
+        other => return Err(unsupported_shape_type(other)),
+    };
+    if let Some(geom) = &geom {
+        check_input_vertices(geom)?;
+    }
+    Ok(geom)
+}
+
+fn unsupported_shape_type(shape_type: i32) -> SqliteGisError {
+    match shape_type {
+        11 | 13 | 15 | 18 | 21 | 23 | 25 | 28 | 31 => invalid(format!(
+            "shape type {shape_type} has Z or M coordinates; only XY shapefiles are supported"
+        )),
+        _ => invalid(format!("unknown shape type {shape_type}")),
+    }
+}
+
+/// One `.dbf` field descriptor.
+#[derive(Debug, Clone)]
+struct DbfField {
+    kind: u8,
+    offset: usize,
+    len: usize,
+    decimals: u8,
+}
+
+impl DbfField {
+    fn field_type(&self) -> FieldType {
+        match self.kind {
+            b'N' | b'F' if self.decimals == 0 && self.len <= 20 => FieldType::Integer,
+            b'N' | b'F' | b'O' => FieldType::Real,
+            b'L' | b'I' => FieldType::Integer,
+            _ => FieldType::Text,
+        }
+    }
+
+    fn read(&self, record: &[u8], charset: Charset) -> Result<Value> {
+        let raw = &record[self.offset..self.offset + self.len];
+        match self.kind {
+            b'I' if raw.len() == 4 => {
+                return Ok(Value::Integer(i64::from(le_i32(raw, 0))));
+            }
+            b'O' if raw.len() == 8 => return Ok(Value::Real(le_f64(raw, 0))),
+            _ => {}
+        }
+        let text = charset.decode(raw)?;
+        let trimmed = text.trim_matches(|c: char| c == ' ' || c == '\0');
+        if trimmed.is_empty() {
+            return Ok(Value::Null);
+        }
+        Ok(match (self.kind, self.field_type()) {
+            (b'L', _) => match trimmed.as_bytes()[0].to_ascii_uppercase() {
+                b'T' | b'Y' => Value::Integer(1),
+                b'F' | b'N' => Value::Integer(0),
+                _ => Value::Null,
+            },
+            (b'D', _) if trimmed.len() == 8 && trimmed.bytes().all(|b| b.is_ascii_digit()) => {
+                Value::Text(format!(
+                    "{}-{}-{}",
+                    &trimmed[..4],
+                    &trimmed[4..6],
+                    &trimmed[6..]
+                ))
+            }
+            (_, FieldType::Integer) => trimmed.parse().map_or(Value::Null, Value::Integer),
+            (_, FieldType::Real) => trimmed.parse().map_or(Value::Null, Value::Real),
+            _ => Value::Text(text.trim_end_matches([' ', '\0']).to_string()),
+        })
+    }
+}
+
+/// Streams features out of a `.shp` and its `.dbf`.
+pub struct ShapefileReader<S: Read, D: Read> {
+    shp: S,
+    dbf: D,
+    charset: Charset,
+    srid: Option<i32>,
+    shape_type: i32,
+    fields: Vec<Field>,
+    dbf_fields: Vec<DbfField>,
+    record: Vec<u8>,
+    remaining: u32,
+}
+
+impl<S: Read, D: Read> ShapefileReader<S, D> {
+    /// Read the `.shp` and `.dbf` headers. Attribute text is decoded with
+    /// `charset`, and geometries get `srid`.
+    pub fn new(mut shp: S, mut dbf: D, charset: Charset, srid: Option<i32>) -> Result<Self> {
+        let mut header = [0u8; HEADER_LEN];
+        if !read_or_eof(&mut shp, &mut header)? || be_i32(&header, 0) != FILE_CODE {
+            return Err(invalid("not a shapefile: bad .shp file code"));
+        }
+        let shape_type = le_i32(&header, 32);
+        if ![
+            SHAPE_NULL,
+            SHAPE_POINT,
+            SHAPE_POLYLINE,
+            SHAPE_POLYGON,
+            SHAPE_MULTIPOINT,
+        ]
+        .contains(&shape_type)
+        {
+            return Err(unsupported_shape_type(shape_type));
+        }
+
+        let mut dbf_header = [0u8; 32];
+        if !read_or_eof(&mut dbf, &mut dbf_header)? {
+            return Err(invalid("not a dBase file: .dbf is empty"));
+        }
+        let remaining = u32::from_le_bytes(dbf_header[4..8].try_into().expect("4 bytes"));
+        let header_len = usize::from(u16::from_le_bytes([dbf_header[8], dbf_header[9]]));
+        let record_len = usize::from(u16::from_le_bytes([dbf_header[10], dbf_header[11]]));
+        if header_len < 33 || record_len == 0 {
+            return Err(invalid("not a dBase file: bad .dbf header"));
+        }
+        let mut descriptors = vec![0u8; header_len - 32];
+        if !read_or_eof(&mut dbf, &mut descriptors)? {
+            return Err(invalid("not a dBase file: .dbf header is truncated"));
+        }
+
+        let mut fields = Vec::new();
+        let mut dbf_fields = Vec::new();
+        let mut offset = 1;
+        for descriptor in descriptors.chunks_exact(32) {
+            if descriptor[0] == DBF_HEADER_END {
+                break;
+            }
+            let name_len = descriptor[..11].iter().position(|&b| b == 0).unwrap_or(11);
+            let name = charset
+                .decode(&descriptor[..name_len])
+                .unwrap_or_else(|_| String::from_utf8_lossy(&descriptor[..name_len]).into())
+                .trim()
+                .to_string();
+            let field = DbfField {
+                kind: descriptor[11].to_ascii_uppercase(),
+                offset,
+                len: usize::from(descriptor[16]),
+                decimals: descriptor[17],
+            };
+            offset += field.len;
+            fields.push(Field::new(name, field.field_type()));
+            dbf_fields.push(field);
+        }
+        if offset > record_len {
+            return Err(invalid("dBase fields overrun the record length"));
+        }
+
+        Ok(Self {
+            shp,
+            dbf,
+            charset,
+            srid,
+            shape_type,
+            fields,
+            dbf_fields,
+            record: vec![0; record_len],
+            remaining,
+        })
+    }
+
+    /// Attribute fields, in `.dbf` order.
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Shape type code from the `.shp` header: 0 null, 1 point, 3
+    /// polyline, 5 polygon or 8 multipoint.
+    pub fn shape_type(&self) -> i32 {
+        self.shape_type
+    }
+
+    /// Read the next shape, with its attributes when the `.dbf` has a
+    /// record left. Deleted `.dbf` records are skipped with their shapes.
+    pub fn next_feature(&mut self) -> Result<Option<Feature>> {
+        loop {
+            let mut record_header = [0u8; 8];
+            if !read_or_eof(&mut self.shp, &mut record_header)? {
+                return Ok(None);
+            }
+            let content_len = usize::try_from(be_i32(&record_header, 4))
+                .map_err(|_| invalid("shapefile record has a negative length"))?
+                * 2;
+            check_input_bytes(content_len)?;
+            let mut content = Vec::new();
+            (&mut self.shp)
+                .take(content_len as u64)
+                .read_to_end(&mut content)?;
+            if content.len() != content_len {
+                return Err(invalid("shapefile record is truncated"));
+            }
+
+            let mut properties = vec![Value::Null; self.fields.len()];
+            if self.remaining > 0 {
+                self.remaining -= 1;
+                if !read_or_eof(&mut self.dbf, &mut self.record)? {
+                    return Err(invalid(".dbf has fewer records than its header says"));
+                }
+                if self.record[0] == DBF_DELETED {
+                    continue;
+                }
+                for (value, field) in properties.iter_mut().zip(&self.dbf_fields) {
+                    *value = field.read(&self.record, self.charset)?;
+                }
+            }
+            let geometry = match read_shape(&content)? {
+                Some(geom) => Some(write_ewkb(&geom, self.srid)?),
+                None => None,
+            };
+            return Ok(Some(Feature {
+                geometry,
+                properties,
+            }));
+        }
+    }
+}
+
+impl<S: Read, D: Read> Iterator for ShapefileReader<S, D> {
+    type Item = Result<Feature>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_feature().transpose()
+    }
+}
+
+/// Shape family of a geometry, which decides the file's shape type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Family {
+    Point,
+    Line,
+    Polygon,
+}
+
+impl Family {
+    fn name(self) -> &'static str {
+        match self {
+            Family::Point => "point",
+            Family::Line => "line",
+            Family::Polygon => "polygon",
+        }
+    }
+}
+
+/// A geometry reduced to shapefile parts.
+enum Shape {
+    Null,
+    Point(Coord<f64>),
+    MultiPoint(Vec<Coord<f64>>),
+    Parts(Family, Vec<Vec<Coord<f64>>>),
+}
+
+impl Shape {
+    fn from_geometry(geom: Geometry<f64>) -> Result<Shape> {
+        let lines = |lines: Vec<LineString<f64>>| {
+            let parts: Vec<_> = lines
+                .into_iter()
+                .filter(|line| !line.0.is_empty())
+                .map(|line| line.0)
+                .collect();
+            if parts.is_empty() {
+                Shape::Null
+            } else {
+                Shape::Parts(Family::Line, parts)
+            }
+        };
+        let polygons = |polygons: Vec<Polygon<f64>>| {
+            let mut parts = Vec::new();
+            for polygon in polygons {
+                let (exterior, interiors) = polygon.into_inner();
+                if exterior.0.is_empty() {
+                    continue;
+                }
+                parts.push(oriented(exterior, true));
+                parts.extend(interiors.into_iter().map(|ring| oriented(ring, false)));
+            }
+            if parts.is_empty() {
+                Shape::Null
+            } else {
+                Shape::Parts(Family::Polygon, parts)
+            }
+        };
+        Ok(match geom {
+            Geometry::Point(p) if p.x().is_nan() && p.y().is_nan() => Shape::Null,
+            Geometry::Point(p) => Shape::Point(p.0),
+            Geometry::MultiPoint(mp) if mp.0.is_empty() => Shape::Null,
+            Geometry::MultiPoint(mp) => Shape::MultiPoint(mp.0.into_iter().map(|p| p.0).collect()),
+            Geometry::Line(line) => lines(vec![LineString::from(line)]),
+            Geometry::LineString(line) => lines(vec![line]),
+            Geometry::MultiLineString(mls) => lines(mls.0),
+            Geometry::Polygon(polygon) => polygons(vec![polygon]),
+            Geometry::Rect(rect) => polygons(vec![rect.to_polygon()]),
+            Geometry::Triangle(triangle) => polygons(vec![triangle.to_polygon()]),
+            Geometry::MultiPolygon(mp) => polygons(mp.0),
+            Geometry::GeometryCollection(_) => {
+                return Err(invalid("a shapefile cannot hold a GeometryCollection"))
+            }
+        })
+    }
+
+    fn family(&self) -> Option<Family> {
+        match self {
+            Shape::Null => None,
+            Shape::Point(_) | Shape::MultiPoint(_) => Some(Family::Point),
+            Shape::Parts(family, _) => Some(*family),
+        }
+    }
+
+    fn coords(&self) -> Box<dyn Iterator<Item = &Coord<f64>> + '_> {
+        match self {
+            Shape::Null => Box::new(std::iter::empty()),
+            Shape::Point(c) => Box::new(std::iter::once(c)),
+            Shape::MultiPoint(coords) => Box::new(coords.iter()),
+            Shape::Parts(_, parts) => Box::new(parts.iter().flatten()),
+        }
+    }
+
+    fn bbox(&self) -> Option<Rect<f64>> {
+        self.coords().fold(None, |bbox, c| {
+            Some(match bbox {
+                None => Rect::new(*c, *c),
+                Some(r) => Rect::new(
+                    Coord {
+                        x: r.min().x.min(c.x),
+                        y: r.min().y.min(c.y),
+                    },
+                    Coord {
+                        x: r.max().x.max(c.x),
+                        y: r.max().y.max(c.y),
+                    },
+                ),
+            })
+        })
+    }
+
+    /// Record content for a file of `shape_type`.
+    fn encode(&self, shape_type: i32) -> Vec<u8> {
+        let mut out = Vec::new();
+        let push_f64 = |out: &mut Vec<u8>, v: f64| out.extend_from_slice(&v.to_le_bytes());
+        let push_i32 = |out: &mut Vec<u8>, v: i32| out.extend_from_slice(&v.to_le_bytes());
+        let push_bbox = |out: &mut Vec<u8>, bbox: Option<Rect<f64>>| {
+            let bbox = bbox.expect("non-null shapes have coordinates");
+            for v in [bbox.min().x, bbox.min().y, bbox.max().x, bbox.max().y] {
+                push_f64(out, v);
+            }
+        };
+        match self {
+            Shape::Null => push_i32(&mut out, SHAPE_NULL),
+            Shape::Point(c) if shape_type == SHAPE_POINT => {
+                push_i32(&mut out, SHAPE_POINT);
+                push_f64(&mut out, c.x);
+                push_f64(&mut out, c.y);
+            }
+            Shape::Point(_) | Shape::MultiPoint(_) => {
+                push_i32(&mut out, SHAPE_MULTIPOINT);
+                push_bbox(&mut out, self.bbox());
+                let coords: Vec<_> = self.coords().collect();
+                push_i32(&mut out, coords.len() as i32);
+                for c in coords {
+                    push_f64(&mut out, c.x);
+                    push_f64(&mut out, c.y);
+                }
+            }
+            Shape::Parts(_, parts) => {
+                push_i32(&mut out, shape_type);
+                push_bbox(&mut out, self.bbox());
+                push_i32(&mut out, parts.len() as i32);
+                push_i32(&mut out, parts.iter().map(Vec::len).sum::<usize>() as i32);
+                let mut start = 0;
+                for part in parts {
+                    push_i32(&mut out, start as i32);
+                    start += part.len();
+                }
+                for c in parts.iter().flatten() {
+                    push_f64(&mut out, c.x);
+                    push_f64(&mut out, c.y);
+                }
+            }
+        }
+        out
+    }
+}
+
+/// A polygon ring running clockwise for a shell, counter-clockwise for a
+/// hole, as shapefiles require.
+fn oriented(ring: LineString<f64>, shell: bool) -> Vec<Coord<f64>> {
+    let mut coords = ring.0;
+    if (signed_area(&LineString::new(coords.clone())) < 0.0) != shell {
+        coords.reverse();
+    }
+    coords
+}
+
+/// Writes features to a `.shp`, `.shx` and `.dbf` triple.
+pub struct ShapefileWriter {
+    fields: Vec<Field>,
+    srid: Option<Option<i32>>,
+    family: Option<Family>,
+    multipoint: bool,
+    shapes: Vec<Shape>,
+    records: Vec<Vec<Value>>,
+}
+
+impl ShapefileWriter {
+    /// A writer with the given attribute fields.
+    pub fn new(fields: Vec<Field>) -> Self {
+        Self {
+            fields,
+            srid: None,
+            family: None,
+            multipoint: false,
+            shapes: Vec::new(),
+            records: Vec::new(),
+        }
+    }
+
+    /// Number of features added so far.
+    pub fn len(&self) -> usize {
+        self.shapes.len()
+    }
+
+    /// Whether no feature has been added.
+    pub fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+
+    /// The SRID shared by the geometries added so far, for the `.prj`.
+    pub fn srid(&self) -> Option<i32> {
+        self.srid.flatten()
+    }
+
+    /// Add one feature. It needs one property per field; geometries must
+    /// share one SRID and one shape family (points, lines or polygons).
+    pub fn add_feature(&mut self, feature: &Feature) -> Result<()> {
+        if feature.properties.len() != self.fields.len() {
+            return Err(invalid(format!(
+                "feature has {} properties but the dataset has {} columns",
+                feature.properties.len(),
+                self.fields.len()
+            )));
+        }
+        let shape = match &feature.geometry {
+            Some(blob) => {
+                let (geom, srid) = parse_ewkb(blob)?;
+                match self.srid {
+                    Some(shared) => {
+                        ensure_matching_srid(shared, srid)?;
+                    }
+                    None => self.srid = Some(srid),
+                }
+                Shape::from_geometry(geom)?
+            }
+            None => Shape::Null,
+        };
+        if let Some(family) = shape.family() {
+            match self.family {
+                Some(shared) if shared != family => {
+                    return Err(invalid(format!(
+                        "a shapefile cannot mix {} and {} geometries",
+                        shared.name(),
+                        family.name()
+                    )))
+                }
+                _ => self.family = Some(family),
+            }
+        }
+        self.multipoint |= matches!(shape, Shape::MultiPoint(_));
+        self.shapes.push(shape);
+        self.records.push(
+            feature
+                .properties
+                .iter()
+                .zip(&self.fields)
+                .map(|(value, field)| value.coerce(field.field_type))
+                .collect(),
+        );
+        Ok(())
+    }
+
+    fn shape_type(&self) -> i32 {
+        match self.family {
+            None => SHAPE_NULL,
+            Some(Family::Point) if self.multipoint => SHAPE_MULTIPOINT,
+            Some(Family::Point) => SHAPE_POINT,
+            Some(Family::Line) => SHAPE_POLYLINE,
+            Some(Family::Polygon) => SHAPE_POLYGON,
+        }
+    }
+
+    /// Write the `.shp`, `.shx` and `.dbf` files and return the number of
+    /// features written.
+    pub fn finish(self, shp: impl Write, shx: impl Write, dbf: impl Write) -> Result<u64> {
+        self.write_shapes(shp, shx)?;
+        self.write_dbf(dbf)?;
+        Ok(self.shapes.len() as u64)
+    }
+
+    fn write_shapes(&self, mut shp: impl Write, mut shx: impl Write) -> Result<()> {
+        let shape_type = self.shape_type();
+        let contents: Vec<Vec<u8>> = self
+            .shapes
+            .iter()
+            .map(|shape| shape.encode(shape_type))
+            .collect();
+        let shp_len = HEADER_LEN + contents.iter().map(|c| c.len() + 8).sum::<usize>();
+        let shx_len = HEADER_LEN + contents.len() * 8;
+        let words = |len: usize| {
+            i32::try_from(len / 2).map_err(|_| invalid("shapefile would exceed 4 GiB"))
+        };
+        let bbox = self.shapes.iter().filter_map(Shape::bbox).reduce(|a, b| {
+            Rect::new(
+                Coord {
+                    x: a.min().x.min(b.min().x),
+                    y: a.min().y.min(b.min().y),
+                },
+                Coord {
+                    x: a.max().x.max(b.max().x),
+                    y: a.max().y.max(b.max().y),
+                },
+            )
+        });
+        let header = |len: i32| {
+            let mut header = Vec::with_capacity(HEADER_LEN);
+            header.extend_from_slice(&FILE_CODE.to_be_bytes());
+            header.extend_from_slice(&[0; 20]);
+            header.extend_from_slice(&len.to_be_bytes());
+            header.extend_from_slice(&VERSION.to_le_bytes());
+            header.extend_from_slice(&shape_type.to_le_bytes());
+            let extent = bbox.map_or([0.0; 4], |b| [b.min().x, b.min().y, b.max().x, b.max().y]);
+            for v in extent.into_iter().chain([0.0; 4]) {
+                header.extend_from_slice(&f64::to_le_bytes(v));
+            }
+            header
+        };
+
+        shp.write_all(&header(words(shp_len)?))?;
+        shx.write_all(&header(words(shx_len)?))?;
+        let mut offset = HEADER_LEN;
+        for (i, content) in contents.iter().enumerate() {
+            let number = i32::try_from(i + 1).map_err(|_| invalid("too many shapes"))?;
+            let content_words = words(content.len())?;
+            shp.write_all(&number.to_be_bytes())?;
+            shp.write_all(&content_words.to_be_bytes())?;
+            shp.write_all(content)?;
+            shx.write_all(&words(offset)?.to_be_bytes())?;
+            shx.write_all(&content_words.to_be_bytes())?;
+            offset += content.len() + 8;
+        }
+        shp.flush()?;
+        shx.flush()?;
+        Ok(())
+    }
+
+    fn write_dbf(&self, mut dbf: impl Write) -> Result<()> {
+        let names = dbf_names(&self.fields);
+        let cells: Vec<Vec<String>> = self
+            .records
+            .iter()
+            .map(|record| {
+                record
+                    .iter()
+                    .map(|value| match value {
+                        Value::Null => String::new(),
+                        Value::Integer(i) => i.to_string(),
+                        Value::Real(r) => format_real(*r),
+                        Value::Text(t) => truncate(t, DBF_MAX_CHAR_LEN).to_string(),
+                        Value::Blob(_) => match value.coerce(FieldType::Text) {
+                            Value::Text(t) => truncate(&t, DBF_MAX_CHAR_LEN).to_string(),
+                            _ => String::new(),
+                        },
+                    })
+                    .collect()
+            })
+            .collect();
+        let descriptors: Vec<(u8, usize, u8)> = self
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                let widest = cells.iter().map(|row| row[i].len()).max().unwrap_or(0);
+                match field.field_type {
+                    FieldType::Integer => (b'N', widest.clamp(1, 20), 0),
+                    FieldType::Real => (b'N', DBF_REAL_LEN, DBF_REAL_DECIMALS as u8),
+                    FieldType::Text | FieldType::Blob => {
+                        (b'C', widest.clamp(1, DBF_MAX_CHAR_LEN), 0)
+                    }
+                }
+            })
+            .collect();
+
+        let header_len = 32 + 32 * self.fields.len() + 1;
+        let record_len = 1 + descriptors.iter().map(|d| d.1).sum::<usize>();
+        let (header_len, record_len) = match (u16::try_from(header_len), u16::try_from(record_len))
+        {
+            (Ok(h), Ok(r)) => (h, r),
+            _ => return Err(invalid("too many attribute columns for a .dbf")),
+        };
+        let count = u32::try_from(self.records.len()).map_err(|_| invalid("too many records"))?;
+        let (year, month, day) = today();
+        let mut header = vec![DBF_VERSION, (year - 1900) as u8, month, day];
+        header.extend_from_slice(&count.to_le_bytes());
+        header.extend_from_slice(&header_len.to_le_bytes());
+        header.extend_from_slice(&record_len.to_le_bytes());
+        header.extend_from_slice(&[0; 20]);
+        for (name, &(kind, len, decimals)) in names.iter().zip(&descriptors) {
+            let mut descriptor = [0u8; 32];
+            descriptor[..name.len()].copy_from_slice(name.as_bytes());
+            descriptor[11] = kind;
+            descriptor[16] = len as u8;
+            descriptor[17] = decimals;
+            header.extend_from_slice(&descriptor);
+        }
+        header.push(DBF_HEADER_END);
+        dbf.write_all(&header)?;
+
+        let mut record = Vec::with_capacity(usize::from(record_len));
+        for row in &cells {
+            record.clear();
+            record.push(b' ');
+            for (cell, &(kind, len, _)) in row.iter().zip(&descriptors) {
+                let padding = len.saturating_sub(cell.len());
+                if kind == b'N' {
+                    record.extend(std::iter::repeat_n(b' ', padding));
+                    record.extend_from_slice(&cell.as_bytes()[..cell.len().min(len)]);
+                } else {
+                    record.extend_from_slice(cell.as_bytes());
+                    record.extend(std::iter::repeat_n(b' ', padding));
+                }
+            }
+            dbf.write_all(&record)?;
+        }
+        dbf.write_all(&[DBF_EOF])?;
+        dbf.flush()?;
+        Ok(())
+    }
+}
+
+/// `s` cut to at most `max` bytes on a character boundary.
+fn truncate(s: &str, max: usize) -> &str {
+    let mut end = s.len().min(max);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// A real as `N` field text: up to 15 decimals within the field width,
+/// falling back to exponent notation for very large or small values.
+fn format_real(r: f64) -> String {
+    let integer_digits = format!("{:.0}", r.trunc()).len();
+    if integer_digits + 2 <= DBF_REAL_LEN {
+        let decimals = DBF_REAL_DECIMALS.min(DBF_REAL_LEN - integer_digits - 1);
+        let fixed = format!("{r:.decimals$}");
+        let trimmed = fixed.trim_end_matches('0').trim_end_matches('.');
+        if trimmed.parse::<f64>().is_ok_and(|v| v == r) || r.abs() >= 1e-6 {
+            return trimmed.to_string();
+        }
+    }
+    format!("{r:e}")
+}
+
+/// dBase field names: at most 10 bytes, made unique with a `_N` suffix.
+fn dbf_names(fields: &[Field]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::with_capacity(fields.len());
+    for field in fields {
+        let base = truncate(&field.name, DBF_MAX_NAME_LEN);
+        let mut name = base.to_string();
+        let mut n = 1;
+        while names.iter().any(|taken| taken.eq_ignore_ascii_case(&name)) {
+            let suffix = format!("_{n}");
+            name = format!(
+                "{}{suffix}",
+                truncate(base, DBF_MAX_NAME_LEN - suffix.len())
+            );
+            n += 1;
+        }
+        names.push(name);
+    }
+    names
+}
+
+/// Today's UTC date for the `.dbf` header.
+fn today() -> (i32, u8, u8) {
+    let days = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| (d.as_secs() / 86_400) as i64);
+    // Civil-from-days, proleptic Gregorian calendar.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+    let year = (yoe + era * 400 + i64::from(month <= 2)) as i32;
+    (year, month, day)
+}
+
+/// WKT of the geographic CRSs [`prj_for_srid`] knows, keyed by SRID.
+const GEOGCS: [(i32, &str); 3] = [
+    (
+        4326,
+        r#"GEOGCS["GCS_WGS_1984",DATUM["D_WGS_1984",SPHEROID["WGS_1984",6378137.0,298.257223563]],PRIMEM["Greenwich",0.0],UNIT["Degree",0.0174532925199433]]"#,
+    ),
+    (
+        4269,
+        r#"GEOGCS["GCS_North_American_1983",DATUM["D_North_American_1983",SPHEROID["GRS_1980",6378137.0,298.257222101]],PRIMEM["Greenwich",0.0],UNIT["Degree",0.0174532925199433]]"#,
+    ),
+    (
+        4258,
+        r#"GEOGCS["GCS_ETRS_1989",DATUM["D_ETRS_1989",SPHEROID["GRS_1980",6378137.0,298.257222101]],PRIMEM["Greenwich",0.0],UNIT["Degree",0.0174532925199433]]"#,
+    ),
+];
+
+/// UTM zone families: SRID of zone 0, ESRI name prefix, zones, and the
+/// geographic SRID they project.
+const UTM: [(i32, &str, std::ops::RangeInclusive<i32>, i32, bool); 4] = [
+    (32600, "WGS_1984", 1..=60, 4326, false),
+    (32700, "WGS_1984", 1..=60, 4326, true),
+    (26900, "NAD_1983", 1..=23, 4269, false),
+    (25800, "ETRS_1989", 28..=38, 4258, false),
+];
+
+/// ESRI-flavoured WKT for a `.prj`: WGS 84, NAD83, ETRS89, Web Mercator,
+/// and their UTM zones. `None` for any other SRID.
+pub fn prj_for_srid(srid: i32) -> Option<String> {
+    let geogcs = |srid: i32| GEOGCS.iter().find(|(s, _)| *s == srid).map(|(_, wkt)| *wkt);
+    if let Some(wkt) = geogcs(srid) {
+        return Some(wkt.to_string());
+    }
+    if srid == 3857 {
+        return Some(format!(
+            r#"PROJCS["WGS_1984_Web_Mercator_Auxiliary_Sphere",{},PROJECTION["Mercator_Auxiliary_Sphere"],PARAMETER["False_Easting",0.0],PARAMETER["False_Northing",0.0],PARAMETER["Central_Meridian",0.0],PARAMETER["Standard_Parallel_1",0.0],PARAMETER["Auxiliary_Sphere_Type",0.0],UNIT["Meter",1.0]]"#,
+            geogcs(4326)?
+        ));
+    }
+    let (base, prefix, _, geographic, south) = UTM
+        .iter()
+        .find(|(base, _, zones, _, _)| zones.contains(&(srid - base)))?;
+    let zone = srid - base;
+    Some(format!(
+        r#"PROJCS["{prefix}_UTM_Zone_{zone}{}",{},PROJECTION["Transverse_Mercator"],PARAMETER["False_Easting",500000.0],PARAMETER["False_Northing",{}],PARAMETER["Central_Meridian",{}.0],PARAMETER["Scale_Factor",0.9996],PARAMETER["Latitude_Of_Origin",0.0],UNIT["Meter",1.0]]"#,
+        if *south { "S" } else { "N" },
+        geogcs(*geographic)?,
+        if *south { "10000000.0" } else { "0.0" },
+        zone * 6 - 183,
+    ))
+}
+
+/// SRID of a `.prj` WKT: the top-level `AUTHORITY["EPSG", ...]` or
+/// `ID["EPSG", ...]` when there is one, else the SRID of a recognised
+/// CRS name (the CRSs of [`prj_for_srid`], by ESRI or EPSG name).
+pub fn srid_from_prj(wkt: &str) -> Option<i32> {
+    top_level_epsg(wkt).or_else(|| srid_from_crs_name(wkt))
+}
+
+/// Code of an EPSG authority node directly under the root WKT node.
+fn top_level_epsg(wkt: &str) -> Option<i32> {
+    let mut depth = 0;
+    let mut found = None;
+    let bytes = wkt.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'[' | b'(' => depth += 1,
+            b']' | b')' => depth -= 1,
+            b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    i += 1;
+                }
+            }
+            _ if depth == 1 => {
+                let rest = &wkt[i..];
+                let keyword = ["AUTHORITY[", "ID["]
+                    .into_iter()
+                    .find(|k| rest.len() >= k.len() && rest[..k.len()].eq_ignore_ascii_case(k));
+                if let Some(keyword) = keyword {
+                    found = epsg_code(&rest[keyword.len()..]).or(found);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    found
+}
+
+/// Code of an `"EPSG", code]` authority body.
+fn epsg_code(body: &str) -> Option<i32> {
+    let body = &body[..body.find(']')?];
+    let (authority, code) = body.split_once(',')?;
+    if !authority
+        .trim()
+        .trim_matches('"')
+        .eq_ignore_ascii_case("EPSG")
+    {
+        return None;
+    }
+    code.trim().trim_matches('"').parse().ok()
+}
+
+fn srid_from_crs_name(wkt: &str) -> Option<i32> {
+    let wkt = wkt.trim_start();
+    let keyword_end = wkt.find('[')?;
+    let keyword = wkt[..keyword_end].trim().to_ascii_uppercase();
+    let name_start = wkt[keyword_end..].find('"')? + keyword_end + 1;
+    let name_len = wkt[name_start..].find('"')?;
+    let name: String = wkt[name_start..name_start + name_len]
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+        .to_ascii_uppercase()
+        .replace("WGS_84", "WGS_1984")
+        .replace("NAD83", "NAD_1983")
+        .replace("ETRS89", "ETRS_1989");
+    match keyword.as_str() {
+        "GEOGCS" | "GEOGCRS" | "GEODCRS" => match name.as_str() {
+            "GCS_WGS_1984" | "WGS_1984" => Some(4326),
+            "GCS_NORTH_AMERICAN_1983" | "NAD_1983" => Some(4269),
+            "GCS_ETRS_1989" | "ETRS_1989" => Some(4258),
+            _ => None,
+        },
+        "PROJCS" | "PROJCRS" => {
+            if name == "WGS_1984_WEB_MERCATOR_AUXILIARY_SPHERE"
+                || name == "WGS_1984_PSEUDO_MERCATOR"
+            {
+                return Some(3857);
+            }
+            let (prefix, zone) = name.split_once("_UTM_ZONE_")?;
+            let (digits, hemisphere) = zone.split_at(zone.len().checked_sub(1)?);
+            let zone: i32 = digits.parse().ok()?;
+            let south = hemisphere == "S";
+            if hemisphere != "N" && !south {
+                return None;
+            }
+            UTM.iter()
+                .find(|(_, p, zones, _, s)| *p == prefix && zones.contains(&zone) && *s == south)
+                .map(|(base, ..)| base + zone)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::functions::io::{as_text, geom_from_text};
+    use crate::core::limits::{with_limits, Limits};
+
+    fn write(fields: Vec<Field>, features: &[Feature]) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        let mut writer = ShapefileWriter::new(fields);
+        for feature in features {
+            writer.add_feature(feature).unwrap();
+        }
+        let (mut shp, mut shx, mut dbf) = (Vec::new(), Vec::new(), Vec::new());
+        assert_eq!(
+            writer.finish(&mut shp, &mut shx, &mut dbf).unwrap(),
+            features.len() as u64
+        );
+        (shp, shx, dbf)
+    }
+
+    fn wkt(blob: &Option<Vec<u8>>) -> String {
+        as_text(blob.as_ref().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn round_trips_polygons_and_attributes() {
+        let fields = vec![
+            Field::new("id", FieldType::Integer),
+            Field::new("area_hectares", FieldType::Real),
+            Field::new("area_hectares_2", FieldType::Text),
+        ];
+        let features = vec![
+            Feature {
+                // Counter-clockwise shell and clockwise hole, reoriented on write.
+                geometry: Some(
+                    geom_from_text(
+                        "POLYGON((0 0,10 0,10 10,0 10,0 0),(2 2,2 4,4 4,4 2,2 2))",
+                        Some(2154),
+                    )
+                    .unwrap(),
+                ),
+                properties: vec![
+                    Value::Integer(-7),
+                    Value::Real(0.1),
+                    Value::Text("é, ü".into()),
+                ],
+            },
+            Feature {
+                geometry: None,
+                properties: vec![Value::Null, Value::Real(-1234.5), Value::Null],
+            },
+            Feature {
+                geometry: Some(
+                    geom_from_text(
+                        "MULTIPOLYGON(((20 0,30 0,30 10,20 0)),((40 0,50 0,50 10,40 0)))",
+                        Some(2154),
+                    )
+                    .unwrap(),
+                ),
+                properties: vec![
+                    Value::Integer(3),
+                    Value::Integer(2),
+                    Value::Text("x".into()),
+                ],
+            },
+        ];
+        let (shp, shx, dbf) = write(fields, &features);
+        assert_eq!(shx.len(), 100 + 8 * 3);
+        assert_eq!(be_i32(&shp, 24) as usize * 2, shp.len());
+        assert_eq!(le_i32(&shp, 32), SHAPE_POLYGON);
+
+        let reader =
+            ShapefileReader::new(shp.as_slice(), dbf.as_slice(), Charset::Utf8, Some(2154))
+                .unwrap();
+        assert_eq!(
+            reader.fields(),
+            &[
+                Field::new("id", FieldType::Integer),
+                Field::new("area_hecta", FieldType::Real),
+                Field::new("area_hec_1", FieldType::Text),
+            ]
+        );
+        let read: Vec<Feature> = reader.map(|f| f.unwrap()).collect();
+        assert_eq!(read.len(), 3);
+        assert_eq!(
+            wkt(&read[0].geometry),
+            "MULTIPOLYGON(((0 0,0 10,10 10,10 0,0 0),(2 2,4 2,4 4,2 4,2 2)))"
+        );
+        assert_eq!(
+            read[0].properties,
+            vec![
+                Value::Integer(-7),
+                Value::Real(0.1),
+                Value::Text("é, ü".into())
+            ]
+        );
+        assert_eq!(read[1].geometry, None);
+        assert_eq!(
+            read[1].properties,
+            vec![Value::Null, Value::Real(-1234.5), Value::Null]
+        );
+        assert_eq!(
+            wkt(&read[2].geometry),
+            "MULTIPOLYGON(((20 0,30 10,30 0,20 0)),((40 0,50 10,50 0,40 0)))"
+        );
+        assert_eq!(read[2].properties[1], Value::Real(2.0));
+    }
+
+    #[test]
+    fn round_trips_points_lines_and_multipoints() {
+        let point = |wkt: &str| Feature {
+            geometry: Some(geom_from_text(wkt, None).unwrap()),
+            properties: vec![],
+        };
+        let (shp, _, dbf) = write(
+            vec![],
+            &[point("POINT(1 2)"), point("MULTIPOINT((3 4),(5 6))")],
+        );
+        assert_eq!(le_i32(&shp, 32), SHAPE_MULTIPOINT);
+        let read: Vec<Feature> =
+            ShapefileReader::new(shp.as_slice(), dbf.as_slice(), Charset::Utf8, None)
+                .unwrap()
+                .map(|f| f.unwrap())
+                .collect();
+        assert_eq!(wkt(&read[0].geometry), "MULTIPOINT(1 2)");
+        assert_eq!(wkt(&read[1].geometry), "MULTIPOINT(3 4,5 6)");
+
+        let (shp, _, dbf) = write(vec![], &[point("LINESTRING(0 0,1 1)")]);
+        let mut reader =
+            ShapefileReader::new(shp.as_slice(), dbf.as_slice(), Charset::Utf8, None).unwrap();
+        assert_eq!(reader.shape_type(), SHAPE_POLYLINE);
+        assert_eq!(
+            wkt(&reader.next().unwrap().unwrap().geometry),
+            "MULTILINESTRING((0 0,1 1))"
+        );
+
+        let mut writer = ShapefileWriter::new(vec![]);
+        writer.add_feature(&point("POINT(1 2)")).unwrap();
+        let err = writer
+            .add_feature(&point("LINESTRING(0 0,1 1)"))
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("cannot mix point and line"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn decodes_dbf_charsets_and_field_types() {
+        assert_eq!(Charset::from_name("utf-8").unwrap(), Charset::Utf8);
+        assert_eq!(Charset::from_name("ISO-8859-1").unwrap(), Charset::Latin1);
+        assert_eq!(Charset::from_name("cp1252").unwrap(), Charset::Windows1252);
+        assert!(Charset::from_name("EBCDIC").is_err());
+        assert_eq!(
+            Charset::Windows1252.decode(&[0x80, b'1', 0xE9]).unwrap(),
+            "€1é"
+        );
+        assert_eq!(Charset::Latin1.decode(&[0xE9]).unwrap(), "é");
+        assert!(Charset::Utf8.decode(&[0xE9]).is_err());
+
+        let field = |kind, offset, len| DbfField {
+            kind,
+            offset,
+            len,
+            decimals: 0,
+        };
+        let record = b" T19991231  ";
+        assert_eq!(
+            field(b'L', 1, 1).read(record, Charset::Utf8).unwrap(),
+            Value::Integer(1)
+        );
+        assert_eq!(
+            field(b'D', 2, 8).read(record, Charset::Utf8).unwrap(),
+            Value::Text("1999-12-31".into())
+        );
+        assert_eq!(
+            field(b'N', 10, 2).read(record, Charset::Utf8).unwrap(),
+            Value::Null
+        );
+    }
+
+    #[test]
+    fn formats_reals_within_the_field_width() {
+        assert_eq!(format_real(0.1), "0.1");
+        assert_eq!(format_real(-1234.5), "-1234.5");
+        assert_eq!(format_real(1e30), "1e30");
+        assert_eq!(format_real(1.5e-12), "0.0000000000015");
+        assert_eq!(format_real(1.2345678e-12), "1.2345678e-12");
+        for r in [0.1, 123456.789, 1e30, 1.5e-12, 1.2345678e-12, -2.0] {
+            assert!(format_real(r).len() <= DBF_REAL_LEN);
+            assert_eq!(format_real(r).parse::<f64>().unwrap(), r);
+        }
+    }
+
+    #[test]
+    fn maps_prj_to_srid() {
+        for srid in [4326, 4269, 4258, 3857, 32631, 32733, 26910, 25832] {
+            let prj = prj_for_srid(srid).unwrap();
+            assert_eq!(srid_from_prj(&prj), Some(srid), "{prj}");
+        }
+        assert_eq!(prj_for_srid(2154), None);
+        let ogc = r#"PROJCS["RGF93 / Lambert-93",GEOGCS["RGF93",AUTHORITY["EPSG","4171"]],UNIT["metre",1,AUTHORITY["EPSG","9001"]],AUTHORITY["EPSG","2154"]]"#;
+        assert_eq!(srid_from_prj(ogc), Some(2154));
+        assert_eq!(
+            srid_from_prj(r#"PROJCS["WGS 84 / UTM zone 33N",GEOGCS["WGS 84"]]"#),
+            Some(32633)
+        );
+        assert_eq!(srid_from_prj(r#"PROJCS["Custom",GEOGCS["x"]]"#), None);
+    }
+
+    #[test]
+    fn rejects_z_shapes_and_bad_headers() {
+        let (mut shp, _, dbf) = write(vec![], &[]);
+        shp[32..36].copy_from_slice(&11i32.to_le_bytes());
+        let err = ShapefileReader::new(shp.as_slice(), dbf.as_slice(), Charset::Utf8, None)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("Z or M"), "{err}");
+        assert!(ShapefileReader::new(&b"nope"[..], dbf.as_slice(), Charset::Utf8, None).is_err());
+
+        // A polyline record claiming more parts than fit in memory.
+        let mut record = vec![0; 44];
+        record[..4].copy_from_slice(&SHAPE_POLYLINE.to_le_bytes());
+        record[36..40].copy_from_slice(&i32::MAX.to_le_bytes());
+        let err = read_shape(&record).unwrap_err();
+        assert!(err.to_string().contains("truncated"), "{err}");
+    }
+
+    #[test]
+    fn enforces_max_vertices_on_records() {
+        let line = Feature {
+            geometry: Some(geom_from_text("LINESTRING(0 0,1 1,2 0)", None).unwrap()),
+            properties: vec![],
+        };
+        let (shp, _, dbf) = write(vec![], &[line]);
+        let limits = Limits {
+            max_vertices: Some(2),
+            ..Limits::default()
+        };
+        let err = with_limits(limits, || {
+            ShapefileReader::new(shp.as_slice(), dbf.as_slice(), Charset::Utf8, None)?
+                .next()
+                .unwrap()
+        })
+        .unwrap_err();
+        assert!(
+            matches!(
+                err,
+                SqliteGisError::LimitExceeded {
+                    limit: "max_vertices",
+                    ..
+                }
+            ),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,492 @@
+//! Whole-file readers and writers for GeoJSON and CSV-with-WKT, the two
+//! text formats the `sqlitegis_geojson` and `sqlitegis_csv` virtual tables
+//! expose.
+//!
+//! The readers return a [`Field`] list plus one [`Feature`] per record,
+//! with geometries parsed by the same code as `ST_GeomFromGeoJSON` and
+//! `ST_GeomFromText`; the writers take the same pair back.
+//!
+//! GeoJSON properties become fields in order of first appearance, taking
+//! each feature's new keys in name order. JSON integers map to `Integer`,
+//! other numbers to `Real`, booleans to `0` and `1`, strings to `Text`,
+//! and arrays and objects to their JSON text. A column mixing types is
+//! typed by [`FieldType::infer`].
+//!
+//! CSV files need a header row. Every column other than the WKT column is
+//! `Integer` when all of its non-empty cells parse as integers, `Real`
+//! when they all parse as numbers, and `Text` otherwise; empty cells are
+//! `NULL`, as is an empty WKT cell.
+//!
+//! [`write_geojson`] writes a `FeatureCollection` and [`write_csv`] a
+//! header row plus one record per feature, the geometry as WKT in the
+//! first column. Neither format carries an SRID, and BLOB values are
+//! written as hex text.
+//!
+//! ```
+//! use sqlitegis::core::features::{FieldType, Value};
+//! use sqlitegis::core::text_features::read_csv;
+//!
+//! let (fields, features) = read_csv("id,wkt\n1,POINT(1 2)\n2,\n", "wkt", None).unwrap();
+//! assert_eq!(fields[0].field_type, FieldType::Integer);
+//! assert_eq!(features[0].properties, vec![Value::Integer(1)]);
+//! assert!(features[0].geometry.is_some());
+//! assert!(features[1].geometry.is_none());
+//! ```
+
+use serde_json::Value as Json;
+
+use crate::core::error::{Result, SqliteGisError};
+use crate::core::features::{Feature, Field, FieldType, Value};
+use crate::core::functions::io::{as_geojson, as_text, geom_from_geojson, geom_from_text};
+
+/// Read a GeoJSON `FeatureCollection`, a single `Feature`, or a bare
+/// geometry (one feature without properties). Geometries get `srid`, or
+/// 4326 when it is `None`, as GeoJSON coordinates are longitude/latitude.
+pub fn read_geojson(text: &str, srid: Option<i32>) -> Result<(Vec<Field>, Vec<Feature>)> {
+    let json: Json = serde_json::from_str(text)
+        .map_err(|e| SqliteGisError::InvalidInput(format!("invalid GeoJSON: {e}")))?;
+    let objects: Vec<&Json> = match json["type"].as_str() {
+        Some("FeatureCollection") => json["features"]
+            .as_array()
+            .ok_or_else(|| {
+                SqliteGisError::InvalidInput(
+                    "GeoJSON FeatureCollection has no features array".to_string(),
+                )
+            })?
+            .iter()
+            .collect(),
+        Some(_) => vec![&json],
+        None => {
+            return Err(SqliteGisError::InvalidInput(
+                "GeoJSON object has no type".to_string(),
+            ))
+        }
+    };
+
+    let mut names: Vec<String> = Vec::new();
+    let mut geometries = Vec::with_capacity(objects.len());
+    let mut records = Vec::with_capacity(objects.len());
+    for object in objects {
+        let (geometry, properties) = match object["type"].as_str() {
+            Some("Feature") => (&object["geometry"], object["properties"].as_object()),
+            Some(_) => (object, None),
+            None => {
+                return Err(SqliteGisError::InvalidInput(
+                    "GeoJSON feature has no type".to_string(),
+                ))
+            }
+        };
+        geometries.push(match geometry {
+            Json::Null => None,
+            geometry => Some(geom_from_geojson(&geometry.to_string(), srid)?),
+        });
+        let mut record = vec![Value::Null; names.len()];
+        for (name, value) in properties.into_iter().flatten() {
+            let i = match names.iter().position(|n| n == name) {
+                Some(i) => i,
+                None => {
+                    names.push(name.clone());
+                    record.push(Value::Null);
+                    names.len() - 1
+                }
+            };
+            record[i] = json_value(value);
+        }
+        records.push(record);
+    }
+
+    let fields: Vec<Field> = names
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let values = records.iter().filter_map(|record| record.get(i));
+            Field::new(name, FieldType::infer(values))
+        })
+        .collect();
+    let features = geometries
+        .into_iter()
+        .zip(records)
+        .map(|(geometry, record)| Feature {
+            geometry,
+            properties: fields
+                .iter()
+                .enumerate()
+                .map(|(i, field)| {
+                    record
+                        .get(i)
+                        .map_or(Value::Null, |value| value.coerce(field.field_type))
+                })
+                .collect(),
+        })
+        .collect();
+    Ok((fields, features))
+}
+
+fn json_value(value: &Json) -> Value {
+    match value {
+        Json::Null => Value::Null,
+        Json::Bool(b) => Value::Integer(i64::from(*b)),
+        Json::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => n.as_f64().map_or(Value::Null, Value::Real),
+        },
+        Json::String(s) => Value::Text(s.clone()),
+        Json::Array(_) | Json::Object(_) => Value::Text(value.to_string()),
+    }
+}
+
+/// Read a comma-separated file with a header row, taking geometries from
+/// the WKT column named `wkt_column` (matched case-insensitively) with
+/// SRID `srid`. Quoted cells may contain commas, doubled quotes and line
+/// breaks, as in RFC 4180.
+pub fn read_csv(
+    text: &str,
+    wkt_column: &str,
+    srid: Option<i32>,
+) -> Result<(Vec<Field>, Vec<Feature>)> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut records = parse_csv(text)?.into_iter();
+    let header = records
+        .next()
+        .ok_or_else(|| SqliteGisError::InvalidInput("CSV has no header row".to_string()))?;
+    let wkt_index = header
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(wkt_column))
+        .ok_or_else(|| SqliteGisError::InvalidInput(format!("CSV has no [{wkt_column}] column")))?;
+    let rows: Vec<Vec<String>> = records
+        .enumerate()
+        .map(|(i, row)| {
+            if row.len() == header.len() {
+                Ok(row)
+            } else {
+                Err(SqliteGisError::InvalidInput(format!(
+                    "CSV record {} has {} fields but the header has {}",
+                    i + 1,
+                    row.len(),
+                    header.len()
+                )))
+            }
+        })
+        .collect::<Result<_>>()?;
+
+    let columns: Vec<usize> = (0..header.len()).filter(|&i| i != wkt_index).collect();
+    let fields: Vec<Field> = columns
+        .iter()
+        .map(|&i| {
+            let cells = rows.iter().map(|row| row[i].as_str());
+            Field::new(header[i].clone(), csv_field_type(cells))
+        })
+        .collect();
+    let features = rows
+        .iter()
+        .map(|row| {
+            let wkt = row[wkt_index].trim();
+            Ok(Feature {
+                geometry: if wkt.is_empty() {
+                    None
+                } else {
+                    Some(geom_from_text(wkt, srid)?)
+                },
+                properties: columns
+                    .iter()
+                    .zip(&fields)
+                    .map(|(&i, field)| csv_value(&row[i], field.field_type))
+                    .collect(),
+            })
+        })
+        .collect::<Result<_>>()?;
+    Ok((fields, features))
+}
+
+fn csv_number(cell: &str) -> Option<f64> {
+    // `f64::from_str` also takes "inf" and "NaN", which are text here.
+    if !cell.bytes().any(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    cell.parse::<f64>().ok()
+}
+
+fn csv_field_type<'a>(cells: impl Iterator<Item = &'a str> + Clone) -> FieldType {
+    let mut non_empty = cells.map(str::trim).filter(|cell| !cell.is_empty());
+    if non_empty.clone().all(|cell| cell.parse::<i64>().is_ok()) {
+        FieldType::Integer
+    } else if non_empty.all(|cell| csv_number(cell).is_some()) {
+        FieldType::Real
+    } else {
+        FieldType::Text
+    }
+}
+
+fn csv_value(cell: &str, field_type: FieldType) -> Value {
+    let trimmed = cell.trim();
+    match field_type {
+        _ if trimmed.is_empty() => Value::Null,
+        FieldType::Integer => trimmed.parse().map_or(Value::Null, Value::Integer),
+        FieldType::Real => csv_number(trimmed).map_or(Value::Null, Value::Real),
+        FieldType::Text | FieldType::Blob => Value::Text(cell.to_string()),
+    }
+}
+
+/// Write `features` as a GeoJSON `FeatureCollection` whose properties are
+/// named after `fields`. Real values that are not finite become `null`.
+pub fn write_geojson(fields: &[Field], features: &[Feature]) -> Result<String> {
+    let features = features
+        .iter()
+        .map(|feature| {
+            let geometry = match &feature.geometry {
+                Some(blob) => serde_json::from_str(&as_geojson(blob)?)
+                    .map_err(|e| SqliteGisError::InvalidInput(e.to_string()))?,
+                None => Json::Null,
+            };
+            let properties: serde_json::Map<String, Json> = fields
+                .iter()
+                .zip(&feature.properties)
+                .map(|(field, value)| (field.name.clone(), to_json(value)))
+                .collect();
+            Ok(serde_json::json!({
+                "type": "Feature",
+                "geometry": geometry,
+                "properties": properties,
+            }))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(serde_json::json!({"type": "FeatureCollection", "features": features}).to_string())
+}
+
+fn to_json(value: &Value) -> Json {
+    match value {
+        Value::Null => Json::Null,
+        Value::Integer(i) => Json::from(*i),
+        Value::Real(r) => serde_json::Number::from_f64(*r).map_or(Json::Null, Json::Number),
+        Value::Text(t) => Json::String(t.clone()),
+        Value::Blob(_) => match value.coerce(FieldType::Text) {
+            Value::Text(t) => Json::String(t),
+            _ => Json::Null,
+        },
+    }
+}
+
+/// Write `features` as CSV with a header row: the geometry as WKT in a
+/// column named `wkt_column`, then one column per field. NULLs are empty
+/// cells, and cells holding a comma, quote or line break are quoted.
+pub fn write_csv(fields: &[Field], features: &[Feature], wkt_column: &str) -> Result<String> {
+    let mut out = String::new();
+    let header = std::iter::once(wkt_column).chain(fields.iter().map(|f| f.name.as_str()));
+    push_csv_record(&mut out, header);
+    for feature in features {
+        let wkt = match &feature.geometry {
+            Some(blob) => as_text(blob)?,
+            None => String::new(),
+        };
+        let cells: Vec<String> = feature
+            .properties
+            .iter()
+            .map(|value| match value.coerce(FieldType::Text) {
+                Value::Text(t) => t,
+                _ => String::new(),
+            })
+            .collect();
+        push_csv_record(
+            &mut out,
+            std::iter::once(wkt.as_str()).chain(cells.iter().map(String::as_str)),
+        );
+    }
+    Ok(out)
+}
+
+fn push_csv_record<'a>(out: &mut String, cells: impl Iterator<Item = &'a str>) {
+    for (i, cell) in cells.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        if cell.contains([',', '"', '\n', '\r']) {
+            out.push('"');
+            out.push_str(&cell.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(cell);
+        }
+    }
+    out.push_str("\r\n");
+}
+
+/// Split CSV text into records of unquoted cells, skipping blank lines.
+fn parse_csv(text: &str) -> Result<Vec<Vec<String>>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut cell = String::new();
+    let mut chars = text.chars().peekable();
+    let mut quoted = false;
+    let mut cell_started = false;
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    cell.push('"');
+                }
+                '"' => quoted = false,
+                c => cell.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if cell.is_empty() => quoted = true,
+            ',' => record.push(std::mem::take(&mut cell)),
+            '\r' if chars.peek() == Some(&'\n') => continue,
+            '\n' | '\r' => {
+                if cell_started || !record.is_empty() {
+                    record.push(std::mem::take(&mut cell));
+                    records.push(std::mem::take(&mut record));
+                }
+                cell_started = false;
+                continue;
+            }
+            c => cell.push(c),
+        }
+        cell_started = true;
+    }
+    if quoted {
+        return Err(SqliteGisError::InvalidInput(
+            "CSV ends inside a quoted field".to_string(),
+        ));
+    }
+    if cell_started || !record.is_empty() {
+        record.push(cell);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::ewkb::parse_ewkb_header;
+
+    #[test]
+    fn reads_a_feature_collection() {
+        let text = r#"{"type":"FeatureCollection","features":[
+            {"type":"Feature","geometry":{"type":"Point","coordinates":[1,2]},
+             "properties":{"name":"a","n":1,"tags":["x"]}},
+            {"type":"Feature","geometry":null,"properties":{"n":2.5,"ok":true}}
+        ]}"#;
+        let (fields, features) = read_geojson(text, None).unwrap();
+        assert_eq!(
+            fields,
+            vec![
+                Field::new("n", FieldType::Real),
+                Field::new("name", FieldType::Text),
+                Field::new("tags", FieldType::Text),
+                Field::new("ok", FieldType::Integer),
+            ]
+        );
+        let geometry = features[0].geometry.as_ref().unwrap();
+        assert_eq!(parse_ewkb_header(geometry).unwrap().srid, Some(4326));
+        assert_eq!(
+            features[0].properties,
+            vec![
+                Value::Real(1.0),
+                Value::Text("a".into()),
+                Value::Text(r#"["x"]"#.into()),
+                Value::Null,
+            ]
+        );
+        assert_eq!(features[1].geometry, None);
+        assert_eq!(
+            features[1].properties,
+            vec![
+                Value::Real(2.5),
+                Value::Null,
+                Value::Null,
+                Value::Integer(1)
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_a_bare_geometry_with_an_srid() {
+        let (fields, features) =
+            read_geojson(r#"{"type":"Point","coordinates":[1,2]}"#, Some(3857)).unwrap();
+        assert!(fields.is_empty());
+        let geometry = features[0].geometry.as_ref().unwrap();
+        assert_eq!(parse_ewkb_header(geometry).unwrap().srid, Some(3857));
+        assert!(read_geojson("[1, 2]", None).is_err());
+        assert!(read_geojson("{", None).is_err());
+    }
+
+    #[test]
+    fn reads_quoted_csv_cells() {
+        let text = "\u{feff}id,Name,score,WKT\r\n\
+                    1,\"a, \"\"b\"\"\",0.5,\"POINT(1 2)\"\r\n\
+                    \r\n\
+                    02,\"multi\nline\",,\"LINESTRING(0 0, 1 1)\"\r\n";
+        let (fields, features) = read_csv(text, "wkt", Some(4326)).unwrap();
+        assert_eq!(
+            fields,
+            vec![
+                Field::new("id", FieldType::Integer),
+                Field::new("Name", FieldType::Text),
+                Field::new("score", FieldType::Real),
+            ]
+        );
+        assert_eq!(features.len(), 2);
+        assert_eq!(
+            features[0].properties,
+            vec![
+                Value::Integer(1),
+                Value::Text("a, \"b\"".into()),
+                Value::Real(0.5)
+            ]
+        );
+        assert_eq!(
+            features[1].properties,
+            vec![
+                Value::Integer(2),
+                Value::Text("multi\nline".into()),
+                Value::Null
+            ]
+        );
+        let geometry = features[1].geometry.as_ref().unwrap();
+        assert_eq!(parse_ewkb_header(geometry).unwrap().srid, Some(4326));
+    }
+
+    #[test]
+    fn rejects_malformed_csv() {
+        assert!(read_csv("id,wkt\n1,POINT(1 2)\n", "geom", None).is_err());
+        assert!(read_csv("id,wkt\n1\n", "wkt", None).is_err());
+        assert!(read_csv("id,wkt\n1,\"POINT(1 2)\n", "wkt", None).is_err());
+        assert!(read_csv("id,wkt\n1,POINT(1\n", "wkt", None).is_err());
+        assert!(read_csv("", "wkt", None).is_err());
+        let (_, features) = read_csv("code,wkt\nNaN,\n", "wkt", None).unwrap();
+        assert_eq!(features[0].properties, vec![Value::Text("NaN".into())]);
+    }
+
+    #[test]
+    fn writes_what_it_reads() {
+        let (fields, features) = read_csv(
+            "id,name,score,wkt\n1,\"a, \"\"b\"\"\",0.5,POINT(1 2)\n2,,,\n",
+            "wkt",
+            Some(4326),
+        )
+        .unwrap();
+        let csv = write_csv(&fields, &features, "wkt").unwrap();
+        assert_eq!(
+            csv,
+            "wkt,id,name,score\r\nPOINT(1 2),1,\"a, \"\"b\"\"\",0.5\r\n,2,,\r\n"
+        );
+        assert_eq!(
+            read_csv(&csv, "wkt", Some(4326)).unwrap(),
+            (fields.clone(), features.clone())
+        );
+
+        let geojson = write_geojson(&fields, &features).unwrap();
+        let (read_fields, read_features) = read_geojson(&geojson, Some(4326)).unwrap();
+        assert_eq!(read_fields.len(), 3);
+        assert_eq!(read_features[0].geometry, features[0].geometry);
+        assert_eq!(read_features[1].geometry, None);
+        assert!(
+            geojson.contains(r#""properties":{"id":2,"name":null,"score":null}"#),
+            "{geojson}"
+        );
+    }
+}
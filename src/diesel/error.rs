@@ -0,0 +1,144 @@
+//! SQLiteGIS function errors recovered from Diesel's error messages.
+//!
+//! The SQLite layer reports every core error with the extended result code
+//! of its [`ErrorKind`] (see [`ErrorKind::sqlite_code`]), but Diesel's
+//! SQLite backend does not keep the code: it maps a few constraint codes to
+//! a [`DatabaseErrorKind`] and reports everything else, spatial errors
+//! included, as `DatabaseErrorKind::Unknown` with only the message. So this
+//! module parses the message instead. The SQLite layer writes it as
+//! `function(arg N) [kind]: message` (the `(arg N)` part only when one
+//! argument is at fault), with the stable [`ErrorKind::as_str`] tag, and
+//! [`SpatialError::from_diesel`] reads those parts back. Hosts that reach
+//! the connection through the C API or rusqlite can match the code itself
+//! with [`ErrorKind::from_sqlite_code`].
+//!
+//! ```
+//! use diesel::{Connection, RunQueryDsl, sqlite::SqliteConnection};
+//! use sqlitegis::core::error::ErrorKind;
+//! use sqlitegis::diesel::SpatialError;
+//!
+//! sqlitegis::sqlite::register_on_every_new_connection();
+//! let mut conn = SqliteConnection::establish(":memory:").unwrap();
+//!
+//! let err = diesel::sql_query("SELECT ST_X(ST_GeomFromText('LINESTRING(0 0,1 1)'))")
+//!     .execute(&mut conn)
+//!     .unwrap_err();
+//! let spatial = SpatialError::from_diesel(&err).unwrap();
+//! assert_eq!(spatial.function, "ST_X");
+//! assert_eq!(spatial.arg, Some(1));
+//! assert_eq!(spatial.kind, ErrorKind::WrongType);
+//! ```
+//!
+//! [`DatabaseErrorKind`]: ::diesel::result::DatabaseErrorKind
+
+use std::fmt;
+
+use crate::core::error::ErrorKind;
+
+/// A SQLiteGIS function error parsed from a database error message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpatialError {
+    /// SQL name of the function that failed (e.g. `ST_Union`).
+    pub function: String,
+    /// 1-based position of the offending argument, when the error can be
+    /// pinned on one.
+    pub arg: Option<usize>,
+    /// Classification of the underlying [`SqliteGisError`](crate::SqliteGisError).
+    pub kind: ErrorKind,
+    /// The error's own message, without the function/argument/kind prefix.
+    pub message: String,
+}
+
+impl SpatialError {
+    /// Parse a `function(arg N) [kind]: message` error message. Returns
+    /// `None` for messages in any other shape, such as SQL syntax errors or
+    /// PostGIS errors.
+    ///
+    /// ```
+    /// use sqlitegis::core::error::ErrorKind;
+    /// use sqlitegis::diesel::SpatialError;
+    ///
+    /// let err = SpatialError::parse(
+    ///     "ST_Union [srid_mismatch]: operation on mixed SRID geometries (4326 != 3857)",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(err.function, "ST_Union");
+    /// assert_eq!(err.arg, None);
+    /// assert_eq!(err.kind, ErrorKind::SridMismatch);
+    ///
+    /// assert!(SpatialError::parse("no such table: t").is_none());
+    /// ```
+    pub fn parse(message: &str) -> Option<Self> {
+        let (head, rest) = message.split_once("]: ")?;
+        let (location, tag) = head.rsplit_once(" [")?;
+        let kind = ErrorKind::from_tag(tag)?;
+        let (function, arg) = match location.strip_suffix(')') {
+            Some(call) => {
+                let (function, arg) = call.split_once("(arg ")?;
+                (function, Some(arg.parse().ok()?))
+            }
+            None => (location, None),
+        };
+        if function.is_empty() || function.contains(char::is_whitespace) {
+            return None;
+        }
+        Some(Self {
+            function: function.to_string(),
+            arg,
+            kind,
+            message: rest.to_string(),
+        })
+    }
+
+    /// [`SpatialError::parse`] the message of a Diesel `DatabaseError`.
+    /// Returns `None` for every other Diesel error.
+    pub fn from_diesel(err: &::diesel::result::Error) -> Option<Self> {
+        match err {
+            ::diesel::result::Error::DatabaseError(_, info) => Self::parse(info.message()),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SpatialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.function)?;
+        if let Some(arg) = self.arg {
+            write!(f, "(arg {arg})")?;
+        }
+        write!(f, " [{}]: {}", self.kind, self.message)
+    }
+}
+
+impl std::error::Error for SpatialError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_round_trips_display() {
+        for message in [
+            "ST_PointN(arg 2) [out_of_bounds]: index out of bounds: 9 (len 2)",
+            "ST_Buffer [limit_exceeded]: max_output_vertices exceeded: 33 > 8",
+            "ST_AsText(arg 1) [invalid_ewkb]: invalid EWKB: truncated header",
+        ] {
+            let parsed = SpatialError::parse(message).unwrap();
+            assert_eq!(parsed.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn parse_rejects_other_messages() {
+        for message in [
+            "",
+            "near \"SELEC\": syntax error",
+            "ST_X: geometry is not a Point; got LineString",
+            "ST_X [no_such_kind]: boom",
+            "ST_X(arg x) [wrong_type]: boom",
+            "CHECK constraint failed: a [b]: c",
+        ] {
+            assert_eq!(SpatialError::parse(message), None, "{message}");
+        }
+    }
+}
@@ -55,7 +55,7 @@
 //! ```
 
 use diesel::expression::{AsExpression, Expression};
-use diesel::sql_types::{Double, Integer, Nullable};
+use diesel::sql_types::{Bool, Double, Integer, Nullable};
 
 use crate::diesel::functions;
 use crate::diesel::types::Geometry;
@@ -99,6 +99,24 @@ pub trait GeometryExpressionMethods: Expression<SqlType = Nullable<Geometry>> +
         functions::st_asewkb(self)
     }
 
+    /// Serialize this geometry to little-endian hex EWKB text.
+    ///
+    /// See [`crate::diesel::functions::st_ashexewkb()`] for an executable example.
+    fn st_ashexewkb(self) -> functions::st_ashexewkb<Self> {
+        functions::st_ashexewkb(self)
+    }
+
+    /// Serialize this geometry to hex EWKB text in `'NDR'` or `'XDR'` byte
+    /// order.
+    ///
+    /// See [`crate::diesel::functions::st_ashexewkb_endian()`] for an executable example.
+    fn st_ashexewkb_endian<E>(self, endian: E) -> functions::st_ashexewkb_endian<Self, E>
+    where
+        E: AsExpression<diesel::sql_types::Text>,
+    {
+        functions::st_ashexewkb_endian(self, endian)
+    }
+
     /// Serialize this geometry to GeoJSON text.
     ///
     /// See [`crate::diesel::functions::st_asgeojson()`] for an executable example.
@@ -106,6 +124,298 @@ pub trait GeometryExpressionMethods: Expression<SqlType = Nullable<Geometry>> +
         functions::st_asgeojson(self)
     }
 
+    /// Serialize this geometry to GeoJSON text with at most
+    /// `maxdecimaldigits` decimal digits.
+    ///
+    /// See [`crate::diesel::functions::st_asgeojson_precision()`] for an executable example.
+    fn st_asgeojson_precision<P>(
+        self,
+        maxdecimaldigits: P,
+    ) -> functions::st_asgeojson_precision<Self, P>
+    where
+        P: AsExpression<Integer>,
+    {
+        functions::st_asgeojson_precision(self, maxdecimaldigits)
+    }
+
+    /// Serialize this geometry to GeoPackage binary geometry (GPB).
+    ///
+    /// See [`crate::diesel::functions::st_asgpb()`] for an executable example.
+    fn st_asgpb(self) -> functions::st_asgpb<Self> {
+        functions::st_asgpb(self)
+    }
+
+    /// Serialize this geometry to SpatiaLite's internal BLOB format.
+    ///
+    /// See [`crate::diesel::functions::st_asspatialite()`] for an executable example.
+    fn st_asspatialite(self) -> functions::st_asspatialite<Self> {
+        functions::st_asspatialite(self)
+    }
+
+    /// Serialize this geometry to TWKB (Tiny WKB) at precision 0.
+    ///
+    /// See [`crate::diesel::functions::st_astwkb()`] for an executable example.
+    fn st_astwkb(self) -> functions::st_astwkb<Self> {
+        functions::st_astwkb(self)
+    }
+
+    /// Serialize this geometry to TWKB, rounding coordinates to
+    /// `precision_xy` decimal digits.
+    ///
+    /// See [`crate::diesel::functions::st_astwkb_precision()`] for an executable example.
+    fn st_astwkb_precision<P>(self, precision_xy: P) -> functions::st_astwkb_precision<Self, P>
+    where
+        P: AsExpression<Integer>,
+    {
+        functions::st_astwkb_precision(self, precision_xy)
+    }
+
+    /// Serialize this geometry to TWKB with explicit XY and Z precisions.
+    ///
+    /// See [`crate::diesel::functions::st_astwkb_precision_z()`] for an executable example.
+    fn st_astwkb_precision_z<P, Z>(
+        self,
+        precision_xy: P,
+        precision_z: Z,
+    ) -> functions::st_astwkb_precision_z<Self, P, Z>
+    where
+        P: AsExpression<Integer>,
+        Z: AsExpression<Integer>,
+    {
+        functions::st_astwkb_precision_z(self, precision_xy, precision_z)
+    }
+
+    /// Serialize this geometry to TWKB with explicit XY, Z and M precisions.
+    ///
+    /// See [`crate::diesel::functions::st_astwkb_precision_m()`] for an executable example.
+    fn st_astwkb_precision_m<P, Z, M>(
+        self,
+        precision_xy: P,
+        precision_z: Z,
+        precision_m: M,
+    ) -> functions::st_astwkb_precision_m<Self, P, Z, M>
+    where
+        P: AsExpression<Integer>,
+        Z: AsExpression<Integer>,
+        M: AsExpression<Integer>,
+    {
+        functions::st_astwkb_precision_m(self, precision_xy, precision_z, precision_m)
+    }
+
+    /// Serialize this geometry to TWKB, optionally including the size field.
+    ///
+    /// See [`crate::diesel::functions::st_astwkb_sizes()`] for an executable example.
+    fn st_astwkb_sizes<P, Z, M, S>(
+        self,
+        precision_xy: P,
+        precision_z: Z,
+        precision_m: M,
+        with_sizes: S,
+    ) -> functions::st_astwkb_sizes<Self, P, Z, M, S>
+    where
+        P: AsExpression<Integer>,
+        Z: AsExpression<Integer>,
+        M: AsExpression<Integer>,
+        S: AsExpression<Bool>,
+    {
+        functions::st_astwkb_sizes(self, precision_xy, precision_z, precision_m, with_sizes)
+    }
+
+    /// Serialize this geometry to TWKB, optionally including the size and
+    /// bounding-box fields.
+    ///
+    /// See [`crate::diesel::functions::st_astwkb_boxes()`] for an executable example.
+    fn st_astwkb_boxes<P, Z, M, S, B>(
+        self,
+        precision_xy: P,
+        precision_z: Z,
+        precision_m: M,
+        with_sizes: S,
+        with_boxes: B,
+    ) -> functions::st_astwkb_boxes<Self, P, Z, M, S, B>
+    where
+        P: AsExpression<Integer>,
+        Z: AsExpression<Integer>,
+        M: AsExpression<Integer>,
+        S: AsExpression<Bool>,
+        B: AsExpression<Bool>,
+    {
+        functions::st_astwkb_boxes(
+            self,
+            precision_xy,
+            precision_z,
+            precision_m,
+            with_sizes,
+            with_boxes,
+        )
+    }
+
+    /// Serialize this WGS 84 geometry to a KML geometry element.
+    ///
+    /// See [`crate::diesel::functions::st_askml()`] for an executable example.
+    fn st_askml(self) -> functions::st_askml<Self> {
+        functions::st_askml(self)
+    }
+
+    /// Serialize this WGS 84 geometry to KML with at most `precision`
+    /// decimal digits.
+    ///
+    /// See [`crate::diesel::functions::st_askml_precision()`] for an executable example.
+    fn st_askml_precision<P>(self, precision: P) -> functions::st_askml_precision<Self, P>
+    where
+        P: AsExpression<Integer>,
+    {
+        functions::st_askml_precision(self, precision)
+    }
+
+    /// Serialize this geometry to a GML 2 geometry element.
+    ///
+    /// See [`crate::diesel::functions::st_asgml()`] for an executable example.
+    fn st_asgml(self) -> functions::st_asgml<Self> {
+        functions::st_asgml(self)
+    }
+
+    /// Serialize this geometry to SVG path data with absolute moves and the
+    /// y axis flipped.
+    ///
+    /// See [`crate::diesel::functions::st_assvg()`] for an executable example.
+    fn st_assvg(self) -> functions::st_assvg<Self> {
+        functions::st_assvg(self)
+    }
+
+    /// Serialize this geometry to SVG path data, with relative moves when
+    /// `rel` is non-zero.
+    ///
+    /// See [`crate::diesel::functions::st_assvg_rel()`] for an executable example.
+    fn st_assvg_rel<R>(self, rel: R) -> functions::st_assvg_rel<Self, R>
+    where
+        R: AsExpression<Integer>,
+    {
+        functions::st_assvg_rel(self, rel)
+    }
+
+    /// Serialize this geometry to SVG path data with at most
+    /// `maxdecimaldigits` decimal digits.
+    ///
+    /// See [`crate::diesel::functions::st_assvg_precision()`] for an executable example.
+    fn st_assvg_precision<R, P>(
+        self,
+        rel: R,
+        maxdecimaldigits: P,
+    ) -> functions::st_assvg_precision<Self, R, P>
+    where
+        R: AsExpression<Integer>,
+        P: AsExpression<Integer>,
+    {
+        functions::st_assvg_precision(self, rel, maxdecimaldigits)
+    }
+
+    /// Encode this LineString or MultiPoint as a Google encoded polyline
+    /// with 5 decimal digits.
+    ///
+    /// See [`crate::diesel::functions::st_asencodedpolyline()`] for an executable example.
+    fn st_asencodedpolyline(self) -> functions::st_asencodedpolyline<Self> {
+        functions::st_asencodedpolyline(self)
+    }
+
+    /// Encode this LineString or MultiPoint as a Google encoded polyline
+    /// with `precision` decimal digits.
+    ///
+    /// See [`crate::diesel::functions::st_asencodedpolyline_precision()`] for an executable example.
+    fn st_asencodedpolyline_precision<P>(
+        self,
+        precision: P,
+    ) -> functions::st_asencodedpolyline_precision<Self, P>
+    where
+        P: AsExpression<Integer>,
+    {
+        functions::st_asencodedpolyline_precision(self, precision)
+    }
+
+    /// Return the GeoHash of this longitude/latitude geometry, sized to its
+    /// bounding box.
+    ///
+    /// See [`crate::diesel::functions::st_geohash()`] for an executable example.
+    fn st_geohash(self) -> functions::st_geohash<Self> {
+        functions::st_geohash(self)
+    }
+
+    /// Return the GeoHash of this longitude/latitude geometry with at most
+    /// `maxchars` characters.
+    ///
+    /// See [`crate::diesel::functions::st_geohash_precision()`] for an executable example.
+    fn st_geohash_precision<P>(self, maxchars: P) -> functions::st_geohash_precision<Self, P>
+    where
+        P: AsExpression<Integer>,
+    {
+        functions::st_geohash_precision(self, maxchars)
+    }
+
+    // Vector tiles
+
+    /// Transform this geometry into the integer space of the tile covering
+    /// `bounds`.
+    ///
+    /// See [`crate::diesel::functions::st_asmvtgeom()`] for an executable example.
+    fn st_asmvtgeom<B>(self, bounds: B) -> functions::st_asmvtgeom<Self, B>
+    where
+        B: AsExpression<Nullable<Geometry>>,
+    {
+        functions::st_asmvtgeom(self, bounds)
+    }
+
+    /// Transform this geometry into tile space with an explicit extent.
+    ///
+    /// See [`crate::diesel::functions::st_asmvtgeom_extent()`] for an executable example.
+    fn st_asmvtgeom_extent<B, E>(
+        self,
+        bounds: B,
+        extent: E,
+    ) -> functions::st_asmvtgeom_extent<Self, B, E>
+    where
+        B: AsExpression<Nullable<Geometry>>,
+        E: AsExpression<Integer>,
+    {
+        functions::st_asmvtgeom_extent(self, bounds, extent)
+    }
+
+    /// Transform this geometry into tile space with an explicit extent and
+    /// clip buffer.
+    ///
+    /// See [`crate::diesel::functions::st_asmvtgeom_buffer()`] for an executable example.
+    fn st_asmvtgeom_buffer<B, E, U>(
+        self,
+        bounds: B,
+        extent: E,
+        buffer: U,
+    ) -> functions::st_asmvtgeom_buffer<Self, B, E, U>
+    where
+        B: AsExpression<Nullable<Geometry>>,
+        E: AsExpression<Integer>,
+        U: AsExpression<Integer>,
+    {
+        functions::st_asmvtgeom_buffer(self, bounds, extent, buffer)
+    }
+
+    /// Transform this geometry into tile space, choosing whether to clip.
+    ///
+    /// See [`crate::diesel::functions::st_asmvtgeom_clip()`] for an executable example.
+    fn st_asmvtgeom_clip<B, E, U, C>(
+        self,
+        bounds: B,
+        extent: E,
+        buffer: U,
+        clip: C,
+    ) -> functions::st_asmvtgeom_clip<Self, B, E, U, C>
+    where
+        B: AsExpression<Nullable<Geometry>>,
+        E: AsExpression<Integer>,
+        U: AsExpression<Integer>,
+        C: AsExpression<Bool>,
+    {
+        functions::st_asmvtgeom_clip(self, bounds, extent, buffer, clip)
+    }
+
     // Constructors / transforms
 
     /// Construct a LineString from this geometry and another Point geometry.
@@ -345,6 +655,13 @@ pub trait GeometryExpressionMethods: Expression<SqlType = Nullable<Geometry>> +
         functions::st_xmin(self)
     }
 
+    /// Alias for `st_xmin`: return the X coordinate of the bounding-box minimum corner.
+    ///
+    /// See [`crate::diesel::functions::st_minx()`] for an executable example.
+    fn st_minx(self) -> functions::st_minx<Self> {
+        functions::st_minx(self)
+    }
+
     /// Return the X coordinate of the bounding-box maximum corner.
     ///
     /// See [`crate::diesel::functions::st_xmax()`] for an executable example.
@@ -352,6 +669,13 @@ pub trait GeometryExpressionMethods: Expression<SqlType = Nullable<Geometry>> +
         functions::st_xmax(self)
     }
 
+    /// Alias for `st_xmax`: return the X coordinate of the bounding-box maximum corner.
+    ///
+    /// See [`crate::diesel::functions::st_maxx()`] for an executable example.
+    fn st_maxx(self) -> functions::st_maxx<Self> {
+        functions::st_maxx(self)
+    }
+
     /// Return the Y coordinate of the bounding-box minimum corner.
     ///
     /// See [`crate::diesel::functions::st_ymin()`] for an executable example.
@@ -359,6 +683,13 @@ pub trait GeometryExpressionMethods: Expression<SqlType = Nullable<Geometry>> +
         functions::st_ymin(self)
     }
 
+    /// Alias for `st_ymin`: return the Y coordinate of the bounding-box minimum corner.
+    ///
+    /// See [`crate::diesel::functions::st_miny()`] for an executable example.
+    fn st_miny(self) -> functions::st_miny<Self> {
+        functions::st_miny(self)
+    }
+
     /// Return the Y coordinate of the bounding-box maximum corner.
     ///
     /// See [`crate::diesel::functions::st_ymax()`] for an executable example.
@@ -366,6 +697,13 @@ pub trait GeometryExpressionMethods: Expression<SqlType = Nullable<Geometry>> +
         functions::st_ymax(self)
     }
 
+    /// Alias for `st_ymax`: return the Y coordinate of the bounding-box maximum corner.
+    ///
+    /// See [`crate::diesel::functions::st_maxy()`] for an executable example.
+    fn st_maxy(self) -> functions::st_maxy<Self> {
+        functions::st_maxy(self)
+    }
+
     // Measurement
 
     /// Return the planar area of a polygon geometry.
@@ -76,7 +76,7 @@
 //! which mirrors the PostGIS workflow where index lifecycle is DDL/SQL-driven.
 
 use crate::diesel::types::Geometry;
-use diesel::sql_types::{Binary, Double, Integer, Nullable, Text};
+use diesel::sql_types::{Binary, Bool, Double, Integer, Nullable, Text};
 
 // I/O
 
@@ -111,6 +111,18 @@ diesel::define_sql_function! {
     fn st_asewkb(geom: Nullable<Geometry>) -> Nullable<Binary>;
 }
 
+diesel::define_sql_function! {
+    /// Serialize a geometry BLOB to little-endian hex EWKB text.
+    fn st_ashexewkb(geom: Nullable<Geometry>) -> Nullable<Text>;
+}
+
+diesel::define_sql_function! {
+    /// Serialize a geometry BLOB to hex EWKB text in `'NDR'` or `'XDR'`
+    /// byte order.
+    #[sql_name = "ST_AsHEXEWKB"]
+    fn st_ashexewkb_endian(geom: Nullable<Geometry>, endian: Text) -> Nullable<Text>;
+}
+
 diesel::define_sql_function! {
     /// Parse ISO WKB bytes into a geometry BLOB.
     fn st_geomfromwkb(wkb: Nullable<Binary>) -> Nullable<Geometry>;
@@ -127,11 +139,72 @@ diesel::define_sql_function! {
     fn st_geomfromewkb(ewkb: Nullable<Binary>) -> Nullable<Geometry>;
 }
 
+diesel::define_sql_function! {
+    /// Parse hex EWKB text, as printed by psql, into a geometry BLOB.
+    fn st_geomfromhexewkb(hexewkb: Text) -> Nullable<Geometry>;
+}
+
 diesel::define_sql_function! {
     /// Serialize a geometry BLOB to GeoJSON text.
     fn st_asgeojson(geom: Nullable<Geometry>) -> Nullable<Text>;
 }
 
+diesel::define_sql_function! {
+    /// Serialize a geometry BLOB to GeoJSON text with at most
+    /// `maxdecimaldigits` decimal digits.
+    #[sql_name = "ST_AsGeoJSON"]
+    fn st_asgeojson_precision(geom: Nullable<Geometry>, maxdecimaldigits: Integer) -> Nullable<Text>;
+}
+
+diesel::define_sql_function! {
+    /// Serialize a geometry BLOB as a GeoJSON Feature whose properties are
+    /// the `properties` JSON object (`json_object(...)`).
+    #[sql_name = "ST_AsGeoJSON"]
+    fn st_asgeojson_feature(properties: Nullable<Text>, geom: Nullable<Geometry>) -> Nullable<Text>;
+}
+
+diesel::define_sql_function! {
+    /// Serialize a geometry BLOB as a GeoJSON Feature with at most
+    /// `maxdecimaldigits` decimal digits.
+    #[sql_name = "ST_AsGeoJSON"]
+    fn st_asgeojson_feature_precision(
+        properties: Nullable<Text>,
+        geom: Nullable<Geometry>,
+        maxdecimaldigits: Integer,
+    ) -> Nullable<Text>;
+}
+
+diesel::define_sql_function! {
+    /// Aggregate GeoJSON Features (as written by `ST_AsGeoJSON(properties,
+    /// geom)`) into a FeatureCollection.
+    #[aggregate]
+    #[sql_name = "ST_AsGeoJSONFeatureCollection"]
+    fn st_asgeojsonfeaturecollection(feature: Nullable<Text>) -> Nullable<Text>;
+}
+
+diesel::define_sql_function! {
+    /// Aggregate `properties` JSON objects and geometries into a GeoJSON
+    /// FeatureCollection.
+    #[aggregate]
+    #[sql_name = "ST_AsGeoJSONFeatureCollection"]
+    fn st_asgeojsonfeaturecollection_properties(
+        properties: Nullable<Text>,
+        geom: Nullable<Geometry>,
+    ) -> Nullable<Text>;
+}
+
+diesel::define_sql_function! {
+    /// Aggregate `properties` JSON objects and geometries into a GeoJSON
+    /// FeatureCollection with at most `maxdecimaldigits` decimal digits.
+    #[aggregate]
+    #[sql_name = "ST_AsGeoJSONFeatureCollection"]
+    fn st_asgeojsonfeaturecollection_precision(
+        properties: Nullable<Text>,
+        geom: Nullable<Geometry>,
+        maxdecimaldigits: Integer,
+    ) -> Nullable<Text>;
+}
+
 diesel::define_sql_function! {
     /// Parse a GeoJSON string into a geometry BLOB.
     ///
@@ -140,6 +213,297 @@ diesel::define_sql_function! {
     fn st_geomfromgeojson(json: Text) -> Nullable<Geometry>;
 }
 
+diesel::define_sql_function! {
+    /// Parse GeoPackage binary geometry (GPB) into a geometry BLOB, keeping
+    /// its SRS id as the SRID.
+    fn st_geomfromgpb(gpb: Nullable<Binary>) -> Nullable<Geometry>;
+}
+
+diesel::define_sql_function! {
+    /// Serialize a geometry BLOB to GeoPackage binary geometry (GPB).
+    #[sql_name = "AsGPB"]
+    fn st_asgpb(geom: Nullable<Geometry>) -> Nullable<Binary>;
+}
+
+diesel::define_sql_function! {
+    /// Parse a SpatiaLite geometry BLOB into a geometry BLOB.
+    fn st_geomfromspatialite(blob: Nullable<Binary>) -> Nullable<Geometry>;
+}
+
+diesel::define_sql_function! {
+    /// Serialize a geometry BLOB to SpatiaLite's internal BLOB format.
+    fn st_asspatialite(geom: Nullable<Geometry>) -> Nullable<Binary>;
+}
+
+diesel::define_sql_function! {
+    /// Serialize a geometry to TWKB (Tiny WKB) at precision 0.
+    fn st_astwkb(geom: Nullable<Geometry>) -> Nullable<Binary>;
+}
+
+diesel::define_sql_function! {
+    /// Serialize a geometry to TWKB, rounding coordinates to `precision_xy`
+    /// decimal digits.
+    #[sql_name = "ST_AsTWKB"]
+    fn st_astwkb_precision(
+        geom: Nullable<Geometry>,
+        precision_xy: Integer,
+    ) -> Nullable<Binary>;
+}
+
+diesel::define_sql_function! {
+    /// Serialize a geometry to TWKB with explicit XY and Z precisions.
+    #[sql_name = "ST_AsTWKB"]
+    fn st_astwkb_precision_z(
+        geom: Nullable<Geometry>,
+        precision_xy: Integer,
+        precision_z: Integer,
+    ) -> Nullable<Binary>;
+}
+
+diesel::define_sql_function! {
+    /// Serialize a geometry to TWKB with explicit XY, Z and M precisions.
+    #[sql_name = "ST_AsTWKB"]
+    fn st_astwkb_precision_m(
+        geom: Nullable<Geometry>,
+        precision_xy: Integer,
+        precision_z: Integer,
+        precision_m: Integer,
+    ) -> Nullable<Binary>;
+}
+
+diesel::define_sql_function! {
+    /// Serialize a geometry to TWKB, optionally including the size field.
+    #[sql_name = "ST_AsTWKB"]
+    fn st_astwkb_sizes(
+        geom: Nullable<Geometry>,
+        precision_xy: Integer,
+        precision_z: Integer,
+        precision_m: Integer,
+        with_sizes: Bool,
+    ) -> Nullable<Binary>;
+}
+
+diesel::define_sql_function! {
+    /// Serialize a geometry to TWKB, optionally including the size and
+    /// bounding-box fields.
+    #[sql_name = "ST_AsTWKB"]
+    fn st_astwkb_boxes(
+        geom: Nullable<Geometry>,
+        precision_xy: Integer,
+        precision_z: Integer,
+        precision_m: Integer,
+        with_sizes: Bool,
+        with_boxes: Bool,
+    ) -> Nullable<Binary>;
+}
+
+diesel::define_sql_function! {
+    /// Parse a TWKB BLOB into a geometry BLOB without SRID.
+    fn st_geomfromtwkb(blob: Nullable<Binary>) -> Nullable<Geometry>;
+}
+
+diesel::define_sql_function! {
+    /// Serialize a WGS 84 geometry to a KML geometry element.
+    fn st_askml(geom: Nullable<Geometry>) -> Nullable<Text>;
+}
+
+diesel::define_sql_function! {
+    /// Serialize a WGS 84 geometry to KML with at most `precision` decimal
+    /// digits.
+    #[sql_name = "ST_AsKML"]
+    fn st_askml_precision(geom: Nullable<Geometry>, precision: Integer) -> Nullable<Text>;
+}
+
+diesel::define_sql_function! {
+    /// Serialize a geometry to a GML 2 geometry element.
+    fn st_asgml(geom: Nullable<Geometry>) -> Nullable<Text>;
+}
+
+diesel::define_sql_function! {
+    /// Serialize a geometry to GML `version` 2 or 3.
+    #[sql_name = "ST_AsGML"]
+    fn st_asgml_version(version: Integer, geom: Nullable<Geometry>) -> Nullable<Text>;
+}
+
+diesel::define_sql_function! {
+    /// Serialize a geometry to GML with at most `precision` decimal digits.
+    #[sql_name = "ST_AsGML"]
+    fn st_asgml_precision(
+        version: Integer,
+        geom: Nullable<Geometry>,
+        precision: Integer,
+    ) -> Nullable<Text>;
+}
+
+diesel::define_sql_function! {
+    /// Serialize a geometry to GML with PostGIS `options` bits (long CRS,
+    /// lat/lon axes, envelope, ...).
+    #[sql_name = "ST_AsGML"]
+    fn st_asgml_options(
+        version: Integer,
+        geom: Nullable<Geometry>,
+        precision: Integer,
+        options: Integer,
+    ) -> Nullable<Text>;
+}
+
+diesel::define_sql_function! {
+    /// Parse a KML geometry element into a geometry BLOB with SRID 4326.
+    fn st_geomfromkml(kml: Text) -> Nullable<Geometry>;
+}
+
+diesel::define_sql_function! {
+    /// Parse a GML geometry element into a geometry BLOB, taking the SRID
+    /// from its srsName.
+    fn st_geomfromgml(gml: Text) -> Nullable<Geometry>;
+}
+
+diesel::define_sql_function! {
+    /// Parse a GML geometry element with explicit SRID into a geometry BLOB.
+    #[sql_name = "ST_GeomFromGML"]
+    fn st_geomfromgml_srid(gml: Text, srid: Integer) -> Nullable<Geometry>;
+}
+
+diesel::define_sql_function! {
+    /// Serialize a geometry to SVG path data with absolute moves and the
+    /// y axis flipped.
+    fn st_assvg(geom: Nullable<Geometry>) -> Nullable<Text>;
+}
+
+diesel::define_sql_function! {
+    /// Serialize a geometry to SVG path data, with relative moves when
+    /// `rel` is non-zero.
+    #[sql_name = "ST_AsSVG"]
+    fn st_assvg_rel(geom: Nullable<Geometry>, rel: Integer) -> Nullable<Text>;
+}
+
+diesel::define_sql_function! {
+    /// Serialize a geometry to SVG path data with at most
+    /// `maxdecimaldigits` decimal digits.
+    #[sql_name = "ST_AsSVG"]
+    fn st_assvg_precision(
+        geom: Nullable<Geometry>,
+        rel: Integer,
+        maxdecimaldigits: Integer,
+    ) -> Nullable<Text>;
+}
+
+diesel::define_sql_function! {
+    /// Encode a LineString or MultiPoint as a Google encoded polyline with
+    /// 5 decimal digits.
+    fn st_asencodedpolyline(geom: Nullable<Geometry>) -> Nullable<Text>;
+}
+
+diesel::define_sql_function! {
+    /// Encode a LineString or MultiPoint as a Google encoded polyline with
+    /// `precision` decimal digits.
+    #[sql_name = "ST_AsEncodedPolyline"]
+    fn st_asencodedpolyline_precision(
+        geom: Nullable<Geometry>,
+        precision: Integer,
+    ) -> Nullable<Text>;
+}
+
+diesel::define_sql_function! {
+    /// Decode a Google encoded polyline with 5 decimal digits into an
+    /// SRID 4326 LineString.
+    fn st_linefromencodedpolyline(polyline: Text) -> Nullable<Geometry>;
+}
+
+diesel::define_sql_function! {
+    /// Decode a Google encoded polyline with `precision` decimal digits
+    /// into an SRID 4326 LineString.
+    #[sql_name = "ST_LineFromEncodedPolyline"]
+    fn st_linefromencodedpolyline_precision(
+        polyline: Text,
+        precision: Integer,
+    ) -> Nullable<Geometry>;
+}
+
+diesel::define_sql_function! {
+    /// Return the GeoHash of a longitude/latitude geometry, sized to its
+    /// bounding box.
+    fn st_geohash(geom: Nullable<Geometry>) -> Nullable<Text>;
+}
+
+diesel::define_sql_function! {
+    /// Return the GeoHash of a longitude/latitude geometry with at most
+    /// `maxchars` characters.
+    #[sql_name = "ST_GeoHash"]
+    fn st_geohash_precision(geom: Nullable<Geometry>, maxchars: Integer) -> Nullable<Text>;
+}
+
+// Vector tiles
+
+diesel::define_sql_function! {
+    /// Transform a geometry into the integer space of the tile covering
+    /// `bounds` (extent 4096, buffer 256, clipped).
+    fn st_asmvtgeom(geom: Nullable<Geometry>, bounds: Nullable<Geometry>) -> Nullable<Geometry>;
+}
+
+diesel::define_sql_function! {
+    /// Transform a geometry into tile space with an explicit extent.
+    #[sql_name = "ST_AsMVTGeom"]
+    fn st_asmvtgeom_extent(
+        geom: Nullable<Geometry>,
+        bounds: Nullable<Geometry>,
+        extent: Integer,
+    ) -> Nullable<Geometry>;
+}
+
+diesel::define_sql_function! {
+    /// Transform a geometry into tile space with an explicit extent and
+    /// clip buffer.
+    #[sql_name = "ST_AsMVTGeom"]
+    fn st_asmvtgeom_buffer(
+        geom: Nullable<Geometry>,
+        bounds: Nullable<Geometry>,
+        extent: Integer,
+        buffer: Integer,
+    ) -> Nullable<Geometry>;
+}
+
+diesel::define_sql_function! {
+    /// Transform a geometry into tile space, choosing whether to clip.
+    #[sql_name = "ST_AsMVTGeom"]
+    fn st_asmvtgeom_clip(
+        geom: Nullable<Geometry>,
+        bounds: Nullable<Geometry>,
+        extent: Integer,
+        buffer: Integer,
+        clip: Bool,
+    ) -> Nullable<Geometry>;
+}
+
+diesel::define_sql_function! {
+    /// Aggregate rows into a single-layer Mapbox Vector Tile. SQLite has no
+    /// row values, so each row is a JSON object (`json_object(...)`) whose
+    /// `geom` member is the hex EWKB of an `ST_AsMVTGeom` result.
+    #[aggregate]
+    #[sql_name = "ST_AsMVT"]
+    fn st_asmvt(row: Nullable<Text>, name: Text) -> Nullable<Binary>;
+}
+
+diesel::define_sql_function! {
+    /// Aggregate JSON rows into a vector tile layer with an explicit extent.
+    #[aggregate]
+    #[sql_name = "ST_AsMVT"]
+    fn st_asmvt_extent(row: Nullable<Text>, name: Text, extent: Integer) -> Nullable<Binary>;
+}
+
+diesel::define_sql_function! {
+    /// Aggregate JSON rows into a vector tile layer, reading the geometry
+    /// from the `geom_column` member.
+    #[aggregate]
+    #[sql_name = "ST_AsMVT"]
+    fn st_asmvt_geom_column(
+        row: Nullable<Text>,
+        name: Text,
+        extent: Integer,
+        geom_column: Text,
+    ) -> Nullable<Binary>;
+}
+
 // Constructors
 
 diesel::define_sql_function! {
@@ -175,6 +539,30 @@ diesel::define_sql_function! {
     fn st_tileenvelope(zoom: Integer, x: Integer, y: Integer) -> Geometry;
 }
 
+diesel::define_sql_function! {
+    /// Construct the SRID 4326 cell polygon of a GeoHash.
+    fn st_geomfromgeohash(geohash: Text) -> Nullable<Geometry>;
+}
+
+diesel::define_sql_function! {
+    /// Construct the SRID 4326 cell polygon of the first `precision`
+    /// characters of a GeoHash.
+    #[sql_name = "ST_GeomFromGeoHash"]
+    fn st_geomfromgeohash_precision(geohash: Text, precision: Integer) -> Nullable<Geometry>;
+}
+
+diesel::define_sql_function! {
+    /// Construct the SRID 4326 centre point of a GeoHash cell.
+    fn st_pointfromgeohash(geohash: Text) -> Nullable<Geometry>;
+}
+
+diesel::define_sql_function! {
+    /// Construct the SRID 4326 centre point of the cell of the first
+    /// `precision` characters of a GeoHash.
+    #[sql_name = "ST_PointFromGeoHash"]
+    fn st_pointfromgeohash_precision(geohash: Text, precision: Integer) -> Nullable<Geometry>;
+}
+
 diesel::define_sql_function! {
     /// Construct a LineString from two Point geometries.
     fn st_makeline(a: Nullable<Geometry>, b: Nullable<Geometry>) -> Nullable<Geometry>;
@@ -580,3 +968,27 @@ diesel::define_sql_function! {
     #[sql_name = "ST_Perimeter2D"]
     fn st_perimeter2d(geom: Nullable<Geometry>) -> Nullable<Double>;
 }
+
+diesel::define_sql_function! {
+    /// Alias for `ST_XMin` (GeoPackage spelling): return the bounding-box minimum X.
+    #[sql_name = "ST_MinX"]
+    fn st_minx(geom: Nullable<Geometry>) -> Nullable<Double>;
+}
+
+diesel::define_sql_function! {
+    /// Alias for `ST_XMax` (GeoPackage spelling): return the bounding-box maximum X.
+    #[sql_name = "ST_MaxX"]
+    fn st_maxx(geom: Nullable<Geometry>) -> Nullable<Double>;
+}
+
+diesel::define_sql_function! {
+    /// Alias for `ST_YMin` (GeoPackage spelling): return the bounding-box minimum Y.
+    #[sql_name = "ST_MinY"]
+    fn st_miny(geom: Nullable<Geometry>) -> Nullable<Double>;
+}
+
+diesel::define_sql_function! {
+    /// Alias for `ST_YMax` (GeoPackage spelling): return the bounding-box maximum Y.
+    #[sql_name = "ST_MaxY"]
+    fn st_maxy(geom: Nullable<Geometry>) -> Nullable<Double>;
+}
@@ -2,6 +2,7 @@
 //! [`GeometryExpressionMethods`] trait live here. Enable `diesel-sqlite` or
 //! `diesel-postgres` to compile the backend-specific impls.
 
+pub mod error;
 pub mod expression_methods;
 pub mod functions;
 pub mod prelude;
@@ -14,6 +15,8 @@ pub mod types;
 // `crate::diesel::trait.GeometryExpressionMethods.html` resolve instead
 // of 404'ing as plain `pub use` re-exports would.
 #[doc(inline)]
+pub use error::SpatialError;
+#[doc(inline)]
 pub use expression_methods::GeometryExpressionMethods;
 #[doc(inline)]
 pub use query_helpers::{
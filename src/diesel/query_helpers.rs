@@ -41,64 +41,10 @@
 //! assert_eq!(hits[0].id, 1);
 //! ```
 
-/// Conservative degree offsets that enclose a geodesic circle.
-///
-/// `dlat` is constant (one degree of latitude is ~111.32 km everywhere on
-/// the WGS84 ellipsoid). `dlon` scales by `1 / cos(lat)` because one
-/// degree of longitude shrinks toward the poles.
-///
-/// At lat 60 degrees `dlon` is roughly twice the equator value. Near the
-/// poles `dlon` would diverge, so [`radius_bbox`] clamps it to 180.0 (the
-/// entire longitude range).
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub struct RadiusBbox {
-    /// Half-width of the bounding box in degrees of longitude.
-    pub dlon: f64,
-    /// Half-height of the bounding box in degrees of latitude.
-    pub dlat: f64,
-}
-
-/// Approximate metres per degree of latitude on the WGS84 ellipsoid.
-const METRES_PER_DEGREE: f64 = 111_320.0;
-
-/// Compute the degree-offset bounding box for a geodesic radius search.
-///
-/// `lat_deg` is the latitude of the probe point in WGS84 degrees,
-/// `radius_m` is the search radius in metres. The result is a [`RadiusBbox`]
-/// that is *guaranteed* to enclose every point within `radius_m` of the
-/// probe (it may include extra points, so refine with `ST_DWithinSphere`).
-///
-/// Worked numbers for a 1000 km radius:
-///
-/// | latitude | `dlon`  | `dlat` |
-/// | -------: | ------: | -----: |
-/// | 0°       | 8.98°   | 8.98°  |
-/// | 45°      | 12.7°   | 8.98°  |
-/// | 60°      | 17.96°  | 8.98°  |
-/// | 80°      | 51.7°   | 8.98°  |
-/// | 89°      | 180.0°  | 8.98°  |
-///
-/// The clamp at 180° applies whenever `cos(lat)` falls below 1e-6, which
-/// happens within roughly 0.00006° of the pole.
-///
-/// ```rust
-/// use sqlitegis::diesel::query_helpers::radius_bbox;
-///
-/// let equator = radius_bbox(0.0, 1_000_000.0);
-/// let berlin = radius_bbox(52.5, 1_000_000.0);
-/// // dlat is constant. dlon grows with |lat| because longitude shrinks.
-/// assert!((equator.dlat - berlin.dlat).abs() < 1e-9);
-/// assert!(berlin.dlon > equator.dlon);
-///
-/// // Near the pole dlon saturates at 180 rather than diverging.
-/// assert_eq!(radius_bbox(89.9999, 1_000_000.0).dlon, 180.0);
-/// ```
-pub fn radius_bbox(lat_deg: f64, radius_m: f64) -> RadiusBbox {
-    let dlat = radius_m / METRES_PER_DEGREE;
-    let cos_lat = lat_deg.to_radians().cos().abs().max(1.0e-6);
-    let dlon = (radius_m / (METRES_PER_DEGREE * cos_lat)).min(180.0);
-    RadiusBbox { dlon, dlat }
-}
+pub use crate::core::index::{
+    dwithin_sphere_indexed_sql_string, intersects_window_indexed_sql_string,
+    nearest_sphere_indexed_sql_string, radius_bbox, RadiusBbox,
+};
 
 /// Build a [`diesel::sql_query`] that runs a radius search through the
 /// R-tree shadow table.
@@ -167,45 +113,6 @@ pub fn dwithin_sphere_indexed_sql(
     ))
 }
 
-/// Render the SQL string that [`dwithin_sphere_indexed_sql`] wraps.
-///
-/// Same inputs and contract, useful when the caller needs the raw SQL
-/// (for logging, for prepending `EXPLAIN QUERY PLAN`, or for piping it
-/// through `diesel::sql_query` together with extra binds).
-///
-/// ```rust
-/// use sqlitegis::diesel::query_helpers::dwithin_sphere_indexed_sql_string;
-///
-/// let sql = dwithin_sphere_indexed_sql_string(
-///     "places", "geom", (13.4, 52.5), 1_000_000.0, "t.id, t.name",
-/// );
-/// assert!(sql.contains("JOIN [places_geom_rtree]"));
-/// assert!(sql.contains("ST_DWithinSphere"));
-/// ```
-pub fn dwithin_sphere_indexed_sql_string(
-    table: &str,
-    geom_column: &str,
-    probe: (f64, f64),
-    radius_m: f64,
-    select_cols: &str,
-) -> String {
-    let (lon, lat) = probe;
-    let bbox = radius_bbox(lat, radius_m);
-    format!(
-        "SELECT {select_cols} \
-         FROM [{table}] t \
-         JOIN [{table}_{geom_column}_rtree] r ON t.rowid = r.id \
-         WHERE r.xmax >= {x_min} AND r.xmin <= {x_max} \
-           AND r.ymax >= {y_min} AND r.ymin <= {y_max} \
-           AND ST_DWithinSphere(t.[{geom_column}], \
-                                ST_Point({lon}, {lat}, 4326), {radius_m})",
-        x_min = lon - bbox.dlon,
-        x_max = lon + bbox.dlon,
-        y_min = lat - bbox.dlat,
-        y_max = lat + bbox.dlat,
-    )
-}
-
 /// Build a [`diesel::sql_query`] that runs an envelope-window search
 /// through the R-tree shadow table.
 ///
@@ -267,44 +174,11 @@ pub fn intersects_window_indexed_sql(
     ))
 }
 
-/// Render the SQL string that [`intersects_window_indexed_sql`] wraps.
-///
-/// Same inputs and contract, useful when the caller needs the raw SQL
-/// (for logging, for prepending `EXPLAIN QUERY PLAN`, or for piping it
-/// through `diesel::sql_query` together with extra binds).
-///
-/// ```rust
-/// use sqlitegis::diesel::query_helpers::intersects_window_indexed_sql_string;
-///
-/// let sql = intersects_window_indexed_sql_string(
-///     "places", "geom", (-1.6, 37.5, 28.4, 67.5), "t.id, t.name",
-/// );
-/// assert!(sql.contains("JOIN [places_geom_rtree]"));
-/// assert!(sql.contains("ST_MakeEnvelope(-1.6, 37.5, 28.4, 67.5, 4326)"));
-/// ```
-pub fn intersects_window_indexed_sql_string(
-    table: &str,
-    geom_column: &str,
-    window: (f64, f64, f64, f64),
-    select_cols: &str,
-) -> String {
-    let (xmin, ymin, xmax, ymax) = window;
-    format!(
-        "SELECT {select_cols} \
-         FROM [{table}] t \
-         JOIN [{table}_{geom_column}_rtree] r ON t.rowid = r.id \
-         WHERE r.xmax >= {xmin} AND r.xmin <= {xmax} \
-           AND r.ymax >= {ymin} AND r.ymin <= {ymax} \
-           AND ST_Intersects(t.[{geom_column}], \
-                             ST_MakeEnvelope({xmin}, {ymin}, {xmax}, {ymax}, 4326))",
-    )
-}
-
 /// Build a [`diesel::sql_query`] that runs a geodesic nearest-N search
 /// through the R-tree shadow table.
 ///
-/// The query JOINs against the R-tree shadow with a cos(lat)-scaled
-/// bounding box (same math as [`radius_bbox`]) and then `ORDER BY`s the
+/// The query JOINs against the R-tree shadow with the [`radius_bbox`] of
+/// `search_radius_m` and then `ORDER BY`s the
 /// resulting candidates by `ST_DistanceSphere` to pick the N closest.
 /// No `ST_DWithinSphere` refinement is needed: the `ORDER BY ... LIMIT`
 /// is itself the refinement.
@@ -370,61 +244,25 @@ pub fn nearest_sphere_indexed_sql(
     ))
 }
 
-/// Render the SQL string that [`nearest_sphere_indexed_sql`] wraps.
-///
-/// Same inputs and contract, useful when the caller needs the raw SQL
-/// (for logging, for prepending `EXPLAIN QUERY PLAN`, or for piping it
-/// through `diesel::sql_query` together with extra binds).
-///
-/// ```rust
-/// use sqlitegis::diesel::query_helpers::nearest_sphere_indexed_sql_string;
-///
-/// let sql = nearest_sphere_indexed_sql_string(
-///     "places", "geom", (13.4, 52.5), 1_000_000.0, 10, "t.id, t.name",
-/// );
-/// assert!(sql.contains("JOIN [places_geom_rtree]"));
-/// assert!(sql.contains("ORDER BY ST_DistanceSphere"));
-/// assert!(sql.contains("LIMIT 10"));
-/// ```
-pub fn nearest_sphere_indexed_sql_string(
-    table: &str,
-    geom_column: &str,
-    probe: (f64, f64),
-    search_radius_m: f64,
-    limit: usize,
-    select_cols: &str,
-) -> String {
-    let (lon, lat) = probe;
-    let bbox = radius_bbox(lat, search_radius_m);
-    format!(
-        "SELECT {select_cols} \
-         FROM [{table}] t \
-         JOIN [{table}_{geom_column}_rtree] r ON t.rowid = r.id \
-         WHERE r.xmax >= {x_min} AND r.xmin <= {x_max} \
-           AND r.ymax >= {y_min} AND r.ymin <= {y_max} \
-         ORDER BY ST_DistanceSphere(t.[{geom_column}], \
-                                    ST_Point({lon}, {lat}, 4326)) \
-         LIMIT {limit}",
-        x_min = lon - bbox.dlon,
-        x_max = lon + bbox.dlon,
-        y_min = lat - bbox.dlat,
-        y_max = lat + bbox.dlat,
-    )
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use geo::{Destination, Distance, Haversine, Point};
+
+    /// Angular radius in degrees of `r` metres on the Haversine sphere.
+    fn cap_degrees(r: f64) -> f64 {
+        (r / Haversine.radius()).to_degrees()
+    }
 
     /// `dlat` is independent of latitude.
     #[test]
     fn radius_bbox_constant_dlat() {
         let r = 1_000_000.0;
-        let expected = r / METRES_PER_DEGREE;
+        let expected = cap_degrees(r);
         for lat in [-89.0_f64, -45.0, 0.0, 45.0, 89.0] {
             let bbox = radius_bbox(lat, r);
             assert!(
-                (bbox.dlat - expected).abs() < 1e-9,
+                (bbox.dlat - expected).abs() < 1e-6,
                 "dlat at lat={lat} was {dlat}, expected {expected}",
                 dlat = bbox.dlat,
             );
@@ -445,15 +283,45 @@ mod tests {
                 "dlon sequence should be increasing, got {dlons:?}",
             );
         }
-        // Equator: ~8.98 degrees. 45 degrees: dlon ~= 8.98 / cos(45) ~= 12.7.
+        // The cap's widest longitude: asin(sin(delta) / cos(lat)).
         let at_45 = radius_bbox(45.0, r).dlon;
-        let expected_at_45 = r / (METRES_PER_DEGREE * 45.0_f64.to_radians().cos());
+        let delta = (r / Haversine.radius()).sin();
+        let expected_at_45 = (delta / 45.0_f64.to_radians().cos()).asin().to_degrees();
         assert!(
             (at_45 - expected_at_45).abs() < 1e-6,
             "dlon at 45 was {at_45}, expected {expected_at_45}",
         );
     }
 
+    /// Every point `ST_DistanceSphere` puts on the circle of radius `r`
+    /// lies inside the box, in every direction and at every latitude.
+    #[test]
+    fn radius_bbox_encloses_the_haversine_circle() {
+        for r in [1.0, 5_000.0, 250_000.0, 1_000_000.0, 3_000_000.0] {
+            for lat in [-70.0_f64, -12.5, 0.0, 33.3, 52.5, 80.0] {
+                // Far enough east that the larger circles cross the antimeridian.
+                let centre = Point::new(170.0, lat);
+                let bbox = radius_bbox(lat, r);
+                for bearing in (0..360).step_by(5) {
+                    let edge = Haversine.destination(centre, f64::from(bearing), r);
+                    assert!((Haversine.distance(centre, edge) - r).abs() < 1e-3 * r.max(1.0));
+                    assert!(
+                        (edge.y() - lat).abs() <= bbox.dlat,
+                        "r={r} lat={lat} bearing={bearing}: {edge:?} outside dlat {}",
+                        bbox.dlat,
+                    );
+                    assert!(
+                        bbox.lon_ranges(170.0)
+                            .iter()
+                            .any(|&(west, east)| (west..=east).contains(&edge.x())),
+                        "r={r} lat={lat} bearing={bearing}: {edge:?} outside dlon {}",
+                        bbox.dlon,
+                    );
+                }
+            }
+        }
+    }
+
     /// Near the pole `dlon` saturates at 180 instead of diverging.
     #[test]
     fn radius_bbox_clamps_near_pole() {
@@ -269,23 +269,27 @@
 //! "restaurants within 5 km of me").
 //!
 //! Since R-trees store coordinates in the geometry's native CRS (degrees for
-//! WGS84 / SRID 4326), we compute a **degree-offset bounding box** that
-//! conservatively encloses the geodesic circle:
+//! WGS84 / SRID 4326), we compute a **degree-offset bounding box** around
+//! the spherical cap of angular radius `δ = radius_m / R` on the same
+//! sphere (`R = 6_371_008.8` m) that `ST_DWithinSphere` measures on:
 //!
 //! ```text
-//! dlat = radius_m / 111_320.0
-//! dlon = radius_m / (111_320.0 * cos(lat_radians))
+//! dlat = degrees(δ)
+//! dlon = degrees(asin(sin δ / cos φ))   -- 180 once the cap reaches a pole
 //! ```
 //!
-//! This is always slightly larger than the true geodesic circle, so the
-//! prefilter never produces false negatives.
+//! [`radius_bbox`](crate::core::index::radius_bbox) computes both with a
+//! small epsilon pad, so rows exactly on the circle are not lost to float
+//! rounding. When `lon ± dlon` crosses the antimeridian the longitude
+//! range has to be split in two; see
+//! [`RadiusBbox::lon_ranges`](crate::core::index::RadiusBbox::lon_ranges).
 //!
 //! ### SQL Template
 //!
 //! ```sql
 //! -- :lat, :lon = center point (degrees)
 //! -- :radius_m  = search radius in metres
-//! -- :dlat, :dlon = precomputed degree offsets (see formula above)
+//! -- :dlat, :dlon = radius_bbox(:lat, :radius_m) (see formula above)
 //!
 //! -- PREFILTER: degree-offset bbox
 //! SELECT t.* FROM my_table t
@@ -307,8 +311,8 @@
 //!
 //! let (lon, lat) = (-0.1278f64, 51.5074f64); // London
 //! let radius_m = 400_000.0f64;
-//! let dlat = radius_m / 111_320.0;
-//! let dlon = radius_m / (111_320.0 * lat.to_radians().cos());
+//! let sqlitegis::core::index::RadiusBbox { dlat, dlon } =
+//!     sqlitegis::core::index::radius_bbox(lat, radius_m);
 //!
 //! let query = diesel::sql_query(
 //!     "SELECT t.id, t.name FROM my_table t \
@@ -341,8 +345,8 @@
 //!
 //! - Use `ST_DWithinSpheroid` instead of `ST_DWithinSphere` for higher
 //!   accuracy (Karney algorithm on WGS84 ellipsoid vs. Haversine on sphere).
-//! - The `dlon` formula diverges near the poles (`cos(lat) -> 0`). For polar
-//!   queries, clamp `dlon` to 360 deg or use a full-scan fallback.
+//! - Once the cap reaches a pole `dlon` is 180 deg and the prefilter only
+//!   narrows on latitude.
 //! - All geometries must have SRID 4326 for the geodesic functions.
 //!
 //! ### Using the built-in helper
@@ -510,8 +514,8 @@
 //!
 //! let (lon, lat) = (2.3522f64, 48.8566f64); // Paris
 //! let search_radius_m = 1_000_000.0f64; // 1000 km
-//! let dlat = search_radius_m / 111_320.0;
-//! let dlon = search_radius_m / (111_320.0 * lat.to_radians().cos());
+//! let sqlitegis::core::index::RadiusBbox { dlat, dlon } =
+//!     sqlitegis::core::index::radius_bbox(lat, search_radius_m);
 //! let n = 3i32;
 //!
 //! let query = diesel::sql_query(
@@ -557,7 +561,8 @@
 //!
 //! For the common case where the N true nearest are known to sit inside a
 //! search radius, SQLiteGIS ships a free function that builds the JOIN +
-//! cos(lat)-scaled bbox prefilter + `ORDER BY ST_DistanceSphere LIMIT N`
+//! [`radius_bbox`](crate::core::index::radius_bbox) prefilter +
+//! `ORDER BY ST_DistanceSphere LIMIT N`
 //! template above:
 //!
 //! ```
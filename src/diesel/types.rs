@@ -3,6 +3,13 @@
 //! Both `Geometry` and `Geography` map to `Binary` (BLOB) in SQLite and
 //! to PostGIS's native `geometry` / `geography` types in PostgreSQL,
 //! storing EWKB-encoded geometry.
+//!
+//! On SQLite, values read back go through
+//! [`crate::core::ewkb::normalize_input`], so wrapping a load in
+//! [`crate::core::ewkb::with_input_formats`] with `hex_ewkb` enabled also
+//! accepts hex EWKB strings stored in TEXT columns. A hex `str` or
+//! `String` bound as [`Geometry`] is decoded and validated before it is
+//! stored as a BLOB.
 
 // SQL types
 
@@ -82,7 +89,12 @@ mod sqlite_impls {
                 fn from_sql(
                     bytes: <Sqlite as diesel::backend::Backend>::RawValue<'_>,
                 ) -> deserialize::Result<Self> {
-                    <Vec<u8> as FromSql<Binary, Sqlite>>::from_sql(bytes)
+                    let blob = <Vec<u8> as FromSql<Binary, Sqlite>>::from_sql(bytes)?;
+                    match crate::core::ewkb::normalize_input(&blob) {
+                        Ok(std::borrow::Cow::Owned(converted)) => Ok(converted),
+                        Ok(std::borrow::Cow::Borrowed(_)) => Ok(blob),
+                        Err(e) => Err(Box::new(e) as DynError),
+                    }
                 }
             }
 
@@ -105,6 +117,23 @@ mod sqlite_impls {
         }
     }
 
+    // --- hex EWKB text (psql dumps, `ST_AsHEXEWKB`) ---
+
+    impl ToSql<Geometry, Sqlite> for str {
+        fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Sqlite>) -> serialize::Result {
+            let blob = crate::core::functions::io::geom_from_hexewkb(self)
+                .map_err(|e| Box::new(e) as DynError)?;
+            out.set_value(blob);
+            Ok(IsNull::No)
+        }
+    }
+
+    impl ToSql<Geometry, Sqlite> for String {
+        fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Sqlite>) -> serialize::Result {
+            <str as ToSql<Geometry, Sqlite>>::to_sql(self.as_str(), out)
+        }
+    }
+
     // --- geo::Geometry<f64> ---
     //
     // The only semantic difference between Geometry and Geography here is the
@@ -118,6 +147,8 @@ mod sqlite_impls {
                     bytes: <Sqlite as diesel::backend::Backend>::RawValue<'_>,
                 ) -> deserialize::Result<Self> {
                     let blob = <Vec<u8> as FromSql<Binary, Sqlite>>::from_sql(bytes)?;
+                    let blob = crate::core::ewkb::normalize_input(&blob)
+                        .map_err(|e| Box::new(e) as DynError)?;
                     super::parse_blob_with_srid_constraint(&blob, $srid).map_err(Into::into)
                 }
             }
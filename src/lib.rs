@@ -8,10 +8,21 @@
 //!
 //! - `core` is always available (pure-Rust geometry, EWKB I/O, function
 //!   catalog, no SQLite or Diesel deps).
+//! - `rayon` adds [`crate::core::batch`], parallel one-probe-vs-many
+//!   variants of the hot predicates and measurements.
 //! - `sqlite` adds [`crate::sqlite::register_functions`] for in-process
 //!   registration against a `*mut sqlite3` connection.
 //! - `sqlite-extension` further adds the `#[no_mangle]` C entry points so
 //!   the cdylib build is loadable via SQLite's `load_extension`.
+//! - `cli` adds [`crate::sqlite::cli`] and the `sqlitegis` binary built
+//!   on it.
+//! - `tile-server` adds [`crate::sqlite::tile_server`] and the
+//!   `sqlitegis-tiles` binary serving vector tiles from a SQLite file.
+//! - `ogc-api` adds [`crate::sqlite::ogc_features`] and the
+//!   `sqlitegis-features` OGC API - Features server.
+//! - `rusqlite` adds [`crate::rusqlite`]: safe registration on a
+//!   `rusqlite::Connection`, geometry `ToSql` / `FromSql` values and the
+//!   R-tree query helpers as prepared statements.
 //! - `diesel` adds backend-agnostic types
 //!   ([`Geometry`](crate::diesel::Geometry),
 //!   [`Geography`](crate::diesel::Geography)) plus
@@ -29,6 +40,9 @@ pub use core::error::{Result, SqliteGisError};
 #[cfg(feature = "sqlite")]
 pub mod sqlite;
 
+#[cfg(all(feature = "rusqlite", not(target_arch = "wasm32")))]
+pub mod rusqlite;
+
 #[cfg(feature = "diesel")]
 pub mod diesel;
 
@@ -0,0 +1,99 @@
+//! rusqlite integration: safe registration on a [`rusqlite::Connection`],
+//! geometry values that bind and read as EWKB, and the R-tree query
+//! helpers as prepared statements.
+//!
+//! rusqlite and this crate link the same `libsqlite3-sys` (Cargo allows
+//! only one crate that links `sqlite3`), so [`register`] hands the
+//! connection's handle to [`register_functions`](crate::sqlite::register_functions)
+//! without any version juggling on the caller's side.
+//!
+//! ```
+//! use rusqlite::Connection;
+//! use sqlitegis::rusqlite::{intersects_window_indexed, register, Geometry};
+//!
+//! let conn = Connection::open_in_memory().unwrap();
+//! register(&conn).unwrap();
+//! conn.execute_batch(
+//!     "CREATE TABLE pts (id INTEGER PRIMARY KEY, geom BLOB);
+//!      SELECT CreateSpatialIndex('pts', 'geom');",
+//! )
+//! .unwrap();
+//!
+//! let berlin = Geometry::new(geo::Point::new(13.4, 52.5), Some(4326));
+//! conn.execute("INSERT INTO pts (id, geom) VALUES (1, ?1)", [&berlin])
+//!     .unwrap();
+//!
+//! let mut stmt =
+//!     intersects_window_indexed(&conn, "pts", "geom", (0.0, 40.0, 20.0, 60.0), "t.id, t.geom")
+//!         .unwrap();
+//! let hits: Vec<(i64, Geometry)> = stmt
+//!     .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+//!     .unwrap()
+//!     .collect::<Result<_, _>>()
+//!     .unwrap();
+//! assert_eq!(hits, vec![(1, berlin)]);
+//! ```
+
+pub mod query_helpers;
+pub mod types;
+
+use rusqlite::Connection;
+
+use crate::core::limits::Limits;
+use crate::sqlite::{
+    register_functions, register_functions_with_file_access, register_functions_with_limits,
+};
+
+#[doc(inline)]
+pub use query_helpers::{
+    dwithin_sphere_indexed, intersects_window_indexed, nearest_sphere_indexed,
+};
+#[doc(inline)]
+pub use types::{Ewkb, Geometry};
+
+// rusqlite's `ffi` has to be our `libsqlite3-sys` for `conn.handle()` to
+// be a handle `register_functions` accepts. Spelled out here so a
+// rusqlite requirement that resolves to another `libsqlite3-sys` fails
+// at this line instead of at the first call site.
+const _: fn(*mut rusqlite::ffi::sqlite3) -> *mut libsqlite3_sys::sqlite3 = |db| db;
+
+/// Turn a registration return code into a rusqlite error.
+fn check(rc: std::os::raw::c_int) -> rusqlite::Result<()> {
+    if rc == rusqlite::ffi::SQLITE_OK {
+        Ok(())
+    } else {
+        Err(rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rc),
+            Some("cannot register the sqlitegis functions".to_string()),
+        ))
+    }
+}
+
+/// Register every sqlitegis SQL function on `conn`.
+///
+/// The safe counterpart of
+/// [`register_functions`](crate::sqlite::register_functions); to cover
+/// every connection the process opens instead, call
+/// [`register_on_every_new_connection`](crate::sqlite::register_on_every_new_connection)
+/// once before opening them.
+pub fn register(conn: &Connection) -> rusqlite::Result<()> {
+    // SAFETY: the handle is open for as long as `conn` is borrowed.
+    check(unsafe { register_functions(conn.handle()) })
+}
+
+/// [`register`] with caps on input size and vertex counts for every call
+/// on `conn`; see [`Limits`].
+pub fn register_with_limits(conn: &Connection, limits: Limits) -> rusqlite::Result<()> {
+    // SAFETY: as in `register`.
+    check(unsafe { register_functions_with_limits(conn.handle(), limits) })
+}
+
+/// [`register_with_limits`] that also lets SQL on `conn` read and write
+/// files through the `Import*`, `Export*` and `GenerateMBTiles` functions
+/// and the file virtual tables; see
+/// [`register_functions_with_file_access`]. Only for connections whose SQL
+/// you trust.
+pub fn register_with_file_access(conn: &Connection, limits: Limits) -> rusqlite::Result<()> {
+    // SAFETY: as in `register`.
+    check(unsafe { register_functions_with_file_access(conn.handle(), limits) })
+}
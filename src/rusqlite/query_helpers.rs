@@ -0,0 +1,101 @@
+//! The R-tree-prefiltered spatial queries of
+//! [`crate::core::index`] as prepared rusqlite statements.
+//!
+//! Each helper JOINs the `{table}_{geom_column}_rtree` shadow table that
+//! `CreateSpatialIndex` maintains and refines the candidates exactly,
+//! the same SQL the Diesel helpers run. `table` and `geom_column` are
+//! bracketed into the SQL, not bound, so they must be trusted
+//! identifiers; the numbers are formatted as `f64` literals.
+//! `select_cols` goes between `SELECT` and `FROM`, with the table
+//! aliased `t` and the R-tree `r`. The statements take no parameters,
+//! so run them with `[]`.
+
+use rusqlite::{Connection, Statement};
+
+use crate::core::index::{
+    dwithin_sphere_indexed_sql_string, intersects_window_indexed_sql_string,
+    nearest_sphere_indexed_sql_string,
+};
+
+/// Rows within `radius_m` metres of the `(lon, lat)` probe on the
+/// sphere.
+///
+/// ```
+/// use rusqlite::Connection;
+/// use sqlitegis::rusqlite::{dwithin_sphere_indexed, register};
+///
+/// let conn = Connection::open_in_memory().unwrap();
+/// register(&conn).unwrap();
+/// conn.execute_batch(
+///     "CREATE TABLE pts (id INTEGER PRIMARY KEY, geom BLOB);
+///      SELECT CreateSpatialIndex('pts', 'geom');
+///      INSERT INTO pts VALUES (1, ST_Point(13.4, 52.5, 4326)),
+///                             (2, ST_Point(2.35, 48.85, 4326));",
+/// )
+/// .unwrap();
+///
+/// let mut stmt =
+///     dwithin_sphere_indexed(&conn, "pts", "geom", (13.4, 52.5), 100_000.0, "t.id").unwrap();
+/// let ids: Vec<i64> = stmt
+///     .query_map([], |row| row.get(0))
+///     .unwrap()
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+/// assert_eq!(ids, vec![1]);
+/// ```
+pub fn dwithin_sphere_indexed<'c>(
+    conn: &'c Connection,
+    table: &str,
+    geom_column: &str,
+    probe: (f64, f64),
+    radius_m: f64,
+    select_cols: &str,
+) -> rusqlite::Result<Statement<'c>> {
+    conn.prepare(&dwithin_sphere_indexed_sql_string(
+        table,
+        geom_column,
+        probe,
+        radius_m,
+        select_cols,
+    ))
+}
+
+/// Rows whose geometry intersects the `(xmin, ymin, xmax, ymax)` window,
+/// given in SRID 4326.
+pub fn intersects_window_indexed<'c>(
+    conn: &'c Connection,
+    table: &str,
+    geom_column: &str,
+    window: (f64, f64, f64, f64),
+    select_cols: &str,
+) -> rusqlite::Result<Statement<'c>> {
+    conn.prepare(&intersects_window_indexed_sql_string(
+        table,
+        geom_column,
+        window,
+        select_cols,
+    ))
+}
+
+/// The `limit` rows nearest the `(lon, lat)` probe on the sphere, closest
+/// first, among those whose R-tree box meets a `search_radius_m` box
+/// around it. Neighbours beyond the search radius are missed, so pick it
+/// comfortably above the expected distance.
+pub fn nearest_sphere_indexed<'c>(
+    conn: &'c Connection,
+    table: &str,
+    geom_column: &str,
+    probe: (f64, f64),
+    search_radius_m: f64,
+    limit: usize,
+    select_cols: &str,
+) -> rusqlite::Result<Statement<'c>> {
+    conn.prepare(&nearest_sphere_indexed_sql_string(
+        table,
+        geom_column,
+        probe,
+        search_radius_m,
+        limit,
+        select_cols,
+    ))
+}
@@ -0,0 +1,146 @@
+//! Geometry values for rusqlite parameters and columns, stored as EWKB
+//! BLOBs.
+//!
+//! Values read back go through [`normalize_input`], so the formats
+//! enabled with [`crate::core::ewkb::with_input_formats`] are accepted
+//! too. rusqlite's traits cannot be implemented for `geo::Geometry`
+//! outside rusqlite or geo, so it travels wrapped in [`Geometry`], which
+//! also keeps the SRID.
+
+use std::borrow::Cow;
+
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, Value, ValueRef};
+
+use crate::core::error::Result;
+use crate::core::ewkb::{extract_srid, normalize_input, parse_ewkb, parse_ewkb_header, write_ewkb};
+
+/// Validated EWKB bytes of a BLOB column, or raw EWKB to bind.
+///
+/// ```
+/// use rusqlite::Connection;
+/// use sqlitegis::rusqlite::{register, Ewkb};
+///
+/// let conn = Connection::open_in_memory().unwrap();
+/// register(&conn).unwrap();
+/// let point: Ewkb = conn
+///     .query_row("SELECT ST_Point(1, 2, 4326)", [], |row| row.get(0))
+///     .unwrap();
+/// assert_eq!(point.srid(), Some(4326));
+/// let wkt: String = conn
+///     .query_row("SELECT ST_AsText(?1)", [&point], |row| row.get(0))
+///     .unwrap();
+/// assert_eq!(wkt, "POINT(1 2)");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Ewkb(pub Vec<u8>);
+
+impl Ewkb {
+    /// Encode `geometry` with `srid`.
+    pub fn from_geo(geometry: &geo::Geometry<f64>, srid: Option<i32>) -> Result<Self> {
+        write_ewkb(geometry, srid).map(Self)
+    }
+
+    /// Decode into a `geo` geometry, dropping the SRID.
+    pub fn to_geo(&self) -> Result<geo::Geometry<f64>> {
+        parse_ewkb(&self.0).map(|(geometry, _)| geometry)
+    }
+
+    /// The SRID, if the EWKB carries one.
+    pub fn srid(&self) -> Option<i32> {
+        extract_srid(&self.0)
+    }
+}
+
+impl From<Vec<u8>> for Ewkb {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+}
+
+impl AsRef<[u8]> for Ewkb {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// The column's BLOB as EWKB, converted by [`normalize_input`].
+fn ewkb_blob(value: ValueRef<'_>) -> FromSqlResult<Cow<'_, [u8]>> {
+    normalize_input(value.as_blob()?).map_err(|e| FromSqlError::Other(Box::new(e)))
+}
+
+impl ToSql for Ewkb {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Borrowed(ValueRef::Blob(&self.0)))
+    }
+}
+
+impl FromSql for Ewkb {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let blob = ewkb_blob(value)?;
+        parse_ewkb_header(&blob).map_err(|e| FromSqlError::Other(Box::new(e)))?;
+        Ok(Self(blob.into_owned()))
+    }
+}
+
+/// A `geo` geometry and its SRID, bound and read as EWKB.
+///
+/// ```
+/// use rusqlite::Connection;
+/// use sqlitegis::rusqlite::{register, Geometry};
+///
+/// let conn = Connection::open_in_memory().unwrap();
+/// register(&conn).unwrap();
+/// let square = Geometry::new(
+///     geo::Rect::new((0.0, 0.0), (2.0, 2.0)).to_polygon(),
+///     None,
+/// );
+/// let area: f64 = conn
+///     .query_row("SELECT ST_Area(?1)", [&square], |row| row.get(0))
+///     .unwrap();
+/// assert_eq!(area, 4.0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Geometry {
+    /// The geometry.
+    pub geometry: geo::Geometry<f64>,
+    /// Its SRID, `None` for EWKB without one.
+    pub srid: Option<i32>,
+}
+
+impl Geometry {
+    /// `geometry` in `srid`.
+    pub fn new(geometry: impl Into<geo::Geometry<f64>>, srid: Option<i32>) -> Self {
+        Self {
+            geometry: geometry.into(),
+            srid,
+        }
+    }
+}
+
+impl From<geo::Geometry<f64>> for Geometry {
+    fn from(geometry: geo::Geometry<f64>) -> Self {
+        Self::new(geometry, None)
+    }
+}
+
+impl From<Geometry> for geo::Geometry<f64> {
+    fn from(geometry: Geometry) -> Self {
+        geometry.geometry
+    }
+}
+
+impl ToSql for Geometry {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        let blob = write_ewkb(&self.geometry, self.srid)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+        Ok(ToSqlOutput::Owned(Value::Blob(blob)))
+    }
+}
+
+impl FromSql for Geometry {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let (geometry, srid) =
+            parse_ewkb(&ewkb_blob(value)?).map_err(|e| FromSqlError::Other(Box::new(e)))?;
+        Ok(Self { geometry, srid })
+    }
+}
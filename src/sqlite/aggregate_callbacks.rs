@@ -0,0 +1,28 @@
+// Hand-maintained. Each row must correspond 1:1 to an entry in
+// crate::core::function_catalog::SQLITE_AGGREGATE_FUNCTIONS. The
+// assert_catalog_callback_parity const-assertion in ffi.rs verifies this at
+// compile time.
+
+const SQLITE_AGGREGATE_CALLBACKS: &[SqliteCallbackSpec] = &[
+    aggregate_callback_spec!("ST_AsMVT", 2, st_asmvt_step, st_asmvt_final),
+    aggregate_callback_spec!("ST_AsMVT", 3, st_asmvt_step, st_asmvt_final),
+    aggregate_callback_spec!("ST_AsMVT", 4, st_asmvt_step, st_asmvt_final),
+    aggregate_callback_spec!(
+        "ST_AsGeoJSONFeatureCollection",
+        1,
+        st_asgeojsonfeaturecollection_step,
+        st_asgeojsonfeaturecollection_final
+    ),
+    aggregate_callback_spec!(
+        "ST_AsGeoJSONFeatureCollection",
+        2,
+        st_asgeojsonfeaturecollection_step,
+        st_asgeojsonfeaturecollection_final
+    ),
+    aggregate_callback_spec!(
+        "ST_AsGeoJSONFeatureCollection",
+        3,
+        st_asgeojsonfeaturecollection_step,
+        st_asgeojsonfeaturecollection_final
+    ),
+];
@@ -0,0 +1,867 @@
+//! The `sqlitegis` command-line tool, built with the `cli` feature.
+//!
+//! ```text
+//! sqlitegis import  <DB> <FILE> [--table T] [--format F] [--srid N] [--no-index]
+//! sqlitegis export  <DB> <OUTPUT> (--table T | --query SQL) [--format F]
+//! sqlitegis index   create|check|drop <DB> <TABLE> [COLUMN]
+//! sqlitegis info    <DB>
+//! sqlitegis convert <INPUT> <OUTPUT> [--from F] [--to F] [--srid N]
+//! ```
+//!
+//! Formats are `geojson`, `csv` (geometries as WKT), `fgb` (FlatGeobuf),
+//! `shp` (Shapefile), `parquet` (GeoParquet, with the `geoparquet`
+//! feature) and `osm` (OpenStreetMap PBF, import only), guessed from the
+//! file extension unless named. Every command goes through the same
+//! readers, writers and table plumbing as the `Import*` and `Export*` SQL
+//! functions, so a file loads the same way from either side.
+
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+
+use super::connection::Connection;
+use super::ffi::{validate_identifier, SPATIAL_INDEX_CATALOG_TABLE};
+use super::osm_pbf::import_osm_pbf;
+use super::shapefile::{open_shapefile, write_shapefile};
+use super::table_io::{
+    for_each_feature, import_features, in_savepoint, query_layout, quote, GEOMETRY_COLUMN,
+};
+use crate::core::error::{Result, SqliteGisError};
+use crate::core::features::{Feature, Field, FieldType, Value};
+use crate::core::flatgeobuf::{FgbReader, FgbWriter};
+use crate::core::functions::accessors::st_set_srid;
+use crate::core::shapefile::ShapefileWriter;
+use crate::core::text_features::{read_csv, read_geojson, write_csv, write_geojson};
+
+/// Run the tool on `args`, the first of which is the program name, and
+/// return the process exit code: 0 on success, 1 when the command fails
+/// (including `index check` finding a stale index) and 2 for usage errors.
+pub fn run<I, T>(args: I) -> ExitCode
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let matches = match command().try_get_matches_from(args) {
+        Ok(matches) => matches,
+        Err(e) => {
+            let _ = e.print();
+            return if e.use_stderr() {
+                ExitCode::from(2)
+            } else {
+                ExitCode::SUCCESS
+            };
+        }
+    };
+    let stdout = io::stdout();
+    match dispatch(&matches, &mut stdout.lock()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("sqlitegis: error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn command() -> Command {
+    let db = || {
+        Arg::new("db")
+            .value_name("DB")
+            .required(true)
+            .help("SQLite database file")
+    };
+    let format = |id: &'static str, help: &'static str| {
+        Arg::new(id)
+            .long(id)
+            .value_name("FORMAT")
+            .value_parser(FORMATS.map(|(name, _)| name))
+            .help(help)
+    };
+    let srid = || {
+        Arg::new("srid")
+            .long("srid")
+            .value_name("SRID")
+            .value_parser(value_parser!(i32))
+            .help("SRID for the geometries, overriding any the file declares")
+    };
+    let wkt_column = || {
+        Arg::new("wkt-column")
+            .long("wkt-column")
+            .value_name("COLUMN")
+            .default_value("wkt")
+            .help("CSV column holding the geometries as WKT")
+    };
+    let charset = || {
+        Arg::new("charset")
+            .long("charset")
+            .value_name("CHARSET")
+            .help("Shapefile attribute encoding, overriding the .cpg")
+    };
+    let index_target = |name: &'static str, about: &'static str| {
+        Command::new(name)
+            .about(about)
+            .arg(db())
+            .arg(Arg::new("table").value_name("TABLE").required(true))
+            .arg(
+                Arg::new("column")
+                    .value_name("COLUMN")
+                    .default_value(GEOMETRY_COLUMN),
+            )
+    };
+
+    Command::new("sqlitegis")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Load, export, index and inspect geometry tables in SQLite")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .subcommand(
+            Command::new("import")
+                .about("Load a geometry file into a table and index it")
+                .arg(db())
+                .arg(Arg::new("file").value_name("FILE").required(true))
+                .arg(
+                    Arg::new("table")
+                        .long("table")
+                        .short('t')
+                        .value_name("TABLE")
+                        .help("Target table; defaults to the file name"),
+                )
+                .arg(format(
+                    "format",
+                    "Input format, when the extension does not tell",
+                ))
+                .arg(srid())
+                .arg(wkt_column())
+                .arg(charset())
+                .arg(
+                    Arg::new("no-index")
+                        .long("no-index")
+                        .action(ArgAction::SetTrue)
+                        .help("Skip creating the spatial index"),
+                ),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Write a table or query to a geometry file")
+                .arg(db())
+                .arg(Arg::new("output").value_name("OUTPUT").required(true))
+                .arg(
+                    Arg::new("table")
+                        .long("table")
+                        .short('t')
+                        .value_name("TABLE")
+                        .help("Table to export"),
+                )
+                .arg(
+                    Arg::new("query")
+                        .long("query")
+                        .short('q')
+                        .value_name("SQL")
+                        .conflicts_with("table")
+                        .required_unless_present("table")
+                        .help("Read-only query to export"),
+                )
+                .arg(
+                    Arg::new("geometry-column")
+                        .long("geometry-column")
+                        .short('g')
+                        .value_name("COLUMN")
+                        .help("Column holding the geometries; guessed when omitted"),
+                )
+                .arg(format(
+                    "format",
+                    "Output format, when the extension does not tell",
+                ))
+                .arg(wkt_column()),
+        )
+        .subcommand(
+            Command::new("index")
+                .about("Create, check or drop a spatial index")
+                .subcommand_required(true)
+                .subcommand(index_target(
+                    "create",
+                    "Create or rebuild the spatial index on a column",
+                ))
+                .subcommand(index_target(
+                    "check",
+                    "Compare a spatial index with its column; fails when stale",
+                ))
+                .subcommand(index_target("drop", "Drop the spatial index on a column")),
+        )
+        .subcommand(
+            Command::new("info")
+                .about("List geometry columns with their SRIDs, extents and index health")
+                .arg(db()),
+        )
+        .subcommand(
+            Command::new("convert")
+                .about("Convert a geometry file to another format")
+                .arg(Arg::new("input").value_name("INPUT").required(true))
+                .arg(Arg::new("output").value_name("OUTPUT").required(true))
+                .arg(format(
+                    "from",
+                    "Input format, when the extension does not tell",
+                ))
+                .arg(format(
+                    "to",
+                    "Output format, when the extension does not tell",
+                ))
+                .arg(srid())
+                .arg(wkt_column())
+                .arg(charset()),
+        )
+}
+
+fn dispatch(matches: &ArgMatches, out: &mut dyn Write) -> Result<()> {
+    fn string<'a>(m: &'a ArgMatches, id: &str) -> Option<&'a str> {
+        m.get_one::<String>(id).map(String::as_str)
+    }
+    let required = |m: &ArgMatches, id: &str| string(m, id).unwrap_or_default().to_string();
+    let read_options = |m: &ArgMatches| ReadOptions {
+        srid: m.get_one::<i32>("srid").copied(),
+        wkt_column: required(m, "wkt-column"),
+        charset: string(m, "charset").map(str::to_string),
+    };
+
+    match matches.subcommand() {
+        Some(("import", m)) => {
+            let path = Path::new(string(m, "file").unwrap_or_default());
+            let format = Format::resolve(string(m, "format"), path)?;
+            let db = Connection::open(&required(m, "db"), true)?;
+            let spatial_index = !m.get_flag("no-index");
+            if format == Format::Osm {
+                let rows = unsafe { import_osm_pbf(db.0, path, spatial_index)? };
+                writeln!(
+                    out,
+                    "imported {rows} rows into osm_points, osm_lines and osm_polygons"
+                )?;
+                return Ok(());
+            }
+            let table = match string(m, "table") {
+                Some(table) => table.to_string(),
+                None => default_table(path)?,
+            };
+            let (fields, features) = read_features(path, format, &read_options(m))?;
+            let rows = unsafe {
+                import_features(
+                    db.0,
+                    &table,
+                    &fields,
+                    features.into_iter().map(Ok),
+                    spatial_index,
+                )?
+            };
+            writeln!(out, "imported {rows} rows into {table}")?;
+        }
+        Some(("export", m)) => {
+            let path = Path::new(string(m, "output").unwrap_or_default());
+            let format = Format::resolve(string(m, "format"), path)?;
+            let db = Connection::open_read_only(&required(m, "db"))?;
+            let query = match string(m, "query") {
+                Some(query) => query.to_string(),
+                None => format!("SELECT * FROM [{}]", identifier(&required(m, "table"))?),
+            };
+            let wkt_column = required(m, "wkt-column");
+            let written = unsafe {
+                in_savepoint(db.0, || {
+                    let layout = query_layout(db.0, &query, string(m, "geometry-column"))?;
+                    let mut sink = Sink::new(path, format, layout.fields.clone(), &wkt_column)?;
+                    for_each_feature(db.0, &query, &layout, |feature| sink.add(feature))?;
+                    sink.finish()
+                })?
+            };
+            writeln!(out, "exported {written} features to {}", path.display())?;
+        }
+        Some(("index", m)) => {
+            let (action, m) = m.subcommand().unwrap_or(("", m));
+            let db = Connection::open(&required(m, "db"), false)?;
+            let table = identifier(string(m, "table").unwrap_or_default())?;
+            let column = identifier(string(m, "column").unwrap_or_default())?;
+            match action {
+                "create" => {
+                    db.query_value(&format!("SELECT CreateSpatialIndex('{table}', '{column}')"))?;
+                    let health = index_health(&db, table, column)?;
+                    writeln!(out, "{table}.{column}: {health}")?;
+                }
+                "drop" => {
+                    db.query_value(&format!("SELECT DropSpatialIndex('{table}', '{column}')"))?;
+                    writeln!(out, "{table}.{column}: dropped")?;
+                }
+                _ => {
+                    let health = index_health(&db, table, column)?;
+                    writeln!(out, "{table}.{column}: {health}")?;
+                    if !matches!(health, IndexHealth::Ok { .. }) {
+                        return Err(SqliteGisError::InvalidInput(format!(
+                            "spatial index on {table}.{column} needs a rebuild \
+                             (sqlitegis index create)"
+                        )));
+                    }
+                }
+            }
+        }
+        Some(("info", m)) => {
+            let db = Connection::open_read_only(&required(m, "db"))?;
+            info(&db, out)?;
+        }
+        Some(("convert", m)) => {
+            let input = Path::new(string(m, "input").unwrap_or_default());
+            let output = Path::new(string(m, "output").unwrap_or_default());
+            let from = Format::resolve(string(m, "from"), input)?;
+            let to = Format::resolve(string(m, "to"), output)?;
+            let (fields, features) = read_features(input, from, &read_options(m))?;
+            let wkt_column = required(m, "wkt-column");
+            let mut sink = Sink::new(output, to, fields, &wkt_column)?;
+            for feature in features {
+                sink.add(feature)?;
+            }
+            let written = sink.finish()?;
+            writeln!(out, "converted {written} features to {}", output.display())?;
+        }
+        _ => unreachable!("clap requires a subcommand"),
+    }
+    Ok(())
+}
+
+fn identifier(name: &str) -> Result<&str> {
+    validate_identifier(name).ok_or_else(|| {
+        SqliteGisError::InvalidInput(format!(
+            "invalid identifier [{name}] (only [a-zA-Z0-9_] allowed)"
+        ))
+    })
+}
+
+/// The table `import` fills when none is named: the file name up to its
+/// first dot, with anything outside `[A-Za-z0-9_]` replaced by `_`.
+fn default_table(path: &Path) -> Result<String> {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let stem = name.split('.').next().unwrap_or_default();
+    let table: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if table.is_empty() {
+        return Err(SqliteGisError::InvalidInput(
+            "cannot name a table after the file; pass --table".to_string(),
+        ));
+    }
+    Ok(table)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    GeoJson,
+    Csv,
+    FlatGeobuf,
+    Shapefile,
+    GeoParquet,
+    Osm,
+}
+
+const FORMATS: [(&str, Format); 6] = [
+    ("geojson", Format::GeoJson),
+    ("csv", Format::Csv),
+    ("fgb", Format::FlatGeobuf),
+    ("shp", Format::Shapefile),
+    ("parquet", Format::GeoParquet),
+    ("osm", Format::Osm),
+];
+
+impl Format {
+    /// The format called `name`, or else the one `path`'s extension names.
+    fn resolve(name: Option<&str>, path: &Path) -> Result<Self> {
+        if let Some(name) = name {
+            if let Some(&(_, format)) = FORMATS.iter().find(|(n, _)| *n == name) {
+                return Ok(format);
+            }
+        }
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "geojson" | "json" => Ok(Self::GeoJson),
+            "csv" => Ok(Self::Csv),
+            "fgb" => Ok(Self::FlatGeobuf),
+            "shp" => Ok(Self::Shapefile),
+            "parquet" | "geoparquet" => Ok(Self::GeoParquet),
+            "pbf" => Ok(Self::Osm),
+            _ => Err(SqliteGisError::InvalidInput(format!(
+                "cannot tell the format of [{}] from its extension; name it",
+                path.display()
+            ))),
+        }
+    }
+}
+
+struct ReadOptions {
+    srid: Option<i32>,
+    wkt_column: String,
+    charset: Option<String>,
+}
+
+fn read_features(
+    path: &Path,
+    format: Format,
+    options: &ReadOptions,
+) -> Result<(Vec<Field>, Vec<Feature>)> {
+    let with_srid = |mut features: Vec<Feature>| -> Result<Vec<Feature>> {
+        if let Some(srid) = options.srid {
+            for geometry in features.iter_mut().filter_map(|f| f.geometry.as_mut()) {
+                *geometry = st_set_srid(geometry, srid)?;
+            }
+        }
+        Ok(features)
+    };
+    match format {
+        Format::GeoJson => read_geojson(&fs::read_to_string(path)?, options.srid),
+        Format::Csv => read_csv(
+            &fs::read_to_string(path)?,
+            &options.wkt_column,
+            options.srid,
+        ),
+        Format::FlatGeobuf => {
+            let reader = FgbReader::open(BufReader::new(File::open(path)?))?;
+            let fields = reader.header().fields.clone();
+            Ok((fields, with_srid(reader.collect::<Result<_>>()?)?))
+        }
+        Format::Shapefile => {
+            let reader = open_shapefile(path, options.srid, options.charset.as_deref())?;
+            let fields = reader.fields().to_vec();
+            Ok((fields, reader.collect::<Result<_>>()?))
+        }
+        #[cfg(feature = "geoparquet")]
+        Format::GeoParquet => {
+            let reader = crate::core::geoparquet::GeoParquetReader::open(File::open(path)?)?;
+            let fields = reader.fields().to_vec();
+            Ok((fields, with_srid(reader.collect::<Result<_>>()?)?))
+        }
+        #[cfg(not(feature = "geoparquet"))]
+        Format::GeoParquet => Err(geoparquet_disabled()),
+        Format::Osm => Err(SqliteGisError::InvalidInput(
+            "an OSM extract holds three layers; load it with `sqlitegis import`".to_string(),
+        )),
+    }
+}
+
+/// An output file being written one feature at a time.
+enum Sink<'a> {
+    /// GeoJSON and CSV, rendered in one piece once every feature is in.
+    Text {
+        path: &'a Path,
+        format: Format,
+        fields: Vec<Field>,
+        features: Vec<Feature>,
+        wkt_column: &'a str,
+    },
+    FlatGeobuf(&'a Path, FgbWriter),
+    Shapefile(&'a Path, ShapefileWriter),
+    #[cfg(feature = "geoparquet")]
+    GeoParquet(crate::core::geoparquet::GeoParquetWriter<File>),
+}
+
+impl<'a> Sink<'a> {
+    fn new(
+        path: &'a Path,
+        format: Format,
+        fields: Vec<Field>,
+        wkt_column: &'a str,
+    ) -> Result<Self> {
+        Ok(match format {
+            Format::GeoJson | Format::Csv => Sink::Text {
+                path,
+                format,
+                fields,
+                features: Vec::new(),
+                wkt_column,
+            },
+            Format::FlatGeobuf => {
+                let name = path
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                Sink::FlatGeobuf(path, FgbWriter::new(name, fields)?)
+            }
+            Format::Shapefile => Sink::Shapefile(path, ShapefileWriter::new(fields)),
+            #[cfg(feature = "geoparquet")]
+            Format::GeoParquet => Sink::GeoParquet(crate::core::geoparquet::GeoParquetWriter::new(
+                File::create(path)?,
+                GEOMETRY_COLUMN,
+                fields,
+            )?),
+            #[cfg(not(feature = "geoparquet"))]
+            Format::GeoParquet => return Err(geoparquet_disabled()),
+            Format::Osm => {
+                return Err(SqliteGisError::InvalidInput(
+                    "OSM PBF output is not supported".to_string(),
+                ))
+            }
+        })
+    }
+
+    fn add(&mut self, feature: Feature) -> Result<()> {
+        match self {
+            Sink::Text { features, .. } => features.push(feature),
+            Sink::FlatGeobuf(_, writer) => writer.add_feature(&feature)?,
+            Sink::Shapefile(_, writer) => writer.add_feature(&feature)?,
+            #[cfg(feature = "geoparquet")]
+            Sink::GeoParquet(writer) => writer.add_feature(&feature)?,
+        }
+        Ok(())
+    }
+
+    /// Write out the file and return the number of features in it.
+    fn finish(self) -> Result<i64> {
+        match self {
+            Sink::Text {
+                path,
+                format,
+                fields,
+                features,
+                wkt_column,
+            } => {
+                let text = match format {
+                    Format::Csv => write_csv(&fields, &features, wkt_column)?,
+                    _ => write_geojson(&fields, &features)?,
+                };
+                fs::write(path, text)?;
+                Ok(features.len() as i64)
+            }
+            Sink::FlatGeobuf(path, writer) => {
+                Ok(writer.finish(BufWriter::new(File::create(path)?))? as i64)
+            }
+            Sink::Shapefile(path, writer) => write_shapefile(path, writer),
+            #[cfg(feature = "geoparquet")]
+            Sink::GeoParquet(writer) => Ok(writer.finish()? as i64),
+        }
+    }
+}
+
+#[cfg(not(feature = "geoparquet"))]
+fn geoparquet_disabled() -> SqliteGisError {
+    SqliteGisError::InvalidInput("GeoParquet support needs the `geoparquet` feature".to_string())
+}
+
+/// How a spatial index compares with the column it indexes.
+#[derive(Debug, PartialEq)]
+enum IndexHealth {
+    Missing,
+    Ok {
+        entries: i64,
+    },
+    Stale {
+        missing: i64,
+        orphaned: i64,
+        mismatched: i64,
+        triggers: bool,
+    },
+}
+
+impl std::fmt::Display for IndexHealth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing => write!(f, "no spatial index"),
+            Self::Ok { entries } => write!(f, "ok ({entries} entries)"),
+            Self::Stale {
+                missing,
+                orphaned,
+                mismatched,
+                triggers,
+            } => {
+                write!(
+                    f,
+                    "stale ({missing} missing, {orphaned} orphaned, {mismatched} mismatched"
+                )?;
+                if !triggers {
+                    write!(f, ", maintenance triggers missing")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// Check the R-tree `CreateSpatialIndex` built for `table.column`: every
+/// non-empty geometry must have an entry whose box contains it, no entry
+/// may point at a row without one, and the three maintenance triggers
+/// must be in place.
+fn index_health(db: &Connection, table: &str, column: &str) -> Result<IndexHealth> {
+    let rtree = format!("{table}_{column}_rtree");
+    let exists = db.query_i64(&format!(
+        "SELECT count(*) FROM sqlite_master WHERE type = 'table' AND name = '{rtree}'"
+    ))?;
+    if exists == 0 {
+        return Ok(IndexHealth::Missing);
+    }
+    let (t, c) = (quote(table), quote(column));
+    let indexed = format!("{c} IS NOT NULL AND ST_IsEmpty({c}) = 0");
+    let missing = db.query_i64(&format!(
+        "SELECT count(*) FROM {t} WHERE {indexed} \
+         AND rowid NOT IN (SELECT id FROM [{rtree}])"
+    ))?;
+    let orphaned = db.query_i64(&format!(
+        "SELECT count(*) FROM [{rtree}] \
+         WHERE id NOT IN (SELECT rowid FROM {t} WHERE {indexed})"
+    ))?;
+    let mismatched = db.query_i64(&format!(
+        "SELECT count(*) FROM {t} JOIN [{rtree}] r ON r.id = {t}.rowid WHERE {indexed} \
+         AND (r.xmin > ST_XMin({c}) OR r.xmax < ST_XMax({c}) \
+           OR r.ymin > ST_YMin({c}) OR r.ymax < ST_YMax({c}))"
+    ))?;
+    let triggers = db.query_i64(&format!(
+        "SELECT count(*) FROM sqlite_master WHERE type = 'trigger' AND name IN \
+         ('{table}_{column}_insert', '{table}_{column}_update', '{table}_{column}_delete')"
+    ))? == 3;
+    if missing == 0 && orphaned == 0 && mismatched == 0 && triggers {
+        let entries = db.query_i64(&format!("SELECT count(*) FROM [{rtree}]"))?;
+        Ok(IndexHealth::Ok { entries })
+    } else {
+        Ok(IndexHealth::Stale {
+            missing,
+            orphaned,
+            mismatched,
+            triggers,
+        })
+    }
+}
+
+/// The geometry columns of `db`: the indexed ones from the spatial index
+/// catalog, plus every column whose first non-NULL values are all EWKB.
+fn geometry_columns(db: &Connection) -> Result<Vec<(String, String)>> {
+    let text = |value: &Value| match value {
+        Value::Text(text) => text.clone(),
+        _ => String::new(),
+    };
+    let objects = db.query_rows(
+        "SELECT name, type = 'table' AND sql LIKE 'CREATE VIRTUAL TABLE%' \
+         FROM sqlite_master WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%' \
+         ORDER BY name",
+    )?;
+    let virtual_tables: Vec<String> = objects
+        .iter()
+        .filter(|row| row[1] == Value::Integer(1))
+        .map(|row| text(&row[0]))
+        .collect();
+    let is_internal = |name: &str| {
+        name == SPATIAL_INDEX_CATALOG_TABLE
+            || virtual_tables.iter().any(|vt| {
+                name == vt
+                    || ["_node", "_rowid", "_parent"]
+                        .iter()
+                        .any(|suffix| name == format!("{vt}{suffix}"))
+            })
+    };
+
+    let mut columns = Vec::new();
+    for table in objects.iter().map(|row| text(&row[0])) {
+        if is_internal(&table) {
+            continue;
+        }
+        for info in db.query_rows(&format!("PRAGMA table_info({})", quote(&table)))? {
+            let column = text(&info[1]);
+            let sample = db.query_rows(&format!(
+                "SELECT {c} FROM {t} WHERE {c} IS NOT NULL LIMIT 100",
+                c = quote(&column),
+                t = quote(&table)
+            ))?;
+            let is_geometry = !sample.is_empty()
+                && sample.iter().all(|row| match &row[0] {
+                    Value::Blob(blob) => crate::core::ewkb::normalize_input(blob)
+                        .is_ok_and(|b| crate::core::ewkb::parse_ewkb_header(&b).is_ok()),
+                    _ => false,
+                });
+            if is_geometry {
+                columns.push((table.clone(), column));
+            }
+        }
+    }
+
+    let catalog = db.query_i64(&format!(
+        "SELECT count(*) FROM sqlite_master WHERE name = '{SPATIAL_INDEX_CATALOG_TABLE}'"
+    ))?;
+    if catalog > 0 {
+        for row in db.query_rows(&format!(
+            "SELECT table_name, column_name FROM [{SPATIAL_INDEX_CATALOG_TABLE}] \
+             ORDER BY table_name, column_name"
+        ))? {
+            let entry = (text(&row[0]), text(&row[1]));
+            if !columns.contains(&entry) {
+                columns.push(entry);
+            }
+        }
+    }
+    Ok(columns)
+}
+
+fn info(db: &Connection, out: &mut dyn Write) -> Result<()> {
+    let columns = geometry_columns(db)?;
+    if columns.is_empty() {
+        writeln!(out, "no geometry columns")?;
+    }
+    let listed = |rows: Vec<Vec<Value>>| {
+        let values: Vec<String> = rows
+            .into_iter()
+            .map(|row| match &row[0] {
+                Value::Null => "none".to_string(),
+                value => match value.coerce(FieldType::Text) {
+                    Value::Text(text) => text,
+                    _ => String::new(),
+                },
+            })
+            .collect();
+        if values.is_empty() {
+            "-".to_string()
+        } else {
+            values.join(", ")
+        }
+    };
+    for (table, column) in columns {
+        let (t, c) = (quote(&table), quote(&column));
+        let counts = db.query_rows(&format!("SELECT count(*), count({c}) FROM {t}"))?;
+        let (rows, with_geometry) = match counts.first().map(Vec::as_slice) {
+            Some([Value::Integer(rows), Value::Integer(with)]) => (*rows, *with),
+            _ => (0, 0),
+        };
+        let types = db.query_rows(&format!(
+            "SELECT DISTINCT ST_GeometryType({c}) FROM {t} WHERE {c} IS NOT NULL ORDER BY 1"
+        ))?;
+        let srids = db.query_rows(&format!(
+            "SELECT DISTINCT ST_SRID({c}) FROM {t} WHERE {c} IS NOT NULL ORDER BY 1"
+        ))?;
+        let extent = db.query_rows(&format!(
+            "SELECT min(ST_XMin({c})), min(ST_YMin({c})), max(ST_XMax({c})), max(ST_YMax({c})) \
+             FROM {t} WHERE {c} IS NOT NULL AND ST_IsEmpty({c}) = 0"
+        ))?;
+        let extent = match extent.first().map(Vec::as_slice) {
+            Some([Value::Real(x0), Value::Real(y0), Value::Real(x1), Value::Real(y1)]) => {
+                format!("{x0} {y0}, {x1} {y1}")
+            }
+            _ => "empty".to_string(),
+        };
+        let health = match (validate_identifier(&table), validate_identifier(&column)) {
+            (Some(table), Some(column)) => index_health(db, table, column)?.to_string(),
+            _ => IndexHealth::Missing.to_string(),
+        };
+        writeln!(out, "{table}.{column}")?;
+        writeln!(out, "  rows:   {rows} ({with_geometry} with a geometry)")?;
+        writeln!(out, "  types:  {}", listed(types))?;
+        writeln!(out, "  srid:   {}", listed(srids))?;
+        writeln!(out, "  extent: {extent}")?;
+        writeln!(out, "  index:  {health}")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_ok(args: &[&str]) -> Result<String> {
+        let matches = command()
+            .try_get_matches_from(std::iter::once("sqlitegis").chain(args.iter().copied()))
+            .map_err(|e| SqliteGisError::InvalidInput(e.to_string()))?;
+        let mut out = Vec::new();
+        dispatch(&matches, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn formats_come_from_extensions_or_names() {
+        let resolve = |name, path: &str| Format::resolve(name, Path::new(path));
+        assert_eq!(resolve(None, "a/parcels.GeoJSON").unwrap(), Format::GeoJson);
+        assert_eq!(resolve(None, "roads.shp").unwrap(), Format::Shapefile);
+        assert_eq!(resolve(None, "extract.osm.pbf").unwrap(), Format::Osm);
+        assert_eq!(resolve(Some("csv"), "points.txt").unwrap(), Format::Csv);
+        assert!(resolve(None, "points.txt").is_err());
+        assert_eq!(
+            default_table(Path::new("dir/my-parcels.osm.pbf")).unwrap(),
+            "my_parcels"
+        );
+    }
+
+    #[test]
+    fn import_index_info_export_and_convert() {
+        let dir = std::env::temp_dir().join(format!("sqlitegis-cli-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_string_lossy().into_owned();
+        fs::write(
+            path("parcels.geojson"),
+            r#"{"type":"FeatureCollection","features":[
+                {"type":"Feature","properties":{"name":"a"},
+                 "geometry":{"type":"Point","coordinates":[1,2]}},
+                {"type":"Feature","properties":{"name":"b"},
+                 "geometry":{"type":"LineString","coordinates":[[3,4],[5,7]]}}]}"#,
+        )
+        .unwrap();
+        let db = path("test.db");
+
+        assert_eq!(
+            run_ok(&["import", &db, &path("parcels.geojson"), "--srid", "4326"]).unwrap(),
+            "imported 2 rows into parcels\n"
+        );
+        assert_eq!(
+            run_ok(&["index", "check", &db, "parcels"]).unwrap(),
+            "parcels.geom: ok (2 entries)\n"
+        );
+        let info = run_ok(&["info", &db]).unwrap();
+        assert!(info.starts_with("parcels.geom\n"), "{info}");
+        assert!(info.contains("  srid:   4326\n"), "{info}");
+        assert!(info.contains("  extent: 1 2, 5 7\n"), "{info}");
+        assert!(info.contains("  index:  ok (2 entries)\n"), "{info}");
+
+        let shp = path("parcels.shp");
+        assert!(run_ok(&["export", &db, &shp, "--table", "parcels"]).is_err());
+        let points = path("points.shp");
+        assert_eq!(
+            run_ok(&[
+                "export",
+                &db,
+                &points,
+                "-q",
+                "SELECT geom, name FROM parcels WHERE name = 'a'",
+            ])
+            .unwrap(),
+            format!("exported 1 features to {points}\n")
+        );
+        let csv = path("points.csv");
+        run_ok(&["convert", &points, &csv]).unwrap();
+        assert_eq!(
+            fs::read_to_string(&csv).unwrap(),
+            "wkt,name\r\nPOINT(1 2),a\r\n"
+        );
+
+        let db_handle = Connection::open(&db, false).unwrap();
+        db_handle
+            .query_value("DELETE FROM parcels_geom_rtree WHERE id = 1")
+            .unwrap();
+        assert_eq!(
+            index_health(&db_handle, "parcels", "geom").unwrap(),
+            IndexHealth::Stale {
+                missing: 1,
+                orphaned: 0,
+                mismatched: 0,
+                triggers: true
+            }
+        );
+        drop(db_handle);
+        assert!(run_ok(&["index", "check", &db, "parcels"]).is_err());
+        assert_eq!(
+            run_ok(&["index", "create", &db, "parcels"]).unwrap(),
+            "parcels.geom: ok (2 entries)\n"
+        );
+        assert_eq!(
+            run_ok(&["index", "drop", &db, "parcels", "geom"]).unwrap(),
+            "parcels.geom: dropped\n"
+        );
+        assert!(run_ok(&["info", &db])
+            .unwrap()
+            .contains("  index:  no spatial index\n"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
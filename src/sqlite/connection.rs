@@ -0,0 +1,157 @@
+//! An owned SQLite connection for the binaries and for the files
+//! `GenerateMBTiles` writes: opened by path, with the sqlitegis functions
+//! registered, closed on drop.
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_int;
+
+use super::ffi::register_functions;
+use super::sqlite_compat::*;
+use super::table_io::Statement;
+use crate::core::error::{Result, SqliteGisError};
+use crate::core::features::Value;
+
+/// A connection with the sqlitegis functions registered, closed on drop.
+pub(super) struct Connection(pub(super) *mut sqlite3);
+
+impl Connection {
+    /// Open the database at `path`, which must exist unless `create`.
+    pub(super) fn open(path: &str, create: bool) -> Result<Self> {
+        let flags = SQLITE_OPEN_READWRITE | if create { SQLITE_OPEN_CREATE } else { 0 };
+        Self::open_with_flags(path, flags)
+    }
+
+    /// Open the existing database at `path` for reading only.
+    #[cfg_attr(
+        not(any(feature = "cli", feature = "tile-server", feature = "ogc-api")),
+        allow(dead_code)
+    )]
+    pub(super) fn open_read_only(path: &str) -> Result<Self> {
+        Self::open_with_flags(path, SQLITE_OPEN_READONLY)
+    }
+
+    fn open_with_flags(path: &str, flags: c_int) -> Result<Self> {
+        let c_path = CString::new(path)
+            .map_err(|_| SqliteGisError::InvalidInput("path contains a NUL byte".to_string()))?;
+        let mut db = std::ptr::null_mut();
+        unsafe {
+            let rc = sqlite3_open_v2(c_path.as_ptr(), &mut db, flags, std::ptr::null());
+            let connection = Self(db);
+            if rc != SQLITE_OK {
+                let message = if db.is_null() {
+                    "out of memory".to_string()
+                } else {
+                    CStr::from_ptr(sqlite3_errmsg(db))
+                        .to_string_lossy()
+                        .into_owned()
+                };
+                return Err(SqliteGisError::Sqlite(format!("{path}: {message}")));
+            }
+            if register_functions(db) != SQLITE_OK {
+                return Err(SqliteGisError::Sqlite(
+                    "cannot register the sqlitegis functions".to_string(),
+                ));
+            }
+            Ok(connection)
+        }
+    }
+
+    pub(super) fn query_rows(&self, sql: &str) -> Result<Vec<Vec<Value>>> {
+        self.query_rows_with(sql, &[])
+    }
+
+    /// Run `sql` with `params` bound to its `?` parameters, in order.
+    pub(super) fn query_rows_with(&self, sql: &str, params: &[Value]) -> Result<Vec<Vec<Value>>> {
+        unsafe {
+            let mut stmt = Statement::prepare(self.0, sql)?;
+            for (i, param) in params.iter().enumerate() {
+                stmt.bind(i as c_int + 1, param)?;
+            }
+            let columns = stmt.column_names().len() as c_int;
+            let mut rows = Vec::new();
+            while stmt.step()? {
+                rows.push((0..columns).map(|i| stmt.column(i)).collect());
+            }
+            Ok(rows)
+        }
+    }
+
+    #[cfg_attr(
+        not(any(feature = "cli", feature = "tile-server", feature = "ogc-api")),
+        allow(dead_code)
+    )]
+    pub(super) fn query_value(&self, sql: &str) -> Result<Value> {
+        Ok(self
+            .query_rows(sql)?
+            .into_iter()
+            .next()
+            .and_then(|row| row.into_iter().next())
+            .unwrap_or(Value::Null))
+    }
+
+    #[cfg_attr(
+        not(any(feature = "cli", feature = "tile-server", feature = "ogc-api")),
+        allow(dead_code)
+    )]
+    pub(super) fn query_i64(&self, sql: &str) -> Result<i64> {
+        match self.query_value(sql)? {
+            Value::Integer(i) => Ok(i),
+            _ => Ok(0),
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        unsafe {
+            sqlite3_close(self.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_by_path_and_binds_parameters() {
+        let path = std::env::temp_dir().join(format!("sqlitegis-conn-{}.db", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        let _ = std::fs::remove_file(&path);
+
+        let err = Connection::open(&path, false).err().unwrap();
+        assert!(matches!(err, SqliteGisError::Sqlite(_)), "{err}");
+        let db = Connection::open(&path, true).unwrap();
+        db.query_rows("CREATE TABLE t (a, b)").unwrap();
+        db.query_rows_with(
+            "INSERT INTO t VALUES (?, ?)",
+            &[Value::Integer(1), Value::Text("x".to_string())],
+        )
+        .unwrap();
+        // The functions are registered on the connection.
+        assert_eq!(
+            db.query_value("SELECT ST_AsText(ST_Point(1, 2))").unwrap(),
+            Value::Text("POINT(1 2)".to_string())
+        );
+        assert_eq!(
+            db.query_rows("SELECT a, b FROM t").unwrap(),
+            vec![vec![Value::Integer(1), Value::Text("x".to_string())]]
+        );
+        assert_eq!(
+            db.query_value("SELECT a FROM t WHERE 0").unwrap(),
+            Value::Null
+        );
+        drop(db);
+
+        let db = Connection::open_read_only(&path).unwrap();
+        assert_eq!(db.query_i64("SELECT count(*) FROM t").unwrap(), 1);
+        let err = db.query_rows("DELETE FROM t").unwrap_err();
+        assert!(err.to_string().contains("readonly"), "{err}");
+        let err = db.query_rows("SELECT nope()").unwrap_err();
+        assert!(matches!(err, SqliteGisError::Sqlite(_)), "{err}");
+        drop(db);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(Connection::open("a\0b", true).is_err());
+    }
+}
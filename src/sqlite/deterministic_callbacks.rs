@@ -10,12 +10,50 @@ const SQLITE_DETERMINISTIC_CALLBACKS: &[SqliteCallbackSpec] = &[
     callback_spec!("ST_GeomFromWKB", 1, st_geomfromwkb_1_xfunc),
     callback_spec!("ST_GeomFromWKB", 2, st_geomfromwkb_2_xfunc),
     callback_spec!("ST_GeomFromEWKB", 1, st_geomfromewkb_xfunc),
+    callback_spec!("ST_GeomFromHEXEWKB", 1, st_geomfromhexewkb_xfunc),
     callback_spec!("ST_GeomFromGeoJSON", 1, st_geomfromgeojson_xfunc),
     callback_spec!("ST_AsText", 1, st_astext_xfunc),
     callback_spec!("ST_AsEWKT", 1, st_asewkt_xfunc),
     callback_spec!("ST_AsBinary", 1, st_asbinary_xfunc),
     callback_spec!("ST_AsEWKB", 1, st_asewkb_xfunc),
+    callback_spec!("ST_AsHEXEWKB", 1, st_ashexewkb_1_xfunc),
+    callback_spec!("ST_AsHEXEWKB", 2, st_ashexewkb_2_xfunc),
     callback_spec!("ST_AsGeoJSON", 1, st_asgeojson_xfunc),
+    callback_spec!("ST_AsGeoJSON", 2, st_asgeojson_2_xfunc),
+    callback_spec!("ST_AsGeoJSON", 3, st_asgeojson_3_xfunc),
+    callback_spec!("ST_GeomFromGPB", 1, st_geomfromgpb_xfunc),
+    callback_spec!("AsGPB", 1, asgpb_xfunc),
+    callback_spec!("ST_GeomFromSpatiaLite", 1, st_geomfromspatialite_xfunc),
+    callback_spec!("ST_AsSpatiaLite", 1, st_asspatialite_xfunc),
+    callback_spec!("ST_AsTWKB", 1, st_astwkb_1_xfunc),
+    callback_spec!("ST_AsTWKB", 2, st_astwkb_2_xfunc),
+    callback_spec!("ST_AsTWKB", 3, st_astwkb_3_xfunc),
+    callback_spec!("ST_AsTWKB", 4, st_astwkb_4_xfunc),
+    callback_spec!("ST_AsTWKB", 5, st_astwkb_5_xfunc),
+    callback_spec!("ST_AsTWKB", 6, st_astwkb_6_xfunc),
+    callback_spec!("ST_GeomFromTWKB", 1, st_geomfromtwkb_xfunc),
+    callback_spec!("ST_AsKML", 1, st_askml_1_xfunc),
+    callback_spec!("ST_AsKML", 2, st_askml_2_xfunc),
+    callback_spec!("ST_AsGML", 1, st_asgml_1_xfunc),
+    callback_spec!("ST_AsGML", 2, st_asgml_2_xfunc),
+    callback_spec!("ST_AsGML", 3, st_asgml_3_xfunc),
+    callback_spec!("ST_AsGML", 4, st_asgml_4_xfunc),
+    callback_spec!("ST_GeomFromKML", 1, st_geomfromkml_xfunc),
+    callback_spec!("ST_GeomFromGML", 1, st_geomfromgml_1_xfunc),
+    callback_spec!("ST_GeomFromGML", 2, st_geomfromgml_2_xfunc),
+    callback_spec!("ST_AsSVG", 1, st_assvg_1_xfunc),
+    callback_spec!("ST_AsSVG", 2, st_assvg_2_xfunc),
+    callback_spec!("ST_AsSVG", 3, st_assvg_3_xfunc),
+    callback_spec!("ST_AsEncodedPolyline", 1, st_asencodedpolyline_1_xfunc),
+    callback_spec!("ST_AsEncodedPolyline", 2, st_asencodedpolyline_2_xfunc),
+    callback_spec!("ST_LineFromEncodedPolyline", 1, st_linefromencodedpolyline_1_xfunc),
+    callback_spec!("ST_LineFromEncodedPolyline", 2, st_linefromencodedpolyline_2_xfunc),
+    callback_spec!("ST_GeoHash", 1, st_geohash_1_xfunc),
+    callback_spec!("ST_GeoHash", 2, st_geohash_2_xfunc),
+    callback_spec!("ST_AsMVTGeom", 2, st_asmvtgeom_2_xfunc),
+    callback_spec!("ST_AsMVTGeom", 3, st_asmvtgeom_3_xfunc),
+    callback_spec!("ST_AsMVTGeom", 4, st_asmvtgeom_4_xfunc),
+    callback_spec!("ST_AsMVTGeom", 5, st_asmvtgeom_5_xfunc),
     callback_spec!("ST_Point", 2, st_point_2_xfunc),
     callback_spec!("ST_Point", 3, st_point_3_xfunc),
     callback_spec!("ST_MakePoint", 2, st_point_2_xfunc),
@@ -25,6 +63,10 @@ const SQLITE_DETERMINISTIC_CALLBACKS: &[SqliteCallbackSpec] = &[
     callback_spec!("ST_MakeEnvelope", 5, st_makeenvelope_5_xfunc),
     callback_spec!("ST_Collect", 2, st_collect_xfunc),
     callback_spec!("ST_TileEnvelope", 3, st_tileenvelope_xfunc),
+    callback_spec!("ST_GeomFromGeoHash", 1, st_geomfromgeohash_1_xfunc),
+    callback_spec!("ST_GeomFromGeoHash", 2, st_geomfromgeohash_2_xfunc),
+    callback_spec!("ST_PointFromGeoHash", 1, st_pointfromgeohash_1_xfunc),
+    callback_spec!("ST_PointFromGeoHash", 2, st_pointfromgeohash_2_xfunc),
     callback_spec!("ST_SRID", 1, st_srid_xfunc),
     callback_spec!("ST_SetSRID", 2, st_setsrid_xfunc),
     callback_spec!("ST_GeometryType", 1, st_geometrytype_xfunc),
@@ -66,6 +108,10 @@ const SQLITE_DETERMINISTIC_CALLBACKS: &[SqliteCallbackSpec] = &[
     callback_spec!("ST_XMax", 1, st_xmax_xfunc),
     callback_spec!("ST_YMin", 1, st_ymin_xfunc),
     callback_spec!("ST_YMax", 1, st_ymax_xfunc),
+    callback_spec!("ST_MinX", 1, st_xmin_xfunc),
+    callback_spec!("ST_MaxX", 1, st_xmax_xfunc),
+    callback_spec!("ST_MinY", 1, st_ymin_xfunc),
+    callback_spec!("ST_MaxY", 1, st_ymax_xfunc),
     callback_spec!("ST_DistanceSphere", 2, st_distancesphere_xfunc),
     callback_spec!("ST_DistanceSpheroid", 2, st_distancespheroid_xfunc),
     callback_spec!("ST_LengthSphere", 1, st_lengthsphere_xfunc),
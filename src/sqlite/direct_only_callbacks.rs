@@ -6,4 +6,24 @@
 const SQLITE_DIRECT_ONLY_CALLBACKS: &[SqliteCallbackSpec] = &[
     callback_spec!("CreateSpatialIndex", 2, create_spatial_index_xfunc),
     callback_spec!("DropSpatialIndex", 2, drop_spatial_index_xfunc),
+    callback_spec!("gpkgAddGeometryColumn", 4, gpkg_add_geometry_column_xfunc),
+    callback_spec!("gpkgAddSpatialIndex", 2, gpkg_add_spatial_index_xfunc),
+    callback_spec!("ConvertSpatiaLiteColumn", 2, convert_spatialite_column_xfunc),
+    callback_spec!("sqlitegis_config", 1, sqlitegis_config_get_xfunc),
+    callback_spec!("sqlitegis_config", 2, sqlitegis_config_set_xfunc),
+    callback_spec!("ImportFlatGeobuf", 2, import_flatgeobuf_xfunc),
+    callback_spec!("ImportFlatGeobuf", 3, import_flatgeobuf_xfunc),
+    callback_spec!("ExportFlatGeobuf", 2, export_flatgeobuf_xfunc),
+    callback_spec!("ImportSHP", 2, import_shp_xfunc),
+    callback_spec!("ImportSHP", 4, import_shp_xfunc),
+    callback_spec!("ExportSHP", 3, export_shp_xfunc),
+    callback_spec!("ImportOSM", 1, import_osm_xfunc),
+    callback_spec!("ImportOSM", 2, import_osm_xfunc),
+    callback_spec!("GenerateMBTiles", 4, generate_mbtiles_xfunc),
+    #[cfg(feature = "geoparquet")]
+    callback_spec!("ImportGeoParquet", 2, import_geoparquet_xfunc),
+    #[cfg(feature = "geoparquet")]
+    callback_spec!("ImportGeoParquet", 3, import_geoparquet_xfunc),
+    #[cfg(feature = "geoparquet")]
+    callback_spec!("ExportGeoParquet", 2, export_geoparquet_xfunc),
 ];
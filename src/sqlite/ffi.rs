@@ -6,18 +6,29 @@
 
 use super::sqlite_compat::sqlite_transient;
 use super::sqlite_compat::*;
-use std::ffi::{CStr, CString};
+use super::vtab::FILE_MODULES;
+use std::borrow::Cow;
+use std::ffi::{c_void, CStr, CString};
 use std::os::raw::c_int;
+use std::sync::{Arc, Mutex, PoisonError};
 
+use crate::core::error::{track_failed_operand, SqliteGisError};
+use crate::core::ewkb::spatialite::{ewkb_to_spatialite, spatialite_to_ewkb};
+use crate::core::ewkb::{normalize_input, with_input_formats, InputFormats};
 use crate::core::function_catalog::{
-    SqliteFunctionSpec, SQLITE_DETERMINISTIC_FUNCTIONS, SQLITE_DIRECT_ONLY_FUNCTIONS,
+    SqliteFunctionSpec, SQLITE_AGGREGATE_FUNCTIONS, SQLITE_DETERMINISTIC_FUNCTIONS,
+    SQLITE_DIRECT_ONLY_FUNCTIONS,
 };
 use crate::core::functions::accessors::*;
 use crate::core::functions::constructors::*;
 use crate::core::functions::io::*;
 use crate::core::functions::measurement::*;
+use crate::core::functions::mvt::*;
 use crate::core::functions::operations::*;
 use crate::core::functions::predicates::*;
+use crate::core::gpb::{ewkb_to_gpb, gpb_to_ewkb};
+use crate::core::interrupt::Interrupt;
+use crate::core::limits::{with_limits, Limits};
 
 // Constants
 
@@ -28,9 +39,31 @@ const DET: c_int = SQLITE_UTF8 | SQLITE_DETERMINISTIC | SQLITE_INNOCUOUS;
 const SQLITE_DIRECTONLY_FLAG: c_int = 0x0008_0000;
 const DIRECT: c_int = SQLITE_UTF8 | SQLITE_DIRECTONLY_FLAG;
 
+/// `SQLITE_RESULT_SUBTYPE` (0x1000000) declares that a function may call
+/// `sqlite3_result_subtype`. Only exported by recent `libsqlite3-sys`
+/// versions, so we define it here.
+const SQLITE_RESULT_SUBTYPE_FLAG: c_int = 0x0100_0000;
+
+/// Subtype SQLite's JSON functions use to mark JSON text (`'J'`).
+const JSON_SUBTYPE: u32 = b'J' as u32;
+
+/// Functions whose TEXT result carries [`JSON_SUBTYPE`].
+const JSON_RESULT_FUNCTIONS: &[&str] = &["ST_AsGeoJSON", "ST_AsGeoJSONFeatureCollection"];
+
+/// Registration flags the function `name` needs beyond `DET` or `DIRECT`.
+fn extra_flags(name: &str) -> c_int {
+    if JSON_RESULT_FUNCTIONS.contains(&name) {
+        SQLITE_RESULT_SUBTYPE_FLAG
+    } else {
+        0
+    }
+}
+
 // Argument-extraction helpers
 
-unsafe fn get_blob<'a>(argv: *mut *mut sqlite3_value, i: usize) -> Option<&'a [u8]> {
+/// BLOB argument `i` as stored, without converting other accepted
+/// geometry formats (see [`get_blob`]).
+unsafe fn get_raw_blob<'a>(argv: *mut *mut sqlite3_value, i: usize) -> Option<&'a [u8]> {
     let v = *argv.add(i);
     if sqlite3_value_type(v) == SQLITE_NULL {
         return None;
@@ -46,6 +79,23 @@ unsafe fn get_blob<'a>(argv: *mut *mut sqlite3_value, i: usize) -> Option<&'a [u
     Some(std::slice::from_raw_parts(ptr, len))
 }
 
+/// BLOB argument `i`, converted to EWKB when it is in one of the
+/// connection's accepted input formats. A value detected as one of those
+/// formats that fails to convert is an error; anything else is passed on
+/// as-is and left to the EWKB parsers.
+unsafe fn get_blob<'a>(
+    argv: *mut *mut sqlite3_value,
+    i: usize,
+) -> Result<Option<&'a [u8]>, SqliteGisError> {
+    let Some(blob) = get_raw_blob(argv, i) else {
+        return Ok(None);
+    };
+    Ok(Some(match normalize_input(blob)? {
+        Cow::Owned(converted) => keep_converted_arg(converted),
+        Cow::Borrowed(_) => blob,
+    }))
+}
+
 enum SqlTextArg<'a> {
     Null,
     Value(&'a str),
@@ -154,11 +204,251 @@ unsafe fn set_error(ctx: *mut sqlite3_context, msg: &str) {
     sqlite3_result_error(ctx, ERROR_MSG_TOO_LARGE.as_ptr().cast(), len);
 }
 
+/// Pin `e` on a single SQL argument where possible: the index argument for
+/// `OutOfBounds`, the geometry argument the core recorded through
+/// [`on_operand`](crate::core::error::on_operand) for functions of several
+/// geometries, or the only geometry argument for an input error of a
+/// function of one. `inputs` are the 1-based positions of the geometry
+/// arguments, in the order the core function takes them. Errors that
+/// involve several inputs (SRID mismatch) or none (limits on the result,
+/// interrupts) are not attributed.
+fn culprit_arg(
+    e: &SqliteGisError,
+    inputs: &[usize],
+    index_arg: Option<usize>,
+    operand: Option<usize>,
+) -> Option<usize> {
+    if let SqliteGisError::OutOfBounds { .. } = e {
+        return index_arg;
+    }
+    if let Some(operand) = operand {
+        return inputs.get(operand).copied();
+    }
+    let input_error = match e {
+        SqliteGisError::InvalidEwkb(_)
+        | SqliteGisError::Geozero(_)
+        | SqliteGisError::WrongType { .. }
+        | SqliteGisError::UnsupportedDimensions { .. } => true,
+        SqliteGisError::LimitExceeded { limit, .. } => *limit != "max_output_vertices",
+        _ => false,
+    };
+    match inputs {
+        [pos] if input_error => Some(*pos),
+        _ => None,
+    }
+}
+
+/// Report a core error as `label(arg N) [kind]: message` with the kind's
+/// extended result code (see [`ErrorKind`](crate::core::error::ErrorKind)),
+/// so hosts can branch on the code or the tag instead of the wording.
+/// `Interrupted` has the primary code `SQLITE_INTERRUPT`, the same as an
+/// interrupt between VM opcodes.
+unsafe fn set_gis_error(
+    ctx: *mut sqlite3_context,
+    label: &str,
+    arg: Option<usize>,
+    e: &SqliteGisError,
+) {
+    let kind = e.kind();
+    let msg = match arg {
+        Some(arg) => format!("{label}(arg {arg}) [{kind}]: {e}"),
+        None => format!("{label} [{kind}]: {e}"),
+    };
+    set_error(ctx, &msg);
+    sqlite3_result_error_code(ctx, kind.sqlite_code());
+}
+
+/// [`Interrupt`] hook over the calling connection's interrupt flag, set by
+/// `sqlite3_interrupt`.
+///
+/// Progress handlers only run between VM opcodes, so a host that wants to
+/// stop a call already in progress must call `sqlite3_interrupt` (a
+/// progress handler may do so before returning). The bundled and wasm
+/// builds ship SQLite 3.41 or newer and read the flag with
+/// `sqlite3_is_interrupted`.
+#[cfg(any(feature = "bundled-sqlite", target_arch = "wasm32"))]
+struct ConnectionInterrupt {
+    db: *mut sqlite3,
+}
+
+#[cfg(any(feature = "bundled-sqlite", target_arch = "wasm32"))]
+impl ConnectionInterrupt {
+    unsafe fn for_context(ctx: *mut sqlite3_context) -> Self {
+        Self {
+            db: sqlite3_context_db_handle(ctx),
+        }
+    }
+}
+
+#[cfg(any(feature = "bundled-sqlite", target_arch = "wasm32"))]
+impl Interrupt for ConnectionInterrupt {
+    fn is_interrupted(&self) -> bool {
+        // SAFETY: the handle comes from the live call's context and is only
+        // used for the duration of that call.
+        !self.db.is_null() && unsafe { sqlite3_is_interrupted(self.db) } != 0
+    }
+}
+
+/// Polls between two real checks of the connection's interrupt flag. The
+/// first poll of a call always checks, so operations that poll only at
+/// their stage boundaries still see the flag, while the per-vertex and
+/// per-segment loops check every few hundred iterations.
+#[cfg(not(any(feature = "bundled-sqlite", target_arch = "wasm32")))]
+const INTERRUPT_POLL_STRIDE: u32 = 256;
+
+/// [`Interrupt`] hook over the calling connection's interrupt flag, set by
+/// `sqlite3_interrupt`.
+///
+/// A system SQLite may predate `sqlite3_is_interrupted` (3.41), so the flag
+/// is read by stepping a nested `SELECT 1` on the same connection: the
+/// VDBE checks the flag on entry and fails the step with
+/// `SQLITE_INTERRUPT`. The probe is prepared on the first real check of a
+/// call and finalized when the call returns; it cannot outlive the call
+/// because an unfinalized statement makes `sqlite3_close` fail with
+/// `SQLITE_BUSY`. Calls that never reach a real check prepare nothing.
+/// Progress handlers only run between VM opcodes, so a host that wants to
+/// stop a call already in progress must call `sqlite3_interrupt` (a
+/// progress handler may do so before returning).
+#[cfg(not(any(feature = "bundled-sqlite", target_arch = "wasm32")))]
+struct ConnectionInterrupt {
+    db: *mut sqlite3,
+    probe: std::cell::Cell<*mut sqlite3_stmt>,
+    polls: std::cell::Cell<u32>,
+}
+
+#[cfg(not(any(feature = "bundled-sqlite", target_arch = "wasm32")))]
+impl ConnectionInterrupt {
+    unsafe fn for_context(ctx: *mut sqlite3_context) -> Self {
+        Self {
+            db: sqlite3_context_db_handle(ctx),
+            probe: std::cell::Cell::new(std::ptr::null_mut()),
+            polls: std::cell::Cell::new(0),
+        }
+    }
+
+    unsafe fn flag_is_set(&self) -> bool {
+        if self.db.is_null() {
+            return false;
+        }
+        let mut stmt = self.probe.get();
+        if stmt.is_null() {
+            let rc = sqlite3_prepare_v2(
+                self.db,
+                c"SELECT 1".as_ptr(),
+                -1,
+                &mut stmt,
+                std::ptr::null_mut(),
+            );
+            if rc != SQLITE_OK {
+                return rc == SQLITE_INTERRUPT;
+            }
+            self.probe.set(stmt);
+        }
+        let rc = sqlite3_step(stmt);
+        sqlite3_reset(stmt);
+        rc == SQLITE_INTERRUPT
+    }
+}
+
+#[cfg(not(any(feature = "bundled-sqlite", target_arch = "wasm32")))]
+impl Interrupt for ConnectionInterrupt {
+    fn is_interrupted(&self) -> bool {
+        let polls = self.polls.get().wrapping_add(1);
+        self.polls.set(polls);
+        if polls % INTERRUPT_POLL_STRIDE != 1 {
+            return false;
+        }
+        // SAFETY: the handle comes from the live call's context and is only
+        // used for the duration of that call.
+        unsafe { self.flag_is_set() }
+    }
+}
+
+#[cfg(not(any(feature = "bundled-sqlite", target_arch = "wasm32")))]
+impl Drop for ConnectionInterrupt {
+    fn drop(&mut self) {
+        let stmt = self.probe.get();
+        if !stmt.is_null() {
+            // SAFETY: prepared by `flag_is_set` and not finalized elsewhere.
+            unsafe {
+                sqlite3_finalize(stmt);
+            }
+        }
+    }
+}
+
+/// Settings shared by every function registered on one connection: the
+/// resource limits, the accepted geometry input formats and whether SQL
+/// may read and write files. Each registration holds an `Arc` clone as
+/// its `pApp` user data, released by [`drop_connection_settings`] when
+/// SQLite drops the function.
+#[derive(Debug, Clone, Copy, Default)]
+struct ConnectionSettings {
+    limits: Limits,
+    input_formats: InputFormats,
+    /// The value `accept_gpb` was last set to, which GPB input falls back
+    /// to when `autodetect_input` is turned off.
+    accept_gpb: bool,
+    /// Set only by [`register_functions_with_file_access`]; SQL can clear
+    /// it but never set it.
+    file_access: bool,
+}
+
+type SharedSettings = Mutex<ConnectionSettings>;
+
+unsafe extern "C" fn drop_connection_settings(p: *mut c_void) {
+    drop(Arc::from_raw(p as *const SharedSettings));
+}
+
+/// The settings shared by the calling function's connection, if it was
+/// registered with any.
+unsafe fn connection_settings<'a>(ctx: *mut sqlite3_context) -> Option<&'a SharedSettings> {
+    (sqlite3_user_data(ctx) as *const SharedSettings).as_ref()
+}
+
+/// Snapshot of the calling connection's settings; unlimited and EWKB-only
+/// when the function was registered without any.
+unsafe fn current_connection_settings(ctx: *mut sqlite3_context) -> ConnectionSettings {
+    connection_settings(ctx)
+        .map(|shared| *shared.lock().unwrap_or_else(PoisonError::into_inner))
+        .unwrap_or_default()
+}
+
+thread_local! {
+    /// Geometry arguments [`get_blob`] converted to EWKB for the running
+    /// callbacks. Each [`xfunc_guard`] frees the ones created inside it, so
+    /// nested callbacks (triggers fired by a spatial index helper) keep
+    /// their caller's conversions alive.
+    static CONVERTED_ARGS: std::cell::RefCell<Vec<Vec<u8>>> =
+        const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// Keep `blob` alive until the enclosing [`xfunc_guard`] returns and hand
+/// out a slice of it.
+unsafe fn keep_converted_arg<'a>(blob: Vec<u8>) -> &'a [u8] {
+    CONVERTED_ARGS.with(|args| {
+        let mut args = args.borrow_mut();
+        args.push(blob);
+        let kept = args.last().expect("just pushed");
+        // SAFETY: the heap buffer does not move when the outer Vec grows,
+        // and it is only freed by the xfunc_guard that encloses this call.
+        std::slice::from_raw_parts(kept.as_ptr(), kept.len())
+    })
+}
+
+/// Run `f` with the connection's limits and input formats installed,
+/// converting a panic into a SQLite error instead of unwinding across the
+/// FFI boundary.
 unsafe fn xfunc_guard<F>(ctx: *mut sqlite3_context, label: &str, f: F)
 where
     F: FnOnce(),
 {
-    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f));
+    let settings = current_connection_settings(ctx);
+    let converted = CONVERTED_ARGS.with(|args| args.borrow().len());
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        with_input_formats(settings.input_formats, || with_limits(settings.limits, f))
+    }));
+    CONVERTED_ARGS.with(|args| args.borrow_mut().truncate(converted));
     if result.is_err() {
         set_error(ctx, &format!("{label}: {PANIC_IN_CALLBACK_MSG}"));
     }
@@ -211,6 +501,28 @@ unsafe fn require_i32_arg(
     }
 }
 
+/// Geometry argument `i` (see [`get_blob`]). A NULL sets a NULL result and
+/// a failed conversion reports its error against the argument; both
+/// return `None`.
+unsafe fn require_blob_arg<'a>(
+    ctx: *mut sqlite3_context,
+    argv: *mut *mut sqlite3_value,
+    i: usize,
+    fn_name: &str,
+) -> Option<&'a [u8]> {
+    match get_blob(argv, i) {
+        Ok(Some(blob)) => Some(blob),
+        Ok(None) => {
+            set_null(ctx);
+            None
+        }
+        Err(e) => {
+            set_gis_error(ctx, fn_name, Some(i + 1), &e);
+            None
+        }
+    }
+}
+
 unsafe fn require_text_arg<'a>(
     ctx: *mut sqlite3_context,
     argv: *mut *mut sqlite3_value,
@@ -270,19 +582,31 @@ unsafe fn set_text_owned(ctx: *mut sqlite3_context, v: impl AsRef<str>) {
     set_text(ctx, v.as_ref());
 }
 
+/// Set a TEXT result tagged with SQLite's JSON subtype, so `json_object`,
+/// `json_array` and friends embed it as JSON instead of quoting it. The
+/// function must be registered with [`SQLITE_RESULT_SUBTYPE_FLAG`].
+unsafe fn set_json(ctx: *mut sqlite3_context, s: &str) {
+    set_text(ctx, s);
+    sqlite3_result_subtype(ctx, JSON_SUBTYPE);
+}
+
+unsafe fn set_json_owned(ctx: *mut sqlite3_context, v: impl AsRef<str>) {
+    set_json(ctx, v.as_ref());
+}
+
 // Callback macros
 //
 // Each xfunc_* macro below generates an `unsafe extern "C" fn` with the
 // standard SQLite scalar-function signature. NULL blob/text inputs produce
 // NULL output (PostGIS-compatible). Errors produce sqlite3_result_error.
 //
-// All eleven macros share two pieces of boilerplate that are factored out
+// All twelve macros share two pieces of boilerplate that are factored out
 // into the two inner helpers below:
 //
 // - `xfunc_decl!` emits the extern "C" fn signature and wraps the body in
 //   the panic-catching `xfunc_guard`.
 // - `xfunc_dispatch!` matches a `Result<T, _>` and routes the Ok arm to a
-//   setter expression while the Err arm formats and calls `set_error`.
+//   setter expression while the Err arm reports through `set_gis_error`.
 
 /// Emit an `unsafe extern "C" fn $name` with the SQLite scalar signature,
 /// wrapping `$body` in `xfunc_guard`. `$ctx` and `$argv` are bound to the
@@ -300,12 +624,15 @@ macro_rules! xfunc_decl {
 }
 
 /// Match a `Result<T, _>` from a callback: route `Ok(v)` to `$set(ctx, v)`
-/// and format `Err(e)` into a SQLite error message tagged with `$label`.
+/// and report `Err(e)` through `set_gis_error`, attributed to one of the
+/// `$inputs` (or to the `$index` argument for out-of-bounds errors).
 macro_rules! xfunc_dispatch {
-    ($ctx:expr, $label:expr, $result:expr, $set:expr) => {
-        match $result {
-            Ok(v) => $set($ctx, v),
-            Err(e) => set_error($ctx, &format!(concat!($label, ": {}"), e)),
+    ($ctx:expr, $label:expr, $inputs:expr, $index:expr, $result:expr, $set:expr) => {
+        match track_failed_operand(|| $result) {
+            (Ok(v), _) => $set($ctx, v),
+            (Err(e), operand) => {
+                set_gis_error($ctx, $label, culprit_arg(&e, $inputs, $index, operand), &e)
+            }
         }
     };
 }
@@ -314,11 +641,10 @@ macro_rules! xfunc_dispatch {
 macro_rules! xfunc_blob {
     ($name:ident, $label:expr, $func:expr, $set:expr) => {
         xfunc_decl!($name, $label, ctx, argv, {
-            let Some(b) = get_blob(argv, 0) else {
-                set_null(ctx);
+            let Some(b) = require_blob_arg(ctx, argv, 0, $label) else {
                 return;
             };
-            xfunc_dispatch!(ctx, $label, $func(b), $set);
+            xfunc_dispatch!(ctx, $label, &[1], None, $func(b), $set);
         });
     };
 }
@@ -327,15 +653,30 @@ macro_rules! xfunc_blob {
 macro_rules! xfunc_blob2 {
     ($name:ident, $label:expr, $func:expr, $set:expr) => {
         xfunc_decl!($name, $label, ctx, argv, {
-            let Some(a) = get_blob(argv, 0) else {
-                set_null(ctx);
+            let Some(a) = require_blob_arg(ctx, argv, 0, $label) else {
+                return;
+            };
+            let Some(b) = require_blob_arg(ctx, argv, 1, $label) else {
+                return;
+            };
+            xfunc_dispatch!(ctx, $label, &[1, 2], None, $func(a, b), $set);
+        });
+    };
+}
+
+/// 2 blobs + connection interrupt hook -> Result<T>, for the operations
+/// that can run long enough to need cancelling.
+macro_rules! xfunc_blob2_interruptible {
+    ($name:ident, $label:expr, $func:expr, $set:expr) => {
+        xfunc_decl!($name, $label, ctx, argv, {
+            let Some(a) = require_blob_arg(ctx, argv, 0, $label) else {
                 return;
             };
-            let Some(b) = get_blob(argv, 1) else {
-                set_null(ctx);
+            let Some(b) = require_blob_arg(ctx, argv, 1, $label) else {
                 return;
             };
-            xfunc_dispatch!(ctx, $label, $func(a, b), $set);
+            let interrupt = ConnectionInterrupt::for_context(ctx);
+            xfunc_dispatch!(ctx, $label, &[1, 2], None, $func(a, b, &interrupt), $set);
         });
     };
 }
@@ -347,14 +688,13 @@ macro_rules! xfunc_blob2 {
 macro_rules! xfunc_blob_opt_f64 {
     ($name:ident, $label:expr, $func:expr) => {
         xfunc_decl!($name, $label, ctx, argv, {
-            let Some(blob) = get_blob(argv, 0) else {
-                set_null(ctx);
+            let Some(blob) = require_blob_arg(ctx, argv, 0, $label) else {
                 return;
             };
             match $func(blob) {
                 Ok(Some(v)) => set_f64(ctx, v),
                 Ok(None) => set_null(ctx),
-                Err(e) => set_error(ctx, &format!(concat!($label, ": {}"), e)),
+                Err(e) => set_gis_error(ctx, $label, culprit_arg(&e, &[1], None, None), &e),
             }
         });
     };
@@ -364,30 +704,36 @@ macro_rules! xfunc_blob_opt_f64 {
 macro_rules! xfunc_blob_i32_blob {
     ($name:ident, $label:expr, $arg_name:expr, $func:expr) => {
         xfunc_decl!($name, $label, ctx, argv, {
-            let Some(b) = get_blob(argv, 0) else {
-                set_null(ctx);
+            let Some(b) = require_blob_arg(ctx, argv, 0, $label) else {
                 return;
             };
             let Some(n) = require_i32_arg(ctx, argv, 1, $label, $arg_name) else {
                 return;
             };
-            xfunc_dispatch!(ctx, $label, ($func)(b, n), set_blob_owned);
+            xfunc_dispatch!(ctx, $label, &[1], Some(2), ($func)(b, n), set_blob_owned);
         });
     };
 }
 
-/// blob + numeric arg -> Result<Vec<u8>>.
-macro_rules! xfunc_blob_f64_blob {
+/// blob + numeric arg + connection interrupt hook -> Result<Vec<u8>>.
+macro_rules! xfunc_blob_f64_blob_interruptible {
     ($name:ident, $label:expr, $arg_name:expr, $func:expr) => {
         xfunc_decl!($name, $label, ctx, argv, {
-            let Some(b) = get_blob(argv, 0) else {
-                set_null(ctx);
+            let Some(b) = require_blob_arg(ctx, argv, 0, $label) else {
                 return;
             };
             let Some(v) = require_f64_arg(ctx, argv, 1, $label, $arg_name) else {
                 return;
             };
-            xfunc_dispatch!(ctx, $label, ($func)(b, v), set_blob_owned);
+            let interrupt = ConnectionInterrupt::for_context(ctx);
+            xfunc_dispatch!(
+                ctx,
+                $label,
+                &[1],
+                None,
+                ($func)(b, v, &interrupt),
+                set_blob_owned
+            );
         });
     };
 }
@@ -396,8 +742,7 @@ macro_rules! xfunc_blob_f64_blob {
 macro_rules! xfunc_blob_f64_f64_blob {
     ($name:ident, $label:expr, $arg1_name:expr, $arg2_name:expr, $func:expr) => {
         xfunc_decl!($name, $label, ctx, argv, {
-            let Some(b) = get_blob(argv, 0) else {
-                set_null(ctx);
+            let Some(b) = require_blob_arg(ctx, argv, 0, $label) else {
                 return;
             };
             let Some(v1) = require_f64_arg(ctx, argv, 1, $label, $arg1_name) else {
@@ -406,7 +751,7 @@ macro_rules! xfunc_blob_f64_f64_blob {
             let Some(v2) = require_f64_arg(ctx, argv, 2, $label, $arg2_name) else {
                 return;
             };
-            xfunc_dispatch!(ctx, $label, ($func)(b, v1, v2), set_blob_owned);
+            xfunc_dispatch!(ctx, $label, &[1], None, ($func)(b, v1, v2), set_blob_owned);
         });
     };
 }
@@ -415,18 +760,16 @@ macro_rules! xfunc_blob_f64_f64_blob {
 macro_rules! xfunc_blob2_f64_bool {
     ($name:ident, $label:expr, $arg_name:expr, $func:expr) => {
         xfunc_decl!($name, $label, ctx, argv, {
-            let Some(a) = get_blob(argv, 0) else {
-                set_null(ctx);
+            let Some(a) = require_blob_arg(ctx, argv, 0, $label) else {
                 return;
             };
-            let Some(b) = get_blob(argv, 1) else {
-                set_null(ctx);
+            let Some(b) = require_blob_arg(ctx, argv, 1, $label) else {
                 return;
             };
             let Some(v) = require_f64_arg(ctx, argv, 2, $label, $arg_name) else {
                 return;
             };
-            xfunc_dispatch!(ctx, $label, ($func)(a, b, v), set_bool);
+            xfunc_dispatch!(ctx, $label, &[1, 2], None, ($func)(a, b, v), set_bool);
         });
     };
 }
@@ -435,18 +778,16 @@ macro_rules! xfunc_blob2_f64_bool {
 macro_rules! xfunc_blob2_text_bool {
     ($name:ident, $label:expr, $arg_name:expr, $func:expr) => {
         xfunc_decl!($name, $label, ctx, argv, {
-            let Some(a) = get_blob(argv, 0) else {
-                set_null(ctx);
+            let Some(a) = require_blob_arg(ctx, argv, 0, $label) else {
                 return;
             };
-            let Some(b) = get_blob(argv, 1) else {
-                set_null(ctx);
+            let Some(b) = require_blob_arg(ctx, argv, 1, $label) else {
                 return;
             };
             let Some(v) = require_text_arg(ctx, argv, 2, $label, $arg_name) else {
                 return;
             };
-            xfunc_dispatch!(ctx, $label, ($func)(a, b, v), set_bool);
+            xfunc_dispatch!(ctx, $label, &[1, 2], None, ($func)(a, b, v), set_bool);
         });
     };
 }
@@ -461,7 +802,7 @@ macro_rules! xfunc_text2_bool {
             let Some(b) = require_text_arg(ctx, argv, 1, $label, $arg2_name) else {
                 return;
             };
-            xfunc_dispatch!(ctx, $label, ($func)(a, b), set_bool);
+            xfunc_dispatch!(ctx, $label, &[], None, ($func)(a, b), set_bool);
         });
     };
 }
@@ -470,21 +811,28 @@ macro_rules! xfunc_text2_bool {
 
 /// text + optional SRID -> blob (generates two callbacks: 1-arg and 2-arg).
 macro_rules! xfunc_text_optsrid_blob {
-    ($name1:ident, $name2:ident, $label:expr, $func:expr) => {
+    ($name1:ident, $name2:ident, $label:expr, $arg_name:expr, $func:expr) => {
         xfunc_decl!($name1, $label, ctx, argv, {
-            let Some(t) = require_text_arg(ctx, argv, 0, $label, "wkt") else {
+            let Some(t) = require_text_arg(ctx, argv, 0, $label, $arg_name) else {
                 return;
             };
-            xfunc_dispatch!(ctx, $label, $func(t, None), set_blob_owned);
+            xfunc_dispatch!(ctx, $label, &[1], None, $func(t, None), set_blob_owned);
         });
         xfunc_decl!($name2, $label, ctx, argv, {
-            let Some(t) = require_text_arg(ctx, argv, 0, $label, "wkt") else {
+            let Some(t) = require_text_arg(ctx, argv, 0, $label, $arg_name) else {
                 return;
             };
             let Some(srid) = require_i32_arg(ctx, argv, 1, $label, "srid") else {
                 return;
             };
-            xfunc_dispatch!(ctx, $label, $func(t, Some(srid)), set_blob_owned);
+            xfunc_dispatch!(
+                ctx,
+                $label,
+                &[1],
+                None,
+                $func(t, Some(srid)),
+                set_blob_owned
+            );
         });
     };
 }
@@ -493,21 +841,26 @@ macro_rules! xfunc_text_optsrid_blob {
 macro_rules! xfunc_blob_optsrid_blob {
     ($name1:ident, $name2:ident, $label:expr, $func:expr) => {
         xfunc_decl!($name1, $label, ctx, argv, {
-            let Some(b) = get_blob(argv, 0) else {
-                set_null(ctx);
+            let Some(b) = require_blob_arg(ctx, argv, 0, $label) else {
                 return;
             };
-            xfunc_dispatch!(ctx, $label, $func(b, None), set_blob_owned);
+            xfunc_dispatch!(ctx, $label, &[1], None, $func(b, None), set_blob_owned);
         });
         xfunc_decl!($name2, $label, ctx, argv, {
-            let Some(b) = get_blob(argv, 0) else {
-                set_null(ctx);
+            let Some(b) = require_blob_arg(ctx, argv, 0, $label) else {
                 return;
             };
             let Some(srid) = require_i32_arg(ctx, argv, 1, $label, "srid") else {
                 return;
             };
-            xfunc_dispatch!(ctx, $label, $func(b, Some(srid)), set_blob_owned);
+            xfunc_dispatch!(
+                ctx,
+                $label,
+                &[1],
+                None,
+                $func(b, Some(srid)),
+                set_blob_owned
+            );
         });
     };
 }
@@ -516,6 +869,7 @@ xfunc_text_optsrid_blob!(
     st_geomfromtext_1_xfunc,
     st_geomfromtext_2_xfunc,
     "ST_GeomFromText",
+    "wkt",
     geom_from_text
 );
 xfunc_blob_optsrid_blob!(
@@ -524,12 +878,55 @@ xfunc_blob_optsrid_blob!(
     "ST_GeomFromWKB",
     geom_from_wkb
 );
-xfunc_blob!(
-    st_geomfromewkb_xfunc,
-    "ST_GeomFromEWKB",
-    geom_from_ewkb,
-    set_blob_owned
-);
+// ST_GeomFromEWKB also takes the hex TEXT that psql and ST_AsHEXEWKB print.
+xfunc_decl!(st_geomfromewkb_xfunc, "ST_GeomFromEWKB", ctx, argv, {
+    if sqlite3_value_type(*argv) == SQLITE_TEXT {
+        let Some(hex) = require_text_arg(ctx, argv, 0, "ST_GeomFromEWKB", "ewkb") else {
+            return;
+        };
+        xfunc_dispatch!(
+            ctx,
+            "ST_GeomFromEWKB",
+            &[1],
+            None,
+            geom_from_hexewkb(hex),
+            set_blob_owned
+        );
+        return;
+    }
+    let Some(b) = require_blob_arg(ctx, argv, 0, "ST_GeomFromEWKB") else {
+        return;
+    };
+    xfunc_dispatch!(
+        ctx,
+        "ST_GeomFromEWKB",
+        &[1],
+        None,
+        geom_from_ewkb(b),
+        set_blob_owned
+    );
+});
+
+unsafe extern "C" fn st_geomfromhexewkb_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_GeomFromHEXEWKB", || {
+        let Some(hex) = require_text_arg(ctx, argv, 0, "ST_GeomFromHEXEWKB", "hexewkb") else {
+            return;
+        };
+        match geom_from_hexewkb(hex) {
+            Ok(v) => set_blob(ctx, &v),
+            Err(e) => set_gis_error(
+                ctx,
+                "ST_GeomFromHEXEWKB",
+                culprit_arg(&e, &[1], None, None),
+                &e,
+            ),
+        }
+    });
+}
 
 unsafe extern "C" fn st_geomfromgeojson_xfunc(
     ctx: *mut sqlite3_context,
@@ -542,7 +939,12 @@ unsafe extern "C" fn st_geomfromgeojson_xfunc(
         };
         match geom_from_geojson(json, None) {
             Ok(v) => set_blob(ctx, &v),
-            Err(e) => set_error(ctx, &format!("ST_GeomFromGeoJSON: {e}")),
+            Err(e) => set_gis_error(
+                ctx,
+                "ST_GeomFromGeoJSON",
+                culprit_arg(&e, &[1], None, None),
+                &e,
+            ),
         }
     });
 }
@@ -551,1179 +953,2997 @@ xfunc_blob!(st_astext_xfunc, "ST_AsText", as_text, set_text_owned);
 xfunc_blob!(st_asewkt_xfunc, "ST_AsEWKT", as_ewkt, set_text_owned);
 xfunc_blob!(st_asbinary_xfunc, "ST_AsBinary", as_binary, set_blob_owned);
 xfunc_blob!(st_asewkb_xfunc, "ST_AsEWKB", as_ewkb, set_blob_owned);
-xfunc_blob!(
-    st_asgeojson_xfunc,
-    "ST_AsGeoJSON",
-    as_geojson,
-    set_text_owned
-);
-
-// Constructor callbacks
-
-unsafe fn st_point_impl(ctx: *mut sqlite3_context, argv: *mut *mut sqlite3_value, with_srid: bool) {
-    let arg_count = if with_srid { 3 } else { 2 };
-    if any_arg_is_null(argv, arg_count) {
-        set_null(ctx);
-        return;
-    }
 
-    let Some(x) = require_f64_arg(ctx, argv, 0, "ST_Point", "x") else {
-        return;
-    };
-    let Some(y) = require_f64_arg(ctx, argv, 1, "ST_Point", "y") else {
+unsafe fn st_ashexewkb_impl(
+    ctx: *mut sqlite3_context,
+    argv: *mut *mut sqlite3_value,
+    n_arg: usize,
+) {
+    let Some(geom) = require_blob_arg(ctx, argv, 0, "ST_AsHEXEWKB") else {
         return;
     };
-    let Some(srid) = optional_srid_arg(ctx, argv, with_srid, 2, "ST_Point") else {
-        return;
+    let endian = if n_arg > 1 {
+        let Some(endian) = require_text_arg(ctx, argv, 1, "ST_AsHEXEWKB", "endian") else {
+            return;
+        };
+        endian
+    } else {
+        "NDR"
     };
-
-    match st_point(x, y, srid) {
-        Ok(v) => set_blob(ctx, &v),
-        Err(e) => set_error(ctx, &format!("ST_Point: {e}")),
+    match as_hexewkb(geom, endian) {
+        Ok(hex) => set_text(ctx, &hex),
+        Err(e) => set_gis_error(ctx, "ST_AsHEXEWKB", culprit_arg(&e, &[1], None, None), &e),
     }
 }
 
-unsafe extern "C" fn st_point_2_xfunc(
+unsafe extern "C" fn st_ashexewkb_1_xfunc(
     ctx: *mut sqlite3_context,
     _n: c_int,
     argv: *mut *mut sqlite3_value,
 ) {
-    xfunc_guard(ctx, "ST_Point", || {
-        st_point_impl(ctx, argv, false);
-    });
+    xfunc_guard(ctx, "ST_AsHEXEWKB", || st_ashexewkb_impl(ctx, argv, 1));
 }
 
-unsafe extern "C" fn st_point_3_xfunc(
+unsafe extern "C" fn st_ashexewkb_2_xfunc(
     ctx: *mut sqlite3_context,
     _n: c_int,
     argv: *mut *mut sqlite3_value,
 ) {
-    xfunc_guard(ctx, "ST_Point", || {
-        st_point_impl(ctx, argv, true);
-    });
+    xfunc_guard(ctx, "ST_AsHEXEWKB", || st_ashexewkb_impl(ctx, argv, 2));
 }
-
-xfunc_blob2!(
-    st_makeline_xfunc,
-    "ST_MakeLine",
-    st_make_line,
-    set_blob_owned
-);
 xfunc_blob!(
-    st_makepolygon_xfunc,
-    "ST_MakePolygon",
-    st_make_polygon,
-    set_blob_owned
+    st_asgeojson_xfunc,
+    "ST_AsGeoJSON",
+    as_geojson,
+    set_json_owned
 );
 
-unsafe fn st_makeenvelope_impl(
+/// `ST_AsGeoJSON(geom, maxdecimaldigits)` when the second argument is an
+/// integer (or the first a BLOB), otherwise the Feature form
+/// `ST_AsGeoJSON(properties, geom[, maxdecimaldigits])`.
+unsafe fn st_asgeojson_impl(
     ctx: *mut sqlite3_context,
     argv: *mut *mut sqlite3_value,
-    with_srid: bool,
+    n_arg: usize,
 ) {
-    let arg_count = if with_srid { 5 } else { 4 };
-    if any_arg_is_null(argv, arg_count) {
-        set_null(ctx);
+    let label = "ST_AsGeoJSON";
+    if n_arg == 2
+        && (sqlite3_value_type(*argv) == SQLITE_BLOB
+            || sqlite3_value_type(*argv.add(1)) == SQLITE_INTEGER)
+    {
+        let Some(geom) = require_blob_arg(ctx, argv, 0, label) else {
+            return;
+        };
+        let Some(digits) = require_i32_arg(ctx, argv, 1, label, "maxdecimaldigits") else {
+            return;
+        };
+        match as_geojson_precision(geom, digits) {
+            Ok(json) => set_json(ctx, &json),
+            Err(e) => set_gis_error(ctx, label, culprit_arg(&e, &[1], None, None), &e),
+        }
         return;
     }
-
-    let Some(xmin) = require_f64_arg(ctx, argv, 0, "ST_MakeEnvelope", "xmin") else {
-        return;
-    };
-    let Some(ymin) = require_f64_arg(ctx, argv, 1, "ST_MakeEnvelope", "ymin") else {
-        return;
-    };
-    let Some(xmax) = require_f64_arg(ctx, argv, 2, "ST_MakeEnvelope", "xmax") else {
-        return;
+    let properties = match get_text(argv, 0) {
+        SqlTextArg::Value(v) => Some(v),
+        SqlTextArg::Null => None,
+        SqlTextArg::InvalidUtf8 => {
+            set_error(
+                ctx,
+                &format!("{label}: properties must be valid UTF-8 text"),
+            );
+            return;
+        }
     };
-    let Some(ymax) = require_f64_arg(ctx, argv, 3, "ST_MakeEnvelope", "ymax") else {
+    let Some(geom) = require_blob_arg(ctx, argv, 1, label) else {
         return;
     };
-    let Some(srid) = optional_srid_arg(ctx, argv, with_srid, 4, "ST_MakeEnvelope") else {
-        return;
+    let digits = if n_arg > 2 {
+        let Some(digits) = require_i32_arg(ctx, argv, 2, label, "maxdecimaldigits") else {
+            return;
+        };
+        Some(digits)
+    } else {
+        None
     };
-
-    match st_make_envelope(xmin, ymin, xmax, ymax, srid) {
-        Ok(v) => set_blob(ctx, &v),
-        Err(e) => set_error(ctx, &format!("ST_MakeEnvelope: {e}")),
+    match as_geojson_feature(properties, geom, digits) {
+        Ok(json) => set_json(ctx, &json),
+        Err(e) => set_gis_error(ctx, label, culprit_arg(&e, &[2], None, None), &e),
     }
 }
 
-unsafe extern "C" fn st_makeenvelope_4_xfunc(
+unsafe extern "C" fn st_asgeojson_2_xfunc(
     ctx: *mut sqlite3_context,
     _n: c_int,
     argv: *mut *mut sqlite3_value,
 ) {
-    xfunc_guard(ctx, "ST_MakeEnvelope", || {
-        st_makeenvelope_impl(ctx, argv, false);
-    });
+    xfunc_guard(ctx, "ST_AsGeoJSON", || st_asgeojson_impl(ctx, argv, 2));
 }
 
-unsafe extern "C" fn st_makeenvelope_5_xfunc(
+unsafe extern "C" fn st_asgeojson_3_xfunc(
     ctx: *mut sqlite3_context,
     _n: c_int,
     argv: *mut *mut sqlite3_value,
 ) {
-    xfunc_guard(ctx, "ST_MakeEnvelope", || {
-        st_makeenvelope_impl(ctx, argv, true);
-    });
+    xfunc_guard(ctx, "ST_AsGeoJSON", || st_asgeojson_impl(ctx, argv, 3));
 }
 
-xfunc_blob2!(st_collect_xfunc, "ST_Collect", st_collect, set_blob_owned);
+unsafe fn st_askml_impl(ctx: *mut sqlite3_context, argv: *mut *mut sqlite3_value, n_arg: usize) {
+    let Some(geom) = require_blob_arg(ctx, argv, 0, "ST_AsKML") else {
+        return;
+    };
+    let precision = if n_arg > 1 {
+        let Some(precision) = require_i32_arg(ctx, argv, 1, "ST_AsKML", "precision") else {
+            return;
+        };
+        precision
+    } else {
+        MAX_DECIMAL_DIGITS
+    };
+    match as_kml(geom, precision) {
+        Ok(Some(kml)) => set_text(ctx, &kml),
+        Ok(None) => set_null(ctx),
+        Err(e) => set_gis_error(ctx, "ST_AsKML", culprit_arg(&e, &[1], None, None), &e),
+    }
+}
 
-unsafe extern "C" fn st_tileenvelope_xfunc(
+unsafe extern "C" fn st_askml_1_xfunc(
     ctx: *mut sqlite3_context,
     _n: c_int,
     argv: *mut *mut sqlite3_value,
 ) {
-    xfunc_guard(ctx, "ST_TileEnvelope", || {
-        let Some(zoom_i32) = require_i32_arg(ctx, argv, 0, "ST_TileEnvelope", "zoom") else {
-            return;
-        };
-        let Some(tile_x_i32) = require_i32_arg(ctx, argv, 1, "ST_TileEnvelope", "tile x") else {
-            return;
-        };
-        let Some(tile_y_i32) = require_i32_arg(ctx, argv, 2, "ST_TileEnvelope", "tile y") else {
+    xfunc_guard(ctx, "ST_AsKML", || st_askml_impl(ctx, argv, 1));
+}
+
+unsafe extern "C" fn st_askml_2_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_AsKML", || st_askml_impl(ctx, argv, 2));
+}
+
+/// `ST_AsGML` mirrors PostGIS's two overload families: `(geom, precision,
+/// options)` writes GML 2, `(version, geom, precision, options)` picks the
+/// version. SQLite cannot overload on argument types, so an integer first
+/// argument selects the second form.
+unsafe fn st_asgml_impl(ctx: *mut sqlite3_context, argv: *mut *mut sqlite3_value, n_arg: usize) {
+    if any_arg_is_null(argv, n_arg) {
+        set_null(ctx);
+        return;
+    }
+    let (version, geom_arg) = if sqlite3_value_type(*argv) == SQLITE_INTEGER {
+        let Some(version) = require_i32_arg(ctx, argv, 0, "ST_AsGML", "version") else {
             return;
         };
-
-        if zoom_i32 < 0 {
-            set_error(ctx, "ST_TileEnvelope: zoom must be non-negative");
+        (version, 1)
+    } else {
+        (2, 0)
+    };
+    let geom = match get_blob(argv, geom_arg) {
+        Ok(Some(geom)) => geom,
+        Ok(None) => {
+            set_error(ctx, "ST_AsGML: geom must be a geometry BLOB");
             return;
         }
-        if tile_x_i32 < 0 {
-            set_error(ctx, "ST_TileEnvelope: tile x must be non-negative");
+        Err(e) => {
+            set_gis_error(ctx, "ST_AsGML", Some(geom_arg + 1), &e);
             return;
         }
-        if tile_y_i32 < 0 {
-            set_error(ctx, "ST_TileEnvelope: tile y must be non-negative");
-            return;
+    };
+    let mut values = [MAX_DECIMAL_DIGITS, 0];
+    for (i, name) in ["precision", "options"].into_iter().enumerate() {
+        let arg = geom_arg + 1 + i;
+        if arg >= n_arg {
+            break;
         }
+        let Some(v) = require_i32_arg(ctx, argv, arg, "ST_AsGML", name) else {
+            return;
+        };
+        values[i] = v;
+    }
+    let [precision, options] = values;
+    match as_gml(version, geom, precision, options) {
+        Ok(Some(gml)) => set_text(ctx, &gml),
+        Ok(None) => set_null(ctx),
+        Err(e) => set_gis_error(
+            ctx,
+            "ST_AsGML",
+            culprit_arg(&e, &[geom_arg + 1], None, None),
+            &e,
+        ),
+    }
+}
 
-        let zoom = zoom_i32 as u32;
-        let tile_x = tile_x_i32 as u32;
-        let tile_y = tile_y_i32 as u32;
-        match st_tile_envelope(zoom, tile_x, tile_y) {
-            Ok(v) => set_blob(ctx, &v),
-            Err(e) => set_error(ctx, &format!("ST_TileEnvelope: {e}")),
-        }
+unsafe extern "C" fn st_asgml_1_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_AsGML", || st_asgml_impl(ctx, argv, 1));
+}
+
+unsafe extern "C" fn st_asgml_2_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_AsGML", || st_asgml_impl(ctx, argv, 2));
+}
+
+unsafe extern "C" fn st_asgml_3_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_AsGML", || st_asgml_impl(ctx, argv, 3));
+}
+
+unsafe extern "C" fn st_asgml_4_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_AsGML", || st_asgml_impl(ctx, argv, 4));
+}
+
+unsafe fn st_assvg_impl(ctx: *mut sqlite3_context, argv: *mut *mut sqlite3_value, n_arg: usize) {
+    let Some(geom) = require_blob_arg(ctx, argv, 0, "ST_AsSVG") else {
+        return;
+    };
+    let relative = if n_arg > 1 {
+        let Some(rel) = require_i32_arg(ctx, argv, 1, "ST_AsSVG", "rel") else {
+            return;
+        };
+        rel != 0
+    } else {
+        false
+    };
+    let precision = if n_arg > 2 {
+        let Some(precision) = require_i32_arg(ctx, argv, 2, "ST_AsSVG", "maxdecimaldigits") else {
+            return;
+        };
+        precision
+    } else {
+        MAX_DECIMAL_DIGITS
+    };
+    match as_svg(geom, relative, precision) {
+        Ok(svg) => set_text(ctx, &svg),
+        Err(e) => set_gis_error(ctx, "ST_AsSVG", culprit_arg(&e, &[1], None, None), &e),
+    }
+}
+
+unsafe extern "C" fn st_assvg_1_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_AsSVG", || st_assvg_impl(ctx, argv, 1));
+}
+
+unsafe extern "C" fn st_assvg_2_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_AsSVG", || st_assvg_impl(ctx, argv, 2));
+}
+
+unsafe extern "C" fn st_assvg_3_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_AsSVG", || st_assvg_impl(ctx, argv, 3));
+}
+
+unsafe fn st_asencodedpolyline_impl(
+    ctx: *mut sqlite3_context,
+    argv: *mut *mut sqlite3_value,
+    n_arg: usize,
+) {
+    let Some(geom) = require_blob_arg(ctx, argv, 0, "ST_AsEncodedPolyline") else {
+        return;
+    };
+    let precision = if n_arg > 1 {
+        let Some(precision) = require_i32_arg(ctx, argv, 1, "ST_AsEncodedPolyline", "precision")
+        else {
+            return;
+        };
+        precision
+    } else {
+        DEFAULT_POLYLINE_PRECISION
+    };
+    match as_encoded_polyline(geom, precision) {
+        Ok(polyline) => set_text(ctx, &polyline),
+        Err(e) => set_gis_error(
+            ctx,
+            "ST_AsEncodedPolyline",
+            culprit_arg(&e, &[1], None, None),
+            &e,
+        ),
+    }
+}
+
+unsafe extern "C" fn st_asencodedpolyline_1_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_AsEncodedPolyline", || {
+        st_asencodedpolyline_impl(ctx, argv, 1)
     });
 }
 
-// Accessor callbacks
+unsafe extern "C" fn st_asencodedpolyline_2_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_AsEncodedPolyline", || {
+        st_asencodedpolyline_impl(ctx, argv, 2)
+    });
+}
 
-xfunc_blob!(st_srid_xfunc, "ST_SRID", st_srid, set_i32);
+unsafe fn st_linefromencodedpolyline_impl(
+    ctx: *mut sqlite3_context,
+    argv: *mut *mut sqlite3_value,
+    n_arg: usize,
+) {
+    let label = "ST_LineFromEncodedPolyline";
+    let Some(polyline) = require_text_arg(ctx, argv, 0, label, "polyline") else {
+        return;
+    };
+    let precision = if n_arg > 1 {
+        let Some(precision) = require_i32_arg(ctx, argv, 1, label, "precision") else {
+            return;
+        };
+        precision
+    } else {
+        DEFAULT_POLYLINE_PRECISION
+    };
+    match line_from_encoded_polyline(polyline, precision) {
+        Ok(v) => set_blob(ctx, &v),
+        Err(e) => set_gis_error(ctx, label, None, &e),
+    }
+}
 
-unsafe extern "C" fn st_setsrid_xfunc(
+unsafe extern "C" fn st_linefromencodedpolyline_1_xfunc(
     ctx: *mut sqlite3_context,
     _n: c_int,
     argv: *mut *mut sqlite3_value,
 ) {
-    xfunc_guard(ctx, "ST_SetSRID", || {
-        let Some(b) = get_blob(argv, 0) else {
-            set_null(ctx);
+    xfunc_guard(ctx, "ST_LineFromEncodedPolyline", || {
+        st_linefromencodedpolyline_impl(ctx, argv, 1)
+    });
+}
+
+unsafe extern "C" fn st_linefromencodedpolyline_2_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_LineFromEncodedPolyline", || {
+        st_linefromencodedpolyline_impl(ctx, argv, 2)
+    });
+}
+
+unsafe fn st_geohash_impl(ctx: *mut sqlite3_context, argv: *mut *mut sqlite3_value, n_arg: usize) {
+    let Some(geom) = require_blob_arg(ctx, argv, 0, "ST_GeoHash") else {
+        return;
+    };
+    let max_chars = if n_arg > 1 {
+        let Some(max_chars) = require_i32_arg(ctx, argv, 1, "ST_GeoHash", "maxchars") else {
             return;
         };
-        let Some(srid) = require_i32_arg(ctx, argv, 1, "ST_SetSRID", "srid") else {
+        Some(max_chars)
+    } else {
+        None
+    };
+    match as_geohash(geom, max_chars) {
+        Ok(Some(hash)) => set_text(ctx, &hash),
+        Ok(None) => set_null(ctx),
+        Err(e) => set_gis_error(ctx, "ST_GeoHash", culprit_arg(&e, &[1], None, None), &e),
+    }
+}
+
+unsafe extern "C" fn st_geohash_1_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_GeoHash", || st_geohash_impl(ctx, argv, 1));
+}
+
+unsafe extern "C" fn st_geohash_2_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_GeoHash", || st_geohash_impl(ctx, argv, 2));
+}
+
+unsafe extern "C" fn st_geomfromkml_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_GeomFromKML", || {
+        let Some(kml) = require_text_arg(ctx, argv, 0, "ST_GeomFromKML", "kml") else {
             return;
         };
-        match st_set_srid(b, srid) {
+        match geom_from_kml(kml) {
             Ok(v) => set_blob(ctx, &v),
-            Err(e) => set_error(ctx, &format!("ST_SetSRID: {e}")),
+            Err(e) => set_gis_error(ctx, "ST_GeomFromKML", culprit_arg(&e, &[1], None, None), &e),
         }
     });
 }
 
-xfunc_blob!(
-    st_geometrytype_xfunc,
-    "ST_GeometryType",
-    st_geometry_type,
-    set_text_owned
+xfunc_text_optsrid_blob!(
+    st_geomfromgml_1_xfunc,
+    st_geomfromgml_2_xfunc,
+    "ST_GeomFromGML",
+    "gml",
+    geom_from_gml
 );
-xfunc_blob!(st_ndims_xfunc, "ST_NDims", st_ndims, set_i32);
-xfunc_blob!(st_coorddim_xfunc, "ST_CoordDim", st_coord_dim, set_i32);
-xfunc_blob!(st_zmflag_xfunc, "ST_Zmflag", st_zmflag, set_i32);
-xfunc_blob!(st_isempty_xfunc, "ST_IsEmpty", st_is_empty, set_bool);
-xfunc_blob!(st_memsize_xfunc, "ST_MemSize", st_mem_size, set_i64);
-xfunc_blob_opt_f64!(st_x_xfunc, "ST_X", st_x);
-xfunc_blob_opt_f64!(st_y_xfunc, "ST_Y", st_y);
-xfunc_blob_opt_f64!(st_z_xfunc, "ST_Z", st_z);
 
-xfunc_blob!(st_numpoints_xfunc, "ST_NumPoints", st_num_points, set_i32);
-xfunc_blob!(st_npoints_xfunc, "ST_NPoints", st_npoints, set_i32);
-xfunc_blob!(
-    st_numgeometries_xfunc,
-    "ST_NumGeometries",
-    st_num_geometries,
-    set_i32
-);
-xfunc_blob!(
-    st_numinteriorrings_xfunc,
-    "ST_NumInteriorRings",
-    st_num_interior_rings,
-    set_i32
-);
-xfunc_blob!(st_numrings_xfunc, "ST_NumRings", st_num_rings, set_i32);
-xfunc_blob_i32_blob!(st_pointn_xfunc, "ST_PointN", "n", |b, n| st_point_n(
-    b, n, None
-));
+unsafe extern "C" fn st_geomfromgpb_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_GeomFromGPB", || {
+        // With `accept_gpb` on, `get_blob` would hand over EWKB already.
+        let Some(b) = get_raw_blob(argv, 0) else {
+            set_null(ctx);
+            return;
+        };
+        match gpb_to_ewkb(b) {
+            Ok(v) => set_blob(ctx, &v),
+            Err(e) => set_gis_error(ctx, "ST_GeomFromGPB", Some(1), &e),
+        }
+    });
+}
 
-xfunc_blob!(
-    st_startpoint_xfunc,
-    "ST_StartPoint",
-    st_start_point,
-    set_blob_owned
-);
-xfunc_blob!(
-    st_endpoint_xfunc,
-    "ST_EndPoint",
-    st_end_point,
-    set_blob_owned
-);
-xfunc_blob!(
-    st_exteriorring_xfunc,
-    "ST_ExteriorRing",
-    st_exterior_ring,
-    set_blob_owned
-);
-xfunc_blob_i32_blob!(
-    st_interiorringn_xfunc,
-    "ST_InteriorRingN",
-    "n",
-    st_interior_ring_n
-);
-xfunc_blob_i32_blob!(st_geometryn_xfunc, "ST_GeometryN", "n", st_geometry_n);
+xfunc_blob!(asgpb_xfunc, "AsGPB", ewkb_to_gpb, set_blob_owned);
+
+unsafe extern "C" fn st_geomfromspatialite_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_GeomFromSpatiaLite", || {
+        let Some(b) = get_raw_blob(argv, 0) else {
+            set_null(ctx);
+            return;
+        };
+        match spatialite_to_ewkb(b) {
+            Ok(v) => set_blob(ctx, &v),
+            Err(e) => set_gis_error(ctx, "ST_GeomFromSpatiaLite", Some(1), &e),
+        }
+    });
+}
 
-xfunc_blob!(st_dimension_xfunc, "ST_Dimension", st_dimension, set_i32);
 xfunc_blob!(
-    st_envelope_xfunc,
-    "ST_Envelope",
-    st_envelope,
+    st_asspatialite_xfunc,
+    "ST_AsSpatiaLite",
+    ewkb_to_spatialite,
     set_blob_owned
 );
-xfunc_blob!(st_isvalid_xfunc, "ST_IsValid", st_is_valid, set_bool);
-xfunc_blob!(
-    st_isvalidreason_xfunc,
-    "ST_IsValidReason",
-    st_is_valid_reason,
-    set_text_owned
-);
-
-// Measurement callbacks
-
-xfunc_blob!(st_area_xfunc, "ST_Area", st_area, set_f64);
-xfunc_blob!(st_length_xfunc, "ST_Length", st_length, set_f64);
-xfunc_blob!(st_perimeter_xfunc, "ST_Perimeter", st_perimeter, set_f64);
-xfunc_blob2!(st_distance_xfunc, "ST_Distance", st_distance, set_f64);
-xfunc_blob!(
-    st_centroid_xfunc,
-    "ST_Centroid",
-    st_centroid,
-    set_blob_owned
-);
-xfunc_blob!(
-    st_pointonsurface_xfunc,
-    "ST_PointOnSurface",
-    st_point_on_surface,
-    set_blob_owned
-);
-xfunc_blob2!(
-    st_hausdorffdistance_xfunc,
-    "ST_HausdorffDistance",
-    st_hausdorff_distance,
-    set_f64
-);
-xfunc_blob_opt_f64!(st_xmin_xfunc, "ST_XMin", st_xmin);
-xfunc_blob_opt_f64!(st_xmax_xfunc, "ST_XMax", st_xmax);
-xfunc_blob_opt_f64!(st_ymin_xfunc, "ST_YMin", st_ymin);
-xfunc_blob_opt_f64!(st_ymax_xfunc, "ST_YMax", st_ymax);
-xfunc_blob2!(
-    st_distancesphere_xfunc,
-    "ST_DistanceSphere",
-    st_distance_sphere,
-    set_f64
-);
-xfunc_blob2!(
-    st_distancespheroid_xfunc,
-    "ST_DistanceSpheroid",
-    st_distance_spheroid,
-    set_f64
-);
-xfunc_blob!(
-    st_lengthsphere_xfunc,
-    "ST_LengthSphere",
-    st_length_sphere,
-    set_f64
-);
-xfunc_blob2!(st_azimuth_xfunc, "ST_Azimuth", st_azimuth, set_f64);
-
-xfunc_blob_f64_f64_blob!(
-    st_project_xfunc,
-    "ST_Project",
-    "distance",
-    "azimuth",
-    st_project
-);
-
-xfunc_blob2!(
-    st_closestpoint_xfunc,
-    "ST_ClosestPoint",
-    st_closest_point,
-    set_blob_owned
-);
-
-// Operation callbacks
-
-xfunc_blob2!(st_union_xfunc, "ST_Union", st_union, set_blob_owned);
-xfunc_blob2!(
-    st_intersection_xfunc,
-    "ST_Intersection",
-    st_intersection,
-    set_blob_owned
-);
-xfunc_blob2!(
-    st_difference_xfunc,
-    "ST_Difference",
-    st_difference,
-    set_blob_owned
-);
-xfunc_blob2!(
-    st_symdifference_xfunc,
-    "ST_SymDifference",
-    st_sym_difference,
-    set_blob_owned
-);
-
-xfunc_blob_f64_blob!(st_buffer_xfunc, "ST_Buffer", "distance", st_buffer);
-
-// Predicate callbacks
-
-xfunc_blob2!(
-    st_intersects_xfunc,
-    "ST_Intersects",
-    st_intersects,
-    set_bool
-);
-xfunc_blob2!(st_contains_xfunc, "ST_Contains", st_contains, set_bool);
-xfunc_blob2!(st_within_xfunc, "ST_Within", st_within, set_bool);
-xfunc_blob2!(st_disjoint_xfunc, "ST_Disjoint", st_disjoint, set_bool);
 
-xfunc_blob2_f64_bool!(st_dwithin_xfunc, "ST_DWithin", "distance", st_dwithin);
-xfunc_blob2_f64_bool!(
-    st_dwithinsphere_xfunc,
-    "ST_DWithinSphere",
-    "distance",
-    st_dwithin_sphere
-);
-xfunc_blob2_f64_bool!(
-    st_dwithinspheroid_xfunc,
-    "ST_DWithinSpheroid",
-    "distance",
-    st_dwithin_spheroid
-);
+unsafe fn st_astwkb_impl(ctx: *mut sqlite3_context, argv: *mut *mut sqlite3_value, n_arg: usize) {
+    let Some(geom) = require_blob_arg(ctx, argv, 0, "ST_AsTWKB") else {
+        return;
+    };
+    // Optional integer arguments in positional order; absent ones keep the
+    // PostGIS defaults (precision 0, no sizes, no boxes).
+    let mut values = [0i32; 5];
+    for (i, name) in [
+        "precision_xy",
+        "precision_z",
+        "precision_m",
+        "with_sizes",
+        "with_boxes",
+    ]
+    .into_iter()
+    .enumerate()
+    .take(n_arg - 1)
+    {
+        let Some(v) = require_i32_arg(ctx, argv, i + 1, "ST_AsTWKB", name) else {
+            return;
+        };
+        values[i] = v;
+    }
+    let [precision_xy, precision_z, precision_m, with_sizes, with_boxes] = values;
+
+    match as_twkb(
+        geom,
+        precision_xy,
+        precision_z,
+        precision_m,
+        with_sizes != 0,
+        with_boxes != 0,
+    ) {
+        Ok(v) => set_blob(ctx, &v),
+        Err(e) => set_gis_error(ctx, "ST_AsTWKB", culprit_arg(&e, &[1], None, None), &e),
+    }
+}
 
-xfunc_blob2!(st_covers_xfunc, "ST_Covers", st_covers, set_bool);
-xfunc_blob2!(st_coveredby_xfunc, "ST_CoveredBy", st_covered_by, set_bool);
-xfunc_blob2!(st_equals_xfunc, "ST_Equals", st_equals, set_bool);
-xfunc_blob2!(st_touches_xfunc, "ST_Touches", st_touches, set_bool);
-xfunc_blob2!(st_crosses_xfunc, "ST_Crosses", st_crosses, set_bool);
-xfunc_blob2!(st_overlaps_xfunc, "ST_Overlaps", st_overlaps, set_bool);
+unsafe extern "C" fn st_astwkb_1_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_AsTWKB", || st_astwkb_impl(ctx, argv, 1));
+}
 
-xfunc_blob2!(st_relate_2_xfunc, "ST_Relate", st_relate, set_text_owned);
+unsafe extern "C" fn st_astwkb_2_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_AsTWKB", || st_astwkb_impl(ctx, argv, 2));
+}
 
-xfunc_blob2_text_bool!(
-    st_relate_3_xfunc,
-    "ST_Relate",
-    "pattern",
-    st_relate_match_geoms
-);
-xfunc_text2_bool!(
-    st_relatematch_xfunc,
-    "ST_RelateMatch",
-    "matrix",
-    "pattern",
-    st_relate_match
-);
+unsafe extern "C" fn st_astwkb_3_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_AsTWKB", || st_astwkb_impl(ctx, argv, 3));
+}
 
-// Spatial index helpers
+unsafe extern "C" fn st_astwkb_4_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_AsTWKB", || st_astwkb_impl(ctx, argv, 4));
+}
 
-fn validate_identifier(s: &str) -> Option<&str> {
-    if s.is_empty() {
-        return None;
-    }
-    if s.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_') {
-        Some(s)
-    } else {
-        None
-    }
+unsafe extern "C" fn st_astwkb_5_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_AsTWKB", || st_astwkb_impl(ctx, argv, 5));
 }
 
-fn sql_to_cstring(sql: &str) -> std::result::Result<CString, std::ffi::NulError> {
-    CString::new(sql)
+unsafe extern "C" fn st_astwkb_6_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_AsTWKB", || st_astwkb_impl(ctx, argv, 6));
 }
 
-unsafe fn exec_sql_inner(db: *mut sqlite3, sql: &str, ctx: Option<*mut sqlite3_context>) -> c_int {
-    let c_sql = match sql_to_cstring(sql) {
-        Ok(v) => v,
-        Err(_) => {
-            if let Some(ctx) = ctx {
-                set_error(ctx, "internal error: generated SQL contains NUL byte");
-            }
-            return SQLITE_ERROR;
+unsafe extern "C" fn st_geomfromtwkb_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_GeomFromTWKB", || {
+        let Some(b) = get_raw_blob(argv, 0) else {
+            set_null(ctx);
+            return;
+        };
+        match geom_from_twkb(b) {
+            Ok(v) => set_blob(ctx, &v),
+            Err(e) => set_gis_error(ctx, "ST_GeomFromTWKB", Some(1), &e),
         }
-    };
+    });
+}
 
-    let mut err_msg: *mut std::ffi::c_char = std::ptr::null_mut();
-    let rc = sqlite3_exec(db, c_sql.as_ptr(), None, std::ptr::null_mut(), &mut err_msg);
+// Constructor callbacks
 
-    if rc != SQLITE_OK {
-        if let Some(ctx) = ctx {
-            if err_msg.is_null() {
-                set_error(ctx, "exec_sql failed");
-            } else {
-                let msg = CStr::from_ptr(err_msg).to_string_lossy();
-                set_error(ctx, &msg);
-            }
-        }
+unsafe fn st_point_impl(ctx: *mut sqlite3_context, argv: *mut *mut sqlite3_value, with_srid: bool) {
+    let arg_count = if with_srid { 3 } else { 2 };
+    if any_arg_is_null(argv, arg_count) {
+        set_null(ctx);
+        return;
     }
 
-    if !err_msg.is_null() {
-        sqlite3_free(err_msg.cast());
+    let Some(x) = require_f64_arg(ctx, argv, 0, "ST_Point", "x") else {
+        return;
+    };
+    let Some(y) = require_f64_arg(ctx, argv, 1, "ST_Point", "y") else {
+        return;
+    };
+    let Some(srid) = optional_srid_arg(ctx, argv, with_srid, 2, "ST_Point") else {
+        return;
+    };
+
+    match st_point(x, y, srid) {
+        Ok(v) => set_blob(ctx, &v),
+        Err(e) => set_gis_error(ctx, "ST_Point", None, &e),
     }
-    rc
 }
 
-/// Run SQL via `sqlite3_exec`, returning `SQLITE_OK` on success.
-/// On failure, sets `sqlite3_result_error` on `ctx` with the error message
-/// from SQLite and frees it via `sqlite3_free`.
-unsafe fn exec_sql(db: *mut sqlite3, ctx: *mut sqlite3_context, sql: &str) -> c_int {
-    exec_sql_inner(db, sql, Some(ctx))
+unsafe extern "C" fn st_point_2_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_Point", || {
+        st_point_impl(ctx, argv, false);
+    });
 }
 
-/// Run SQL via `sqlite3_exec` but never touch sqlite3_result_error.
-/// Used for best-effort rollback paths where the original error should win.
-unsafe fn exec_sql_silent(db: *mut sqlite3, sql: &str) -> c_int {
-    exec_sql_inner(db, sql, None)
+unsafe extern "C" fn st_point_3_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_Point", || {
+        st_point_impl(ctx, argv, true);
+    });
 }
 
-unsafe fn rollback_savepoint(db: *mut sqlite3, ctx: *mut sqlite3_context, savepoint: &str) {
-    let _ = ctx;
-    let _ = exec_sql_silent(db, &format!("ROLLBACK TO {savepoint}"));
-    let _ = exec_sql_silent(db, &format!("RELEASE {savepoint}"));
-}
+xfunc_blob2!(
+    st_makeline_xfunc,
+    "ST_MakeLine",
+    st_make_line,
+    set_blob_owned
+);
+xfunc_blob!(
+    st_makepolygon_xfunc,
+    "ST_MakePolygon",
+    st_make_polygon,
+    set_blob_owned
+);
 
-unsafe fn sqlite_master_lookup_text(
-    db: *mut sqlite3,
-    sql: &str,
-) -> std::result::Result<Option<String>, String> {
-    let c_sql = sql_to_cstring(sql)
-        .map_err(|_| "internal error: generated SQL contains NUL byte".to_string())?;
-    let mut stmt: *mut sqlite3_stmt = std::ptr::null_mut();
-    let rc = sqlite3_prepare_v2(db, c_sql.as_ptr(), -1, &mut stmt, std::ptr::null_mut());
-    if rc != SQLITE_OK {
-        return Err(CStr::from_ptr(sqlite3_errmsg(db))
-            .to_string_lossy()
-            .into_owned());
+unsafe fn st_makeenvelope_impl(
+    ctx: *mut sqlite3_context,
+    argv: *mut *mut sqlite3_value,
+    with_srid: bool,
+) {
+    let arg_count = if with_srid { 5 } else { 4 };
+    if any_arg_is_null(argv, arg_count) {
+        set_null(ctx);
+        return;
     }
 
-    let mut result = None;
-    let step = sqlite3_step(stmt);
-    if step == SQLITE_ROW {
-        if sqlite3_column_type(stmt, 0) != SQLITE_NULL {
-            let ptr = sqlite3_column_text(stmt, 0);
-            if !ptr.is_null() {
-                result = Some(CStr::from_ptr(ptr.cast()).to_string_lossy().into_owned());
-            }
-        }
-    } else if step != SQLITE_DONE {
-        let err = CStr::from_ptr(sqlite3_errmsg(db))
-            .to_string_lossy()
-            .into_owned();
-        let _ = sqlite3_finalize(stmt);
-        return Err(err);
-    }
+    let Some(xmin) = require_f64_arg(ctx, argv, 0, "ST_MakeEnvelope", "xmin") else {
+        return;
+    };
+    let Some(ymin) = require_f64_arg(ctx, argv, 1, "ST_MakeEnvelope", "ymin") else {
+        return;
+    };
+    let Some(xmax) = require_f64_arg(ctx, argv, 2, "ST_MakeEnvelope", "xmax") else {
+        return;
+    };
+    let Some(ymax) = require_f64_arg(ctx, argv, 3, "ST_MakeEnvelope", "ymax") else {
+        return;
+    };
+    let Some(srid) = optional_srid_arg(ctx, argv, with_srid, 4, "ST_MakeEnvelope") else {
+        return;
+    };
 
-    let _ = sqlite3_finalize(stmt);
-    Ok(result)
+    match st_make_envelope(xmin, ymin, xmax, ymax, srid) {
+        Ok(v) => set_blob(ctx, &v),
+        Err(e) => set_gis_error(ctx, "ST_MakeEnvelope", None, &e),
+    }
 }
 
-const SPATIAL_INDEX_CATALOG_TABLE: &str = "sqlitegis_spatial_index_catalog";
-const SPATIAL_INDEX_CATALOG_REQUIRED_COLUMNS: [&str; 3] = ["prefix", "table_name", "column_name"];
-
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-enum SpatialIndexOwnership {
-    Owned,
-    Absent,
+unsafe extern "C" fn st_makeenvelope_4_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_MakeEnvelope", || {
+        st_makeenvelope_impl(ctx, argv, false);
+    });
 }
 
-unsafe fn lookup_sqlite_master_object_type(
-    db: *mut sqlite3,
-    object_name: &str,
-) -> std::result::Result<Option<String>, String> {
-    let sql = format!("SELECT type FROM sqlite_master WHERE name = '{object_name}' LIMIT 1");
-    sqlite_master_lookup_text(db, &sql)
+unsafe extern "C" fn st_makeenvelope_5_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_MakeEnvelope", || {
+        st_makeenvelope_impl(ctx, argv, true);
+    });
 }
 
-unsafe fn inspect_spatial_index_catalog_columns(
-    db: *mut sqlite3,
-) -> std::result::Result<(bool, bool, bool), String> {
-    let sql = format!("PRAGMA table_info([{SPATIAL_INDEX_CATALOG_TABLE}])");
-    let c_sql = sql_to_cstring(&sql)
-        .map_err(|_| "internal error: generated SQL contains NUL byte".to_string())?;
-    let mut stmt: *mut sqlite3_stmt = std::ptr::null_mut();
-    let rc = sqlite3_prepare_v2(db, c_sql.as_ptr(), -1, &mut stmt, std::ptr::null_mut());
-    if rc != SQLITE_OK {
-        return Err(CStr::from_ptr(sqlite3_errmsg(db))
-            .to_string_lossy()
-            .into_owned());
-    }
+xfunc_blob2!(st_collect_xfunc, "ST_Collect", st_collect, set_blob_owned);
 
-    let mut has_prefix = false;
-    let mut has_table_name = false;
-    let mut has_column_name = false;
+unsafe extern "C" fn st_tileenvelope_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_TileEnvelope", || {
+        let Some(zoom_i32) = require_i32_arg(ctx, argv, 0, "ST_TileEnvelope", "zoom") else {
+            return;
+        };
+        let Some(tile_x_i32) = require_i32_arg(ctx, argv, 1, "ST_TileEnvelope", "tile x") else {
+            return;
+        };
+        let Some(tile_y_i32) = require_i32_arg(ctx, argv, 2, "ST_TileEnvelope", "tile y") else {
+            return;
+        };
 
-    loop {
-        let step = sqlite3_step(stmt);
-        if step == SQLITE_ROW {
-            if sqlite3_column_type(stmt, 1) != SQLITE_NULL {
-                let ptr = sqlite3_column_text(stmt, 1);
-                if !ptr.is_null() {
-                    let column_name = CStr::from_ptr(ptr.cast()).to_string_lossy();
-                    match column_name.as_ref() {
-                        "prefix" => has_prefix = true,
-                        "table_name" => has_table_name = true,
-                        "column_name" => has_column_name = true,
-                        _ => {}
-                    }
-                }
-            }
-            continue;
+        if zoom_i32 < 0 {
+            set_error(ctx, "ST_TileEnvelope: zoom must be non-negative");
+            return;
         }
-        if step == SQLITE_DONE {
-            break;
+        if tile_x_i32 < 0 {
+            set_error(ctx, "ST_TileEnvelope: tile x must be non-negative");
+            return;
+        }
+        if tile_y_i32 < 0 {
+            set_error(ctx, "ST_TileEnvelope: tile y must be non-negative");
+            return;
         }
-        let err = CStr::from_ptr(sqlite3_errmsg(db))
-            .to_string_lossy()
-            .into_owned();
-        let _ = sqlite3_finalize(stmt);
-        return Err(err);
-    }
 
-    let _ = sqlite3_finalize(stmt);
-    Ok((has_prefix, has_table_name, has_column_name))
+        let zoom = zoom_i32 as u32;
+        let tile_x = tile_x_i32 as u32;
+        let tile_y = tile_y_i32 as u32;
+        match st_tile_envelope(zoom, tile_x, tile_y) {
+            Ok(v) => set_blob(ctx, &v),
+            Err(e) => set_gis_error(ctx, "ST_TileEnvelope", None, &e),
+        }
+    });
 }
 
-unsafe fn validate_spatial_index_catalog_shape(
-    db: *mut sqlite3,
+/// Shared body of `ST_GeomFromGeoHash` and `ST_PointFromGeoHash`.
+unsafe fn geohash_constructor_impl(
     ctx: *mut sqlite3_context,
+    argv: *mut *mut sqlite3_value,
+    n_arg: usize,
     label: &str,
-) -> bool {
-    let object_type = match lookup_sqlite_master_object_type(db, SPATIAL_INDEX_CATALOG_TABLE) {
-        Ok(v) => v,
-        Err(e) => {
-            set_error(
-                ctx,
-                &format!("{label}: failed to inspect spatial index catalog metadata: {e}"),
-            );
-            return false;
-        }
+    build: fn(&str, Option<i32>) -> Result<Vec<u8>, SqliteGisError>,
+) {
+    let Some(hash) = require_text_arg(ctx, argv, 0, label, "geohash") else {
+        return;
     };
-    let Some(object_type) = object_type else {
-        set_error(
-            ctx,
-            &format!(
-                "{label}: failed to inspect spatial index catalog metadata: \
-                 missing sqlite_master entry for [{SPATIAL_INDEX_CATALOG_TABLE}]"
-            ),
-        );
-        return false;
+    let precision = if n_arg > 1 {
+        let Some(precision) = require_i32_arg(ctx, argv, 1, label, "precision") else {
+            return;
+        };
+        Some(precision)
+    } else {
+        None
     };
-    if object_type != "table" {
-        set_error(
-            ctx,
-            &format!(
-                "{label}: invalid spatial index catalog object type for \
-                 [{SPATIAL_INDEX_CATALOG_TABLE}] (expected table, found [{object_type}])"
-            ),
-        );
-        return false;
+    match build(hash, precision) {
+        Ok(v) => set_blob(ctx, &v),
+        Err(e) => set_gis_error(ctx, label, None, &e),
     }
+}
 
-    let (has_prefix, has_table_name, has_column_name) =
-        match inspect_spatial_index_catalog_columns(db) {
-            Ok(v) => v,
-            Err(e) => {
-                set_error(
-                    ctx,
-                    &format!("{label}: failed to inspect spatial index catalog metadata: {e}"),
-                );
-                return false;
-            }
-        };
+unsafe extern "C" fn st_geomfromgeohash_1_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_GeomFromGeoHash", || {
+        geohash_constructor_impl(ctx, argv, 1, "ST_GeomFromGeoHash", st_geom_from_geohash)
+    });
+}
 
-    let present = [has_prefix, has_table_name, has_column_name];
-    for (i, required_column) in SPATIAL_INDEX_CATALOG_REQUIRED_COLUMNS.iter().enumerate() {
-        if !present[i] {
-            set_error(
-                ctx,
-                &format!(
-                    "{label}: invalid spatial index catalog schema for \
-                     [{SPATIAL_INDEX_CATALOG_TABLE}] (missing required column [{required_column}])"
-                ),
-            );
-            return false;
-        }
-    }
+unsafe extern "C" fn st_geomfromgeohash_2_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_GeomFromGeoHash", || {
+        geohash_constructor_impl(ctx, argv, 2, "ST_GeomFromGeoHash", st_geom_from_geohash)
+    });
+}
 
-    true
+unsafe extern "C" fn st_pointfromgeohash_1_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_PointFromGeoHash", || {
+        geohash_constructor_impl(ctx, argv, 1, "ST_PointFromGeoHash", st_point_from_geohash)
+    });
 }
 
-unsafe fn ensure_spatial_index_catalog_table(
-    db: *mut sqlite3,
+unsafe extern "C" fn st_pointfromgeohash_2_xfunc(
     ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_PointFromGeoHash", || {
+        geohash_constructor_impl(ctx, argv, 2, "ST_PointFromGeoHash", st_point_from_geohash)
+    });
+}
+
+// Accessor callbacks
+
+xfunc_blob!(st_srid_xfunc, "ST_SRID", st_srid, set_i32);
+
+unsafe extern "C" fn st_setsrid_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_SetSRID", || {
+        let Some(b) = require_blob_arg(ctx, argv, 0, "ST_SetSRID") else {
+            return;
+        };
+        let Some(srid) = require_i32_arg(ctx, argv, 1, "ST_SetSRID", "srid") else {
+            return;
+        };
+        match st_set_srid(b, srid) {
+            Ok(v) => set_blob(ctx, &v),
+            Err(e) => set_gis_error(ctx, "ST_SetSRID", culprit_arg(&e, &[1], None, None), &e),
+        }
+    });
+}
+
+xfunc_blob!(
+    st_geometrytype_xfunc,
+    "ST_GeometryType",
+    st_geometry_type,
+    set_text_owned
+);
+xfunc_blob!(st_ndims_xfunc, "ST_NDims", st_ndims, set_i32);
+xfunc_blob!(st_coorddim_xfunc, "ST_CoordDim", st_coord_dim, set_i32);
+xfunc_blob!(st_zmflag_xfunc, "ST_Zmflag", st_zmflag, set_i32);
+xfunc_blob!(st_isempty_xfunc, "ST_IsEmpty", st_is_empty, set_bool);
+xfunc_blob!(st_memsize_xfunc, "ST_MemSize", st_mem_size, set_i64);
+xfunc_blob_opt_f64!(st_x_xfunc, "ST_X", st_x);
+xfunc_blob_opt_f64!(st_y_xfunc, "ST_Y", st_y);
+xfunc_blob_opt_f64!(st_z_xfunc, "ST_Z", st_z);
+
+xfunc_blob!(st_numpoints_xfunc, "ST_NumPoints", st_num_points, set_i32);
+xfunc_blob!(st_npoints_xfunc, "ST_NPoints", st_npoints, set_i32);
+xfunc_blob!(
+    st_numgeometries_xfunc,
+    "ST_NumGeometries",
+    st_num_geometries,
+    set_i32
+);
+xfunc_blob!(
+    st_numinteriorrings_xfunc,
+    "ST_NumInteriorRings",
+    st_num_interior_rings,
+    set_i32
+);
+xfunc_blob!(st_numrings_xfunc, "ST_NumRings", st_num_rings, set_i32);
+xfunc_blob_i32_blob!(st_pointn_xfunc, "ST_PointN", "n", |b, n| st_point_n(
+    b, n, None
+));
+
+xfunc_blob!(
+    st_startpoint_xfunc,
+    "ST_StartPoint",
+    st_start_point,
+    set_blob_owned
+);
+xfunc_blob!(
+    st_endpoint_xfunc,
+    "ST_EndPoint",
+    st_end_point,
+    set_blob_owned
+);
+xfunc_blob!(
+    st_exteriorring_xfunc,
+    "ST_ExteriorRing",
+    st_exterior_ring,
+    set_blob_owned
+);
+xfunc_blob_i32_blob!(
+    st_interiorringn_xfunc,
+    "ST_InteriorRingN",
+    "n",
+    st_interior_ring_n
+);
+xfunc_blob_i32_blob!(st_geometryn_xfunc, "ST_GeometryN", "n", st_geometry_n);
+
+xfunc_blob!(st_dimension_xfunc, "ST_Dimension", st_dimension, set_i32);
+xfunc_blob!(
+    st_envelope_xfunc,
+    "ST_Envelope",
+    st_envelope,
+    set_blob_owned
+);
+xfunc_blob!(st_isvalid_xfunc, "ST_IsValid", st_is_valid, set_bool);
+xfunc_blob!(
+    st_isvalidreason_xfunc,
+    "ST_IsValidReason",
+    st_is_valid_reason,
+    set_text_owned
+);
+
+// Measurement callbacks
+
+xfunc_blob!(st_area_xfunc, "ST_Area", st_area, set_f64);
+xfunc_blob!(st_length_xfunc, "ST_Length", st_length, set_f64);
+xfunc_blob!(st_perimeter_xfunc, "ST_Perimeter", st_perimeter, set_f64);
+xfunc_blob2!(st_distance_xfunc, "ST_Distance", st_distance, set_f64);
+xfunc_blob!(
+    st_centroid_xfunc,
+    "ST_Centroid",
+    st_centroid,
+    set_blob_owned
+);
+xfunc_blob!(
+    st_pointonsurface_xfunc,
+    "ST_PointOnSurface",
+    st_point_on_surface,
+    set_blob_owned
+);
+xfunc_blob2_interruptible!(
+    st_hausdorffdistance_xfunc,
+    "ST_HausdorffDistance",
+    st_hausdorff_distance_interruptible,
+    set_f64
+);
+xfunc_blob_opt_f64!(st_xmin_xfunc, "ST_XMin", st_xmin);
+xfunc_blob_opt_f64!(st_xmax_xfunc, "ST_XMax", st_xmax);
+xfunc_blob_opt_f64!(st_ymin_xfunc, "ST_YMin", st_ymin);
+xfunc_blob_opt_f64!(st_ymax_xfunc, "ST_YMax", st_ymax);
+xfunc_blob2!(
+    st_distancesphere_xfunc,
+    "ST_DistanceSphere",
+    st_distance_sphere,
+    set_f64
+);
+xfunc_blob2!(
+    st_distancespheroid_xfunc,
+    "ST_DistanceSpheroid",
+    st_distance_spheroid,
+    set_f64
+);
+xfunc_blob!(
+    st_lengthsphere_xfunc,
+    "ST_LengthSphere",
+    st_length_sphere,
+    set_f64
+);
+xfunc_blob2!(st_azimuth_xfunc, "ST_Azimuth", st_azimuth, set_f64);
+
+xfunc_blob_f64_f64_blob!(
+    st_project_xfunc,
+    "ST_Project",
+    "distance",
+    "azimuth",
+    st_project
+);
+
+xfunc_blob2!(
+    st_closestpoint_xfunc,
+    "ST_ClosestPoint",
+    st_closest_point,
+    set_blob_owned
+);
+
+// Operation callbacks
+
+xfunc_blob2_interruptible!(
+    st_union_xfunc,
+    "ST_Union",
+    st_union_interruptible,
+    set_blob_owned
+);
+xfunc_blob2_interruptible!(
+    st_intersection_xfunc,
+    "ST_Intersection",
+    st_intersection_interruptible,
+    set_blob_owned
+);
+xfunc_blob2_interruptible!(
+    st_difference_xfunc,
+    "ST_Difference",
+    st_difference_interruptible,
+    set_blob_owned
+);
+xfunc_blob2_interruptible!(
+    st_symdifference_xfunc,
+    "ST_SymDifference",
+    st_sym_difference_interruptible,
+    set_blob_owned
+);
+
+xfunc_blob_f64_blob_interruptible!(
+    st_buffer_xfunc,
+    "ST_Buffer",
+    "distance",
+    st_buffer_interruptible
+);
+
+// Predicate callbacks
+
+xfunc_blob2!(
+    st_intersects_xfunc,
+    "ST_Intersects",
+    st_intersects,
+    set_bool
+);
+xfunc_blob2!(st_contains_xfunc, "ST_Contains", st_contains, set_bool);
+xfunc_blob2!(st_within_xfunc, "ST_Within", st_within, set_bool);
+xfunc_blob2!(st_disjoint_xfunc, "ST_Disjoint", st_disjoint, set_bool);
+
+xfunc_blob2_f64_bool!(st_dwithin_xfunc, "ST_DWithin", "distance", st_dwithin);
+xfunc_blob2_f64_bool!(
+    st_dwithinsphere_xfunc,
+    "ST_DWithinSphere",
+    "distance",
+    st_dwithin_sphere
+);
+xfunc_blob2_f64_bool!(
+    st_dwithinspheroid_xfunc,
+    "ST_DWithinSpheroid",
+    "distance",
+    st_dwithin_spheroid
+);
+
+xfunc_blob2!(st_covers_xfunc, "ST_Covers", st_covers, set_bool);
+xfunc_blob2!(st_coveredby_xfunc, "ST_CoveredBy", st_covered_by, set_bool);
+xfunc_blob2!(st_equals_xfunc, "ST_Equals", st_equals, set_bool);
+xfunc_blob2!(st_touches_xfunc, "ST_Touches", st_touches, set_bool);
+xfunc_blob2!(st_crosses_xfunc, "ST_Crosses", st_crosses, set_bool);
+xfunc_blob2!(st_overlaps_xfunc, "ST_Overlaps", st_overlaps, set_bool);
+
+xfunc_blob2!(st_relate_2_xfunc, "ST_Relate", st_relate, set_text_owned);
+
+xfunc_blob2_text_bool!(
+    st_relate_3_xfunc,
+    "ST_Relate",
+    "pattern",
+    st_relate_match_geoms
+);
+xfunc_text2_bool!(
+    st_relatematch_xfunc,
+    "ST_RelateMatch",
+    "matrix",
+    "pattern",
+    st_relate_match
+);
+
+// Vector tile callbacks
+
+unsafe fn st_asmvtgeom_impl(
+    ctx: *mut sqlite3_context,
+    argv: *mut *mut sqlite3_value,
+    n_arg: usize,
+) {
+    let Some(geom) = require_blob_arg(ctx, argv, 0, "ST_AsMVTGeom") else {
+        return;
+    };
+    let Some(bounds) = require_blob_arg(ctx, argv, 1, "ST_AsMVTGeom") else {
+        return;
+    };
+    let extent = if n_arg > 2 {
+        let Some(extent) = require_i32_arg(ctx, argv, 2, "ST_AsMVTGeom", "extent") else {
+            return;
+        };
+        if extent <= 0 {
+            set_error(ctx, "ST_AsMVTGeom: extent must be positive");
+            return;
+        }
+        extent as u32
+    } else {
+        DEFAULT_EXTENT
+    };
+    let buffer = if n_arg > 3 {
+        let Some(buffer) = require_i32_arg(ctx, argv, 3, "ST_AsMVTGeom", "buffer") else {
+            return;
+        };
+        if buffer < 0 {
+            set_error(ctx, "ST_AsMVTGeom: buffer must be non-negative");
+            return;
+        }
+        buffer as u32
+    } else {
+        DEFAULT_BUFFER
+    };
+    let clip = if n_arg > 4 {
+        let Some(clip) = require_i32_arg(ctx, argv, 4, "ST_AsMVTGeom", "clip") else {
+            return;
+        };
+        clip != 0
+    } else {
+        true
+    };
+
+    match track_failed_operand(|| st_as_mvt_geom(geom, bounds, extent, buffer, clip)) {
+        (Ok(Some(v)), _) => set_blob(ctx, &v),
+        (Ok(None), _) => set_null(ctx),
+        (Err(e), operand) => set_gis_error(
+            ctx,
+            "ST_AsMVTGeom",
+            culprit_arg(&e, &[1, 2], None, operand),
+            &e,
+        ),
+    }
+}
+
+unsafe extern "C" fn st_asmvtgeom_2_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_AsMVTGeom", || st_asmvtgeom_impl(ctx, argv, 2));
+}
+
+unsafe extern "C" fn st_asmvtgeom_3_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_AsMVTGeom", || st_asmvtgeom_impl(ctx, argv, 3));
+}
+
+unsafe extern "C" fn st_asmvtgeom_4_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_AsMVTGeom", || st_asmvtgeom_impl(ctx, argv, 4));
+}
+
+unsafe extern "C" fn st_asmvtgeom_5_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_AsMVTGeom", || st_asmvtgeom_impl(ctx, argv, 5));
+}
+
+/// Per-group state of an aggregate (`ST_AsMVT`, `ST_AsGeoJSONFeatureCollection`),
+/// kept in SQLite's aggregate context. The zero-filled allocation SQLite
+/// hands out reads as `None` until the first non-NULL row creates the state.
+type AggregateState<T> = Option<Box<T>>;
+
+/// The calling group's aggregate state. With `allocate` false this never
+/// allocates and returns `None` for a group that saw no rows.
+unsafe fn aggregate_state<'a, T>(
+    ctx: *mut sqlite3_context,
+    allocate: bool,
+) -> Option<&'a mut AggregateState<T>> {
+    let size = if allocate {
+        std::mem::size_of::<AggregateState<T>>() as c_int
+    } else {
+        0
+    };
+    (sqlite3_aggregate_context(ctx, size) as *mut AggregateState<T>).as_mut()
+}
+
+/// Read an optional text argument of `ST_AsMVT`, falling back to `default`
+/// when it is absent or NULL.
+unsafe fn mvt_text_arg<'a>(
+    ctx: *mut sqlite3_context,
+    argv: *mut *mut sqlite3_value,
+    n_arg: usize,
+    i: usize,
+    arg_name: &str,
+    default: &'a str,
+) -> Option<&'a str> {
+    if i >= n_arg {
+        return Some(default);
+    }
+    match get_text(argv, i) {
+        SqlTextArg::Value(v) => Some(v),
+        SqlTextArg::Null => Some(default),
+        SqlTextArg::InvalidUtf8 => {
+            set_error(
+                ctx,
+                &format!("ST_AsMVT: {arg_name} must be valid UTF-8 text"),
+            );
+            None
+        }
+    }
+}
+
+/// `ST_AsMVT(row, name[, extent[, geom_column]])` step: add one JSON row to
+/// the group's layer. NULL rows are skipped; the layer name and extent are
+/// taken from the first non-NULL row, and NULL for either means the PostGIS
+/// default (`'default'`, 4096).
+unsafe extern "C" fn st_asmvt_step(
+    ctx: *mut sqlite3_context,
+    n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "ST_AsMVT", || {
+        let n_arg = n as usize;
+        let row = match get_text(argv, 0) {
+            SqlTextArg::Value(v) => v,
+            SqlTextArg::Null => return,
+            SqlTextArg::InvalidUtf8 => {
+                set_error(ctx, "ST_AsMVT: row must be valid UTF-8 text");
+                return;
+            }
+        };
+        let Some(state) = aggregate_state::<MvtLayer>(ctx, true) else {
+            sqlite3_result_error_nomem(ctx);
+            return;
+        };
+        let layer = match state {
+            Some(layer) => layer,
+            None => {
+                let Some(name) = mvt_text_arg(ctx, argv, n_arg, 1, "layer name", "default") else {
+                    return;
+                };
+                let extent = if n_arg > 2 {
+                    match get_i32_arg(argv, 2) {
+                        SqlI32Arg::Null => DEFAULT_EXTENT,
+                        SqlI32Arg::Value(v) if v > 0 => v as u32,
+                        SqlI32Arg::Value(_) | SqlI32Arg::OutOfRange(_) => {
+                            set_error(ctx, "ST_AsMVT: extent must be positive");
+                            return;
+                        }
+                        SqlI32Arg::InvalidType => {
+                            set_error(ctx, "ST_AsMVT: extent must be integer");
+                            return;
+                        }
+                    }
+                } else {
+                    DEFAULT_EXTENT
+                };
+                state.insert(Box::new(MvtLayer::new(name, extent)))
+            }
+        };
+        let Some(geom_column) = mvt_text_arg(ctx, argv, n_arg, 3, "geometry column", "geom") else {
+            return;
+        };
+        if let Err(e) = layer.add_json_row(row, geom_column) {
+            set_gis_error(ctx, "ST_AsMVT", Some(1), &e);
+        }
+    });
+}
+
+/// `ST_AsMVT` final: encode the group's layer as a single-layer tile, or
+/// NULL when no row produced a feature.
+unsafe extern "C" fn st_asmvt_final(ctx: *mut sqlite3_context) {
+    xfunc_guard(ctx, "ST_AsMVT", || {
+        match aggregate_state::<MvtLayer>(ctx, false).and_then(Option::take) {
+            Some(layer) if !layer.is_empty() => set_blob_owned(ctx, layer.encode()),
+            _ => set_null(ctx),
+        }
+    });
+}
+
+/// `ST_AsGeoJSONFeatureCollection(feature)` /
+/// `ST_AsGeoJSONFeatureCollection(properties, geom[, maxdecimaldigits])`
+/// step: add one Feature to the group's collection. The one-argument form
+/// takes Feature text (as `ST_AsGeoJSON(properties, geom)` writes it) or a
+/// bare geometry; rows with a NULL Feature or geometry are skipped.
+unsafe extern "C" fn st_asgeojsonfeaturecollection_step(
+    ctx: *mut sqlite3_context,
+    n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    let label = "ST_AsGeoJSONFeatureCollection";
+    xfunc_guard(ctx, label, || {
+        let n_arg = n as usize;
+        let feature = if n_arg == 1 && sqlite3_value_type(*argv) == SQLITE_TEXT {
+            let Some(text) = require_text_arg(ctx, argv, 0, label, "feature") else {
+                return;
+            };
+            Ok(Cow::Borrowed(text))
+        } else {
+            let (properties, geom_index) = if n_arg == 1 {
+                (None, 0)
+            } else {
+                match get_text(argv, 0) {
+                    SqlTextArg::Value(v) => (Some(v), 1),
+                    SqlTextArg::Null => (None, 1),
+                    SqlTextArg::InvalidUtf8 => {
+                        set_error(
+                            ctx,
+                            &format!("{label}: properties must be valid UTF-8 text"),
+                        );
+                        return;
+                    }
+                }
+            };
+            let geom = match get_blob(argv, geom_index) {
+                Ok(Some(geom)) => geom,
+                Ok(None) => return,
+                Err(e) => {
+                    set_gis_error(ctx, label, Some(geom_index + 1), &e);
+                    return;
+                }
+            };
+            let digits = if n_arg > 2 {
+                let Some(digits) = require_i32_arg(ctx, argv, 2, label, "maxdecimaldigits") else {
+                    return;
+                };
+                Some(digits)
+            } else {
+                None
+            };
+            as_geojson_feature(properties, geom, digits).map(Cow::Owned)
+        };
+        let Some(state) = aggregate_state::<GeoJsonFeatureCollection>(ctx, true) else {
+            sqlite3_result_error_nomem(ctx);
+            return;
+        };
+        let collection = state.get_or_insert_with(Box::default);
+        if let Err(e) = feature.and_then(|feature| collection.add_feature(&feature)) {
+            set_gis_error(ctx, label, Some(1), &e);
+        }
+    });
+}
+
+/// `ST_AsGeoJSONFeatureCollection` final: write the group's collection, or
+/// NULL when no row contributed a Feature.
+unsafe extern "C" fn st_asgeojsonfeaturecollection_final(ctx: *mut sqlite3_context) {
+    xfunc_guard(
+        ctx,
+        "ST_AsGeoJSONFeatureCollection",
+        || match aggregate_state::<GeoJsonFeatureCollection>(ctx, false).and_then(Option::take) {
+            Some(collection) if !collection.is_empty() => set_json(ctx, &collection.finish()),
+            _ => set_null(ctx),
+        },
+    );
+}
+
+// Spatial index helpers
+
+pub(super) fn validate_identifier(s: &str) -> Option<&str> {
+    if s.is_empty() {
+        return None;
+    }
+    if s.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_') {
+        Some(s)
+    } else {
+        None
+    }
+}
+
+fn sql_to_cstring(sql: &str) -> std::result::Result<CString, std::ffi::NulError> {
+    CString::new(sql)
+}
+
+pub(super) unsafe fn exec_sql_inner(
+    db: *mut sqlite3,
+    sql: &str,
+    ctx: Option<*mut sqlite3_context>,
+) -> c_int {
+    let c_sql = match sql_to_cstring(sql) {
+        Ok(v) => v,
+        Err(_) => {
+            if let Some(ctx) = ctx {
+                set_error(ctx, "internal error: generated SQL contains NUL byte");
+            }
+            return SQLITE_ERROR;
+        }
+    };
+
+    let mut err_msg: *mut std::ffi::c_char = std::ptr::null_mut();
+    let rc = sqlite3_exec(db, c_sql.as_ptr(), None, std::ptr::null_mut(), &mut err_msg);
+
+    if rc != SQLITE_OK {
+        if let Some(ctx) = ctx {
+            if err_msg.is_null() {
+                set_error(ctx, "exec_sql failed");
+            } else {
+                let msg = CStr::from_ptr(err_msg).to_string_lossy();
+                set_error(ctx, &msg);
+            }
+        }
+    }
+
+    if !err_msg.is_null() {
+        sqlite3_free(err_msg.cast());
+    }
+    rc
+}
+
+/// Run SQL via `sqlite3_exec`, returning `SQLITE_OK` on success.
+/// On failure, sets `sqlite3_result_error` on `ctx` with the error message
+/// from SQLite and frees it via `sqlite3_free`.
+unsafe fn exec_sql(db: *mut sqlite3, ctx: *mut sqlite3_context, sql: &str) -> c_int {
+    exec_sql_inner(db, sql, Some(ctx))
+}
+
+/// Run SQL via `sqlite3_exec` but never touch sqlite3_result_error.
+/// Used for best-effort rollback paths where the original error should win.
+unsafe fn exec_sql_silent(db: *mut sqlite3, sql: &str) -> c_int {
+    exec_sql_inner(db, sql, None)
+}
+
+unsafe fn rollback_savepoint(db: *mut sqlite3, ctx: *mut sqlite3_context, savepoint: &str) {
+    let _ = ctx;
+    let _ = exec_sql_silent(db, &format!("ROLLBACK TO {savepoint}"));
+    let _ = exec_sql_silent(db, &format!("RELEASE {savepoint}"));
+}
+
+unsafe fn sqlite_master_lookup_text(
+    db: *mut sqlite3,
+    sql: &str,
+) -> std::result::Result<Option<String>, String> {
+    let c_sql = sql_to_cstring(sql)
+        .map_err(|_| "internal error: generated SQL contains NUL byte".to_string())?;
+    let mut stmt: *mut sqlite3_stmt = std::ptr::null_mut();
+    let rc = sqlite3_prepare_v2(db, c_sql.as_ptr(), -1, &mut stmt, std::ptr::null_mut());
+    if rc != SQLITE_OK {
+        return Err(CStr::from_ptr(sqlite3_errmsg(db))
+            .to_string_lossy()
+            .into_owned());
+    }
+
+    let mut result = None;
+    let step = sqlite3_step(stmt);
+    if step == SQLITE_ROW {
+        if sqlite3_column_type(stmt, 0) != SQLITE_NULL {
+            let ptr = sqlite3_column_text(stmt, 0);
+            if !ptr.is_null() {
+                result = Some(CStr::from_ptr(ptr.cast()).to_string_lossy().into_owned());
+            }
+        }
+    } else if step != SQLITE_DONE {
+        let err = CStr::from_ptr(sqlite3_errmsg(db))
+            .to_string_lossy()
+            .into_owned();
+        let _ = sqlite3_finalize(stmt);
+        return Err(err);
+    }
+
+    let _ = sqlite3_finalize(stmt);
+    Ok(result)
+}
+
+pub(super) const SPATIAL_INDEX_CATALOG_TABLE: &str = "sqlitegis_spatial_index_catalog";
+const SPATIAL_INDEX_CATALOG_REQUIRED_COLUMNS: [&str; 3] = ["prefix", "table_name", "column_name"];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum SpatialIndexOwnership {
+    Owned,
+    Absent,
+}
+
+unsafe fn lookup_sqlite_master_object_type(
+    db: *mut sqlite3,
+    object_name: &str,
+) -> std::result::Result<Option<String>, String> {
+    let sql = format!("SELECT type FROM sqlite_master WHERE name = '{object_name}' LIMIT 1");
+    sqlite_master_lookup_text(db, &sql)
+}
+
+unsafe fn inspect_spatial_index_catalog_columns(
+    db: *mut sqlite3,
+) -> std::result::Result<(bool, bool, bool), String> {
+    let sql = format!("PRAGMA table_info([{SPATIAL_INDEX_CATALOG_TABLE}])");
+    let c_sql = sql_to_cstring(&sql)
+        .map_err(|_| "internal error: generated SQL contains NUL byte".to_string())?;
+    let mut stmt: *mut sqlite3_stmt = std::ptr::null_mut();
+    let rc = sqlite3_prepare_v2(db, c_sql.as_ptr(), -1, &mut stmt, std::ptr::null_mut());
+    if rc != SQLITE_OK {
+        return Err(CStr::from_ptr(sqlite3_errmsg(db))
+            .to_string_lossy()
+            .into_owned());
+    }
+
+    let mut has_prefix = false;
+    let mut has_table_name = false;
+    let mut has_column_name = false;
+
+    loop {
+        let step = sqlite3_step(stmt);
+        if step == SQLITE_ROW {
+            if sqlite3_column_type(stmt, 1) != SQLITE_NULL {
+                let ptr = sqlite3_column_text(stmt, 1);
+                if !ptr.is_null() {
+                    let column_name = CStr::from_ptr(ptr.cast()).to_string_lossy();
+                    match column_name.as_ref() {
+                        "prefix" => has_prefix = true,
+                        "table_name" => has_table_name = true,
+                        "column_name" => has_column_name = true,
+                        _ => {}
+                    }
+                }
+            }
+            continue;
+        }
+        if step == SQLITE_DONE {
+            break;
+        }
+        let err = CStr::from_ptr(sqlite3_errmsg(db))
+            .to_string_lossy()
+            .into_owned();
+        let _ = sqlite3_finalize(stmt);
+        return Err(err);
+    }
+
+    let _ = sqlite3_finalize(stmt);
+    Ok((has_prefix, has_table_name, has_column_name))
+}
+
+unsafe fn validate_spatial_index_catalog_shape(
+    db: *mut sqlite3,
+    ctx: *mut sqlite3_context,
+    label: &str,
+) -> bool {
+    let object_type = match lookup_sqlite_master_object_type(db, SPATIAL_INDEX_CATALOG_TABLE) {
+        Ok(v) => v,
+        Err(e) => {
+            set_error(
+                ctx,
+                &format!("{label}: failed to inspect spatial index catalog metadata: {e}"),
+            );
+            return false;
+        }
+    };
+    let Some(object_type) = object_type else {
+        set_error(
+            ctx,
+            &format!(
+                "{label}: failed to inspect spatial index catalog metadata: \
+                 missing sqlite_master entry for [{SPATIAL_INDEX_CATALOG_TABLE}]"
+            ),
+        );
+        return false;
+    };
+    if object_type != "table" {
+        set_error(
+            ctx,
+            &format!(
+                "{label}: invalid spatial index catalog object type for \
+                 [{SPATIAL_INDEX_CATALOG_TABLE}] (expected table, found [{object_type}])"
+            ),
+        );
+        return false;
+    }
+
+    let (has_prefix, has_table_name, has_column_name) =
+        match inspect_spatial_index_catalog_columns(db) {
+            Ok(v) => v,
+            Err(e) => {
+                set_error(
+                    ctx,
+                    &format!("{label}: failed to inspect spatial index catalog metadata: {e}"),
+                );
+                return false;
+            }
+        };
+
+    let present = [has_prefix, has_table_name, has_column_name];
+    for (i, required_column) in SPATIAL_INDEX_CATALOG_REQUIRED_COLUMNS.iter().enumerate() {
+        if !present[i] {
+            set_error(
+                ctx,
+                &format!(
+                    "{label}: invalid spatial index catalog schema for \
+                     [{SPATIAL_INDEX_CATALOG_TABLE}] (missing required column [{required_column}])"
+                ),
+            );
+            return false;
+        }
+    }
+
+    true
+}
+
+unsafe fn ensure_spatial_index_catalog_table(
+    db: *mut sqlite3,
+    ctx: *mut sqlite3_context,
+    label: &str,
+) -> bool {
+    let object_type = match lookup_sqlite_master_object_type(db, SPATIAL_INDEX_CATALOG_TABLE) {
+        Ok(v) => v,
+        Err(e) => {
+            set_error(
+                ctx,
+                &format!("{label}: failed to inspect spatial index catalog metadata: {e}"),
+            );
+            return false;
+        }
+    };
+    if let Some(object_type) = object_type {
+        if object_type != "table" {
+            set_error(
+                ctx,
+                &format!(
+                    "{label}: invalid spatial index catalog object type for \
+                     [{SPATIAL_INDEX_CATALOG_TABLE}] (expected table, found [{object_type}])"
+                ),
+            );
+            return false;
+        }
+    }
+
+    let sql = format!(
+        "CREATE TABLE IF NOT EXISTS [{SPATIAL_INDEX_CATALOG_TABLE}] (\
+         prefix TEXT PRIMARY KEY, \
+         table_name TEXT NOT NULL, \
+         column_name TEXT NOT NULL, \
+         UNIQUE(table_name, column_name)\
+         )"
+    );
+    if exec_sql_silent(db, &sql) == SQLITE_OK {
+        return true;
+    }
+
+    let err = CStr::from_ptr(sqlite3_errmsg(db))
+        .to_string_lossy()
+        .into_owned();
+    set_error(
+        ctx,
+        &format!("{label}: failed to ensure spatial index catalog: {err}"),
+    );
+    false
+}
+
+unsafe fn lookup_spatial_index_catalog_owner(
+    db: *mut sqlite3,
+    prefix: &str,
+) -> std::result::Result<Option<(String, String)>, String> {
+    let sql = format!(
+        "SELECT table_name FROM [{SPATIAL_INDEX_CATALOG_TABLE}] \
+         WHERE prefix = '{prefix}' LIMIT 1"
+    );
+    let owner_table = sqlite_master_lookup_text(db, &sql)?;
+    let Some(owner_table) = owner_table else {
+        return Ok(None);
+    };
+
+    let sql = format!(
+        "SELECT column_name FROM [{SPATIAL_INDEX_CATALOG_TABLE}] \
+         WHERE prefix = '{prefix}' LIMIT 1"
+    );
+    let owner_column = sqlite_master_lookup_text(db, &sql)?;
+    let Some(owner_column) = owner_column else {
+        return Err(format!(
+            "internal error: catalog row for prefix [{prefix}] is missing column_name"
+        ));
+    };
+    Ok(Some((owner_table, owner_column)))
+}
+
+unsafe fn managed_spatial_index_objects_exist(
+    db: *mut sqlite3,
+    prefix: &str,
+) -> std::result::Result<bool, String> {
+    let rtree_name = format!("{prefix}_rtree");
+    let sql = format!(
+        "SELECT name FROM sqlite_master WHERE name IN (\
+         '{rtree_name}', \
+         '{rtree_name}_node', \
+         '{rtree_name}_parent', \
+         '{rtree_name}_rowid', \
+         '{prefix}_insert', \
+         '{prefix}_update', \
+         '{prefix}_delete'\
+         ) LIMIT 1"
+    );
+    Ok(sqlite_master_lookup_text(db, &sql)?.is_some())
+}
+
+unsafe fn ensure_spatial_index_table_shape(
+    db: *mut sqlite3,
+    ctx: *mut sqlite3_context,
+    prefix: &str,
     label: &str,
 ) -> bool {
-    let object_type = match lookup_sqlite_master_object_type(db, SPATIAL_INDEX_CATALOG_TABLE) {
+    // The managed object `{prefix}_rtree` must be either absent or a real
+    // SQLite table backed by the expected R-tree shadow tables.
+    let rtree_name = format!("{prefix}_rtree");
+    let sql = format!("SELECT type FROM sqlite_master WHERE name = '{rtree_name}' LIMIT 1");
+    let object_type = match sqlite_master_lookup_text(db, &sql) {
         Ok(v) => v,
         Err(e) => {
             set_error(
                 ctx,
-                &format!("{label}: failed to inspect spatial index catalog metadata: {e}"),
+                &format!("{label}: failed to inspect sqlite_master: {e}"),
+            );
+            return false;
+        }
+    };
+    if let Some(object_type) = object_type {
+        if object_type != "table" {
+            set_error(
+                ctx,
+                &format!(
+                    "{label}: unexpected sqlite_master entry for [{rtree_name}] \
+                     (type [{object_type}]); expected table"
+                ),
             );
             return false;
         }
-    };
-    if let Some(object_type) = object_type {
-        if object_type != "table" {
+
+        for shadow_suffix in &["_node", "_parent", "_rowid"] {
+            let shadow_name = format!("{rtree_name}{shadow_suffix}");
+            let sql = format!(
+                "SELECT name FROM sqlite_master \
+                 WHERE type = 'table' AND name = '{shadow_name}' LIMIT 1"
+            );
+            let shadow_exists = match sqlite_master_lookup_text(db, &sql) {
+                Ok(v) => v,
+                Err(e) => {
+                    set_error(
+                        ctx,
+                        &format!("{label}: failed to inspect sqlite_master: {e}"),
+                    );
+                    return false;
+                }
+            };
+            if shadow_exists.is_none() {
+                set_error(
+                    ctx,
+                    &format!(
+                        "{label}: existing table [{rtree_name}] is not an R-tree index \
+                         managed by SQLiteGIS (missing shadow table [{shadow_name}])"
+                    ),
+                );
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+unsafe fn ensure_spatial_index_objects_owned_by_table(
+    db: *mut sqlite3,
+    ctx: *mut sqlite3_context,
+    table: &str,
+    column: &str,
+    label: &str,
+) -> Option<SpatialIndexOwnership> {
+    // Object names are built as `{table}_{column}_...`. Different input pairs
+    // can collide (e.g. `a_b`+`c` vs `a`+`b_c`). Detect and fail fast instead
+    // of silently reusing another table's triggers/index objects.
+    let prefix = format!("{table}_{column}");
+    for suffix in &["_insert", "_update", "_delete"] {
+        let trigger_name = format!("{prefix}{suffix}");
+        let sql = format!(
+            "SELECT tbl_name FROM sqlite_master \
+             WHERE type = 'trigger' AND name = '{trigger_name}' LIMIT 1"
+        );
+        let owner = match sqlite_master_lookup_text(db, &sql) {
+            Ok(v) => v,
+            Err(e) => {
+                set_error(
+                    ctx,
+                    &format!("{label}: failed to inspect sqlite_master: {e}"),
+                );
+                return None;
+            }
+        };
+        if let Some(owner) = owner {
+            if owner != table {
+                set_error(
+                    ctx,
+                    &format!(
+                        "{label}: naming collision for trigger [{trigger_name}] \
+                         between tables [{owner}] and [{table}]"
+                    ),
+                );
+                return None;
+            }
+        }
+    }
+
+    if !ensure_spatial_index_table_shape(db, ctx, &prefix, label) {
+        return None;
+    }
+
+    let owner = match lookup_spatial_index_catalog_owner(db, &prefix) {
+        Ok(v) => v,
+        Err(e) => {
+            set_error(ctx, &format!("{label}: failed to inspect catalog: {e}"));
+            return None;
+        }
+    };
+    if let Some((owner_table, owner_column)) = owner {
+        if owner_table == table && owner_column == column {
+            return Some(SpatialIndexOwnership::Owned);
+        }
+        set_error(
+            ctx,
+            &format!(
+                "{label}: naming collision for managed prefix [{prefix}] \
+                 between [{owner_table}.{owner_column}] and [{table}.{column}]"
+            ),
+        );
+        return None;
+    }
+
+    let objects_exist = match managed_spatial_index_objects_exist(db, &prefix) {
+        Ok(v) => v,
+        Err(e) => {
+            set_error(
+                ctx,
+                &format!("{label}: failed to inspect sqlite_master: {e}"),
+            );
+            return None;
+        }
+    };
+    if objects_exist {
+        set_error(
+            ctx,
+            &format!(
+                "{label}: cannot prove ownership for [{prefix}] because managed objects exist \
+                 without an ownership marker"
+            ),
+        );
+        return None;
+    }
+
+    Some(SpatialIndexOwnership::Absent)
+}
+
+// Spatial index callbacks
+
+/// Extract and validate `(table, column)` identifiers from the first two args.
+/// On failure, sets an error on `ctx` and returns `None`.
+unsafe fn get_table_column<'a>(
+    ctx: *mut sqlite3_context,
+    argv: *mut *mut sqlite3_value,
+    label: &str,
+) -> Option<(&'a str, &'a str)> {
+    let table = match get_text(argv, 0) {
+        SqlTextArg::Value(v) => v,
+        SqlTextArg::Null => {
+            set_error(ctx, &format!("{label}: table name must not be NULL"));
+            return None;
+        }
+        SqlTextArg::InvalidUtf8 => {
+            set_error(
+                ctx,
+                &format!("{label}: table name must be valid UTF-8 text"),
+            );
+            return None;
+        }
+    };
+    let column = match get_text(argv, 1) {
+        SqlTextArg::Value(v) => v,
+        SqlTextArg::Null => {
+            set_error(ctx, &format!("{label}: column name must not be NULL"));
+            return None;
+        }
+        SqlTextArg::InvalidUtf8 => {
+            set_error(
+                ctx,
+                &format!("{label}: column name must be valid UTF-8 text"),
+            );
+            return None;
+        }
+    };
+    let Some(table) = validate_identifier(table) else {
+        set_error(
+            ctx,
+            &format!("{label}: invalid table name (only [a-zA-Z0-9_] allowed)"),
+        );
+        return None;
+    };
+    let Some(column) = validate_identifier(column) else {
+        set_error(
+            ctx,
+            &format!("{label}: invalid column name (only [a-zA-Z0-9_] allowed)"),
+        );
+        return None;
+    };
+    Some((table, column))
+}
+
+unsafe extern "C" fn create_spatial_index_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "CreateSpatialIndex", || {
+        let Some((table, column)) = get_table_column(ctx, argv, "CreateSpatialIndex") else {
+            return;
+        };
+
+        let db = sqlite3_context_db_handle(ctx);
+        let prefix = format!("{table}_{column}");
+        let rtree = format!("{prefix}_rtree");
+        let savepoint = "sqlitegis_create_spatial_index";
+
+        if exec_sql(db, ctx, &format!("SAVEPOINT {savepoint}")) != SQLITE_OK {
+            return;
+        }
+
+        if !ensure_spatial_index_catalog_table(db, ctx, "CreateSpatialIndex") {
+            rollback_savepoint(db, ctx, savepoint);
+            return;
+        }
+        if !validate_spatial_index_catalog_shape(db, ctx, "CreateSpatialIndex") {
+            rollback_savepoint(db, ctx, savepoint);
+            return;
+        }
+
+        if ensure_spatial_index_objects_owned_by_table(db, ctx, table, column, "CreateSpatialIndex")
+            .is_none()
+        {
+            rollback_savepoint(db, ctx, savepoint);
+            return;
+        }
+
+        // Reject WITHOUT ROWID tables before creating any state. The
+        // maintenance triggers reference NEW.rowid and OLD.rowid, which only
+        // exist on regular rowid tables. SQLite refuses to prepare a SELECT
+        // of rowid against a WITHOUT ROWID table, so the probe fails cleanly
+        // at parse time. A successful probe also incidentally proves the
+        // table exists.
+        let probe = format!("SELECT rowid FROM [{table}] LIMIT 0");
+        if exec_sql_silent(db, &probe) != SQLITE_OK {
             set_error(
                 ctx,
                 &format!(
-                    "{label}: invalid spatial index catalog object type for \
-                     [{SPATIAL_INDEX_CATALOG_TABLE}] (expected table, found [{object_type}])"
+                    "CreateSpatialIndex: table [{table}] has no rowid column. \
+                     WITHOUT ROWID tables are not supported. Recreate the table \
+                     without the WITHOUT ROWID clause, or verify the table exists."
                 ),
             );
-            return false;
+            rollback_savepoint(db, ctx, savepoint);
+            return;
         }
-    }
 
-    let sql = format!(
-        "CREATE TABLE IF NOT EXISTS [{SPATIAL_INDEX_CATALOG_TABLE}] (\
-         prefix TEXT PRIMARY KEY, \
-         table_name TEXT NOT NULL, \
-         column_name TEXT NOT NULL, \
-         UNIQUE(table_name, column_name)\
-         )"
-    );
-    if exec_sql_silent(db, &sql) == SQLITE_OK {
-        return true;
-    }
+        // 1. Create the R-tree virtual table if missing.
+        let sql = format!(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS [{rtree}] USING rtree(id, xmin, xmax, ymin, ymax)"
+        );
+        if exec_sql(db, ctx, &sql) != SQLITE_OK {
+            rollback_savepoint(db, ctx, savepoint);
+            return;
+        }
 
-    let err = CStr::from_ptr(sqlite3_errmsg(db))
-        .to_string_lossy()
-        .into_owned();
-    set_error(
-        ctx,
-        &format!("{label}: failed to ensure spatial index catalog: {err}"),
-    );
-    false
-}
+        // 2. Rebuild index contents from the base table (idempotent on repeated calls).
+        let sql = format!("DELETE FROM [{rtree}]");
+        if exec_sql(db, ctx, &sql) != SQLITE_OK {
+            rollback_savepoint(db, ctx, savepoint);
+            return;
+        }
 
-unsafe fn lookup_spatial_index_catalog_owner(
-    db: *mut sqlite3,
-    prefix: &str,
-) -> std::result::Result<Option<(String, String)>, String> {
-    let sql = format!(
-        "SELECT table_name FROM [{SPATIAL_INDEX_CATALOG_TABLE}] \
-         WHERE prefix = '{prefix}' LIMIT 1"
-    );
-    let owner_table = sqlite_master_lookup_text(db, &sql)?;
-    let Some(owner_table) = owner_table else {
-        return Ok(None);
-    };
+        let sql = format!(
+            "INSERT INTO [{rtree}] \
+             SELECT rowid, ST_XMin([{column}]), ST_XMax([{column}]), \
+             ST_YMin([{column}]), ST_YMax([{column}]) \
+             FROM [{table}] WHERE [{column}] IS NOT NULL AND ST_IsEmpty([{column}]) = 0"
+        );
+        if exec_sql(db, ctx, &sql) != SQLITE_OK {
+            rollback_savepoint(db, ctx, savepoint);
+            return;
+        }
 
-    let sql = format!(
-        "SELECT column_name FROM [{SPATIAL_INDEX_CATALOG_TABLE}] \
-         WHERE prefix = '{prefix}' LIMIT 1"
-    );
-    let owner_column = sqlite_master_lookup_text(db, &sql)?;
-    let Some(owner_column) = owner_column else {
-        return Err(format!(
-            "internal error: catalog row for prefix [{prefix}] is missing column_name"
-        ));
-    };
-    Ok(Some((owner_table, owner_column)))
+        // 3. AFTER INSERT trigger
+        let trigger_insert = format!("{table}_{column}_insert");
+        let sql = format!(
+            "CREATE TRIGGER IF NOT EXISTS [{trigger_insert}] AFTER INSERT ON [{table}] \
+             WHEN NEW.[{column}] IS NOT NULL AND ST_IsEmpty(NEW.[{column}]) = 0 \
+             BEGIN \
+               INSERT INTO [{rtree}] VALUES ( \
+                 NEW.rowid, \
+                 ST_XMin(NEW.[{column}]), ST_XMax(NEW.[{column}]), \
+                 ST_YMin(NEW.[{column}]), ST_YMax(NEW.[{column}]) \
+               ); \
+             END"
+        );
+        if exec_sql(db, ctx, &sql) != SQLITE_OK {
+            rollback_savepoint(db, ctx, savepoint);
+            return;
+        }
+
+        // 4. AFTER UPDATE trigger. Broad UPDATE so that rowid changes
+        // (UPDATE ... SET rowid = ... or via INTEGER PRIMARY KEY rewrite)
+        // still propagate to the index. The WHEN clause skips the DELETE
+        // plus INSERT when neither the geometry blob nor the rowid changed,
+        // which is the common case for UPDATEs that only touch unrelated
+        // columns.
+        let trigger_update = format!("{table}_{column}_update");
+        let sql = format!(
+            "CREATE TRIGGER IF NOT EXISTS [{trigger_update}] AFTER UPDATE ON [{table}] \
+             WHEN OLD.[{column}] IS NOT NEW.[{column}] OR OLD.rowid IS NOT NEW.rowid \
+             BEGIN \
+               DELETE FROM [{rtree}] WHERE id = OLD.rowid; \
+               INSERT INTO [{rtree}] \
+                 SELECT NEW.rowid, \
+                   ST_XMin(NEW.[{column}]), ST_XMax(NEW.[{column}]), \
+                   ST_YMin(NEW.[{column}]), ST_YMax(NEW.[{column}]) \
+                 WHERE NEW.[{column}] IS NOT NULL AND ST_IsEmpty(NEW.[{column}]) = 0; \
+             END"
+        );
+        if exec_sql(db, ctx, &sql) != SQLITE_OK {
+            rollback_savepoint(db, ctx, savepoint);
+            return;
+        }
+
+        // 5. AFTER DELETE trigger
+        let trigger_delete = format!("{table}_{column}_delete");
+        let sql = format!(
+            "CREATE TRIGGER IF NOT EXISTS [{trigger_delete}] AFTER DELETE ON [{table}] \
+             BEGIN \
+               DELETE FROM [{rtree}] WHERE id = OLD.rowid; \
+             END"
+        );
+        if exec_sql(db, ctx, &sql) != SQLITE_OK {
+            rollback_savepoint(db, ctx, savepoint);
+            return;
+        }
+
+        let sql = format!(
+            "INSERT INTO [{SPATIAL_INDEX_CATALOG_TABLE}] (prefix, table_name, column_name) \
+             VALUES ('{prefix}', '{table}', '{column}') \
+             ON CONFLICT(prefix) DO UPDATE SET \
+             table_name = excluded.table_name, \
+             column_name = excluded.column_name"
+        );
+        if exec_sql(db, ctx, &sql) != SQLITE_OK {
+            rollback_savepoint(db, ctx, savepoint);
+            return;
+        }
+
+        if exec_sql(db, ctx, &format!("RELEASE {savepoint}")) != SQLITE_OK {
+            return;
+        }
+
+        set_i32(ctx, 1);
+    });
 }
 
-unsafe fn managed_spatial_index_objects_exist(
-    db: *mut sqlite3,
-    prefix: &str,
-) -> std::result::Result<bool, String> {
-    let rtree_name = format!("{prefix}_rtree");
-    let sql = format!(
-        "SELECT name FROM sqlite_master WHERE name IN (\
-         '{rtree_name}', \
-         '{rtree_name}_node', \
-         '{rtree_name}_parent', \
-         '{rtree_name}_rowid', \
-         '{prefix}_insert', \
-         '{prefix}_update', \
-         '{prefix}_delete'\
-         ) LIMIT 1"
-    );
-    Ok(sqlite_master_lookup_text(db, &sql)?.is_some())
+unsafe extern "C" fn drop_spatial_index_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "DropSpatialIndex", || {
+        let Some((table, column)) = get_table_column(ctx, argv, "DropSpatialIndex") else {
+            return;
+        };
+
+        let db = sqlite3_context_db_handle(ctx);
+        let prefix = format!("{table}_{column}");
+        let savepoint = "sqlitegis_drop_spatial_index";
+
+        if exec_sql(db, ctx, &format!("SAVEPOINT {savepoint}")) != SQLITE_OK {
+            return;
+        }
+
+        if !ensure_spatial_index_catalog_table(db, ctx, "DropSpatialIndex") {
+            rollback_savepoint(db, ctx, savepoint);
+            return;
+        }
+        if !validate_spatial_index_catalog_shape(db, ctx, "DropSpatialIndex") {
+            rollback_savepoint(db, ctx, savepoint);
+            return;
+        }
+
+        let ownership = match ensure_spatial_index_objects_owned_by_table(
+            db,
+            ctx,
+            table,
+            column,
+            "DropSpatialIndex",
+        ) {
+            Some(v) => v,
+            None => {
+                rollback_savepoint(db, ctx, savepoint);
+                return;
+            }
+        };
+        if ownership == SpatialIndexOwnership::Absent {
+            if exec_sql(db, ctx, &format!("RELEASE {savepoint}")) != SQLITE_OK {
+                return;
+            }
+            set_i32(ctx, 1);
+            return;
+        }
+
+        // Drop triggers first, then the R-tree table. Ownership has already
+        // been verified to avoid cross-table collisions on derived names.
+        for suffix in &["_insert", "_update", "_delete"] {
+            let sql = format!("DROP TRIGGER IF EXISTS [{prefix}{suffix}]");
+            if exec_sql(db, ctx, &sql) != SQLITE_OK {
+                rollback_savepoint(db, ctx, savepoint);
+                return;
+            }
+        }
+        let sql = format!("DROP TABLE IF EXISTS [{prefix}_rtree]");
+        if exec_sql(db, ctx, &sql) != SQLITE_OK {
+            rollback_savepoint(db, ctx, savepoint);
+            return;
+        }
+
+        let sql = format!("DELETE FROM [{SPATIAL_INDEX_CATALOG_TABLE}] WHERE prefix = '{prefix}'");
+        if exec_sql(db, ctx, &sql) != SQLITE_OK {
+            rollback_savepoint(db, ctx, savepoint);
+            return;
+        }
+
+        if exec_sql(db, ctx, &format!("RELEASE {savepoint}")) != SQLITE_OK {
+            return;
+        }
+
+        set_i32(ctx, 1);
+    });
 }
 
-unsafe fn ensure_spatial_index_table_shape(
-    db: *mut sqlite3,
-    ctx: *mut sqlite3_context,
-    prefix: &str,
-    label: &str,
-) -> bool {
-    // The managed object `{prefix}_rtree` must be either absent or a real
-    // SQLite table backed by the expected R-tree shadow tables.
-    let rtree_name = format!("{prefix}_rtree");
-    let sql = format!("SELECT type FROM sqlite_master WHERE name = '{rtree_name}' LIMIT 1");
-    let object_type = match sqlite_master_lookup_text(db, &sql) {
-        Ok(v) => v,
-        Err(e) => {
-            set_error(
-                ctx,
-                &format!("{label}: failed to inspect sqlite_master: {e}"),
+// GeoPackage layout callbacks
+
+/// `PRAGMA application_id` of a GeoPackage file ("GPKG").
+const GPKG_APPLICATION_ID: i32 = 0x4750_4B47;
+/// `PRAGMA user_version` for GeoPackage 1.3.0.
+const GPKG_USER_VERSION: i32 = 10300;
+
+/// Geometry type names GeoPackage allows in `gpkg_geometry_columns`.
+const GPKG_GEOMETRY_TYPES: [&str; 8] = [
+    "GEOMETRY",
+    "POINT",
+    "LINESTRING",
+    "POLYGON",
+    "MULTIPOINT",
+    "MULTILINESTRING",
+    "MULTIPOLYGON",
+    "GEOMETRYCOLLECTION",
+];
+
+/// Required GeoPackage tables, with the two undefined SRS rows and WGS 84
+/// that the spec mandates in `gpkg_spatial_ref_sys`. Column layout follows
+/// GeoPackage 1.3, Annex C.
+const GPKG_BASE_TABLES_SQL: &str = "\
+    CREATE TABLE IF NOT EXISTS gpkg_spatial_ref_sys ( \
+      srs_name TEXT NOT NULL, \
+      srs_id INTEGER PRIMARY KEY, \
+      organization TEXT NOT NULL, \
+      organization_coordsys_id INTEGER NOT NULL, \
+      definition TEXT NOT NULL, \
+      description TEXT \
+    ); \
+    INSERT OR IGNORE INTO gpkg_spatial_ref_sys VALUES \
+      ('Undefined cartesian SRS', -1, 'NONE', -1, 'undefined', \
+       'undefined cartesian coordinate reference system'), \
+      ('Undefined geographic SRS', 0, 'NONE', 0, 'undefined', \
+       'undefined geographic coordinate reference system'), \
+      ('WGS 84 geodetic', 4326, 'EPSG', 4326, \
+       'GEOGCS[\"WGS 84\",DATUM[\"WGS_1984\",SPHEROID[\"WGS 84\",6378137,298.257223563,\
+AUTHORITY[\"EPSG\",\"7030\"]],AUTHORITY[\"EPSG\",\"6326\"]],PRIMEM[\"Greenwich\",0,\
+AUTHORITY[\"EPSG\",\"8901\"]],UNIT[\"degree\",0.0174532925199433,AUTHORITY[\"EPSG\",\"9122\"]],\
+AXIS[\"Latitude\",NORTH],AXIS[\"Longitude\",EAST],AUTHORITY[\"EPSG\",\"4326\"]]', \
+       'longitude/latitude coordinates in decimal degrees on the WGS 84 spheroid'); \
+    CREATE TABLE IF NOT EXISTS gpkg_contents ( \
+      table_name TEXT NOT NULL PRIMARY KEY, \
+      data_type TEXT NOT NULL, \
+      identifier TEXT UNIQUE, \
+      description TEXT DEFAULT '', \
+      last_change DATETIME NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ','now')), \
+      min_x DOUBLE, min_y DOUBLE, max_x DOUBLE, max_y DOUBLE, \
+      srs_id INTEGER, \
+      CONSTRAINT fk_gc_r_srs_id FOREIGN KEY (srs_id) REFERENCES gpkg_spatial_ref_sys(srs_id) \
+    ); \
+    CREATE TABLE IF NOT EXISTS gpkg_geometry_columns ( \
+      table_name TEXT NOT NULL, \
+      column_name TEXT NOT NULL, \
+      geometry_type_name TEXT NOT NULL, \
+      srs_id INTEGER NOT NULL, \
+      z TINYINT NOT NULL, \
+      m TINYINT NOT NULL, \
+      CONSTRAINT pk_geom_cols PRIMARY KEY (table_name, column_name), \
+      CONSTRAINT uk_gc_table_name UNIQUE (table_name), \
+      CONSTRAINT fk_gc_tn FOREIGN KEY (table_name) REFERENCES gpkg_contents(table_name), \
+      CONSTRAINT fk_gc_srs FOREIGN KEY (srs_id) REFERENCES gpkg_spatial_ref_sys (srs_id) \
+    ); \
+    CREATE TABLE IF NOT EXISTS gpkg_extensions ( \
+      table_name TEXT, \
+      column_name TEXT, \
+      extension_name TEXT NOT NULL, \
+      definition TEXT NOT NULL, \
+      scope TEXT NOT NULL, \
+      CONSTRAINT ge_tce UNIQUE (table_name, column_name, extension_name) \
+    );";
+
+/// Create the GeoPackage base tables and stamp the file header, leaving an
+/// existing non-GeoPackage `application_id` alone.
+unsafe fn ensure_gpkg_base_tables(db: *mut sqlite3, ctx: *mut sqlite3_context) -> bool {
+    if exec_sql(db, ctx, GPKG_BASE_TABLES_SQL) != SQLITE_OK {
+        return false;
+    }
+    match sqlite_master_lookup_text(db, "PRAGMA application_id") {
+        Ok(Some(id)) if id == "0" => {
+            let sql = format!(
+                "PRAGMA application_id = {GPKG_APPLICATION_ID}; \
+                 PRAGMA user_version = {GPKG_USER_VERSION}"
             );
-            return false;
+            exec_sql(db, ctx, &sql) == SQLITE_OK
         }
-    };
-    if let Some(object_type) = object_type {
-        if object_type != "table" {
+        Ok(_) => true,
+        Err(e) => {
+            set_error(ctx, &e);
+            false
+        }
+    }
+}
+
+/// `gpkgAddGeometryColumn(table, column, geometry_type, srs_id)`: create
+/// the GeoPackage base tables if missing, create `table` (with an integer
+/// `fid` key) or add `column` to it, and register both in `gpkg_contents`
+/// and `gpkg_geometry_columns`. `srs_id` must already be in
+/// `gpkg_spatial_ref_sys`; `-1`, `0` and `4326` always are.
+unsafe extern "C" fn gpkg_add_geometry_column_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    const LABEL: &str = "gpkgAddGeometryColumn";
+    xfunc_guard(ctx, LABEL, || {
+        let Some((table, column)) = get_table_column(ctx, argv, LABEL) else {
+            return;
+        };
+        let geometry_type = match get_text(argv, 2) {
+            SqlTextArg::Value(v) => v.to_ascii_uppercase(),
+            SqlTextArg::Null | SqlTextArg::InvalidUtf8 => {
+                set_error(ctx, &format!("{LABEL}: geometry type must be text"));
+                return;
+            }
+        };
+        if !GPKG_GEOMETRY_TYPES.contains(&geometry_type.as_str()) {
             set_error(
                 ctx,
                 &format!(
-                    "{label}: unexpected sqlite_master entry for [{rtree_name}] \
-                     (type [{object_type}]); expected table"
+                    "{LABEL}: unsupported geometry type [{geometry_type}] (expected one of {})",
+                    GPKG_GEOMETRY_TYPES.join(", ")
                 ),
             );
-            return false;
+            return;
+        }
+        let srs_id = match get_i32_arg(argv, 3) {
+            SqlI32Arg::Value(v) => v,
+            _ => {
+                set_error(ctx, &format!("{LABEL}: srs_id must be a 32-bit integer"));
+                return;
+            }
+        };
+
+        let db = sqlite3_context_db_handle(ctx);
+        let savepoint = "sqlitegis_gpkg_add_geometry_column";
+        if exec_sql(db, ctx, &format!("SAVEPOINT {savepoint}")) != SQLITE_OK {
+            return;
+        }
+        if !ensure_gpkg_base_tables(db, ctx) {
+            rollback_savepoint(db, ctx, savepoint);
+            return;
         }
 
-        for shadow_suffix in &["_node", "_parent", "_rowid"] {
-            let shadow_name = format!("{rtree_name}{shadow_suffix}");
-            let sql = format!(
-                "SELECT name FROM sqlite_master \
-                 WHERE type = 'table' AND name = '{shadow_name}' LIMIT 1"
-            );
-            let shadow_exists = match sqlite_master_lookup_text(db, &sql) {
-                Ok(v) => v,
-                Err(e) => {
-                    set_error(
-                        ctx,
-                        &format!("{label}: failed to inspect sqlite_master: {e}"),
-                    );
-                    return false;
-                }
-            };
-            if shadow_exists.is_none() {
+        let sql = format!("SELECT srs_id FROM gpkg_spatial_ref_sys WHERE srs_id = {srs_id}");
+        match sqlite_master_lookup_text(db, &sql) {
+            Ok(Some(_)) => {}
+            Ok(None) => {
                 set_error(
                     ctx,
                     &format!(
-                        "{label}: existing table [{rtree_name}] is not an R-tree index \
-                         managed by SQLiteGIS (missing shadow table [{shadow_name}])"
+                        "{LABEL}: srs_id {srs_id} is not in gpkg_spatial_ref_sys; \
+                         insert its definition first"
                     ),
                 );
-                return false;
+                rollback_savepoint(db, ctx, savepoint);
+                return;
+            }
+            Err(e) => {
+                set_error(ctx, &format!("{LABEL}: {e}"));
+                rollback_savepoint(db, ctx, savepoint);
+                return;
             }
         }
-    }
 
-    true
-}
-
-unsafe fn ensure_spatial_index_objects_owned_by_table(
-    db: *mut sqlite3,
-    ctx: *mut sqlite3_context,
-    table: &str,
-    column: &str,
-    label: &str,
-) -> Option<SpatialIndexOwnership> {
-    // Object names are built as `{table}_{column}_...`. Different input pairs
-    // can collide (e.g. `a_b`+`c` vs `a`+`b_c`). Detect and fail fast instead
-    // of silently reusing another table's triggers/index objects.
-    let prefix = format!("{table}_{column}");
-    for suffix in &["_insert", "_update", "_delete"] {
-        let trigger_name = format!("{prefix}{suffix}");
-        let sql = format!(
-            "SELECT tbl_name FROM sqlite_master \
-             WHERE type = 'trigger' AND name = '{trigger_name}' LIMIT 1"
-        );
-        let owner = match sqlite_master_lookup_text(db, &sql) {
-            Ok(v) => v,
-            Err(e) => {
-                set_error(
-                    ctx,
-                    &format!("{label}: failed to inspect sqlite_master: {e}"),
+        // GeoPackage wants the geometry type name as the declared column type.
+        let sql = match lookup_sqlite_master_object_type(db, table) {
+            Ok(None) => format!(
+                "CREATE TABLE [{table}] \
+                 (fid INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL, [{column}] {geometry_type})"
+            ),
+            Ok(Some(kind)) if kind == "table" => {
+                let probe = format!(
+                    "SELECT name FROM pragma_table_info('{table}') WHERE name = '{column}'"
                 );
-                return None;
+                match sqlite_master_lookup_text(db, &probe) {
+                    Ok(Some(_)) => String::new(),
+                    Ok(None) => {
+                        format!("ALTER TABLE [{table}] ADD COLUMN [{column}] {geometry_type}")
+                    }
+                    Err(e) => {
+                        set_error(ctx, &format!("{LABEL}: {e}"));
+                        rollback_savepoint(db, ctx, savepoint);
+                        return;
+                    }
+                }
             }
-        };
-        if let Some(owner) = owner {
-            if owner != table {
-                set_error(
-                    ctx,
-                    &format!(
-                        "{label}: naming collision for trigger [{trigger_name}] \
-                         between tables [{owner}] and [{table}]"
-                    ),
-                );
-                return None;
+            Ok(Some(kind)) => {
+                set_error(ctx, &format!("{LABEL}: [{table}] is a {kind}, not a table"));
+                rollback_savepoint(db, ctx, savepoint);
+                return;
             }
+            Err(e) => {
+                set_error(ctx, &format!("{LABEL}: {e}"));
+                rollback_savepoint(db, ctx, savepoint);
+                return;
+            }
+        };
+        if !sql.is_empty() && exec_sql(db, ctx, &sql) != SQLITE_OK {
+            rollback_savepoint(db, ctx, savepoint);
+            return;
         }
-    }
 
-    if !ensure_spatial_index_table_shape(db, ctx, &prefix, label) {
-        return None;
-    }
-
-    let owner = match lookup_spatial_index_catalog_owner(db, &prefix) {
-        Ok(v) => v,
-        Err(e) => {
-            set_error(ctx, &format!("{label}: failed to inspect catalog: {e}"));
-            return None;
-        }
-    };
-    if let Some((owner_table, owner_column)) = owner {
-        if owner_table == table && owner_column == column {
-            return Some(SpatialIndexOwnership::Owned);
-        }
-        set_error(
-            ctx,
-            &format!(
-                "{label}: naming collision for managed prefix [{prefix}] \
-                 between [{owner_table}.{owner_column}] and [{table}.{column}]"
-            ),
+        // z and m are "optional" (2): the column accepts any dimension.
+        let sql = format!(
+            "INSERT INTO gpkg_contents (table_name, data_type, identifier, srs_id) \
+             VALUES ('{table}', 'features', '{table}', {srs_id}) \
+             ON CONFLICT(table_name) DO UPDATE SET \
+             data_type = 'features', srs_id = excluded.srs_id; \
+             INSERT INTO gpkg_geometry_columns \
+             VALUES ('{table}', '{column}', '{geometry_type}', {srs_id}, 2, 2) \
+             ON CONFLICT(table_name, column_name) DO UPDATE SET \
+             geometry_type_name = excluded.geometry_type_name, srs_id = excluded.srs_id"
         );
-        return None;
-    }
-
-    let objects_exist = match managed_spatial_index_objects_exist(db, &prefix) {
-        Ok(v) => v,
-        Err(e) => {
-            set_error(
-                ctx,
-                &format!("{label}: failed to inspect sqlite_master: {e}"),
-            );
-            return None;
+        if exec_sql(db, ctx, &sql) != SQLITE_OK {
+            rollback_savepoint(db, ctx, savepoint);
+            return;
         }
-    };
-    if objects_exist {
-        set_error(
-            ctx,
-            &format!(
-                "{label}: cannot prove ownership for [{prefix}] because managed objects exist \
-                 without an ownership marker"
-            ),
-        );
-        return None;
-    }
-
-    Some(SpatialIndexOwnership::Absent)
-}
-
-// Spatial index callbacks
 
-/// Extract and validate `(table, column)` identifiers from the first two args.
-/// On failure, sets an error on `ctx` and returns `None`.
-unsafe fn get_table_column<'a>(
-    ctx: *mut sqlite3_context,
-    argv: *mut *mut sqlite3_value,
-    label: &str,
-) -> Option<(&'a str, &'a str)> {
-    let table = match get_text(argv, 0) {
-        SqlTextArg::Value(v) => v,
-        SqlTextArg::Null => {
-            set_error(ctx, &format!("{label}: table name must not be NULL"));
-            return None;
-        }
-        SqlTextArg::InvalidUtf8 => {
-            set_error(
-                ctx,
-                &format!("{label}: table name must be valid UTF-8 text"),
-            );
-            return None;
-        }
-    };
-    let column = match get_text(argv, 1) {
-        SqlTextArg::Value(v) => v,
-        SqlTextArg::Null => {
-            set_error(ctx, &format!("{label}: column name must not be NULL"));
-            return None;
-        }
-        SqlTextArg::InvalidUtf8 => {
-            set_error(
-                ctx,
-                &format!("{label}: column name must be valid UTF-8 text"),
-            );
-            return None;
+        if exec_sql(db, ctx, &format!("RELEASE {savepoint}")) != SQLITE_OK {
+            return;
         }
-    };
-    let Some(table) = validate_identifier(table) else {
-        set_error(
-            ctx,
-            &format!("{label}: invalid table name (only [a-zA-Z0-9_] allowed)"),
-        );
-        return None;
-    };
-    let Some(column) = validate_identifier(column) else {
-        set_error(
-            ctx,
-            &format!("{label}: invalid column name (only [a-zA-Z0-9_] allowed)"),
-        );
-        return None;
-    };
-    Some((table, column))
+        set_i32(ctx, 1);
+    });
 }
 
-unsafe extern "C" fn create_spatial_index_xfunc(
+/// `gpkgAddSpatialIndex(table, column)`: build the `rtree_<table>_<column>`
+/// index and the maintenance triggers of the GeoPackage RTree Spatial
+/// Indexes extension (1.3, F.3), and record the extension in
+/// `gpkg_extensions`. The column must be registered by
+/// `gpkgAddGeometryColumn` first.
+///
+/// The triggers decode the stored blobs with `ST_GeomFromGPB`, so they
+/// work on any connection with the functions registered, whatever its
+/// `accept_gpb` setting.
+unsafe extern "C" fn gpkg_add_spatial_index_xfunc(
     ctx: *mut sqlite3_context,
     _n: c_int,
     argv: *mut *mut sqlite3_value,
 ) {
-    xfunc_guard(ctx, "CreateSpatialIndex", || {
-        let Some((table, column)) = get_table_column(ctx, argv, "CreateSpatialIndex") else {
+    const LABEL: &str = "gpkgAddSpatialIndex";
+    xfunc_guard(ctx, LABEL, || {
+        let Some((t, c)) = get_table_column(ctx, argv, LABEL) else {
             return;
         };
 
         let db = sqlite3_context_db_handle(ctx);
-        let prefix = format!("{table}_{column}");
-        let rtree = format!("{prefix}_rtree");
-        let savepoint = "sqlitegis_create_spatial_index";
+        let sql = format!(
+            "SELECT column_name FROM gpkg_geometry_columns \
+             WHERE table_name = '{t}' AND column_name = '{c}'"
+        );
+        if !matches!(sqlite_master_lookup_text(db, &sql), Ok(Some(_))) {
+            set_error(
+                ctx,
+                &format!(
+                    "{LABEL}: [{t}].[{c}] is not registered in gpkg_geometry_columns; \
+                     call gpkgAddGeometryColumn first"
+                ),
+            );
+            return;
+        }
 
-        if exec_sql(db, ctx, &format!("SAVEPOINT {savepoint}")) != SQLITE_OK {
+        // The spec triggers key the index by the table's integer primary
+        // key; `rowid` is the same value and covers tables without one.
+        let sql = format!(
+            "SELECT name FROM pragma_table_info('{t}') \
+             WHERE pk = 1 AND upper(type) = 'INTEGER' \
+             AND (SELECT count(*) FROM pragma_table_info('{t}') WHERE pk > 0) = 1"
+        );
+        let i = match sqlite_master_lookup_text(db, &sql) {
+            Ok(Some(pk)) => format!("[{pk}]"),
+            Ok(None) => "rowid".to_string(),
+            Err(e) => {
+                set_error(ctx, &format!("{LABEL}: {e}"));
+                return;
+            }
+        };
+        if exec_sql_silent(db, &format!("SELECT {i} FROM [{t}] LIMIT 0")) != SQLITE_OK {
+            set_error(
+                ctx,
+                &format!(
+                    "{LABEL}: table [{t}] has no rowid column. \
+                     WITHOUT ROWID tables are not supported."
+                ),
+            );
             return;
         }
 
-        if !ensure_spatial_index_catalog_table(db, ctx, "CreateSpatialIndex") {
-            rollback_savepoint(db, ctx, savepoint);
+        let savepoint = "sqlitegis_gpkg_add_spatial_index";
+        if exec_sql(db, ctx, &format!("SAVEPOINT {savepoint}")) != SQLITE_OK {
             return;
         }
-        if !validate_spatial_index_catalog_shape(db, ctx, "CreateSpatialIndex") {
+
+        let rtree = format!("rtree_{t}_{c}");
+        let g = format!("ST_GeomFromGPB([{c}])");
+        let new = format!("ST_GeomFromGPB(NEW.[{c}])");
+        let values = format!("ST_MinX({new}), ST_MaxX({new}), ST_MinY({new}), ST_MaxY({new})");
+        let sql = format!(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS [{rtree}] USING rtree(id, minx, maxx, miny, maxy); \
+             DELETE FROM [{rtree}]; \
+             INSERT INTO [{rtree}] \
+               SELECT {i}, ST_MinX({g}), ST_MaxX({g}), ST_MinY({g}), ST_MaxY({g}) \
+               FROM [{t}] WHERE [{c}] NOT NULL AND NOT ST_IsEmpty({g}); \
+             CREATE TRIGGER IF NOT EXISTS [{rtree}_insert] AFTER INSERT ON [{t}] \
+               WHEN (NEW.[{c}] NOT NULL AND NOT ST_IsEmpty({new})) \
+             BEGIN \
+               INSERT OR REPLACE INTO [{rtree}] VALUES (NEW.{i}, {values}); \
+             END; \
+             CREATE TRIGGER IF NOT EXISTS [{rtree}_update1] AFTER UPDATE OF [{c}] ON [{t}] \
+               WHEN OLD.{i} = NEW.{i} AND (NEW.[{c}] NOTNULL AND NOT ST_IsEmpty({new})) \
+             BEGIN \
+               INSERT OR REPLACE INTO [{rtree}] VALUES (NEW.{i}, {values}); \
+             END; \
+             CREATE TRIGGER IF NOT EXISTS [{rtree}_update2] AFTER UPDATE OF [{c}] ON [{t}] \
+               WHEN OLD.{i} = NEW.{i} AND (NEW.[{c}] ISNULL OR ST_IsEmpty({new})) \
+             BEGIN \
+               DELETE FROM [{rtree}] WHERE id = OLD.{i}; \
+             END; \
+             CREATE TRIGGER IF NOT EXISTS [{rtree}_update3] AFTER UPDATE ON [{t}] \
+               WHEN OLD.{i} != NEW.{i} AND (NEW.[{c}] NOTNULL AND NOT ST_IsEmpty({new})) \
+             BEGIN \
+               DELETE FROM [{rtree}] WHERE id = OLD.{i}; \
+               INSERT OR REPLACE INTO [{rtree}] VALUES (NEW.{i}, {values}); \
+             END; \
+             CREATE TRIGGER IF NOT EXISTS [{rtree}_update4] AFTER UPDATE ON [{t}] \
+               WHEN OLD.{i} != NEW.{i} AND (NEW.[{c}] ISNULL OR ST_IsEmpty({new})) \
+             BEGIN \
+               DELETE FROM [{rtree}] WHERE id IN (OLD.{i}, NEW.{i}); \
+             END; \
+             CREATE TRIGGER IF NOT EXISTS [{rtree}_delete] AFTER DELETE ON [{t}] \
+               WHEN OLD.[{c}] NOT NULL \
+             BEGIN \
+               DELETE FROM [{rtree}] WHERE id = OLD.{i}; \
+             END; \
+             INSERT OR IGNORE INTO gpkg_extensions VALUES ('{t}', '{c}', 'gpkg_rtree_index', \
+               'http://www.geopackage.org/spec120/#extension_rtree', 'write-only')"
+        );
+        if exec_sql(db, ctx, &sql) != SQLITE_OK {
             rollback_savepoint(db, ctx, savepoint);
             return;
         }
 
-        if ensure_spatial_index_objects_owned_by_table(db, ctx, table, column, "CreateSpatialIndex")
-            .is_none()
-        {
-            rollback_savepoint(db, ctx, savepoint);
+        if exec_sql(db, ctx, &format!("RELEASE {savepoint}")) != SQLITE_OK {
             return;
         }
+        set_i32(ctx, 1);
+    });
+}
 
-        // Reject WITHOUT ROWID tables before creating any state. The
-        // maintenance triggers reference NEW.rowid and OLD.rowid, which only
-        // exist on regular rowid tables. SQLite refuses to prepare a SELECT
-        // of rowid against a WITHOUT ROWID table, so the probe fails cleanly
-        // at parse time. A successful probe also incidentally proves the
-        // table exists.
-        let probe = format!("SELECT rowid FROM [{table}] LIMIT 0");
-        if exec_sql_silent(db, &probe) != SQLITE_OK {
-            set_error(
-                ctx,
-                &format!(
-                    "CreateSpatialIndex: table [{table}] has no rowid column. \
-                     WITHOUT ROWID tables are not supported. Recreate the table \
-                     without the WITHOUT ROWID clause, or verify the table exists."
-                ),
-            );
-            rollback_savepoint(db, ctx, savepoint);
+// SpatiaLite migration callbacks
+
+/// Triggers SpatiaLite 4.x keeps on a geometry column, as `<prefix>_<table>_<column>`:
+/// type/SRID constraints, R*Tree maintenance, MBR cache and statistics.
+/// They call SpatiaLite functions and would fail once the column is EWKB.
+const SPATIALITE_TRIGGER_PREFIXES: [&str; 11] = [
+    "ggi", "ggu", "gii", "giu", "gid", "gci", "gcu", "gcd", "tmi", "tmu", "tmd",
+];
+
+/// `ConvertSpatiaLiteColumn(table, column)`: rewrite every SpatiaLite BLOB
+/// in `column` to EWKB in place and drop SpatiaLite's triggers on the
+/// column. Values that are not SpatiaLite BLOBs are left alone, so a
+/// repeated call is a no-op. Returns the number of rows rewritten.
+///
+/// SpatiaLite's `idx_<table>_<column>` R*Tree and its metadata tables are
+/// left in place; `CreateSpatialIndex` builds this crate's index.
+unsafe extern "C" fn convert_spatialite_column_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    const LABEL: &str = "ConvertSpatiaLiteColumn";
+    xfunc_guard(ctx, LABEL, || {
+        let Some((table, column)) = get_table_column(ctx, argv, LABEL) else {
+            return;
+        };
+
+        let db = sqlite3_context_db_handle(ctx);
+        let savepoint = "sqlitegis_convert_spatialite_column";
+        if exec_sql(db, ctx, &format!("SAVEPOINT {savepoint}")) != SQLITE_OK {
             return;
         }
 
-        // 1. Create the R-tree virtual table if missing.
+        for prefix in SPATIALITE_TRIGGER_PREFIXES {
+            let sql = format!("DROP TRIGGER IF EXISTS [{prefix}_{table}_{column}]");
+            if exec_sql(db, ctx, &sql) != SQLITE_OK {
+                rollback_savepoint(db, ctx, savepoint);
+                return;
+            }
+        }
+
+        // Same markers as `is_spatialite_blob`: start, then the MBR end of
+        // the regular layout or the byte order of a TinyPoint, then end.
         let sql = format!(
-            "CREATE VIRTUAL TABLE IF NOT EXISTS [{rtree}] USING rtree(id, xmin, xmax, ymin, ymax)"
+            "UPDATE [{table}] SET [{column}] = ST_GeomFromSpatiaLite([{column}]) \
+             WHERE typeof([{column}]) = 'blob' \
+             AND substr([{column}], 1, 1) = X'00' AND substr([{column}], -1, 1) = X'FE' \
+             AND (substr([{column}], 39, 1) = X'7C' AND substr([{column}], 2, 1) IN (X'00', X'01') \
+               OR substr([{column}], 2, 1) IN (X'80', X'81') AND length([{column}]) IN (24, 32, 40))"
         );
         if exec_sql(db, ctx, &sql) != SQLITE_OK {
             rollback_savepoint(db, ctx, savepoint);
             return;
         }
+        let converted = sqlite3_changes(db);
 
-        // 2. Rebuild index contents from the base table (idempotent on repeated calls).
-        let sql = format!("DELETE FROM [{rtree}]");
-        if exec_sql(db, ctx, &sql) != SQLITE_OK {
-            rollback_savepoint(db, ctx, savepoint);
+        if exec_sql(db, ctx, &format!("RELEASE {savepoint}")) != SQLITE_OK {
             return;
         }
+        set_i32(ctx, converted);
+    });
+}
 
-        let sql = format!(
-            "INSERT INTO [{rtree}] \
-             SELECT rowid, ST_XMin([{column}]), ST_XMax([{column}]), \
-             ST_YMin([{column}]), ST_YMax([{column}]) \
-             FROM [{table}] WHERE [{column}] IS NOT NULL AND ST_IsEmpty([{column}]) = 0"
-        );
-        if exec_sql(db, ctx, &sql) != SQLITE_OK {
-            rollback_savepoint(db, ctx, savepoint);
+// File import and export callbacks
+
+/// Whether the calling connection lets SQL touch the filesystem. When it
+/// does not, sets the refusal as the call's error and returns `false`.
+///
+/// The file functions check this after decoding their arguments, so the
+/// argument errors read the same either way.
+unsafe fn ensure_file_access(ctx: *mut sqlite3_context, label: &str) -> bool {
+    if current_connection_settings(ctx).file_access {
+        return true;
+    }
+    set_gis_error(ctx, label, None, &file_access_denied());
+    false
+}
+
+/// The error file functions and file virtual tables report on a
+/// connection without file access.
+pub(super) fn file_access_denied() -> SqliteGisError {
+    SqliteGisError::Io(std::io::Error::new(
+        std::io::ErrorKind::PermissionDenied,
+        "file access is disabled on this connection; the host must register \
+         with register_functions_with_file_access (or set SQLITEGIS_SECURITY=relaxed \
+         before loading the extension)",
+    ))
+}
+
+/// Read the text argument `i` of a file function; NULL and invalid UTF-8
+/// set an error on `ctx` and return `None`.
+unsafe fn get_required_text<'a>(
+    ctx: *mut sqlite3_context,
+    argv: *mut *mut sqlite3_value,
+    i: usize,
+    label: &str,
+    what: &str,
+) -> Option<&'a str> {
+    match get_text(argv, i) {
+        SqlTextArg::Value(v) => Some(v),
+        SqlTextArg::Null => {
+            set_error(ctx, &format!("{label}: {what} must not be NULL"));
+            None
+        }
+        SqlTextArg::InvalidUtf8 => {
+            set_error(ctx, &format!("{label}: {what} must be valid UTF-8 text"));
+            None
+        }
+    }
+}
+
+/// `ImportFlatGeobuf(path, table[, spatial_index])`: see
+/// [`import_flatgeobuf`](super::flatgeobuf::import_flatgeobuf). Returns
+/// the number of rows inserted.
+unsafe extern "C" fn import_flatgeobuf_xfunc(
+    ctx: *mut sqlite3_context,
+    n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    const LABEL: &str = "ImportFlatGeobuf";
+    xfunc_guard(ctx, LABEL, || {
+        let Some(path) = get_required_text(ctx, argv, 0, LABEL, "path") else {
+            return;
+        };
+        let Some(table) = get_required_text(ctx, argv, 1, LABEL, "table name") else {
+            return;
+        };
+        let spatial_index = n > 2 && sqlite3_value_int(*argv.add(2)) != 0;
+        if !ensure_file_access(ctx, LABEL) {
             return;
         }
+        let db = sqlite3_context_db_handle(ctx);
+        match super::flatgeobuf::import_flatgeobuf(db, path, table, spatial_index) {
+            Ok(rows) => set_i64(ctx, rows),
+            Err(e) => set_gis_error(ctx, LABEL, None, &e),
+        }
+    });
+}
 
-        // 3. AFTER INSERT trigger
-        let trigger_insert = format!("{table}_{column}_insert");
-        let sql = format!(
-            "CREATE TRIGGER IF NOT EXISTS [{trigger_insert}] AFTER INSERT ON [{table}] \
-             WHEN NEW.[{column}] IS NOT NULL AND ST_IsEmpty(NEW.[{column}]) = 0 \
-             BEGIN \
-               INSERT INTO [{rtree}] VALUES ( \
-                 NEW.rowid, \
-                 ST_XMin(NEW.[{column}]), ST_XMax(NEW.[{column}]), \
-                 ST_YMin(NEW.[{column}]), ST_YMax(NEW.[{column}]) \
-               ); \
-             END"
-        );
-        if exec_sql(db, ctx, &sql) != SQLITE_OK {
-            rollback_savepoint(db, ctx, savepoint);
+/// `ExportFlatGeobuf(path, query)`: see
+/// [`export_flatgeobuf`](super::flatgeobuf::export_flatgeobuf). Returns
+/// the number of features written.
+unsafe extern "C" fn export_flatgeobuf_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    const LABEL: &str = "ExportFlatGeobuf";
+    xfunc_guard(ctx, LABEL, || {
+        let Some(path) = get_required_text(ctx, argv, 0, LABEL, "path") else {
+            return;
+        };
+        let Some(query) = get_required_text(ctx, argv, 1, LABEL, "query") else {
             return;
+        };
+        if !ensure_file_access(ctx, LABEL) {
+            return;
+        }
+        let db = sqlite3_context_db_handle(ctx);
+        match super::flatgeobuf::export_flatgeobuf(db, path, query) {
+            Ok(rows) => set_i64(ctx, rows),
+            Err(e) => set_gis_error(ctx, LABEL, None, &e),
         }
+    });
+}
 
-        // 4. AFTER UPDATE trigger. Broad UPDATE so that rowid changes
-        // (UPDATE ... SET rowid = ... or via INTEGER PRIMARY KEY rewrite)
-        // still propagate to the index. The WHEN clause skips the DELETE
-        // plus INSERT when neither the geometry blob nor the rowid changed,
-        // which is the common case for UPDATEs that only touch unrelated
-        // columns.
-        let trigger_update = format!("{table}_{column}_update");
-        let sql = format!(
-            "CREATE TRIGGER IF NOT EXISTS [{trigger_update}] AFTER UPDATE ON [{table}] \
-             WHEN OLD.[{column}] IS NOT NEW.[{column}] OR OLD.rowid IS NOT NEW.rowid \
-             BEGIN \
-               DELETE FROM [{rtree}] WHERE id = OLD.rowid; \
-               INSERT INTO [{rtree}] \
-                 SELECT NEW.rowid, \
-                   ST_XMin(NEW.[{column}]), ST_XMax(NEW.[{column}]), \
-                   ST_YMin(NEW.[{column}]), ST_YMax(NEW.[{column}]) \
-                 WHERE NEW.[{column}] IS NOT NULL AND ST_IsEmpty(NEW.[{column}]) = 0; \
-             END"
-        );
-        if exec_sql(db, ctx, &sql) != SQLITE_OK {
-            rollback_savepoint(db, ctx, savepoint);
+/// `ImportSHP(path, table[, srid, charset])`: see
+/// [`import_shapefile`](super::shapefile::import_shapefile). A NULL
+/// `srid` or `charset` is taken from the `.prj` or `.cpg`. Returns the
+/// number of rows inserted.
+unsafe extern "C" fn import_shp_xfunc(
+    ctx: *mut sqlite3_context,
+    n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    const LABEL: &str = "ImportSHP";
+    xfunc_guard(ctx, LABEL, || {
+        let Some(path) = get_required_text(ctx, argv, 0, LABEL, "path") else {
+            return;
+        };
+        let Some(table) = get_required_text(ctx, argv, 1, LABEL, "table name") else {
             return;
+        };
+        let srid = (n > 2 && sqlite3_value_type(*argv.add(2)) != SQLITE_NULL)
+            .then(|| sqlite3_value_int(*argv.add(2)));
+        let charset = if n > 3 {
+            match get_text(argv, 3) {
+                SqlTextArg::Value(v) => Some(v),
+                SqlTextArg::Null => None,
+                SqlTextArg::InvalidUtf8 => {
+                    set_error(ctx, &format!("{LABEL}: charset must be valid UTF-8 text"));
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+        if !ensure_file_access(ctx, LABEL) {
+            return;
+        }
+        let db = sqlite3_context_db_handle(ctx);
+        match super::shapefile::import_shapefile(db, path, table, srid, charset) {
+            Ok(rows) => set_i64(ctx, rows),
+            Err(e) => set_gis_error(ctx, LABEL, None, &e),
         }
+    });
+}
 
-        // 5. AFTER DELETE trigger
-        let trigger_delete = format!("{table}_{column}_delete");
-        let sql = format!(
-            "CREATE TRIGGER IF NOT EXISTS [{trigger_delete}] AFTER DELETE ON [{table}] \
-             BEGIN \
-               DELETE FROM [{rtree}] WHERE id = OLD.rowid; \
-             END"
-        );
-        if exec_sql(db, ctx, &sql) != SQLITE_OK {
-            rollback_savepoint(db, ctx, savepoint);
+/// `ExportSHP(table, geom_col, path)`: see
+/// [`export_shapefile`](super::shapefile::export_shapefile). Returns the
+/// number of features written.
+unsafe extern "C" fn export_shp_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    const LABEL: &str = "ExportSHP";
+    xfunc_guard(ctx, LABEL, || {
+        let Some(table) = get_required_text(ctx, argv, 0, LABEL, "table name") else {
+            return;
+        };
+        let Some(column) = get_required_text(ctx, argv, 1, LABEL, "geometry column") else {
+            return;
+        };
+        let Some(path) = get_required_text(ctx, argv, 2, LABEL, "path") else {
+            return;
+        };
+        if !ensure_file_access(ctx, LABEL) {
+            return;
+        }
+        let db = sqlite3_context_db_handle(ctx);
+        match super::shapefile::export_shapefile(db, table, column, path) {
+            Ok(rows) => set_i64(ctx, rows),
+            Err(e) => set_gis_error(ctx, LABEL, None, &e),
+        }
+    });
+}
+
+/// `ImportOSM(path[, spatial_index])`: see
+/// [`import_osm_pbf`](super::osm_pbf::import_osm_pbf). Returns the number
+/// of rows inserted across `osm_points`, `osm_lines` and `osm_polygons`.
+unsafe extern "C" fn import_osm_xfunc(
+    ctx: *mut sqlite3_context,
+    n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    const LABEL: &str = "ImportOSM";
+    xfunc_guard(ctx, LABEL, || {
+        let Some(path) = get_required_text(ctx, argv, 0, LABEL, "path") else {
             return;
+        };
+        let spatial_index = n > 1 && sqlite3_value_int(*argv.add(1)) != 0;
+        if !ensure_file_access(ctx, LABEL) {
+            return;
+        }
+        let db = sqlite3_context_db_handle(ctx);
+        match super::osm_pbf::import_osm_pbf(db, path, spatial_index) {
+            Ok(rows) => set_i64(ctx, rows),
+            Err(e) => set_gis_error(ctx, LABEL, None, &e),
         }
+    });
+}
 
-        let sql = format!(
-            "INSERT INTO [{SPATIAL_INDEX_CATALOG_TABLE}] (prefix, table_name, column_name) \
-             VALUES ('{prefix}', '{table}', '{column}') \
-             ON CONFLICT(prefix) DO UPDATE SET \
-             table_name = excluded.table_name, \
-             column_name = excluded.column_name"
-        );
-        if exec_sql(db, ctx, &sql) != SQLITE_OK {
-            rollback_savepoint(db, ctx, savepoint);
+/// `GenerateMBTiles(path, query, minzoom, maxzoom)`: see
+/// [`generate_mbtiles`](super::mbtiles::generate_mbtiles). Returns the
+/// number of tiles written.
+unsafe extern "C" fn generate_mbtiles_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    const LABEL: &str = "GenerateMBTiles";
+    xfunc_guard(ctx, LABEL, || {
+        let Some(path) = get_required_text(ctx, argv, 0, LABEL, "path") else {
+            return;
+        };
+        let Some(query) = get_required_text(ctx, argv, 1, LABEL, "query") else {
+            return;
+        };
+        let mut zooms = [0u32; 2];
+        for (i, (zoom, name)) in zooms.iter_mut().zip(["minzoom", "maxzoom"]).enumerate() {
+            match get_i32_arg(argv, i + 2) {
+                SqlI32Arg::Value(v) if v >= 0 => *zoom = v as u32,
+                SqlI32Arg::Null => {
+                    set_error(ctx, &format!("{LABEL}: {name} must not be NULL"));
+                    return;
+                }
+                SqlI32Arg::Value(_) | SqlI32Arg::OutOfRange(_) => {
+                    set_error(ctx, &format!("{LABEL}: {name} out of range"));
+                    return;
+                }
+                SqlI32Arg::InvalidType => {
+                    set_error(ctx, &format!("{LABEL}: {name} must be integer"));
+                    return;
+                }
+            }
+        }
+        if !ensure_file_access(ctx, LABEL) {
             return;
         }
+        let db = sqlite3_context_db_handle(ctx);
+        match super::mbtiles::generate_mbtiles(db, path, query, zooms[0], zooms[1]) {
+            Ok(tiles) => set_i64(ctx, tiles),
+            Err(e) => set_gis_error(ctx, LABEL, None, &e),
+        }
+    });
+}
 
-        if exec_sql(db, ctx, &format!("RELEASE {savepoint}")) != SQLITE_OK {
+/// `ImportGeoParquet(path, table[, spatial_index])`: see
+/// [`import_geoparquet`](super::geoparquet::import_geoparquet). Returns
+/// the number of rows inserted.
+#[cfg(feature = "geoparquet")]
+unsafe extern "C" fn import_geoparquet_xfunc(
+    ctx: *mut sqlite3_context,
+    n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    const LABEL: &str = "ImportGeoParquet";
+    xfunc_guard(ctx, LABEL, || {
+        let Some(path) = get_required_text(ctx, argv, 0, LABEL, "path") else {
+            return;
+        };
+        let Some(table) = get_required_text(ctx, argv, 1, LABEL, "table name") else {
+            return;
+        };
+        let spatial_index = n > 2 && sqlite3_value_int(*argv.add(2)) != 0;
+        if !ensure_file_access(ctx, LABEL) {
             return;
         }
-
-        set_i32(ctx, 1);
+        let db = sqlite3_context_db_handle(ctx);
+        match super::geoparquet::import_geoparquet(db, path, table, spatial_index) {
+            Ok(rows) => set_i64(ctx, rows),
+            Err(e) => set_gis_error(ctx, LABEL, None, &e),
+        }
     });
 }
 
-unsafe extern "C" fn drop_spatial_index_xfunc(
+/// `ExportGeoParquet(path, query)`: see
+/// [`export_geoparquet`](super::geoparquet::export_geoparquet). Returns
+/// the number of rows written.
+#[cfg(feature = "geoparquet")]
+unsafe extern "C" fn export_geoparquet_xfunc(
     ctx: *mut sqlite3_context,
     _n: c_int,
     argv: *mut *mut sqlite3_value,
 ) {
-    xfunc_guard(ctx, "DropSpatialIndex", || {
-        let Some((table, column)) = get_table_column(ctx, argv, "DropSpatialIndex") else {
+    const LABEL: &str = "ExportGeoParquet";
+    xfunc_guard(ctx, LABEL, || {
+        let Some(path) = get_required_text(ctx, argv, 0, LABEL, "path") else {
             return;
         };
-
+        let Some(query) = get_required_text(ctx, argv, 1, LABEL, "query") else {
+            return;
+        };
+        if !ensure_file_access(ctx, LABEL) {
+            return;
+        }
         let db = sqlite3_context_db_handle(ctx);
-        let prefix = format!("{table}_{column}");
-        let savepoint = "sqlitegis_drop_spatial_index";
+        match super::geoparquet::export_geoparquet(db, path, query) {
+            Ok(rows) => set_i64(ctx, rows),
+            Err(e) => set_gis_error(ctx, LABEL, None, &e),
+        }
+    });
+}
 
-        if exec_sql(db, ctx, &format!("SAVEPOINT {savepoint}")) != SQLITE_OK {
-            return;
+// Configuration callbacks
+
+/// Read the `name` argument of `sqlitegis_config`.
+unsafe fn get_config_name<'a>(
+    ctx: *mut sqlite3_context,
+    argv: *mut *mut sqlite3_value,
+) -> Option<&'a str> {
+    match get_text(argv, 0) {
+        SqlTextArg::Value(v) => Some(v),
+        SqlTextArg::Null => {
+            set_error(ctx, "sqlitegis_config: name must not be NULL");
+            None
+        }
+        SqlTextArg::InvalidUtf8 => {
+            set_error(ctx, "sqlitegis_config: name must be valid UTF-8 text");
+            None
         }
+    }
+}
 
-        if !ensure_spatial_index_catalog_table(db, ctx, "DropSpatialIndex") {
-            rollback_savepoint(db, ctx, savepoint);
+/// `sqlitegis_config` switch for GeoPackage geometry arguments. Unlike the
+/// limits it can be turned on and off from SQL, since it only widens what
+/// decodes instead of bounding work.
+const ACCEPT_GPB: &str = "accept_gpb";
+const AUTODETECT_INPUT: &str = "autodetect_input";
+/// `sqlitegis_config` view of [`ConnectionSettings::file_access`]: readable,
+/// and settable to `0` only.
+const FILE_ACCESS: &str = "file_access";
+
+/// SQL spelling of a limit: `0` means unlimited.
+fn limit_to_sql(value: Option<usize>) -> i64 {
+    value.map_or(0, |v| i64::try_from(v).unwrap_or(i64::MAX))
+}
+
+unsafe extern "C" fn sqlitegis_config_get_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "sqlitegis_config", || {
+        let Some(name) = get_config_name(ctx, argv) else {
+            return;
+        };
+        let settings = current_connection_settings(ctx);
+        if name == ACCEPT_GPB {
+            set_bool(ctx, settings.input_formats.gpb);
             return;
         }
-        if !validate_spatial_index_catalog_shape(db, ctx, "DropSpatialIndex") {
-            rollback_savepoint(db, ctx, savepoint);
+        if name == FILE_ACCESS {
+            set_bool(ctx, settings.file_access);
+            return;
+        }
+        if name == AUTODETECT_INPUT {
+            let formats = InputFormats {
+                gpb: true,
+                ..settings.input_formats
+            };
+            set_bool(ctx, formats == InputFormats::ALL);
             return;
         }
+        match settings.limits.get(name) {
+            Ok(value) => set_i64(ctx, limit_to_sql(value)),
+            Err(e) => set_gis_error(ctx, "sqlitegis_config", Some(1), &e),
+        }
+    });
+}
 
-        let ownership = match ensure_spatial_index_objects_owned_by_table(
-            db,
-            ctx,
-            table,
-            column,
-            "DropSpatialIndex",
-        ) {
-            Some(v) => v,
-            None => {
-                rollback_savepoint(db, ctx, savepoint);
+/// Tighten one of the connection's limits, toggle `accept_gpb` or
+/// `autodetect_input` (every format in [`InputFormats::ALL`], GPB included;
+/// turning it off keeps GPB only if `accept_gpb` was set) with `0` or
+/// `1`, or turn `file_access`
+/// off with `0`. SQL can only lower a limit (or set one that is currently
+/// unlimited) and never turn file access on, so untrusted SQL cannot lift
+/// what the host configured through [`register_functions_with_limits`]
+/// and [`register_functions_with_file_access`].
+unsafe extern "C" fn sqlitegis_config_set_xfunc(
+    ctx: *mut sqlite3_context,
+    _n: c_int,
+    argv: *mut *mut sqlite3_value,
+) {
+    xfunc_guard(ctx, "sqlitegis_config", || {
+        let Some(name) = get_config_name(ctx, argv) else {
+            return;
+        };
+        let value = match sqlite3_value_type(*argv.add(1)) {
+            SQLITE_INTEGER => sqlite3_value_int64(*argv.add(1)),
+            _ => {
+                set_error(ctx, "sqlitegis_config: value must be integer");
                 return;
             }
         };
-        if ownership == SpatialIndexOwnership::Absent {
-            if exec_sql(db, ctx, &format!("RELEASE {savepoint}")) != SQLITE_OK {
+        let Some(shared) = connection_settings(ctx) else {
+            set_error(ctx, "sqlitegis_config: connection has no settings");
+            return;
+        };
+        let mut settings = shared.lock().unwrap_or_else(PoisonError::into_inner);
+        if name == FILE_ACCESS {
+            if value != 0 && !(value == 1 && settings.file_access) {
+                set_error(
+                    ctx,
+                    &format!(
+                        "sqlitegis_config: {name} can only be turned off from SQL, got {value}"
+                    ),
+                );
                 return;
             }
-            set_i32(ctx, 1);
+            settings.file_access = value == 1;
+            set_i64(ctx, value);
             return;
         }
-
-        // Drop triggers first, then the R-tree table. Ownership has already
-        // been verified to avoid cross-table collisions on derived names.
-        for suffix in &["_insert", "_update", "_delete"] {
-            let sql = format!("DROP TRIGGER IF EXISTS [{prefix}{suffix}]");
-            if exec_sql(db, ctx, &sql) != SQLITE_OK {
-                rollback_savepoint(db, ctx, savepoint);
+        if name == ACCEPT_GPB || name == AUTODETECT_INPUT {
+            if !matches!(value, 0 | 1) {
+                set_error(
+                    ctx,
+                    &format!("sqlitegis_config: {name} must be 0 or 1, got {value}"),
+                );
                 return;
             }
-        }
-        let sql = format!("DROP TABLE IF EXISTS [{prefix}_rtree]");
-        if exec_sql(db, ctx, &sql) != SQLITE_OK {
-            rollback_savepoint(db, ctx, savepoint);
+            if name == ACCEPT_GPB {
+                settings.accept_gpb = value == 1;
+                settings.input_formats.gpb = value == 1;
+            } else if value == 1 {
+                settings.input_formats = InputFormats::ALL;
+            } else {
+                settings.input_formats = InputFormats {
+                    gpb: settings.accept_gpb,
+                    ..InputFormats::NONE
+                };
+            }
+            set_i64(ctx, value);
             return;
         }
-
-        let sql = format!("DELETE FROM [{SPATIAL_INDEX_CATALOG_TABLE}] WHERE prefix = '{prefix}'");
-        if exec_sql(db, ctx, &sql) != SQLITE_OK {
-            rollback_savepoint(db, ctx, savepoint);
+        let Some(value) = usize::try_from(value).ok().filter(|v| *v > 0) else {
+            set_error(
+                ctx,
+                &format!("sqlitegis_config: value must be positive, got {value}"),
+            );
             return;
-        }
-
-        if exec_sql(db, ctx, &format!("RELEASE {savepoint}")) != SQLITE_OK {
+        };
+        let limits = &mut settings.limits;
+        let current = match limits.get(name) {
+            Ok(current) => current,
+            Err(e) => {
+                set_gis_error(ctx, "sqlitegis_config", Some(1), &e);
+                return;
+            }
+        };
+        if let Some(current) = current.filter(|current| value > *current) {
+            set_error(
+                ctx,
+                &format!(
+                    "sqlitegis_config: {name} can only be lowered from SQL \
+                     (current {current}, requested {value})"
+                ),
+            );
             return;
         }
-
-        set_i32(ctx, 1);
+        match limits.set(name, Some(value)) {
+            Ok(()) => set_i64(ctx, limit_to_sql(Some(value))),
+            Err(e) => set_gis_error(ctx, "sqlitegis_config", Some(1), &e),
+        }
     });
 }
 
 // Registration
 
 type XFunc = unsafe extern "C" fn(*mut sqlite3_context, c_int, *mut *mut sqlite3_value);
+type XFinal = unsafe extern "C" fn(*mut sqlite3_context);
+
+/// The C callbacks behind one registration: `xFunc` for a scalar function,
+/// `xStep`/`xFinal` for an aggregate.
+#[derive(Clone, Copy)]
+enum SqliteCallback {
+    Scalar(XFunc),
+    Aggregate { step: XFunc, finalize: XFinal },
+}
 
 #[derive(Clone, Copy)]
 struct SqliteCallbackSpec {
     name: &'static str,
     n_arg: i32,
-    xfunc: XFunc,
+    callback: SqliteCallback,
 }
 
 macro_rules! callback_spec {
@@ -1731,13 +3951,27 @@ macro_rules! callback_spec {
         SqliteCallbackSpec {
             name: $name,
             n_arg: $n_arg,
-            xfunc: $xfunc,
+            callback: SqliteCallback::Scalar($xfunc),
+        }
+    };
+}
+
+macro_rules! aggregate_callback_spec {
+    ($name:literal, $n_arg:literal, $step:ident, $finalize:ident) => {
+        SqliteCallbackSpec {
+            name: $name,
+            n_arg: $n_arg,
+            callback: SqliteCallback::Aggregate {
+                step: $step,
+                finalize: $finalize,
+            },
         }
     };
 }
 
 include!("deterministic_callbacks.rs");
 include!("direct_only_callbacks.rs");
+include!("aggregate_callbacks.rs");
 
 const fn const_str_eq(a: &str, b: &str) -> bool {
     let a_bytes = a.as_bytes();
@@ -1775,25 +4009,83 @@ const _: () = assert_catalog_callback_parity(
 );
 const _: () =
     assert_catalog_callback_parity(SQLITE_DIRECT_ONLY_FUNCTIONS, SQLITE_DIRECT_ONLY_CALLBACKS);
+const _: () =
+    assert_catalog_callback_parity(SQLITE_AGGREGATE_FUNCTIONS, SQLITE_AGGREGATE_CALLBACKS);
 
-unsafe fn reg(db: *mut sqlite3, name: &str, n_arg: c_int, flags: c_int, xfunc: XFunc) -> c_int {
+unsafe fn reg(
+    db: *mut sqlite3,
+    name: &str,
+    n_arg: c_int,
+    flags: c_int,
+    callback: SqliteCallback,
+    settings: &Arc<SharedSettings>,
+) -> c_int {
     let c_name = match CString::new(name) {
         Ok(v) => v,
         Err(_) => return SQLITE_ERROR,
     };
+    let flags = flags | extra_flags(name);
+    let (xfunc, xstep, xfinal) = match callback {
+        SqliteCallback::Scalar(xfunc) => (Some(xfunc), None, None),
+        SqliteCallback::Aggregate { step, finalize } => (None, Some(step), Some(finalize)),
+    };
+    // SQLite calls the destructor even when registration fails, so the
+    // clone is never leaked.
     sqlite3_create_function_v2(
         db,
         c_name.as_ptr(),
         n_arg,
         flags,
-        std::ptr::null_mut(),
-        Some(xfunc),
-        None,
-        None,
-        None,
+        Arc::into_raw(Arc::clone(settings)) as *mut c_void,
+        xfunc,
+        xstep,
+        xfinal,
+        Some(drop_connection_settings),
+    )
+}
+
+unsafe fn reg_module(
+    db: *mut sqlite3,
+    name: &str,
+    module: &'static sqlite3_module,
+    settings: &Arc<SharedSettings>,
+) -> c_int {
+    let c_name = match CString::new(name) {
+        Ok(v) => v,
+        Err(_) => return SQLITE_ERROR,
+    };
+    // As with `reg`, SQLite calls the destructor even on failure.
+    sqlite3_create_module_v2(
+        db,
+        c_name.as_ptr(),
+        module,
+        Arc::into_raw(Arc::clone(settings)) as *mut c_void,
+        Some(drop_connection_settings),
     )
 }
 
+/// Snapshot of the settings a virtual table module was registered with,
+/// passed to its `xCreate` and `xConnect` as client data.
+unsafe fn module_settings(aux: *mut c_void) -> ConnectionSettings {
+    (aux as *const SharedSettings)
+        .as_ref()
+        .map(|shared| *shared.lock().unwrap_or_else(PoisonError::into_inner))
+        .unwrap_or_default()
+}
+
+/// Run `f` under the limits and input formats of the settings a virtual
+/// table module was registered with.
+pub(super) unsafe fn with_module_settings<R>(aux: *mut c_void, f: impl FnOnce() -> R) -> R {
+    let settings = module_settings(aux);
+    with_input_formats(settings.input_formats, || with_limits(settings.limits, f))
+}
+
+/// Whether the connection a virtual table module was registered on lets
+/// SQL read files.
+pub(super) unsafe fn module_file_access(aux: *mut c_void) -> bool {
+    module_settings(aux).file_access
+}
+
 /// Register all SQLiteGIS spatial functions into an open SQLite database.
 ///
 /// Returns `SQLITE_OK` (0) on success, or the first error code on failure.
@@ -1839,13 +4131,79 @@ unsafe fn reg(db: *mut sqlite3, name: &str, n_arg: c_int, flags: c_int, xfunc: X
 /// }
 /// ```
 pub unsafe fn register_functions(db: *mut sqlite3) -> c_int {
+    register_functions_with_limits(db, Limits::default())
+}
+
+/// Like [`register_functions`], but every function on this connection
+/// enforces `limits` (see [`crate::core::limits`]). Exceeding a limit fails
+/// the call with a `... exceeded: value > max` error.
+///
+/// SQL on the connection can read the limits with
+/// `sqlitegis_config('max_vertices')` (`0` means unlimited) and tighten
+/// them with `sqlitegis_config('max_vertices', 10000)`, but never raise or
+/// remove them.
+///
+/// # Safety
+/// `db` must be a valid, open SQLite database handle for the lifetime of the call.
+pub unsafe fn register_functions_with_limits(db: *mut sqlite3, limits: Limits) -> c_int {
+    register(
+        db,
+        ConnectionSettings {
+            limits,
+            ..ConnectionSettings::default()
+        },
+    )
+}
+
+/// Like [`register_functions_with_limits`], and also lets SQL on this
+/// connection read and write files through the `Import*` and `Export*`
+/// functions, `GenerateMBTiles` and the `sqlitegis_geojson` and
+/// `sqlitegis_csv` virtual tables.
+///
+/// Every other registration leaves file access off, since any SQL the
+/// connection runs could otherwise read or overwrite whatever the process
+/// can reach. Only call this for connections whose SQL you trust as much
+/// as the process itself. SQL can check the setting with
+/// `sqlitegis_config('file_access')` and give it up with
+/// `sqlitegis_config('file_access', 0)`, but cannot turn it on.
+///
+/// # Safety
+/// `db` must be a valid, open SQLite database handle for the lifetime of the call.
+pub unsafe fn register_functions_with_file_access(db: *mut sqlite3, limits: Limits) -> c_int {
+    register(
+        db,
+        ConnectionSettings {
+            limits,
+            file_access: true,
+            ..ConnectionSettings::default()
+        },
+    )
+}
+
+unsafe fn register(db: *mut sqlite3, settings: ConnectionSettings) -> c_int {
+    let settings = Arc::new(Mutex::new(settings));
     for callback in SQLITE_DETERMINISTIC_CALLBACKS {
         let rc = reg(
             db,
             callback.name,
             callback.n_arg as c_int,
             DET,
-            callback.xfunc,
+            callback.callback,
+            &settings,
+        );
+        if rc != SQLITE_OK {
+            return rc;
+        }
+    }
+
+    for callback in SQLITE_AGGREGATE_CALLBACKS {
+        let rc = reg(
+            db,
+            callback.name,
+            callback.n_arg as c_int,
+            DET,
+            callback.callback,
+            &settings,
         );
         if rc != SQLITE_OK {
             return rc;
@@ -1858,13 +4216,21 @@ pub unsafe fn register_functions(db: *mut sqlite3) -> c_int {
             callback.name,
             callback.n_arg as c_int,
             DIRECT,
-            callback.xfunc,
+            callback.callback,
+            &settings,
         );
         if rc != SQLITE_OK {
             return rc;
         }
     }
 
+    for (name, module) in &FILE_MODULES {
+        let rc = reg_module(db, name, module, &settings);
+        if rc != SQLITE_OK {
+            return rc;
+        }
+    }
+
     SQLITE_OK
 }
 
@@ -1932,6 +4298,10 @@ unsafe extern "C" fn sqlitegis_auto_extension_init(
 
 /// `sqlite3_sqlitegis_init` is the entry point called by SQLite when loading
 /// this library as a loadable extension (`SELECT load_extension('libsqlitegis')`).
+///
+/// File access stays off unless the process environment has
+/// `SQLITEGIS_SECURITY=relaxed`, the only switch a host that loads the
+/// extension from SQL can set (as SpatiaLite's `SPATIALITE_SECURITY`).
 #[cfg(all(feature = "sqlite-extension", not(target_arch = "wasm32")))]
 #[no_mangle]
 pub unsafe extern "C" fn sqlite3_sqlitegis_init(
@@ -1939,7 +4309,15 @@ pub unsafe extern "C" fn sqlite3_sqlitegis_init(
     _pz_err_msg: *mut *mut std::ffi::c_char,
     _p_api: *mut sqlite3_api_routines,
 ) -> c_int {
-    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| register_functions(db))) {
+    let relaxed = std::env::var_os("SQLITEGIS_SECURITY").is_some_and(|v| v == "relaxed");
+    let register = || {
+        if relaxed {
+            register_functions_with_file_access(db, Limits::default())
+        } else {
+            register_functions(db)
+        }
+    };
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(register)) {
         Ok(rc) => rc,
         Err(_) => SQLITE_ERROR,
     }
@@ -1972,6 +4350,18 @@ mod tests {
         });
     }
 
+    /// Raises the connection's interrupt flag mid-statement, then passes
+    /// its BLOB argument through so the enclosing function runs with the
+    /// flag set.
+    unsafe extern "C" fn interrupting_xfunc(
+        ctx: *mut sqlite3_context,
+        _n: c_int,
+        argv: *mut *mut sqlite3_value,
+    ) {
+        sqlite3_interrupt(sqlite3_context_db_handle(ctx));
+        set_blob(ctx, get_raw_blob(argv, 0).expect("blob argument"));
+    }
+
     unsafe fn open_db() -> *mut sqlite3 {
         let mut db = ptr::null_mut();
         let path = CString::new(":memory:").expect("valid sqlite path");
@@ -2268,6 +4658,90 @@ mod tests {
         }
     }
 
+    #[test]
+    fn heavy_operations_return_sqlite_interrupt() {
+        unsafe {
+            let db = open_db();
+            assert_eq!(register_functions(db), SQLITE_OK);
+
+            let func_name = CString::new("Interrupting").expect("valid function name");
+            let rc = sqlite3_create_function_v2(
+                db,
+                func_name.as_ptr(),
+                1,
+                SQLITE_UTF8,
+                ptr::null_mut(),
+                Some(interrupting_xfunc),
+                None,
+                None,
+                None,
+            );
+            assert_eq!(rc, SQLITE_OK, "function registration should succeed");
+
+            // Long enough that the per-vertex / per-segment / per-member
+            // loops cross `INTERRUPT_POLL_STRIDE`; the polygon operations
+            // only poll at their stage boundaries, which check on the
+            // first poll.
+            let coords: Vec<String> = (0..1000).map(|i| format!("{i} {}", i % 2)).collect();
+            let line = format!("ST_GeomFromText('LINESTRING({})')", coords.join(","));
+            let points: Vec<String> = (0..1000).map(|i| format!("POINT({i} 0)")).collect();
+            let collection = format!(
+                "ST_GeomFromText('GEOMETRYCOLLECTION({})')",
+                points.join(",")
+            );
+            let square = "ST_GeomFromText('POLYGON((0 0,2 0,2 2,0 2,0 0))')";
+            let shifted = "ST_GeomFromText('POLYGON((1 1,3 1,3 3,1 3,1 1))')";
+            for (label, sql) in [
+                (
+                    "ST_Union",
+                    format!("SELECT ST_Union(Interrupting({square}), {shifted})"),
+                ),
+                (
+                    "ST_Difference",
+                    format!("SELECT ST_Difference(Interrupting({square}), {shifted})"),
+                ),
+                (
+                    "ST_SymDifference",
+                    format!("SELECT ST_SymDifference(Interrupting({square}), {shifted})"),
+                ),
+                (
+                    "ST_Buffer",
+                    format!("SELECT ST_Buffer(Interrupting({square}), 0.1)"),
+                ),
+                (
+                    "ST_HausdorffDistance",
+                    format!("SELECT ST_HausdorffDistance(Interrupting({line}), {line})"),
+                ),
+                (
+                    "ST_Intersection",
+                    format!("SELECT ST_Intersection({line}, Interrupting({line}))"),
+                ),
+                (
+                    "ST_Buffer",
+                    format!("SELECT ST_Buffer(Interrupting({collection}), 0.1)"),
+                ),
+            ] {
+                let sql_c = CString::new(sql).expect("valid SQL");
+                let mut stmt = ptr::null_mut();
+                let rc = sqlite3_prepare_v2(db, sql_c.as_ptr(), -1, &mut stmt, ptr::null_mut());
+                assert_eq!(rc, SQLITE_OK, "{label}");
+                let step = sqlite3_step(stmt);
+                let msg = CStr::from_ptr(sqlite3_errmsg(db))
+                    .to_string_lossy()
+                    .into_owned();
+                sqlite3_finalize(stmt);
+                assert_eq!(step, SQLITE_INTERRUPT, "{label}: {msg}");
+                assert_eq!(msg, format!("{label} [interrupted]: interrupted"));
+            }
+
+            // The flag clears once no statement is running.
+            let value = query_value(db, "SELECT ST_Buffer(ST_Point(0, 0), 1.0)");
+            assert!(matches!(value, Ok(QueryValue::Blob(_))), "{value:?}");
+
+            close_db(db);
+        }
+    }
+
     #[test]
     fn register_functions_semantic_smoke_covers_full_catalog() {
         unsafe {
@@ -2292,7 +4766,8 @@ mod tests {
         unsafe {
             let db = open_db();
 
-            let rc = register_functions(db);
+            // With file access, so the file functions reach the filesystem.
+            let rc = register_functions_with_file_access(db, Limits::default());
             assert_eq!(rc, SQLITE_OK, "register_functions should succeed");
 
             assert_eq!(
@@ -2310,4 +4785,23 @@ mod tests {
             close_db(db);
         }
     }
+
+    #[test]
+    fn register_functions_aggregate_semantic_smoke() {
+        unsafe {
+            let db = open_db();
+
+            let rc = register_functions(db);
+            assert_eq!(rc, SQLITE_OK, "register_functions should succeed");
+
+            for spec in SQLITE_AGGREGATE_FUNCTIONS {
+                for case in spec.semantic_cases {
+                    let result = query_value(db, case.sql);
+                    assert_semantic_expectation(spec, case, result);
+                }
+            }
+
+            close_db(db);
+        }
+    }
 }
@@ -0,0 +1,145 @@
+//! FlatGeobuf import and export over a raw `*mut sqlite3`, the Rust side
+//! of the `ImportFlatGeobuf` and `ExportFlatGeobuf` SQL functions.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+
+use super::sqlite_compat::sqlite3;
+use super::table_io::{for_each_feature, import_features, in_savepoint, query_layout};
+use crate::core::error::Result;
+use crate::core::flatgeobuf::{FgbReader, FgbWriter};
+
+/// Stream the FlatGeobuf file at `path` into `table` and return the
+/// number of rows inserted.
+///
+/// A missing table is created with a `geom` BLOB column holding EWKB plus
+/// one column per attribute, declared `INTEGER`, `REAL`, `TEXT` or `BLOB`.
+/// An existing table must have a `geom` column and the file's attribute
+/// columns. With `spatial_index`, `CreateSpatialIndex(table, 'geom')`
+/// indexes the column once the rows are in; it and the `ST_*` functions
+/// its triggers call must be registered on `db` (see
+/// [`register_functions`](crate::sqlite::register_functions)). The import
+/// runs in one savepoint, so a failure leaves the database untouched.
+///
+/// # Safety
+///
+/// `db` must be a valid, open SQLite connection handle.
+pub unsafe fn import_flatgeobuf(
+    db: *mut sqlite3,
+    path: impl AsRef<Path>,
+    table: &str,
+    spatial_index: bool,
+) -> Result<i64> {
+    let reader = FgbReader::open(BufReader::new(File::open(path)?))?;
+    let fields = reader.header().fields.clone();
+    import_features(db, table, &fields, reader, spatial_index)
+}
+
+/// Run the read-only `query` on `db`, write its rows to a FlatGeobuf file
+/// at `path` with a packed Hilbert R-tree index, and return the number of
+/// features written.
+///
+/// The geometry is the `geom` column, or else the first column holding
+/// only geometry BLOBs and NULLs. Every other column becomes an attribute
+/// typed after its values: `Long` for integers, `Double` for reals or a
+/// mix of integers and reals, `Binary` for BLOBs and `String` for text or
+/// any other mix. The file's CRS is the geometries' shared SRID, and rows
+/// without a geometry leave the file without an index.
+///
+/// The query runs twice, once to type the columns and once to stream its
+/// rows into the writer, which spills them to a temporary file; memory
+/// grows only by the index's 48 bytes per feature.
+///
+/// # Safety
+///
+/// `db` must be a valid, open SQLite connection handle.
+pub unsafe fn export_flatgeobuf(
+    db: *mut sqlite3,
+    path: impl AsRef<Path>,
+    query: &str,
+) -> Result<i64> {
+    let path = path.as_ref();
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let written = in_savepoint(db, || {
+        let layout = query_layout(db, query, None)?;
+        let mut writer = FgbWriter::new(name, layout.fields.clone())?;
+        for_each_feature(db, query, &layout, |feature| writer.add_feature(&feature))?;
+        writer.finish(BufWriter::new(File::create(path)?))
+    })?;
+    Ok(written as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sqlite::test_support::{assert_import_rejects_malformed, places};
+
+    #[test]
+    fn export_then_import_round_trips_rows() {
+        let db = places(3857);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("places.fgb");
+        // SAFETY: the handle stays open while `db` lives.
+        unsafe {
+            assert_eq!(
+                export_flatgeobuf(db.0, &path, "SELECT * FROM places").unwrap(),
+                3
+            );
+            assert_eq!(import_flatgeobuf(db.0, &path, "copy", true).unwrap(), 3);
+        }
+        assert_eq!(
+            db.query_i64(
+                "SELECT count(*) FROM places p JOIN copy c ON c.id = p.id \
+                 WHERE c.name IS p.name AND c.score IS p.score AND c.geom = p.geom"
+            )
+            .unwrap(),
+            3
+        );
+        assert_eq!(
+            db.query_i64("SELECT count(*) FROM copy_geom_rtree")
+                .unwrap(),
+            3
+        );
+    }
+
+    #[test]
+    fn export_writes_an_index_only_when_every_row_has_a_geometry() {
+        let db = places(3857);
+        db.query_rows("INSERT INTO places VALUES (4, 'd', NULL, NULL)")
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let header = |query: &str| {
+            let path = dir.path().join("places.fgb");
+            // SAFETY: the handle stays open while `db` lives.
+            unsafe { export_flatgeobuf(db.0, &path, query) }.unwrap();
+            let reader = FgbReader::open(BufReader::new(File::open(&path).unwrap())).unwrap();
+            reader.header().clone()
+        };
+        let indexed = header("SELECT * FROM places WHERE geom IS NOT NULL");
+        assert_eq!(indexed.name.as_deref(), Some("places"));
+        assert_eq!(indexed.srid, Some(3857));
+        assert_eq!(indexed.features_count, 3);
+        assert_ne!(indexed.index_node_size, 0);
+        let unindexed = header("SELECT * FROM places");
+        assert_eq!(unindexed.features_count, 4);
+        assert_eq!(unindexed.index_node_size, 0);
+    }
+
+    #[test]
+    fn import_rejects_malformed_files() {
+        let db = places(3857);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("places.fgb");
+        // SAFETY: the handle stays open while `db` lives.
+        unsafe { export_flatgeobuf(db.0, &path, "SELECT * FROM places") }.unwrap();
+        let valid = std::fs::read(&path).unwrap();
+        // SAFETY: as above.
+        assert_import_rejects_malformed(&db, &valid, |bad| unsafe {
+            import_flatgeobuf(db.0, bad, "copy", false)
+        });
+    }
+}
@@ -0,0 +1,147 @@
+//! GeoParquet import and export over a raw `*mut sqlite3`, the Rust side
+//! of the `ImportGeoParquet` and `ExportGeoParquet` SQL functions.
+
+use std::fs::File;
+use std::path::Path;
+
+use super::sqlite_compat::sqlite3;
+use super::table_io::{
+    for_each_feature, import_features, in_savepoint, query_layout, GEOMETRY_COLUMN,
+};
+use crate::core::error::Result;
+use crate::core::geoparquet::{GeoParquetReader, GeoParquetWriter};
+
+/// Stream the GeoParquet file at `path` into `table` and return the number
+/// of rows inserted.
+///
+/// The primary geometry column lands in a `geom` BLOB column as EWKB with
+/// the SRID of the column's CRS; any other geometry column becomes a BLOB
+/// column of EWKB, and the remaining columns are declared `INTEGER`,
+/// `REAL`, `TEXT` or `BLOB`. A missing table is created; an existing one
+/// must have those columns. With `spatial_index`,
+/// `CreateSpatialIndex(table, 'geom')` indexes the column once the rows
+/// are in, and must be registered on `db` (see
+/// [`register_functions`](crate::sqlite::register_functions)). The import
+/// runs in one savepoint, so a failure leaves the database untouched.
+///
+/// # Safety
+///
+/// `db` must be a valid, open SQLite connection handle.
+pub unsafe fn import_geoparquet(
+    db: *mut sqlite3,
+    path: impl AsRef<Path>,
+    table: &str,
+    spatial_index: bool,
+) -> Result<i64> {
+    let reader = GeoParquetReader::open(File::open(path)?)?;
+    let fields = reader.fields().to_vec();
+    import_features(db, table, &fields, reader, spatial_index)
+}
+
+/// Run the read-only `query` on `db`, write its rows to a GeoParquet file
+/// at `path`, and return the number of rows written.
+///
+/// The geometry is the `geom` column, or else the first column holding
+/// only geometry BLOBs and NULLs, and is written as a WKB `geom` column
+/// whose `geo` metadata records the bounding box, the geometry types and
+/// the CRS of the geometries' shared SRID. Every other column is typed
+/// after its values: `INT64` for integers, `DOUBLE` for reals or a mix of
+/// integers and reals, binary for BLOBs and UTF-8 strings for text or any
+/// other mix.
+///
+/// The query runs twice, once to type the columns and once to stream its
+/// rows into the writer, so memory holds one row group at a time rather
+/// than the whole result.
+///
+/// # Safety
+///
+/// `db` must be a valid, open SQLite connection handle.
+pub unsafe fn export_geoparquet(
+    db: *mut sqlite3,
+    path: impl AsRef<Path>,
+    query: &str,
+) -> Result<i64> {
+    let path = path.as_ref();
+    let written = in_savepoint(db, || {
+        let layout = query_layout(db, query, None)?;
+        let mut writer =
+            GeoParquetWriter::new(File::create(path)?, GEOMETRY_COLUMN, layout.fields.clone())?;
+        for_each_feature(db, query, &layout, |feature| writer.add_feature(&feature))?;
+        writer.finish()
+    })?;
+    Ok(written as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use serde_json::{json, Value as Json};
+
+    use super::*;
+    use crate::core::geoparquet::GEO_METADATA_KEY;
+    use crate::sqlite::test_support::{assert_import_rejects_malformed, places};
+
+    fn geo_metadata(path: &Path) -> Json {
+        let reader = SerializedFileReader::new(File::open(path).unwrap()).unwrap();
+        let kv = reader.metadata().file_metadata().key_value_metadata();
+        let geo = kv.unwrap().iter().find(|kv| kv.key == GEO_METADATA_KEY);
+        serde_json::from_str(geo.unwrap().value.as_deref().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn export_describes_the_geometry_column_and_keeps_a_projected_srid() {
+        let db = places(32631);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("places.parquet");
+        // SAFETY: the handle stays open while `db` lives.
+        unsafe {
+            assert_eq!(
+                export_geoparquet(db.0, &path, "SELECT * FROM places").unwrap(),
+                3
+            );
+        }
+
+        let geo = geo_metadata(&path);
+        assert_eq!(geo["primary_column"], GEOMETRY_COLUMN);
+        let column = &geo["columns"][GEOMETRY_COLUMN];
+        assert_eq!(column["encoding"], "WKB");
+        assert_eq!(
+            column["geometry_types"],
+            json!(["Point", "LineString", "Polygon"])
+        );
+        assert_eq!(column["bbox"], json!([0.0, 0.0, 3.0, 4.0]));
+        assert_eq!(
+            column["crs"]["id"],
+            json!({ "authority": "EPSG", "code": 32631 })
+        );
+
+        // SAFETY: as above.
+        assert_eq!(
+            unsafe { import_geoparquet(db.0, &path, "copy", false) }.unwrap(),
+            3
+        );
+        assert_eq!(
+            db.query_i64(
+                "SELECT count(*) FROM places p JOIN copy c ON c.id = p.id \
+                 WHERE c.name IS p.name AND c.score IS p.score AND c.geom = p.geom \
+                   AND ST_SRID(c.geom) = 32631"
+            )
+            .unwrap(),
+            3
+        );
+    }
+
+    #[test]
+    fn import_rejects_malformed_files() {
+        let db = places(4326);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("places.parquet");
+        // SAFETY: the handle stays open while `db` lives.
+        unsafe { export_geoparquet(db.0, &path, "SELECT * FROM places") }.unwrap();
+        let valid = std::fs::read(&path).unwrap();
+        // SAFETY: as above.
+        assert_import_rejects_malformed(&db, &valid, |bad| unsafe {
+            import_geoparquet(db.0, bad, "copy", false)
+        });
+    }
+}
@@ -0,0 +1,409 @@
+//! The HTTP/1.1 plumbing of the local servers: argument parsing, one
+//! thread and one read-only connection per client (up to
+//! [`MAX_CONNECTIONS`] at once), keep-alive, and `GET` and `HEAD` requests
+//! answered by a handler. Plain std networking with no TLS; meant for
+//! localhost.
+
+use std::ffi::OsString;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use clap::{Arg, Command};
+
+use super::connection::Connection;
+use super::layers::open_for_serving;
+use crate::core::error::Result;
+
+/// Longest request head read before the connection is dropped.
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+
+/// Clients served at once; further clients get a `503` until one leaves.
+pub(super) const MAX_CONNECTIONS: usize = 64;
+
+/// How long an idle keep-alive connection is held open.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// One `GET` or `HEAD` request.
+pub(super) struct Request<'a> {
+    /// Path without the query string.
+    pub(super) path: &'a str,
+    /// Query string without the `?`, still percent-encoded.
+    #[cfg_attr(not(feature = "ogc-api"), allow(dead_code))]
+    pub(super) query: &'a str,
+    /// `http://` plus the `Host` the client asked for, to build links.
+    pub(super) base: String,
+}
+
+#[cfg_attr(not(feature = "ogc-api"), allow(dead_code))]
+impl Request<'_> {
+    /// The percent-decoded value of query parameter `name`, if present.
+    pub(super) fn param(&self, name: &str) -> Option<String> {
+        self.query.split('&').find_map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key) == name).then(|| percent_decode(value))
+        })
+    }
+}
+
+/// Decode `%XX` escapes and `+` as in `application/x-www-form-urlencoded`.
+#[cfg_attr(not(feature = "ogc-api"), allow(dead_code))]
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                (Some(hi), Some(lo)) => {
+                    out.push(hi << 4 | lo);
+                    i += 2;
+                }
+                _ => out.push(b'%'),
+            },
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// What a handler answers.
+pub(super) struct Response {
+    pub(super) status: u16,
+    pub(super) content_type: &'static str,
+    pub(super) body: Vec<u8>,
+}
+
+impl Response {
+    /// `200` with `value` as `content_type`.
+    pub(super) fn json(value: &serde_json::Value, content_type: &'static str) -> Self {
+        Self {
+            status: 200,
+            content_type,
+            body: value.to_string().into_bytes(),
+        }
+    }
+
+    /// `status` with a plain-text `message`.
+    pub(super) fn error(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: message.into().into_bytes(),
+        }
+    }
+}
+
+/// Answers a request on the client's connection.
+pub(super) type Handler = dyn Fn(&Connection, &Request) -> Response + Send + Sync;
+
+/// Parse `<DB> [--bind ADDR]` for the server binary `name`. On `Err` the
+/// usage error or help has been printed and the process should exit with
+/// the code given.
+pub(super) fn server_args<I, T>(
+    name: &'static str,
+    about: &'static str,
+    args: I,
+) -> std::result::Result<(String, String), ExitCode>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let command = Command::new(name)
+        .version(env!("CARGO_PKG_VERSION"))
+        .about(about)
+        .arg(
+            Arg::new("db")
+                .value_name("DB")
+                .required(true)
+                .help("SQLite database file"),
+        )
+        .arg(
+            Arg::new("bind")
+                .long("bind")
+                .short('b')
+                .value_name("ADDR")
+                .default_value("127.0.0.1:8080")
+                .help("Address to listen on"),
+        );
+    match command.try_get_matches_from(args) {
+        Ok(matches) => {
+            let value = |id| matches.get_one::<String>(id).cloned().unwrap_or_default();
+            Ok((value("db"), value("bind")))
+        }
+        Err(e) => {
+            let _ = e.print();
+            Err(if e.use_stderr() {
+                ExitCode::from(2)
+            } else {
+                ExitCode::SUCCESS
+            })
+        }
+    }
+}
+
+/// Accept clients on `listener` until it fails, serving each on its own
+/// thread with its own read-only connection to `db`, at most
+/// [`MAX_CONNECTIONS`] at a time.
+pub(super) fn serve(listener: &TcpListener, db: &str, handler: Arc<Handler>) -> Result<()> {
+    serve_at_most(listener, db, handler, MAX_CONNECTIONS)
+}
+
+/// [`serve`] with at most `max_connections` clients at a time. A client
+/// over the cap is answered `503` and disconnected without a thread.
+fn serve_at_most(
+    listener: &TcpListener,
+    db: &str,
+    handler: Arc<Handler>,
+    max_connections: usize,
+) -> Result<()> {
+    let active = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let stream = stream?;
+        if active.fetch_add(1, Ordering::AcqRel) >= max_connections {
+            active.fetch_sub(1, Ordering::AcqRel);
+            let _ = stream.set_write_timeout(Some(Duration::from_secs(1)));
+            let busy = Response::error(503, "too many connections, try again later");
+            // Answered before the client's request is read: the
+            // response does not depend on it.
+            let _ = write_response(&stream, &busy, false, true);
+            let _ = stream.shutdown(Shutdown::Write);
+            continue;
+        }
+        let slot = ConnectionSlot(Arc::clone(&active));
+        let db = db.to_string();
+        let handler = Arc::clone(&handler);
+        thread::spawn(move || {
+            serve_connection(stream, &db, &*handler);
+            drop(slot);
+        });
+    }
+    Ok(())
+}
+
+/// One of the [`serve_at_most`] slots, given back on drop so a panicking
+/// handler frees its slot too.
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+/// Serve the requests of one client until it closes the connection or
+/// asks to.
+fn serve_connection(stream: TcpStream, db: &str, handler: &Handler) {
+    let _ = stream.set_read_timeout(Some(IDLE_TIMEOUT));
+    let local = stream
+        .local_addr()
+        .map(|addr| addr.to_string())
+        .unwrap_or_default();
+    let db = open_for_serving(db);
+    let mut reader = BufReader::new(&stream);
+    loop {
+        // A head that does not end within the budget cuts a line short.
+        let mut limited = reader.by_ref().take(MAX_HEAD_BYTES as u64);
+        let mut head = Vec::new();
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match limited.read_line(&mut line) {
+                Ok(0) | Err(_) => return,
+                Ok(_) if !line.ends_with('\n') => return,
+                Ok(_) => {}
+            }
+            if line.trim_end().is_empty() {
+                break;
+            }
+            head.push(line.trim_end().to_string());
+        }
+
+        let mut request_line = head.first().map_or("", String::as_str).split(' ');
+        let (method, target, version) = (
+            request_line.next().unwrap_or_default(),
+            request_line.next().unwrap_or_default(),
+            request_line.next().unwrap_or_default(),
+        );
+        let header = |name: &str| {
+            head.iter().skip(1).find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                key.trim()
+                    .eq_ignore_ascii_case(name)
+                    .then(|| value.trim().to_string())
+            })
+        };
+        let close = match header("connection") {
+            Some(value) if value.eq_ignore_ascii_case("close") => true,
+            Some(value) if value.eq_ignore_ascii_case("keep-alive") => false,
+            _ => version != "HTTP/1.1",
+        };
+        let target = target.split('#').next().unwrap_or_default();
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let request = Request {
+            path,
+            query,
+            base: format!("http://{}", header("host").unwrap_or_else(|| local.clone())),
+        };
+
+        let response = match (&db, method) {
+            (_, method) if method != "GET" && method != "HEAD" => {
+                Response::error(405, "only GET and HEAD are supported")
+            }
+            (Ok(db), _) => handler(db, &request),
+            (Err(e), _) => Response::error(500, e.to_string()),
+        };
+        let written = write_response(&stream, &response, method == "HEAD", close);
+        if written.is_err() || close {
+            return;
+        }
+    }
+}
+
+/// Send `response`, without its body for a `HEAD` request.
+fn write_response(
+    mut writer: impl Write,
+    response: &Response,
+    head_only: bool,
+    close: bool,
+) -> std::io::Result<()> {
+    write!(
+        writer,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\nConnection: {}\r\n\r\n",
+        response.status,
+        reason(response.status),
+        response.content_type,
+        response.body.len(),
+        if close { "close" } else { "keep-alive" }
+    )?;
+    if !head_only {
+        writer.write_all(&response.body)?;
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::SocketAddr;
+
+    /// `GET path` from the server at `addr`, as the response head and
+    /// body.
+    pub(in crate::sqlite) fn get(addr: SocketAddr, path: &str) -> (String, Vec<u8>) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "GET {path} HTTP/1.1\r\nHost: tiles.test\r\nConnection: close\r\n\r\n"
+        )
+        .unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).unwrap();
+        let split = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+        let head = String::from_utf8(response[..split].to_vec()).unwrap();
+        (head, response[split + 4..].to_vec())
+    }
+
+    /// A server over an empty database answering every request with its
+    /// path, at most `max_connections` clients at a time. Returns its
+    /// address and the database file to remove.
+    fn echo_server(name: &str, max_connections: usize) -> (SocketAddr, String) {
+        let path =
+            std::env::temp_dir().join(format!("sqlitegis-http-{name}-{}.db", std::process::id()));
+        let path = path.to_string_lossy().into_owned();
+        drop(Connection::open(&path, true).unwrap());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handler: Arc<Handler> = Arc::new(|_: &Connection, request: &Request| Response {
+            status: 200,
+            content_type: "text/plain",
+            body: request.path.as_bytes().to_vec(),
+        });
+        let db = path.clone();
+        thread::spawn(move || serve_at_most(&listener, &db, handler, max_connections));
+        (addr, path)
+    }
+
+    #[test]
+    fn drops_clients_whose_head_exceeds_the_budget() {
+        let (addr, path) = echo_server("head", MAX_CONNECTIONS);
+        for head in [
+            "a".repeat(MAX_HEAD_BYTES + 1),
+            format!(
+                "GET / HTTP/1.1\r\n{}",
+                "X-Pad: 0123456789abcdef\r\n".repeat(MAX_HEAD_BYTES / 16)
+            ),
+        ] {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            let _ = stream.write_all(head.as_bytes());
+            let mut response = Vec::new();
+            let _ = stream.read_to_end(&mut response);
+            assert!(
+                response.is_empty(),
+                "{}",
+                String::from_utf8_lossy(&response)
+            );
+        }
+        let (head, body) = get(addr, "/still-up");
+        assert!(head.starts_with("HTTP/1.1 200"), "{head}");
+        assert_eq!(body, b"/still-up");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn turns_away_clients_over_the_connection_cap() {
+        let (addr, path) = echo_server("cap", 2);
+        let idle: Vec<TcpStream> = (0..2).map(|_| TcpStream::connect(addr).unwrap()).collect();
+        // Turned away without the server waiting for a request.
+        let mut response = String::new();
+        TcpStream::connect(addr)
+            .unwrap()
+            .read_to_string(&mut response)
+            .unwrap();
+        assert!(response.starts_with("HTTP/1.1 503"), "{response}");
+
+        // A slot frees up once an idle client leaves.
+        drop(idle);
+        let served = (0..100).any(|_| {
+            let (head, _) = get(addr, "/");
+            head.starts_with("HTTP/1.1 200") || {
+                thread::sleep(Duration::from_millis(20));
+                false
+            }
+        });
+        assert!(served);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn query_parameters_are_percent_decoded() {
+        let request = Request {
+            path: "/",
+            query: "bbox=1%2C2,3%2c4&name=a+b%26c&limit",
+            base: String::new(),
+        };
+        assert_eq!(request.param("bbox").as_deref(), Some("1,2,3,4"));
+        assert_eq!(request.param("name").as_deref(), Some("a b&c"));
+        assert_eq!(request.param("limit").as_deref(), Some(""));
+        assert_eq!(request.param("offset"), None);
+    }
+}
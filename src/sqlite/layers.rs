@@ -0,0 +1,342 @@
+//! Spatially indexed geometry columns as the layers the local servers
+//! publish: discovery from the sqlitegis spatial index catalog and from
+//! GeoPackage's `gpkg_geometry_columns`, plus the R-tree window join both
+//! servers query through.
+
+use std::borrow::Cow;
+
+use geo::Coord;
+
+use super::connection::Connection;
+use super::ffi::SPATIAL_INDEX_CATALOG_TABLE;
+use super::table_io::{quote, GEOMETRY_COLUMN};
+use crate::core::error::Result;
+use crate::core::ewkb::{extract_srid, normalize_input};
+use crate::core::features::Value;
+use crate::core::functions::constructors::from_web_mercator;
+use crate::core::gpb::{gpb_to_ewkb, is_gpb};
+
+/// Bound columns of the `{table}_{column}_rtree` tables `CreateSpatialIndex`
+/// builds, as `[xmin, xmax, ymin, ymax]`.
+const SQLITEGIS_RTREE_COLUMNS: [&str; 4] = ["xmin", "xmax", "ymin", "ymax"];
+
+/// Bound columns of GeoPackage's `rtree_{table}_{column}` tables.
+const GPKG_RTREE_COLUMNS: [&str; 4] = ["minx", "maxx", "miny", "maxy"];
+
+/// Coordinate system of a layer's geometries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Projection {
+    /// SRID 3857.
+    WebMercator,
+    /// SRID 4326, or no SRID at all.
+    LonLat,
+}
+
+/// One published geometry column.
+#[derive(Debug)]
+pub(super) struct Layer {
+    /// The table name, or `table.column` for a second geometry column.
+    pub(super) name: String,
+    pub(super) table: String,
+    pub(super) column: String,
+    pub(super) projection: Projection,
+    /// Every other column with its JSON type, `Number` or `String`.
+    pub(super) properties: Vec<(String, &'static str)>,
+    /// Extent in degrees, `[west, south, east, north]`.
+    pub(super) bounds: Option<[f64; 4]>,
+    rtree: String,
+    rtree_columns: [&'static str; 4],
+}
+
+impl Layer {
+    /// `t.`-qualified select list: the geometry column, then the
+    /// properties.
+    pub(super) fn select_list(&self) -> String {
+        std::iter::once(&self.column)
+            .chain(self.properties.iter().map(|(name, _)| name))
+            .map(|name| format!("t.{}", quote(name)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// `FROM` clause joining the table, as `t`, to its R-tree on the rows
+    /// whose box meets `[min, max]` in the layer's coordinates, with the
+    /// parameters it binds.
+    pub(super) fn window(&self, min: Coord, max: Coord) -> (String, Vec<Value>) {
+        let [xmin, xmax, ymin, ymax] = self.rtree_columns;
+        let sql = format!(
+            "FROM {} t JOIN {} r ON r.id = t.rowid \
+             WHERE r.{xmax} >= ? AND r.{xmin} <= ? AND r.{ymax} >= ? AND r.{ymin} <= ?",
+            quote(&self.table),
+            quote(&self.rtree)
+        );
+        let params = vec![
+            Value::Real(min.x),
+            Value::Real(max.x),
+            Value::Real(min.y),
+            Value::Real(max.y),
+        ];
+        (sql, params)
+    }
+}
+
+/// Layers that cannot be published, with the reason why.
+pub(super) type Skipped = Vec<(String, String)>;
+
+/// Open the database at `path` read-only for serving, with GeoPackage
+/// geometries accepted by the SQL functions.
+pub(super) fn open_for_serving(path: &str) -> Result<Connection> {
+    let db = Connection::open_read_only(path)?;
+    db.query_value("SELECT sqlitegis_config('accept_gpb', 1)")?;
+    Ok(db)
+}
+
+/// A stored geometry as EWKB, from EWKB, GeoPackage or any format
+/// [`normalize_input`] accepts.
+pub(super) fn geometry_ewkb(blob: &[u8]) -> Result<Cow<'_, [u8]>> {
+    if is_gpb(blob) {
+        Ok(Cow::Owned(gpb_to_ewkb(blob)?))
+    } else {
+        normalize_input(blob)
+    }
+}
+
+/// The spatially indexed columns of `db` as layers: those in the
+/// sqlitegis spatial index catalog, then the GeoPackage ones with an
+/// `rtree_*` index. Columns without an R-tree, or in an SRID other than
+/// 3857 and 4326, are skipped.
+pub(super) fn find_layers(db: &Connection) -> Result<(Vec<Layer>, Skipped)> {
+    let text = |value: &Value| match value {
+        Value::Text(text) => text.clone(),
+        _ => String::new(),
+    };
+    let exists = |name: &str| -> Result<bool> {
+        Ok(db.query_i64(&format!(
+            "SELECT count(*) FROM sqlite_master WHERE name = '{}'",
+            name.replace('\'', "''")
+        ))? > 0)
+    };
+
+    let mut candidates = Vec::new();
+    if exists(SPATIAL_INDEX_CATALOG_TABLE)? {
+        for row in db.query_rows(&format!(
+            "SELECT table_name, column_name FROM [{SPATIAL_INDEX_CATALOG_TABLE}] \
+             ORDER BY table_name, column_name"
+        ))? {
+            let (table, column) = (text(&row[0]), text(&row[1]));
+            let rtree = format!("{table}_{column}_rtree");
+            candidates.push((table, column, rtree, SQLITEGIS_RTREE_COLUMNS));
+        }
+    }
+    if exists("gpkg_geometry_columns")? {
+        for row in db.query_rows(
+            "SELECT table_name, column_name FROM gpkg_geometry_columns \
+             ORDER BY table_name, column_name",
+        )? {
+            let (table, column) = (text(&row[0]), text(&row[1]));
+            if !candidates
+                .iter()
+                .any(|(t, c, _, _)| *t == table && *c == column)
+            {
+                let rtree = format!("rtree_{table}_{column}");
+                candidates.push((table, column, rtree, GPKG_RTREE_COLUMNS));
+            }
+        }
+    }
+
+    let mut layers = Vec::new();
+    let mut skipped = Vec::new();
+    for (table, column, rtree, rtree_columns) in candidates {
+        // GeoPackage allows one geometry column per table.
+        let name = if column == GEOMETRY_COLUMN || rtree_columns == GPKG_RTREE_COLUMNS {
+            table.clone()
+        } else {
+            format!("{table}.{column}")
+        };
+        if !exists(&rtree)? {
+            skipped.push((name, "no spatial index".to_string()));
+            continue;
+        }
+        let (t, c) = (quote(&table), quote(&column));
+        let sample = db.query_value(&format!(
+            "SELECT {c} FROM {t} WHERE {c} IS NOT NULL LIMIT 1"
+        ))?;
+        let srid = match &sample {
+            Value::Null => None,
+            Value::Blob(blob) => match geometry_ewkb(blob) {
+                Ok(ewkb) => extract_srid(&ewkb),
+                Err(e) => {
+                    skipped.push((name, format!("unreadable geometry: {e}")));
+                    continue;
+                }
+            },
+            _ => {
+                skipped.push((name, "not a geometry column".to_string()));
+                continue;
+            }
+        };
+        let projection = match srid {
+            Some(3857) => Projection::WebMercator,
+            None | Some(0) | Some(4326) => Projection::LonLat,
+            Some(srid) => {
+                skipped.push((name, format!("SRID {srid} is neither 3857 nor 4326")));
+                continue;
+            }
+        };
+        let properties = db
+            .query_rows(&format!("PRAGMA table_info({t})"))?
+            .iter()
+            .filter(|info| text(&info[1]) != column)
+            .map(|info| {
+                let declared = text(&info[2]).to_ascii_uppercase();
+                let numeric = ["INT", "REAL", "FLOA", "DOUB", "NUM"]
+                    .iter()
+                    .any(|affinity| declared.contains(affinity));
+                (text(&info[1]), if numeric { "Number" } else { "String" })
+            })
+            .filter(|(name, _)| !name.is_empty())
+            .collect();
+        let [xmin, xmax, ymin, ymax] = rtree_columns;
+        let extent = db.query_rows(&format!(
+            "SELECT min({xmin}), min({ymin}), max({xmax}), max({ymax}) FROM {}",
+            quote(&rtree)
+        ))?;
+        let bounds = match extent.first().map(Vec::as_slice) {
+            Some([Value::Real(x0), Value::Real(y0), Value::Real(x1), Value::Real(y1)]) => {
+                Some(match projection {
+                    Projection::LonLat => [*x0, *y0, *x1, *y1],
+                    Projection::WebMercator => {
+                        let sw = from_web_mercator(Coord { x: *x0, y: *y0 });
+                        let ne = from_web_mercator(Coord { x: *x1, y: *y1 });
+                        [sw.x, sw.y, ne.x, ne.y]
+                    }
+                })
+            }
+            _ => None,
+        };
+        layers.push(Layer {
+            name,
+            table,
+            column,
+            projection,
+            properties,
+            bounds,
+            rtree,
+            rtree_columns,
+        });
+    }
+    Ok((layers, skipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_indexed_columns_and_skips_the_rest() {
+        let db = Connection::open(":memory:", true).unwrap();
+        for sql in [
+            "CREATE TABLE cities (name TEXT, pop INTEGER, geom BLOB)",
+            "INSERT INTO cities VALUES \
+               ('a', 1, ST_Point(1, 2, 4326)), ('b', 2, ST_Point(3, 5, 4326))",
+            "SELECT CreateSpatialIndex('cities', 'geom')",
+            "CREATE TABLE grid (id INTEGER, shape BLOB)",
+            "INSERT INTO grid VALUES (1, ST_Point(0, 0, 3857))",
+            "SELECT CreateSpatialIndex('grid', 'shape')",
+            "CREATE TABLE lambert (geom BLOB)",
+            "INSERT INTO lambert VALUES (ST_Point(700000, 6600000, 2154))",
+            "SELECT CreateSpatialIndex('lambert', 'geom')",
+            "SELECT gpkgAddGeometryColumn('roads', 'geom', 'LINESTRING', 4326)",
+            "SELECT gpkgAddSpatialIndex('roads', 'geom')",
+            "INSERT INTO roads (geom) VALUES (AsGPB(ST_GeomFromText('LINESTRING(0 0,4 2)', 4326)))",
+            "SELECT gpkgAddGeometryColumn('rivers', 'geom', 'LINESTRING', 4326)",
+        ] {
+            db.query_rows(sql).unwrap();
+        }
+
+        let (layers, skipped) = find_layers(&db).unwrap();
+        let names: Vec<&str> = layers.iter().map(|layer| layer.name.as_str()).collect();
+        assert_eq!(names, ["cities", "grid.shape", "roads"]);
+        assert_eq!(
+            skipped,
+            [
+                (
+                    "lambert".to_string(),
+                    "SRID 2154 is neither 3857 nor 4326".to_string()
+                ),
+                ("rivers".to_string(), "no spatial index".to_string()),
+            ]
+        );
+
+        let cities = &layers[0];
+        assert_eq!(cities.projection, Projection::LonLat);
+        assert_eq!(
+            cities.properties,
+            [
+                ("name".to_string(), "String"),
+                ("pop".to_string(), "Number")
+            ]
+        );
+        let bounds = cities.bounds.unwrap();
+        assert!(bounds
+            .iter()
+            .zip([1.0, 2.0, 3.0, 5.0])
+            .all(|(got, want)| (got - want).abs() < 1e-6));
+        let (from, params) = cities.window(Coord { x: 0.0, y: 0.0 }, Coord { x: 2.0, y: 3.0 });
+        let rows = db
+            .query_rows_with(&format!("SELECT t.name {from}"), &params)
+            .unwrap();
+        assert_eq!(rows, [[Value::Text("a".to_string())]]);
+        assert_eq!(cities.select_list(), "t.\"geom\", t.\"name\", t.\"pop\"");
+
+        assert_eq!(layers[1].projection, Projection::WebMercator);
+        assert_eq!(layers[1].bounds.unwrap()[..2], [0.0, 0.0]);
+        let roads = &layers[2];
+        let (from, params) = roads.window(Coord { x: 1.0, y: 1.0 }, Coord { x: 2.0, y: 2.0 });
+        let rows = db
+            .query_rows_with(&format!("SELECT count(*) {from}"), &params)
+            .unwrap();
+        assert_eq!(rows, [[Value::Integer(1)]]);
+    }
+
+    #[test]
+    fn reports_unreadable_geometry_columns() {
+        let db = Connection::open(":memory:", true).unwrap();
+        for sql in [
+            "CREATE TABLE t (geom BLOB)",
+            "SELECT CreateSpatialIndex('t', 'geom')",
+            "INSERT INTO t VALUES (ST_Point(1, 2))",
+        ] {
+            db.query_rows(sql).unwrap();
+        }
+        // Drop the index triggers so a corrupt blob can be stored.
+        for trigger in db
+            .query_rows("SELECT name FROM sqlite_master WHERE type = 'trigger'")
+            .unwrap()
+        {
+            let [Value::Text(name)] = trigger.as_slice() else {
+                panic!("trigger name {trigger:?}");
+            };
+            db.query_rows(&format!("DROP TRIGGER {}", quote(name)))
+                .unwrap();
+        }
+        // A GeoPackage header with its geometry cut off.
+        db.query_rows("UPDATE t SET geom = substr(AsGPB(ST_Point(1, 2, 4326)), 1, 12)")
+            .unwrap();
+        let (layers, skipped) = find_layers(&db).unwrap();
+        assert!(layers.is_empty());
+        assert_eq!(skipped.len(), 1);
+        assert!(
+            skipped[0].1.starts_with("unreadable geometry"),
+            "{skipped:?}"
+        );
+
+        let gpb = db
+            .query_value("SELECT AsGPB(ST_Point(1, 2, 4326))")
+            .unwrap();
+        let Value::Blob(gpb) = gpb else {
+            panic!("AsGPB returned {gpb:?}");
+        };
+        assert_eq!(extract_srid(&geometry_ewkb(&gpb).unwrap()), Some(4326));
+    }
+}
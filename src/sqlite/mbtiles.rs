@@ -0,0 +1,373 @@
+//! MBTiles vector tile packages over a raw `*mut sqlite3`, the Rust side
+//! of the `GenerateMBTiles` SQL function.
+
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use geo::{Coord, MapCoords, Rect};
+use serde_json::json;
+
+use super::connection::Connection;
+use super::sqlite_compat::sqlite3;
+use super::table_io::{for_each_feature, in_savepoint, query_layout};
+use crate::core::error::{Result, SqliteGisError};
+use crate::core::ewkb::{extract_mbr, parse_ewkb, write_ewkb};
+use crate::core::features::{FieldType, Value};
+use crate::core::functions::constructors::{
+    from_web_mercator, st_tile_envelope, to_web_mercator, MAX_LATITUDE, WEB_MERCATOR_HALF_SIZE,
+};
+use crate::core::functions::mvt::{
+    st_as_mvt_geom, MvtLayer, MvtValue, DEFAULT_BUFFER, DEFAULT_EXTENT,
+};
+use crate::core::index::SpatialIndex;
+
+/// Highest zoom level a package may go to.
+const MAX_ZOOM: u32 = 22;
+
+const SCHEMA: [&str; 3] = [
+    "CREATE TABLE metadata (name TEXT, value TEXT)",
+    "CREATE TABLE tiles (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, \
+     tile_data BLOB)",
+    "CREATE UNIQUE INDEX tile_index ON tiles (zoom_level, tile_column, tile_row)",
+];
+
+/// A geometry in SRID 3857: as it is when already there, projected from
+/// SRID 4326 or no SRID.
+fn web_mercator_ewkb(blob: &[u8]) -> Result<Vec<u8>> {
+    let (geometry, srid) = parse_ewkb(blob)?;
+    match srid {
+        Some(3857) => Ok(blob.to_vec()),
+        None | Some(0) | Some(4326) => {
+            write_ewkb(&geometry.map_coords(to_web_mercator), Some(3857))
+        }
+        Some(srid) => Err(SqliteGisError::InvalidInput(format!(
+            "SRID {srid} is neither 3857 nor 4326"
+        ))),
+    }
+}
+
+/// Run the read-only `query` on `db`, cut its rows into Mapbox Vector
+/// Tiles for zoom levels `minzoom` to `maxzoom`, write them to a new
+/// [MBTiles] 1.3 file at `path` and return the number of tiles written.
+///
+/// The geometry is the `geom` column, or else the first column holding
+/// only geometry BLOBs and NULLs, in SRID 3857 or in SRID 4326 (or none),
+/// which is projected. Every other integer, real or text column becomes
+/// a tile property. The file holds one layer named after its file stem,
+/// and its `metadata` table carries the zoom range, the data's bounds and
+/// the `vector_layers` JSON map viewers read the layer's fields from.
+///
+/// The query's rows are streamed, once to type their columns and once to
+/// load their Web Mercator geometries and attributes into an in-memory
+/// [`SpatialIndex`]; rows without a geometry are not kept. Only the tiles
+/// meeting a feature's bounding box, plus the tile buffer, are rendered;
+/// tiles left without features are not stored. Tiles are gzip-compressed
+/// and stored in TMS row order, as the format requires.
+/// `path` must not exist yet, and on error no file is left behind. A
+/// polygon covering a continent still yields every tile under it at high
+/// zoom levels, so keep `maxzoom` in proportion to the data.
+///
+/// [MBTiles]: https://github.com/mapbox/mbtiles-spec
+///
+/// # Safety
+///
+/// `db` must be a valid, open SQLite connection handle.
+pub unsafe fn generate_mbtiles(
+    db: *mut sqlite3,
+    path: impl AsRef<Path>,
+    query: &str,
+    minzoom: u32,
+    maxzoom: u32,
+) -> Result<i64> {
+    if minzoom > maxzoom || maxzoom > MAX_ZOOM {
+        return Err(SqliteGisError::InvalidInput(format!(
+            "zoom levels must satisfy minzoom <= maxzoom <= {MAX_ZOOM}, \
+             got {minzoom} and {maxzoom}"
+        )));
+    }
+    let path = path.as_ref();
+    // An empty file is an empty database; creating it first refuses an
+    // existing one.
+    File::create_new(path)?;
+    let result = write_mbtiles(db, path, query, minzoom, maxzoom);
+    if result.is_err() {
+        let _ = fs::remove_file(path);
+    }
+    result
+}
+
+/// [`generate_mbtiles`] once the empty file at `path` exists.
+unsafe fn write_mbtiles(
+    db: *mut sqlite3,
+    path: &Path,
+    query: &str,
+    minzoom: u32,
+    maxzoom: u32,
+) -> Result<i64> {
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    // Stream the rows, keeping only the projected geometries and their
+    // properties; index key `i` is the `i`-th geometry loaded, with
+    // `attributes[i]`. Rows without a geometry are dropped on the way.
+    let mut rects = Vec::new();
+    let mut projected = Vec::new();
+    let mut attributes = Vec::new();
+    let fields = in_savepoint(db, || {
+        let layout = query_layout(db, query, None)?;
+        for_each_feature(db, query, &layout, |feature| {
+            let Some(blob) = feature.geometry else {
+                return Ok(());
+            };
+            let blob = web_mercator_ewkb(&blob)?;
+            if let Some(rect) = extract_mbr(&blob)? {
+                rects.push(rect);
+            }
+            projected.push((attributes.len(), blob));
+            attributes.push(feature.properties);
+            Ok(())
+        })?;
+        Ok(layout.fields)
+    })?;
+    let index = SpatialIndex::bulk_load(projected)?;
+    let blobs: Vec<&[u8]> = index.iter().map(|(_, blob)| blob).collect();
+    let extent = rects.iter().copied().reduce(|a, b| {
+        Rect::new(
+            Coord {
+                x: a.min().x.min(b.min().x),
+                y: a.min().y.min(b.min().y),
+            },
+            Coord {
+                x: a.max().x.max(b.max().x),
+                y: a.max().y.max(b.max().y),
+            },
+        )
+    });
+    let [west, south, east, north] = match extent {
+        Some(rect) => {
+            let (sw, ne) = (from_web_mercator(rect.min()), from_web_mercator(rect.max()));
+            [sw.x, sw.y, ne.x, ne.y]
+        }
+        None => [-180.0, -MAX_LATITUDE, 180.0, MAX_LATITUDE],
+    };
+    let vector_fields: serde_json::Map<String, serde_json::Value> = fields
+        .iter()
+        .filter_map(|field| {
+            let kind = match field.field_type {
+                FieldType::Integer | FieldType::Real => "Number",
+                FieldType::Text => "String",
+                FieldType::Blob => return None,
+            };
+            Some((field.name.clone(), json!(kind)))
+        })
+        .collect();
+    let metadata = [
+        ("name", name.clone()),
+        ("format", "pbf".to_string()),
+        ("type", "overlay".to_string()),
+        ("minzoom", minzoom.to_string()),
+        ("maxzoom", maxzoom.to_string()),
+        ("bounds", format!("{west},{south},{east},{north}")),
+        (
+            "center",
+            format!(
+                "{},{},{minzoom}",
+                (west + east) / 2.0,
+                (south + north) / 2.0
+            ),
+        ),
+        (
+            "json",
+            json!({
+                "vector_layers": [{
+                    "id": name,
+                    "fields": vector_fields,
+                    "minzoom": minzoom,
+                    "maxzoom": maxzoom,
+                }],
+            })
+            .to_string(),
+        ),
+    ];
+
+    let out = Connection::open(&path.to_string_lossy(), false)?;
+    out.query_rows("BEGIN")?;
+    for sql in SCHEMA {
+        out.query_rows(sql)?;
+    }
+    for (key, value) in metadata {
+        out.query_rows_with(
+            "INSERT INTO metadata VALUES (?, ?)",
+            &[Value::Text(key.to_string()), Value::Text(value)],
+        )?;
+    }
+
+    let mut written = 0;
+    for z in minzoom..=maxzoom {
+        let n = 1u32 << z;
+        let size = WEB_MERCATOR_HALF_SIZE * 2.0 / f64::from(n);
+        let margin = size * f64::from(DEFAULT_BUFFER) / f64::from(DEFAULT_EXTENT);
+        // Tile column or row holding `offset` metres from the west or
+        // north edge of the world.
+        let tile = |offset: f64| ((offset / size).floor().max(0.0) as u32).min(n - 1);
+        let mut tiles = BTreeSet::new();
+        for rect in &rects {
+            let columns = tile(rect.min().x + WEB_MERCATOR_HALF_SIZE - margin)
+                ..=tile(rect.max().x + WEB_MERCATOR_HALF_SIZE + margin);
+            let rows = tile(WEB_MERCATOR_HALF_SIZE - rect.max().y - margin)
+                ..=tile(WEB_MERCATOR_HALF_SIZE - rect.min().y + margin);
+            for x in columns {
+                tiles.extend(rows.clone().map(|y| (x, y)));
+            }
+        }
+
+        for (x, y) in tiles {
+            let envelope = st_tile_envelope(z, x, y)?;
+            let xmin = f64::from(x) * size - WEB_MERCATOR_HALF_SIZE;
+            let ymax = WEB_MERCATOR_HALF_SIZE - f64::from(y) * size;
+            let window = (
+                xmin - margin,
+                ymax - size - margin,
+                xmin + size + margin,
+                ymax + margin,
+            );
+            let mut layer = MvtLayer::new(&name, DEFAULT_EXTENT);
+            for &key in index.bbox_candidates(window) {
+                let Some(tile_geometry) =
+                    st_as_mvt_geom(blobs[key], &envelope, DEFAULT_EXTENT, DEFAULT_BUFFER, true)?
+                else {
+                    continue;
+                };
+                let properties =
+                    fields
+                        .iter()
+                        .zip(&attributes[key])
+                        .filter_map(|(field, value)| {
+                            let value = match value {
+                                Value::Integer(i) => MvtValue::Int(*i),
+                                Value::Real(r) => MvtValue::Double(*r),
+                                Value::Text(t) => MvtValue::String(t.clone()),
+                                Value::Null | Value::Blob(_) => return None,
+                            };
+                            Some((field.name.as_str(), value))
+                        });
+                layer.add_feature(&tile_geometry, properties)?;
+            }
+            if layer.is_empty() {
+                continue;
+            }
+            out.query_rows_with(
+                "INSERT INTO tiles VALUES (?, ?, ?, ?)",
+                &[
+                    Value::Integer(i64::from(z)),
+                    Value::Integer(i64::from(x)),
+                    Value::Integer(i64::from(n - 1 - y)),
+                    Value::Blob(gzip(&layer.encode())?),
+                ],
+            )?;
+            written += 1;
+        }
+    }
+    out.query_rows("COMMIT")?;
+    Ok(written)
+}
+
+/// `data` as a gzip member, the encoding MBTiles readers expect of vector
+/// tiles.
+fn gzip(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_one_tile_per_zoom_and_feature() {
+        let dir = std::env::temp_dir().join(format!("sqlitegis-mbtiles-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let out = dir.join("cities.mbtiles");
+        let out_sql = out.to_string_lossy().replace('\'', "''");
+        let db = Connection::open(&dir.join("src.db").to_string_lossy(), true).unwrap();
+        for sql in [
+            "CREATE TABLE cities (name TEXT, population INTEGER, geom BLOB)",
+            "INSERT INTO cities VALUES \
+               ('Berlin', 3800000, ST_Point(13.4, 52.5, 4326)), \
+               ('Lima', 10000000, ST_Point(-77.0, -12.0, 4326)), \
+               ('Nowhere', 0, NULL)",
+        ] {
+            db.query_rows(sql).unwrap();
+        }
+
+        // Both cities share tile 0/0/0 and sit in different tiles below.
+        let generate = |query: &str, maxzoom: u32| {
+            db.query_value(&format!(
+                "SELECT GenerateMBTiles('{out_sql}', '{query}', 0, {maxzoom})"
+            ))
+        };
+        let err = generate("SELECT * FROM cities", 4).unwrap_err().to_string();
+        assert!(err.contains("file access is disabled"), "{err}");
+        assert!(!out.exists());
+        // SAFETY: the handle stays open while `db` lives.
+        let rc =
+            unsafe { crate::sqlite::register_functions_with_file_access(db.0, Default::default()) };
+        assert_eq!(rc, crate::sqlite::sqlite_compat::SQLITE_OK);
+        assert_eq!(
+            generate("SELECT * FROM cities", 4).unwrap(),
+            Value::Integer(9)
+        );
+        let tiles = Connection::open(&out.to_string_lossy(), false).unwrap();
+        let meta = |key: &str| {
+            tiles
+                .query_value(&format!("SELECT value FROM metadata WHERE name = '{key}'"))
+                .unwrap()
+        };
+        assert_eq!(meta("name"), Value::Text("cities".to_string()));
+        assert_eq!(meta("format"), Value::Text("pbf".to_string()));
+        assert_eq!(meta("maxzoom"), Value::Text("4".to_string()));
+        let Value::Text(json) = meta("json") else {
+            panic!("no json metadata");
+        };
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json["vector_layers"][0]["fields"]["population"], "Number");
+        let Value::Text(bounds) = meta("bounds") else {
+            panic!("no bounds metadata");
+        };
+        let bounds: Vec<f64> = bounds.split(',').map(|v| v.parse().unwrap()).collect();
+        assert!((bounds[0] + 77.0).abs() < 1e-6 && (bounds[3] - 52.5).abs() < 1e-6);
+        // Berlin is in XYZ tile 4/8/5, which is TMS row 15 - 5.
+        let tile = tiles
+            .query_value("SELECT tile_data FROM tiles WHERE zoom_level = 4 AND tile_column = 8 AND tile_row = 10")
+            .unwrap();
+        let Value::Blob(tile) = tile else {
+            panic!("no tile 4/8/5");
+        };
+        assert_eq!(tile[..2], [0x1F, 0x8B]);
+        let mut mvt = Vec::new();
+        std::io::Read::read_to_end(&mut flate2::read::GzDecoder::new(tile.as_slice()), &mut mvt)
+            .unwrap();
+        assert!(mvt.windows(6).any(|w| w == b"Berlin"));
+        drop(tiles);
+
+        let err = generate("SELECT * FROM cities", 4).unwrap_err().to_string();
+        assert!(err.contains("[io]"), "{err}");
+        std::fs::remove_file(&out).unwrap();
+        assert!(generate("SELECT * FROM cities", 23).is_err());
+        let err = generate("SELECT ST_Point(1, 2, 2154) AS geom", 2)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("SRID 2154"), "{err}");
+        assert!(!out.exists());
+
+        drop(db);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
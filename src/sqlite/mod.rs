@@ -3,7 +3,43 @@
 //! `feature = "sqlite-extension"` for the `#[no_mangle]` C entry points
 //! that make the cdylib loadable via SQLite's `load_extension`.
 
+#[cfg(all(feature = "cli", not(target_arch = "wasm32")))]
+pub mod cli;
+mod connection;
 mod ffi;
+mod flatgeobuf;
+#[cfg(feature = "geoparquet")]
+mod geoparquet;
+#[cfg(all(
+    any(feature = "tile-server", feature = "ogc-api"),
+    not(target_arch = "wasm32")
+))]
+mod http;
+#[cfg(all(
+    any(feature = "tile-server", feature = "ogc-api"),
+    not(target_arch = "wasm32")
+))]
+mod layers;
+mod mbtiles;
+#[cfg(all(feature = "ogc-api", not(target_arch = "wasm32")))]
+pub mod ogc_features;
+mod osm_pbf;
+mod shapefile;
 mod sqlite_compat;
+mod table_io;
+#[cfg(test)]
+mod test_support;
+#[cfg(all(feature = "tile-server", not(target_arch = "wasm32")))]
+pub mod tile_server;
+mod vtab;
 
-pub use ffi::{register_functions, register_on_every_new_connection};
+pub use ffi::{
+    register_functions, register_functions_with_file_access, register_functions_with_limits,
+    register_on_every_new_connection,
+};
+pub use flatgeobuf::{export_flatgeobuf, import_flatgeobuf};
+#[cfg(feature = "geoparquet")]
+pub use geoparquet::{export_geoparquet, import_geoparquet};
+pub use mbtiles::generate_mbtiles;
+pub use osm_pbf::import_osm_pbf;
+pub use shapefile::{export_shapefile, import_shapefile};
@@ -0,0 +1,573 @@
+//! Local OGC API - Features server, built with the `ogc-api` feature and
+//! run as the `sqlitegis-features` binary.
+//!
+//! It implements the Core and GeoJSON conformance classes of Part 1 over
+//! the same layers as the tile server: every column in the sqlitegis
+//! spatial index catalog or in GeoPackage's `gpkg_geometry_columns` with
+//! an R-tree, as a collection named after its table (`table.column` for
+//! a second sqlitegis column). The server answers
+//!
+//! - `GET /`, `/conformance` and `/collections` with the landing page,
+//!   the conformance classes and the collections with their extents;
+//! - `GET /collections/{id}` with one collection;
+//! - `GET /collections/{id}/items` with a GeoJSON FeatureCollection,
+//!   paged by `limit` (default 10, at most 10000) and `offset`, and
+//!   filtered by `bbox=west,south,east,north` through the R-tree window
+//!   join plus an exact `ST_Intersects`;
+//! - `GET /collections/{id}/items/{fid}` with one GeoJSON Feature, whose
+//!   id is the row's rowid.
+//!
+//! Geometries come back in CRS84; SRID 3857 layers are unprojected on the
+//! fly. Like the tile server it is meant for localhost, and shares its
+//! limits on request size and concurrent clients.
+
+use std::ffi::OsString;
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use geo::{Coord, MapCoords};
+use serde_json::{json, Map, Value as Json};
+
+use super::connection::Connection;
+use super::http::{self, Request, Response};
+use super::layers::{find_layers, geometry_ewkb, open_for_serving, Layer, Projection, Skipped};
+use super::table_io::quote;
+use crate::core::error::Result;
+use crate::core::ewkb::{parse_ewkb, write_ewkb};
+use crate::core::features::{FieldType, Value};
+use crate::core::functions::constructors::{from_web_mercator, to_web_mercator};
+use crate::core::functions::io::as_geojson;
+
+/// `limit` when the request names none.
+const DEFAULT_LIMIT: i64 = 10;
+
+/// Largest `limit` honoured; larger ones are clamped.
+const MAX_LIMIT: i64 = 10_000;
+
+const CRS84: &str = "http://www.opengis.net/def/crs/OGC/1.3/CRS84";
+
+const CONFORMANCE: [&str; 2] = [
+    "http://www.opengis.net/spec/ogcapi-features-1/1.0/conf/core",
+    "http://www.opengis.net/spec/ogcapi-features-1/1.0/conf/geojson",
+];
+
+const JSON: &str = "application/json";
+const GEOJSON: &str = "application/geo+json";
+
+/// Run the `sqlitegis-features` binary on `args`, the first of which is
+/// the program name. Serves until killed; returns 1 when the database or
+/// the address cannot be opened and 2 for usage errors.
+pub fn run<I, T>(args: I) -> ExitCode
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let (db, bind) = match http::server_args(
+        "sqlitegis-features",
+        "Serve the spatially indexed tables of a SQLite file as OGC API - Features",
+        args,
+    ) {
+        Ok(args) => args,
+        Err(code) => return code,
+    };
+
+    let server = match FeatureServer::bind(&db, &bind) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("sqlitegis-features: error: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    for (name, reason) in &server.skipped {
+        eprintln!("sqlitegis-features: skipping {name}: {reason}");
+    }
+    let base = match server.local_addr() {
+        Ok(addr) => format!("http://{addr}"),
+        Err(_) => format!("http://{bind}"),
+    };
+    println!("serving {} collections from {base}/", server.layers.len());
+    match server.run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("sqlitegis-features: error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// An OGC API - Features server over one SQLite file.
+#[derive(Debug)]
+pub struct FeatureServer {
+    listener: TcpListener,
+    db: String,
+    layers: Arc<Vec<Layer>>,
+    skipped: Skipped,
+}
+
+impl FeatureServer {
+    /// Open the database at `db`, find its collections and listen on
+    /// `addr`. Bind to port 0 to let the system pick a free port.
+    pub fn bind(db: impl AsRef<Path>, addr: impl ToSocketAddrs) -> Result<Self> {
+        let db = db.as_ref().to_string_lossy().into_owned();
+        let (layers, skipped) = find_layers(&open_for_serving(&db)?)?;
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            db,
+            layers: Arc::new(layers),
+            skipped,
+        })
+    }
+
+    /// Address the server listens on.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Ids of the collections served, in order.
+    pub fn collection_ids(&self) -> Vec<&str> {
+        self.layers
+            .iter()
+            .map(|layer| layer.name.as_str())
+            .collect()
+    }
+
+    /// Accept connections until the listener fails, serving each on its
+    /// own thread, up to 64 at once. Clients over the cap get a `503`.
+    pub fn run(self) -> Result<()> {
+        let layers = Arc::clone(&self.layers);
+        http::serve(
+            &self.listener,
+            &self.db,
+            Arc::new(move |db: &Connection, request: &Request| route(db, &layers, request)),
+        )
+    }
+}
+
+/// An OGC exception document.
+fn exception(status: u16, description: impl Into<String>) -> Response {
+    let code = match status {
+        400 => "InvalidParameterValue",
+        404 => "NotFound",
+        _ => "ServerError",
+    };
+    Response {
+        status,
+        ..Response::json(
+            &json!({ "code": code, "description": description.into() }),
+            JSON,
+        )
+    }
+}
+
+fn link(href: String, rel: &str, media_type: &str, title: &str) -> Json {
+    json!({ "href": href, "rel": rel, "type": media_type, "title": title })
+}
+
+/// Answer one request.
+fn route(db: &Connection, layers: &[Layer], request: &Request) -> Response {
+    let base = &request.base;
+    let path = request.path.trim_end_matches('/');
+    let parts: Vec<&str> = path.split('/').skip(1).collect();
+    match parts[..] {
+        [] => Response::json(
+            &json!({
+                "title": "sqlitegis",
+                "description": "Spatially indexed tables of a SQLite database",
+                "links": [
+                    link(format!("{base}/"), "self", JSON, "This document"),
+                    link(format!("{base}/conformance"), "conformance", JSON, "Conformance classes"),
+                    link(format!("{base}/collections"), "data", JSON, "Collections"),
+                ],
+            }),
+            JSON,
+        ),
+        ["conformance"] => Response::json(&json!({ "conformsTo": CONFORMANCE }), JSON),
+        ["collections"] => Response::json(
+            &json!({
+                "links": [link(format!("{base}/collections"), "self", JSON, "Collections")],
+                "collections": layers.iter().map(|l| collection(l, base)).collect::<Vec<_>>(),
+            }),
+            JSON,
+        ),
+        ["collections", id, ref rest @ ..] => {
+            let Some(layer) = layers.iter().find(|layer| layer.name == id) else {
+                return exception(404, format!("no collection [{id}]"));
+            };
+            let result = match rest {
+                [] => Ok(Response::json(&collection(layer, base), JSON)),
+                ["items"] => items(db, layer, request),
+                ["items", fid] => item(db, layer, fid, base),
+                _ => return exception(404, "not found"),
+            };
+            result.unwrap_or_else(|e| exception(500, e.to_string()))
+        }
+        _ => exception(404, "not found"),
+    }
+}
+
+fn collection(layer: &Layer, base: &str) -> Json {
+    let href = format!("{base}/collections/{}", layer.name);
+    let mut collection = json!({
+        "id": layer.name,
+        "title": layer.name,
+        "itemType": "feature",
+        "crs": [CRS84],
+        "links": [
+            link(href.clone(), "self", JSON, "This collection"),
+            link(format!("{href}/items"), "items", GEOJSON, "Items"),
+        ],
+    });
+    if let Some(bounds) = layer.bounds {
+        collection["extent"] = json!({ "spatial": { "bbox": [bounds], "crs": CRS84 } });
+    }
+    collection
+}
+
+/// `GET /collections/{id}/items`.
+fn items(db: &Connection, layer: &Layer, request: &Request) -> Result<Response> {
+    let limit = match request.param("limit") {
+        None => DEFAULT_LIMIT,
+        Some(limit) => match limit.parse::<i64>() {
+            Ok(limit) if limit >= 1 => limit.min(MAX_LIMIT),
+            _ => return Ok(exception(400, "limit must be a positive integer")),
+        },
+    };
+    let offset = match request.param("offset") {
+        None => 0,
+        Some(offset) => match offset.parse::<i64>() {
+            Ok(offset) if offset >= 0 => offset,
+            _ => return Ok(exception(400, "offset must be a non-negative integer")),
+        },
+    };
+    let bbox = match request.param("bbox") {
+        None => None,
+        Some(bbox) => match parse_bbox(&bbox) {
+            Ok(bbox) => Some(bbox),
+            Err(message) => return Ok(exception(400, message)),
+        },
+    };
+
+    let (from, mut params) = match bbox {
+        None => (format!("FROM {} t", quote(&layer.table)), Vec::new()),
+        Some([west, south, east, north]) => {
+            let (mut min, mut max) = (Coord { x: west, y: south }, Coord { x: east, y: north });
+            if layer.projection == Projection::WebMercator {
+                min = to_web_mercator(min);
+                max = to_web_mercator(max);
+            }
+            let (window, mut params) = layer.window(min, max);
+            let c = format!("t.{}", quote(&layer.column));
+            params.extend([min.x, min.y, max.x, max.y].map(Value::Real));
+            (
+                format!(
+                    "{window} AND ST_Intersects({c}, \
+                     ST_SetSRID(ST_MakeEnvelope(?, ?, ?, ?), ST_SRID({c})))"
+                ),
+                params,
+            )
+        }
+    };
+    let matched = match db.query_rows_with(&format!("SELECT count(*) {from}"), &params)?[..] {
+        [ref row] => match row[..] {
+            [Value::Integer(n)] => n,
+            _ => 0,
+        },
+        _ => 0,
+    };
+    params.extend([Value::Integer(limit), Value::Integer(offset)]);
+    let rows = db.query_rows_with(
+        &format!(
+            "SELECT t.rowid, {} {from} ORDER BY t.rowid LIMIT ? OFFSET ?",
+            layer.select_list()
+        ),
+        &params,
+    )?;
+    let features = rows
+        .into_iter()
+        .map(|row| feature(layer, row))
+        .collect::<Result<Vec<_>>>()?;
+
+    let href = |offset: i64| {
+        let mut query = format!("limit={limit}&offset={offset}");
+        if let Some(bbox) = request.param("bbox") {
+            query.push_str(&format!("&bbox={bbox}"));
+        }
+        format!("{}/collections/{}/items?{query}", request.base, layer.name)
+    };
+    let returned = features.len() as i64;
+    let mut links = vec![link(href(offset), "self", GEOJSON, "This page")];
+    if offset + returned < matched {
+        links.push(link(href(offset + returned), "next", GEOJSON, "Next page"));
+    }
+    if offset > 0 {
+        links.push(link(
+            href((offset - limit).max(0)),
+            "prev",
+            GEOJSON,
+            "Previous page",
+        ));
+    }
+    Ok(Response::json(
+        &json!({
+            "type": "FeatureCollection",
+            "numberMatched": matched,
+            "numberReturned": returned,
+            "features": features,
+            "links": links,
+        }),
+        GEOJSON,
+    ))
+}
+
+/// `GET /collections/{id}/items/{fid}`.
+fn item(db: &Connection, layer: &Layer, fid: &str, base: &str) -> Result<Response> {
+    let Ok(rowid) = fid.parse::<i64>() else {
+        return Ok(exception(404, format!("no feature [{fid}]")));
+    };
+    let rows = db.query_rows_with(
+        &format!(
+            "SELECT t.rowid, {} FROM {} t WHERE t.rowid = ?",
+            layer.select_list(),
+            quote(&layer.table)
+        ),
+        &[Value::Integer(rowid)],
+    )?;
+    let Some(row) = rows.into_iter().next() else {
+        return Ok(exception(404, format!("no feature [{fid}]")));
+    };
+    let href = format!("{base}/collections/{}", layer.name);
+    let mut feature = feature(layer, row)?;
+    feature["links"] = json!([
+        link(
+            format!("{href}/items/{rowid}"),
+            "self",
+            GEOJSON,
+            "This feature"
+        ),
+        link(href, "collection", JSON, "The collection"),
+    ]);
+    Ok(Response::json(&feature, GEOJSON))
+}
+
+/// `west,south,east,north` in degrees.
+fn parse_bbox(bbox: &str) -> std::result::Result<[f64; 4], &'static str> {
+    let values: Vec<f64> = bbox
+        .split(',')
+        .map(|v| v.trim().parse::<f64>())
+        .collect::<std::result::Result<_, _>>()
+        .map_err(|_| "bbox must be four comma-separated numbers")?;
+    let [west, south, east, north] = values[..] else {
+        return Err("bbox must be four comma-separated numbers");
+    };
+    if !values.iter().all(|v| v.is_finite()) || south > north {
+        return Err("bbox must have south <= north");
+    }
+    if west > east {
+        return Err("bbox crossing the antimeridian is not supported");
+    }
+    Ok([west, south, east, north])
+}
+
+/// A `rowid, geometry, properties...` row as a GeoJSON Feature.
+fn feature(layer: &Layer, row: Vec<Value>) -> Result<Json> {
+    let mut row = row.into_iter();
+    let id = row.next().unwrap_or(Value::Null);
+    let geometry = match row.next() {
+        Some(Value::Blob(blob)) => {
+            let ewkb = geometry_ewkb(&blob)?;
+            let text = match layer.projection {
+                Projection::LonLat => as_geojson(&ewkb)?,
+                Projection::WebMercator => {
+                    let (geometry, _) = parse_ewkb(&ewkb)?;
+                    as_geojson(&write_ewkb(
+                        &geometry.map_coords(from_web_mercator),
+                        Some(4326),
+                    )?)?
+                }
+            };
+            serde_json::from_str(&text).unwrap_or(Json::Null)
+        }
+        _ => Json::Null,
+    };
+    let properties: Map<String, Json> = layer
+        .properties
+        .iter()
+        .zip(row)
+        .map(|((name, _), value)| {
+            let value = match value {
+                Value::Null => Json::Null,
+                Value::Integer(i) => json!(i),
+                Value::Real(r) => serde_json::Number::from_f64(r).map_or(Json::Null, Json::Number),
+                Value::Text(t) => Json::String(t),
+                blob => match blob.coerce(FieldType::Text) {
+                    Value::Text(hex) => Json::String(hex),
+                    _ => Json::Null,
+                },
+            };
+            (name.clone(), value)
+        })
+        .collect();
+    Ok(json!({
+        "type": "Feature",
+        "id": match id { Value::Integer(i) => json!(i), _ => Json::Null },
+        "geometry": geometry,
+        "properties": properties,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sqlite::http::tests::get;
+
+    fn get_json(addr: SocketAddr, path: &str) -> (String, Json) {
+        let (head, body) = get(addr, path);
+        (head, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[test]
+    fn bbox_parsing() {
+        assert_eq!(parse_bbox("1,2,3,4"), Ok([1.0, 2.0, 3.0, 4.0]));
+        assert!(parse_bbox("1,2,3").is_err());
+        assert!(parse_bbox("1,2,3,4,5,6").is_err());
+        assert!(parse_bbox("1,4,3,2").is_err());
+        assert!(parse_bbox("170,0,-170,10").is_err());
+    }
+
+    #[test]
+    fn serves_collections_and_items_on_localhost() {
+        let dir = std::env::temp_dir().join(format!("sqlitegis-ogc-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("features.db").to_string_lossy().into_owned();
+        let db = Connection::open(&path, true).unwrap();
+        for sql in [
+            "CREATE TABLE cities (name TEXT, population INTEGER, geom BLOB)",
+            "INSERT INTO cities VALUES \
+               ('Berlin', 3800000, ST_Point(13.4, 52.5, 4326)), \
+               ('Lima', 10000000, ST_Point(-77.0, -12.0, 4326)), \
+               ('Paris', 2100000, ST_Point(2.35, 48.85, 4326)), \
+               ('Nowhere', 0, NULL)",
+            "SELECT CreateSpatialIndex('cities', 'geom')",
+            "SELECT sqlitegis_config('accept_gpb', 1)",
+            "SELECT gpkgAddGeometryColumn('roads', 'geom', 'LINESTRING', 4326)",
+            "INSERT INTO roads (geom) VALUES \
+               (AsGPB(ST_GeomFromText('LINESTRING(0 0,4 2)', 4326)))",
+            "SELECT gpkgAddSpatialIndex('roads', 'geom')",
+        ] {
+            db.query_rows(sql).unwrap();
+        }
+        drop(db);
+
+        let server = FeatureServer::bind(&path, "127.0.0.1:0").unwrap();
+        assert_eq!(server.collection_ids(), ["cities", "roads"]);
+        let addr = server.local_addr().unwrap();
+        std::thread::spawn(move || server.run());
+
+        let (_, conformance) = get_json(addr, "/conformance");
+        assert_eq!(conformance["conformsTo"][0], CONFORMANCE[0]);
+        let (_, collections) = get_json(addr, "/collections");
+        let cities = &collections["collections"][0];
+        assert_eq!(cities["id"], "cities");
+        let bbox: Vec<f64> =
+            serde_json::from_value(cities["extent"]["spatial"]["bbox"][0].clone()).unwrap();
+        assert!((bbox[0] + 77.0).abs() < 1e-4 && (bbox[3] - 52.5).abs() < 1e-4);
+
+        let (head, page) = get_json(addr, "/collections/cities/items?limit=2");
+        assert!(head.contains("application/geo+json"), "{head}");
+        assert_eq!(page["numberMatched"], 4);
+        assert_eq!(page["numberReturned"], 2);
+        assert_eq!(page["features"][0]["properties"]["name"], "Berlin");
+        assert_eq!(
+            page["links"][1]["href"],
+            "http://tiles.test/collections/cities/items?limit=2&offset=2"
+        );
+        let (_, page) = get_json(addr, "/collections/cities/items?limit=2&offset=2");
+        assert_eq!(page["features"][1]["geometry"], Json::Null);
+
+        let (_, europe) = get_json(addr, "/collections/cities/items?bbox=-10%2C35%2C30%2C60");
+        assert_eq!(europe["numberMatched"], 2);
+        let names: Vec<&Json> = europe["features"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| &f["properties"]["name"])
+            .collect();
+        assert_eq!(names, ["Berlin", "Paris"]);
+
+        let (_, lima) = get_json(addr, "/collections/cities/items/2");
+        assert_eq!(lima["id"], 2);
+        assert_eq!(lima["geometry"]["coordinates"], json!([-77, -12]));
+
+        let (_, roads) = get_json(addr, "/collections/roads/items?bbox=3,1,5,3");
+        assert_eq!(roads["features"][0]["geometry"]["type"], "LineString");
+        let (_, roads) = get_json(addr, "/collections/roads/items?bbox=3,-1,5,0.5");
+        assert_eq!(roads["numberMatched"], 0);
+
+        assert!(get(addr, "/collections/cities/items/99")
+            .0
+            .starts_with("HTTP/1.1 404"));
+        assert!(get(addr, "/collections/rivers")
+            .0
+            .starts_with("HTTP/1.1 404"));
+        assert!(get(addr, "/collections/cities/items?limit=0")
+            .0
+            .starts_with("HTTP/1.1 400"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn bounds_request_heads_and_concurrent_clients() {
+        use std::io::{Read, Write};
+        use std::net::TcpStream;
+
+        let dir = std::env::temp_dir().join(format!("sqlitegis-ogc-limits-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("features.db").to_string_lossy().into_owned();
+        let db = Connection::open(&path, true).unwrap();
+        for sql in [
+            "CREATE TABLE cities (name TEXT, geom BLOB)",
+            "INSERT INTO cities VALUES ('Berlin', ST_Point(13.4, 52.5, 4326))",
+            "SELECT CreateSpatialIndex('cities', 'geom')",
+        ] {
+            db.query_rows(sql).unwrap();
+        }
+        drop(db);
+
+        let server = FeatureServer::bind(&path, "127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        std::thread::spawn(move || server.run());
+
+        // A head that never ends is cut off instead of buffered.
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let _ = write!(stream, "GET /{} HTTP/1.1\r\n", "a".repeat(32 * 1024));
+        let mut response = Vec::new();
+        let _ = stream.read_to_end(&mut response);
+        assert!(response.is_empty());
+
+        let idle: Vec<TcpStream> = (0..http::MAX_CONNECTIONS)
+            .map(|_| TcpStream::connect(addr).unwrap())
+            .collect();
+        let mut response = String::new();
+        TcpStream::connect(addr)
+            .unwrap()
+            .read_to_string(&mut response)
+            .unwrap();
+        assert!(response.starts_with("HTTP/1.1 503"), "{response}");
+        drop(idle);
+        let served = (0..100).any(|_| {
+            get(addr, "/collections/cities")
+                .0
+                .starts_with("HTTP/1.1 200")
+                || {
+                    std::thread::sleep(std::time::Duration::from_millis(20));
+                    false
+                }
+        });
+        assert!(served);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,153 @@
+//! OpenStreetMap PBF import over a raw `*mut sqlite3`, the Rust side of
+//! the `ImportOSM` SQL function.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use super::sqlite_compat::sqlite3;
+use super::table_io::{import_features, in_savepoint};
+use crate::core::error::Result;
+use crate::core::osm_pbf::read_osm_pbf;
+
+/// Prefix of the tables [`import_osm_pbf`] fills.
+const TABLE_PREFIX: &str = "osm_";
+
+/// Read the `.osm.pbf` extract at `path` into the `osm_points`,
+/// `osm_lines` and `osm_polygons` tables and return the number of rows
+/// inserted across all three.
+///
+/// Each table has a `geom` BLOB column holding EWKB in SRID 4326, an
+/// `osm_id INTEGER`, an `osm_type TEXT` and a `tags TEXT` column holding
+/// the tags as a JSON object; see [`crate::core::osm_pbf`] for which
+/// elements land in which table. Missing tables are created and existing
+/// ones appended to. With `spatial_index`, `CreateSpatialIndex` indexes
+/// each table's `geom` column once the rows are in; it and the `ST_*`
+/// functions its triggers call must be registered on `db` (see
+/// [`register_functions`](crate::sqlite::register_functions)). The import
+/// runs in one savepoint, so a failure leaves the database untouched.
+///
+/// # Safety
+///
+/// `db` must be a valid, open SQLite connection handle.
+pub unsafe fn import_osm_pbf(
+    db: *mut sqlite3,
+    path: impl AsRef<Path>,
+    spatial_index: bool,
+) -> Result<i64> {
+    let layers = read_osm_pbf(BufReader::new(File::open(path)?))?;
+    in_savepoint(db, || {
+        let mut rows = 0;
+        for layer in layers {
+            rows += import_features(
+                db,
+                &format!("{TABLE_PREFIX}{}", layer.name),
+                &layer.fields,
+                layer.features.into_iter().map(Ok),
+                spatial_index,
+            )?;
+        }
+        Ok(rows)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::features::Value;
+    use crate::core::osm_pbf::tests::sample_pbf;
+    use crate::sqlite::connection::Connection;
+
+    fn temp_file(name: &str, data: &[u8]) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("sqlitegis-osm-{}-{name}", std::process::id()));
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn import_fills_the_three_layers() {
+        let db = Connection::open(":memory:", true).unwrap();
+        // SAFETY: the handle stays open while `db` lives.
+        let rc =
+            unsafe { crate::sqlite::register_functions_with_file_access(db.0, Default::default()) };
+        assert_eq!(rc, crate::sqlite::sqlite_compat::SQLITE_OK);
+        let path = temp_file("sample.osm.pbf", &sample_pbf());
+        let sql_path = path.to_string_lossy().replace('\'', "''");
+
+        assert_eq!(
+            db.query_i64(&format!("SELECT ImportOSM('{sql_path}', 1)"))
+                .unwrap(),
+            4
+        );
+        assert_eq!(
+            db.query_i64(
+                "SELECT count(*) FROM osm_points \
+                 WHERE osm_type = 'node' AND json_extract(tags, '$.amenity') = 'cafe' \
+                 AND ST_SRID(geom) = 4326"
+            )
+            .unwrap(),
+            1
+        );
+        assert_eq!(
+            db.query_i64("SELECT osm_id FROM osm_lines WHERE tags ->> 'name' = 'Main St'")
+                .unwrap(),
+            100
+        );
+        assert_eq!(
+            db.query_i64(
+                "SELECT CAST(ST_Area(geom) AS INTEGER) FROM osm_polygons \
+                 WHERE osm_type = 'relation' AND osm_id = 200"
+            )
+            .unwrap(),
+            96
+        );
+        assert_eq!(
+            db.query_i64("SELECT count(*) FROM osm_polygons_geom_rtree")
+                .unwrap(),
+            2
+        );
+
+        // SAFETY: as above.
+        assert_eq!(unsafe { import_osm_pbf(db.0, &path, false) }.unwrap(), 4);
+        assert_eq!(db.query_i64("SELECT count(*) FROM osm_points").unwrap(), 2);
+
+        // A failure in a later table rolls back the earlier ones too.
+        db.query_rows("DROP TABLE osm_lines").unwrap();
+        db.query_rows("CREATE TABLE osm_lines(geom BLOB)").unwrap();
+        let err = db
+            .query_value(&format!("SELECT ImportOSM('{sql_path}')"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("[sqlite]"), "{err}");
+        assert_eq!(db.query_i64("SELECT count(*) FROM osm_points").unwrap(), 2);
+        let err = db
+            .query_value("SELECT ImportOSM('sqlitegis-missing-dir/x.osm.pbf')")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("[io]"), "{err}");
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn malformed_extract_creates_no_tables() {
+        let db = Connection::open(":memory:", true).unwrap();
+        let mut truncated = sample_pbf();
+        truncated.truncate(truncated.len() / 2);
+        for (name, data) in [
+            ("garbage.osm.pbf", b"not a pbf file".to_vec()),
+            ("truncated.osm.pbf", truncated),
+        ] {
+            let path = temp_file(name, &data);
+            // SAFETY: the handle stays open while `db` lives.
+            assert!(unsafe { import_osm_pbf(db.0, &path, false) }.is_err());
+            std::fs::remove_file(path).unwrap();
+        }
+        assert_eq!(
+            db.query_value("SELECT count(*) FROM sqlite_master")
+                .unwrap(),
+            Value::Integer(0)
+        );
+    }
+}
@@ -0,0 +1,260 @@
+//! Shapefile import and export over a raw `*mut sqlite3`, the Rust side
+//! of the `ImportSHP` and `ExportSHP` SQL functions.
+
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use super::ffi::validate_identifier;
+use super::sqlite_compat::sqlite3;
+use super::table_io::{import_features, query_features};
+use crate::core::error::{Result, SqliteGisError};
+use crate::core::shapefile::{
+    prj_for_srid, srid_from_prj, Charset, ShapefileReader, ShapefileWriter,
+};
+
+/// `path` without a trailing `.shp`, in any case.
+fn base_path(path: &Path) -> PathBuf {
+    match path.extension() {
+        Some(ext) if ext.eq_ignore_ascii_case("shp") => path.with_extension(""),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// The companion file of `base` with extension `ext`, falling back to the
+/// upper-case extension when only that one exists.
+fn companion(base: &Path, ext: &str) -> PathBuf {
+    let with = |ext: &str| {
+        let mut name = OsString::from(base.as_os_str());
+        name.push(".");
+        name.push(ext);
+        PathBuf::from(name)
+    };
+    let lower = with(ext);
+    let upper = with(&ext.to_ascii_uppercase());
+    if !lower.exists() && upper.exists() {
+        upper
+    } else {
+        lower
+    }
+}
+
+/// Read the optional companion file of `base` with extension `ext`.
+fn read_optional(base: &Path, ext: &str) -> Result<Option<String>> {
+    match fs::read(companion(base, ext)) {
+        Ok(bytes) => Ok(Some(String::from_utf8_lossy(&bytes).trim().to_string())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Read the shapefile at `path` (with or without its `.shp` extension)
+/// into `table` and return the number of rows inserted.
+///
+/// The `.shp` and `.dbf` are required. Geometries get `srid`, or when it
+/// is `None` the SRID [`srid_from_prj`] finds in the `.prj`, if any.
+/// Attribute text is decoded with `charset` (see [`Charset::from_name`]),
+/// or when it is `None` the charset named in the `.cpg`, defaulting to
+/// UTF-8. A missing table is created with a `geom` BLOB column holding
+/// EWKB plus one column per `.dbf` field, declared `INTEGER`, `REAL` or
+/// `TEXT`; an existing table must have those columns. The import runs in
+/// one savepoint, so a failure leaves the database untouched.
+///
+/// # Safety
+///
+/// `db` must be a valid, open SQLite connection handle.
+pub unsafe fn import_shapefile(
+    db: *mut sqlite3,
+    path: impl AsRef<Path>,
+    table: &str,
+    srid: Option<i32>,
+    charset: Option<&str>,
+) -> Result<i64> {
+    let reader = open_shapefile(path.as_ref(), srid, charset)?;
+    let fields = reader.fields().to_vec();
+    import_features(db, table, &fields, reader, false)
+}
+
+/// Open the shapefile at `path`, taking a missing SRID from the `.prj`
+/// and a missing charset from the `.cpg`, as [`import_shapefile`] does.
+pub(super) fn open_shapefile(
+    path: &Path,
+    srid: Option<i32>,
+    charset: Option<&str>,
+) -> Result<ShapefileReader<BufReader<File>, BufReader<File>>> {
+    let base = base_path(path);
+    let shp = File::open(companion(&base, "shp"))?;
+    let dbf = File::open(companion(&base, "dbf"))?;
+    let srid = match srid {
+        Some(srid) => Some(srid),
+        None => read_optional(&base, "prj")?.and_then(|prj| srid_from_prj(&prj)),
+    };
+    let charset = match charset {
+        Some(name) => Charset::from_name(name)?,
+        None => match read_optional(&base, "cpg")? {
+            Some(name) => Charset::from_name(&name)?,
+            None => Charset::Utf8,
+        },
+    };
+    ShapefileReader::new(BufReader::new(shp), BufReader::new(dbf), charset, srid)
+}
+
+/// Write the rows of `table` to a shapefile at `path` (with or without
+/// its `.shp` extension) and return the number of features written.
+///
+/// `geometry_column` holds the geometries, which must share one SRID and
+/// one shape family (points, lines or polygons); every other column
+/// becomes a `.dbf` field typed after its values. Besides the `.shp`,
+/// `.shx` and `.dbf`, the export writes a `.cpg` naming UTF-8 and, for
+/// the SRIDs [`prj_for_srid`] knows, a `.prj`.
+///
+/// # Safety
+///
+/// `db` must be a valid, open SQLite connection handle.
+pub unsafe fn export_shapefile(
+    db: *mut sqlite3,
+    table: &str,
+    geometry_column: &str,
+    path: impl AsRef<Path>,
+) -> Result<i64> {
+    let Some(table) = validate_identifier(table) else {
+        return Err(SqliteGisError::InvalidInput(
+            "invalid table name (only [a-zA-Z0-9_] allowed)".to_string(),
+        ));
+    };
+    let (fields, features) = query_features(
+        db,
+        &format!("SELECT * FROM \"{table}\""),
+        Some(geometry_column),
+    )?;
+    let mut writer = ShapefileWriter::new(fields);
+    for feature in &features {
+        writer.add_feature(feature)?;
+    }
+    write_shapefile(path.as_ref(), writer)
+}
+
+/// Write the features added to `writer` to the shapefile at `path` with
+/// its `.cpg` and, when the SRID is known, `.prj`, as [`export_shapefile`]
+/// does.
+pub(super) fn write_shapefile(path: &Path, writer: ShapefileWriter) -> Result<i64> {
+    let base = base_path(path);
+    let create = |ext: &str| -> Result<BufWriter<File>> {
+        let mut name = OsString::from(base.as_os_str());
+        name.push(".");
+        name.push(ext);
+        Ok(BufWriter::new(File::create(name)?))
+    };
+    let (shp, shx, dbf) = (create("shp")?, create("shx")?, create("dbf")?);
+    let prj = writer.srid().and_then(prj_for_srid);
+    let written = writer.finish(shp, shx, dbf)?;
+    create("cpg")?.write_all(b"UTF-8")?;
+    if let Some(prj) = prj {
+        create("prj")?.write_all(prj.as_bytes())?;
+    }
+    Ok(written as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::features::Value;
+    use crate::sqlite::connection::Connection;
+
+    fn temp_base(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("sqlitegis-shp-{}-{name}", std::process::id()))
+    }
+
+    fn remove(base: &Path, exts: &[&str]) {
+        for ext in exts {
+            let _ = fs::remove_file(format!("{}.{ext}", base.display()));
+        }
+    }
+
+    #[test]
+    fn companion_paths_accept_either_case() {
+        let base = temp_base("upper");
+        assert_eq!(base_path(&base.with_extension("SHP")), base);
+        assert_eq!(
+            base_path(&base.with_extension("dbf")),
+            base.with_extension("dbf")
+        );
+        assert_eq!(companion(&base, "prj"), base.with_extension("prj"));
+        fs::write(format!("{}.PRJ", base.display()), " GEOGCS[] \n").unwrap();
+        assert_eq!(companion(&base, "prj"), base.with_extension("PRJ"));
+        assert_eq!(
+            read_optional(&base, "prj").unwrap().as_deref(),
+            Some("GEOGCS[]")
+        );
+        assert_eq!(read_optional(&base, "cpg").unwrap(), None);
+        remove(&base, &["PRJ"]);
+    }
+
+    #[test]
+    fn export_then_import_round_trips_rows() {
+        let db = Connection::open(":memory:", true).unwrap();
+        db.query_rows("CREATE TABLE roads (id INTEGER, name TEXT, geom BLOB)")
+            .unwrap();
+        db.query_rows(
+            "INSERT INTO roads VALUES \
+               (1, 'Grüne Allee', ST_GeomFromText('LINESTRING(0 0,3 4)', 4326)), \
+               (2, NULL, ST_GeomFromText('MULTILINESTRING((0 0,1 1),(2 2,3 3))', 4326))",
+        )
+        .unwrap();
+        let base = temp_base("roads");
+        // SAFETY: the handle stays open while `db` lives.
+        unsafe {
+            assert_eq!(export_shapefile(db.0, "roads", "geom", &base).unwrap(), 2);
+            assert_eq!(
+                import_shapefile(db.0, base.with_extension("shp"), "copy", None, None).unwrap(),
+                2
+            );
+        }
+        assert_eq!(
+            db.query_i64(
+                "SELECT count(*) FROM roads r JOIN copy c ON c.id = r.id \
+                 WHERE c.name IS r.name AND ST_Equals(c.geom, r.geom) \
+                 AND ST_SRID(c.geom) = 4326"
+            )
+            .unwrap(),
+            2
+        );
+        // SAFETY: as above.
+        let err = unsafe { export_shapefile(db.0, "roads; --", "geom", &base) }.unwrap_err();
+        assert!(err.to_string().contains("invalid table name"), "{err}");
+        remove(&base, &["shp", "shx", "dbf", "prj", "cpg"]);
+    }
+
+    #[test]
+    fn malformed_files_leave_no_table_behind() {
+        let db = Connection::open(":memory:", true).unwrap();
+        db.query_rows("CREATE TABLE pts (id INTEGER, geom BLOB)")
+            .unwrap();
+        db.query_rows("INSERT INTO pts VALUES (1, ST_Point(1, 2)), (2, ST_Point(3, 4))")
+            .unwrap();
+        let base = temp_base("pts");
+        // SAFETY: the handle stays open while `db` lives.
+        unsafe { export_shapefile(db.0, "pts", "geom", &base) }.unwrap();
+        let shp_path = base.with_extension("shp");
+        let shp = fs::read(&shp_path).unwrap();
+
+        for data in [&b"not a shapefile"[..], &shp[..shp.len() - 10]] {
+            fs::write(&shp_path, data).unwrap();
+            // SAFETY: as above.
+            assert!(unsafe { import_shapefile(db.0, &base, "copy", None, None) }.is_err());
+            assert_eq!(
+                db.query_value("SELECT count(*) FROM sqlite_master WHERE name = 'copy'")
+                    .unwrap(),
+                Value::Integer(0)
+            );
+        }
+        // The .dbf is required.
+        fs::write(&shp_path, &shp).unwrap();
+        remove(&base, &["dbf"]);
+        // SAFETY: as above.
+        let err = unsafe { import_shapefile(db.0, &base, "copy", None, None) }.unwrap_err();
+        assert!(matches!(err, SqliteGisError::Io(_)), "{err}");
+        remove(&base, &["shp", "shx", "cpg"]);
+    }
+}
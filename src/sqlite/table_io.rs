@@ -0,0 +1,623 @@
+//! Moving [`Feature`]s between tables and the file-format readers and
+//! writers: prepared-statement plumbing plus the import and export loops
+//! the format modules share.
+
+use std::borrow::Cow;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_int;
+
+use super::ffi::{exec_sql_inner, validate_identifier};
+use super::sqlite_compat::*;
+use crate::core::error::{Result, SqliteGisError};
+use crate::core::ewkb::{normalize_input, parse_ewkb_header};
+use crate::core::features::{Feature, Field, FieldType, Value};
+
+/// Name of the geometry column [`import_features`] creates, and the
+/// column [`query_features`] prefers.
+pub(super) const GEOMETRY_COLUMN: &str = "geom";
+
+unsafe fn sqlite_error(db: *mut sqlite3) -> SqliteGisError {
+    SqliteGisError::Sqlite(
+        CStr::from_ptr(sqlite3_errmsg(db))
+            .to_string_lossy()
+            .into_owned(),
+    )
+}
+
+/// Run `sql`, reporting a failure as [`SqliteGisError::Sqlite`].
+unsafe fn exec(db: *mut sqlite3, sql: &str) -> Result<()> {
+    if exec_sql_inner(db, sql, None) == SQLITE_OK {
+        Ok(())
+    } else {
+        Err(sqlite_error(db))
+    }
+}
+
+/// Quote an arbitrary column name taken from a file.
+pub(super) fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn c_len(len: usize) -> Result<c_int> {
+    c_int::try_from(len)
+        .map_err(|_| SqliteGisError::InvalidInput("value too large to bind".to_string()))
+}
+
+/// Prepared statement, finalized on drop.
+pub(super) struct Statement {
+    db: *mut sqlite3,
+    stmt: *mut sqlite3_stmt,
+}
+
+impl Statement {
+    /// Prepare the single statement in `sql`.
+    pub(super) unsafe fn prepare(db: *mut sqlite3, sql: &str) -> Result<Self> {
+        let c_sql = CString::new(sql)
+            .map_err(|_| SqliteGisError::InvalidInput("SQL contains a NUL byte".to_string()))?;
+        let mut stmt = std::ptr::null_mut();
+        let mut tail = std::ptr::null();
+        if sqlite3_prepare_v2(db, c_sql.as_ptr(), -1, &mut stmt, &mut tail) != SQLITE_OK {
+            return Err(sqlite_error(db));
+        }
+        let statement = Self { db, stmt };
+        if stmt.is_null() {
+            return Err(SqliteGisError::InvalidInput(
+                "SQL contains no statement".to_string(),
+            ));
+        }
+        if !tail.is_null() && !CStr::from_ptr(tail).to_bytes().trim_ascii().is_empty() {
+            return Err(SqliteGisError::InvalidInput(
+                "SQL must be a single statement".to_string(),
+            ));
+        }
+        Ok(statement)
+    }
+
+    unsafe fn is_readonly(&self) -> bool {
+        sqlite3_stmt_readonly(self.stmt) != 0
+    }
+
+    /// Bind `value` to the 1-based parameter `index`.
+    pub(super) unsafe fn bind(&mut self, index: c_int, value: &Value) -> Result<()> {
+        let rc = match value {
+            Value::Null => sqlite3_bind_null(self.stmt, index),
+            Value::Integer(i) => sqlite3_bind_int64(self.stmt, index, *i),
+            Value::Real(r) => sqlite3_bind_double(self.stmt, index, *r),
+            Value::Text(t) => sqlite3_bind_text(
+                self.stmt,
+                index,
+                t.as_ptr().cast(),
+                c_len(t.len())?,
+                sqlite_transient(),
+            ),
+            Value::Blob(b) => sqlite3_bind_blob(
+                self.stmt,
+                index,
+                b.as_ptr().cast(),
+                c_len(b.len())?,
+                sqlite_transient(),
+            ),
+        };
+        if rc == SQLITE_OK {
+            Ok(())
+        } else {
+            Err(sqlite_error(self.db))
+        }
+    }
+
+    /// Step once; `true` when a row is available.
+    pub(super) unsafe fn step(&mut self) -> Result<bool> {
+        match sqlite3_step(self.stmt) {
+            SQLITE_ROW => Ok(true),
+            SQLITE_DONE => Ok(false),
+            _ => Err(sqlite_error(self.db)),
+        }
+    }
+
+    unsafe fn reset(&mut self) {
+        sqlite3_reset(self.stmt);
+        sqlite3_clear_bindings(self.stmt);
+    }
+
+    pub(super) unsafe fn column_names(&self) -> Vec<String> {
+        (0..sqlite3_column_count(self.stmt))
+            .map(|i| {
+                let name = sqlite3_column_name(self.stmt, i);
+                if name.is_null() {
+                    format!("column{}", i + 1)
+                } else {
+                    CStr::from_ptr(name).to_string_lossy().into_owned()
+                }
+            })
+            .collect()
+    }
+
+    pub(super) unsafe fn column(&self, i: c_int) -> Value {
+        match sqlite3_column_type(self.stmt, i) {
+            SQLITE_INTEGER => Value::Integer(sqlite3_column_int64(self.stmt, i)),
+            SQLITE_FLOAT => Value::Real(sqlite3_column_double(self.stmt, i)),
+            SQLITE_TEXT => {
+                let ptr = sqlite3_column_text(self.stmt, i);
+                let len = sqlite3_column_bytes(self.stmt, i) as usize;
+                if ptr.is_null() {
+                    return Value::Text(String::new());
+                }
+                let bytes = std::slice::from_raw_parts(ptr, len);
+                Value::Text(String::from_utf8_lossy(bytes).into_owned())
+            }
+            SQLITE_BLOB => {
+                let ptr = sqlite3_column_blob(self.stmt, i) as *const u8;
+                let len = sqlite3_column_bytes(self.stmt, i) as usize;
+                if ptr.is_null() || len == 0 {
+                    return Value::Blob(Vec::new());
+                }
+                Value::Blob(std::slice::from_raw_parts(ptr, len).to_vec())
+            }
+            _ => Value::Null,
+        }
+    }
+}
+
+impl Drop for Statement {
+    fn drop(&mut self) {
+        unsafe {
+            sqlite3_finalize(self.stmt);
+        }
+    }
+}
+
+/// Insert `features` into `table`, creating it with a [`GEOMETRY_COLUMN`]
+/// BLOB column plus one column per field when it does not exist. With
+/// `spatial_index` the column is then indexed by `CreateSpatialIndex`,
+/// which must be registered on `db`. Everything runs in one savepoint, so
+/// a failure leaves the database untouched. Returns the number of rows
+/// inserted.
+pub(super) unsafe fn import_features(
+    db: *mut sqlite3,
+    table: &str,
+    fields: &[Field],
+    features: impl Iterator<Item = Result<Feature>>,
+    spatial_index: bool,
+) -> Result<i64> {
+    let Some(table) = validate_identifier(table) else {
+        return Err(SqliteGisError::InvalidInput(
+            "invalid table name (only [a-zA-Z0-9_] allowed)".to_string(),
+        ));
+    };
+    if let Some(field) = fields
+        .iter()
+        .find(|f| f.name.eq_ignore_ascii_case(GEOMETRY_COLUMN))
+    {
+        return Err(SqliteGisError::InvalidInput(format!(
+            "attribute [{}] clashes with the geometry column",
+            field.name
+        )));
+    }
+
+    in_savepoint(db, || {
+        insert_features(db, table, fields, features, spatial_index)
+    })
+}
+
+/// Run `f` in a savepoint, rolled back when it fails. Savepoints nest, so
+/// an import of several tables can wrap the per-table ones.
+pub(super) unsafe fn in_savepoint<T>(db: *mut sqlite3, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    let savepoint = "sqlitegis_import";
+    exec(db, &format!("SAVEPOINT {savepoint}"))?;
+    let result = f();
+    if result.is_ok() {
+        exec(db, &format!("RELEASE {savepoint}"))?;
+    } else {
+        let _ = exec_sql_inner(db, &format!("ROLLBACK TO {savepoint}"), None);
+        let _ = exec_sql_inner(db, &format!("RELEASE {savepoint}"), None);
+    }
+    result
+}
+
+unsafe fn insert_features(
+    db: *mut sqlite3,
+    table: &str,
+    fields: &[Field],
+    features: impl Iterator<Item = Result<Feature>>,
+    spatial_index: bool,
+) -> Result<i64> {
+    let columns: Vec<String> = std::iter::once(quote(GEOMETRY_COLUMN))
+        .chain(fields.iter().map(|f| quote(&f.name)))
+        .collect();
+    let declarations: Vec<String> = std::iter::once(format!("{} BLOB", columns[0]))
+        .chain(
+            fields
+                .iter()
+                .zip(&columns[1..])
+                .map(|(f, column)| format!("{column} {}", f.field_type.sql_type())),
+        )
+        .collect();
+    exec(
+        db,
+        &format!(
+            "CREATE TABLE IF NOT EXISTS [{table}] ({})",
+            declarations.join(", ")
+        ),
+    )?;
+
+    let placeholders = vec!["?"; columns.len()].join(", ");
+    let mut insert = Statement::prepare(
+        db,
+        &format!(
+            "INSERT INTO [{table}] ({}) VALUES ({placeholders})",
+            columns.join(", ")
+        ),
+    )?;
+    let mut rows = 0;
+    for feature in features {
+        let feature = feature?;
+        let geometry = feature.geometry.map_or(Value::Null, Value::Blob);
+        insert.bind(1, &geometry)?;
+        for (i, value) in feature.properties.iter().enumerate() {
+            insert.bind(i as c_int + 2, value)?;
+        }
+        insert.step()?;
+        insert.reset();
+        rows += 1;
+    }
+    drop(insert);
+
+    if spatial_index {
+        exec(
+            db,
+            &format!("SELECT CreateSpatialIndex('{table}', '{GEOMETRY_COLUMN}')"),
+        )?;
+    }
+    Ok(rows)
+}
+
+/// Where the geometry and the attributes of a query's rows are, worked
+/// out by [`query_layout`] or [`query_features`].
+pub(super) struct FeatureLayout {
+    /// One field per column other than the geometry column.
+    pub(super) fields: Vec<Field>,
+    geometry_column: usize,
+    names: Vec<String>,
+}
+
+impl FeatureLayout {
+    /// Split a row of the query into a feature.
+    fn feature(&self, mut row: Vec<Value>) -> Result<Feature> {
+        let geometry = match row.remove(self.geometry_column) {
+            Value::Null => None,
+            Value::Blob(blob) => Some(match normalize_input(&blob)? {
+                Cow::Borrowed(_) => blob,
+                Cow::Owned(converted) => converted,
+            }),
+            _ => {
+                return Err(SqliteGisError::InvalidInput(format!(
+                    "column [{}] holds a non-geometry value",
+                    self.names[self.geometry_column]
+                )))
+            }
+        };
+        Ok(Feature {
+            geometry,
+            properties: row,
+        })
+    }
+}
+
+/// Per-column state of the scan that picks the geometry column and types
+/// the fields, fed one row at a time.
+struct LayoutScan {
+    names: Vec<String>,
+    /// The geometry column, once known from its name.
+    named: Option<usize>,
+    field_types: Vec<Option<FieldType>>,
+    /// Whether each column has held a non-NULL value.
+    non_null: Vec<bool>,
+    /// Whether each column has held nothing but geometry BLOBs and NULLs.
+    geometry: Vec<bool>,
+}
+
+impl LayoutScan {
+    fn new(names: Vec<String>, geometry_column: Option<&str>) -> Result<Self> {
+        let named = |column: &str| {
+            names
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case(column))
+        };
+        let named = match geometry_column {
+            Some(column) => Some(named(column).ok_or_else(|| {
+                SqliteGisError::InvalidInput(format!("query returns no column [{column}]"))
+            })?),
+            None => named(GEOMETRY_COLUMN),
+        };
+        let n = names.len();
+        Ok(Self {
+            names,
+            named,
+            field_types: vec![None; n],
+            non_null: vec![false; n],
+            geometry: vec![named.is_none(); n],
+        })
+    }
+
+    fn add(&mut self, row: &[Value]) {
+        for (i, value) in row.iter().enumerate() {
+            self.field_types[i] = FieldType::widen(self.field_types[i], value);
+            self.non_null[i] |= *value != Value::Null;
+            self.geometry[i] &= match value {
+                Value::Null => true,
+                Value::Blob(blob) => {
+                    normalize_input(blob).is_ok_and(|b| parse_ewkb_header(&b).is_ok())
+                }
+                _ => false,
+            };
+        }
+    }
+
+    fn finish(self) -> Result<FeatureLayout> {
+        let geometry_column = self
+            .named
+            .or_else(|| (0..self.names.len()).find(|&i| self.non_null[i] && self.geometry[i]))
+            .ok_or_else(|| {
+                SqliteGisError::InvalidInput("query returns no geometry column".to_string())
+            })?;
+        let fields = self
+            .names
+            .iter()
+            .zip(self.field_types)
+            .enumerate()
+            .filter(|&(i, _)| i != geometry_column)
+            .map(|(_, (name, field_type))| Field::new(name, field_type.unwrap_or(FieldType::Text)))
+            .collect();
+        Ok(FeatureLayout {
+            fields,
+            geometry_column,
+            names: self.names,
+        })
+    }
+}
+
+/// Prepare `query`, which must be a read-only statement.
+unsafe fn prepare_query(db: *mut sqlite3, query: &str) -> Result<Statement> {
+    let stmt = Statement::prepare(db, query)?;
+    if !stmt.is_readonly() {
+        return Err(SqliteGisError::InvalidInput(
+            "query must be a read-only statement".to_string(),
+        ));
+    }
+    Ok(stmt)
+}
+
+/// The next row of `stmt`, or `None` once it is done.
+unsafe fn next_row(stmt: &mut Statement, columns: usize) -> Result<Option<Vec<Value>>> {
+    if !stmt.step()? {
+        return Ok(None);
+    }
+    Ok(Some(
+        (0..columns as c_int).map(|i| stmt.column(i)).collect(),
+    ))
+}
+
+/// Run the read-only `query` and return its rows as features: the
+/// geometry comes from the column named `geometry_column` when given,
+/// else the column named [`GEOMETRY_COLUMN`] or the first column holding
+/// nothing but geometry BLOBs and NULLs, and every other column becomes a
+/// field typed by [`FieldType::infer`].
+pub(super) unsafe fn query_features(
+    db: *mut sqlite3,
+    query: &str,
+    geometry_column: Option<&str>,
+) -> Result<(Vec<Field>, Vec<Feature>)> {
+    let mut stmt = prepare_query(db, query)?;
+    let names = stmt.column_names();
+    let columns = names.len();
+    let mut scan = LayoutScan::new(names, geometry_column)?;
+    let mut rows = Vec::new();
+    while let Some(row) = next_row(&mut stmt, columns)? {
+        scan.add(&row);
+        rows.push(row);
+    }
+    drop(stmt);
+
+    let layout = scan.finish()?;
+    let features = rows
+        .into_iter()
+        .map(|row| layout.feature(row))
+        .collect::<Result<_>>()?;
+    Ok((layout.fields, features))
+}
+
+/// The layout [`query_features`] would find for `query`, worked out
+/// without keeping its rows. [`for_each_feature`] then streams them.
+pub(super) unsafe fn query_layout(
+    db: *mut sqlite3,
+    query: &str,
+    geometry_column: Option<&str>,
+) -> Result<FeatureLayout> {
+    let mut stmt = prepare_query(db, query)?;
+    let names = stmt.column_names();
+    let columns = names.len();
+    let mut scan = LayoutScan::new(names, geometry_column)?;
+    while let Some(row) = next_row(&mut stmt, columns)? {
+        scan.add(&row);
+    }
+    scan.finish()
+}
+
+/// Run `query` again and pass its rows to `f` as features split by
+/// `layout`, one at a time. Run both passes in one transaction, such as
+/// [`in_savepoint`], so they see the same rows.
+pub(super) unsafe fn for_each_feature(
+    db: *mut sqlite3,
+    query: &str,
+    layout: &FeatureLayout,
+    mut f: impl FnMut(Feature) -> Result<()>,
+) -> Result<()> {
+    let mut stmt = prepare_query(db, query)?;
+    let columns = layout.names.len();
+    if stmt.column_names() != layout.names {
+        return Err(SqliteGisError::InvalidInput(
+            "query returned different columns on its second run".to_string(),
+        ));
+    }
+    while let Some(row) = next_row(&mut stmt, columns)? {
+        f(layout.feature(row)?)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::functions::io::geom_from_text;
+    use crate::sqlite::connection::Connection;
+
+    fn point(wkt: &str) -> Option<Vec<u8>> {
+        Some(geom_from_text(wkt, Some(4326)).unwrap())
+    }
+
+    #[test]
+    fn prepare_takes_exactly_one_statement() {
+        let db = Connection::open(":memory:", true).unwrap();
+        // SAFETY: the handle stays open while `db` lives.
+        unsafe {
+            assert!(Statement::prepare(db.0, "SELECT 1;  ").is_ok());
+            let err = Statement::prepare(db.0, "SELECT 1; SELECT 2")
+                .err()
+                .unwrap();
+            assert!(err.to_string().contains("single statement"), "{err}");
+            let err = Statement::prepare(db.0, "  -- nothing").err().unwrap();
+            assert!(err.to_string().contains("no statement"), "{err}");
+            let err = Statement::prepare(db.0, "SELEC 1").err().unwrap();
+            assert!(matches!(err, SqliteGisError::Sqlite(_)), "{err}");
+        }
+    }
+
+    #[test]
+    fn import_then_query_round_trips_features() {
+        let db = Connection::open(":memory:", true).unwrap();
+        let fields = [
+            Field::new("name", FieldType::Text),
+            Field::new("rank", FieldType::Integer),
+        ];
+        let features = vec![
+            Feature {
+                geometry: point("POINT(1 2)"),
+                properties: vec![Value::Text("a".to_string()), Value::Integer(1)],
+            },
+            Feature {
+                geometry: None,
+                properties: vec![Value::Null, Value::Integer(2)],
+            },
+        ];
+        // SAFETY: the handle stays open while `db` lives.
+        unsafe {
+            let rows = import_features(
+                db.0,
+                "t",
+                &fields,
+                features.clone().into_iter().map(Ok),
+                false,
+            );
+            assert_eq!(rows.unwrap(), 2);
+            let (queried_fields, queried) =
+                query_features(db.0, "SELECT * FROM t ORDER BY rank", None).unwrap();
+            assert_eq!(queried_fields, fields);
+            assert_eq!(queried, features);
+
+            // Without a `geom` column, the geometry is the first column
+            // holding only geometries.
+            let (_, queried) =
+                query_features(db.0, "SELECT rank, geom AS shape FROM t", None).unwrap();
+            assert_eq!(queried[0].geometry, features[0].geometry);
+            assert_eq!(queried[0].properties, vec![Value::Integer(1)]);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_tables_and_rolls_back_failed_imports() {
+        let db = Connection::open(":memory:", true).unwrap();
+        let fields = [Field::new("n", FieldType::Integer)];
+        let feature = |n| {
+            Ok(Feature {
+                geometry: point("POINT(0 0)"),
+                properties: vec![Value::Integer(n)],
+            })
+        };
+        // SAFETY: the handle stays open while `db` lives.
+        unsafe {
+            let err = import_features(db.0, "a b", &fields, std::iter::empty(), false).unwrap_err();
+            assert!(err.to_string().contains("invalid table name"), "{err}");
+            let clash = [Field::new("GEOM", FieldType::Text)];
+            let err = import_features(db.0, "t", &clash, std::iter::empty(), false).unwrap_err();
+            assert!(err.to_string().contains("clashes"), "{err}");
+
+            // A reader error midway leaves neither the table nor its rows.
+            let failing = [
+                feature(1),
+                Err(SqliteGisError::InvalidInput("truncated file".to_string())),
+            ];
+            let err = import_features(db.0, "t", &fields, failing.into_iter(), false).unwrap_err();
+            assert!(err.to_string().contains("truncated file"), "{err}");
+        }
+        assert_eq!(
+            db.query_i64("SELECT count(*) FROM sqlite_master").unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn query_features_rejects_writes_and_non_geometry_columns() {
+        let db = Connection::open(":memory:", true).unwrap();
+        db.query_rows("CREATE TABLE t (geom, label TEXT)").unwrap();
+        db.query_rows("INSERT INTO t VALUES ('POINT(0 0)', 'x')")
+            .unwrap();
+        // SAFETY: the handle stays open while `db` lives.
+        unsafe {
+            let err = query_features(db.0, "DELETE FROM t", None).unwrap_err();
+            assert!(err.to_string().contains("read-only"), "{err}");
+            let err = query_features(db.0, "SELECT * FROM t", None).unwrap_err();
+            assert!(err.to_string().contains("non-geometry value"), "{err}");
+            let err = query_features(db.0, "SELECT label FROM t", None).unwrap_err();
+            assert!(err.to_string().contains("no geometry column"), "{err}");
+            let err = query_features(db.0, "SELECT * FROM t", Some("shape")).unwrap_err();
+            assert!(err.to_string().contains("no column [shape]"), "{err}");
+        }
+    }
+
+    #[test]
+    fn query_layout_streams_what_query_features_collects() {
+        let db = Connection::open(":memory:", true).unwrap();
+        db.query_rows("CREATE TABLE t (n, label TEXT, shape BLOB)")
+            .unwrap();
+        db.query_rows(
+            "INSERT INTO t VALUES (1, 'a', ST_Point(0, 0)), (2.5, NULL, NULL), \
+               (3, 'c', ST_Point(1, 1))",
+        )
+        .unwrap();
+        let query = "SELECT * FROM t ORDER BY n";
+        // SAFETY: the handle stays open while `db` lives.
+        unsafe {
+            let (fields, features) = query_features(db.0, query, None).unwrap();
+            let layout = query_layout(db.0, query, None).unwrap();
+            assert_eq!(layout.fields, fields);
+            assert_eq!(
+                fields,
+                vec![
+                    Field::new("n", FieldType::Real),
+                    Field::new("label", FieldType::Text),
+                ]
+            );
+            let mut streamed = Vec::new();
+            for_each_feature(db.0, query, &layout, |feature| {
+                streamed.push(feature);
+                Ok(())
+            })
+            .unwrap();
+            assert_eq!(streamed, features);
+
+            let err =
+                for_each_feature(db.0, "SELECT n, shape FROM t", &layout, |_| Ok(())).unwrap_err();
+            assert!(err.to_string().contains("different columns"), "{err}");
+        }
+    }
+}
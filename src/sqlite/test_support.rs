@@ -0,0 +1,48 @@
+//! Fixtures shared by the unit tests of the file-format import and export
+//! glue.
+
+use std::path::Path;
+
+use super::connection::Connection;
+use crate::core::error::{Result, SqliteGisError};
+use crate::core::features::Value;
+
+/// An in-memory database with a `places` table of three rows, one each of
+/// a point, a line and a polygon in `srid`, with integer, text and real
+/// attributes and one NULL.
+pub(super) fn places(srid: i32) -> Connection {
+    let db = Connection::open(":memory:", true).unwrap();
+    db.query_rows("CREATE TABLE places (id INTEGER, name TEXT, score REAL, geom BLOB)")
+        .unwrap();
+    db.query_rows(&format!(
+        "INSERT INTO places VALUES \
+           (1, 'a', 0.5, ST_Point(1, 2, {srid})), \
+           (2, NULL, 2, ST_GeomFromText('LINESTRING(0 0,3 4)', {srid})), \
+           (3, 'c', NULL, ST_GeomFromText('POLYGON((0 0,1 0,1 1,0 0))', {srid}))"
+    ))
+    .unwrap();
+    db
+}
+
+/// Check that `import` into table `copy` fails, and leaves no table behind,
+/// for garbage, for `valid` cut short and for a missing file.
+pub(super) fn assert_import_rejects_malformed(
+    db: &Connection,
+    valid: &[u8],
+    import: impl Fn(&Path) -> Result<i64>,
+) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("bad");
+    let cut_short = [&valid[..valid.len() / 2], &valid[..valid.len() - 8]];
+    for data in [&b"not a geospatial file"[..]].into_iter().chain(cut_short) {
+        std::fs::write(&path, data).unwrap();
+        assert!(import(&path).is_err(), "{} bytes", data.len());
+        assert_eq!(
+            db.query_value("SELECT count(*) FROM sqlite_master WHERE name = 'copy'")
+                .unwrap(),
+            Value::Integer(0)
+        );
+    }
+    let err = import(&dir.path().join("missing")).unwrap_err();
+    assert!(matches!(err, SqliteGisError::Io(_)), "{err}");
+}
@@ -0,0 +1,342 @@
+//! Local vector tile server, built with the `tile-server` feature and run
+//! as the `sqlitegis-tiles` binary.
+//!
+//! Every column with a spatial index (see `CreateSpatialIndex`) is a
+//! layer, named after its table, or `table.column` for a column other
+//! than `geom`. The server answers
+//!
+//! - `GET /{layer}/{z}/{x}/{y}.mvt` with a single-layer Mapbox Vector
+//!   Tile: the rows whose `*_rtree` entry meets the buffered tile
+//!   envelope, clipped by `ST_AsMVTGeom` and carrying every other column
+//!   as a property;
+//! - `GET /{layer}.json` with the layer's TileJSON 3.0.0, including its
+//!   extent in longitude and latitude;
+//! - `GET /` with a JSON object mapping layer names to TileJSON URLs.
+//!
+//! Tiles are Web Mercator, so layers must hold geometries in SRID 3857,
+//! which are used as they are, or SRID 4326, which are projected on the
+//! fly. It is meant for a laptop: HTTP/1.1 with one thread and one
+//! read-only connection per client (64 clients at most), no TLS and no
+//! caching.
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::net::{SocketAddr, TcpListener, ToSocketAddrs};
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::Arc;
+
+use geo::{BoundingRect, Coord, MapCoords};
+use serde_json::json;
+
+use super::connection::Connection;
+use super::http::{self, Request, Response};
+use super::layers::{find_layers, geometry_ewkb, open_for_serving, Layer, Projection, Skipped};
+use crate::core::error::{Result, SqliteGisError};
+use crate::core::ewkb::{parse_ewkb, write_ewkb};
+use crate::core::features::Value;
+use crate::core::functions::constructors::{
+    from_web_mercator, st_tile_envelope, to_web_mercator, MAX_LATITUDE,
+};
+use crate::core::functions::mvt::{
+    st_as_mvt_geom, MvtLayer, MvtValue, DEFAULT_BUFFER, DEFAULT_EXTENT,
+};
+
+/// Highest zoom level the TileJSON advertises.
+const MAX_ZOOM: u32 = 22;
+
+/// Run the `sqlitegis-tiles` binary on `args`, the first of which is the
+/// program name. Serves until killed; returns 1 when the database or the
+/// address cannot be opened and 2 for usage errors.
+pub fn run<I, T>(args: I) -> ExitCode
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let (db, bind) = match http::server_args(
+        "sqlitegis-tiles",
+        "Serve the spatially indexed tables of a SQLite file as vector tiles",
+        args,
+    ) {
+        Ok(args) => args,
+        Err(code) => return code,
+    };
+
+    let server = match TileServer::bind(&db, &bind) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("sqlitegis-tiles: error: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    for (name, reason) in &server.skipped {
+        eprintln!("sqlitegis-tiles: skipping {name}: {reason}");
+    }
+    let base = match server.local_addr() {
+        Ok(addr) => format!("http://{addr}"),
+        Err(_) => format!("http://{bind}"),
+    };
+    println!("serving {} layers from {base}/", server.layers.len());
+    for layer in server.layers.iter() {
+        println!("  {base}/{}.json", layer.name);
+    }
+    match server.run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("sqlitegis-tiles: error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// A vector tile server over one SQLite file.
+#[derive(Debug)]
+pub struct TileServer {
+    listener: TcpListener,
+    db: String,
+    layers: Arc<Vec<Layer>>,
+    skipped: Skipped,
+}
+
+impl TileServer {
+    /// Open the database at `db`, find its layers and listen on `addr`.
+    /// Bind to port 0 to let the system pick a free port.
+    pub fn bind(db: impl AsRef<Path>, addr: impl ToSocketAddrs) -> Result<Self> {
+        let db = db.as_ref().to_string_lossy().into_owned();
+        let (layers, skipped) = find_layers(&open_for_serving(&db)?)?;
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            db,
+            layers: Arc::new(layers),
+            skipped,
+        })
+    }
+
+    /// Address the server listens on.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Names of the layers served, in order.
+    pub fn layer_names(&self) -> Vec<&str> {
+        self.layers
+            .iter()
+            .map(|layer| layer.name.as_str())
+            .collect()
+    }
+
+    /// Accept connections until the listener fails, serving each on its
+    /// own thread.
+    pub fn run(self) -> Result<()> {
+        let layers = Arc::clone(&self.layers);
+        http::serve(
+            &self.listener,
+            &self.db,
+            Arc::new(move |db: &Connection, request: &Request| route(db, &layers, request)),
+        )
+    }
+}
+
+/// Encode tile `z/x/y` of `layer`.
+fn render_tile(db: &Connection, layer: &Layer, z: u32, x: u32, y: u32) -> Result<Vec<u8>> {
+    let envelope = st_tile_envelope(z, x, y)?;
+    let rect = parse_ewkb(&envelope)?
+        .0
+        .bounding_rect()
+        .ok_or_else(|| SqliteGisError::InvalidInput("empty tile envelope".to_string()))?;
+    let margin = rect.width() * f64::from(DEFAULT_BUFFER) / f64::from(DEFAULT_EXTENT);
+    let mut min = Coord {
+        x: rect.min().x - margin,
+        y: rect.min().y - margin,
+    };
+    let mut max = Coord {
+        x: rect.max().x + margin,
+        y: rect.max().y + margin,
+    };
+    if layer.projection == Projection::LonLat {
+        min = from_web_mercator(min);
+        max = from_web_mercator(max);
+    }
+
+    let (window, params) = layer.window(min, max);
+    let rows = db.query_rows_with(&format!("SELECT {} {window}", layer.select_list()), &params)?;
+
+    let mut mvt = MvtLayer::new(&layer.name, DEFAULT_EXTENT);
+    for mut row in rows {
+        let Value::Blob(blob) = std::mem::replace(&mut row[0], Value::Null) else {
+            continue;
+        };
+        let blob = geometry_ewkb(&blob)?;
+        let projected;
+        let geometry = match layer.projection {
+            Projection::WebMercator => &blob[..],
+            Projection::LonLat => {
+                let (geometry, _) = parse_ewkb(&blob)?;
+                projected = write_ewkb(&geometry.map_coords(to_web_mercator), Some(3857))?;
+                &projected[..]
+            }
+        };
+        let Some(tile_geometry) =
+            st_as_mvt_geom(geometry, &envelope, DEFAULT_EXTENT, DEFAULT_BUFFER, true)?
+        else {
+            continue;
+        };
+        let properties = layer
+            .properties
+            .iter()
+            .zip(row.into_iter().skip(1))
+            .filter_map(|((name, _), value)| {
+                let value = match value {
+                    Value::Integer(i) => MvtValue::Int(i),
+                    Value::Real(r) => MvtValue::Double(r),
+                    Value::Text(t) => MvtValue::String(t),
+                    Value::Null | Value::Blob(_) => return None,
+                };
+                Some((name, value))
+            });
+        mvt.add_feature(&tile_geometry, properties)?;
+    }
+    Ok(if mvt.is_empty() {
+        Vec::new()
+    } else {
+        mvt.encode()
+    })
+}
+
+fn tilejson(layer: &Layer, base: &str) -> serde_json::Value {
+    let [west, south, east, north] =
+        layer
+            .bounds
+            .unwrap_or([-180.0, -MAX_LATITUDE, 180.0, MAX_LATITUDE]);
+    let fields: BTreeMap<&str, &str> = layer
+        .properties
+        .iter()
+        .map(|(name, kind)| (name.as_str(), *kind))
+        .collect();
+    json!({
+        "tilejson": "3.0.0",
+        "name": layer.name,
+        "scheme": "xyz",
+        "tiles": [format!("{base}/{}/{{z}}/{{x}}/{{y}}.mvt", layer.name)],
+        "minzoom": 0,
+        "maxzoom": MAX_ZOOM,
+        "bounds": [west, south, east, north],
+        "center": [(west + east) / 2.0, (south + north) / 2.0, 0],
+        "vector_layers": [{
+            "id": layer.name,
+            "fields": fields,
+            "minzoom": 0,
+            "maxzoom": MAX_ZOOM,
+        }],
+    })
+}
+
+/// Answer one request.
+fn route(db: &Connection, layers: &[Layer], request: &Request) -> Response {
+    let (path, base) = (request.path, &request.base);
+    let find = |name: &str| layers.iter().find(|layer| layer.name == name);
+    if path == "/" {
+        let index: BTreeMap<&str, String> = layers
+            .iter()
+            .map(|layer| (layer.name.as_str(), format!("{base}/{}.json", layer.name)))
+            .collect();
+        return Response::json(&json!(index), "application/json");
+    }
+    let path = path.trim_start_matches('/');
+    if let Some(layer) = path.strip_suffix(".json").and_then(find) {
+        return Response::json(&tilejson(layer, base), "application/json");
+    }
+    let parts: Vec<&str> = path.split('/').collect();
+    let [name, z, x, y] = parts[..] else {
+        return Response::error(404, "not found");
+    };
+    let Some(layer) = find(name) else {
+        return Response::error(404, format!("no layer [{name}]"));
+    };
+    let coords = y
+        .strip_suffix(".mvt")
+        .and_then(|y| Some((z.parse().ok()?, x.parse().ok()?, y.parse().ok()?)));
+    let Some((z, x, y)) = coords else {
+        return Response::error(404, "not found");
+    };
+    match render_tile(db, layer, z, x, y) {
+        Ok(body) => Response {
+            status: 200,
+            content_type: "application/vnd.mapbox-vector-tile",
+            body,
+        },
+        Err(e @ SqliteGisError::InvalidInput(_)) => Response::error(400, e.to_string()),
+        Err(e) => Response::error(500, e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sqlite::http::tests::get;
+
+    #[test]
+    fn serves_tiles_and_tilejson_on_localhost() {
+        let dir = std::env::temp_dir().join(format!("sqlitegis-tiles-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("tiles.db").to_string_lossy().into_owned();
+        let db = Connection::open(&path, true).unwrap();
+        for sql in [
+            "CREATE TABLE cities (name TEXT, population INTEGER, geom BLOB)",
+            "INSERT INTO cities VALUES \
+               ('Berlin', 3800000, ST_Point(13.4, 52.5, 4326)), \
+               ('Lima', 10000000, ST_Point(-77.0, -12.0, 4326))",
+            "SELECT CreateSpatialIndex('cities', 'geom')",
+            "CREATE TABLE grid (geom BLOB)",
+            "INSERT INTO grid VALUES (ST_GeomFromText('POINT(1000 1000)', 2154))",
+            "SELECT CreateSpatialIndex('grid', 'geom')",
+        ] {
+            db.query_rows(sql).unwrap();
+        }
+        drop(db);
+
+        let server = TileServer::bind(&path, "127.0.0.1:0").unwrap();
+        assert_eq!(server.layer_names(), ["cities"]);
+        assert_eq!(server.skipped[0].0, "grid");
+        let addr = server.local_addr().unwrap();
+        std::thread::spawn(move || server.run());
+
+        let (head, body) = get(addr, "/");
+        assert!(head.starts_with("HTTP/1.1 200"), "{head}");
+        let index: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(index["cities"], "http://tiles.test/cities.json");
+
+        let (_, body) = get(addr, "/cities.json");
+        let tilejson: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            tilejson["tiles"][0],
+            "http://tiles.test/cities/{z}/{x}/{y}.mvt"
+        );
+        assert_eq!(
+            tilejson["vector_layers"][0]["fields"]["population"],
+            "Number"
+        );
+        let bounds: Vec<f64> = serde_json::from_value(tilejson["bounds"].clone()).unwrap();
+        assert!((bounds[0] + 77.0).abs() < 1e-4 && (bounds[3] - 52.5).abs() < 1e-4);
+
+        // Berlin is in tile 4/8/5, Lima is not.
+        let (head, tile) = get(addr, "/cities/4/8/5.mvt?fresh=1");
+        assert!(
+            head.contains("application/vnd.mapbox-vector-tile"),
+            "{head}"
+        );
+        let contains = |needle: &[u8]| tile.windows(needle.len()).any(|w| w == needle);
+        assert!(contains(b"cities") && contains(b"Berlin") && !contains(b"Lima"));
+        let (head, tile) = get(addr, "/cities/4/0/0.mvt");
+        assert!(
+            head.starts_with("HTTP/1.1 200") && tile.is_empty(),
+            "{head}"
+        );
+
+        assert!(get(addr, "/cities/1/5/0.mvt").0.starts_with("HTTP/1.1 400"));
+        assert!(get(addr, "/roads/0/0/0.mvt").0.starts_with("HTTP/1.1 404"));
+        assert!(get(addr, "/cities/0/0/0.png").0.starts_with("HTTP/1.1 404"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
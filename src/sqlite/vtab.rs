@@ -0,0 +1,402 @@
+//! `sqlitegis_geojson` and `sqlitegis_csv` virtual tables: read-only views
+//! of a GeoJSON or CSV-with-WKT file, one row per feature.
+//!
+//! ```sql
+//! CREATE VIRTUAL TABLE parks USING sqlitegis_geojson('parks.geojson');
+//! CREATE VIRTUAL TABLE stops USING sqlitegis_csv('stops.csv', wkt_column='wkt', srid=4326);
+//! ```
+//!
+//! The first column is `geom`, the feature geometry as EWKB, followed by
+//! one column per attribute as read by [`crate::core::text_features`]. The
+//! file is parsed when the table is created or the schema is loaded, so
+//! later edits to the file show up only on the next connection.
+//!
+//! Like the `Import*` functions, the tables only read files on connections
+//! registered with file access, and they are direct-only: triggers and
+//! views, which a database file can carry, cannot query them.
+
+use std::ffi::{c_void, CStr, CString};
+use std::os::raw::{c_char, c_int};
+
+use super::ffi::{file_access_denied, module_file_access, with_module_settings};
+use super::sqlite_compat::*;
+use super::table_io::{quote, GEOMETRY_COLUMN};
+use crate::core::error::{Result, SqliteGisError};
+use crate::core::features::{Feature, Field, Value};
+use crate::core::text_features::{read_csv, read_geojson};
+
+/// Module name of the GeoJSON virtual table.
+const GEOJSON_MODULE: &str = "sqlitegis_geojson";
+/// Module name of the CSV-with-WKT virtual table.
+const CSV_MODULE: &str = "sqlitegis_csv";
+/// Name of the WKT column `sqlitegis_csv` reads when none is given.
+const DEFAULT_WKT_COLUMN: &str = "wkt";
+
+/// `SQLITE_VTAB_DIRECTONLY`, the `sqlite3_vtab_config` option that keeps
+/// a virtual table out of triggers and views. Defined here like the
+/// function flags in `ffi`, since not every binding exports it.
+const SQLITE_VTAB_DIRECTONLY_OP: c_int = 3;
+
+/// `idxNum` of a plan that looks a row up by `rowid`.
+const ROWID_LOOKUP: c_int = 1;
+
+/// Module names paired with the module they are registered as.
+pub(super) static FILE_MODULES: [(&str, &sqlite3_module); 2] =
+    [(GEOJSON_MODULE, &FILE_MODULE), (CSV_MODULE, &FILE_MODULE)];
+
+static FILE_MODULE: sqlite3_module = file_module();
+
+const fn file_module() -> sqlite3_module {
+    // SAFETY: an all-zero `sqlite3_module` is valid, every callback `None`.
+    let mut module: sqlite3_module = unsafe { std::mem::zeroed() };
+    module.iVersion = 1;
+    module.xCreate = Some(x_connect);
+    module.xConnect = Some(x_connect);
+    module.xBestIndex = Some(x_best_index);
+    module.xDisconnect = Some(x_disconnect);
+    module.xDestroy = Some(x_disconnect);
+    module.xOpen = Some(x_open);
+    module.xClose = Some(x_close);
+    module.xFilter = Some(x_filter);
+    module.xNext = Some(x_next);
+    module.xEof = Some(x_eof);
+    module.xColumn = Some(x_column);
+    module.xRowid = Some(x_rowid);
+    module
+}
+
+#[repr(C)]
+struct FileTable {
+    base: sqlite3_vtab,
+    features: Vec<Feature>,
+}
+
+#[repr(C)]
+struct FileCursor {
+    base: sqlite3_vtab_cursor,
+    row: usize,
+    end: usize,
+}
+
+/// Arguments of `CREATE VIRTUAL TABLE ... USING module(...)`.
+#[derive(Debug, PartialEq)]
+struct FileTableArgs {
+    path: String,
+    srid: Option<i32>,
+    wkt_column: String,
+}
+
+/// Strip SQL quotes from a module argument.
+fn unquote(arg: &str) -> String {
+    let arg = arg.trim();
+    for quote in ['\'', '"'] {
+        if arg.len() >= 2 && arg.starts_with(quote) && arg.ends_with(quote) {
+            let doubled = format!("{quote}{quote}");
+            return arg[1..arg.len() - 1].replace(&doubled, &quote.to_string());
+        }
+    }
+    arg.to_string()
+}
+
+/// Parse the module arguments: a quoted file path, then `key=value`
+/// options (`srid`, and `wkt_column` for CSV).
+fn parse_args(module: &str, args: &[&str]) -> Result<FileTableArgs> {
+    let mut parsed = FileTableArgs {
+        path: String::new(),
+        srid: None,
+        wkt_column: DEFAULT_WKT_COLUMN.to_string(),
+    };
+    let mut path = None;
+    for arg in args {
+        let arg = arg.trim();
+        let option = match arg.split_once('=') {
+            Some((key, value)) if !arg.starts_with(['\'', '"']) => Some((key.trim(), value)),
+            _ => None,
+        };
+        match option {
+            None if path.is_none() => path = Some(unquote(arg)),
+            None => {
+                return Err(SqliteGisError::InvalidInput(format!(
+                    "unexpected argument [{arg}]"
+                )))
+            }
+            Some((key, value)) if key.eq_ignore_ascii_case("srid") => {
+                let value = unquote(value);
+                parsed.srid = Some(value.parse().map_err(|_| {
+                    SqliteGisError::InvalidInput(format!("srid [{value}] is not an integer"))
+                })?);
+            }
+            Some((key, value))
+                if module == CSV_MODULE && key.eq_ignore_ascii_case("wkt_column") =>
+            {
+                parsed.wkt_column = unquote(value);
+            }
+            Some((key, _)) => {
+                return Err(SqliteGisError::InvalidInput(format!(
+                    "unknown option [{key}]"
+                )))
+            }
+        }
+    }
+    parsed.path = path.ok_or_else(|| {
+        SqliteGisError::InvalidInput("a file path argument is required".to_string())
+    })?;
+    Ok(parsed)
+}
+
+/// Read the file named by `args` and build the `CREATE TABLE` statement
+/// for `sqlite3_declare_vtab`. Arguments are checked before file access,
+/// so their errors read the same either way.
+fn load(module: &str, args: &[&str], file_access: bool) -> Result<(String, Vec<Feature>)> {
+    let args = parse_args(module, args)?;
+    if !file_access {
+        return Err(file_access_denied());
+    }
+    let text = std::fs::read_to_string(&args.path)?;
+    let (fields, features) = if module == CSV_MODULE {
+        read_csv(&text, &args.wkt_column, args.srid)?
+    } else {
+        read_geojson(&text, args.srid)?
+    };
+    Ok((declare_sql(&fields)?, features))
+}
+
+fn declare_sql(fields: &[Field]) -> Result<String> {
+    if let Some(field) = fields
+        .iter()
+        .find(|f| f.name.eq_ignore_ascii_case(GEOMETRY_COLUMN))
+    {
+        return Err(SqliteGisError::InvalidInput(format!(
+            "attribute [{}] clashes with the geometry column",
+            field.name
+        )));
+    }
+    let columns: Vec<String> = std::iter::once(format!("{} BLOB", quote(GEOMETRY_COLUMN)))
+        .chain(
+            fields
+                .iter()
+                .map(|f| format!("{} {}", quote(&f.name), f.field_type.sql_type())),
+        )
+        .collect();
+    Ok(format!("CREATE TABLE x({})", columns.join(", ")))
+}
+
+/// Hand `msg` to SQLite as the `CREATE VIRTUAL TABLE` error.
+unsafe fn set_vtab_error(pz_err: *mut *mut c_char, msg: &str) {
+    let msg = CString::new(msg.replace('\0', " ")).expect("NUL bytes were replaced");
+    *pz_err = sqlite3_mprintf(c"%s".as_ptr(), msg.as_ptr());
+}
+
+unsafe extern "C" fn x_connect(
+    db: *mut sqlite3,
+    aux: *mut c_void,
+    argc: c_int,
+    argv: *const *const c_char,
+    pp_vtab: *mut *mut sqlite3_vtab,
+    pz_err: *mut *mut c_char,
+) -> c_int {
+    let args: Vec<&str> = (0..argc.max(0) as usize)
+        .map(|i| CStr::from_ptr(*argv.add(i)).to_str().unwrap_or_default())
+        .collect();
+    let module = args.first().copied().unwrap_or(GEOJSON_MODULE);
+    let rc = sqlite3_vtab_config(db, SQLITE_VTAB_DIRECTONLY_OP);
+    if rc != SQLITE_OK {
+        set_vtab_error(
+            pz_err,
+            &format!("{module}: cannot mark the table direct-only"),
+        );
+        return rc;
+    }
+    let file_access = module_file_access(aux);
+    let loaded = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        with_module_settings(aux, || {
+            load(module, args.get(3..).unwrap_or_default(), file_access)
+        })
+    }));
+    let (sql, features) = match loaded {
+        Ok(Ok(loaded)) => loaded,
+        Ok(Err(e)) => {
+            set_vtab_error(pz_err, &format!("{module} [{}]: {e}", e.kind()));
+            return e.kind().sqlite_code();
+        }
+        Err(_) => {
+            set_vtab_error(pz_err, &format!("{module}: panic while reading the file"));
+            return SQLITE_ERROR;
+        }
+    };
+    let Ok(sql) = CString::new(sql) else {
+        set_vtab_error(
+            pz_err,
+            &format!("{module}: column name contains a NUL byte"),
+        );
+        return SQLITE_ERROR;
+    };
+    let rc = sqlite3_declare_vtab(db, sql.as_ptr());
+    if rc != SQLITE_OK {
+        return rc;
+    }
+    let table = Box::new(FileTable {
+        base: std::mem::zeroed(),
+        features,
+    });
+    *pp_vtab = Box::into_raw(table).cast();
+    SQLITE_OK
+}
+
+unsafe extern "C" fn x_disconnect(vtab: *mut sqlite3_vtab) -> c_int {
+    drop(Box::from_raw(vtab.cast::<FileTable>()));
+    SQLITE_OK
+}
+
+unsafe extern "C" fn x_best_index(vtab: *mut sqlite3_vtab, info: *mut sqlite3_index_info) -> c_int {
+    let table = &*vtab.cast::<FileTable>();
+    let info = &mut *info;
+    let constraints = std::slice::from_raw_parts(info.aConstraint, info.nConstraint as usize);
+    let rowid_eq = constraints.iter().position(|c| {
+        c.iColumn == -1 && c.usable != 0 && c_int::from(c.op) == SQLITE_INDEX_CONSTRAINT_EQ
+    });
+    if let Some(i) = rowid_eq {
+        let usage = &mut *info.aConstraintUsage.add(i);
+        usage.argvIndex = 1;
+        usage.omit = 1;
+        info.idxNum = ROWID_LOOKUP;
+        info.idxFlags = SQLITE_INDEX_SCAN_UNIQUE;
+        info.estimatedCost = 1.0;
+        info.estimatedRows = 1;
+    } else {
+        info.estimatedCost = table.features.len() as f64 + 1.0;
+        info.estimatedRows = table.features.len() as i64;
+    }
+    SQLITE_OK
+}
+
+unsafe extern "C" fn x_open(
+    _vtab: *mut sqlite3_vtab,
+    pp_cursor: *mut *mut sqlite3_vtab_cursor,
+) -> c_int {
+    let cursor = Box::new(FileCursor {
+        base: std::mem::zeroed(),
+        row: 0,
+        end: 0,
+    });
+    *pp_cursor = Box::into_raw(cursor).cast();
+    SQLITE_OK
+}
+
+unsafe extern "C" fn x_close(cursor: *mut sqlite3_vtab_cursor) -> c_int {
+    drop(Box::from_raw(cursor.cast::<FileCursor>()));
+    SQLITE_OK
+}
+
+unsafe fn cursor_parts<'a>(
+    cursor: *mut sqlite3_vtab_cursor,
+) -> (&'a mut FileCursor, &'a FileTable) {
+    let cursor = &mut *cursor.cast::<FileCursor>();
+    let table = &*cursor.base.pVtab.cast::<FileTable>();
+    (cursor, table)
+}
+
+unsafe extern "C" fn x_filter(
+    cursor: *mut sqlite3_vtab_cursor,
+    idx_num: c_int,
+    _idx_str: *const c_char,
+    argc: c_int,
+    argv: *mut *mut sqlite3_value,
+) -> c_int {
+    let (cursor, table) = cursor_parts(cursor);
+    let len = table.features.len();
+    (cursor.row, cursor.end) = (0, len);
+    if idx_num == ROWID_LOOKUP && argc > 0 {
+        let value = *argv;
+        let rowid = sqlite3_value_int64(value);
+        let row = usize::try_from(rowid - 1).ok().filter(|&row| row < len);
+        (cursor.row, cursor.end) = match row {
+            Some(row) if sqlite3_value_type(value) == SQLITE_INTEGER => (row, row + 1),
+            _ => (0, 0),
+        };
+    }
+    SQLITE_OK
+}
+
+unsafe extern "C" fn x_next(cursor: *mut sqlite3_vtab_cursor) -> c_int {
+    (*cursor.cast::<FileCursor>()).row += 1;
+    SQLITE_OK
+}
+
+unsafe extern "C" fn x_eof(cursor: *mut sqlite3_vtab_cursor) -> c_int {
+    let cursor = &*cursor.cast::<FileCursor>();
+    c_int::from(cursor.row >= cursor.end)
+}
+
+unsafe extern "C" fn x_column(
+    cursor: *mut sqlite3_vtab_cursor,
+    ctx: *mut sqlite3_context,
+    i: c_int,
+) -> c_int {
+    let (cursor, table) = cursor_parts(cursor);
+    let feature = &table.features[cursor.row];
+    let value = match i {
+        0 => match &feature.geometry {
+            Some(blob) => return result_blob(ctx, blob),
+            None => &Value::Null,
+        },
+        i => feature
+            .properties
+            .get(i as usize - 1)
+            .unwrap_or(&Value::Null),
+    };
+    match value {
+        Value::Null => sqlite3_result_null(ctx),
+        Value::Integer(v) => sqlite3_result_int64(ctx, *v),
+        Value::Real(v) => sqlite3_result_double(ctx, *v),
+        Value::Text(v) => match c_int::try_from(v.len()) {
+            Ok(len) => sqlite3_result_text(ctx, v.as_ptr().cast(), len, sqlite_transient()),
+            Err(_) => sqlite3_result_error_toobig(ctx),
+        },
+        Value::Blob(v) => return result_blob(ctx, v),
+    }
+    SQLITE_OK
+}
+
+unsafe fn result_blob(ctx: *mut sqlite3_context, blob: &[u8]) -> c_int {
+    match c_int::try_from(blob.len()) {
+        Ok(len) => sqlite3_result_blob(ctx, blob.as_ptr().cast(), len, sqlite_transient()),
+        Err(_) => sqlite3_result_error_toobig(ctx),
+    }
+    SQLITE_OK
+}
+
+unsafe extern "C" fn x_rowid(cursor: *mut sqlite3_vtab_cursor, rowid: *mut sqlite3_int64) -> c_int {
+    *rowid = (*cursor.cast::<FileCursor>()).row as sqlite3_int64 + 1;
+    SQLITE_OK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_module_arguments() {
+        let args = parse_args(
+            CSV_MODULE,
+            &["'it''s.csv'", "wkt_column='shape'", " srid = 4326"],
+        )
+        .unwrap();
+        assert_eq!(
+            args,
+            FileTableArgs {
+                path: "it's.csv".to_string(),
+                srid: Some(4326),
+                wkt_column: "shape".to_string(),
+            }
+        );
+        let args = parse_args(GEOJSON_MODULE, &["\"a=b.geojson\""]).unwrap();
+        assert_eq!(args.path, "a=b.geojson");
+        assert_eq!(args.srid, None);
+
+        assert!(parse_args(GEOJSON_MODULE, &[]).is_err());
+        assert!(parse_args(GEOJSON_MODULE, &["'a'", "'b'"]).is_err());
+        assert!(parse_args(GEOJSON_MODULE, &["'a'", "wkt_column=wkt"]).is_err());
+        assert!(parse_args(CSV_MODULE, &["'a'", "srid=x"]).is_err());
+    }
+}
@@ -5,7 +5,9 @@
 
 use diesel::dsl::select;
 use diesel::sql_types::{Integer, Nullable};
-use sqlitegis::core::function_catalog::SQLITE_DETERMINISTIC_FUNCTIONS;
+use sqlitegis::core::function_catalog::{
+    SQLITE_AGGREGATE_FUNCTIONS, SQLITE_DETERMINISTIC_FUNCTIONS,
+};
 use sqlitegis::diesel::prelude::*;
 use std::collections::BTreeSet;
 
@@ -168,6 +170,7 @@ fn diesel_sql_functions_are_backed_by_sqlite_catalog() {
     let diesel_signatures = diesel_sql_signatures(DIESEL_FUNCTIONS_SRC);
     let catalog_signatures: BTreeSet<(String, usize)> = SQLITE_DETERMINISTIC_FUNCTIONS
         .iter()
+        .chain(SQLITE_AGGREGATE_FUNCTIONS)
         .map(|spec| (spec.name.to_ascii_uppercase(), spec.n_arg as usize))
         .collect();
 
@@ -186,6 +189,7 @@ fn diesel_sql_functions_are_backed_by_sqlite_catalog() {
 fn catalog_functions_are_covered_by_diesel_declarations() {
     let catalog_signatures: BTreeSet<(String, usize)> = SQLITE_DETERMINISTIC_FUNCTIONS
         .iter()
+        .chain(SQLITE_AGGREGATE_FUNCTIONS)
         .map(|spec| (spec.name.to_ascii_uppercase(), spec.n_arg as usize))
         .collect();
 
@@ -8,8 +8,8 @@
 use diesel::prelude::*;
 use diesel::sql_query;
 use sqlitegis::core::function_catalog::{
-    SemanticCase, SemanticExpectation, SqliteFunctionSpec, SQLITE_DETERMINISTIC_FUNCTIONS,
-    SQLITE_DIRECT_ONLY_FUNCTIONS,
+    SemanticCase, SemanticExpectation, SqliteFunctionSpec, SQLITE_AGGREGATE_FUNCTIONS,
+    SQLITE_DETERMINISTIC_FUNCTIONS, SQLITE_DIRECT_ONLY_FUNCTIONS,
 };
 
 #[path = "diesel_predicate_bool_helpers.rs"]
@@ -47,6 +47,38 @@ fn shared_predicates_and_relate_bool_semantics() {
     predicate_bool_helpers::assert_predicates_and_relate_bool_semantics_sqlite(&mut c);
 }
 
+#[test]
+fn mvt_tile_via_typed_functions() {
+    use diesel::dsl::{select, sql};
+    use diesel::sql_types::{Nullable, Text};
+    use sqlitegis::core::functions::io::geom_from_text;
+    use sqlitegis::core::functions::mvt::MvtLayer;
+    use sqlitegis::diesel::functions::{st_asmvt, st_makeenvelope, st_point};
+    use sqlitegis::diesel::prelude::*;
+
+    let mut c = conn();
+    let tile_geom: Option<String> = select(
+        st_point(5.0, 2.5)
+            .nullable()
+            .st_asmvtgeom(st_makeenvelope(0.0, 0.0, 10.0, 10.0).nullable())
+            .st_astext(),
+    )
+    .get_result(&mut c)
+    .unwrap();
+    assert_eq!(tile_geom.as_deref(), Some("POINT(2048 3072)"));
+
+    let row = sql::<Nullable<Text>>(
+        "json_object('name', 'a', 'geom', \
+         hex(ST_AsMVTGeom(ST_Point(5, 2.5), ST_MakeEnvelope(0, 0, 10, 10))))",
+    );
+    let tile: Option<Vec<u8>> = select(st_asmvt(row, "places")).get_result(&mut c).unwrap();
+
+    let mut layer = MvtLayer::new("places", 4096);
+    let point = geom_from_text("POINT(2048 3072)", None).unwrap();
+    layer.add_feature(&point, [("name", "a".into())]).unwrap();
+    assert_eq!(tile, Some(layer.encode()));
+}
+
 // Native-only: deterministic spatial index behavior
 
 #[test]
@@ -496,6 +528,12 @@ fn catalog_semantic_goldens_via_diesel_sqlite() {
         }
     }
 
+    for spec in SQLITE_AGGREGATE_FUNCTIONS {
+        for case in spec.semantic_cases {
+            assert_semantic_case_via_diesel(&mut c, spec, case);
+        }
+    }
+
     for spec in SQLITE_DIRECT_ONLY_FUNCTIONS {
         for case in spec.semantic_cases {
             assert_semantic_case_via_diesel(&mut c, spec, case);
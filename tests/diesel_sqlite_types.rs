@@ -238,6 +238,79 @@ fn slice_tosql() {
     assert_eq!(blob, ewkb);
 }
 
+// Hex EWKB text
+
+const HEX_POINT_4326: &str = "0101000020E6100000000000000000F03F0000000000000040";
+
+#[test]
+fn hex_string_tosql_stores_ewkb_blob() {
+    let mut c = conn();
+
+    sql_query("INSERT INTO t (id, geom) VALUES (1, ?)")
+        .bind::<Geometry, _>(HEX_POINT_4326)
+        .execute(&mut c)
+        .unwrap();
+
+    let row: GeomRow = sql_query("SELECT id, geom FROM t WHERE id = 1")
+        .get_result(&mut c)
+        .unwrap();
+    let blob = row.geom.expect("geom should not be NULL");
+    assert_eq!(
+        sqlitegis::core::functions::io::as_ewkt(&blob).unwrap(),
+        "SRID=4326;POINT(1 2)"
+    );
+
+    let err = sql_query("INSERT INTO t (id, geom) VALUES (2, ?)")
+        .bind::<Geometry, _>("not hex".to_string())
+        .execute(&mut c);
+    assert!(err.is_err());
+}
+
+#[test]
+fn hex_text_column_is_read_when_enabled() {
+    use sqlitegis::core::ewkb::{with_input_formats, InputFormats};
+
+    let mut c = conn();
+    sql_query(format!(
+        "INSERT INTO t (id, geom) VALUES (1, '{HEX_POINT_4326}')"
+    ))
+    .execute(&mut c)
+    .unwrap();
+
+    let load = |c: &mut SqliteConnection| {
+        sql_query("SELECT id, geom FROM t WHERE id = 1").get_result::<GeoGeogRow>(c)
+    };
+    assert!(load(&mut c).is_err());
+
+    let hex_only = InputFormats {
+        hex_ewkb: true,
+        ..InputFormats::NONE
+    };
+    let row = with_input_formats(hex_only, || load(&mut c)).unwrap();
+    assert_eq!(
+        row.geom,
+        Some(geo::Geometry::Point(geo::Point::new(1.0, 2.0)))
+    );
+    let raw: GeomRow = with_input_formats(hex_only, || {
+        sql_query("SELECT id, geom FROM t WHERE id = 1").get_result(&mut c)
+    })
+    .unwrap();
+    assert_eq!(
+        raw.geom.as_deref().map(sqlitegis::core::ewkb::extract_srid),
+        Some(Some(4326))
+    );
+
+    // Hex that fails to decode is an error for raw bytes as for `geo`.
+    sql_query("INSERT INTO t (id, geom) VALUES (2, '01010000000')")
+        .execute(&mut c)
+        .unwrap();
+    let bad = "SELECT id, geom FROM t WHERE id = 2";
+    assert!(
+        with_input_formats(hex_only, || sql_query(bad).get_result::<GeoGeogRow>(&mut c)).is_err()
+    );
+    assert!(with_input_formats(hex_only, || sql_query(bad).get_result::<GeomRow>(&mut c)).is_err());
+}
+
 // NULL handling
 
 #[test]
@@ -370,6 +443,20 @@ fn debug_query_st_geomfromwkb_accepts_st_asbinary_output() {
     );
 }
 
+#[test]
+fn debug_query_st_ashexewkb() {
+    use sqlitegis::diesel::functions::*;
+    assert_sql_contains!(diesel::dsl::select(st_ashexewkb(g!())), "st_ashexewkb");
+    assert_sql_contains!(
+        diesel::dsl::select(st_ashexewkb_endian(g!(), t!())),
+        "st_ashexewkb"
+    );
+    assert_sql_contains!(
+        diesel::dsl::select(st_geomfromhexewkb(t!())),
+        "st_geomfromhexewkb"
+    );
+}
+
 #[test]
 fn debug_query_st_geomfromewkb() {
     use sqlitegis::diesel::functions::*;
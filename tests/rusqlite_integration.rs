@@ -0,0 +1,233 @@
+#![cfg(all(feature = "rusqlite", not(target_arch = "wasm32")))]
+//! Native SQLite integration tests for the rusqlite integration.
+//!
+//! Registers the functions on each connection with
+//! `sqlitegis::rusqlite::register`, then round-trips geometry values and
+//! runs the R-tree query helpers against a real database.
+
+use rusqlite::Connection;
+use sqlitegis::core::ewkb::{with_input_formats, InputFormats};
+use sqlitegis::core::functions::io::geom_from_text;
+use sqlitegis::core::limits::Limits;
+use sqlitegis::rusqlite::{
+    dwithin_sphere_indexed, intersects_window_indexed, nearest_sphere_indexed, register,
+    register_with_file_access, register_with_limits, Ewkb, Geometry,
+};
+
+fn conn() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    register(&conn).unwrap();
+    conn
+}
+
+/// An indexed `pts` table with Berlin, Paris and an L-shaped polygon whose
+/// box, but not its shape, covers (3, 3).
+fn places() -> Connection {
+    places_in(Connection::open_in_memory().unwrap(), register)
+}
+
+/// [`places`] on `conn`, with the functions registered by `register`.
+fn places_in(
+    conn: Connection,
+    register: impl FnOnce(&Connection) -> rusqlite::Result<()>,
+) -> Connection {
+    register(&conn).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE pts (id INTEGER PRIMARY KEY, name TEXT, geom BLOB);
+         SELECT CreateSpatialIndex('pts', 'geom');
+         INSERT INTO pts VALUES
+           (1, 'berlin', ST_Point(13.4, 52.5, 4326)),
+           (2, 'paris', ST_Point(2.35, 48.85, 4326)),
+           (3, 'ell', ST_GeomFromText('POLYGON((0 0,4 0,4 1,1 1,1 4,0 4,0 0))', 4326));",
+    )
+    .unwrap();
+    conn
+}
+
+fn ids(stmt: &mut rusqlite::Statement<'_>) -> Vec<i64> {
+    stmt.query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn geometry_values_round_trip_with_their_srid() {
+    let conn = conn();
+    conn.execute_batch("CREATE TABLE shapes (geom BLOB)")
+        .unwrap();
+    let line = Geometry::new(
+        geo::LineString::from(vec![(0.0, 0.0), (3.0, 4.0)]),
+        Some(3857),
+    );
+    conn.execute("INSERT INTO shapes VALUES (?1)", [&line])
+        .unwrap();
+    let back: Geometry = conn
+        .query_row("SELECT geom FROM shapes", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(back, line);
+    let length: f64 = conn
+        .query_row("SELECT ST_Length(geom) FROM shapes", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(length, 5.0);
+
+    let ewkb: Ewkb = conn
+        .query_row("SELECT geom FROM shapes", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(ewkb.srid(), Some(3857));
+    assert_eq!(ewkb.to_geo().unwrap(), line.geometry);
+    assert_eq!(Ewkb::from_geo(&line.geometry, Some(3857)).unwrap(), ewkb);
+
+    let null: Option<Geometry> = conn.query_row("SELECT NULL", [], |row| row.get(0)).unwrap();
+    assert_eq!(null, None);
+}
+
+#[test]
+fn non_geometry_columns_are_rejected() {
+    let conn = conn();
+    let err = conn
+        .query_row("SELECT X'DEADBEEF'", [], |row| row.get::<_, Geometry>(0))
+        .unwrap_err();
+    assert!(
+        matches!(err, rusqlite::Error::FromSqlConversionFailure(..)),
+        "{err:?}"
+    );
+    let err = conn
+        .query_row("SELECT 'POINT(1 2)'", [], |row| row.get::<_, Ewkb>(0))
+        .unwrap_err();
+    assert!(
+        matches!(err, rusqlite::Error::InvalidColumnType(..)),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn query_helpers_prefilter_then_refine() {
+    let conn = places();
+    let mut stmt =
+        intersects_window_indexed(&conn, "pts", "geom", (2.0, 2.0, 5.0, 5.0), "t.id").unwrap();
+    assert!(ids(&mut stmt).is_empty());
+    let mut stmt =
+        intersects_window_indexed(&conn, "pts", "geom", (0.0, 40.0, 20.0, 60.0), "t.id").unwrap();
+    assert_eq!(ids(&mut stmt), vec![1, 2]);
+
+    let mut stmt =
+        dwithin_sphere_indexed(&conn, "pts", "geom", (2.35, 48.85), 10_000.0, "t.id").unwrap();
+    assert_eq!(ids(&mut stmt), vec![2]);
+
+    let mut stmt =
+        nearest_sphere_indexed(&conn, "pts", "geom", (13.4, 52.5), 2_000_000.0, 2, "t.id").unwrap();
+    assert_eq!(ids(&mut stmt), vec![1, 2]);
+
+    assert!(
+        intersects_window_indexed(&conn, "nope", "geom", (0.0, 0.0, 1.0, 1.0), "t.id").is_err()
+    );
+}
+
+#[test]
+fn registration_with_limits_caps_inputs() {
+    let conn = Connection::open_in_memory().unwrap();
+    let limits = Limits {
+        max_vertices: Some(3),
+        ..Limits::default()
+    };
+    register_with_limits(&conn, limits).unwrap();
+    let line = geom_from_text("LINESTRING(0 0,1 1,2 2,3 3,4 4)", None).unwrap();
+    let err = conn
+        .query_row("SELECT ST_NPoints(?1)", [&Ewkb(line)], |row| {
+            row.get::<_, i64>(0)
+        })
+        .unwrap_err();
+    assert!(err.to_string().contains("exceeded"), "{err}");
+}
+
+#[test]
+fn malformed_blobs_fail_to_convert() {
+    let conn = conn();
+    // A valid point header with its coordinates cut off.
+    let truncated: Vec<u8> = conn
+        .query_row("SELECT substr(ST_Point(1, 2), 1, 9)", [], |row| row.get(0))
+        .unwrap();
+    let err = conn
+        .query_row("SELECT ?1", [&truncated], |row| row.get::<_, Geometry>(0))
+        .unwrap_err();
+    assert!(
+        matches!(err, rusqlite::Error::FromSqlConversionFailure(..)),
+        "{err:?}"
+    );
+    assert!(Ewkb(truncated).to_geo().is_err());
+    assert!(Ewkb(vec![1, 2]).to_geo().is_err());
+    assert_eq!(Ewkb(vec![1, 2]).srid(), None);
+
+    // Other encodings read back only when enabled on the thread.
+    let spatialite: Vec<u8> = conn
+        .query_row("SELECT ST_AsSpatiaLite(ST_Point(1, 2, 4326))", [], |row| {
+            row.get(0)
+        })
+        .unwrap();
+    let read = || conn.query_row("SELECT ?1", [&spatialite], |row| row.get::<_, Geometry>(0));
+    assert!(read().is_err());
+    let formats = InputFormats {
+        spatialite: true,
+        ..InputFormats::NONE
+    };
+    let point = with_input_formats(formats, read).unwrap();
+    assert_eq!(point, Geometry::new(geo::Point::new(1.0, 2.0), Some(4326)));
+}
+
+#[test]
+fn file_functions_need_file_access_registration() {
+    let path = std::env::temp_dir().join(format!(
+        "sqlitegis-rusqlite-{}-places.fgb",
+        std::process::id()
+    ));
+    let path = path.to_str().unwrap();
+    let export = format!("SELECT ExportFlatGeobuf('{path}', 'SELECT * FROM pts')");
+
+    let conn = places_in(Connection::open_in_memory().unwrap(), register);
+    let err = conn
+        .query_row(&export, [], |row| row.get::<_, i64>(0))
+        .unwrap_err();
+    assert!(err.to_string().contains("file access is disabled"), "{err}");
+
+    let conn = places_in(Connection::open_in_memory().unwrap(), |conn| {
+        register_with_file_access(conn, Limits::default())
+    });
+    let written: i64 = conn.query_row(&export, [], |row| row.get(0)).unwrap();
+    assert_eq!(written, 3);
+    let read: i64 = conn
+        .query_row(
+            &format!("SELECT ImportFlatGeobuf('{path}', 'copy')"),
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(read, 3);
+    let names: Vec<String> = conn
+        .prepare("SELECT name FROM copy ORDER BY name")
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(names, ["berlin", "ell", "paris"]);
+
+    // A file that is not FlatGeobuf fails the import and leaves no table.
+    std::fs::write(path, b"not a flatgeobuf").unwrap();
+    assert!(conn
+        .query_row(
+            &format!("SELECT ImportFlatGeobuf('{path}', 'broken')"),
+            [],
+            |row| row.get::<_, i64>(0),
+        )
+        .is_err());
+    let tables: i64 = conn
+        .query_row(
+            "SELECT count(*) FROM sqlite_master WHERE name = 'broken'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(tables, 0);
+    std::fs::remove_file(path).unwrap();
+}
@@ -10,12 +10,35 @@ macro_rules! define_test_db {
 
         impl $name {
             fn open() -> Self {
+                Self::open_at(":memory:", |db| unsafe {
+                    sqlitegis::sqlite::register_functions(db)
+                })
+            }
+
+            fn open_with_limits(limits: sqlitegis::core::limits::Limits) -> Self {
+                Self::open_at(":memory:", |db| unsafe {
+                    sqlitegis::sqlite::register_functions_with_limits(db, limits)
+                })
+            }
+
+            fn open_with_file_access() -> Self {
+                Self::open_at(":memory:", |db| unsafe {
+                    sqlitegis::sqlite::register_functions_with_file_access(db, Default::default())
+                })
+            }
+
+            /// Open `path` with the functions registered as [`Self::open`] does.
+            fn open_path(path: &str) -> Self {
+                Self::open_at(path, |db| unsafe { sqlitegis::sqlite::register_functions(db) })
+            }
+
+            fn open_at<F: FnOnce(*mut sqlite3) -> i32>(path: &str, register: F) -> Self {
                 let mut db = std::ptr::null_mut();
-                let path = CString::new(":memory:").unwrap();
+                let path = CString::new(path).unwrap();
                 unsafe {
                     assert_eq!(SQLITE_OK, sqlite3_open(path.as_ptr(), &mut db));
-                    assert_eq!(SQLITE_OK, sqlitegis::sqlite::register_functions(db));
                 }
+                assert_eq!(SQLITE_OK, register(db));
                 $name(db)
             }
 
@@ -53,6 +76,17 @@ macro_rules! define_test_db {
                 unsafe { self.query_row(sql, |stmt| sqlite3_column_int64(stmt, 0)) }
             }
 
+            fn query_blob(&self, sql: &str) -> Vec<u8> {
+                unsafe {
+                    self.query_row(sql, |stmt| {
+                        assert_eq!(SQLITE_BLOB, sqlite3_column_type(stmt, 0), "not a BLOB: {sql}");
+                        let ptr = sqlite3_column_blob(stmt, 0) as *const u8;
+                        let len = sqlite3_column_bytes(stmt, 0) as usize;
+                        std::slice::from_raw_parts(ptr, len).to_vec()
+                    })
+                }
+            }
+
             fn query_is_null(&self, sql: &str) -> bool {
                 unsafe { self.query_row(sql, |stmt| sqlite3_column_type(stmt, 0) == SQLITE_NULL) }
             }
@@ -71,6 +105,49 @@ macro_rules! define_test_db {
                 }
             }
 
+            fn try_exec(&self, sql: &str) -> Result<(), String> {
+                let sql_c = CString::new(sql).unwrap();
+                unsafe {
+                    let rc = sqlite3_exec(
+                        self.0,
+                        sql_c.as_ptr(),
+                        None,
+                        std::ptr::null_mut(),
+                        std::ptr::null_mut(),
+                    );
+                    if rc != SQLITE_OK {
+                        let err = sqlite3_errmsg(self.0);
+                        return Err(CStr::from_ptr(err).to_string_lossy().into_owned());
+                    }
+                    Ok(())
+                }
+            }
+
+            /// Step the single statement `sql` once and return the step
+            /// result code, the connection's extended result code and its
+            /// error message.
+            fn step_error(&self, sql: &str) -> (i32, i32, String) {
+                let sql_c = CString::new(sql).unwrap();
+                unsafe {
+                    let mut stmt = std::ptr::null_mut();
+                    let rc = sqlite3_prepare_v2(
+                        self.0,
+                        sql_c.as_ptr(),
+                        -1,
+                        &mut stmt,
+                        std::ptr::null_mut(),
+                    );
+                    assert_eq!(SQLITE_OK, rc, "prepare failed for: {sql}");
+                    let step = sqlite3_step(stmt);
+                    let err = CStr::from_ptr(sqlite3_errmsg(self.0))
+                        .to_string_lossy()
+                        .into_owned();
+                    let extended = sqlite3_extended_errcode(self.0);
+                    sqlite3_finalize(stmt);
+                    (step, extended, err)
+                }
+            }
+
             fn query_all_i64(&self, sql: &str) -> Vec<i64> {
                 let sql_c = CString::new(sql).unwrap();
                 unsafe {
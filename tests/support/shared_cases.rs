@@ -3458,6 +3458,972 @@ fn spatial_index_knn_nearest_n_geodesic() {
     assert_eq!(indexed[2], 3, "Berlin should be third");
 }
 
+// GeoJSON features and MVT aggregates
+
+#[$test_attr]
+fn geojson_features_embed_as_json_and_aggregate() {
+    let db = ActiveTestDb::open();
+    db.exec(
+        "CREATE TABLE places(id INTEGER, name TEXT, geom BLOB); \
+         INSERT INTO places VALUES \
+             (1, 'a', ST_Point(1.23456, 2, 4326)), \
+             (2, 'b', ST_Point(3, 4, 4326)), \
+             (3, 'c', NULL)",
+    );
+
+    // The JSON subtype makes json_object embed rather than quote.
+    assert_eq!(
+        db.query_i64(
+            "SELECT json_type(json_object('g', ST_AsGeoJSON(geom)), '$.g') = 'object' \
+             FROM places WHERE id = 1"
+        ),
+        1
+    );
+    assert_eq!(
+        db.query_text(
+            "SELECT json_object('f', ST_AsGeoJSON(json_object('name', name), geom, 2)) \
+             FROM places WHERE id = 1"
+        ),
+        r#"{"f":{"type": "Feature", "geometry": {"type": "Point", "coordinates": [1.23,2]}, "properties": {"name":"a"}}}"#
+    );
+
+    assert_eq!(
+        db.query_i64(
+            "SELECT json_array_length(json_extract(\
+             ST_AsGeoJSONFeatureCollection(json_object('id', id), geom), \
+             '$.features')) FROM places"
+        ),
+        2
+    );
+    assert_eq!(
+        db.query_i64(
+            "SELECT json_extract(ST_AsGeoJSONFeatureCollection(\
+             ST_AsGeoJSON(json_object('id', id), geom)), '$.features[1].properties.id') \
+             FROM places"
+        ),
+        2
+    );
+    assert!(db.query_is_null(
+        "SELECT ST_AsGeoJSONFeatureCollection(geom) FROM places WHERE id = 3"
+    ));
+    assert_eq!(
+        db.query_i64(
+            "SELECT ST_GeomFromGeoJSON(ST_AsGeoJSON(json_object(), geom)) = geom \
+             FROM places WHERE id = 2"
+        ),
+        1
+    );
+
+    let err = db
+        .try_query_i64("SELECT ST_AsGeoJSONFeatureCollection(ST_AsGeoJSON(geom)) FROM places")
+        .unwrap_err();
+    assert!(err.contains("of type \"Feature\""), "got: {err}");
+    let err = db
+        .try_query_i64("SELECT ST_AsGeoJSON('[1]', ST_Point(1, 2))")
+        .unwrap_err();
+    assert!(err.contains("properties must be a JSON object"), "got: {err}");
+}
+
+#[$test_attr]
+fn st_asmvt_aggregates_rows_into_one_layer() {
+    use sqlitegis::core::functions::mvt::{MvtLayer, MvtValue};
+
+    let db = ActiveTestDb::open();
+    db.exec(
+        "CREATE TABLE roads(id INTEGER, name TEXT, geom BLOB); \
+         INSERT INTO roads VALUES \
+             (1, 'a', ST_GeomFromText('LINESTRING(1 1,9 9)')), \
+             (2, 'b', ST_GeomFromText('POINT(50 50)')), \
+             (3, NULL, NULL)",
+    );
+    let tile_sql = |layer: &str| {
+        format!(
+            "SELECT ST_AsMVT(json_object('id', id, 'name', name, 'geom', hex(geom)), \
+             '{layer}', 64) \
+             FROM (SELECT id, name, \
+                   ST_AsMVTGeom(geom, ST_MakeEnvelope(0, 0, 10, 10), 64, 8) AS geom \
+                   FROM roads)"
+        )
+    };
+
+    // Only the line lands in the tile; the row with no geometry and the
+    // clipped-away point are skipped.
+    let tile = db.query_blob(&tile_sql("roads"));
+    let mut layer = MvtLayer::new("roads", 64);
+    let line = sqlitegis::core::functions::io::geom_from_text("LINESTRING(6 58,58 6)", None)
+        .unwrap();
+    layer
+        .add_feature(
+            &line,
+            [("id", MvtValue::Int(1)), ("name", MvtValue::from("a"))],
+        )
+        .unwrap();
+    assert_eq!(tile, layer.encode());
+
+    // Tiles concatenate into a multi-layer tile. `||` yields TEXT in
+    // SQLite, so cast the result back to a BLOB.
+    let both = db.query_blob(&format!(
+        "SELECT CAST(({}) || ({}) AS BLOB)",
+        tile_sql("a"),
+        tile_sql("b")
+    ));
+    assert_eq!(both.len(), 2 * tile.len() - 2 * "roads".len() + 2);
+
+    assert!(db.query_is_null("SELECT ST_AsMVT(NULL, 'empty') FROM roads"));
+    let err = db.try_query_i64("SELECT ST_AsMVT('[1]', 'l')").unwrap_err();
+    assert_eq!(err, "ST_AsMVT(arg 1) [invalid_input]: row must be a JSON object");
+    let err = db
+        .try_query_i64("SELECT ST_AsMVT(json_object('id', 1), 'l')")
+        .unwrap_err();
+    assert!(err.contains("no geometry column \"geom\""), "{err}");
+}
+
+// Connection limits and sqlitegis_config
+
+#[$test_attr]
+fn connection_limits_apply_to_every_function() {
+    let db = ActiveTestDb::open_with_limits(sqlitegis::core::limits::Limits {
+        max_vertices: Some(3),
+        max_output_vertices: Some(8),
+        ..Default::default()
+    });
+
+    assert_eq!(
+        db.query_i64("SELECT ST_NPoints(ST_GeomFromText('LINESTRING(0 0,1 1,2 2)'))"),
+        3
+    );
+    let err = db
+        .try_query_i64("SELECT ST_NPoints(ST_GeomFromText('LINESTRING(0 0,1 1,2 2,3 3)'))")
+        .unwrap_err();
+    assert!(err.contains("max_vertices exceeded: 4 > 3"), "got: {err}");
+    let err = db
+        .try_query_i64("SELECT length(ST_Buffer(ST_Point(0, 0), 1.0))")
+        .expect_err("buffer output should exceed the cap");
+    assert!(err.contains("max_output_vertices exceeded"), "got: {err}");
+
+    assert_eq!(db.query_i64("SELECT sqlitegis_config('max_vertices')"), 3);
+    assert_eq!(db.query_i64("SELECT sqlitegis_config('max_input_bytes')"), 0);
+}
+
+#[$test_attr]
+fn sqlitegis_config_only_tightens() {
+    let db = ActiveTestDb::open();
+
+    assert_eq!(db.query_i64("SELECT sqlitegis_config('max_vertices', 10)"), 10);
+    assert_eq!(db.query_i64("SELECT sqlitegis_config('max_vertices', 2)"), 2);
+    let err = db
+        .try_query_i64("SELECT sqlitegis_config('max_vertices', 5)")
+        .unwrap_err();
+    assert!(err.contains("can only be lowered"), "got: {err}");
+    let err = db
+        .try_query_i64("SELECT sqlitegis_config('max_vertices', 0)")
+        .unwrap_err();
+    assert!(err.contains("must be positive"), "got: {err}");
+    let err = db
+        .try_query_i64("SELECT sqlitegis_config('max_rows', 1)")
+        .unwrap_err();
+    assert!(err.contains("unknown limit"), "got: {err}");
+    assert_eq!(db.query_i64("SELECT sqlitegis_config('max_vertices')"), 2);
+
+    // The tightened limit is visible to later statements.
+    let err = db
+        .try_query_i64("SELECT ST_NPoints(ST_GeomFromText('LINESTRING(0 0,1 1,2 2)'))")
+        .unwrap_err();
+    assert!(err.contains("max_vertices exceeded: 3 > 2"), "got: {err}");
+}
+
+// Legacy and GeoPackage inputs
+
+#[$test_attr]
+fn accept_gpb_lets_geometry_functions_read_geopackage_blobs() {
+    let db = ActiveTestDb::open();
+
+    assert_eq!(
+        db.query_i64("SELECT ST_SRID(ST_GeomFromGPB(AsGPB(ST_Point(1, 2, 4326))))"),
+        4326
+    );
+    let gpb = "AsGPB(ST_MakeEnvelope(0, 0, 2, 3))";
+    let err = db
+        .try_query_i64(&format!("SELECT ST_Area({gpb}) > 0"))
+        .unwrap_err();
+    assert!(err.contains("ST_Area(arg 1) [invalid_ewkb]"), "got: {err}");
+
+    assert_eq!(db.query_i64("SELECT sqlitegis_config('accept_gpb')"), 0);
+    assert_eq!(db.query_i64("SELECT sqlitegis_config('accept_gpb', 1)"), 1);
+    assert_eq!(
+        db.query_i64(&format!("SELECT CAST(ST_Area({gpb}) AS INTEGER)")),
+        6
+    );
+    // ST_GeomFromGPB still sees the raw blob with the switch on.
+    assert_eq!(
+        db.query_i64(&format!(
+            "SELECT ST_GeomFromGPB({gpb}) = ST_MakeEnvelope(0, 0, 2, 3)"
+        )),
+        1
+    );
+    assert_eq!(db.query_i64("SELECT sqlitegis_config('accept_gpb', 0)"), 0);
+    let err = db
+        .try_query_i64("SELECT sqlitegis_config('accept_gpb', 2)")
+        .unwrap_err();
+    assert!(err.contains("accept_gpb must be 0 or 1"), "got: {err}");
+}
+
+#[$test_attr]
+fn autodetect_input_reads_mixed_legacy_columns() {
+    let db = ActiveTestDb::open();
+
+    // One unit square per encoding.
+    let square = "ST_MakeEnvelope(0, 0, 1, 1, 4326)";
+    db.exec(&format!(
+        "CREATE TABLE legacy (geom); \
+         INSERT INTO legacy VALUES \
+           ({square}), \
+           (ST_AsBinary({square})), \
+           (AsGPB({square})), \
+           (ST_AsSpatiaLite({square})), \
+           (hex({square})), \
+           ('SRID=4326;POLYGON((0 0,1 0,1 1,0 1,0 0))'), \
+           ('POLYGON((0 0,1 0,1 1,0 1,0 0))')"
+    ));
+    assert!(db.try_query_i64("SELECT sum(ST_Area(geom)) FROM legacy").is_err());
+
+    assert_eq!(db.query_i64("SELECT sqlitegis_config('autodetect_input', 1)"), 1);
+    assert_eq!(db.query_i64("SELECT sqlitegis_config('autodetect_input')"), 1);
+    // Autodetection alone decodes GPB along with the other formats.
+    assert_eq!(db.query_i64("SELECT sqlitegis_config('accept_gpb')"), 1);
+    assert_eq!(
+        db.query_i64("SELECT CAST(sum(ST_Area(geom)) AS INTEGER) FROM legacy"),
+        7
+    );
+    assert_eq!(
+        db.query_i64("SELECT count(*) FROM legacy WHERE ST_SRID(geom) = 4326"),
+        5
+    );
+    let err = db
+        .try_query_i64("SELECT ST_Area('POLYGON((0 0,1 0')")
+        .unwrap_err();
+    assert!(err.contains("ST_Area(arg 1)"), "got: {err}");
+    // A value detected as an accepted format reports why it failed to
+    // convert, not an EWKB parse error.
+    let err = db
+        .try_query_i64("SELECT ST_Area('SRID=abc;POINT(0 0)')")
+        .unwrap_err();
+    assert!(
+        err.contains("ST_Area(arg 1) [invalid_input]: invalid EWKT SRID [abc]"),
+        "got: {err}"
+    );
+    let err = db
+        .try_query_i64("SELECT ST_Distance(ST_Point(0, 0, 4326), 'SRID=4326;POINT(0')")
+        .unwrap_err();
+    assert!(err.contains("ST_Distance(arg 2) [parse]"), "got: {err}");
+
+    // Turning autodetection off clears the GPB it enabled, but keeps a
+    // separately enabled one.
+    assert_eq!(db.query_i64("SELECT sqlitegis_config('autodetect_input', 0)"), 0);
+    assert_eq!(db.query_i64("SELECT sqlitegis_config('accept_gpb')"), 0);
+    assert!(db
+        .try_query_i64("SELECT ST_Area(AsGPB(ST_MakeEnvelope(0, 0, 2, 2)))")
+        .is_err());
+    assert_eq!(db.query_i64("SELECT sqlitegis_config('accept_gpb', 1)"), 1);
+    assert_eq!(db.query_i64("SELECT sqlitegis_config('autodetect_input', 1)"), 1);
+    assert_eq!(db.query_i64("SELECT sqlitegis_config('autodetect_input', 0)"), 0);
+    assert_eq!(db.query_i64("SELECT sqlitegis_config('autodetect_input')"), 0);
+    assert_eq!(db.query_i64("SELECT sqlitegis_config('accept_gpb')"), 1);
+    assert_eq!(
+        db.query_i64("SELECT CAST(ST_Area(AsGPB(ST_MakeEnvelope(0, 0, 2, 2))) AS INTEGER)"),
+        4
+    );
+    assert!(db.try_query_i64("SELECT ST_Area(hex(ST_Point(1, 2)))").is_err());
+}
+
+#[$test_attr]
+fn gpkg_helpers_build_a_geopackage_layout() {
+    let db = ActiveTestDb::open();
+    assert_eq!(db.query_i64("SELECT sqlitegis_config('accept_gpb', 1)"), 1);
+
+    assert_eq!(
+        db.query_i64("SELECT gpkgAddGeometryColumn('roads', 'geom', 'linestring', 4326)"),
+        1
+    );
+    assert_eq!(db.query_i64("PRAGMA application_id"), 0x4750_4B47);
+    assert_eq!(
+        db.query_i64(
+            "SELECT count(*) FROM gpkg_geometry_columns \
+             WHERE table_name = 'roads' AND column_name = 'geom' \
+             AND geometry_type_name = 'LINESTRING' AND srs_id = 4326"
+        ),
+        1
+    );
+    assert_eq!(
+        db.query_i64(
+            "SELECT count(*) FROM gpkg_contents \
+             WHERE table_name = 'roads' AND data_type = 'features'"
+        ),
+        1
+    );
+    // Repeating the call is a no-op.
+    assert_eq!(
+        db.query_i64("SELECT gpkgAddGeometryColumn('roads', 'geom', 'LINESTRING', 4326)"),
+        1
+    );
+    let err = db
+        .try_query_i64("SELECT gpkgAddGeometryColumn('roads', 'g2', 'POINT', 3857)")
+        .unwrap_err();
+    assert!(
+        err.contains("srs_id 3857 is not in gpkg_spatial_ref_sys"),
+        "got: {err}"
+    );
+
+    db.exec(
+        "INSERT INTO roads (geom) \
+         VALUES (AsGPB(ST_GeomFromText('LINESTRING(0 0,4 2)', 4326)))",
+    );
+    assert_eq!(db.query_i64("SELECT gpkgAddSpatialIndex('roads', 'geom')"), 1);
+    db.exec(
+        "INSERT INTO roads (geom) \
+         VALUES (AsGPB(ST_GeomFromText('LINESTRING(10 10,12 14)', 4326)))",
+    );
+    assert_eq!(
+        db.query_i64("SELECT sum(maxx + maxy) FROM rtree_roads_geom"),
+        32
+    );
+    assert_eq!(
+        db.query_i64("UPDATE roads SET geom = NULL WHERE fid = 2 RETURNING fid"),
+        2
+    );
+    assert_eq!(db.query_i64("SELECT count(*) FROM rtree_roads_geom"), 1);
+    assert_eq!(
+        db.query_i64(
+            "SELECT count(*) FROM gpkg_extensions \
+             WHERE table_name = 'roads' AND extension_name = 'gpkg_rtree_index'"
+        ),
+        1
+    );
+
+    let err = db
+        .try_query_i64("SELECT gpkgAddSpatialIndex('roads', 'other')")
+        .unwrap_err();
+    assert!(
+        err.contains("not registered in gpkg_geometry_columns"),
+        "got: {err}"
+    );
+}
+
+#[$test_attr]
+fn convert_spatialite_column_rewrites_blobs_in_place() {
+    let db = ActiveTestDb::open();
+
+    // A SpatiaLite-style table: mixed rows plus a constraint trigger
+    // calling a function this crate does not provide.
+    db.exec(
+        "CREATE TABLE places (id INTEGER PRIMARY KEY, geom BLOB); \
+         INSERT INTO places (geom) VALUES \
+           (ST_AsSpatiaLite(ST_Point(1, 2, 4326))), \
+           (ST_AsSpatiaLite(ST_GeomFromText('LINESTRING(0 0,3 4)', 4326))), \
+           (ST_Point(5, 6, 4326)), \
+           (NULL); \
+         CREATE TRIGGER ggu_places_geom BEFORE UPDATE OF geom ON places \
+         BEGIN SELECT GeometryConstraints(NEW.geom, 'POINT', 4326); END",
+    );
+    assert!(db
+        .try_query_i64("SELECT ST_SRID(geom) FROM places WHERE id = 1")
+        .is_err());
+
+    assert_eq!(
+        db.query_i64("SELECT ConvertSpatiaLiteColumn('places', 'geom')"),
+        2
+    );
+    assert_eq!(
+        db.query_i64("SELECT count(*) FROM sqlite_master WHERE name = 'ggu_places_geom'"),
+        0
+    );
+    assert_eq!(db.query_i64("SELECT sum(ST_SRID(geom)) FROM places"), 3 * 4326);
+    assert_eq!(
+        db.query_i64("SELECT CAST(ST_Length(geom) AS INTEGER) FROM places WHERE id = 2"),
+        5
+    );
+    assert_eq!(
+        db.query_i64("SELECT ConvertSpatiaLiteColumn('places', 'geom')"),
+        0
+    );
+}
+
+// Text and binary interchange formats
+
+#[$test_attr]
+fn twkb_functions_round_trip_and_report_bad_arguments() {
+    let db = ActiveTestDb::open();
+
+    assert_eq!(
+        db.query_text("SELECT hex(ST_AsTWKB(ST_GeomFromText('LINESTRING(1 1,5 5)')))"),
+        "02000202020808"
+    );
+    assert_eq!(
+        db.query_i64(
+            "SELECT ST_GeomFromTWKB(ST_AsTWKB(ST_MakeEnvelope(0, 0, 2, 3), 0, 0, 0, 1, 1)) \
+             = ST_MakeEnvelope(0, 0, 2, 3)"
+        ),
+        1
+    );
+    assert!(db.query_is_null("SELECT ST_AsTWKB(NULL, 2)"));
+
+    let err = db
+        .try_query_i64("SELECT ST_AsTWKB(ST_Point(1, 2), 9)")
+        .unwrap_err();
+    assert!(err.contains("ST_AsTWKB [invalid_input]"), "got: {err}");
+    let err = db
+        .try_query_i64("SELECT ST_AsTWKB(ST_Point(1, 2), 'x')")
+        .unwrap_err();
+    assert!(err.contains("precision_xy"), "got: {err}");
+    // Coordinates that do not fit the scaled 64-bit integers fail cleanly
+    // instead of panicking or saturating.
+    for wkt in ["LINESTRING(-1e300 0,1e300 0)", "LINESTRING(1e300 0,0 0)"] {
+        let err = db
+            .try_query_i64(&format!(
+                "SELECT ST_AsTWKB(ST_GeomFromText('{wkt}'), 5)"
+            ))
+            .unwrap_err();
+        assert!(err.contains("ST_AsTWKB [invalid_input]"), "{wkt}: {err}");
+    }
+    let err = db
+        .try_query_i64("SELECT ST_GeomFromTWKB(X'0100')")
+        .unwrap_err();
+    assert!(
+        err.contains("ST_GeomFromTWKB(arg 1) [invalid_input]"),
+        "got: {err}"
+    );
+}
+
+#[$test_attr]
+fn kml_gml_and_svg_functions_pick_overloads_and_round_trip() {
+    let db = ActiveTestDb::open();
+
+    assert_eq!(
+        db.query_text("SELECT ST_AsKML(ST_Point(1.23456, 2, 4326), 2)"),
+        "<Point><coordinates>1.23,2</coordinates></Point>"
+    );
+    // A geometry first argument selects GML 2, an integer the version.
+    assert_eq!(
+        db.query_text("SELECT ST_AsGML(ST_Point(1, 2, 4326), 0)"),
+        "<gml:Point srsName=\"EPSG:4326\"><gml:coordinates>1,2</gml:coordinates></gml:Point>"
+    );
+    assert_eq!(
+        db.query_text("SELECT ST_AsGML(3, ST_Point(1, 2, 4326), 15, 1)"),
+        "<gml:Point srsName=\"urn:ogc:def:crs:EPSG::4326\">\
+         <gml:pos srsDimension=\"2\">2 1</gml:pos></gml:Point>"
+    );
+    assert_eq!(
+        db.query_i64(
+            "SELECT ST_GeomFromGML(ST_AsGML(3, ST_Point(1, 2, 4326), 15, 1)) \
+             = ST_Point(1, 2, 4326)"
+        ),
+        1
+    );
+    assert_eq!(
+        db.query_i64(
+            "SELECT ST_GeomFromKML(ST_AsKML(ST_MakeEnvelope(0, 0, 2, 3, 4326))) \
+             = ST_MakeEnvelope(0, 0, 2, 3, 4326)"
+        ),
+        1
+    );
+    assert!(db.query_is_null("SELECT ST_AsKML(ST_GeomFromText('POINT EMPTY'))"));
+
+    let err = db
+        .try_query_i64("SELECT ST_AsKML(ST_Point(1, 2, 3857))")
+        .unwrap_err();
+    assert!(err.contains("ST_AsKML [invalid_input]"), "got: {err}");
+    let err = db
+        .try_query_i64("SELECT ST_AsGML(4, ST_Point(1, 2))")
+        .unwrap_err();
+    assert!(err.contains("GML version must be 2 or 3"), "got: {err}");
+    assert_eq!(
+        db.query_text("SELECT ST_AsSVG(ST_GeomFromText('LINESTRING(1 2,3 4)'), 1, 0)"),
+        "M 1 -2 l 2 -2"
+    );
+    let err = db
+        .try_query_i64("SELECT ST_AsSVG(ST_Point(1, 2), 0, 99)")
+        .unwrap_err();
+    assert!(err.contains("ST_AsSVG [invalid_input]"), "got: {err}");
+    let err = db
+        .try_query_i64("SELECT ST_GeomFromGML('<gml:Point>')")
+        .unwrap_err();
+    assert!(
+        err.contains("ST_GeomFromGML [invalid_input]: invalid XML"),
+        "got: {err}"
+    );
+}
+
+#[$test_attr]
+fn hexewkb_functions_round_trip_psql_text() {
+    let db = ActiveTestDb::open();
+
+    let hex = "0101000020E6100000000000000000F03F0000000000000040";
+    assert_eq!(
+        db.query_text("SELECT ST_AsHEXEWKB(ST_Point(1, 2, 4326))"),
+        hex
+    );
+    assert_eq!(
+        db.query_i64(&format!(
+            "SELECT ST_GeomFromHEXEWKB('{hex}') = ST_Point(1, 2, 4326) \
+             AND ST_GeomFromEWKB('{hex}') = ST_Point(1, 2, 4326) \
+             AND ST_AsHEXEWKB(ST_GeomFromEWKB(ST_AsHEXEWKB(ST_Point(1, 2, 4326), 'XDR'))) = '{hex}'"
+        )),
+        1
+    );
+
+    let err = db
+        .try_query_i64("SELECT ST_AsHEXEWKB(ST_Point(1, 2), 'LE')")
+        .unwrap_err();
+    assert!(err.contains("ST_AsHEXEWKB [invalid_input]"), "got: {err}");
+    let err = db
+        .try_query_i64("SELECT ST_GeomFromEWKB('0101')")
+        .unwrap_err();
+    assert!(err.contains("ST_GeomFromEWKB"), "got: {err}");
+}
+
+#[$test_attr]
+fn polyline_and_geohash_functions_round_trip() {
+    let db = ActiveTestDb::open();
+
+    let line = "ST_GeomFromText('LINESTRING(-120.2 38.5,-120.95 40.7)', 4326)";
+    assert_eq!(
+        db.query_i64(&format!(
+            "SELECT ST_LineFromEncodedPolyline(ST_AsEncodedPolyline({line}, 6), 6) = {line}"
+        )),
+        1
+    );
+    assert_eq!(
+        db.query_i64(
+            "SELECT ST_Within(ST_Point(-126, 48, 4326), \
+             ST_GeomFromGeoHash(ST_GeoHash(ST_Point(-126, 48, 4326), 6)))"
+        ),
+        1
+    );
+    assert_eq!(
+        db.query_i64(
+            "SELECT ST_SRID(ST_PointFromGeoHash('c0w3h')) = 4326 \
+             AND ST_GeoHash(ST_GeomFromText('POINT EMPTY')) IS NULL"
+        ),
+        1
+    );
+
+    let err = db
+        .try_query_i64("SELECT ST_GeoHash(ST_Point(500, 0))")
+        .unwrap_err();
+    assert!(err.contains("ST_GeoHash [invalid_input]"), "got: {err}");
+    let err = db
+        .try_query_i64("SELECT ST_GeomFromGeoHash('abc')")
+        .unwrap_err();
+    assert!(err.contains("invalid GeoHash character 'a'"), "got: {err}");
+    let err = db
+        .try_query_i64("SELECT ST_LineFromEncodedPolyline('_p~iF')")
+        .unwrap_err();
+    assert!(err.contains("truncated encoded polyline"), "got: {err}");
+}
+
+#[$test_attr]
+fn core_errors_carry_result_code_and_argument() {
+    use sqlitegis::core::error::ErrorKind;
+
+    let db = ActiveTestDb::open();
+    for (sql, code, msg) in [
+        (
+            "SELECT ST_X(ST_GeomFromText('LINESTRING(0 0,1 1)'))",
+            SQLITE_MISMATCH,
+            "ST_X(arg 1) [wrong_type]: geometry is not a Point; got LineString",
+        ),
+        (
+            "SELECT ST_MakeLine(ST_Point(0, 0), ST_GeomFromText('LINESTRING(0 0,1 1)'))",
+            SQLITE_MISMATCH,
+            "ST_MakeLine(arg 2) [wrong_type]:",
+        ),
+        (
+            // Both inputs are LineStrings; only the second must be a Point.
+            "SELECT ST_ClosestPoint(ST_GeomFromText('LINESTRING(0 0,1 1)'), \
+             ST_GeomFromText('LINESTRING(0 0,2 2)'))",
+            SQLITE_MISMATCH,
+            "ST_ClosestPoint(arg 2) [wrong_type]:",
+        ),
+        (
+            "SELECT ST_MakeLine(ST_Point(0, 0), ST_GeomFromText('POINT EMPTY'))",
+            SQLITE_ERROR,
+            "ST_MakeLine(arg 2) [invalid_input]: point must not be empty",
+        ),
+        (
+            "SELECT ST_Distance(ST_Point(0, 0), X'0102')",
+            SQLITE_MISMATCH,
+            "ST_Distance(arg 2) [invalid_ewkb]:",
+        ),
+        (
+            "SELECT ST_PointN(ST_GeomFromText('LINESTRING(0 0,1 1)'), 9)",
+            SQLITE_RANGE,
+            "ST_PointN(arg 2) [out_of_bounds]:",
+        ),
+        (
+            "SELECT ST_Distance(ST_Point(0, 0, 4326), ST_Point(0, 0, 3857))",
+            SQLITE_CONSTRAINT,
+            "ST_Distance [srid_mismatch]: operation on mixed SRID geometries (4326 != 3857)",
+        ),
+        (
+            "SELECT ST_GeomFromText('POINT(')",
+            SQLITE_ERROR,
+            "ST_GeomFromText(arg 1) [parse]:",
+        ),
+    ] {
+        let (step, extended, err) = db.step_error(sql);
+        assert_eq!(step, code, "{sql}: {err}");
+        assert!(err.starts_with(msg), "{sql}: {err}");
+        // The extended code names the kind on its own.
+        let kind = ErrorKind::from_sqlite_code(extended).expect("SQLiteGIS code");
+        assert_eq!(extended & 0xff, code, "{sql}");
+        assert!(err.contains(&format!("[{kind}]")), "{sql}: {err}");
+    }
+}
+
+// File import, export and virtual tables
+
+#[cfg(not(target_arch = "wasm32"))]
+fn temp_path(name: &str) -> String {
+    std::env::temp_dir()
+        .join(format!("sqlitegis-{}-{name}", std::process::id()))
+        .to_str()
+        .expect("temp path is UTF-8")
+        .to_string()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[$test_attr]
+fn flatgeobuf_export_then_import_round_trips_a_table() {
+    let db = ActiveTestDb::open_with_file_access();
+    db.exec(
+        "CREATE TABLE places(id INTEGER, name TEXT, score REAL, geom BLOB); \
+         INSERT INTO places VALUES \
+             (1, 'a', 0.5, ST_Point(1, 2, 4326)), \
+             (2, NULL, 1, ST_GeomFromText('LINESTRING(0 0,3 4)', 4326)), \
+             (3, 'c', NULL, ST_GeomFromText('POLYGON((5 5,6 5,6 6,5 5))', 4326))",
+    );
+    let path = temp_path("places.fgb");
+
+    assert_eq!(
+        db.query_i64(&format!("SELECT ExportFlatGeobuf('{path}', 'SELECT * FROM places')")),
+        3
+    );
+    assert_eq!(
+        db.query_i64(&format!("SELECT ImportFlatGeobuf('{path}', 'copy', 1)")),
+        3
+    );
+    // Same rows, with the integer column widened to REAL for score.
+    assert_eq!(
+        db.query_i64(
+            "SELECT count(*) FROM places p JOIN copy c ON c.id = p.id \
+             WHERE c.name IS p.name AND c.score IS p.score \
+             AND ST_Equals(c.geom, p.geom) AND ST_SRID(c.geom) = 4326"
+        ),
+        3
+    );
+    assert_eq!(
+        db.query_i64("SELECT typeof(score) = 'real' FROM copy WHERE id = 2"),
+        1
+    );
+    assert_eq!(db.query_i64("SELECT count(*) FROM copy_geom_rtree"), 3);
+    db.exec("INSERT INTO copy(id, geom) VALUES (4, ST_Point(9, 9, 4326))");
+    assert_eq!(db.query_i64("SELECT count(*) FROM copy_geom_rtree"), 4);
+
+    // The Rust API appends to an existing table.
+    let appended = unsafe { sqlitegis::sqlite::import_flatgeobuf(db.0, &path, "copy", false) };
+    assert_eq!(appended.unwrap(), 3);
+    assert_eq!(db.query_i64("SELECT count(*) FROM copy"), 7);
+
+    let err = db
+        .try_query_i64(&format!("SELECT ImportFlatGeobuf('{path}', 'bad name')"))
+        .unwrap_err();
+    assert!(err.contains("invalid table name"), "got: {err}");
+    // A failed import leaves nothing behind.
+    db.exec("CREATE TABLE narrow(geom BLOB)");
+    let err = db
+        .try_query_i64(&format!("SELECT ImportFlatGeobuf('{path}', 'narrow')"))
+        .unwrap_err();
+    assert!(err.contains("[sqlite]"), "got: {err}");
+    assert_eq!(db.query_i64("SELECT count(*) FROM narrow"), 0);
+
+    let err = db
+        .try_query_i64(&format!("SELECT ExportFlatGeobuf('{path}', 'DELETE FROM places')"))
+        .unwrap_err();
+    assert!(err.contains("read-only"), "got: {err}");
+    let err = db
+        .try_query_i64(&format!("SELECT ExportFlatGeobuf('{path}', 'SELECT id FROM places')"))
+        .unwrap_err();
+    assert!(err.contains("no geometry column"), "got: {err}");
+
+    std::fs::remove_file(&path).expect("remove exported file");
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[$test_attr]
+fn shapefile_export_then_import_round_trips_a_table() {
+    let db = ActiveTestDb::open_with_file_access();
+    db.exec(
+        "CREATE TABLE parcels(id INTEGER, owner TEXT, area REAL, shape BLOB); \
+         INSERT INTO parcels VALUES \
+             (1, 'Zoë', 1.5, ST_GeomFromText('POLYGON((0 0,4 0,4 4,0 4,0 0))', 32631)), \
+             (2, NULL, NULL, NULL), \
+             (3, 'b', 2, ST_GeomFromText('MULTIPOLYGON(((5 5,6 5,6 6,5 5)))', 32631))",
+    );
+    let base = temp_path("parcels");
+
+    assert_eq!(
+        db.query_i64(&format!("SELECT ExportSHP('parcels', 'shape', '{base}.shp')")),
+        3
+    );
+    // The SRID comes back from the .prj and the charset from the .cpg.
+    assert_eq!(db.query_i64(&format!("SELECT ImportSHP('{base}', 'copy')")), 3);
+    assert_eq!(
+        db.query_i64(
+            "SELECT count(*) FROM parcels p JOIN copy c ON c.id = p.id \
+             WHERE c.owner IS p.owner AND c.area IS p.area \
+             AND (c.geom IS NULL AND p.shape IS NULL \
+                  OR ST_Equals(c.geom, p.shape) AND ST_SRID(c.geom) = 32631)"
+        ),
+        3
+    );
+    assert_eq!(
+        db.query_i64("SELECT count(*) FROM copy WHERE ST_GeometryType(geom) = 'ST_MultiPolygon'"),
+        2
+    );
+
+    // An explicit SRID and charset win over the companion files.
+    assert_eq!(
+        db.query_i64(&format!("SELECT ImportSHP('{base}', 'latin', 2154, 'ISO-8859-1')")),
+        3
+    );
+    assert_eq!(
+        db.query_i64("SELECT count(*) FROM latin WHERE ST_SRID(geom) = 2154 AND owner = 'ZoÃ«'"),
+        1
+    );
+    let appended = unsafe {
+        sqlitegis::sqlite::import_shapefile(db.0, format!("{base}.shp"), "copy", None, None)
+    };
+    assert_eq!(appended.unwrap(), 3);
+    assert_eq!(db.query_i64("SELECT count(*) FROM copy"), 6);
+
+    let err = db
+        .try_query_i64(&format!("SELECT ImportSHP('{base}', 'x', NULL, 'EBCDIC')"))
+        .unwrap_err();
+    assert!(err.contains("unsupported charset"), "got: {err}");
+    let err = db
+        .try_query_i64(&format!("SELECT ExportSHP('parcels', 'nope', '{base}')"))
+        .unwrap_err();
+    assert!(err.contains("no column [nope]"), "got: {err}");
+    db.exec("INSERT INTO parcels VALUES (4, 'c', 1, ST_Point(1, 2, 32631))");
+    let err = db
+        .try_query_i64(&format!("SELECT ExportSHP('parcels', 'shape', '{base}')"))
+        .unwrap_err();
+    assert!(err.contains("cannot mix polygon and point"), "got: {err}");
+
+    for ext in ["shp", "shx", "dbf", "prj", "cpg"] {
+        std::fs::remove_file(format!("{base}.{ext}")).expect("remove exported file");
+    }
+}
+
+#[cfg(all(feature = "geoparquet", not(target_arch = "wasm32")))]
+#[$test_attr]
+fn geoparquet_export_then_import_round_trips_a_table() {
+    let db = ActiveTestDb::open_with_file_access();
+    db.exec(
+        "CREATE TABLE places(id INTEGER, name TEXT, geom BLOB); \
+         INSERT INTO places VALUES \
+             (1, 'a', ST_Point(1, 2, 32631)), \
+             (2, NULL, NULL), \
+             (3, 'c', ST_GeomFromText('POLYGON((5 5,6 5,6 6,5 5))', 32631))",
+    );
+    let path = temp_path("places.parquet");
+
+    assert_eq!(
+        db.query_i64(&format!("SELECT ExportGeoParquet('{path}', 'SELECT * FROM places')")),
+        3
+    );
+    assert_eq!(
+        db.query_i64(&format!("SELECT ImportGeoParquet('{path}', 'copy', 1)")),
+        3
+    );
+    assert_eq!(
+        db.query_i64(
+            "SELECT count(*) FROM places p JOIN copy c ON c.id = p.id \
+             WHERE c.name IS p.name AND (c.geom IS p.geom \
+             OR (ST_Equals(c.geom, p.geom) AND ST_SRID(c.geom) = 32631))"
+        ),
+        3
+    );
+    assert_eq!(db.query_i64("SELECT count(*) FROM copy_geom_rtree"), 2);
+
+    let err = db
+        .try_query_i64(&format!("SELECT ExportGeoParquet('{path}', 'DELETE FROM places')"))
+        .unwrap_err();
+    assert!(err.contains("read-only"), "got: {err}");
+
+    std::fs::remove_file(&path).expect("remove exported file");
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[$test_attr]
+fn file_virtual_tables_read_geojson_and_csv() {
+    let db = ActiveTestDb::open_with_file_access();
+    let geojson = temp_path("parks.geojson");
+    let csv = temp_path("stops.csv");
+    std::fs::write(
+        &geojson,
+        r#"{"type":"FeatureCollection","features":[
+            {"type":"Feature","properties":{"name":"north","area":2},
+             "geometry":{"type":"Polygon","coordinates":[[[0,0],[4,0],[4,4],[0,4],[0,0]]]}},
+            {"type":"Feature","properties":{"name":"south","area":1.5},
+             "geometry":{"type":"Polygon","coordinates":[[[0,-4],[4,-4],[4,-1],[0,-4]]]}}
+        ]}"#,
+    )
+    .expect("write GeoJSON");
+    std::fs::write(
+        &csv,
+        "id,label,shape\n1,\"a, b\",POINT(1 1)\n2,c,POINT(3 -2)\n3,d,POINT(9 9)\n",
+    )
+    .expect("write CSV");
+
+    db.exec(&format!(
+        "CREATE VIRTUAL TABLE parks USING sqlitegis_geojson('{geojson}'); \
+         CREATE VIRTUAL TABLE stops USING \
+             sqlitegis_csv('{csv}', wkt_column='shape', srid=4326)"
+    ));
+    assert_eq!(db.query_i64("SELECT count(*) FROM parks"), 2);
+    assert_eq!(
+        db.query_i64("SELECT typeof(area) = 'real' FROM parks WHERE name = 'north'"),
+        1
+    );
+    assert_eq!(db.query_i64("SELECT ST_SRID(geom) FROM parks LIMIT 1"), 4326);
+    assert_eq!(
+        db.query_i64("SELECT sum(id) FROM stops WHERE label = 'a, b' OR rowid = 3"),
+        4
+    );
+    assert_eq!(
+        db.query_i64(
+            "SELECT group_concat(s.id || p.name) = '1north,2south' FROM stops s \
+             JOIN parks p ON ST_Intersects(p.geom, s.geom)"
+        ),
+        1
+    );
+    assert_eq!(db.query_i64("SELECT count(*) FROM stops WHERE rowid = 7"), 0);
+    // Direct-only: a view or trigger stored in the file cannot read them.
+    db.exec("CREATE VIEW park_count AS SELECT count(*) FROM parks");
+    let err = db.try_query_i64("SELECT * FROM park_count").unwrap_err();
+    assert!(err.contains("unsafe use of virtual table"), "got: {err}");
+
+    let err = db
+        .try_exec(&format!("CREATE VIRTUAL TABLE bad USING sqlitegis_csv('{csv}')"))
+        .unwrap_err();
+    assert!(err.contains("no [wkt] column"), "got: {err}");
+    let err = db
+        .try_exec(
+            "CREATE VIRTUAL TABLE gone USING sqlitegis_geojson('sqlitegis-missing-dir/x.geojson')",
+        )
+        .unwrap_err();
+    assert!(err.contains("sqlitegis_geojson [io]"), "got: {err}");
+    let err = db
+        .try_exec(&format!("CREATE VIRTUAL TABLE opt USING sqlitegis_geojson('{geojson}', x=1)"))
+        .unwrap_err();
+    assert!(err.contains("unknown option [x]"), "got: {err}");
+
+    std::fs::remove_file(&geojson).expect("remove GeoJSON");
+    std::fs::remove_file(&csv).expect("remove CSV");
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[$test_attr]
+fn file_functions_need_file_access() {
+    let db = ActiveTestDb::open();
+    db.exec("CREATE TABLE t(geom BLOB)");
+
+    let path = temp_path("denied.fgb");
+    #[allow(unused_mut)]
+    let mut denied = vec![
+        format!("SELECT ImportFlatGeobuf('{path}', 't')"),
+        format!("SELECT ExportFlatGeobuf('{path}', 'SELECT * FROM t')"),
+        format!("SELECT ImportSHP('{path}', 't')"),
+        format!("SELECT ExportSHP('t', 'geom', '{path}')"),
+        format!("SELECT ImportOSM('{path}')"),
+        format!("SELECT GenerateMBTiles('{path}', 'SELECT * FROM t', 0, 1)"),
+    ];
+    #[cfg(feature = "geoparquet")]
+    denied.extend([
+        format!("SELECT ImportGeoParquet('{path}', 't')"),
+        format!("SELECT ExportGeoParquet('{path}', 'SELECT * FROM t')"),
+    ]);
+    for sql in denied {
+        let err = db.try_query_i64(&sql).unwrap_err();
+        assert!(
+            err.contains("[io]") && err.contains("file access is disabled"),
+            "{sql}: {err}"
+        );
+    }
+    assert!(!std::path::Path::new(&path).exists());
+    let err = db
+        .try_exec(&format!("CREATE VIRTUAL TABLE v USING sqlitegis_geojson('{path}')"))
+        .unwrap_err();
+    assert!(
+        err.contains("sqlitegis_geojson [io]") && err.contains("file access is disabled"),
+        "got: {err}"
+    );
+    assert_eq!(db.query_i64("SELECT sqlitegis_config('file_access')"), 0);
+    let err = db
+        .try_query_i64("SELECT sqlitegis_config('file_access', 1)")
+        .unwrap_err();
+    assert!(err.contains("can only be turned off"), "got: {err}");
+
+    // SQL can give file access up, but not take it back.
+    let db = ActiveTestDb::open_with_file_access();
+    assert_eq!(db.query_i64("SELECT sqlitegis_config('file_access')"), 1);
+    assert_eq!(db.query_i64("SELECT sqlitegis_config('file_access', 1)"), 1);
+    assert_eq!(db.query_i64("SELECT sqlitegis_config('file_access', 0)"), 0);
+    let err = db
+        .try_query_i64("SELECT sqlitegis_config('file_access', 1)")
+        .unwrap_err();
+    assert!(err.contains("can only be turned off"), "got: {err}");
+    let err = db
+        .try_query_i64(&format!("SELECT ImportFlatGeobuf('{path}', 't')"))
+        .unwrap_err();
+    assert!(err.contains("file access is disabled"), "got: {err}");
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[$test_attr]
+fn gpkg_spatial_index_triggers_survive_a_reopen() {
+    let path = temp_path("reopen.gpkg");
+    let _ = std::fs::remove_file(&path);
+
+    let db = ActiveTestDb::open_path(&path);
+    assert_eq!(
+        db.query_i64(
+            "SELECT gpkgAddGeometryColumn('roads', 'geom', 'LINESTRING', 4326) \
+             + gpkgAddSpatialIndex('roads', 'geom')"
+        ),
+        2
+    );
+    drop(db);
+
+    // A fresh connection, with `accept_gpb` at its default of off.
+    let db = ActiveTestDb::open_path(&path);
+    assert_eq!(db.query_i64("SELECT sqlitegis_config('accept_gpb')"), 0);
+    db.exec(
+        "INSERT INTO roads (geom) \
+         VALUES (AsGPB(ST_GeomFromText('LINESTRING(1 2,5 7)', 4326))); \
+         UPDATE roads SET geom = AsGPB(ST_GeomFromText('POINT(3 3)', 4326)); \
+         INSERT INTO roads (geom) VALUES (AsGPB(ST_GeomFromText('POINT EMPTY', 4326)))",
+    );
+    assert_eq!(
+        db.query_i64("SELECT count(*) FROM rtree_roads_geom WHERE minx = 3 AND maxy = 3"),
+        1
+    );
+    assert_eq!(db.query_i64("SELECT count(*) FROM rtree_roads_geom"), 1);
+    drop(db);
+    std::fs::remove_file(&path).expect("remove test database");
+}
+
 // Index speed tests
 
 #[cfg(not(target_arch = "wasm32"))]